@@ -0,0 +1,345 @@
+#!/usr/bin/env python3
+#
+# Licensed to the Apache Software Foundation (ASF) under one
+# or more contributor license agreements.  See the NOTICE file
+# distributed with this work for additional information
+# regarding copyright ownership.  The ASF licenses this file
+# to you under the Apache License, Version 2.0 (the
+# "License"); you may not use this file except in compliance
+# with the License.  You may obtain a copy of the License at
+#
+#   http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing,
+# software distributed under the License is distributed on an
+# "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+# KIND, either express or implied.  See the License for the
+# specific language governing permissions and limitations
+# under the License.
+#
+# Generates the collateral of test_attest.rs: a test PKI with the names, extensions
+# and layout of the Intel SGX PCK hierarchy, collateral in the form Intel PCS returns
+# it, and a version 3 ECDSA quote certified by the PCK certificate. None of it is
+# issued by Intel. Requires the Python cryptography package.
+#
+#     python3 gen_collateral.py
+
+import datetime
+import hashlib
+import json
+import struct
+
+from cryptography import x509
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import ec
+from cryptography.hazmat.primitives.asymmetric.utils import decode_dss_signature
+from cryptography.x509.oid import NameOID
+
+UTC = datetime.timezone.utc
+SGX_OID = "1.2.840.113741.1.13.1"
+
+FMSPC = bytes.fromhex("00906ED50000")
+PCE_ID = bytes.fromhex("0000")
+PCE_SVN = 13
+TCB_COMPONENTS = [15, 15, 3, 3, 255, 255, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+QE_MR_SIGNER = bytes.fromhex("8C4F5775D796503E96137F77C68A829A0056AC8DED70140B081B094490C57BFF")
+QE_VENDOR_ID = bytes.fromhex("939A7233F79C4CA9940A0DB3957F0607")
+QE_ISV_SVN = 8
+REPORT_DATA_VALUE = b"sgx_tattest unit test"
+
+
+def date(text):
+    return datetime.datetime.strptime(text, "%Y-%m-%dT%H:%M:%SZ").replace(tzinfo=UTC)
+
+
+def name(cn):
+    return x509.Name([
+        x509.NameAttribute(NameOID.COMMON_NAME, cn),
+        x509.NameAttribute(NameOID.ORGANIZATION_NAME, "Intel Corporation"),
+        x509.NameAttribute(NameOID.LOCALITY_NAME, "Santa Clara"),
+        x509.NameAttribute(NameOID.STATE_OR_PROVINCE_NAME, "CA"),
+        x509.NameAttribute(NameOID.COUNTRY_NAME, "US"),
+    ])
+
+
+def der(tag, content):
+    n = len(content)
+    if n < 0x80:
+        head = bytes([n])
+    else:
+        b = n.to_bytes((n.bit_length() + 7) // 8, "big")
+        head = bytes([0x80 | len(b)]) + b
+    return bytes([tag]) + head + content
+
+
+def der_oid(dotted):
+    arcs = [int(a) for a in dotted.split(".")]
+    out = bytes([40 * arcs[0] + arcs[1]])
+    for arc in arcs[2:]:
+        chunk = [arc & 0x7F]
+        arc >>= 7
+        while arc:
+            chunk.append(0x80 | (arc & 0x7F))
+            arc >>= 7
+        out += bytes(reversed(chunk))
+    return der(0x06, out)
+
+
+def der_uint(tag, value):
+    b = value.to_bytes(max(1, (value.bit_length() + 8) // 8), "big")
+    return der(tag, b)
+
+
+def seq(*parts):
+    return der(0x30, b"".join(parts))
+
+
+def sgx_extension():
+    tcb = [seq(der_oid("%s.2.%d" % (SGX_OID, i + 1)), der_uint(0x02, c))
+           for i, c in enumerate(TCB_COMPONENTS)]
+    tcb.append(seq(der_oid(SGX_OID + ".2.17"), der_uint(0x02, PCE_SVN)))
+    tcb.append(seq(der_oid(SGX_OID + ".2.18"), der(0x04, bytes(TCB_COMPONENTS))))
+    return seq(
+        seq(der_oid(SGX_OID + ".1"), der(0x04, bytes(range(0x10, 0x20)))),
+        seq(der_oid(SGX_OID + ".2"), seq(*tcb)),
+        seq(der_oid(SGX_OID + ".3"), der(0x04, PCE_ID)),
+        seq(der_oid(SGX_OID + ".4"), der(0x04, FMSPC)),
+        seq(der_oid(SGX_OID + ".5"), der_uint(0x0A, 0)),
+    )
+
+
+def certificate(subject, key, issuer, issuer_key, serial, not_before, not_after, ca,
+                path_len=None, extra=()):
+    builder = (x509.CertificateBuilder()
+               .subject_name(name(subject))
+               .issuer_name(name(issuer))
+               .public_key(key.public_key())
+               .serial_number(serial)
+               .not_valid_before(date(not_before))
+               .not_valid_after(date(not_after))
+               .add_extension(x509.BasicConstraints(ca, path_len if ca else None), True)
+               .add_extension(x509.SubjectKeyIdentifier.from_public_key(key.public_key()),
+                              False)
+               .add_extension(x509.AuthorityKeyIdentifier.from_issuer_public_key(
+                   issuer_key.public_key()), False))
+    if ca:
+        usage = x509.KeyUsage(False, False, False, False, False, True, True, False, False)
+    else:
+        usage = x509.KeyUsage(True, True, False, False, False, False, False, False, False)
+    builder = builder.add_extension(usage, True)
+    for ext in extra:
+        builder = builder.add_extension(ext, False)
+    return builder.sign(issuer_key, hashes.SHA256())
+
+
+def crl(issuer, issuer_key, this_update, next_update, revoked, number):
+    builder = (x509.CertificateRevocationListBuilder()
+               .issuer_name(name(issuer))
+               .last_update(date(this_update))
+               .next_update(date(next_update))
+               .add_extension(x509.CRLNumber(number), False)
+               .add_extension(x509.AuthorityKeyIdentifier.from_issuer_public_key(
+                   issuer_key.public_key()), False))
+    for serial in revoked:
+        builder = builder.add_revoked_certificate(
+            x509.RevokedCertificateBuilder()
+            .serial_number(serial)
+            .revocation_date(date(this_update))
+            .build())
+    return builder.sign(issuer_key, hashes.SHA256())
+
+
+def raw_signature(key, data):
+    r, s = decode_dss_signature(key.sign(data, ec.ECDSA(hashes.SHA256())))
+    return r.to_bytes(32, "big") + s.to_bytes(32, "big")
+
+
+def point(key):
+    numbers = key.public_key().public_numbers()
+    return numbers.x.to_bytes(32, "big") + numbers.y.to_bytes(32, "big")
+
+
+def pem(*certs):
+    return b"".join(c.public_bytes(serialization.Encoding.PEM) for c in certs)
+
+
+def signed_document(member, body, key):
+    text = json.dumps(body, separators=(",", ":")).encode()
+    signature = raw_signature(key, text).hex()
+    return b'{"%s":%s,"signature":"%s"}' % (member.encode(), text, signature.encode())
+
+
+def tcb_level(components, pce_svn, tcb_date, status, advisories=()):
+    level = {
+        "tcb": {
+            "sgxtcbcomponents": [{"svn": c} for c in components],
+            "pcesvn": pce_svn,
+        },
+        "tcbDate": tcb_date,
+        "tcbStatus": status,
+    }
+    if advisories:
+        level["advisoryIDs"] = list(advisories)
+    return level
+
+
+def tcb_info(key, fmspc, levels):
+    return signed_document("tcbInfo", {
+        "id": "SGX",
+        "version": 3,
+        "issueDate": "2024-06-10T00:00:00Z",
+        "nextUpdate": "2024-07-10T00:00:00Z",
+        "fmspc": fmspc.hex().upper(),
+        "pceId": PCE_ID.hex().upper(),
+        "tcbType": 0,
+        "tcbEvaluationDataNumber": 16,
+        "tcbLevels": levels,
+    }, key)
+
+
+def qe_identity(key, mr_signer, levels):
+    return signed_document("enclaveIdentity", {
+        "id": "QE",
+        "version": 2,
+        "issueDate": "2024-06-11T00:00:00Z",
+        "nextUpdate": "2024-07-11T00:00:00Z",
+        "tcbEvaluationDataNumber": 16,
+        "miscselect": "00000000",
+        "miscselectMask": "FFFFFFFF",
+        "attributes": "11000000000000000000000000000000",
+        "attributesMask": "FBFFFFFFFFFFFFFF0000000000000000",
+        "mrsigner": mr_signer.hex().upper(),
+        "isvprodid": 1,
+        "tcbLevels": levels,
+    }, key)
+
+
+def qe_level(isv_svn, tcb_date, status, advisories=()):
+    level = {"tcb": {"isvsvn": isv_svn}, "tcbDate": tcb_date, "tcbStatus": status}
+    if advisories:
+        level["advisoryIDs"] = list(advisories)
+    return level
+
+
+def report_body(cpu_svn, attributes, mr_enclave, mr_signer, isv_prod_id, isv_svn,
+                report_data):
+    body = (cpu_svn + struct.pack("<I", 0) + bytes(12) + bytes(16) + attributes
+            + mr_enclave + bytes(32) + mr_signer + bytes(32) + bytes(64)
+            + struct.pack("<HHH", isv_prod_id, isv_svn, 0) + bytes(42) + bytes(16)
+            + report_data)
+    assert len(body) == 384
+    return body
+
+
+def quote(attest_key, pck_key, chain):
+    header = struct.pack("<HHIHH", 3, 2, 0, QE_ISV_SVN, PCE_SVN) + QE_VENDOR_ID + bytes(20)
+    bound = hashlib.sha256(b"sgx_tattest report data" + struct.pack("<I", 0)
+                           + struct.pack("<I", len(REPORT_DATA_VALUE))
+                           + REPORT_DATA_VALUE).digest()
+    body = report_body(bytes(TCB_COMPONENTS), struct.pack("<QQ", 0x07, 0xE7),
+                       bytes([0x5A] * 32), bytes([0xA5] * 32), 2, 3, bound + bytes(32))
+    signature = raw_signature(attest_key, header + body)
+
+    auth_data = bytes(range(32))
+    key = point(attest_key)
+    qe_report_data = hashlib.sha256(key + auth_data).digest() + bytes(32)
+    qe_report = report_body(bytes(TCB_COMPONENTS), struct.pack("<QQ", 0x11, 0xE7),
+                            bytes([0x3C] * 32), QE_MR_SIGNER, 1, QE_ISV_SVN,
+                            qe_report_data)
+    qe_signature = raw_signature(pck_key, qe_report)
+    cert_data = chain + b"\0"
+    sig_data = (signature + key + qe_report + qe_signature
+                + struct.pack("<H", len(auth_data)) + auth_data
+                + struct.pack("<HI", 5, len(cert_data)) + cert_data)
+    return header + body + struct.pack("<I", len(sig_data)) + sig_data
+
+
+def write(path, data):
+    with open(path, "wb") as f:
+        f.write(data)
+
+
+def main():
+    keys = {n: ec.generate_private_key(ec.SECP256R1())
+            for n in ("root", "ca", "pck", "signing", "attest", "rogue")}
+    root_cn, ca_cn = "Intel SGX Root CA", "Intel SGX PCK Platform CA"
+    root = certificate(root_cn, keys["root"], root_cn, keys["root"], 0x2A55,
+                       "2018-05-21T10:45:10Z", "2049-12-31T23:59:59Z", True, 1)
+    ca = certificate(ca_cn, keys["ca"], root_cn, keys["root"], 0x7F3A1C,
+                     "2018-05-21T10:50:10Z", "2033-05-21T10:50:10Z", True, 0)
+    pck = certificate("Intel SGX PCK Certificate", keys["pck"], ca_cn, keys["ca"],
+                      0x43B9_0F2C_98D1_7E55, "2024-01-01T00:00:00Z",
+                      "2031-01-01T00:00:00Z", False,
+                      extra=[x509.UnrecognizedExtension(x509.ObjectIdentifier(SGX_OID),
+                                                        sgx_extension())])
+    signing = certificate("Intel SGX TCB Signing", keys["signing"], root_cn, keys["root"],
+                          0x0D5C3E, "2018-05-21T10:50:10Z", "2025-05-21T10:50:10Z", False)
+    rogue = certificate(root_cn, keys["rogue"], root_cn, keys["rogue"], 0x2A55,
+                        "2018-05-21T10:45:10Z", "2049-12-31T23:59:59Z", True, 1)
+
+    der_form = serialization.Encoding.DER
+    pem_form = serialization.Encoding.PEM
+    write("root_ca.der", root.public_bytes(der_form))
+    write("rogue_root_ca.der", rogue.public_bytes(der_form))
+    write("pck_cert_chain.pem", pem(pck, ca, root))
+    write("tcb_signing_chain.pem", pem(signing, root))
+    # A PCK CA of the same name with another key, and the PCK CA without the CA flag.
+    forged_ca = certificate(ca_cn, keys["rogue"], root_cn, keys["root"], 0x7F3A1D,
+                            "2018-05-21T10:50:10Z", "2033-05-21T10:50:10Z", True, 0)
+    not_ca = certificate(ca_cn, keys["ca"], root_cn, keys["root"], 0x7F3A1E,
+                         "2018-05-21T10:50:10Z", "2033-05-21T10:50:10Z", False)
+    write("pck_cert_chain_forged_ca.pem", pem(pck, forged_ca, root))
+    write("pck_cert_chain_not_ca.pem", pem(pck, not_ca, root))
+
+    root_crl = ("2024-05-01T00:00:00Z", "2025-05-01T00:00:00Z")
+    pck_crl = ("2024-06-01T00:00:00Z", "2024-07-01T00:00:00Z")
+    write("root_ca_crl.pem", crl(root_cn, keys["root"], *root_crl, [0x1001], 1)
+          .public_bytes(pem_form))
+    write("root_ca_crl_ca_revoked.pem", crl(root_cn, keys["root"], *root_crl,
+                                            [0x1001, 0x7F3A1C], 2).public_bytes(pem_form))
+    write("root_ca_crl_signing_revoked.pem", crl(root_cn, keys["root"], *root_crl,
+                                                 [0x0D5C3E], 3).public_bytes(pem_form))
+    write("pck_crl.der", crl(ca_cn, keys["ca"], *pck_crl, [0x1002, 0x1003], 1)
+          .public_bytes(der_form))
+    write("pck_crl_revoked.der", crl(ca_cn, keys["ca"], *pck_crl,
+                                     [0x1002, 0x43B9_0F2C_98D1_7E55], 2).public_bytes(der_form))
+    # Current after the PCK certificate expired, for telling the two apart.
+    write("root_ca_crl_2031.pem", crl(root_cn, keys["root"], "2031-01-15T00:00:00Z",
+                                      "2032-01-15T00:00:00Z", [0x1001], 4)
+          .public_bytes(pem_form))
+    write("pck_crl_2031.der", crl(ca_cn, keys["ca"], "2031-02-01T00:00:00Z",
+                                  "2031-03-03T00:00:00Z", [0x1002], 4).public_bytes(der_form))
+    write("pck_crl_forged.der", crl(ca_cn, keys["rogue"], *pck_crl, [], 3)
+          .public_bytes(der_form))
+
+    sa = ["INTEL-SA-00334", "INTEL-SA-00615"]
+    levels = [
+        tcb_level([16, 16, 3, 3, 255, 255, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0], 14,
+                  "2024-03-13T00:00:00Z", "UpToDate"),
+        tcb_level([15, 15, 3, 3, 255, 255, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0], 13,
+                  "2023-08-09T00:00:00Z", "SWHardeningNeeded", sa),
+        tcb_level([14, 14, 3, 3, 255, 255, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0], 13,
+                  "2023-02-15T00:00:00Z", "OutOfDate", sa + ["INTEL-SA-00828"]),
+        tcb_level([2] * 16, 10, "2018-01-04T00:00:00Z", "OutOfDate", ["INTEL-SA-00106"]),
+    ]
+    write("tcb_info.json", tcb_info(keys["signing"], FMSPC, levels))
+    write("tcb_info_fmspc.json", tcb_info(keys["signing"], bytes.fromhex("00606A000000"),
+                                          levels))
+    write("tcb_info_unsupported.json", tcb_info(keys["signing"], FMSPC, levels[:1]))
+
+    qe_levels = [
+        qe_level(8, "2024-03-13T00:00:00Z", "UpToDate"),
+        qe_level(6, "2023-02-15T00:00:00Z", "OutOfDate", ["INTEL-SA-00615"]),
+    ]
+    write("qe_identity.json", qe_identity(keys["signing"], QE_MR_SIGNER, qe_levels))
+    write("qe_identity_mrsigner.json", qe_identity(keys["signing"], bytes([0x11] * 32),
+                                                   qe_levels))
+    write("qe_identity_out_of_date.json",
+          qe_identity(keys["signing"], QE_MR_SIGNER,
+                      [qe_level(9, "2024-03-13T00:00:00Z", "UpToDate")]))
+
+    write("quote.dat", quote(keys["attest"], keys["pck"], pem(pck, ca, root)))
+
+
+if __name__ == "__main__":
+    main()
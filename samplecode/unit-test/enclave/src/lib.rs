@@ -29,10 +29,12 @@ extern crate sgx_types;
 #[cfg(not(target_env = "sgx"))]
 #[macro_use]
 extern crate sgx_tstd as std;
+extern crate sgx_tattest;
 extern crate sgx_tcrypto;
 #[macro_use]
 extern crate sgx_tunittest;
 extern crate rand;
+extern crate rand_core;
 extern crate sgx_align_struct_attribute;
 extern crate sgx_alloc;
 extern crate sgx_rand;
@@ -46,6 +48,7 @@ pub use sgx_serialize::*;
 extern crate sgx_serialize_derive;
 extern crate sgx_libc;
 extern crate sgx_signal;
+extern crate sgx_ecall_derive;
 
 pub use sgx_serialize::*;
 use sgx_tunittest::*;
@@ -92,6 +95,9 @@ use test_env::*;
 mod test_path;
 use test_path::*;
 
+mod test_process;
+use test_process::*;
+
 mod test_thread;
 use test_thread::*;
 
@@ -101,6 +107,9 @@ use test_mpsc::*;
 mod test_alignbox;
 use test_alignbox::*;
 
+mod test_large_page;
+use test_large_page::*;
+
 mod test_alignstruct;
 
 mod test_signal;
@@ -112,16 +121,66 @@ use test_exception::*;
 mod test_fp;
 use test_fp::*;
 
+mod test_net;
+use test_net::*;
+
+mod test_tls;
+use test_tls::*;
+
+mod test_shm;
+use test_shm::*;
+
+mod test_attest;
+use test_attest::*;
+
 #[no_mangle]
 pub extern "C" fn test_main_entrance() -> size_t {
     rsgx_unit_tests!(
         // tcrypto
         test_rsgx_sha256_slice,
         test_rsgx_sha256_handle,
+        test_rsgx_aes_gcm_stream,
+        test_rsgx_aes_ctr_cmac_stream,
+        test_rsgx_sha3,
+        test_rsgx_kdf,
+        test_rsgx_ed25519_x25519,
+        test_rsgx_ecdsa_p384_rfc6979,
+        test_rsgx_rsa_oaep_pss,
+        test_rsgx_rustcrypto_traits,
+        test_rsgx_secret_utils,
+        test_rsgx_aes_key_wrap,
+        test_rsgx_sha256_batch,
+        test_rsgx_aes_gcm_siv,
+        test_rsgx_chacha20_poly1305,
+        test_rsgx_x509,
+        test_rsgx_x509_crl,
+        test_rsgx_hmac_incremental,
+        test_rsgx_bignum,
+        test_rsgx_sm,
+        test_rsgx_threshold_ecdsa,
+        test_rsgx_crypto_self_test,
+        test_rsgx_keygen,
+        test_rsgx_ecvrf,
+        // tattest
+        test_attest_pck_cert_chain,
+        test_attest_tcb_info,
+        test_attest_qe_identity,
+        test_attest_verify_quote,
+        test_attest_verify_quote_rejected,
+        test_attest_tcb_policy,
+        test_attest_report_data,
+        test_attest_freshness,
+        test_attest_evidence,
+        test_attest_epid,
+        test_attest_provision,
         // assert
         foo_panic,
         foo_should,
         foo_assert,
+        foo_ecall_panic,
+        foo_stdout_sink,
+        foo_panic_redaction,
+        foo_log_facade,
         // rts::veh
         test_register_first_exception_handler,
         test_register_last_exception_handler,
@@ -149,40 +208,102 @@ pub extern "C" fn test_main_entrance() -> size_t {
         test_ascii,
         // rts::c_str
         test_cstr,
+        // rts::ecall
+        test_ecall_handler,
+        test_ecall_dispatch_in_enclave,
+        test_ecall_cancel_flag_in_enclave,
         // tseal
         test_seal_unseal,
         test_number_sealing, // Thanks to @silvanegli
         test_array_sealing,  // Thanks to @silvanegli
         test_mac_aadata_slice,
         test_mac_aadata_number,
+        test_seal_stream,
+        test_seal_policy,
+        test_seal_blob_reseal,
+        test_mac_aadata_stream,
+        test_seal_key_cache,
+        test_seal_context,
+        test_seal_state_rollback,
+        test_seal_codec,
+        test_seal_exchange,
+        test_seal_allowlist,
+        test_sealed_metadata,
         // rand
         test_rand_os_sgxrng,
         test_rand_distributions,
         test_rand_isaac_isaacrng,
         test_rand_chacharng,
         test_rand_reseeding,
+        test_rand_hashmap_reseed,
         // serialize
         test_serialize_base,
         test_serialize_struct,
         test_serialize_enum,
+        test_serialize_sgx_types,
         // std::sgxfs
         test_sgxfs,
+        // std::sealedfs
+        test_sealedfs,
+        test_mmap,
+        test_file_vectored_at,
+        test_pipe,
+        test_io_error_kind,
+        test_file_lock,
+        test_memfs,
+        test_sgxfs_secure_tempdir,
+        test_sgxfs_integrity,
+        test_sgxfs_rekey,
+        test_sgxfs_shared_readers,
+        test_sgxfs_cached_file,
+        test_sgxfs_set_len,
+        test_sgxfs_transaction,
+        test_sgxfs_dir,
+        test_sgxfs_key_id,
+        test_sgxfs_convert,
+        test_sgxfs_positional,
+        // std::io::shm
+        test_shm_ring,
         // std::fs
         test_fs,
         // std::fs untrusted mode
         test_fs_untrusted_fs_feature_enabled,
         // std::time
         test_std_time,
+        test_rate_limiter,
         // rand
         test_rand_cratesio,
+        test_rand_cratesio_trusted_rng,
+        test_rand_core_trusted_rng,
         // types
         check_metadata_size,
         check_version,
+        check_quote_parse,
+        check_report_from_bytes,
+        check_secinfo_flags,
         // env
         test_env_vars_os,
         test_env_self_exe_path,
         test_env_current_dir,
         test_env_home_dir,
+        // net
+        test_net_egress_policy_cidr,
+        test_net_egress_policy_ports,
+        test_net_egress_policy_mapped,
+        test_net_egress_policy_redirect,
+        test_net_connect_policy,
+        test_net_copy_through_bounce_buffer,
+        test_net_tcp_stream_options,
+        test_net_tcp_listener_options,
+        test_net_udp_socket_options,
+        test_net_getsockopt_length,
+        test_tls_stream_handshake,
+        test_tls_stream_bad_certificate,
+        test_tls_stream_truncated,
+        test_tls_provider,
+        // process
+        test_process_command_not_allowed,
+        test_process_command_args_rejected,
         //path
         test_path_stat_is_correct_on_is_dir,
         test_path_fileinfo_false_when_checking_is_file_on_a_directory,
@@ -227,9 +348,16 @@ pub extern "C" fn test_main_entrance() -> size_t {
         test_thread_park_timeout_unpark_not_called,
         test_thread_park_timeout_unpark_called_other_thread,
         test_thread_sleep_ms_smoke,
+        test_thread_sleep_checked,
         test_thread_size_of_option_thread_id,
         test_thread_id_equal,
         test_thread_id_not_equal,
+        test_thread_async_block_on,
+        test_thread_async_reentrant_waker,
+        test_thread_tcs,
+        test_thread_once_lock_wait,
+        test_thread_scope_joins_dropped_handles,
+        test_thread_semaphore,
         //test mpsc
         test_mpsc_smoke,
         test_mpsc_drop_full,
@@ -339,6 +467,10 @@ pub extern "C" fn test_main_entrance() -> size_t {
         test_alignbox_clone,
         test_alignbox_clonefrom,
         test_alignbox_clonefrom_no_eq_size,
+        //test large page allocator
+        test_large_page_config,
+        test_large_page_alloc,
+        test_large_page_table_full,
         //test signal
         test_signal_forbidden,
         test_signal_without_pid,
@@ -349,5 +481,7 @@ pub extern "C" fn test_main_entrance() -> size_t {
         test_fp64,
         //test exception
         test_exception_handler,
+        //test env allowlist, which cannot be undone
+        test_env_allowlist,
     )
 }
@@ -15,6 +15,11 @@
 // specific language governing permissions and limitations
 // under the License..
 
+use sgx_types::sgx_status_t;
+use std::ecall::{self, PanicPolicy};
+use std::io::{self, Write};
+use std::sync::{Arc, SgxMutex};
+use std::string::String;
 use std::vec::Vec;
 
 pub fn foo_panic() {
@@ -35,3 +40,113 @@ pub fn foo_assert() {
     should_panic!(assert!(false));
     should_panic!(panic!());
 }
+
+pub fn foo_ecall_panic() {
+    ecall::set_panic_policy(PanicPolicy::Continue);
+    assert_eq!(ecall::run(|| sgx_status_t::SGX_SUCCESS), sgx_status_t::SGX_SUCCESS);
+    assert_eq!(
+        ecall::run(|| panic!("ecall panic")),
+        sgx_status_t::SGX_ERROR_UNEXPECTED
+    );
+    assert_eq!(ecall::panic_message().as_deref(), Some("ecall panic"));
+    assert!(ecall::take_panic_payload().is_some());
+    assert!(ecall::take_panic_payload().is_none());
+    assert!(!ecall::is_poisoned());
+    assert_eq!(ecall::try_run(|| 42), Ok(42));
+}
+
+pub fn foo_stdout_sink() {
+    let sink = Arc::new(SgxMutex::new(Vec::<u8>::new()));
+    let old = io::set_stdout(Some(sink.clone()));
+    println!("captured line");
+    let _ = io::stdout().flush();
+    io::set_stdout(old);
+    assert_eq!(&sink.lock().unwrap()[..], b"captured line\n");
+}
+
+pub fn foo_panic_redaction() {
+    use std::panic::{self, PanicRedaction};
+
+    let sink = Arc::new(SgxMutex::new(Vec::<u8>::new()));
+    let old = io::set_stderr(Some(sink.clone()));
+    panic::set_panic_redaction(PanicRedaction::Hash);
+    let _ = panic::take_redacted_messages();
+    let r = panic::catch_unwind(|| panic!("secret {}", 1234));
+    panic::set_panic_redaction(PanicRedaction::Off);
+    io::set_stderr(old);
+    assert!(r.is_err());
+
+    let output = String::from_utf8(sink.lock().unwrap().clone()).unwrap();
+    assert!(!output.contains("1234"));
+    let messages = panic::take_redacted_messages();
+    assert_eq!(messages.len(), 1);
+    let (id, ref msg) = messages[0];
+    assert!(msg.contains("secret 1234"));
+    assert!(output.contains(&format!("{:016x}", id)));
+    assert!(panic::redacted_message(id).is_none());
+}
+
+pub fn foo_log_facade() {
+    use core::convert::TryInto;
+    use std::log::{self, EncryptedRingSink, Level, LevelFilter, LogSink, Record};
+
+    struct Unavailable;
+    impl LogSink for Unavailable {
+        fn name(&self) -> &str {
+            "unavailable"
+        }
+        fn is_available(&self) -> bool {
+            false
+        }
+        fn log(&self, _record: &Record<'_>) {
+            unreachable!();
+        }
+    }
+
+    let key = [7_u8; 16];
+    let ring = Arc::new(EncryptedRingSink::new(key, 2));
+    let sinks: Vec<Arc<dyn LogSink>> = vec![Arc::new(Unavailable), ring.clone()];
+    assert_eq!(log::init(sinks).unwrap(), "encrypted-ring");
+    assert!(log::init(vec![ring.clone() as Arc<dyn LogSink>]).is_err());
+
+    log::set_max_level(LevelFilter::Warn);
+    log::set_module_level("app::db", LevelFilter::Debug);
+    log::set_module_level("app::db::pool", LevelFilter::Off);
+    assert!(log::enabled(Level::Warn, "app"));
+    assert!(!log::enabled(Level::Info, "app"));
+    assert!(log::enabled(Level::Debug, "app::db::query"));
+    assert!(!log::enabled(Level::Debug, "app::dbx"));
+    assert!(!log::enabled(Level::Error, "app::db::pool"));
+
+    let _ = ring.drain();
+    log::log(Level::Info, "app", format_args!("dropped"));
+    log::log(Level::Debug, "app::db", format_args!("first"));
+    log::log(Level::Error, "app", format_args!("secret {}", 42));
+    log::log(Level::Warn, "app", format_args!("third"));
+    let entries = ring.drain();
+    assert_eq!(entries.len(), 2);
+
+    let entry = &entries[0];
+    let (aad, rest) = entry.split_at(8);
+    let (iv, rest) = rest.split_at(12);
+    let (tag, ciphertext) = rest.split_at(16);
+    let tag: [u8; 16] = tag.try_into().unwrap();
+    let mut plaintext = vec![0_u8; ciphertext.len()];
+    let status = unsafe {
+        sgx_types::sgx_rijndael128GCM_decrypt(
+            &key,
+            ciphertext.as_ptr(),
+            ciphertext.len() as u32,
+            plaintext.as_mut_ptr(),
+            iv.as_ptr(),
+            iv.len() as u32,
+            aad.as_ptr(),
+            aad.len() as u32,
+            &tag,
+        )
+    };
+    assert_eq!(status, sgx_status_t::SGX_SUCCESS);
+    assert_eq!(u64::from_le_bytes(aad.try_into().unwrap()), 1);
+    assert_eq!(&plaintext[..], b"[ERROR app] secret 42");
+    log::set_max_level(LevelFilter::Info);
+}
@@ -0,0 +1,1503 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+// The collateral in ../pcs has the shape of what Intel PCS returns, issued by a test
+// PKI under the names of the Intel SGX hierarchy. gen_collateral.py regenerates it.
+
+use sgx_tattest::*;
+use sgx_tcrypto::*;
+use sgx_types::*;
+use std::str;
+use std::string::String;
+use std::vec::Vec;
+
+static ROOT_CA: &'static [u8] = include_bytes!("../pcs/root_ca.der");
+static ROGUE_ROOT_CA: &'static [u8] = include_bytes!("../pcs/rogue_root_ca.der");
+static PCK_CERT_CHAIN: &'static [u8] = include_bytes!("../pcs/pck_cert_chain.pem");
+static PCK_CERT_CHAIN_FORGED_CA: &'static [u8] =
+    include_bytes!("../pcs/pck_cert_chain_forged_ca.pem");
+static PCK_CERT_CHAIN_NOT_CA: &'static [u8] = include_bytes!("../pcs/pck_cert_chain_not_ca.pem");
+static TCB_SIGNING_CHAIN: &'static [u8] = include_bytes!("../pcs/tcb_signing_chain.pem");
+static ROOT_CA_CRL: &'static [u8] = include_bytes!("../pcs/root_ca_crl.pem");
+static ROOT_CA_CRL_CA_REVOKED: &'static [u8] = include_bytes!("../pcs/root_ca_crl_ca_revoked.pem");
+static ROOT_CA_CRL_SIGNING_REVOKED: &'static [u8] =
+    include_bytes!("../pcs/root_ca_crl_signing_revoked.pem");
+static ROOT_CA_CRL_2031: &'static [u8] = include_bytes!("../pcs/root_ca_crl_2031.pem");
+static PCK_CRL: &'static [u8] = include_bytes!("../pcs/pck_crl.der");
+static PCK_CRL_REVOKED: &'static [u8] = include_bytes!("../pcs/pck_crl_revoked.der");
+static PCK_CRL_FORGED: &'static [u8] = include_bytes!("../pcs/pck_crl_forged.der");
+static PCK_CRL_2031: &'static [u8] = include_bytes!("../pcs/pck_crl_2031.der");
+static TCB_INFO: &'static [u8] = include_bytes!("../pcs/tcb_info.json");
+static TCB_INFO_FMSPC: &'static [u8] = include_bytes!("../pcs/tcb_info_fmspc.json");
+static TCB_INFO_UNSUPPORTED: &'static [u8] = include_bytes!("../pcs/tcb_info_unsupported.json");
+static QE_IDENTITY: &'static [u8] = include_bytes!("../pcs/qe_identity.json");
+static QE_IDENTITY_MRSIGNER: &'static [u8] = include_bytes!("../pcs/qe_identity_mrsigner.json");
+static QE_IDENTITY_OUT_OF_DATE: &'static [u8] =
+    include_bytes!("../pcs/qe_identity_out_of_date.json");
+static QUOTE: &'static [u8] = include_bytes!("../pcs/quote.dat");
+
+// 2024-06-15, when all of the collateral is current.
+const NOW: u64 = 1718409600;
+// 2024-08-01, after the next update of the TCB info, QE identity and PCK CRL.
+const LATER: u64 = 1722470400;
+// 2031-02-15, when the PCK certificate has expired but the 2031 CRLs are current.
+const PCK_EXPIRED: u64 = 1928880000;
+
+// The claim the quoted enclave bound in its report data.
+static BOUND: &'static [u8] = b"sgx_tattest unit test";
+
+static PCK_COMPONENTS: [u8; 16] = [15, 15, 3, 3, 255, 255, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+static FMSPC: [u8; 6] = [0x00, 0x90, 0x6e, 0xd5, 0x00, 0x00];
+static QE_MR_SIGNER: [u8; 32] = [
+    0x8c, 0x4f, 0x57, 0x75, 0xd7, 0x96, 0x50, 0x3e, 0x96, 0x13, 0x7f, 0x77, 0xc6, 0x8a, 0x82, 0x9a,
+    0x00, 0x56, 0xac, 0x8d, 0xed, 0x70, 0x14, 0x0b, 0x08, 0x1b, 0x09, 0x44, 0x90, 0xc5, 0x7b, 0xff,
+];
+
+// Offsets into the quote: the report body, the signature data, and within it the QE
+// report, its signature, the authentication data and the certification data.
+const QUOTE_BODY: usize = 48;
+const QUOTE_SIGNATURE: usize = QUOTE_BODY + 384 + 4;
+const QUOTE_QE_REPORT: usize = QUOTE_SIGNATURE + 128;
+const QUOTE_QE_REPORT_SIGNATURE: usize = QUOTE_QE_REPORT + 384;
+const QUOTE_AUTH_DATA: usize = QUOTE_QE_REPORT_SIGNATURE + 64 + 2;
+const QUOTE_CERT_TYPE: usize = QUOTE_AUTH_DATA + 32;
+
+fn collateral() -> SgxQuoteCollateral {
+    SgxQuoteCollateral {
+        pck_cert_chain: None,
+        root_ca_crl: ROOT_CA_CRL.to_vec(),
+        pck_crl: PCK_CRL.to_vec(),
+        tcb_info: TCB_INFO.to_vec(),
+        tcb_info_issuer_chain: TCB_SIGNING_CHAIN.to_vec(),
+        qe_identity: QE_IDENTITY.to_vec(),
+        qe_identity_issuer_chain: TCB_SIGNING_CHAIN.to_vec(),
+    }
+}
+
+fn root_key(der: &[u8]) -> SgxX509PublicKey {
+    *SgxX509Certificate::parse(der)
+        .unwrap()
+        .public_key()
+        .unwrap()
+}
+
+fn verify(
+    quote: &[u8],
+    collateral: &SgxQuoteCollateral,
+) -> SgxQuote3Result<SgxQuoteVerificationResult> {
+    SgxQuoteVerifier::new(ROOT_CA)
+        .unwrap()
+        .verify(quote, collateral, NOW)
+}
+
+fn verify_error(quote: &[u8], collateral: &SgxQuoteCollateral) -> Option<sgx_quote3_error_t> {
+    verify(quote, collateral).err()
+}
+
+// Replaces the first occurrence of from, which must be there, in a JSON document.
+fn edit(document: &[u8], from: &str, to: &str) -> Vec<u8> {
+    let text = str::from_utf8(document).unwrap();
+    assert!(text.contains(from), "{} is not in the document", from);
+    text.replacen(from, to, 1).into_bytes()
+}
+
+fn tampered(quote: &[u8], offset: usize) -> Vec<u8> {
+    let mut quote = quote.to_vec();
+    quote[offset] ^= 0x01;
+    quote
+}
+
+fn pem_blocks(pem: &[u8]) -> Vec<String> {
+    str::from_utf8(pem)
+        .unwrap()
+        .split_inclusive("-----END CERTIFICATE-----\n")
+        .map(String::from)
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+// The big-endian r || s form of a signature of the SGX crypto library, which keeps
+// big numbers as little-endian 32-bit words.
+fn signature_be(signature: &sgx_ec256_signature_t) -> [u8; 64] {
+    let mut out = [0_u8; 64];
+    for i in 0..8 {
+        out[28 - 4 * i..32 - 4 * i].copy_from_slice(&signature.x[i].to_be_bytes());
+        out[60 - 4 * i..64 - 4 * i].copy_from_slice(&signature.y[i].to_be_bytes());
+    }
+    out
+}
+
+pub fn test_attest_pck_cert_chain() {
+    let root = root_key(ROOT_CA);
+    let chain = SgxPckCertChain::from_pem(PCK_CERT_CHAIN).unwrap();
+    assert_eq!(chain.get_certificates().len(), 3);
+    assert_eq!(chain.get_certificates()[2], ROOT_CA);
+
+    let extensions = chain.get_extensions();
+    let ppid: Vec<u8> = (0x10..0x20).collect();
+    assert_eq!(&extensions.get_ppid()[..], &ppid[..]);
+    assert_eq!(extensions.get_tcb_components(), &PCK_COMPONENTS);
+    assert_eq!(extensions.get_pce_svn(), 13);
+    assert_eq!(extensions.get_cpu_svn().svn, PCK_COMPONENTS);
+    assert_eq!(extensions.get_pce_id(), &[0, 0]);
+    assert_eq!(extensions.get_fmspc(), &FMSPC);
+    assert_eq!(extensions.get_sgx_type(), 0);
+
+    // The PCK CRL is accepted in DER, or as the hex the PCS returns, NUL terminated or not.
+    assert_eq!(chain.verify(&root, ROOT_CA_CRL, PCK_CRL, NOW), Ok(true));
+    let hex = to_hex(PCK_CRL);
+    assert_eq!(
+        chain.verify(&root, ROOT_CA_CRL, hex.as_bytes(), NOW),
+        Ok(true)
+    );
+    let mut hex_nul = hex.into_bytes();
+    hex_nul.push(0);
+    assert_eq!(chain.verify(&root, ROOT_CA_CRL, &hex_nul, NOW), Ok(true));
+
+    // Out of date CRLs, or an expired PCK certificate, leave the chain invalid but trusted.
+    assert_eq!(chain.verify(&root, ROOT_CA_CRL, PCK_CRL, LATER), Ok(false));
+    assert_eq!(
+        chain.verify(&root, ROOT_CA_CRL_2031, PCK_CRL_2031, NOW),
+        Ok(false)
+    );
+    assert_eq!(
+        chain.verify(&root, ROOT_CA_CRL_2031, PCK_CRL_2031, PCK_EXPIRED),
+        Ok(false)
+    );
+    let ca = SgxX509Certificate::parse(&chain.get_certificates()[1]).unwrap();
+    assert!(ca.is_valid_at(PCK_EXPIRED));
+
+    // Revoked PCK certificate or PCK CA.
+    assert_eq!(
+        chain.verify(&root, ROOT_CA_CRL, PCK_CRL_REVOKED, NOW),
+        Err(sgx_quote3_error_t::SGX_QL_PCK_REVOKED)
+    );
+    assert_eq!(
+        chain.verify(&root, ROOT_CA_CRL_CA_REVOKED, PCK_CRL, NOW),
+        Err(sgx_quote3_error_t::SGX_QL_PCK_REVOKED)
+    );
+    // The CRL of the TCB Signing certificate does not revoke the PCK chain.
+    assert_eq!(
+        chain.verify(&root, ROOT_CA_CRL_SIGNING_REVOKED, PCK_CRL, NOW),
+        Ok(true)
+    );
+
+    // A CRL of another issuer, or one in the name of the PCK CA signed by another key.
+    assert_eq!(
+        chain.verify(&root, ROOT_CA_CRL, ROOT_CA_CRL, NOW),
+        Err(sgx_quote3_error_t::SGX_QL_PCK_CERT_CHAIN_ERROR)
+    );
+    assert_eq!(
+        chain.verify(&root, PCK_CRL, PCK_CRL, NOW),
+        Err(sgx_quote3_error_t::SGX_QL_PCK_CERT_CHAIN_ERROR)
+    );
+    assert_eq!(
+        chain.verify(&root, ROOT_CA_CRL, PCK_CRL_FORGED, NOW),
+        Err(sgx_quote3_error_t::SGX_QL_PCK_CERT_CHAIN_ERROR)
+    );
+
+    // CRLs that do not decode.
+    assert_eq!(
+        chain.verify(&root, ROOT_CA_CRL, b"", NOW),
+        Err(sgx_quote3_error_t::SGX_QL_CRL_UNSUPPORTED_FORMAT)
+    );
+    assert_eq!(
+        chain.verify(&root, ROOT_CA_CRL, b"not a crl", NOW),
+        Err(sgx_quote3_error_t::SGX_QL_CRL_UNSUPPORTED_FORMAT)
+    );
+    assert_eq!(
+        chain.verify(&root, ROOT_CA_CRL, &PCK_CRL[..PCK_CRL.len() - 1], NOW),
+        Err(sgx_quote3_error_t::SGX_QL_CRL_UNSUPPORTED_FORMAT)
+    );
+
+    // A root CA with another key than the pinned one.
+    assert_eq!(
+        chain.verify(&root_key(ROGUE_ROOT_CA), ROOT_CA_CRL, PCK_CRL, NOW),
+        Err(sgx_quote3_error_t::SGX_QL_ROOT_CA_UNTRUSTED)
+    );
+
+    // A PCK CA of the right name with another key, and one that is not a CA.
+    let forged = SgxPckCertChain::from_pem(PCK_CERT_CHAIN_FORGED_CA).unwrap();
+    assert_eq!(
+        forged.verify(&root, ROOT_CA_CRL, PCK_CRL, NOW),
+        Err(sgx_quote3_error_t::SGX_QL_PCK_CERT_CHAIN_ERROR)
+    );
+    let not_ca = SgxPckCertChain::from_pem(PCK_CERT_CHAIN_NOT_CA).unwrap();
+    assert_eq!(
+        not_ca.verify(&root, ROOT_CA_CRL, PCK_CRL, NOW),
+        Err(sgx_quote3_error_t::SGX_QL_PCK_CERT_CHAIN_ERROR)
+    );
+
+    // Chains that are not a PCK certificate, a PCK CA and the root CA.
+    let blocks = pem_blocks(PCK_CERT_CHAIN);
+    assert_eq!(blocks.len(), 3);
+    let shapes = [
+        String::new(),
+        blocks[..2].concat(),
+        [blocks[1].clone(), blocks[0].clone(), blocks[2].clone()].concat(),
+        [
+            blocks[0].clone(),
+            blocks[1].clone(),
+            blocks[2].clone(),
+            blocks[2].clone(),
+        ]
+        .concat(),
+        String::from_utf8(TCB_SIGNING_CHAIN.to_vec()).unwrap(),
+    ];
+    for pem in shapes.iter() {
+        assert_eq!(
+            SgxPckCertChain::from_pem(pem.as_bytes()).err(),
+            Some(sgx_quote3_error_t::SGX_QL_PCK_CERT_CHAIN_ERROR)
+        );
+    }
+}
+
+pub fn test_attest_tcb_info() {
+    let info = SgxTcbInfo::parse(TCB_INFO).unwrap();
+    assert_eq!(info.get_version(), 3);
+    assert_eq!(info.get_issue_date(), 1717977600);
+    assert_eq!(info.get_next_update(), 1720569600);
+    assert_eq!(info.get_fmspc(), &FMSPC);
+    assert_eq!(info.get_pce_id(), &[0, 0]);
+    assert_eq!(info.get_tcb_evaluation_data_number(), 16);
+
+    let levels = info.get_levels();
+    assert_eq!(levels.len(), 4);
+    assert_eq!(levels[1].get_components(), &PCK_COMPONENTS);
+    assert_eq!(levels[1].get_pce_svn(), 13);
+    assert_eq!(levels[1].get_tcb_date(), 1691539200);
+    assert_eq!(
+        levels[1].get_status(),
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_SW_HARDENING_NEEDED
+    );
+    assert_eq!(
+        levels[1].get_advisory_ids(),
+        ["INTEL-SA-00334", "INTEL-SA-00615"]
+    );
+    assert!(levels[0].get_advisory_ids().is_empty());
+
+    // The highest level at or below the platform in every component and the PCE SVN.
+    assert!(levels[1].matches(&PCK_COMPONENTS, 13));
+    assert!(levels[2].matches(&PCK_COMPONENTS, 13));
+    assert!(!levels[0].matches(&PCK_COMPONENTS, 13));
+    let level = info.find_level(&PCK_COMPONENTS, 13).unwrap();
+    assert_eq!(level.get_tcb_date(), levels[1].get_tcb_date());
+    let level = info.find_level(&[16, 16, 3, 3, 255, 255, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0], 14);
+    assert_eq!(
+        level.unwrap().get_status(),
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OK
+    );
+    let level = info.find_level(&[16, 16, 3, 3, 255, 255, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0], 13);
+    assert_eq!(level.unwrap().get_tcb_date(), levels[1].get_tcb_date());
+    let level = info.find_level(&[14, 15, 3, 3, 255, 255, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0], 13);
+    assert_eq!(
+        level.unwrap().get_status(),
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OUT_OF_DATE
+    );
+    assert!(info.find_level(&PCK_COMPONENTS, 12).is_none());
+    assert!(info.find_level(&[1; 16], 13).is_none());
+
+    // Dates are RFC 3339 UTC, with an optional fraction of a second.
+    let dates = [
+        ("2024-06-10T00:00:00.123Z", 1717977600),
+        ("2024-02-29T12:34:56Z", 1709210096),
+        ("1970-01-01T00:00:00Z", 0),
+    ];
+    for &(date, epoch) in dates.iter() {
+        let document = edit(TCB_INFO, "2024-06-10T00:00:00Z", date);
+        assert_eq!(
+            SgxTcbInfo::parse(&document).unwrap().get_issue_date(),
+            epoch
+        );
+    }
+    let bad_dates = [
+        "2024-06-10T00:00:00",
+        "2024-06-10T00:00:00+00:00",
+        "2024-06-10 00:00:00Z",
+        "2024-06-10T00:00:00.Z",
+        "2024-06-10T00:00Z",
+        "2024-00-10T00:00:00Z",
+        "2024-13-10T00:00:00Z",
+        "2024-06-00T00:00:00Z",
+        "2024-06-32T00:00:00Z",
+        "2024-06-10T24:00:00Z",
+        "2024-06-10T00:60:00Z",
+        "2024-06-10T00:00:61Z",
+        "1969-12-31T23:59:59Z",
+        "2024-O6-10T00:00:00Z",
+    ];
+    for date in bad_dates.iter() {
+        let document = edit(TCB_INFO, "2024-06-10T00:00:00Z", date);
+        assert_eq!(
+            SgxTcbInfo::parse(&document).err(),
+            Some(sgx_quote3_error_t::SGX_QL_TCBINFO_UNSUPPORTED_FORMAT),
+            "{}",
+            date
+        );
+    }
+
+    // Hex fields have exactly two digits a byte, of either case.
+    let document = edit(TCB_INFO, "\"00906ED50000\"", "\"00906ed50000\"");
+    assert_eq!(SgxTcbInfo::parse(&document).unwrap().get_fmspc(), &FMSPC);
+    for fmspc in [
+        "\"00906ED5000\"",
+        "\"00906ED500000\"",
+        "\"00906ED5000G\"",
+        "\"\"",
+    ]
+    .iter()
+    {
+        let document = edit(TCB_INFO, "\"00906ED50000\"", fmspc);
+        assert!(SgxTcbInfo::parse(&document).is_err(), "{}", fmspc);
+    }
+
+    // Escapes in strings are decoded.
+    let document = edit(
+        TCB_INFO,
+        "\"INTEL-SA-00334\"",
+        r#""INTEL\u002dSA-\u00300334""#,
+    );
+    let info = SgxTcbInfo::parse(&document).unwrap();
+    assert_eq!(info.get_levels()[1].get_advisory_ids()[0], "INTEL-SA-00334");
+
+    // Documents of another version or TEE, or that are not well formed.
+    let unsupported = [
+        ("\"version\":3", "\"version\":4"),
+        ("\"version\":3", "\"version\":03"),
+        ("\"version\":3", "\"version\":3.0"),
+        ("\"version\":3", "\"version\":3e0"),
+        ("\"id\":\"SGX\"", "\"id\":\"TDX\""),
+        ("\"id\":\"SGX\",", ""),
+        ("\"pcesvn\":14", "\"pcesvn\":65536"),
+        ("{\"svn\":255}", "{\"svn\":256}"),
+        ("[{\"svn\":16},{\"svn\":16},", "[{\"svn\":16},"),
+        ("\"UpToDate\"", "\"Current\""),
+        ("\"INTEL-SA-00334\"", "\"INTEL-SA-\n00334\""),
+        ("\"INTEL-SA-00334\"", r#""INTEL-SA-\x00334""#),
+        ("\"INTEL-SA-00334\"", r#""INTEL-SA-\ud83d""#),
+        ("\"INTEL-SA-00334\"", r#""INTEL-SA-\ud83dA""#),
+        ("\"INTEL-SA-00334\"", "\"INTEL-SA-00334"),
+        ("\"signature\":\"", "\"signature\":\"00"),
+        ("{\"tcbInfo\":", "[{\"tcbInfo\":"),
+    ];
+    for &(from, to) in unsupported.iter() {
+        let document = edit(TCB_INFO, from, to);
+        assert_eq!(
+            SgxTcbInfo::parse(&document).err(),
+            Some(sgx_quote3_error_t::SGX_QL_TCBINFO_UNSUPPORTED_FORMAT),
+            "{}",
+            to
+        );
+    }
+    let mut trailing = TCB_INFO.to_vec();
+    trailing.push(b'x');
+    assert!(SgxTcbInfo::parse(&trailing).is_err());
+    assert!(SgxTcbInfo::parse(QE_IDENTITY).is_err());
+
+    // Version 2 names each component.
+    let mut tcb = String::new();
+    for i in 1..17 {
+        tcb.push_str(&format!("\"sgxtcbcomp{:02}svn\":{},", i, i));
+    }
+    let v2 = format!(
+        concat!(
+            "{{\"tcbInfo\":{{\"version\":2,\"issueDate\":\"2024-06-10T00:00:00Z\",",
+            "\"nextUpdate\":\"2024-07-10T00:00:00Z\",\"fmspc\":\"00906ED50000\",",
+            "\"pceId\":\"0000\",\"tcbType\":0,\"tcbEvaluationDataNumber\":8,\"tcbLevels\":",
+            "[{{\"tcb\":{{{}\"pcesvn\":11}},\"tcbDate\":\"2023-02-15T00:00:00Z\",",
+            "\"tcbStatus\":\"ConfigurationNeeded\"}}]}},\"signature\":\"{}\"}}"
+        ),
+        tcb,
+        "00".repeat(64)
+    );
+    let info = SgxTcbInfo::parse(v2.as_bytes()).unwrap();
+    assert_eq!(info.get_version(), 2);
+    assert_eq!(info.get_tcb_evaluation_data_number(), 8);
+    let level = &info.get_levels()[0];
+    let components: Vec<u8> = (1..17).collect();
+    assert_eq!(&level.get_components()[..], &components[..]);
+    assert_eq!(level.get_pce_svn(), 11);
+    assert_eq!(
+        level.get_status(),
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_CONFIG_NEEDED
+    );
+    let missing = v2.replacen("\"sgxtcbcomp16svn\":16,", "", 1);
+    assert!(SgxTcbInfo::parse(missing.as_bytes()).is_err());
+}
+
+pub fn test_attest_qe_identity() {
+    let identity = SgxQeIdentity::parse(QE_IDENTITY).unwrap();
+    assert_eq!(identity.get_issue_date(), 1718064000);
+    assert_eq!(identity.get_next_update(), 1720656000);
+    assert_eq!(identity.get_mr_signer(), &QE_MR_SIGNER);
+    assert_eq!(identity.get_isv_prod_id(), 1);
+    let levels = identity.get_levels();
+    assert_eq!(levels.len(), 2);
+    assert_eq!(levels[0].get_isv_svn(), 8);
+    assert_eq!(levels[0].get_tcb_date(), 1710288000);
+    assert_eq!(levels[1].get_tcb_date(), 1676419200);
+
+    // The highest level at or below the ISVSVN of the QE.
+    assert_eq!(
+        identity.find_level(9).unwrap().get_status(),
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OK
+    );
+    let level = identity.find_level(7).unwrap();
+    assert_eq!(
+        level.get_status(),
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OUT_OF_DATE
+    );
+    assert_eq!(level.get_advisory_ids(), ["INTEL-SA-00615"]);
+    assert!(identity.find_level(5).is_none());
+
+    let mut body = sgx_report_body_t::default();
+    body.mr_signer.m = QE_MR_SIGNER;
+    body.isv_prod_id = 1;
+    body.attributes.flags = 0x11;
+    body.attributes.xfrm = 0xe7;
+    assert!(identity.matches(&body));
+
+    // Attribute bits outside the mask, and the XFRM, are not compared.
+    let mut masked = body;
+    masked.attributes.flags |= 0x04;
+    masked.attributes.xfrm = 0x03;
+    assert!(identity.matches(&masked));
+
+    let mut mutations = Vec::new();
+    let mut debug = body;
+    debug.attributes.flags |= SGX_FLAGS_DEBUG;
+    mutations.push(debug);
+    let mut not_init = body;
+    not_init.attributes.flags &= !SGX_FLAGS_INITTED;
+    mutations.push(not_init);
+    let mut flags_high = body;
+    flags_high.attributes.flags |= 1 << 56;
+    mutations.push(flags_high);
+    let mut misc = body;
+    misc.misc_select = 1;
+    mutations.push(misc);
+    let mut signer = body;
+    signer.mr_signer.m[31] ^= 1;
+    mutations.push(signer);
+    let mut product = body;
+    product.isv_prod_id = 2;
+    mutations.push(product);
+    for body in mutations.iter() {
+        assert!(!identity.matches(body));
+    }
+
+    // The id is optional, but must be the one of the QE when present.
+    let document = edit(QE_IDENTITY, "\"id\":\"QE\",", "");
+    assert!(SgxQeIdentity::parse(&document).is_ok());
+    let unsupported = [
+        ("\"id\":\"QE\"", "\"id\":\"QVE\""),
+        ("\"version\":2", "\"version\":1"),
+        ("\"miscselect\":\"00000000\"", "\"miscselect\":\"000000\""),
+        ("\"isvprodid\":1", "\"isvprodid\":65536"),
+        ("\"isvsvn\":8", "\"isvsvn\":-8"),
+        ("\"OutOfDate\"", "\"Stale\""),
+        ("\"2024-06-11T00:00:00Z\"", "\"2024-06-11\""),
+    ];
+    for &(from, to) in unsupported.iter() {
+        let document = edit(QE_IDENTITY, from, to);
+        assert_eq!(
+            SgxQeIdentity::parse(&document).err(),
+            Some(sgx_quote3_error_t::SGX_QL_QEIDENTITY_UNSUPPORTED_FORMAT),
+            "{}",
+            to
+        );
+    }
+    assert!(SgxQeIdentity::parse(TCB_INFO).is_err());
+}
+
+pub fn test_attest_verify_quote() {
+    let result = verify(QUOTE, &collateral()).unwrap();
+    assert_eq!(
+        result.get_tcb_status(),
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_SW_HARDENING_NEEDED
+    );
+    assert_eq!(
+        result.get_advisory_ids(),
+        ["INTEL-SA-00334", "INTEL-SA-00615"]
+    );
+    assert!(!result.is_collateral_expired());
+    assert_eq!(result.get_earliest_issue_date(), 1717977600);
+
+    let body = result.get_report_body();
+    assert_eq!(body.cpu_svn.svn, PCK_COMPONENTS);
+    assert_eq!(body.attributes.flags, 0x07);
+    assert_eq!(body.attributes.xfrm, 0xe7);
+    assert_eq!(body.mr_enclave.m, [0x5a; 32]);
+    assert_eq!(body.mr_signer.m, [0xa5; 32]);
+    assert_eq!(body.isv_prod_id, 2);
+    assert_eq!(body.isv_svn, 3);
+    assert!(SgxReportData::verify(&body.report_data, &[BOUND]).is_ok());
+
+    // The chain can come with the collateral when the quote does not carry it.
+    let mut quote = QUOTE.to_vec();
+    assert_eq!(&quote[QUOTE_CERT_TYPE..QUOTE_CERT_TYPE + 2], &[5, 0]);
+    quote[QUOTE_CERT_TYPE] = 3;
+    let mut with_chain = collateral();
+    with_chain.pck_cert_chain = Some(PCK_CERT_CHAIN.to_vec());
+    let result = verify(&quote, &with_chain).unwrap();
+    assert_eq!(result.get_report_body().mr_enclave.m, [0x5a; 32]);
+    assert_eq!(
+        verify_error(&quote, &collateral()),
+        Some(sgx_quote3_error_t::SGX_QL_QUOTE_CERTIFICATION_DATA_UNSUPPORTED)
+    );
+
+    // Collateral past its next update still verifies, marked as expired.
+    let verifier = SgxQuoteVerifier::new(ROOT_CA).unwrap();
+    let result = verifier.verify(QUOTE, &collateral(), LATER).unwrap();
+    assert!(result.is_collateral_expired());
+    assert_eq!(
+        result.get_tcb_status(),
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_SW_HARDENING_NEEDED
+    );
+    let mut current_crls = collateral();
+    current_crls.root_ca_crl = ROOT_CA_CRL_2031.to_vec();
+    current_crls.pck_crl = PCK_CRL_2031.to_vec();
+    let result = verifier.verify(QUOTE, &current_crls, PCK_EXPIRED).unwrap();
+    assert!(result.is_collateral_expired());
+
+    assert!(SgxQuoteVerifier::new(b"not a certificate").is_err());
+    assert!(SgxQuoteVerifier::new(ROGUE_ROOT_CA).is_ok());
+}
+
+pub fn test_attest_verify_quote_rejected() {
+    let format = Some(sgx_quote3_error_t::SGX_QL_QUOTE_FORMAT_UNSUPPORTED);
+    assert_eq!(
+        verify_error(&QUOTE[..QUOTE.len() - 1], &collateral()),
+        format
+    );
+    let mut trailing = QUOTE.to_vec();
+    trailing.push(0);
+    assert_eq!(verify_error(&trailing, &collateral()), format);
+    let mut version = QUOTE.to_vec();
+    version[0] = 2;
+    assert_eq!(verify_error(&version, &collateral()), format);
+    let mut key_type = QUOTE.to_vec();
+    key_type[2] = 3;
+    assert_eq!(verify_error(&key_type, &collateral()), format);
+
+    // The attestation key signs the header and the report body.
+    let invalid_report = Some(sgx_quote3_error_t::SGX_QL_INVALID_REPORT);
+    for &offset in [8, QUOTE_BODY + 64, QUOTE_BODY + 320, QUOTE_SIGNATURE + 40].iter() {
+        assert_eq!(
+            verify_error(&tampered(QUOTE, offset), &collateral()),
+            invalid_report
+        );
+    }
+
+    // The PCK signs the QE report, which commits to the attestation key.
+    let qe_report = Some(sgx_quote3_error_t::SGX_QL_QE_REPORT_INVALID_SIGNATURE);
+    for &offset in [
+        QUOTE_QE_REPORT + 64,
+        QUOTE_QE_REPORT + 258,
+        QUOTE_QE_REPORT + 384 - 1,
+        QUOTE_QE_REPORT_SIGNATURE + 40,
+        QUOTE_AUTH_DATA,
+    ]
+    .iter()
+    {
+        assert_eq!(
+            verify_error(&tampered(QUOTE, offset), &collateral()),
+            qe_report
+        );
+    }
+
+    let verifier = SgxQuoteVerifier::new(ROGUE_ROOT_CA).unwrap();
+    assert_eq!(
+        verifier.verify(QUOTE, &collateral(), NOW).err(),
+        Some(sgx_quote3_error_t::SGX_QL_ROOT_CA_UNTRUSTED)
+    );
+
+    let cases: Vec<(fn(&mut SgxQuoteCollateral), sgx_quote3_error_t)> = vec![
+        (
+            |c| c.pck_crl = PCK_CRL_REVOKED.to_vec(),
+            sgx_quote3_error_t::SGX_QL_PCK_REVOKED,
+        ),
+        (
+            |c| c.root_ca_crl = ROOT_CA_CRL_CA_REVOKED.to_vec(),
+            sgx_quote3_error_t::SGX_QL_PCK_REVOKED,
+        ),
+        (
+            |c| c.pck_crl = PCK_CRL_FORGED.to_vec(),
+            sgx_quote3_error_t::SGX_QL_PCK_CERT_CHAIN_ERROR,
+        ),
+        (
+            |c| c.pck_crl = Vec::new(),
+            sgx_quote3_error_t::SGX_QL_CRL_UNSUPPORTED_FORMAT,
+        ),
+        (
+            |c| c.root_ca_crl = Vec::new(),
+            sgx_quote3_error_t::SGX_QL_CRL_UNSUPPORTED_FORMAT,
+        ),
+        // The TCB info must come from the TCB Signing certificate, unrevoked, and
+        // be signed by it.
+        (
+            |c| c.tcb_info_issuer_chain = PCK_CERT_CHAIN.to_vec(),
+            sgx_quote3_error_t::SGX_QL_TCBINFO_CHAIN_ERROR,
+        ),
+        (
+            |c| c.tcb_info_issuer_chain = Vec::new(),
+            sgx_quote3_error_t::SGX_QL_TCBINFO_CHAIN_ERROR,
+        ),
+        (
+            |c| c.root_ca_crl = ROOT_CA_CRL_SIGNING_REVOKED.to_vec(),
+            sgx_quote3_error_t::SGX_QL_TCBINFO_CHAIN_ERROR,
+        ),
+        (
+            |c| {
+                c.tcb_info = edit(
+                    TCB_INFO,
+                    "\"tcbEvaluationDataNumber\":16",
+                    "\"tcbEvaluationDataNumber\":17",
+                )
+            },
+            sgx_quote3_error_t::SGX_QL_TCBINFO_CHAIN_ERROR,
+        ),
+        (
+            |c| c.tcb_info = QE_IDENTITY.to_vec(),
+            sgx_quote3_error_t::SGX_QL_TCBINFO_UNSUPPORTED_FORMAT,
+        ),
+        (
+            |c| c.tcb_info = TCB_INFO_FMSPC.to_vec(),
+            sgx_quote3_error_t::SGX_QL_TCBINFO_MISMATCH,
+        ),
+        (
+            |c| c.tcb_info = TCB_INFO_UNSUPPORTED.to_vec(),
+            sgx_quote3_error_t::SGX_QL_TCB_NOT_SUPPORTED,
+        ),
+        // And so must the QE identity.
+        (
+            |c| c.qe_identity_issuer_chain = PCK_CERT_CHAIN.to_vec(),
+            sgx_quote3_error_t::SGX_QL_QEIDENTITY_CHAIN_ERROR,
+        ),
+        (
+            |c| {
+                c.qe_identity = edit(QE_IDENTITY, "\"isvprodid\":1", "\"isvprodid\":2");
+            },
+            sgx_quote3_error_t::SGX_QL_QEIDENTITY_CHAIN_ERROR,
+        ),
+        (
+            |c| c.qe_identity = TCB_INFO.to_vec(),
+            sgx_quote3_error_t::SGX_QL_QEIDENTITY_UNSUPPORTED_FORMAT,
+        ),
+        (
+            |c| c.qe_identity = QE_IDENTITY_MRSIGNER.to_vec(),
+            sgx_quote3_error_t::SGX_QL_QEIDENTITY_MISMATCH,
+        ),
+        (
+            |c| c.qe_identity = QE_IDENTITY_OUT_OF_DATE.to_vec(),
+            sgx_quote3_error_t::SGX_QL_QE_IDENTITY_OUT_OF_DATE,
+        ),
+    ];
+    for &(change, error) in cases.iter() {
+        let mut collateral = collateral();
+        change(&mut collateral);
+        assert_eq!(verify_error(QUOTE, &collateral), Some(error));
+    }
+}
+
+pub fn test_attest_tcb_policy() {
+    let result = verify(QUOTE, &collateral()).unwrap();
+    let sw_hardening = sgx_ql_qv_result_t::SGX_QL_QV_RESULT_SW_HARDENING_NEEDED;
+
+    let policy = SgxTcbPolicy::new().accept(sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OK);
+    assert_eq!(
+        policy.evaluate(&result, NOW),
+        Err(sgx_quote3_error_t::SGX_QL_TCB_SW_HARDENING_NEEDED)
+    );
+    assert!(SgxTcbPolicy::new()
+        .accept(sw_hardening)
+        .evaluate(&result, NOW)
+        .is_ok());
+
+    // A status accepted with advisories needs all of the platform's to be listed.
+    let policy = SgxTcbPolicy::new().accept_with_advisories(sw_hardening, &["INTEL-SA-00334"]);
+    assert_eq!(
+        policy.evaluate(&result, NOW),
+        Err(sgx_quote3_error_t::SGX_QL_TCB_SW_HARDENING_NEEDED)
+    );
+    let policy = SgxTcbPolicy::new()
+        .accept_with_advisories(sw_hardening, &["INTEL-SA-00615", "INTEL-SA-00334"]);
+    assert!(policy.evaluate(&result, NOW).is_ok());
+
+    // The collateral was issued five days before NOW.
+    let policy = SgxTcbPolicy::new().accept(sw_hardening);
+    assert!(policy
+        .clone()
+        .max_collateral_age(5 * 86400)
+        .evaluate(&result, NOW)
+        .is_ok());
+    assert_eq!(
+        policy
+            .clone()
+            .max_collateral_age(5 * 86400 - 1)
+            .evaluate(&result, NOW),
+        Err(sgx_quote3_error_t::SGX_QL_SGX_TCB_INFO_EXPIRED)
+    );
+
+    let verifier = SgxQuoteVerifier::new(ROOT_CA).unwrap();
+    let expired = verifier.verify(QUOTE, &collateral(), LATER).unwrap();
+    assert_eq!(
+        policy.evaluate(&expired, LATER),
+        Err(sgx_quote3_error_t::SGX_QL_SGX_TCB_INFO_EXPIRED)
+    );
+    assert!(policy
+        .clone()
+        .allow_expired_collateral(true)
+        .evaluate(&expired, LATER)
+        .is_ok());
+
+    // The JSON form round trips, escaping what needs to be.
+    let policy = SgxTcbPolicy::new()
+        .accept(sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OK)
+        .accept_with_advisories(sw_hardening, &["INTEL-SA-00334", "quote\" back\\slash\n"])
+        .max_collateral_age(2592000);
+    let json = policy.to_json();
+    assert_eq!(
+        json,
+        concat!(
+            r#"{"version":1,"accept":["UpToDate"],"acceptWithAdvisories":"#,
+            r#"{"SWHardeningNeeded":["INTEL-SA-00334","quote\" back\\slash\u000a"]},"#,
+            r#""maxCollateralAge":2592000,"allowExpiredCollateral":false}"#
+        )
+    );
+    assert_eq!(
+        SgxTcbPolicy::from_json(json.as_bytes()).unwrap().to_json(),
+        json
+    );
+
+    let policy = SgxTcbPolicy::from_json(
+        br#"{
+          "version": 1,
+          "accept": ["UpToDate"],
+          "acceptWithAdvisories": { "SWHardeningNeeded": ["INTEL-SA-00334", "INTEL-SA-00615"] },
+          "maxCollateralAge": 2592000,
+          "allowExpiredCollateral": false
+        }"#,
+    )
+    .unwrap();
+    assert!(policy.evaluate(&result, NOW).is_ok());
+    assert_eq!(
+        SgxTcbPolicy::from_json(br#"{"version":1}"#)
+            .unwrap()
+            .evaluate(&result, NOW),
+        Err(sgx_quote3_error_t::SGX_QL_TCB_SW_HARDENING_NEEDED)
+    );
+
+    // Documents nest at most 16 levels below the top.
+    let nested = |depth: usize| {
+        format!(
+            "{{\"version\":1,\"extension\":{}{}}}",
+            "[".repeat(depth),
+            "]".repeat(depth)
+        )
+    };
+    assert!(SgxTcbPolicy::from_json(nested(16).as_bytes()).is_ok());
+    assert!(SgxTcbPolicy::from_json(nested(17).as_bytes()).is_err());
+    assert!(SgxTcbPolicy::from_json(nested(1000).as_bytes()).is_err());
+
+    let surrogates = br#"{"version":1,"acceptWithAdvisories":{"UpToDate":["\ud83d\ude00"]}}"#;
+    let policy = SgxTcbPolicy::from_json(surrogates).unwrap();
+    assert!(policy.to_json().contains("[\"\u{1f600}\"]"));
+
+    let invalid: [&[u8]; 20] = [
+        b"",
+        b"[]",
+        b"{}",
+        br#"{"version":2}"#,
+        br#"{"version":"1"}"#,
+        br#"{"version":01}"#,
+        br#"{"version":1.0}"#,
+        br#"{"version":1e0}"#,
+        br#"{"version":1,"accept":"UpToDate"}"#,
+        br#"{"version":1,"accept":["Current"]}"#,
+        br#"{"version":1,"acceptWithAdvisories":{"UpToDate":"INTEL-SA-00334"}}"#,
+        br#"{"version":1,"maxCollateralAge":-1}"#,
+        br#"{"version":1,"allowExpiredCollateral":"yes"}"#,
+        br#"{"version":1,"x":"\ud83d"}"#,
+        br#"{"version":1,"x":"\ud83dA"}"#,
+        br#"{"version":1,"x":"\ude00"}"#,
+        br#"{"version":1,"x":"\q"}"#,
+        b"{\"version\":1,\"x\":\"\x01\"}",
+        br#"{"version":1,"x":"unterminated}"#,
+        br#"{"version":1} {}"#,
+    ];
+    for json in invalid.iter() {
+        assert_eq!(
+            SgxTcbPolicy::from_json(json).err(),
+            Some(sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER),
+            "{}",
+            String::from_utf8_lossy(json)
+        );
+    }
+}
+
+pub fn test_attest_report_data() {
+    let bound = SgxReportData::bind(&[BOUND]).unwrap();
+    assert_eq!(
+        to_hex(&bound.d[..32]),
+        "6987BAA101A36C95A9F19C89A7B1BB79200A8C0EC3FF5271070A296E0375A1BC"
+    );
+    assert_eq!(bound.d[32..], [0; 32]);
+
+    let values: [&[u8]; 2] = [b"public key", b"transcript"];
+    let bound = SgxReportData::bind_in(b"test domain", &values).unwrap();
+    assert_eq!(
+        to_hex(&bound.d[..32]),
+        "BA4535721BEEE0145E75A8278AE1F6D2975BEE600358492E1CAAE4CCE5BE2E2C"
+    );
+    assert!(SgxReportData::verify_in(b"test domain", &bound, &values).is_ok());
+
+    // Values are length prefixed, and bound in their domain only.
+    let mac_mismatch = Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+    let moved: [&[u8]; 2] = [b"public keytrans", b"cript"];
+    assert_eq!(
+        SgxReportData::verify_in(b"test domain", &bound, &moved),
+        mac_mismatch
+    );
+    assert_eq!(
+        SgxReportData::verify_in(b"other domain", &bound, &values),
+        mac_mismatch
+    );
+    assert_eq!(SgxReportData::verify(&bound, &values), mac_mismatch);
+    let swapped: [&[u8]; 2] = [b"transcript", b"public key"];
+    assert_eq!(
+        SgxReportData::verify_in(b"test domain", &bound, &swapped),
+        mac_mismatch
+    );
+    let mut tail = bound;
+    tail.d[63] = 1;
+    assert_eq!(
+        SgxReportData::verify_in(b"test domain", &tail, &values),
+        mac_mismatch
+    );
+    assert_ne!(
+        SgxReportData::bind(&[]).unwrap().d,
+        SgxReportData::bind(&[b""]).unwrap().d
+    );
+}
+
+pub fn test_attest_freshness() {
+    let claim = SgxFreshness::new(b"test domain", b"nonce").with_timestamp(NOW);
+    assert_eq!(claim.get_domain(), b"test domain");
+    assert_eq!(claim.get_nonce(), b"nonce");
+    assert_eq!(claim.get_timestamp(), NOW);
+    let report_data = claim.bind(b"data").unwrap();
+    assert!(claim.verify(&report_data, b"data").is_ok());
+
+    let mac_mismatch = Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+    assert_eq!(claim.verify(&report_data, b"other data"), mac_mismatch);
+    let later = SgxFreshness::new(b"test domain", b"nonce").with_timestamp(NOW + 1);
+    assert_eq!(later.verify(&report_data, b"data"), mac_mismatch);
+    let other = SgxFreshness::new(b"other domain", b"nonce").with_timestamp(NOW);
+    assert_eq!(other.verify(&report_data, b"data"), mac_mismatch);
+    let values: [&[u8]; 1] = [b"data"];
+    assert_eq!(
+        SgxReportData::verify_in(b"test domain", &report_data, &values),
+        mac_mismatch
+    );
+    let empty = SgxFreshness::new(b"test domain", b"");
+    assert_eq!(
+        empty.bind(b"data").err(),
+        Some(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+
+    // A nonce is accepted once, within the window, with a timestamp within the skew.
+    let mut verifier = SgxFreshnessVerifier::new(b"test domain", 60).max_skew(5);
+    let bind = |nonce: &[u8], timestamp: u64| {
+        SgxFreshness::new(b"test domain", nonce)
+            .with_timestamp(timestamp)
+            .bind(b"data")
+            .unwrap()
+    };
+    let invalid_state = Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+    let invalid_parameter = Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+
+    let nonce = verifier.issue_nonce(NOW).unwrap();
+    assert_eq!(verifier.get_outstanding(), 1);
+    let report_data = bind(&nonce, NOW + 10);
+    assert!(verifier
+        .check(&report_data, &nonce, NOW + 10, b"data", NOW + 20)
+        .is_ok());
+    assert_eq!(verifier.get_outstanding(), 0);
+    assert_eq!(
+        verifier.check(&report_data, &nonce, NOW + 10, b"data", NOW + 20),
+        invalid_state
+    );
+    assert_eq!(
+        verifier.check(&report_data, &[0; 32], NOW + 10, b"data", NOW + 20),
+        invalid_state
+    );
+
+    let nonce = verifier.issue_nonce(NOW).unwrap();
+    let report_data = bind(&nonce, NOW + 60);
+    assert_eq!(
+        verifier.check(&report_data, &nonce, NOW + 60, b"data", NOW + 61),
+        invalid_state
+    );
+
+    let nonce = verifier.issue_nonce(NOW).unwrap();
+    assert!(verifier
+        .check(&bind(&nonce, NOW - 5), &nonce, NOW - 5, b"data", NOW)
+        .is_ok());
+    let nonce = verifier.issue_nonce(NOW).unwrap();
+    assert_eq!(
+        verifier.check(&bind(&nonce, NOW - 6), &nonce, NOW - 6, b"data", NOW),
+        invalid_parameter
+    );
+    let nonce = verifier.issue_nonce(NOW).unwrap();
+    assert!(verifier
+        .check(&bind(&nonce, NOW + 15), &nonce, NOW + 15, b"data", NOW + 10)
+        .is_ok());
+    let nonce = verifier.issue_nonce(NOW).unwrap();
+    assert_eq!(
+        verifier.check(&bind(&nonce, NOW + 16), &nonce, NOW + 16, b"data", NOW + 10),
+        invalid_parameter
+    );
+
+    // A failed check consumes the nonce too.
+    let nonce = verifier.issue_nonce(NOW).unwrap();
+    let report_data = bind(&nonce, NOW);
+    assert_eq!(
+        verifier.check(&report_data, &nonce, NOW, b"other data", NOW),
+        mac_mismatch
+    );
+    assert_eq!(
+        verifier.check(&report_data, &nonce, NOW, b"data", NOW),
+        invalid_state
+    );
+    assert_eq!(verifier.get_outstanding(), 0);
+
+    // Past the capacity, the oldest nonces are dropped.
+    let mut verifier = SgxFreshnessVerifier::new(b"test domain", 60).capacity(2);
+    let first = verifier.issue_nonce(NOW).unwrap();
+    let second = verifier.issue_nonce(NOW).unwrap();
+    let third = verifier.issue_nonce(NOW).unwrap();
+    assert_ne!(first, second);
+    assert_eq!(verifier.get_outstanding(), 2);
+    assert_eq!(
+        verifier.check(&bind(&first, NOW), &first, NOW, b"data", NOW),
+        invalid_state
+    );
+    assert!(verifier
+        .check(&bind(&second, NOW), &second, NOW, b"data", NOW)
+        .is_ok());
+    assert!(verifier
+        .check(&bind(&third, NOW), &third, NOW, b"data", NOW)
+        .is_ok());
+
+    let mut verifier = SgxFreshnessVerifier::new(b"test domain", 60).capacity(0);
+    verifier.issue_nonce(NOW).unwrap();
+    verifier.issue_nonce(NOW).unwrap();
+    assert_eq!(verifier.get_outstanding(), 1);
+}
+
+pub fn test_attest_evidence() {
+    let evidence = SgxEcdsaQuoteEvidence::from_bytes(QUOTE).unwrap();
+    assert_eq!(evidence.format(), SgxEvidenceFormat::EcdsaQuote);
+    assert_eq!(evidence.as_bytes(), QUOTE);
+    assert_eq!(evidence.report_body().mr_enclave.m, [0x5a; 32]);
+    assert!(SgxReportData::verify(evidence.report_data(), &[BOUND]).is_ok());
+    assert!(SgxEcdsaQuoteEvidence::from_bytes(&QUOTE[..QUOTE.len() - 1]).is_err());
+
+    // An array of the media type, as a text string, and the evidence, as a byte string.
+    let envelope = evidence.to_envelope();
+    let media_type = b"application/vnd.intel.sgx.ecdsa-quote";
+    assert_eq!(envelope[..2], [0x82, 0x78]);
+    assert_eq!(envelope[2] as usize, media_type.len());
+    assert_eq!(&envelope[3..3 + media_type.len()], media_type);
+    let pos = 3 + media_type.len();
+    assert_eq!(envelope[pos], 0x59);
+    assert_eq!(
+        u16::from_be_bytes([envelope[pos + 1], envelope[pos + 2]]) as usize,
+        QUOTE.len()
+    );
+    assert_eq!(&envelope[pos + 3..], QUOTE);
+    let opened = SgxAnyEvidence::from_envelope(&envelope).unwrap();
+    assert_eq!(opened.format(), SgxEvidenceFormat::EcdsaQuote);
+    assert_eq!(opened.as_bytes(), QUOTE);
+
+    let mut report = sgx_report_t::default();
+    report.body.isv_svn = 7;
+    report.body.report_data = SgxReportData::bind(&[BOUND]).unwrap();
+    let evidence = SgxReportEvidence::new(&report);
+    assert_eq!(evidence.as_bytes().len(), 432);
+    let parsed = SgxReportEvidence::from_bytes(evidence.as_bytes()).unwrap();
+    assert_eq!(parsed.get_report().body.isv_svn, 7);
+    assert!(SgxReportEvidence::from_bytes(&evidence.as_bytes()[1..]).is_err());
+    let envelope = evidence.to_envelope();
+    let opened = SgxAnyEvidence::from_envelope(&envelope).unwrap();
+    assert_eq!(opened.format(), SgxEvidenceFormat::Report);
+    assert_eq!(opened.report_body().isv_svn, 7);
+    assert_eq!(opened.report_data().d, report.body.report_data.d);
+    assert_eq!(
+        SgxAnyEvidence::from(evidence).format(),
+        SgxEvidenceFormat::Report
+    );
+
+    // An EPID quote is a sgx_quote_t followed by its signature.
+    let mut epid = vec![0_u8; 436 + 4];
+    epid[0] = 2;
+    epid[2] = 1;
+    epid[4..8].copy_from_slice(&[0x2c, 0x0b, 0x00, 0x00]);
+    epid[48 + 258] = 9;
+    epid[432] = 4;
+    let evidence = SgxEpidQuoteEvidence::from_bytes(&epid).unwrap();
+    assert_eq!(evidence.get_sign_type(), 1);
+    assert_eq!(evidence.get_epid_group_id(), [0x2c, 0x0b, 0x00, 0x00]);
+    assert_eq!(evidence.report_body().isv_svn, 9);
+    let opened = SgxAnyEvidence::from_envelope(&evidence.to_envelope()).unwrap();
+    assert_eq!(opened.format(), SgxEvidenceFormat::EpidQuote);
+    assert_eq!(opened.as_bytes(), &epid[..]);
+    let mut bad_version = epid.clone();
+    bad_version[0] = 3;
+    assert!(SgxEpidQuoteEvidence::from_bytes(&bad_version).is_err());
+    assert!(SgxEpidQuoteEvidence::from_bytes(&epid[..epid.len() - 1]).is_err());
+    assert!(SgxEpidQuoteEvidence::from_bytes(&epid[..400]).is_err());
+    assert!(SgxAnyEvidence::from_bytes(SgxEvidenceFormat::EcdsaQuote, &epid).is_err());
+
+    for format in [
+        SgxEvidenceFormat::Report,
+        SgxEvidenceFormat::EpidQuote,
+        SgxEvidenceFormat::EcdsaQuote,
+    ]
+    .iter()
+    {
+        assert_eq!(
+            SgxEvidenceFormat::from_media_type(format.media_type()),
+            Some(*format)
+        );
+    }
+    assert_eq!(SgxEvidenceFormat::from_media_type("application/json"), None);
+
+    // Envelopes must be canonical: shortest lengths, known media types, nothing after.
+    let report_type = b"application/vnd.intel.sgx.report";
+    let envelope_with = |head: &[u8], media_type: &[u8], bytes_head: &[u8], body: &[u8]| {
+        let mut out = head.to_vec();
+        out.extend_from_slice(media_type);
+        out.extend_from_slice(bytes_head);
+        out.extend_from_slice(body);
+        out
+    };
+    let body = envelope[envelope.len() - 432..].to_vec();
+    let canonical = envelope_with(&[0x82, 0x78, 32], report_type, &[0x59, 0x01, 0xb0], &body);
+    assert_eq!(canonical, envelope);
+    let invalid = [
+        envelope_with(&[0x83, 0x78, 32], report_type, &[0x59, 0x01, 0xb0], &body),
+        envelope_with(
+            &[0x82, 0x79, 0, 32],
+            report_type,
+            &[0x59, 0x01, 0xb0],
+            &body,
+        ),
+        envelope_with(
+            &[0x82, 0x78, 32],
+            report_type,
+            &[0x5a, 0, 0, 0x01, 0xb0],
+            &body,
+        ),
+        envelope_with(&[0x82, 0x58, 32], report_type, &[0x59, 0x01, 0xb0], &body),
+        envelope_with(&[0x82, 0x78, 32], report_type, &[0x79, 0x01, 0xb0], &body),
+        envelope_with(&[0x82, 0x78, 32], report_type, &[0x59, 0x01, 0xb1], &body),
+        envelope_with(
+            &[0x82, 0x78, 32],
+            report_type,
+            &[0x59, 0x01, 0xaf],
+            &body[1..],
+        ),
+        envelope_with(
+            &[0x82, 0x70],
+            b"application/json",
+            &[0x59, 0x01, 0xb0],
+            &body,
+        ),
+        envelope_with(
+            &[0x82, 0x78, 32],
+            report_type,
+            &[0x59, 0x01, 0xb0, 0],
+            &body,
+        ),
+        [&envelope[..], &[0]].concat(),
+        envelope[..envelope.len() - 1].to_vec(),
+        Vec::new(),
+    ];
+    for envelope in invalid.iter() {
+        assert_eq!(
+            SgxAnyEvidence::from_envelope(envelope).err(),
+            Some(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        );
+    }
+}
+
+pub fn test_attest_epid() {
+    let handle = SgxEccHandle::new();
+    handle.open().unwrap();
+    let (private, public) = handle.create_key_pair().unwrap();
+    let (_, other_public) = handle.create_key_pair().unwrap();
+
+    // A SigRL of group 0x00000b2c, version 5, with two entries.
+    let mut sigrl = vec![0x00, 0x02, 0x00, 0x0e, 0x00, 0x00, 0x0b, 0x2c];
+    sigrl.extend_from_slice(&5_u32.to_be_bytes());
+    sigrl.extend_from_slice(&2_u32.to_be_bytes());
+    sigrl.extend_from_slice(&[0x11; SGX_SIGRL_ENTRY_SIZE]);
+    sigrl.extend_from_slice(&[0x22; SGX_SIGRL_ENTRY_SIZE]);
+    let signature = handle.ecdsa_sign_slice(&sigrl[..], &private).unwrap();
+    sigrl.extend_from_slice(&signature_be(&signature));
+
+    let list = SgxSigRl::parse(&sigrl).unwrap();
+    assert_eq!(list.as_bytes(), &sigrl[..]);
+    assert_eq!(list.get_group_id(), [0x2c, 0x0b, 0x00, 0x00]);
+    assert_eq!(list.get_version(), 5);
+    assert_eq!(list.get_entry_count(), 2);
+    let entries: Vec<&[u8]> = list.get_entries().collect();
+    assert_eq!(entries[1], &[0x22; SGX_SIGRL_ENTRY_SIZE][..]);
+    assert_eq!(list.verify_signature(&public), Ok(true));
+    assert_eq!(list.verify_signature(&other_public), Ok(false));
+    let changed = tampered(&sigrl, 16);
+    assert_eq!(
+        SgxSigRl::parse(&changed).unwrap().verify_signature(&public),
+        Ok(false)
+    );
+
+    // The list must be of the group of the platform, and not rolled back.
+    let group = [0x2c, 0x0b, 0x00, 0x00];
+    assert!(list.check(&group, 5).is_ok());
+    assert_eq!(
+        list.check(&[0x2d, 0x0b, 0x00, 0x00], 5),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+    assert_eq!(
+        list.check(&group, 6),
+        Err(sgx_status_t::SGX_ERROR_INVALID_VERSION)
+    );
+
+    let mut empty = sigrl[..16].to_vec();
+    empty[15] = 0;
+    empty.extend_from_slice(&[0; 64]);
+    assert_eq!(SgxSigRl::parse(&empty).unwrap().get_entry_count(), 0);
+    let mut count = sigrl.clone();
+    count[15] = 3;
+    let mut sver = sigrl.clone();
+    sver[1] = 1;
+    let mut blob_id = sigrl.clone();
+    blob_id[3] = 13;
+    let mut huge = sigrl.clone();
+    huge[12..16].copy_from_slice(&[0xff; 4]);
+    for raw in [
+        count,
+        sver,
+        blob_id,
+        huge,
+        sigrl[..sigrl.len() - 1].to_vec(),
+        empty[..79].to_vec(),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            SgxSigRl::parse(raw).err(),
+            Some(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        );
+    }
+
+    // A platform info blob, signed over its first 37 bytes.
+    let platform_info = |group: u8, tcb: u16, pse: u16| {
+        let mut pib = [0_u8; SGX_PLATFORM_INFO_SIZE];
+        pib[0] = group;
+        pib[1..3].copy_from_slice(&tcb.to_be_bytes());
+        pib[3..5].copy_from_slice(&pse.to_be_bytes());
+        pib[5..23].copy_from_slice(&[0x0f; 18]);
+        pib[23..25].copy_from_slice(&7_u16.to_be_bytes());
+        pib[25..29].copy_from_slice(&2_u32.to_be_bytes());
+        pib[29..33].copy_from_slice(&0_u32.to_be_bytes());
+        pib[33..37].copy_from_slice(&[0x00, 0x00, 0x0b, 0x2c]);
+        let signature = handle.ecdsa_sign_slice(&pib[..37], &private).unwrap();
+        pib[37..].copy_from_slice(&signature_be(&signature));
+        pib
+    };
+    let pib = platform_info(0, 0, 0);
+    let hex = format!("15020065{}", to_hex(&pib));
+    let info = SgxPlatformInfo::from_hex(&hex).unwrap();
+    assert_eq!(&info.get_platform_info().platform_info[..], &pib[..]);
+    assert_eq!(info.get_latest_equivalent_tcb_psvn(), &[0x0f; 18][..]);
+    assert_eq!(info.get_latest_pse_isvsvn(), 7);
+    assert_eq!(info.get_latest_psda_svn(), 2);
+    assert_eq!(info.get_xeid(), 0);
+    assert_eq!(info.get_group_id(), group);
+    assert_eq!(info.get_status(), SgxPlatformStatus::UpToDate);
+    assert_eq!(info.verify_signature(&public), Ok(true));
+    assert_eq!(info.verify_signature(&other_public), Ok(false));
+    let info = SgxPlatformInfo::from_hex(&hex.to_lowercase()).unwrap();
+    assert_eq!(info.get_status(), SgxPlatformStatus::UpToDate);
+
+    let info = SgxPlatformInfo::from_bytes(&platform_info(0, SGX_PIB_QUOTE_CPUSVN_OUT_OF_DATE, 0))
+        .unwrap();
+    assert_eq!(
+        info.get_tcb_evaluation_flags(),
+        SGX_PIB_QUOTE_CPUSVN_OUT_OF_DATE
+    );
+    let update = info.get_update();
+    assert!(update.ucode_update && !update.csme_fw_update && !update.psw_update);
+    assert_eq!(info.get_status(), SgxPlatformStatus::UpdateNeeded(update));
+    let bits = update.to_update_info_bit();
+    assert_eq!(
+        (bits.ucodeUpdate, bits.csmeFwUpdate, bits.pswUpdate),
+        (1, 0, 0)
+    );
+
+    let pib = platform_info(0, 0, SGX_PIB_SVN_FROM_PS_HW_SEC_INFO_OUT_OF_DATE);
+    let update = SgxPlatformInfo::from_bytes(&pib).unwrap().get_update();
+    assert!(update.csme_fw_update && !update.psw_update);
+    let pib = platform_info(0, SGX_PIB_QUOTE_ISVSVN_QE_OUT_OF_DATE, 0);
+    assert!(
+        SgxPlatformInfo::from_bytes(&pib)
+            .unwrap()
+            .get_update()
+            .psw_update
+    );
+    let pib = platform_info(SGX_PIB_QE_EPID_GROUP_OUT_OF_DATE, 0, 0);
+    assert!(
+        SgxPlatformInfo::from_bytes(&pib)
+            .unwrap()
+            .get_update()
+            .psw_update
+    );
+    let pib = platform_info(0, SGX_PIB_PLATFORM_CONFIGURATION_NEEDED, 0);
+    let update = SgxPlatformInfo::from_bytes(&pib).unwrap().get_update();
+    assert!(update.configuration_needed && update.is_needed());
+    let bits = update.to_update_info_bit();
+    assert_eq!(
+        (bits.ucodeUpdate, bits.csmeFwUpdate, bits.pswUpdate),
+        (0, 0, 0)
+    );
+
+    let pib = platform_info(SGX_PIB_QE_EPID_GROUP_REVOKED, 0, 0);
+    let info = SgxPlatformInfo::from_bytes(&pib).unwrap();
+    assert_eq!(info.get_epid_group_flags(), SGX_PIB_QE_EPID_GROUP_REVOKED);
+    assert_eq!(
+        info.get_status(),
+        SgxPlatformStatus::Revoked(SgxPlatformUpdate::default())
+    );
+    let pib = platform_info(
+        SGX_PIB_QE_EPID_GROUP_REVOKED,
+        SGX_PIB_QUOTE_CPUSVN_OUT_OF_DATE,
+        0,
+    );
+    match SgxPlatformInfo::from_bytes(&pib).unwrap().get_status() {
+        SgxPlatformStatus::Revoked(update) => assert!(update.ucode_update),
+        _ => panic!("the group is revoked"),
+    }
+
+    let invalid = [
+        format!("16020065{}", to_hex(&pib)),
+        format!("15010065{}", to_hex(&pib)),
+        format!("15020064{}", to_hex(&pib)),
+        to_hex(&pib),
+        format!("15020065{}", &to_hex(&pib)[2..]),
+        format!("15020065{}0", to_hex(&pib)),
+        format!("15020065{}G", &to_hex(&pib)[1..]),
+    ];
+    for hex in invalid.iter() {
+        assert_eq!(
+            SgxPlatformInfo::from_hex(hex).err(),
+            Some(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        );
+    }
+    assert!(SgxPlatformInfo::from_bytes(&pib[1..]).is_err());
+}
+
+struct FixedPolicy {
+    body: sgx_report_body_t,
+    refuse: bool,
+}
+
+impl SgxProvisionPolicy for FixedPolicy {
+    fn verify_quote(&mut self, quote: &[u8]) -> SgxResult<sgx_report_body_t> {
+        assert_eq!(quote, b"quote");
+        Ok(self.body)
+    }
+
+    fn secret_for(&mut self, _enclave: &sgx_report_body_t) -> SgxResult<(Vec<u8>, Vec<u8>)> {
+        if self.refuse {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE);
+        }
+        Ok((b"key id 1".to_vec(), b"provisioned secret".to_vec()))
+    }
+}
+
+pub fn test_attest_provision() {
+    let session = SgxProvisionSession::new().unwrap();
+    let challenge = session.get_challenge().to_bytes();
+    assert_eq!(challenge.len(), SGX_PROVISION_CHALLENGE_SIZE);
+    assert_eq!(challenge[..8], *b"SGXP\x01\x00\x01\x00");
+    let nonce = *SgxProvisionChallenge::from_bytes(&challenge)
+        .unwrap()
+        .get_nonce();
+    assert_eq!(&nonce, session.get_challenge().get_nonce());
+    assert_ne!(
+        SgxProvisionSession::new()
+            .unwrap()
+            .get_challenge()
+            .get_nonce(),
+        &nonce
+    );
+    let mut kind = challenge;
+    kind[6] = 2;
+    let mut magic = challenge;
+    magic[0] = b'X';
+    let mut version = challenge;
+    version[4] = 2;
+    for bytes in [&kind[..], &magic[..], &version[..], &challenge[..39]].iter() {
+        assert!(SgxProvisionChallenge::from_bytes(bytes).is_err());
+    }
+
+    // The enclave sends its ephemeral key and a quote over the challenge and the key.
+    let client = SgxX25519PrivateKey::generate().unwrap();
+    let client_public = client.public_key();
+    let mut request = b"SGXP\x01\x00\x02\x00".to_vec();
+    request.extend_from_slice(&client_public);
+    request.extend_from_slice(b"quote");
+    assert!(SgxProvisionRequest::from_bytes(&request[..40]).is_err());
+    let request = SgxProvisionRequest::from_bytes(&request).unwrap();
+    assert_eq!(request.get_quote(), b"quote");
+    let mut binding = b"sgx_tattest provision request".to_vec();
+    binding.extend_from_slice(&nonce);
+    binding.extend_from_slice(&client_public);
+    let mut body = sgx_report_body_t::default();
+    body.report_data.d[..32].copy_from_slice(&rsgx_sha256_slice(&binding).unwrap());
+
+    let handle = SgxEccHandle::new();
+    handle.open().unwrap();
+    let (service_private, service_public) = handle.create_key_pair().unwrap();
+    let mut policy = FixedPolicy {
+        body,
+        refuse: false,
+    };
+    let bytes = session
+        .respond(&request, &mut policy, &service_private)
+        .unwrap()
+        .to_bytes();
+    assert_eq!(bytes[..8], *b"SGXP\x01\x00\x03\x00");
+    let response = SgxProvisionResponse::from_bytes(&bytes).unwrap();
+    assert_eq!(response.get_additional_txt(), b"key id 1");
+    assert_eq!(response.to_bytes(), bytes);
+
+    // The service signs the challenge and both ephemeral keys.
+    let service_x25519 = &bytes[8..40];
+    let mut transcript = b"sgx_tattest provision response".to_vec();
+    transcript.extend_from_slice(&nonce);
+    transcript.extend_from_slice(&client_public);
+    transcript.extend_from_slice(service_x25519);
+    let mut signature = sgx_ec256_signature_t::default();
+    for i in 0..8 {
+        let x = 40 + 4 * i;
+        let y = 72 + 4 * i;
+        signature.x[i] = u32::from_le_bytes([bytes[x], bytes[x + 1], bytes[x + 2], bytes[x + 3]]);
+        signature.y[i] = u32::from_le_bytes([bytes[y], bytes[y + 1], bytes[y + 2], bytes[y + 3]]);
+    }
+    assert!(handle
+        .ecdsa_verify_slice(&transcript[..], &service_public, &signature)
+        .unwrap());
+
+    // The secret unwraps with the key derived from the exchange.
+    assert_eq!(bytes[104..112], [8, 0, 0, 0, 18, 0, 0, 0]);
+    assert_eq!(&bytes[112..120], b"key id 1");
+    let mut peer = sgx_x25519_public_t::default();
+    peer.copy_from_slice(service_x25519);
+    let shared = client.diffie_hellman(&peer).unwrap();
+    let mut key = sgx_aes_gcm_128bit_key_t::default();
+    rsgx_hkdf_sha256(
+        b"sgx_tattest provision key",
+        shared.as_bytes(),
+        &transcript,
+        &mut key,
+    )
+    .unwrap();
+    let mut tag = sgx_aes_gcm_128bit_tag_t::default();
+    tag.copy_from_slice(&bytes[bytes.len() - 16..]);
+    let ciphertext = &bytes[120..bytes.len() - 16];
+    let mut secret = vec![0_u8; ciphertext.len()];
+    rsgx_rijndael128GCM_decrypt(
+        &key,
+        ciphertext,
+        &[0; SGX_AESGCM_IV_SIZE],
+        b"key id 1",
+        &tag,
+        &mut secret,
+    )
+    .unwrap();
+    assert_eq!(secret, b"provisioned secret");
+    assert!(rsgx_rijndael128GCM_decrypt(
+        &key,
+        ciphertext,
+        &[0; SGX_AESGCM_IV_SIZE],
+        b"key id 2",
+        &tag,
+        &mut secret
+    )
+    .is_err());
+
+    // Every session has its own service key.
+    let again = session
+        .respond(&request, &mut policy, &service_private)
+        .unwrap()
+        .to_bytes();
+    assert_ne!(again[8..40], bytes[8..40]);
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    let mut lengths = bytes.clone();
+    lengths[104] = 9;
+    let mut kind = bytes.clone();
+    kind[6] = 1;
+    for bytes in [trailing, lengths, kind, bytes[..bytes.len() - 1].to_vec()].iter() {
+        assert_eq!(
+            SgxProvisionResponse::from_bytes(bytes).err(),
+            Some(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        );
+    }
+
+    // A quote over another challenge or key is refused before a secret is chosen.
+    policy.refuse = true;
+    policy.body.report_data.d[0] ^= 1;
+    assert_eq!(
+        session
+            .respond(&request, &mut policy, &service_private)
+            .err(),
+        Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    );
+    let other = SgxProvisionSession::new().unwrap();
+    policy.body = body;
+    policy.refuse = false;
+    assert_eq!(
+        other.respond(&request, &mut policy, &service_private).err(),
+        Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    );
+    policy.refuse = true;
+    assert_eq!(
+        session
+            .respond(&request, &mut policy, &service_private)
+            .err(),
+        Some(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE)
+    );
+}
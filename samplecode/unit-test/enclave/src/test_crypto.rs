@@ -16,7 +16,9 @@
 // under the License..
 
 use sgx_tcrypto::*;
+use sgx_types::*;
 use std::string::String;
+use std::vec::Vec;
 use utils::*;
 
 static HASH_TEST_VEC: &'static [&'static str] = &[
@@ -52,3 +54,1093 @@ pub fn test_rsgx_sha256_handle() {
         assert_eq!(hex_to_bytes(HASH_SHA256_TRUTH[i]), hash);
     }
 }
+
+// Test case 4 of the GCM specification.
+static GCM_KEY: &'static str = "feffe9928665731c6d6a8f9467308308";
+static GCM_IV: &'static str = "cafebabefacedbaddecaf888";
+static GCM_AAD: &'static str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+static GCM_PLAINTEXT: &'static str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39";
+static GCM_CIPHERTEXT: &'static str = "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091";
+static GCM_TAG: &'static str = "5bc94fbc3221a5db94fae95ae7121a47";
+
+pub fn test_rsgx_aes_gcm_stream() {
+    let mut key = [0u8; 16];
+    key.copy_from_slice(&hex_to_bytes(GCM_KEY));
+    let mut iv = [0u8; 12];
+    iv.copy_from_slice(&hex_to_bytes(GCM_IV));
+    let aad = hex_to_bytes(GCM_AAD);
+    let plaintext = hex_to_bytes(GCM_PLAINTEXT);
+    let ciphertext = hex_to_bytes(GCM_CIPHERTEXT);
+    let mut tag = [0u8; 16];
+    tag.copy_from_slice(&hex_to_bytes(GCM_TAG));
+
+    // Odd chunk sizes, so blocks straddle update calls.
+    let mut gcm = SgxAesGcm::new_encrypt(&key, &iv);
+    gcm.update_aad(&aad[..7]).unwrap();
+    gcm.update_aad(&aad[7..]).unwrap();
+    let mut out = vec![0u8; plaintext.len()];
+    let mut offset = 0;
+    for chunk in plaintext.chunks(13) {
+        gcm.update(chunk, &mut out[offset..]).unwrap();
+        offset += chunk.len();
+    }
+    assert_eq!(gcm.update_aad(&aad), Err(sgx_types::sgx_status_t::SGX_ERROR_INVALID_STATE));
+    assert_eq!(gcm.finalize().unwrap(), tag);
+    assert_eq!(out, ciphertext);
+
+    let mut gcm = SgxAesGcm::new_decrypt(&key, &iv);
+    gcm.update_aad(&aad).unwrap();
+    let mut out = vec![0u8; ciphertext.len()];
+    let (head, tail) = ciphertext.split_at(17);
+    gcm.update(head, &mut out).unwrap();
+    gcm.update(tail, &mut out[17..]).unwrap();
+    gcm.verify(&tag).unwrap();
+    assert_eq!(out, plaintext);
+
+    let mut tampered = ciphertext.clone();
+    tampered[0] ^= 1;
+    let mut gcm = SgxAesGcm::new_decrypt(&key, &iv);
+    gcm.update_aad(&aad).unwrap();
+    gcm.update(&tampered, &mut out).unwrap();
+    assert_eq!(gcm.verify(&tag), Err(sgx_types::sgx_status_t::SGX_ERROR_MAC_MISMATCH));
+}
+
+pub fn test_rsgx_aes_ctr_cmac_stream() {
+    // NIST SP 800-38A F.5.1 and RFC 4493.
+    let mut key = [0u8; 16];
+    key.copy_from_slice(&hex_to_bytes("2b7e151628aed2a6abf7158809cf4f3c"));
+    let mut ctr = [0u8; 16];
+    ctr.copy_from_slice(&hex_to_bytes("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff"));
+    let plaintext = hex_to_bytes("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+    let ciphertext = hex_to_bytes("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff");
+
+    let mut aes_ctr = SgxAesCtr::new(&key, &ctr, 128).unwrap();
+    let mut out = vec![0u8; plaintext.len()];
+    let mut offset = 0;
+    for len in [5, 11, 3, 13].iter() {
+        aes_ctr.update(&plaintext[offset..offset + len], &mut out[offset..]).unwrap();
+        offset += len;
+    }
+    assert_eq!(out, ciphertext);
+    assert!(SgxAesCtr::new(&key, &ctr, 0).is_err());
+
+    let mut cmac = SgxAesCmac::new(&key).unwrap();
+    cmac.update(&plaintext[..7]).unwrap();
+    cmac.update(&plaintext[7..16]).unwrap();
+    assert_eq!(cmac.finalize().unwrap().to_vec(), hex_to_bytes("070a16b46b4d4144f79bdd9dd04a287c"));
+
+    let mut tag = [0u8; 16];
+    tag.copy_from_slice(&hex_to_bytes("bb1d6929e95937287fa37d129b756746"));
+    SgxAesCmac::new(&key).unwrap().verify(&tag).unwrap();
+    tag[0] ^= 1;
+    assert!(SgxAesCmac::new(&key).unwrap().verify(&tag).is_err());
+}
+
+pub fn test_rsgx_sha3() {
+    let hash = rsgx_sha3_256_slice(b"abc").unwrap();
+    assert_eq!(
+        hash.to_vec(),
+        hex_to_bytes("3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532")
+    );
+
+    let mut sha3 = SgxSha3_512::new();
+    sha3.update_slice(b"a");
+    sha3.update_slice(b"bc");
+    assert_eq!(
+        sha3.finalize().to_vec(),
+        hex_to_bytes("b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0")
+    );
+
+    let empty: &[u8] = &[];
+    assert_eq!(
+        rsgx_keccak256_slice(empty).unwrap().to_vec(),
+        hex_to_bytes("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+    );
+
+    // SHAKE output can be read in pieces.
+    let mut reader = SgxShake128::new().finalize_xof();
+    let mut out = [0u8; 32];
+    reader.squeeze(&mut out[..10]);
+    reader.squeeze(&mut out[10..]);
+    assert_eq!(
+        out.to_vec(),
+        hex_to_bytes("7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26")
+    );
+}
+
+pub fn test_rsgx_kdf() {
+    // RFC 5869, test case 1
+    let ikm = [0x0b_u8; 22];
+    let salt = hex_to_bytes("000102030405060708090a0b0c");
+    let info = hex_to_bytes("f0f1f2f3f4f5f6f7f8f9");
+    let prk = rsgx_hkdf_sha256_extract(&salt, &ikm).unwrap();
+    assert_eq!(
+        prk.to_vec(),
+        hex_to_bytes("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5")
+    );
+    let mut okm = [0u8; 42];
+    rsgx_hkdf_sha256_expand(&prk, &info, &mut okm).unwrap();
+    assert_eq!(
+        okm.to_vec(),
+        hex_to_bytes("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865")
+    );
+    assert!(rsgx_hkdf_sha256_expand(&prk, &info, &mut [0u8; 255 * 32 + 1]).is_err());
+
+    // RFC 5869, test case 3: empty salt and info
+    let prk = rsgx_hkdf_sha256_extract(&[], &ikm).unwrap();
+    assert_eq!(
+        prk.to_vec(),
+        hex_to_bytes("19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04")
+    );
+    rsgx_hkdf_sha256_expand(&prk, &[], &mut okm).unwrap();
+    assert_eq!(
+        okm.to_vec(),
+        hex_to_bytes("8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8")
+    );
+
+    // RFC 7914, section 11
+    let mut dk = [0u8; 64];
+    rsgx_pbkdf2_hmac_sha256(b"passwd", b"salt", 1, &mut dk).unwrap();
+    assert_eq!(
+        dk.to_vec(),
+        hex_to_bytes("55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783")
+    );
+    assert!(rsgx_pbkdf2_hmac_sha256(b"passwd", b"salt", 0, &mut dk).is_err());
+
+    // Keys derived from the sealing key are stable and separated by info.
+    let mut k1 = [0u8; 32];
+    let mut k2 = [0u8; 32];
+    let mut k3 = [0u8; 32];
+    sgx_tseal::rsgx_derive_seal_key(b"app key", &mut k1).unwrap();
+    sgx_tseal::rsgx_derive_seal_key(b"app key", &mut k2).unwrap();
+    sgx_tseal::rsgx_derive_seal_key(b"other key", &mut k3).unwrap();
+    assert_eq!(k1, k2);
+    assert_ne!(k1, k3);
+}
+
+pub fn test_rsgx_ed25519_x25519() {
+    // RFC 8032, section 7.1, test 2
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&hex_to_bytes(
+        "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+    ));
+    let key = SgxEd25519PrivateKey::from_seed(&seed);
+    assert_eq!(
+        key.public_key().to_vec(),
+        hex_to_bytes("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c")
+    );
+    let sig = key.sign(&[0x72]);
+    assert_eq!(
+        sig.to_vec(),
+        hex_to_bytes("92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00")
+    );
+    assert!(rsgx_ed25519_verify(&key.public_key(), &[0x72], &sig).unwrap());
+    assert!(!rsgx_ed25519_verify(&key.public_key(), &[0x73], &sig).unwrap());
+
+    let key = SgxEd25519PrivateKey::generate().unwrap();
+    let sig = key.sign(b"message");
+    assert!(rsgx_ed25519_verify(&key.public_key(), b"message", &sig).unwrap());
+
+    // RFC 7748, section 6.1
+    let mut a = [0u8; 32];
+    let mut b = [0u8; 32];
+    a.copy_from_slice(&hex_to_bytes(
+        "77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a",
+    ));
+    b.copy_from_slice(&hex_to_bytes(
+        "5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb",
+    ));
+    let alice = SgxX25519PrivateKey::from_bytes(&a);
+    let bob = SgxX25519PrivateKey::from_bytes(&b);
+    assert_eq!(
+        alice.public_key().to_vec(),
+        hex_to_bytes("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+    );
+    let shared = alice.diffie_hellman(&bob.public_key()).unwrap();
+    assert_eq!(
+        shared.as_bytes().to_vec(),
+        hex_to_bytes("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742")
+    );
+    assert_eq!(
+        bob.diffie_hellman(&alice.public_key()).unwrap().as_bytes(),
+        shared.as_bytes()
+    );
+    assert!(alice.diffie_hellman(&[0u8; 32]).is_err());
+}
+
+fn le_from_hex(hex: &str, out: &mut [u8]) {
+    let mut bytes = hex_to_bytes(hex);
+    bytes.reverse();
+    out.copy_from_slice(&bytes);
+}
+
+pub fn test_rsgx_ecdsa_p384_rfc6979() {
+    // RFC 6979, A.2.6, with SHA-384 and message "sample"
+    let mut private = sgx_ec384_private_t::default();
+    le_from_hex(
+        "6b9d3dad2e1b8c1c05b19875b6659f4de23c3b667bf297ba9aa47740787137d896d5724e4c70a825f872c9ea60d2edf5",
+        &mut private.r,
+    );
+    let public = rsgx_ecc384_pub_from_priv(&private).unwrap();
+    assert!(rsgx_ecc384_check_point(&public).unwrap());
+
+    let sig = rsgx_ecdsa384_sign_slice(b"sample", &private, SgxEcdsaNonce::Deterministic).unwrap();
+    let mut r = sgx_ec384_signature_t::default();
+    let mut r_bytes = [0u8; 48];
+    le_from_hex(
+        "94edbb92a5ecb8aad4736e56c691916b3f88140666ce9fa73d64c4ea95ad133c81a648152e44acf96e36dd1e80fabe46",
+        &mut r_bytes,
+    );
+    for (w, chunk) in r.x.iter_mut().zip(r_bytes.chunks(4)) {
+        *w = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    assert_eq!(sig.x, r.x);
+    assert!(rsgx_ecdsa384_verify_slice(b"sample", &public, &sig).unwrap());
+    assert!(!rsgx_ecdsa384_verify_slice(b"samplf", &public, &sig).unwrap());
+
+    let sig = rsgx_ecdsa384_sign_slice(b"sample", &private, SgxEcdsaNonce::Random).unwrap();
+    assert!(rsgx_ecdsa384_verify_slice(b"sample", &public, &sig).unwrap());
+
+    let (d1, q1) = rsgx_ecc384_create_key_pair().unwrap();
+    let (d2, q2) = rsgx_ecc384_create_key_pair().unwrap();
+    assert_eq!(
+        rsgx_ecc384_compute_shared_dhkey(&d1, &q2).unwrap().s.to_vec(),
+        rsgx_ecc384_compute_shared_dhkey(&d2, &q1).unwrap().s.to_vec()
+    );
+
+    // A deterministic P-256 signature verifies with the SDK implementation.
+    let ecc = SgxEccHandle::new();
+    ecc.open().unwrap();
+    let (private, public) = ecc.create_key_pair().unwrap();
+    let sig1 = rsgx_ecdsa256_sign_deterministic_slice(b"sample", &private).unwrap();
+    let sig2 = rsgx_ecdsa256_sign_deterministic_slice(b"sample", &private).unwrap();
+    assert_eq!(sig1.x, sig2.x);
+    assert_eq!(sig1.y, sig2.y);
+    assert!(ecc.ecdsa_verify_slice(b"sample", &public, &sig1).unwrap());
+}
+
+// RSA-2048 key, OAEP ciphertext and PSS signature generated with OpenSSL
+const RSA2048_N: &str = concat!(
+    "adb99fca394883b838e27d9b8fcb9a5033ceb55f4a724a6b5eed45cae4912546",
+    "9b1b9799fd2edbaa39af5c81e699b15e586a7b01d3c2c1a2244188a926d94f60",
+    "320618478bbd8f0bd79e4e553eefe61a9cdcdb95e8803166ea38ffe2800b4fe9",
+    "2c073dbb6d64a5153f77739c27a5654987fa80e926cfc9e38f346b8894cede6a",
+    "fea42be69a35be9153ea5ce945758a1839aa2996263357cc8607b3f3cc9fd947",
+    "3eeba54fb49e9f3c1a5465f098dee73d4b292ff987f660cd04892a176991493a",
+    "18656737b699bacc39f9eacc6388777afe0a8460dd89ac1d4b135c7df2449676",
+    "0bc1123ce2b05d402d1319c6a5fba69bfe77819b19bbebafa9e7aa18350929f3",
+);
+
+const RSA2048_P: &str = concat!(
+    "d6f930903a3f8d43d248140ed880e9710b8617945028f07c43f5025799d32c49",
+    "383567f69594788667c9029e3fc05ec46fb8144ca0cd9b3e9d1e7e25d347b37e",
+    "f72b0de4afd05b95d4d24b562a4d1ba17f7721ca69feea170a4c248b3c81a5b0",
+    "226285d11f49e56ba3da49e79c6282bb8c8c6effba59a4e0dbf78ff29b7f901f",
+);
+
+const RSA2048_Q: &str = concat!(
+    "cee131a7a7aab131a88794fd1dc3581789f9f876bc4155b510038fba9a16097c",
+    "6f560b0ba49605b45b553716df802f5e81b93cc645a6941c71f188f5caf5f531",
+    "f866e072a8a3db6012af21186b1b99fffec9b4619b5c6a6ac743b071f8a00097",
+    "724d9a1d9f31c40d0cd9a4bf3cc4e4e92a72bed1203788ef198f9bb5422e9bad",
+);
+
+const RSA2048_DMP1: &str = concat!(
+    "83387090593dd9b9006ec6ef98f0778e6d7e587e4bbff1aba8bfb1c24f3f3bd5",
+    "25de53ab8cfaa7e7430acbf254efd3e90c58cceb496cb7caac95a462ea68e604",
+    "2653bd934e4e5cd944a369211b891dc8a2c9d17280e8fd733af1e78ca64e6d18",
+    "eaa44112b98a9de3ea8ca07824596d7b95fb52f5fe8621c2b002997779bb8ff9",
+);
+
+const RSA2048_DMQ1: &str = concat!(
+    "2cbe2b9d14f827c693aa6f20a7353e7228d7355388b7a0afbe3ce6f2f6ac5974",
+    "de277fd00ed29b7d3c5b9bc488a612989ff9ce191bab41513d9b7d819b59c2a4",
+    "02134debbe6961e2cf67ad02b8f78941027be15552e293e9701b45a8009baa45",
+    "16e58db5665ebf060b876ec8257e77fbadc08f606f55537a52ceea6033661d29",
+);
+
+const RSA2048_IQMP: &str = concat!(
+    "229fa854b63197e583ae89f20cec1fc144e2f5dbebfa37203775bcf168c6a1a5",
+    "40347abef9e6fa398ea47aafb643b1c12ede597d8b0a8d1bfc780d13d4e79528",
+    "9e3ded4dd0d96edc3c05c3bba088e2c3336cd4391758320160cdc3f5a0223dd8",
+    "90e4f1b6cdbe07f590cf8c27e72d4e13e509f620eacf94802716835a8b04a545",
+);
+
+const RSA2048_OAEP_SHA256: &str = concat!(
+    "7f1efbc810e3ededb45db5db002a9d82dfc59e6f0f9514e71a1f981153288f16",
+    "c68b584750c48f8adf550f2dae5e084d096d7382a98a21ad76d73c630c798b65",
+    "f874de2d23aaaaef02eb64bbea5aa9ba4747c6a63eb1fb2dc75e234019e9aa73",
+    "0300b3580d3f5e25f8b182cf05d53b25d92cadb4b1cfbdb0097bd838560df17e",
+    "8983eca83a91b1a36d285acfe6d95c20e35e81448479cdf64f5cd5bcaa69739a",
+    "0c92f5e3dc0b47c3f3df55a9099fbf58153b7f132f73f9a011c779868ad20c67",
+    "de53abcdf78d5cf0e43ec6e2c8c36ad6a76f5f5a212831f1b78cf4c66448b94e",
+    "aa0143a84eec4047d1d7c6a4de36472af5140b0804b40f49b3a61211d5eceaae",
+);
+
+const RSA2048_PSS_SHA256: &str = concat!(
+    "25b18c974efe0103c71a2b50ad57ca6f01f14e1df87a8a4cda4af47c48c0f116",
+    "cc4b925d941547ff73fd86c9484c0408c3d40dbab48766d8f1a3c36b13a616f1",
+    "e7a7af550e9674ad4054b97a3a37e798301e09b20fb484fc41241dd09a4763da",
+    "0d06bdfc6a47576f4f118ce0ecb218e2dc9bde94822820ee0ced398592af5681",
+    "fec0814e1a41370a6c922ec0eb3ffdb74013705f6cc5aa51ac5151563638c1fb",
+    "04f15a4d8b2f6115856f49e6b986f56178f935544f9ecaf6683b22aab5b08c36",
+    "60037ad3171fae655026d6d1ccd49ff17c97890ec35ff85a90df030e508e0052",
+    "bfa13bd1e2765c31461b5f0175d21fa9e6538d60ace75079feb9f7e5023b1413",
+);
+
+fn le_bytes_from_hex(hex: &str) -> Vec<u8> {
+    let mut bytes = hex_to_bytes(hex);
+    bytes.reverse();
+    bytes
+}
+
+pub fn test_rsgx_rsa_oaep_pss() {
+    let key = SgxRsaPrivateKey::new(
+        &le_bytes_from_hex(RSA2048_N),
+        &65537u32.to_le_bytes(),
+        &le_bytes_from_hex(RSA2048_P),
+        &le_bytes_from_hex(RSA2048_Q),
+        &le_bytes_from_hex(RSA2048_DMP1),
+        &le_bytes_from_hex(RSA2048_DMQ1),
+        &le_bytes_from_hex(RSA2048_IQMP),
+    )
+    .unwrap();
+    let public = key.public_key();
+
+    let ciphertext = hex_to_bytes(RSA2048_OAEP_SHA256);
+    let plaintext = key.oaep_decrypt(SgxRsaHash::Sha256, b"", &ciphertext).unwrap();
+    assert_eq!(plaintext, b"sgx oaep");
+    assert!(key.oaep_decrypt(SgxRsaHash::Sha256, b"label", &ciphertext).is_err());
+
+    let signature = hex_to_bytes(RSA2048_PSS_SHA256);
+    assert!(public.pss_verify(SgxRsaHash::Sha256, b"sgx pss", &signature).unwrap());
+    assert!(!public.pss_verify(SgxRsaHash::Sha256, b"sgx psS", &signature).unwrap());
+
+    let ciphertext = public.oaep_encrypt(SgxRsaHash::Sha384, b"label", b"round trip").unwrap();
+    assert_eq!(key.oaep_decrypt(SgxRsaHash::Sha384, b"label", &ciphertext).unwrap(), b"round trip");
+
+    // Streaming signatures match one-shot verification.
+    let signer = key.pss_signer(SgxRsaHash::Sha384).unwrap();
+    signer.update(b"round ").unwrap();
+    signer.update(b"trip").unwrap();
+    let signature = signer.sign().unwrap();
+    assert!(public.pss_verify(SgxRsaHash::Sha384, b"round trip", &signature).unwrap());
+
+    // Ciphertexts interoperate with the SDK's RSA-OAEP-SHA256 in both directions.
+    let generated = SgxRsaPrivateKey::generate(384, 65537).unwrap();
+    let sdk_public = SgxRsaPubKey::new();
+    sdk_public.create(384, 4, generated.public_key().n(), generated.public_key().e()).unwrap();
+    let mut ciphertext = vec![0u8; 384];
+    let mut len = ciphertext.len();
+    sdk_public.encrypt_sha256(&mut ciphertext, &mut len, b"sdk").unwrap();
+    assert_eq!(
+        generated.oaep_decrypt(SgxRsaHash::Sha256, b"", &ciphertext[..len]).unwrap(),
+        b"sdk"
+    );
+
+    let sdk_private = SgxRsaPrivKey::new();
+    sdk_private
+        .create(
+            256,
+            4,
+            &65537u32.to_le_bytes(),
+            &le_bytes_from_hex(RSA2048_P),
+            &le_bytes_from_hex(RSA2048_Q),
+            &le_bytes_from_hex(RSA2048_DMP1),
+            &le_bytes_from_hex(RSA2048_DMQ1),
+            &le_bytes_from_hex(RSA2048_IQMP),
+        )
+        .unwrap();
+    let ciphertext = public.oaep_encrypt(SgxRsaHash::Sha256, b"", b"to sdk").unwrap();
+    let mut plaintext = vec![0u8; 256];
+    let mut len = plaintext.len();
+    sdk_private.decrypt_sha256(&mut plaintext, &mut len, &ciphertext).unwrap();
+    assert_eq!(&plaintext[..len], b"to sdk");
+}
+
+pub fn test_rsgx_rustcrypto_traits() {
+    use sgx_tcrypto::aead::{Aead, Key, KeyInit, Nonce};
+    use sgx_tcrypto::digest::Digest;
+    use sgx_tcrypto::signature::{Signer, Verifier};
+
+    assert_eq!(
+        SgxSha256::digest(b"abc").to_vec(),
+        hex_to_bytes("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+    );
+    let mut sha384 = SgxSha384::new();
+    sha384.update(b"");
+    assert_eq!(
+        sha384.finalize().to_vec(),
+        hex_to_bytes(
+            "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b"
+        )
+    );
+    assert_eq!(SgxSha3_256::digest(b"abc").to_vec(), rsgx_sha3_256_slice(b"abc").unwrap().to_vec());
+
+    // AES-GCM test cases 1 and 2 from the GCM specification
+    let cipher = SgxAes128Gcm::new(Key::<SgxAes128Gcm>::from_slice(&[0u8; 16]));
+    let nonce = Nonce::<SgxAes128Gcm>::from_slice(&[0u8; 12]);
+    let sealed = cipher.encrypt(nonce, [0u8; 0].as_ref()).unwrap();
+    assert_eq!(sealed, hex_to_bytes("58e2fccefa7e3061367f1d57a4e7455a"));
+    let mut sealed = cipher.encrypt(nonce, [0u8; 16].as_ref()).unwrap();
+    assert_eq!(
+        sealed,
+        hex_to_bytes("0388dace60b6a392f328c2b971b2fe78ab6e47d42cec13bdf53a67b21257bddf")
+    );
+    assert_eq!(cipher.decrypt(nonce, sealed.as_ref()).unwrap(), [0u8; 16]);
+    sealed[0] ^= 1;
+    assert!(cipher.decrypt(nonce, sealed.as_ref()).is_err());
+
+    let ed25519 = SgxEd25519PrivateKey::from_seed(&[7u8; 32]);
+    let signature: sgx_ed25519_signature_t = ed25519.try_sign(b"message").unwrap();
+    let verifying = SgxEd25519VerifyingKey(ed25519.public_key());
+    assert!(verifying.verify(b"message", &signature).is_ok());
+    assert!(verifying.verify(b"massage", &signature).is_err());
+
+    let (private, public) = rsgx_ecc384_create_key_pair().unwrap();
+    let signature = SgxEcdsa384SigningKey::new(&private).try_sign(b"").unwrap();
+    assert!(SgxEcdsa384VerifyingKey(public).verify(b"", &signature).is_ok());
+
+    let ecc = SgxEccHandle::new();
+    ecc.open().unwrap();
+    let (private, public) = ecc.create_key_pair().unwrap();
+    let signature = SgxEcdsa256SigningKey::new(&private).try_sign(b"message").unwrap();
+    assert!(SgxEcdsa256VerifyingKey(public).verify(b"message", &signature).is_ok());
+    assert!(ecc.ecdsa_verify_slice(b"message", &public, &signature).unwrap());
+}
+
+pub fn test_rsgx_secret_utils() {
+    assert!(rsgx_ct_eq(b"tag", b"tag"));
+    assert!(!rsgx_ct_eq(b"tag", b"taG"));
+    assert!(!rsgx_ct_eq(b"tag", b"tag!"));
+    assert!(rsgx_ct_eq(b"", b""));
+
+    let mut buf = [0xa5u8; 33];
+    rsgx_zeroize(&mut buf);
+    assert_eq!(buf, [0u8; 33]);
+
+    let key = SgxSecret::new([0x42u8; 16]);
+    assert_eq!(key.expose(), &[0x42u8; 16]);
+    assert_eq!(format!("{:?}", key), "SgxSecret(..)");
+    let mut other: SgxSecret<[u8; 16]> = SgxSecret::default();
+    assert!(!key.ct_eq(&other));
+    other.expose_mut().copy_from_slice(&[0x42u8; 16]);
+    assert!(key.ct_eq(&other));
+
+    rsgx_lfence();
+    rsgx_mfence();
+}
+
+pub fn test_rsgx_aes_key_wrap() {
+    // RFC 3394, 4.6
+    let kek = hex_to_bytes("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+    let key = hex_to_bytes("00112233445566778899aabbccddeeff000102030405060708090a0b0c0d0e0f");
+    let wrapped = rsgx_aes_kw_wrap(&kek, &key).unwrap();
+    assert_eq!(
+        wrapped,
+        hex_to_bytes(
+            "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326cbc7f0e71a99f43bfb988b9b7a02dd21"
+        )
+    );
+    assert_eq!(rsgx_aes_kw_unwrap(&kek, &wrapped).unwrap(), key);
+    let mut tampered = wrapped.clone();
+    tampered[20] ^= 1;
+    assert_eq!(
+        rsgx_aes_kw_unwrap(&kek, &tampered).unwrap_err(),
+        sgx_status_t::SGX_ERROR_MAC_MISMATCH
+    );
+    assert!(rsgx_aes_kw_wrap(&kek, &key[..12]).is_err());
+
+    // RFC 5649, section 6
+    let kek = hex_to_bytes("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8");
+    let key = hex_to_bytes("c37b7e6492584340bed12207808941155068f738");
+    let wrapped = rsgx_aes_kwp_wrap(&kek, &key).unwrap();
+    assert_eq!(
+        wrapped,
+        hex_to_bytes("138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a")
+    );
+    assert_eq!(rsgx_aes_kwp_unwrap(&kek, &wrapped).unwrap(), key);
+    let wrapped = rsgx_aes_kwp_wrap(&kek, &hex_to_bytes("466f7250617369")).unwrap();
+    assert_eq!(wrapped, hex_to_bytes("afbeb0f07dfbf5419200f2ccb50bb24f"));
+    assert_eq!(rsgx_aes_kwp_unwrap(&kek, &wrapped).unwrap(), hex_to_bytes("466f7250617369"));
+    assert!(rsgx_aes_kwp_unwrap(&kek[..16], &wrapped).is_err());
+}
+
+pub fn test_rsgx_sha256_batch() {
+    let long = [0x61u8; 1000];
+    let msgs: Vec<&[u8]> = vec![b"", b"abc", &long[..55], &long[..56], &long[..64], &long, b"abc"];
+    let hashes = rsgx_sha256_batch(&msgs).unwrap();
+    assert_eq!(hashes.len(), msgs.len());
+    for (msg, hash) in msgs.iter().zip(hashes.iter()) {
+        let handle = SgxShaHandle::new();
+        handle.init().unwrap();
+        if !msg.is_empty() {
+            handle.update_slice(msg).unwrap();
+        }
+        assert_eq!(hash, &handle.get_hash().unwrap());
+    }
+    assert_eq!(
+        hashes[1].to_vec(),
+        hex_to_bytes("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+    );
+    assert_eq!(hashes[1], hashes[6]);
+    assert!(rsgx_sha256_batch(&[]).unwrap().is_empty());
+}
+
+pub fn test_rsgx_aes_gcm_siv() {
+    // RFC 8452, C.1 and C.2
+    let nonce = [3u8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let mut key = [0u8; 32];
+    key[0] = 1;
+    let plaintext = hex_to_bytes("0100000000000000");
+    let mut ciphertext = [0u8; 8];
+    let siv = SgxAesGcmSiv::new_encrypt(&key[..16], &nonce).unwrap();
+    let tag = siv.finalize(&plaintext, &mut ciphertext).unwrap();
+    assert_eq!(ciphertext.to_vec(), hex_to_bytes("b5d839330ac7b786"));
+    assert_eq!(tag.to_vec(), hex_to_bytes("578782fff6013b815b287c22493a364c"));
+
+    let plaintext = hex_to_bytes("020000000000000000000000000000000300000000000000");
+    let expected = hex_to_bytes(
+        "7eea6a8fd59f25afd85ee834afc9ef1e09e0e365ebbc6b7b24b983741740291fd37c17fe62e36b29",
+    );
+    let mut siv = SgxAesGcmSiv::new_encrypt(&key, &nonce).unwrap();
+    siv.update_aad(&[1]).unwrap();
+    let mut ciphertext = [0u8; 24];
+    let tag = siv.finalize(&plaintext, &mut ciphertext).unwrap();
+    assert_eq!(ciphertext.to_vec(), expected[..24].to_vec());
+    assert_eq!(tag.to_vec(), expected[24..].to_vec());
+
+    let decrypt = |ciphertext: &[u8], tag: &sgx_aes_gcm_128bit_tag_t| {
+        let mut siv = SgxAesGcmSiv::new_decrypt(&key, &nonce, tag).unwrap();
+        siv.update_aad(&[1]).unwrap();
+        let mut out = [0u8; 24];
+        siv.update(&ciphertext[..5], &mut out[..5]).unwrap();
+        siv.update(&ciphertext[5..], &mut out[5..]).unwrap();
+        siv.verify().map(|_| out)
+    };
+    assert_eq!(decrypt(&ciphertext, &tag).unwrap().to_vec(), plaintext);
+    ciphertext[23] ^= 1;
+    assert_eq!(decrypt(&ciphertext, &tag).unwrap_err(), sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+    assert!(SgxAesGcmSiv::new_encrypt(&key[..24], &nonce).is_err());
+}
+
+pub fn test_rsgx_chacha20_poly1305() {
+    // RFC 8439, 2.8.2
+    let mut key = [0u8; 32];
+    for (i, k) in key.iter_mut().enumerate() {
+        *k = 0x80 + i as u8;
+    }
+    let nonce = [0x07u8, 0, 0, 0, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47];
+    let aad = hex_to_bytes("50515253c0c1c2c3c4c5c6c7");
+    let plaintext: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you only one \
+        tip for the future, sunscreen would be it.";
+    let expected = hex_to_bytes(concat!(
+        "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6",
+        "3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36",
+        "92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc",
+        "3ff4def08e4b7a9de576d26586cec64b6116"
+    ));
+
+    let mut aead = SgxChaCha20Poly1305::new_encrypt(&key, &nonce);
+    aead.update_aad(&aad[..4]).unwrap();
+    aead.update_aad(&aad[4..]).unwrap();
+    let mut ciphertext = vec![0u8; plaintext.len()];
+    aead.update(&plaintext[..70], &mut ciphertext[..70]).unwrap();
+    aead.update(&plaintext[70..], &mut ciphertext[70..]).unwrap();
+    assert_eq!(aead.update_aad(&aad).unwrap_err(), sgx_status_t::SGX_ERROR_INVALID_STATE);
+    let tag = aead.finalize().unwrap();
+    assert_eq!(ciphertext, expected);
+    assert_eq!(tag.to_vec(), hex_to_bytes("1ae10b594f09e26a7e902ecbd0600691"));
+
+    let mut aead = SgxChaCha20Poly1305::new_decrypt(&key, &nonce);
+    aead.update_aad(&aad).unwrap();
+    let mut decrypted = vec![0u8; ciphertext.len()];
+    aead.update(&ciphertext, &mut decrypted).unwrap();
+    aead.verify(&tag).unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    let mut aead = SgxChaCha20Poly1305::new_decrypt(&key, &nonce);
+    aead.update(&ciphertext, &mut decrypted).unwrap();
+    assert_eq!(aead.verify(&tag).unwrap_err(), sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+}
+
+pub fn test_rsgx_x509() {
+    let oid: &[u64] = &[1, 2, 840, 113741, 1337, 6];
+    let ecc_handle = SgxEccHandle::new();
+    ecc_handle.open().unwrap();
+    let (private, public) = ecc_handle.create_key_pair().unwrap();
+    let der = SgxX509Builder::new(
+        SgxX509Name::new().organization("Teaclave").common_name("enclave"),
+        SgxX509PublicKey::EcP256(public),
+    )
+    .validity(1704067200, 2019686400)
+    .subject_alt_name(SgxX509AltName::Dns("enclave.example".into()))
+    .subject_alt_name(SgxX509AltName::Ip(vec![127, 0, 0, 1]))
+    .extension(oid, false, b"report")
+    .sign(SgxX509SigningKey::EcP256(&private))
+    .unwrap();
+
+    let cert = SgxX509Certificate::parse(&der).unwrap();
+    assert_eq!(cert.subject().get_common_name(), Some("enclave"));
+    assert_eq!(cert.issuer(), cert.subject());
+    assert_eq!(cert.not_after(), 2019686400);
+    assert!(cert.is_valid_at(1800000000));
+    assert_eq!(
+        cert.subject_alt_names().unwrap(),
+        vec![
+            SgxX509AltName::Dns("enclave.example".into()),
+            SgxX509AltName::Ip(vec![127, 0, 0, 1])
+        ]
+    );
+    assert_eq!(cert.extension(oid), Some((false, &b"report"[..])));
+    assert!(cert.verify_signature(cert.public_key().unwrap()).unwrap());
+    let mut tampered = der.clone();
+    let pos = tampered.windows(6).position(|w| w == b"report").unwrap();
+    tampered[pos] ^= 1;
+    let cert = SgxX509Certificate::parse(&tampered).unwrap();
+    assert!(!cert.verify_signature(cert.public_key().unwrap()).unwrap());
+    assert!(SgxX509Certificate::parse(&der[..der.len() - 1]).is_err());
+
+    let (private, public) = rsgx_ecc384_create_key_pair().unwrap();
+    let der = SgxX509Builder::new(SgxX509Name::new().common_name("p384"), SgxX509PublicKey::EcP384(public))
+        .sign(SgxX509SigningKey::EcP384(&private))
+        .unwrap();
+    let cert = SgxX509Certificate::parse(&der).unwrap();
+    assert!(cert.verify_signature(&SgxX509PublicKey::EcP384(public)).unwrap());
+
+    // Made by OpenSSL.
+    let der = hex_to_bytes(concat!(
+        "308201283081cfa00302010202021001300a06082a8648ce3d040302300f310d",
+        "300b06035504030c0474657374301e170d3234303130313030303030305a170d",
+        "3334303130313030303030305a300f310d300b06035504030c04746573743059",
+        "301306072a8648ce3d020106082a8648ce3d03010703420004088bb9ff22ab29",
+        "1a74c86fc677ba897baadee370cc6129b82d170ba3fc26415c442da9a7160679",
+        "56d91eaa02b93ad409490e87cd5e758ea6a331a1deb75ba846a31b3019301706",
+        "03551d110410300e820c746573742e6578616d706c65300a06082a8648ce3d04",
+        "03020348003045022100e64e754973431752210fe4f9461113e9836d87979339",
+        "0db2c8cacd4527b0349102202f3af90f96267aabe38cc453da4df0e0bf797270",
+        "4b2cdbfc99a906dd5d3070e9"
+    ));
+    let cert = SgxX509Certificate::parse(&der).unwrap();
+    assert_eq!(cert.serial_number(), &[0x10, 0x01]);
+    assert_eq!(cert.not_before(), 1704067200);
+    assert_eq!(cert.subject_alt_names().unwrap(), vec![SgxX509AltName::Dns("test.example".into())]);
+    assert!(cert.verify_signature(cert.public_key().unwrap()).unwrap());
+}
+
+pub fn test_rsgx_x509_crl() {
+    // A CA and a CRL revoking serial 1001, made by OpenSSL.
+    let ca = hex_to_bytes(concat!(
+        "3082017330820119a0030201020214224fa3327a171404524f48e537aa48e596",
+        "c4591b300a06082a8648ce3d040302300f310d300b06035504030c0474657374",
+        "301e170d3236313031343038353332315a170d3336313031313038353332315a",
+        "300f310d300b06035504030c04746573743059301306072a8648ce3d02010608",
+        "2a8648ce3d03010703420004de80bf45fdfdc87beed7c06f0021edc50aa0ab0a",
+        "3dfc5e2d3b44a06dc478af6ecbb943152a0bd5e9f7ec7599b160576a3bff8fa9",
+        "cd734688353610d67217c9e8a3533051301d0603551d0e041604142a9564012b",
+        "2325522876fb17091dd40b51643dbb301f0603551d230418301680142a956401",
+        "2b2325522876fb17091dd40b51643dbb300f0603551d130101ff040530030101",
+        "ff300a06082a8648ce3d0403020348003045022100cfad0c6e4f573f39a755e0",
+        "21b5a2e9134a1524895244cbdfd8892a0f3873340e02204a0fab0abc539e8a3d",
+        "4c1003de3205deeaeeb24339075c3f814ca4eca3130a08"
+    ));
+    let der = hex_to_bytes(concat!(
+        "3081be3066020101300a06082a8648ce3d040302300f310d300b06035504030c",
+        "0474657374170d3234303130313030303030305a170d33343031303130303030",
+        "30305a3015301302021001170d3234303130313030303030305aa00f300d300b",
+        "0603551d14040402021001300a06082a8648ce3d04030203480030450221008d",
+        "58e415d104179d3be75915e8dc15e55e28f9d6f10020aadbc533cd107d282102",
+        "206feff88a7c7896fd6b12f50aabb21dc349bb41d735cc669990a484d3fdbb1a",
+        "16"
+    ));
+    let ca = SgxX509Certificate::parse(&ca).unwrap();
+    assert_eq!(ca.basic_constraints().unwrap(), (true, None));
+    let crl = SgxX509Crl::parse(&der).unwrap();
+    assert_eq!(crl.issuer(), ca.subject());
+    assert_eq!(crl.this_update(), 1704067200);
+    assert_eq!(crl.next_update(), Some(2019686400));
+    assert!(crl.is_current_at(1800000000));
+    assert!(!crl.is_current_at(2019686401));
+    assert_eq!(crl.revoked_serial_numbers(), &[&[0x10, 0x01][..]]);
+    assert!(crl.is_revoked(&[0x10, 0x01]));
+    assert!(!crl.is_revoked(&[0x10, 0x02]));
+    assert!(crl.verify_signature(ca.public_key().unwrap()).unwrap());
+    let mut tampered = der.clone();
+    let pos = tampered.windows(2).position(|w| w == [0x10, 0x01]).unwrap();
+    tampered[pos + 1] ^= 1;
+    let crl = SgxX509Crl::parse(&tampered).unwrap();
+    assert!(!crl.verify_signature(ca.public_key().unwrap()).unwrap());
+    assert!(SgxX509Crl::parse(&der[..der.len() - 1]).is_err());
+}
+
+pub fn test_rsgx_hmac_incremental() {
+    // RFC 4231, test case 2
+    let mut hmac = SgxHmacSha256::new(b"Jefe").unwrap();
+    hmac.update(b"what do ya want ").unwrap();
+    hmac.update(b"").unwrap();
+    hmac.update(b"for nothing?").unwrap();
+    assert_eq!(
+        hmac.finalize().unwrap().to_vec(),
+        hex_to_bytes("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+    );
+    let mut hmac = SgxHmacSha384::new(b"Jefe").unwrap();
+    hmac.update(b"what do ya want for nothing?").unwrap();
+    assert_eq!(
+        hmac.finalize().unwrap().to_vec(),
+        hex_to_bytes(concat!(
+            "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47",
+            "e42ec3736322445e8e2240ca5e69e2c78b3239ecfab21649"
+        ))
+    );
+
+    let key = [0x0bu8; SGX_HMAC256_KEY_SIZE];
+    let msg = [0x5au8; 300];
+    let mut hmac = SgxHmacSha256::new(&key).unwrap();
+    for chunk in msg.chunks(7) {
+        hmac.update(chunk).unwrap();
+    }
+    assert_eq!(hmac.finalize().unwrap(), rsgx_hmac_sha256_slice(&key, &msg).unwrap());
+
+    // A key-committing MAC covers the fingerprint, so it differs from the plain MAC
+    // and only verifies under its own key.
+    let tag = rsgx_hmac_sha256_committed(&key, &msg).unwrap();
+    let fingerprint = rsgx_key_fingerprint_sha256(&key).unwrap();
+    let mut hmac = SgxHmacSha256::new(&key).unwrap();
+    hmac.update(&fingerprint).unwrap();
+    hmac.update(&msg).unwrap();
+    hmac.verify(&tag).unwrap();
+    assert_ne!(tag, rsgx_hmac_sha256_slice(&key, &msg).unwrap());
+    let mut hmac = SgxHmacSha256::new_committed(&[0x0cu8; SGX_HMAC256_KEY_SIZE]).unwrap();
+    hmac.update(&msg).unwrap();
+    assert_eq!(hmac.verify(&tag).unwrap_err(), sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+    assert_ne!(rsgx_key_fingerprint_sha384(&key).unwrap()[..32], fingerprint[..]);
+}
+
+pub fn test_rsgx_bignum() {
+    let to_u64 = |n: &SgxBigUint| {
+        let mut out = [0_u8; 8];
+        n.to_be_bytes(&mut out).unwrap();
+        u64::from_be_bytes(out)
+    };
+
+    // The textbook RSA key with n = 61 * 53.
+    let n = SgxBigUint::from_u64(3233);
+    let mont = SgxMontgomery::new(&n).unwrap();
+    let c = mont.mod_exp_vartime(&SgxBigUint::from_u64(65), &SgxBigUint::from_u64(17));
+    assert_eq!(to_u64(&c), 2790);
+    assert_eq!(to_u64(&mont.mod_exp(&c, &SgxBigUint::from_u64(2753))), 65);
+    assert_eq!(to_u64(&mont.mod_inv(&SgxBigUint::from_u64(17)).unwrap()), 2092);
+    assert_eq!(
+        mont.mod_inv(&SgxBigUint::from_u64(61)).unwrap_err(),
+        sgx_status_t::SGX_ERROR_INVALID_PARAMETER
+    );
+    assert!(SgxMontgomery::new(&SgxBigUint::from_u64(3120)).is_err());
+
+    // Paillier with the Mersenne primes 2^61 - 1 and 2^89 - 1 and g = n + 1.
+    let mut p = [0xff_u8; 8];
+    p[0] = 0x1f;
+    let mut q = [0xff_u8; 12];
+    q[0] = 0x01;
+    let p = SgxBigUint::from_be_bytes(&p);
+    let q = SgxBigUint::from_be_bytes(&q);
+    let one = SgxBigUint::from_u64(1);
+    let n = p.mul(&q);
+    let nn = SgxMontgomery::new(&n.mul(&n)).unwrap();
+    let phi = p.sub(&one).unwrap().mul(&q.sub(&one).unwrap());
+    let mu = SgxMontgomery::new(&n).unwrap().mod_inv(&phi).unwrap();
+
+    let m = SgxBigUint::from_be_bytes(b"paillier");
+    let r = SgxBigUint::from_u64(0x1234_5678_9abc_def1);
+    let c = nn.mul(&nn.mod_exp(&n.add(&one), &m), &nn.mod_exp(&r, &n));
+    let l = nn.sub(&nn.mod_exp(&c, &phi), &one).div_exact(&n).unwrap();
+    let decrypted = SgxMontgomery::new(&n).unwrap().mul(&l, &mu);
+    assert!(decrypted.ct_eq(&m));
+    assert_eq!(n.add(&one).div_exact(&n).unwrap_err(), sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+}
+
+pub fn test_rsgx_sm() {
+    let abc = "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0";
+    assert_eq!(rsgx_sm3_slice(b"abc").unwrap().to_vec(), hex_to_bytes(abc));
+
+    // GB/T 32907-2016, appendix A.1
+    let mut key = [0_u8; 16];
+    key.copy_from_slice(&hex_to_bytes("0123456789abcdeffedcba9876543210"));
+    let mut block = key;
+    let sm4 = SgxSm4::new(&key);
+    sm4.encrypt_block(&mut block);
+    assert_eq!(block.to_vec(), hex_to_bytes("681edf34d206965e86b3e94f536e4246"));
+    sm4.decrypt_block(&mut block);
+    assert_eq!(block, key);
+
+    // RFC 8998, appendix A.1
+    let mut iv = [0_u8; 12];
+    iv.copy_from_slice(&hex_to_bytes("00001234567800000000abcd"));
+    let aad = hex_to_bytes("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+    let plaintext = hex_to_bytes(concat!(
+        "aaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbccccccccccccccccdddddddddddddddd",
+        "eeeeeeeeeeeeeeeeffffffffffffffffeeeeeeeeeeeeeeeeaaaaaaaaaaaaaaaa"
+    ));
+    let mut gcm = SgxSm4Gcm::new_encrypt(&key, &iv);
+    gcm.update_aad(&aad).unwrap();
+    let mut ciphertext = vec![0_u8; plaintext.len()];
+    gcm.update(&plaintext[..7], &mut ciphertext[..7]).unwrap();
+    gcm.update(&plaintext[7..], &mut ciphertext[7..]).unwrap();
+    let tag = gcm.finalize().unwrap();
+    assert_eq!(
+        ciphertext,
+        hex_to_bytes(concat!(
+            "17f399f08c67d5ee19d0dc9969c4bb7d5fd46fd3756489069157b282bb200735",
+            "d82710ca5c22f0ccfa7cbf93d496ac15a56834cbcf98c397b4024a2691233b8d"
+        ))
+    );
+    assert_eq!(tag.to_vec(), hex_to_bytes("83de3541e4c2b58177e065a9bf7b62ec"));
+    let mut gcm = SgxSm4Gcm::new_decrypt(&key, &iv);
+    gcm.update_aad(&aad).unwrap();
+    let mut decrypted = vec![0_u8; ciphertext.len()];
+    gcm.update(&ciphertext, &mut decrypted).unwrap();
+    gcm.verify(&tag).unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    // A signature made by OpenSSL with the default ID.
+    let le = |hex: &str| {
+        let mut b = hex_to_bytes(hex);
+        b.reverse();
+        b
+    };
+    let words = |hex: &str| {
+        let b = le(hex);
+        let mut w = [0_u32; 8];
+        for (i, word) in b.chunks(4).enumerate() {
+            w[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        w
+    };
+    let mut public = sgx_ec256_public_t::default();
+    public
+        .gx
+        .copy_from_slice(&le("34c0ef209090e6009a25d309cbe557a072be88add7b9b8952310e3f567fe72cc"));
+    public
+        .gy
+        .copy_from_slice(&le("cfb4864cf93152681fefadfd73752954687180955a5d8062d2fcde57616fba41"));
+    let mut signature = sgx_ec256_signature_t::default();
+    signature.x = words("3478890bdf98886fd99f5a3632617799773e2a6c12ce4d797ceaf17036dd8d67");
+    signature.y = words("1287bc76f09a3fcde0ebaaac2f48516860327a8f142235ca500a96538fd326f5");
+    assert!(rsgx_sm2_verify_slice(SGX_SM2_DEFAULT_ID, b"message digest", &public, &signature).unwrap());
+    assert!(!rsgx_sm2_verify_slice(b"alice", b"message digest", &public, &signature).unwrap());
+
+    let (private, public) = rsgx_sm2_create_key_pair().unwrap();
+    let signature = rsgx_sm2_sign_slice(b"alice", b"message", &private).unwrap();
+    assert!(rsgx_sm2_verify_slice(b"alice", b"message", &public, &signature).unwrap());
+    assert!(!rsgx_sm2_verify_slice(b"alice", b"massage", &public, &signature).unwrap());
+
+    let (b_private, b_public) = rsgx_sm2_create_key_pair().unwrap();
+    let a = SgxSm2KeyExchange::new(SgxSm2Role::Initiator, b"alice", &private).unwrap();
+    let b = SgxSm2KeyExchange::new(SgxSm2Role::Responder, b"bob", &b_private).unwrap();
+    let (a_ephemeral, b_ephemeral) = (*a.ephemeral_public(), *b.ephemeral_public());
+    let mut a_key = [0_u8; 48];
+    let mut b_key = [0_u8; 48];
+    let b_confirm = b
+        .compute_shared_key(b"alice", &public, &a_ephemeral, &mut b_key)
+        .unwrap();
+    let a_confirm = a
+        .compute_shared_key(b"bob", &b_public, &b_ephemeral, &mut a_key)
+        .unwrap();
+    assert_eq!(a_key, b_key);
+    a_confirm.verify(b_confirm.tag()).unwrap();
+    b_confirm.verify(a_confirm.tag()).unwrap();
+    assert_eq!(
+        a_confirm.verify(a_confirm.tag()).unwrap_err(),
+        sgx_status_t::SGX_ERROR_MAC_MISMATCH
+    );
+}
+
+// Stands in for an MtA protocol run between two parties of the same enclave, which
+// can see both sets of shares.
+struct LocalMta {
+    peer: (SgxP256Scalar, SgxP256Scalar),
+    mask: Option<SgxP256Scalar>,
+}
+
+impl SgxP256Mta for LocalMta {
+    fn cross_terms(&mut self, a: &SgxP256Scalar, b: &SgxP256Scalar) -> SgxResult<SgxP256Scalar> {
+        let (peer_a, peer_b) = &self.peer;
+        match self.mask.take() {
+            Some(mask) => Ok(a.mul(peer_b).add(&peer_a.mul(b)).sub(&mask)),
+            None => Ok(SgxP256Scalar::zero()),
+        }
+    }
+}
+
+pub fn test_rsgx_threshold_ecdsa() {
+    let g = SgxP256Point::generator();
+    let g_sec1 = hex_to_bytes("036b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296");
+    assert_eq!(g.to_sec1(true).unwrap(), g_sec1);
+    assert!(SgxP256Point::from_sec1(&g_sec1).unwrap().ct_eq(&g));
+    let g_full = g.to_sec1(false).unwrap();
+    assert!(SgxP256Point::from_sec1(&g_full).unwrap().ct_eq(&g));
+    assert!(SgxP256Point::from_sec1(&g_full[..64]).is_err());
+    let two = SgxP256Scalar::from_u64(2);
+    assert!(g.double().ct_eq(&g.mul(&two)));
+    assert!(g.add(&g).sub(&g).ct_eq(&g));
+    assert!(g.add(&g.neg()).is_identity());
+    assert!(SgxP256Point::identity().to_sec1(true).is_err());
+
+    // A 2-of-3 Shamir sharing of the private key d with f(x) = d + c x.
+    let d = SgxP256Scalar::random().unwrap();
+    let c = SgxP256Scalar::random().unwrap();
+    let f = |x: u64| d.add(&c.mul(&SgxP256Scalar::from_u64(x)));
+    let public = SgxP256Point::mul_base(&d);
+
+    // Parties 1 and 3 sign. Their Lagrange weighted shares add up to d.
+    let signers = [1_u64, 3];
+    let x: Vec<SgxP256Scalar> = signers
+        .iter()
+        .map(|&i| f(i).mul(&SgxP256Scalar::lagrange_coefficient(i, &signers).unwrap()))
+        .collect();
+    assert!(x[0].add(&x[1]).ct_eq(&d));
+    assert!(SgxP256Scalar::lagrange_coefficient(2, &signers).is_err());
+
+    // Each party picks nonce shares k_i and gamma_i and computes its shares of
+    // delta = k gamma and sigma = k d with the other party.
+    let k: Vec<SgxP256Scalar> = (0..2).map(|_| SgxP256Scalar::random().unwrap()).collect();
+    let gamma: Vec<SgxP256Scalar> = (0..2).map(|_| SgxP256Scalar::random().unwrap()).collect();
+    let product = |a: &[SgxP256Scalar], b: &[SgxP256Scalar]| {
+        let mask = SgxP256Scalar::random().unwrap();
+        let mut first = [LocalMta {
+            peer: (a[1].clone(), b[1].clone()),
+            mask: Some(mask.clone()),
+        }];
+        let mut second = [LocalMta {
+            peer: (a[0].clone(), b[0].clone()),
+            mask: None,
+        }];
+        let share0 = SgxP256Scalar::share_product(&a[0], &b[0], &mut first).unwrap();
+        let share1 = SgxP256Scalar::share_product(&a[1], &b[1], &mut second).unwrap();
+        (share0, share1.add(&mask))
+    };
+    let (delta0, delta1) = product(&k, &gamma);
+    let (sigma0, sigma1) = product(&k, &x);
+    assert!(sigma0.add(&sigma1).ct_eq(&k[0].add(&k[1]).mul(&d)));
+
+    // R = gamma G / delta = k^-1 G, and s_i = m k_i + r sigma_i.
+    let delta_inv = delta0.add(&delta1).invert().unwrap();
+    let big_gamma = SgxP256Point::mul_base(&gamma[0]).add(&SgxP256Point::mul_base(&gamma[1]));
+    let r_point = big_gamma.mul(&delta_inv);
+    let mut r_bytes = [0_u8; 32];
+    r_bytes.copy_from_slice(&r_point.to_sec1(true).unwrap()[1..]);
+    let mut wide = [0_u8; 64];
+    wide[32..].copy_from_slice(&r_bytes);
+    let r = SgxP256Scalar::from_be_bytes_reduced(&wide);
+    let hash = rsgx_sha256_slice(b"threshold").unwrap();
+    wide[32..].copy_from_slice(&hash);
+    let m = SgxP256Scalar::from_be_bytes_reduced(&wide);
+    let s_shares = [m.mul(&k[0]).add(&r.mul(&sigma0)), m.mul(&k[1]).add(&r.mul(&sigma1))];
+
+    let signature = rsgx_ecdsa256_combine_shares(&r_point, &s_shares, &hash, &public).unwrap();
+    let ecc = SgxEccHandle::new();
+    ecc.open().unwrap();
+    assert!(ecc.ecdsa_verify_hash(&hash, &public.to_public().unwrap(), &signature).unwrap());
+
+    let bad_shares = [s_shares[0].add(&SgxP256Scalar::one()), s_shares[1].clone()];
+    assert!(matches!(
+        rsgx_ecdsa256_combine_shares(&r_point, &bad_shares, &hash, &public),
+        Err(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE)
+    ));
+}
+
+pub fn test_rsgx_crypto_self_test() {
+    rsgx_crypto_self_test().unwrap();
+    assert_eq!(rsgx_crypto_self_test_failure(), None);
+
+    let policy = SgxAlgorithmPolicy::approved();
+    assert!(policy.is_allowed(SgxAlgorithm::EcdsaP384));
+    assert!(!policy.is_allowed(SgxAlgorithm::ChaCha20Poly1305));
+    assert!(!policy.deny(SgxAlgorithm::RsaPkcs1v15).is_allowed(SgxAlgorithm::RsaPkcs1v15));
+
+    // Later tests still use everything else, so only SHA-1 is taken away.
+    rsgx_crypto_restrict_policy(SgxAlgorithmPolicy::allow_all().deny(SgxAlgorithm::Sha1));
+    assert!(!rsgx_crypto_policy().is_allowed(SgxAlgorithm::Sha1));
+    assert!(matches!(
+        rsgx_sha1_slice(b"abc"),
+        Err(sgx_status_t::SGX_ERROR_FEATURE_NOT_SUPPORTED)
+    ));
+    // Restricting cannot bring an algorithm back.
+    rsgx_crypto_restrict_policy(SgxAlgorithmPolicy::allow_all());
+    assert!(!rsgx_crypto_policy().is_allowed(SgxAlgorithm::Sha1));
+    assert!(rsgx_sha256_slice(b"abc").is_ok());
+    rsgx_crypto_self_test().unwrap();
+}
+
+pub fn test_rsgx_keygen() {
+    let mut private = sgx_ec384_private_t::default();
+    rsgx_generate_scalar(SgxCurve::P384, &mut private.r).unwrap();
+    assert!(rsgx_ecc384_pub_from_priv(&private).is_ok());
+
+    // The Ed25519 group order is just above 2^252.
+    let mut scalar = [0_u8; 32];
+    for _ in 0..64 {
+        rsgx_generate_scalar(SgxCurve::Ed25519, &mut scalar).unwrap();
+        assert!(scalar[31] <= 0x10);
+    }
+    assert!(rsgx_generate_scalar(SgxCurve::P256, &mut private.r).is_err());
+
+    let mut counts = [0_u32; 3];
+    for _ in 0..3000 {
+        counts[rsgx_random_below(3).unwrap() as usize] += 1;
+    }
+    assert!(counts.iter().all(|&c| c > 850));
+    assert_eq!(rsgx_random_below(1).unwrap(), 0);
+    assert!(rsgx_random_below(0).is_err());
+
+    let a = rsgx_generate_key::<sgx_aes_gcm_128bit_key_t>().unwrap();
+    let b = rsgx_generate_key::<sgx_aes_gcm_128bit_key_t>().unwrap();
+    assert!(!a.ct_eq(&b));
+    let mut key = [0_u8; 0];
+    assert!(rsgx_generate_symmetric_key(&mut key).is_err());
+}
+
+pub fn test_rsgx_ecvrf() {
+    // RFC 9380, appendix J.4.1: curve25519_XMD:SHA-512_ELL2_RO_ with the empty message
+    let dst = b"QUUX-V01-CS02-with-curve25519_XMD:SHA-512_ELL2_RO_";
+    assert_eq!(
+        rsgx_curve25519_hash_to_curve(b"", dst).unwrap().to_vec(),
+        le_bytes_from_hex("2de3780abb67e861289f5749d16d3e217ffa722192d16bbd9d1bfb9d112b98c0")
+    );
+    assert!(rsgx_p256_hash_to_curve(b"abc", b"").is_err());
+    let a = rsgx_p256_encode_to_curve(b"abc", b"TEST-DST").unwrap();
+    assert!(a.ct_eq(&rsgx_p256_encode_to_curve(b"abc", b"TEST-DST").unwrap()));
+    assert!(!a.ct_eq(&rsgx_p256_encode_to_curve(b"abd", b"TEST-DST").unwrap()));
+
+    // RFC 9381, appendix B.2, with alpha "test"
+    let mut private = sgx_ec256_private_t::default();
+    le_from_hex(
+        "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721",
+        &mut private.r,
+    );
+    let public = rsgx_ecc256_pub_from_priv(&private).unwrap();
+    let proof = rsgx_ecvrf_p256_prove(&private, b"test").unwrap();
+    let beta = rsgx_ecvrf_p256_verify(&public, b"test", &proof).unwrap();
+    assert_eq!(
+        beta.to_vec(),
+        hex_to_bytes("8e7185d2b420e4f4681f44ce313a26d05613323837da09a69f00491a83ad25dd")
+    );
+    assert_eq!(rsgx_ecvrf_p256_proof_to_hash(&proof).unwrap(), beta);
+    let mut forged = proof;
+    forged[60] ^= 1;
+    assert!(matches!(
+        rsgx_ecvrf_p256_verify(&public, b"test", &forged),
+        Err(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE)
+    ));
+    assert!(rsgx_ecvrf_p256_verify(&public, b"tesT", &proof).is_err());
+
+    let key = SgxEd25519PrivateKey::generate().unwrap();
+    let proof = rsgx_ecvrf_ed25519_prove(&key, b"round 1").unwrap();
+    let beta = rsgx_ecvrf_ed25519_verify(&key.public_key(), b"round 1", &proof).unwrap();
+    assert_eq!(
+        rsgx_ecvrf_ed25519_proof_to_hash(&proof).unwrap()[..],
+        beta[..]
+    );
+    assert!(rsgx_ecvrf_ed25519_verify(&key.public_key(), b"round 2", &proof).is_err());
+    let other = SgxEd25519PrivateKey::generate().unwrap();
+    assert!(rsgx_ecvrf_ed25519_verify(&other.public_key(), b"round 1", &proof).is_err());
+}
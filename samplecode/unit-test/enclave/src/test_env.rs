@@ -1,5 +1,9 @@
 use std::env::*;
+use std::ffi::{CStr, CString};
+use std::io::ErrorKind;
 use std::path::Path;
+use std::prelude::v1::*;
+use std::rt::t_env_snapshot_ecall;
 
 pub fn test_env_vars_os() {
     let p = vars_os();
@@ -24,3 +28,100 @@ pub fn test_env_home_dir() {
     let dir = home_dir();
     println!("{:?}", dir.unwrap());
 }
+
+fn host_var(key: &str) -> Option<Vec<u8>> {
+    let key = CString::new(key).unwrap();
+    let value = unsafe { sgx_libc::ocall::getenv(key.as_ptr()) };
+    if value.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(value) }.to_bytes().to_vec())
+    }
+}
+
+fn pass_env(snapshot: &[u8]) -> i32 {
+    t_env_snapshot_ecall(snapshot.as_ptr(), snapshot.len())
+}
+
+// The allowlist cannot be lifted once set, so this runs after every other test.
+pub fn test_env_allowlist() {
+    // Set on the host before the allowlist, so reads still go through the ocalls.
+    set_var("SGX_TEST_HOST", "host");
+    assert_eq!(var("SGX_TEST_HOST"), Ok("host".to_owned()));
+    assert_eq!(host_var("SGX_TEST_ALLOWED"), None);
+
+    assert_eq!(
+        pass_env(b"SGX_TEST_ALLOWED=first\0SGX_TEST_HIDDEN=hidden\0"),
+        0
+    );
+    assert_eq!(
+        set_allowlist(["SGX_TEST_ALLOWED", "BAD=KEY"])
+            .unwrap_err()
+            .kind(),
+        ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        set_allowlist([""]).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+    set_allowlist(["SGX_TEST_ALLOWED"]).unwrap();
+    assert_eq!(
+        set_allowlist(["SGX_TEST_HOST"]).unwrap_err().kind(),
+        ErrorKind::AlreadyExists
+    );
+
+    // Keys outside the allowlist are not present, whether they come from the host or the snapshot.
+    assert_eq!(var("SGX_TEST_ALLOWED"), Ok("first".to_owned()));
+    assert_eq!(var("SGX_TEST_HOST"), Err(VarError::NotPresent));
+    assert_eq!(var("SGX_TEST_HIDDEN"), Err(VarError::NotPresent));
+    assert_eq!(
+        vars().collect::<Vec<_>>(),
+        vec![("SGX_TEST_ALLOWED".to_owned(), "first".to_owned())]
+    );
+
+    // A bad snapshot is rejected as a whole and the previous one stays in place.
+    let mut too_large = Vec::new();
+    while too_large.len() <= 64 * 1024 {
+        too_large.extend_from_slice(b"SGX_TEST_ALLOWED=second\0");
+    }
+    let mut entry_too_large = b"SGX_TEST_ALLOWED=second\0SGX_TEST_ALLOWED=".to_vec();
+    entry_too_large.resize(entry_too_large.len() + 8 * 1024 - 16, b'x');
+    entry_too_large.push(0);
+    let not_utf8 = b"SGX_TEST_ALLOWED=second\0SGX_TEST_OTHER=\xff\0";
+    for snapshot in [&too_large[..], &entry_too_large[..], &not_utf8[..]] {
+        assert_eq!(pass_env(snapshot), sgx_libc::EINVAL);
+        assert_eq!(var("SGX_TEST_ALLOWED"), Ok("first".to_owned()));
+        assert_eq!(
+            vars().collect::<Vec<_>>(),
+            vec![("SGX_TEST_ALLOWED".to_owned(), "first".to_owned())]
+        );
+    }
+
+    // Both limits are inclusive.
+    let mut largest_entry = b"SGX_TEST_ALLOWED=".to_vec();
+    largest_entry.resize(8 * 1024, b'x');
+    let mut largest = largest_entry.clone();
+    largest.push(0);
+    while largest.len() + largest_entry.len() + 1 <= 64 * 1024 {
+        largest.extend_from_slice(&largest_entry);
+        largest.push(0);
+    }
+    largest.resize(64 * 1024, 0);
+    assert_eq!(pass_env(&largest), 0);
+    assert_eq!(var("SGX_TEST_ALLOWED").unwrap().len(), 8 * 1024 - 17);
+    assert_eq!(pass_env(b"SGX_TEST_ALLOWED=second\0"), 0);
+    assert_eq!(var("SGX_TEST_ALLOWED"), Ok("second".to_owned()));
+
+    // Changes stay inside the enclave.
+    set_var("SGX_TEST_ALLOWED", "enclave");
+    assert_eq!(var("SGX_TEST_ALLOWED"), Ok("enclave".to_owned()));
+    assert_eq!(host_var("SGX_TEST_ALLOWED"), None);
+    set_var("SGX_TEST_HOST", "enclave");
+    assert_eq!(var("SGX_TEST_HOST"), Err(VarError::NotPresent));
+    assert_eq!(host_var("SGX_TEST_HOST"), Some(b"host".to_vec()));
+    remove_var("SGX_TEST_HOST");
+    assert_eq!(host_var("SGX_TEST_HOST"), Some(b"host".to_vec()));
+    remove_var("SGX_TEST_ALLOWED");
+    assert_eq!(var("SGX_TEST_ALLOWED"), Err(VarError::NotPresent));
+    assert_eq!(host_var("SGX_TEST_ALLOWED"), None);
+}
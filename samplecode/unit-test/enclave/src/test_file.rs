@@ -16,11 +16,19 @@
 // under the License..
 
 use sgx_rand::{Rng, StdRng};
-use std::io::{Read, Write};
+use sgx_tcrypto::rsgx_sha256_slice;
+use std::io::{
+    ErrorKind, IoSlice, IoSliceMut, Mmap, MmapOptions, Read, Seek, SeekFrom, Write, MMAP_CHUNK_SIZE,
+};
+use std::io::{pipe, register_fd, PipeReader, PipeWriter};
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::{FromRawFd, IntoRawFd};
+use std::sealedfs;
 use std::sgxfs::{self, SgxFile};
 use std::string::*;
 use std::untrusted::fs::remove_file;
 use std::untrusted::fs::File;
+use std::vec::Vec;
 
 pub fn test_sgxfs() {
     let mut write_data: [u8; 16] = [0; 16];
@@ -86,6 +94,48 @@ pub fn test_sgxfs() {
     }
 }
 
+pub fn test_sealedfs() {
+    {
+        let result = sealedfs::write("sealed_file", b"Hello, world!");
+        assert!(result.is_ok());
+
+        let result = sealedfs::read_to_string("sealed_file");
+        assert_eq!(result.unwrap(), "Hello, world!");
+    }
+    {
+        let opt = sealedfs::File::create_new("sealed_file");
+        assert!(opt.is_err());
+
+        let opt = sealedfs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("sealed_file");
+        assert!(opt.is_ok());
+        let mut file = opt.unwrap();
+
+        let result = file.seek(SeekFrom::Start(7));
+        assert_eq!(result.unwrap(), 7);
+        let result = file.write_all(b"enclave");
+        assert!(result.is_ok());
+        assert!(file.sync_all().is_ok());
+    }
+    {
+        let result = sealedfs::read("sealed_file");
+        assert_eq!(result.unwrap(), b"Hello, enclave");
+
+        // Sealed files are not readable through the untrusted API.
+        let mut s = String::new();
+        let result = File::open("sealed_file").unwrap().read_to_string(&mut s);
+        assert!(result.is_err() || s != "Hello, enclave");
+    }
+
+    let result = sealedfs::remove_file("sealed_file");
+    assert!(result.is_ok());
+
+    let opt = sealedfs::File::open("sealed_file");
+    assert!(opt.is_err());
+}
+
 pub fn test_fs() {
     {
         let f = File::create("foo.txt");
@@ -128,3 +178,661 @@ pub fn test_fs_untrusted_fs_feature_enabled() {
         assert!(f.is_ok());
     }
 }
+
+pub fn test_mmap() {
+    use sgx_trts::enclave::rsgx_is_supported_EDMM;
+
+    let data: Vec<u8> = (0..MMAP_CHUNK_SIZE * 2 + 100).map(|i| i as u8).collect();
+    {
+        let mut file = File::create("mmap_file").unwrap();
+        file.write_all(&data).unwrap();
+    }
+    let file = File::open("mmap_file").unwrap();
+
+    // Mapping needs EDMM hardware; elsewhere only the refusal is checked.
+    if !rsgx_is_supported_EDMM() {
+        let err = unsafe { Mmap::map(&file) }.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        drop(file);
+        remove_file("mmap_file").unwrap();
+        return;
+    }
+
+    let hashes: Vec<_> = data
+        .chunks(MMAP_CHUNK_SIZE)
+        .map(|c| rsgx_sha256_slice(c).unwrap())
+        .collect();
+    {
+        let map = unsafe { MmapOptions::new().chunk_hashes(hashes.clone()).map(&file) }.unwrap();
+        assert_eq!(map.len(), data.len());
+        assert_eq!(&map[MMAP_CHUNK_SIZE..], &data[MMAP_CHUNK_SIZE..]);
+        assert_eq!(&map[..], &data[..]);
+    }
+
+    let wrong_count = vec![[0_u8; 32]; 1];
+    let err = unsafe { MmapOptions::new().chunk_hashes(wrong_count).map(&file) }.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    // Lazily mapped pages are loaded one range at a time, and a page that
+    // fails its hash is not loaded.
+    let mut wrong_hash = hashes;
+    wrong_hash[1] = [0_u8; 32];
+    {
+        let map = unsafe {
+            MmapOptions::new()
+                .chunk_hashes(wrong_hash)
+                .populate(false)
+                .map(&file)
+        }
+        .unwrap();
+        map.ensure_loaded(0..10).unwrap();
+        assert_eq!(&map[..10], &data[..10]);
+        map.ensure_loaded(MMAP_CHUNK_SIZE * 2..data.len()).unwrap();
+        assert_eq!(&map[MMAP_CHUNK_SIZE * 2..], &data[MMAP_CHUNK_SIZE * 2..]);
+        let err = map
+            .ensure_loaded(MMAP_CHUNK_SIZE..MMAP_CHUNK_SIZE + 1)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(map.ensure_loaded(0..data.len() + 1).is_err());
+    }
+
+    let map = unsafe { Mmap::map(&file) }.unwrap();
+    assert_eq!(&map[..], &data[..]);
+    drop(map);
+
+    drop(file);
+    remove_file("mmap_file").unwrap();
+}
+
+pub fn test_file_vectored_at() {
+    let file = File::create("vectored_file").unwrap();
+    let bufs = [IoSlice::new(b"Hello, "), IoSlice::new(b"world")];
+    assert_eq!(file.write_vectored_at(&bufs, 4).unwrap(), 12);
+    drop(file);
+
+    let file = File::open("vectored_file").unwrap();
+    let mut head = [0_u8; 7];
+    let mut tail = [0_u8; 8];
+    let mut bufs = [IoSliceMut::new(&mut head), IoSliceMut::new(&mut tail)];
+    assert_eq!(file.read_vectored_at(&mut bufs, 4).unwrap(), 12);
+    assert_eq!(&head, b"Hello, ");
+    assert_eq!(&tail[..5], b"world");
+    drop(file);
+
+    remove_file("vectored_file").unwrap();
+}
+
+pub fn test_pipe() {
+    let (mut reader, mut writer) = pipe().unwrap();
+    writer.write_all(b"hello pipe").unwrap();
+    drop(writer);
+    let mut s = String::new();
+    reader.read_to_string(&mut s).unwrap();
+    assert_eq!(s, "hello pipe");
+
+    let (reader, writer) = pipe().unwrap();
+    let fd = reader.into_raw_fd();
+    let err = PipeReader::from_received_fd(fd).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    register_fd(fd).unwrap();
+    assert_eq!(register_fd(fd).unwrap_err().kind(), ErrorKind::AlreadyExists);
+    let _reader = PipeReader::from_received_fd(fd).unwrap();
+    let err = PipeReader::from_received_fd(fd).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+
+    let fd = writer.into_raw_fd();
+    register_fd(fd).unwrap();
+    let err = PipeReader::from_received_fd(fd).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    drop(unsafe { PipeWriter::from_raw_fd(fd) });
+}
+
+pub fn test_io_error_kind() {
+    use std::io::Error;
+
+    let cases = [
+        (sgx_libc::ESPIPE, ErrorKind::NotSeekable),
+        (sgx_libc::ENOSPC, ErrorKind::StorageFull),
+        (sgx_libc::EDQUOT, ErrorKind::FilesystemQuotaExceeded),
+        (sgx_libc::EOPNOTSUPP, ErrorKind::Unsupported),
+        (sgx_libc::ENETRESET, ErrorKind::ConnectionReset),
+        (sgx_libc::EBADF, ErrorKind::InvalidInput),
+        (sgx_libc::EILSEQ, ErrorKind::InvalidData),
+        (sgx_libc::ESGX, ErrorKind::SgxError),
+    ];
+    for (errno, kind) in cases {
+        let err = Error::from_raw_os_error(errno);
+        assert_eq!(err.kind(), kind);
+        assert_eq!(err.raw_os_error(), Some(errno));
+    }
+}
+
+pub fn test_file_lock() {
+    let path = "lock_test.txt";
+    let a = File::create(path).unwrap();
+    let b = File::open(path).unwrap();
+    a.lock().unwrap();
+    assert!(!b.try_lock().unwrap());
+    assert!(!b.try_lock_shared().unwrap());
+    a.unlock().unwrap();
+    assert!(b.try_lock_shared().unwrap());
+    a.lock_shared().unwrap();
+    assert!(!a.try_lock().unwrap());
+    drop(a);
+    drop(b);
+    remove_file(path).unwrap();
+
+    let mut f = File::create_anonymous_in(".").unwrap();
+    f.write_all(b"anonymous").unwrap();
+    f.seek(SeekFrom::Start(0)).unwrap();
+    let mut s = String::new();
+    f.read_to_string(&mut s).unwrap();
+    assert_eq!(s, "anonymous");
+}
+
+pub fn test_memfs() {
+    use std::memfs::{self, MemFs};
+
+    let mut builder = MemFs::builder();
+    builder.add_file("conf/app.toml", b"level = 3").unwrap();
+    builder.add_file("/certs/ca.pem", b"PEM").unwrap();
+    builder.add_dir("empty").unwrap();
+    assert_eq!(builder.add_file("conf/app.toml", b"").unwrap_err().kind(), ErrorKind::AlreadyExists);
+    assert_eq!(builder.add_file("../x", b"").unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    let bundle = builder.to_bundle();
+    let digest = rsgx_sha256_slice(&bundle).unwrap();
+    let fs = MemFs::from_bundle_verified(&bundle, &digest).unwrap();
+    let mut bad = digest;
+    bad[0] ^= 1;
+    assert_eq!(MemFs::from_bundle_verified(&bundle, &bad).unwrap_err().kind(), ErrorKind::InvalidData);
+    assert_eq!(MemFs::from_bundle(&bundle[..bundle.len() - 1]).unwrap_err().kind(), ErrorKind::InvalidData);
+
+    assert_eq!(fs.read_to_string("/conf/app.toml").unwrap(), "level = 3");
+    assert!(fs.metadata("empty").unwrap().is_dir());
+    assert_eq!(fs.open("conf").unwrap_err().kind(), ErrorKind::IsADirectory);
+    assert_eq!(fs.open("conf/missing").unwrap_err().kind(), ErrorKind::NotFound);
+    let names: Vec<_> = fs.read_dir("/").unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(names, ["certs", "conf", "empty"]);
+
+    let mut file = fs.open("conf/app.toml").unwrap();
+    file.seek(SeekFrom::Start(8)).unwrap();
+    let mut rest = String::new();
+    file.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "3");
+
+    memfs::mount("/assets", fs).unwrap();
+    assert!(memfs::mount("/assets", MemFs::builder().build()).is_err());
+    assert_eq!(memfs::read("/assets/certs/ca.pem").unwrap(), b"PEM");
+    let entry = memfs::read_dir("/assets/conf").unwrap().next().unwrap().unwrap();
+    assert_eq!(entry.path().to_str(), Some("/assets/conf/app.toml"));
+    assert!(memfs::unmount("/assets").is_some());
+    assert_eq!(memfs::read("/assets/certs/ca.pem").unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+pub fn test_sgxfs_secure_tempdir() {
+    use std::untrusted::path::PathEx;
+
+    let dir = sgxfs::SecureTempDir::new_in(".").unwrap();
+    let path = dir.path().to_path_buf();
+    assert!(path.is_dir());
+
+    dir.create("scratch").unwrap().write_all(b"scratch data").unwrap();
+    let mut contents = String::new();
+    dir.open("scratch").unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "scratch data");
+
+    // The file is encrypted with the directory's own key.
+    assert!(SgxFile::open(path.join("scratch")).is_err());
+    assert_eq!(dir.open("../scratch").unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    drop(dir);
+    assert!(!path.exists());
+}
+
+pub fn test_sgxfs_integrity() {
+    use std::sgxfs::IntegrityFile;
+    use std::untrusted::fs::OpenOptions;
+
+    let data: Vec<u8> = (0..10000).map(|i| i as u8).collect();
+    {
+        let mut file = IntegrityFile::create("integrity_file").unwrap();
+        file.write_all(&data).unwrap();
+        file.seek(SeekFrom::Start(4090)).unwrap();
+        file.write_all(b"across").unwrap();
+        assert_eq!(file.len(), 10000);
+    }
+
+    // The contents stay in clear text on the host.
+    let mut expected = data.clone();
+    expected[4090..4096].copy_from_slice(b"across");
+    let mut plain = Vec::new();
+    File::open("integrity_file").unwrap().read_to_end(&mut plain).unwrap();
+    assert_eq!(plain, expected);
+
+    let mut contents = Vec::new();
+    let mut file = IntegrityFile::open("integrity_file").unwrap();
+    file.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, expected);
+    assert!(file.write(b"x").is_err());
+    drop(file);
+
+    // A modified node fails verification, the others still read.
+    {
+        let mut host = OpenOptions::new().write(true).open("integrity_file").unwrap();
+        host.seek(SeekFrom::Start(5000)).unwrap();
+        host.write_all(b"tampered").unwrap();
+    }
+    let mut file = IntegrityFile::open("integrity_file").unwrap();
+    let mut node = [0_u8; 16];
+    file.read_exact(&mut node).unwrap();
+    assert!(file.verify().is_err());
+    file.seek(SeekFrom::Start(5000)).unwrap();
+    assert!(file.read(&mut node).is_err());
+    drop(file);
+
+    IntegrityFile::remove("integrity_file").unwrap();
+    assert!(IntegrityFile::open("integrity_file").is_err());
+}
+
+pub fn test_sgxfs_rekey() {
+    let old_key = [1_u8; 16];
+    let new_key = [2_u8; 16];
+    let data: Vec<u8> = (0..100000).map(|i| i as u8).collect();
+    SgxFile::create_ex("rekey_file", &old_key)
+        .unwrap()
+        .write_all(&data)
+        .unwrap();
+
+    let mut rekey = sgxfs::SgxRekey::new("rekey_file", Some(&old_key), Some(&new_key)).unwrap();
+    assert_eq!(rekey.len(), 100000);
+    assert!(!rekey.step(4096).unwrap());
+    assert_eq!(rekey.copied(), 4096);
+    // The file keeps its old key until the re-encryption finishes.
+    drop(rekey);
+    assert!(SgxFile::open_ex("rekey_file", &old_key).is_ok());
+
+    let mut rekey = sgxfs::SgxRekey::new("rekey_file", Some(&old_key), Some(&new_key)).unwrap();
+    while !rekey.step(30000).unwrap() {}
+    rekey.finish().unwrap();
+
+    assert!(SgxFile::open_ex("rekey_file", &old_key).is_err());
+    let mut contents = Vec::new();
+    SgxFile::open_ex("rekey_file", &new_key)
+        .unwrap()
+        .read_to_end(&mut contents)
+        .unwrap();
+    assert_eq!(contents, data);
+
+    sgxfs::rekey("rekey_file", Some(&new_key), None).unwrap();
+    assert_eq!(sgxfs::read("rekey_file").unwrap(), data);
+    sgxfs::remove("rekey_file").unwrap();
+}
+
+pub fn test_sgxfs_shared_readers() {
+    let data: Vec<u8> = (0..20000).map(|i| (i % 251) as u8).collect();
+    sgxfs::write("shared_file", &data).unwrap();
+    {
+        let file = sgxfs::SgxSharedFile::open("shared_file").unwrap();
+        assert_eq!(file.len(), 20000);
+
+        let mut first = file.reader();
+        let mut second = file.reader();
+        second.seek(SeekFrom::Start(10000)).unwrap();
+        let mut a = [0_u8; 100];
+        let mut b = [0_u8; 100];
+        first.read_exact(&mut a).unwrap();
+        second.read_exact(&mut b).unwrap();
+        assert_eq!(&a[..], &data[..100]);
+        assert_eq!(&b[..], &data[10000..10100]);
+
+        let mut at = [0_u8; 8];
+        file.read_exact_at(&mut at, 19992).unwrap();
+        assert_eq!(&at[..], &data[19992..]);
+        assert!(file.read_exact_at(&mut at, 19996).is_err());
+
+        let mut rest = Vec::new();
+        first.read_to_end(&mut rest).unwrap();
+        assert_eq!(&rest[..], &data[100..]);
+
+        // Writers are locked out while the file is shared.
+        assert!(SgxFile::create("shared_file").is_err());
+    }
+    sgxfs::remove("shared_file").unwrap();
+}
+
+pub fn test_sgxfs_cached_file() {
+    use std::sgxfs::{SgxCachePolicy, SgxCachedFile};
+
+    let data: Vec<u8> = (0..20000).map(|i| i as u8).collect();
+    sgxfs::write("cached_file", &data).unwrap();
+    {
+        let file = sgxfs::OpenOptions::new()
+            .read(true)
+            .update(true)
+            .open("cached_file")
+            .unwrap();
+        let mut file = SgxCachedFile::new(file, 2, SgxCachePolicy::Lru);
+        let mut buf = [0_u8; 16];
+        for offset in [0, 4096, 0, 8192, 0] {
+            file.seek(SeekFrom::Start(offset)).unwrap();
+            file.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf[..], &data[offset as usize..offset as usize + 16]);
+        }
+        let stats = file.stats();
+        assert_eq!((stats.hits(), stats.misses(), stats.evictions()), (2, 3, 1));
+
+        file.seek(SeekFrom::Start(10)).unwrap();
+        file.write_all(b"cached").unwrap();
+        file.seek(SeekFrom::Start(10)).unwrap();
+        file.read_exact(&mut buf[..6]).unwrap();
+        assert_eq!(&buf[..6], b"cached");
+        assert_eq!(file.seek(SeekFrom::End(0)).unwrap(), 20000);
+    }
+    sgxfs::remove("cached_file").unwrap();
+}
+
+pub fn test_sgxfs_set_len() {
+    use std::sgxfs::IntegrityFile;
+
+    let data: Vec<u8> = (0..10000).map(|i| i as u8).collect();
+    sgxfs::write("set_len_file", &data).unwrap();
+    {
+        let mut file = sgxfs::OpenOptions::new()
+            .read(true)
+            .update(true)
+            .open("set_len_file")
+            .unwrap();
+        file.seek(SeekFrom::Start(100)).unwrap();
+        file.set_len(12000).unwrap();
+        assert_eq!(file.seek(SeekFrom::Current(0)).unwrap(), 100);
+        assert_eq!(file.set_len(5000).unwrap_err().kind(), ErrorKind::Unsupported);
+    }
+    let contents = sgxfs::read("set_len_file").unwrap();
+    assert_eq!(&contents[..10000], &data[..]);
+    assert!(contents[10000..].iter().all(|b| *b == 0));
+
+    sgxfs::set_len("set_len_file", None, 5000).unwrap();
+    assert_eq!(sgxfs::read("set_len_file").unwrap(), &data[..5000]);
+    sgxfs::remove("set_len_file").unwrap();
+
+    let mut file = IntegrityFile::create("set_len_integrity").unwrap();
+    file.write_all(&data).unwrap();
+    file.set_len(5000).unwrap();
+    file.set_len(6000).unwrap();
+    drop(file);
+    let mut contents = Vec::new();
+    IntegrityFile::open("set_len_integrity")
+        .unwrap()
+        .read_to_end(&mut contents)
+        .unwrap();
+    assert_eq!(&contents[..5000], &data[..5000]);
+    assert_eq!(&contents[5000..], &[0_u8; 1000][..]);
+    IntegrityFile::remove("set_len_integrity").unwrap();
+}
+
+pub fn test_sgxfs_transaction() {
+    use std::sgxfs::{SgxRecovery, SgxTxFile};
+
+    {
+        let mut file = SgxTxFile::create("tx_file", None).unwrap();
+        let mut tx = file.begin_transaction();
+        tx.write_all(b"Hello, world!").unwrap();
+        tx.seek(SeekFrom::Start(20)).unwrap();
+        tx.write_all(b"end").unwrap();
+        assert_eq!(tx.writes(), 2);
+        tx.commit().unwrap();
+
+        let mut tx = file.begin_transaction();
+        tx.write_all(b"discarded").unwrap();
+        tx.rollback();
+        assert_eq!(file.len().unwrap(), 23);
+    }
+    let contents = sgxfs::read("tx_file").unwrap();
+    assert_eq!(&contents[..13], b"Hello, world!");
+    assert_eq!(&contents[13..20], &[0_u8; 7][..]);
+    assert_eq!(&contents[20..], b"end");
+
+    // A journal without its commit record is discarded.
+    {
+        let mut journal = SgxFile::create("tx_file.journal").unwrap();
+        journal.write_all(b"SGXJRNL1").unwrap();
+        journal.write_all(&0_u64.to_le_bytes()).unwrap();
+        journal.write_all(&5_u64.to_le_bytes()).unwrap();
+        journal.write_all(b"lost!").unwrap();
+    }
+    assert_eq!(
+        sgxfs::recover("tx_file", None).unwrap(),
+        SgxRecovery::RolledBack { writes: 1, bytes: 5 }
+    );
+    assert_eq!(sgxfs::recover("tx_file", None).unwrap(), SgxRecovery::Clean);
+    assert_eq!(&sgxfs::read("tx_file").unwrap()[..5], b"Hello");
+
+    // A committed journal is applied again.
+    {
+        let mut journal = SgxFile::create("tx_file.journal").unwrap();
+        journal.write_all(b"SGXJRNL1").unwrap();
+        journal.write_all(&7_u64.to_le_bytes()).unwrap();
+        journal.write_all(&7_u64.to_le_bytes()).unwrap();
+        journal.write_all(b"enclave").unwrap();
+        journal.write_all(&u64::MAX.to_le_bytes()).unwrap();
+        journal.write_all(&1_u64.to_le_bytes()).unwrap();
+    }
+    let file = SgxTxFile::open("tx_file", None).unwrap();
+    assert_eq!(
+        file.recovery(),
+        SgxRecovery::RolledForward { writes: 1, bytes: 7 }
+    );
+    drop(file);
+    assert_eq!(&sgxfs::read("tx_file").unwrap()[..14], b"Hello, enclave");
+    sgxfs::remove("tx_file").unwrap();
+}
+
+pub fn test_sgxfs_dir() {
+    use std::sgxfs::SgxDir;
+    use std::untrusted::fs::{read_dir, remove_dir_all};
+
+    let _ = remove_dir_all("protected_dir");
+    {
+        let mut dir = SgxDir::create("protected_dir", None).unwrap();
+        dir.create_file("a.txt").unwrap().write_all(b"first").unwrap();
+        dir.create_file("b.txt").unwrap().write_all(b"second").unwrap();
+        assert_eq!(
+            dir.create_file("a.txt").unwrap_err().kind(),
+            ErrorKind::AlreadyExists
+        );
+        dir.rename("a.txt", "c/d.txt").unwrap();
+        assert_eq!(dir.open_file("a.txt").unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(dir.file_len("c/d.txt").unwrap(), 5);
+    }
+    {
+        let mut dir = SgxDir::open("protected_dir", None).unwrap();
+        assert_eq!(dir.names(), ["b.txt", "c/d.txt"]);
+        let mut contents = String::new();
+        dir.open_file("c/d.txt")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "first");
+
+        dir.rename("c/d.txt", "b.txt").unwrap();
+        dir.remove("b.txt").unwrap();
+        assert!(dir.names().is_empty());
+    }
+
+    // The host only sees the index.
+    let names: Vec<_> = read_dir("protected_dir")
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(names, [".sgxdir"]);
+    remove_dir_all("protected_dir").unwrap();
+}
+
+pub fn test_sgxfs_key_id() {
+    use sgx_tcrypto::rsgx_rijndael128GCM_encrypt;
+    use std::sgxfs::SgxKeyId;
+
+    let key_id: SgxKeyId = [7; 16];
+    let file_key = [0x42_u8; 16];
+    let session_key = [0x24_u8; 16];
+
+    // Wrap the file key the way a provisioning service would.
+    let iv = [1_u8; 12];
+    let mut wrapped = vec![0_u8; sgxfs::SGX_WRAPPED_KEY_LEN];
+    let mut mac = [0_u8; 16];
+    rsgx_rijndael128GCM_encrypt(
+        &session_key,
+        &file_key,
+        &iv,
+        &key_id,
+        &mut wrapped[12..28],
+        &mut mac,
+    )
+    .unwrap();
+    wrapped[..12].copy_from_slice(&iv);
+    wrapped[28..].copy_from_slice(&mac);
+    assert!(sgxfs::provision_wrapped_key(&[8; 16], &wrapped, &session_key).is_err());
+    sgxfs::provision_wrapped_key(&key_id, &wrapped, &session_key).unwrap();
+    assert!(sgxfs::has_key(&key_id));
+
+    {
+        let mut file = SgxFile::create_with_key_id("sgx_file_key_id", &key_id).unwrap();
+        file.write_all(b"shared").unwrap();
+    }
+    assert_eq!(sgxfs::key_id("sgx_file_key_id").unwrap(), key_id);
+    {
+        // The file is encrypted with the provisioned key itself.
+        let mut contents = String::new();
+        SgxFile::open_ex("sgx_file_key_id", &file_key)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "shared");
+    }
+    {
+        let mut contents = String::new();
+        SgxFile::open_with_key_id("sgx_file_key_id")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "shared");
+    }
+
+    sgxfs::provision_key(&[9; 16], &[0x11; 16]);
+    assert_eq!(
+        sgxfs::OpenOptions::new()
+            .read(true)
+            .open_with_key_id("sgx_file_key_id", &[9; 16])
+            .unwrap_err()
+            .kind(),
+        ErrorKind::InvalidData
+    );
+    assert!(sgxfs::revoke_key(&[9; 16]));
+    assert!(sgxfs::revoke_key(&key_id));
+    assert_eq!(
+        SgxFile::open_with_key_id("sgx_file_key_id")
+            .unwrap_err()
+            .kind(),
+        ErrorKind::PermissionDenied
+    );
+
+    sgxfs::remove("sgx_file_key_id").unwrap();
+    remove_file(sgxfs::key_id_path("sgx_file_key_id")).unwrap();
+}
+
+pub fn test_sgxfs_convert() {
+    use std::sgxfs::SGX_CONVERT_CHUNK_SIZE;
+
+    let data: Vec<u8> = (0..SGX_CONVERT_CHUNK_SIZE * 2 + 100)
+        .map(|i| i as u8)
+        .collect();
+    std::untrusted::fs::write("plain_import", &data).unwrap();
+
+    let mut calls = 0;
+    let n = sgxfs::import_file("plain_import", "sgx_convert_a", None, |p| {
+        calls += 1;
+        assert_eq!(p.total, Some(data.len() as u64));
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(n, data.len() as u64);
+    assert_eq!(calls, 3);
+    assert_eq!(sgxfs::read("sgx_convert_a").unwrap(), data);
+
+    // Between differently keyed files.
+    let key = [0x5a_u8; 16];
+    sgxfs::convert("sgx_convert_a", None, "sgx_convert_b", Some(&key), |_| {
+        Ok(())
+    })
+    .unwrap();
+    let mut exported = Vec::new();
+    sgxfs::export("sgx_convert_b", Some(&key), &mut exported, |_| Ok(())).unwrap();
+    assert_eq!(exported, data);
+
+    // A cancelled import leaves no file behind.
+    let err = sgxfs::import(&data[..], "sgx_convert_c", None, |p| {
+        if p.copied >= SGX_CONVERT_CHUNK_SIZE as u64 {
+            Err(ErrorKind::Other.into())
+        } else {
+            Ok(())
+        }
+    })
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    assert!(SgxFile::open("sgx_convert_c").is_err());
+
+    sgxfs::remove("sgx_convert_a").unwrap();
+    sgxfs::remove("sgx_convert_b").unwrap();
+    remove_file("plain_import").unwrap();
+}
+
+pub fn test_sgxfs_positional() {
+    use std::io::BufRead;
+
+    {
+        let mut file = sgxfs::OpenOptions::new()
+            .write(true)
+            .update(true)
+            .open("sgx_positional")
+            .unwrap();
+        for i in 0..1000 {
+            writeln!(file, "row {}", i).unwrap();
+        }
+        // Positional writes leave the cursor where it was.
+        file.write_all_at(b"ROW", 0).unwrap();
+        assert_eq!(
+            file.stream_position().unwrap(),
+            file.seek(SeekFrom::End(0)).unwrap()
+        );
+
+        let mut at = [0_u8; 5];
+        file.seek(SeekFrom::Start(4)).unwrap();
+        file.read_exact_at(&mut at, 0).unwrap();
+        assert_eq!(&at, b"ROW 0");
+        assert_eq!(file.stream_position().unwrap(), 4);
+    }
+    {
+        let file = sgxfs::SgxSharedFile::open("sgx_positional").unwrap();
+        let mut reader = file.reader();
+        let lines: Vec<String> = (&mut reader).lines().map(|l| l.unwrap()).collect();
+        assert_eq!(lines.len(), 1000);
+        assert_eq!(lines[0], "ROW 0");
+        assert_eq!(lines[999], "row 999");
+
+        // Seeking back within the buffered node and past it.
+        reader.seek(SeekFrom::End(-8)).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "row 999\n");
+        reader.seek(SeekFrom::Current(-4)).unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "999\n");
+        reader.seek(SeekFrom::Start(6)).unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "row 1\n");
+    }
+    sgxfs::remove("sgx_positional").unwrap();
+}
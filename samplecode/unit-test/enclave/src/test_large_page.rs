@@ -0,0 +1,167 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use std::alloc::{self, LargePageConfig, Layout};
+use std::io::ErrorKind;
+use std::prelude::v1::*;
+
+use sgx_trts::enclave::rsgx_is_supported_EDMM;
+
+const LARGE_PAGE_SIZE: usize = 0x20_0000;
+const MAX_REGIONS: usize = 256;
+
+// Regions are 2 MiB aligned, heap blocks follow a malloc header.
+fn from_region(ptr: *mut u8) -> bool {
+    ptr as usize % LARGE_PAGE_SIZE == 0
+}
+
+pub fn test_large_page_config() {
+    let default = alloc::large_page_config();
+    assert_eq!(LargePageConfig::default(), LargePageConfig::DEFAULT);
+
+    let invalid = [
+        LargePageConfig {
+            threshold: 0,
+            ..LargePageConfig::DEFAULT
+        },
+        LargePageConfig {
+            arena_size: 0,
+            ..LargePageConfig::DEFAULT
+        },
+        LargePageConfig {
+            arena_size: LARGE_PAGE_SIZE + 0x1000,
+            ..LargePageConfig::DEFAULT
+        },
+        LargePageConfig {
+            arena_size: LARGE_PAGE_SIZE / 2,
+            ..LargePageConfig::DEFAULT
+        },
+        LargePageConfig {
+            fault_ahead: 0,
+            ..LargePageConfig::DEFAULT
+        },
+        LargePageConfig {
+            fault_ahead: 0x1800,
+            ..LargePageConfig::DEFAULT
+        },
+    ];
+    for config in invalid.iter() {
+        let err = alloc::set_large_page_config(*config).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(alloc::large_page_config(), default);
+    }
+
+    let config = LargePageConfig {
+        threshold: 0x10_0000,
+        arena_size: 2 * LARGE_PAGE_SIZE,
+        fault_ahead: 0x1000,
+        decommit_threshold: 0,
+    };
+    alloc::set_large_page_config(config).unwrap();
+    assert_eq!(alloc::large_page_config(), config);
+    alloc::set_large_page_config(default).unwrap();
+}
+
+pub fn test_large_page_alloc() {
+    let layout = Layout::from_size_align(LARGE_PAGE_SIZE + 0x1000, 8).unwrap();
+
+    // Without EDMM every allocation comes from the heap.
+    if !rsgx_is_supported_EDMM() {
+        let ptr = unsafe { alloc::alloc(layout) };
+        assert!(!ptr.is_null());
+        assert!(!from_region(ptr));
+        unsafe { alloc::dealloc(ptr, layout) };
+        return;
+    }
+
+    // Keep every freed region, however many earlier tests left cached.
+    let config = LargePageConfig {
+        decommit_threshold: usize::MAX,
+        ..LargePageConfig::DEFAULT
+    };
+    alloc::set_large_page_config(config).unwrap();
+    unsafe {
+        let small = Layout::from_size_align(LARGE_PAGE_SIZE - 1, 8).unwrap();
+        let ptr = alloc::alloc(small);
+        assert!(!ptr.is_null());
+        assert!(!from_region(ptr));
+        alloc::dealloc(ptr, small);
+
+        // Pages are committed as they are touched.
+        let ptr = alloc::alloc(layout);
+        assert!(!ptr.is_null());
+        assert!(from_region(ptr));
+        *ptr = 1;
+        *ptr.add(layout.size() - 1) = 2;
+        assert_eq!((*ptr, *ptr.add(layout.size() - 1)), (1, 2));
+        alloc::dealloc(ptr, layout);
+
+        // The freed region is cached and handed out again, wiped if asked to.
+        let zeroed = alloc::alloc_zeroed(layout);
+        assert_eq!(zeroed, ptr);
+        assert_eq!((*zeroed, *zeroed.add(layout.size() - 1)), (0, 0));
+        alloc::dealloc(zeroed, layout);
+    }
+    alloc::set_large_page_config(LargePageConfig::DEFAULT).unwrap();
+}
+
+pub fn test_large_page_table_full() {
+    if !rsgx_is_supported_EDMM() {
+        return;
+    }
+
+    // Release freed regions right away, so that they give their slot back.
+    let config = LargePageConfig {
+        decommit_threshold: 0,
+        ..LargePageConfig::DEFAULT
+    };
+    alloc::set_large_page_config(config).unwrap();
+    let layout = Layout::from_size_align(LARGE_PAGE_SIZE, 8).unwrap();
+
+    // Regions cached by earlier tests keep their slots, so the table may
+    // fill up before all of its entries are used here.
+    let mut regions = Vec::with_capacity(MAX_REGIONS);
+    let fallback = loop {
+        let ptr = unsafe { alloc::alloc(layout) };
+        assert!(!ptr.is_null());
+        if !from_region(ptr) {
+            break ptr;
+        }
+        regions.push(ptr);
+        assert!(regions.len() <= MAX_REGIONS);
+    };
+    assert!(!regions.is_empty());
+    unsafe {
+        *fallback = 1;
+        *fallback.add(layout.size() - 1) = 2;
+        assert_eq!((*fallback, *fallback.add(layout.size() - 1)), (1, 2));
+    }
+
+    // Once a slot is free again, large allocations get a region again.
+    unsafe { alloc::dealloc(regions.pop().unwrap(), layout) };
+    let ptr = unsafe { alloc::alloc(layout) };
+    assert!(from_region(ptr));
+    regions.push(ptr);
+
+    unsafe {
+        alloc::dealloc(fallback, layout);
+        for ptr in regions {
+            alloc::dealloc(ptr, layout);
+        }
+    }
+    alloc::set_large_page_config(LargePageConfig::DEFAULT).unwrap();
+}
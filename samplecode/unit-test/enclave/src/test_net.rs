@@ -0,0 +1,355 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+use std::io::{self, ErrorKind, Read, Write};
+use std::mem;
+use std::net::{
+    has_connect_policy, set_connect_policy, ConnectDecision, ConnectPolicy, EgressPolicy, IpAddr,
+    Ipv4Addr, Ipv6Addr, Protocol, SocketAddr, TcpListener, TcpStream, UdpSocket,
+};
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+use std::thread;
+use std::time::Duration;
+use std::untrusted::fs::{self, File};
+use std::vec::Vec;
+
+fn v4(a: u8, b: u8, c: u8, d: u8) -> IpAddr {
+    IpAddr::V4(Ipv4Addr::new(a, b, c, d))
+}
+
+fn v6(segments: [u16; 8]) -> IpAddr {
+    let [a, b, c, d, e, f, g, h] = segments;
+    IpAddr::V6(Ipv6Addr::new(a, b, c, d, e, f, g, h))
+}
+
+fn decide(policy: &EgressPolicy, ip: IpAddr, port: u16) -> ConnectDecision {
+    policy.check(Protocol::Tcp, &SocketAddr::new(ip, port))
+}
+
+pub fn test_net_egress_policy_cidr() {
+    let any_v4 = EgressPolicy::deny_by_default().allow(v4(0, 0, 0, 0), 0, 0..=65535);
+    assert_eq!(decide(&any_v4, v4(1, 2, 3, 4), 1), ConnectDecision::Allow);
+    assert_eq!(
+        decide(&any_v4, v4(255, 255, 255, 255), 65535),
+        ConnectDecision::Allow
+    );
+    assert_eq!(
+        decide(&any_v4, v6([0, 0, 0, 0, 0, 0, 0, 1]), 1),
+        ConnectDecision::Deny
+    );
+
+    let host_v4 = EgressPolicy::deny_by_default().allow(v4(10, 1, 2, 3), 32, 0..=65535);
+    assert_eq!(
+        decide(&host_v4, v4(10, 1, 2, 3), 80),
+        ConnectDecision::Allow
+    );
+    assert_eq!(decide(&host_v4, v4(10, 1, 2, 4), 80), ConnectDecision::Deny);
+
+    let net_v4 = EgressPolicy::deny_by_default().allow(v4(10, 0, 0, 0), 8, 0..=65535);
+    assert_eq!(
+        decide(&net_v4, v4(10, 255, 0, 1), 80),
+        ConnectDecision::Allow
+    );
+    assert_eq!(decide(&net_v4, v4(11, 0, 0, 0), 80), ConnectDecision::Deny);
+
+    let any_v6 = EgressPolicy::deny_by_default().allow(v6([0; 8]), 0, 0..=65535);
+    assert_eq!(
+        decide(&any_v6, v6([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1]), 1),
+        ConnectDecision::Allow
+    );
+    assert_eq!(decide(&any_v6, v4(1, 2, 3, 4), 1), ConnectDecision::Deny);
+
+    let host = [0x2001, 0xdb8, 0, 0, 0, 0, 0, 1];
+    let host_v6 = EgressPolicy::deny_by_default().allow(v6(host), 128, 0..=65535);
+    assert_eq!(decide(&host_v6, v6(host), 443), ConnectDecision::Allow);
+    assert_eq!(
+        decide(&host_v6, v6([0x2001, 0xdb8, 0, 0, 0, 0, 0, 2]), 443),
+        ConnectDecision::Deny
+    );
+
+    let net_v6 =
+        EgressPolicy::deny_by_default().allow(v6([0x2001, 0xdb8, 0, 0, 0, 0, 0, 0]), 32, 0..=65535);
+    assert_eq!(
+        decide(&net_v6, v6([0x2001, 0xdb8, 0xffff, 0, 0, 0, 0, 1]), 1),
+        ConnectDecision::Allow
+    );
+    assert_eq!(
+        decide(&net_v6, v6([0x2001, 0xdb9, 0, 0, 0, 0, 0, 1]), 1),
+        ConnectDecision::Deny
+    );
+
+    should_panic!(EgressPolicy::deny_by_default().allow(v4(10, 0, 0, 0), 33, 0..=65535));
+    should_panic!(EgressPolicy::deny_by_default().allow(v6([0; 8]), 129, 0..=65535));
+}
+
+pub fn test_net_egress_policy_ports() {
+    let policy = EgressPolicy::deny_by_default()
+        .allow(v4(0, 0, 0, 0), 0, 1000..=2000)
+        .allow(v4(0, 0, 0, 0), 0, 443..=443);
+    let ip = v4(192, 0, 2, 1);
+    assert_eq!(decide(&policy, ip, 999), ConnectDecision::Deny);
+    assert_eq!(decide(&policy, ip, 1000), ConnectDecision::Allow);
+    assert_eq!(decide(&policy, ip, 2000), ConnectDecision::Allow);
+    assert_eq!(decide(&policy, ip, 2001), ConnectDecision::Deny);
+    assert_eq!(decide(&policy, ip, 443), ConnectDecision::Allow);
+    assert_eq!(decide(&policy, ip, 444), ConnectDecision::Deny);
+}
+
+pub fn test_net_egress_policy_mapped() {
+    // ::ffff:10.0.0.1 is an IPv6 destination, which IPv4 rules never match.
+    let mapped = IpAddr::V6(Ipv4Addr::new(10, 0, 0, 1).to_ipv6_mapped());
+    let v4_only = EgressPolicy::deny_by_default().allow(v4(10, 0, 0, 0), 8, 0..=65535);
+    assert_eq!(decide(&v4_only, mapped, 80), ConnectDecision::Deny);
+    assert_eq!(
+        decide(&v4_only, v4(10, 0, 0, 1), 80),
+        ConnectDecision::Allow
+    );
+
+    let with_mapped = v4_only.allow(v6([0, 0, 0, 0, 0, 0xffff, 0x0a00, 0]), 104, 0..=65535);
+    assert_eq!(decide(&with_mapped, mapped, 80), ConnectDecision::Allow);
+    let other = IpAddr::V6(Ipv4Addr::new(11, 0, 0, 1).to_ipv6_mapped());
+    assert_eq!(decide(&with_mapped, other, 80), ConnectDecision::Deny);
+}
+
+pub fn test_net_egress_policy_redirect() {
+    let proxy = SocketAddr::from(([127, 0, 0, 1], 3128));
+    let policy = EgressPolicy::allow_by_default()
+        .deny(v4(10, 0, 0, 1), 32, 80..=80)
+        .redirect(v4(10, 0, 0, 0), 8, 80..=80, proxy);
+    // The first matching rule decides.
+    assert_eq!(decide(&policy, v4(10, 0, 0, 1), 80), ConnectDecision::Deny);
+    assert_eq!(
+        decide(&policy, v4(10, 0, 0, 2), 80),
+        ConnectDecision::Redirect(proxy)
+    );
+    assert_eq!(decide(&policy, v4(10, 0, 0, 2), 81), ConnectDecision::Allow);
+    assert_eq!(decide(&policy, v4(11, 0, 0, 2), 80), ConnectDecision::Allow);
+}
+
+// The policy stays installed for the rest of the run, so it only touches the
+// documentation ranges of RFC 5737.
+pub fn test_net_connect_policy() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let local = listener.local_addr().unwrap();
+    let redirected = SocketAddr::from(([198, 51, 100, 1], 80));
+    let denied = SocketAddr::from(([192, 0, 2, 1], 80));
+    let policy = EgressPolicy::allow_by_default()
+        .deny(v4(192, 0, 2, 0), 24, 0..=65535)
+        .redirect(v4(198, 51, 100, 1), 32, 80..=80, local);
+    set_connect_policy(policy).unwrap();
+    assert!(has_connect_policy());
+
+    let err = set_connect_policy(EgressPolicy::allow_by_default()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+    let stream = TcpStream::connect(redirected).unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), local);
+    let (_accepted, peer) = listener.accept().unwrap();
+    assert_eq!(peer, stream.local_addr().unwrap());
+
+    let err = TcpStream::connect(denied).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    let err = TcpStream::connect_timeout(&denied, Duration::from_secs(1)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let err = socket.send_to(b"x", denied).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    let err = socket.connect(denied).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+}
+
+// A socket to file copy goes through the untrusted bounce buffer, here in
+// many chunks.
+pub fn test_net_copy_through_bounce_buffer() {
+    const LEN: usize = 256 * 1024;
+    let data: Vec<u8> = (0..LEN).map(|i| (i % 251) as u8).collect();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let sent = data.clone();
+    let sender = thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(&sent).unwrap();
+    });
+    let (mut stream, _) = listener.accept().unwrap();
+
+    let size = io::copy_buffer_size();
+    io::set_copy_buffer_size(4096);
+    let mut file = File::create("bounce_copy.bin").unwrap();
+    let copied = io::copy(&mut stream, &mut file);
+    io::set_copy_buffer_size(size);
+    assert_eq!(copied.unwrap(), LEN as u64);
+    sender.join().unwrap();
+
+    let mut copy = Vec::new();
+    File::open("bounce_copy.bin")
+        .unwrap()
+        .read_to_end(&mut copy)
+        .unwrap();
+    assert!(copy == data);
+    fs::remove_file("bounce_copy.bin").unwrap();
+}
+
+pub fn test_net_tcp_stream_options() {
+    let stream = TcpStream::new_v4().unwrap();
+
+    assert_eq!(stream.keepalive().unwrap(), None);
+    stream.set_keepalive(Some(Duration::from_secs(60))).unwrap();
+    assert_eq!(stream.keepalive().unwrap(), Some(Duration::from_secs(60)));
+    stream
+        .set_keepalive(Some(Duration::from_millis(500)))
+        .unwrap();
+    assert_eq!(stream.keepalive().unwrap(), Some(Duration::from_secs(1)));
+    stream.set_keepalive(None).unwrap();
+    assert_eq!(stream.keepalive().unwrap(), None);
+    let err = stream.set_keepalive(Some(Duration::ZERO)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = stream
+        .set_keepalive(Some(Duration::from_secs(32768)))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    stream
+        .set_keepalive_interval(Duration::from_secs(10))
+        .unwrap();
+    assert_eq!(
+        stream.keepalive_interval().unwrap(),
+        Duration::from_secs(10)
+    );
+    let err = stream.set_keepalive_interval(Duration::ZERO).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    stream.set_keepalive_retries(3).unwrap();
+    assert_eq!(stream.keepalive_retries().unwrap(), 3);
+    let err = stream.set_keepalive_retries(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = stream.set_keepalive_retries(128).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    assert_eq!(stream.user_timeout().unwrap(), None);
+    stream
+        .set_user_timeout(Some(Duration::from_millis(1500)))
+        .unwrap();
+    assert_eq!(
+        stream.user_timeout().unwrap(),
+        Some(Duration::from_millis(1500))
+    );
+    stream
+        .set_user_timeout(Some(Duration::from_micros(1)))
+        .unwrap();
+    assert_eq!(
+        stream.user_timeout().unwrap(),
+        Some(Duration::from_millis(1))
+    );
+    stream.set_user_timeout(None).unwrap();
+    assert_eq!(stream.user_timeout().unwrap(), None);
+    let err = stream.set_user_timeout(Some(Duration::ZERO)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    stream.set_recv_buffer_size(32 * 1024).unwrap();
+    assert_eq!(stream.recv_buffer_size().unwrap(), 64 * 1024);
+    stream.set_send_buffer_size(32 * 1024).unwrap();
+    assert_eq!(stream.send_buffer_size().unwrap(), 64 * 1024);
+    let err = stream.set_recv_buffer_size(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = stream.set_send_buffer_size(usize::MAX).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+pub fn test_net_tcp_listener_options() {
+    let listener = TcpListener::new_v4().unwrap();
+
+    listener.set_reuse_address(true).unwrap();
+    assert!(listener.reuse_address().unwrap());
+    listener.set_reuse_address(false).unwrap();
+    assert!(!listener.reuse_address().unwrap());
+
+    listener.set_reuse_port(true).unwrap();
+    assert!(listener.reuse_port().unwrap());
+    listener.set_reuse_port(false).unwrap();
+    assert!(!listener.reuse_port().unwrap());
+
+    listener.set_recv_buffer_size(32 * 1024).unwrap();
+    assert_eq!(listener.recv_buffer_size().unwrap(), 64 * 1024);
+    listener.set_send_buffer_size(32 * 1024).unwrap();
+    assert_eq!(listener.send_buffer_size().unwrap(), 64 * 1024);
+    let err = listener.set_recv_buffer_size(usize::MAX).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = listener.set_send_buffer_size(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+pub fn test_net_udp_socket_options() {
+    let socket = UdpSocket::new_v4().unwrap();
+
+    socket.set_reuse_address(true).unwrap();
+    assert!(socket.reuse_address().unwrap());
+    socket.set_reuse_address(false).unwrap();
+    assert!(!socket.reuse_address().unwrap());
+
+    socket.set_reuse_port(true).unwrap();
+    assert!(socket.reuse_port().unwrap());
+    socket.set_reuse_port(false).unwrap();
+    assert!(!socket.reuse_port().unwrap());
+
+    socket.set_recv_buffer_size(32 * 1024).unwrap();
+    assert_eq!(socket.recv_buffer_size().unwrap(), 64 * 1024);
+    socket.set_send_buffer_size(32 * 1024).unwrap();
+    assert_eq!(socket.send_buffer_size().unwrap(), 64 * 1024);
+    let err = socket.set_recv_buffer_size(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = socket.set_send_buffer_size(usize::MAX).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+// Every getter above checks that the host filled the whole option. The host
+// can legitimately report fewer bytes: an unbound socket has no device, so
+// SO_BINDTODEVICE comes back empty, which the getters would reject as
+// InvalidData instead of reading an uninitialized value.
+pub fn test_net_getsockopt_length() {
+    let socket = UdpSocket::new_v4().unwrap();
+    let mut value: sgx_libc::c_int = -1;
+    let mut len = mem::size_of::<sgx_libc::c_int>() as sgx_libc::socklen_t;
+    let ret = unsafe {
+        sgx_libc::ocall::getsockopt(
+            socket.as_raw_fd(),
+            sgx_libc::SOL_SOCKET,
+            sgx_libc::SO_BINDTODEVICE,
+            &mut value as *mut sgx_libc::c_int as *mut sgx_libc::c_void,
+            &mut len,
+        )
+    };
+    assert_eq!(ret, 0);
+    assert_eq!(len, 0);
+
+    // A buffer that is not enclave memory is refused before the ocall.
+    let mut len = mem::size_of::<sgx_libc::c_int>() as sgx_libc::socklen_t;
+    let ret = unsafe {
+        sgx_libc::ocall::getsockopt(
+            socket.as_raw_fd(),
+            sgx_libc::SOL_SOCKET,
+            sgx_libc::SO_REUSEADDR,
+            ptr::null_mut::<u8>().wrapping_add(0x1000) as *mut sgx_libc::c_void,
+            &mut len,
+        )
+    };
+    assert_eq!(ret, -1);
+    assert_eq!(
+        io::Error::last_os_error().raw_os_error(),
+        Some(sgx_libc::EINVAL)
+    );
+}
@@ -0,0 +1,33 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use std::io::ErrorKind;
+use std::process::{self, Command};
+
+pub fn test_process_command_not_allowed() {
+    let err = Command::new("/bin/true").output().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+}
+
+pub fn test_process_command_args_rejected() {
+    process::allow_program("/bin/echo", |args| args.is_empty());
+    let err = Command::new("/bin/echo").arg("hello").status().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    process::disallow_program("/bin/echo");
+    let err = Command::new("/bin/echo").status().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+}
@@ -16,6 +16,7 @@
 // under the License..
 
 use sgx_rand::*;
+use std::collections::hash_map::{self, RandomState};
 
 // pub use os::SgxRng
 pub fn test_rand_os_sgxrng() {
@@ -794,3 +795,34 @@ pub fn test_rand_reseeding() {
 
 // No need for testing others
 // Already included in the above tests
+
+pub fn test_rand_hashmap_reseed() {
+    // The keys are private; read them back in whichever order the fields are laid out.
+    fn keys(state: RandomState) -> [u64; 2] {
+        unsafe { std::mem::transmute::<RandomState, [u64; 2]>(state) }
+    }
+
+    // Without a reseed the thread's cached keys are reused with k0 stepped by one.
+    let first = keys(RandomState::new());
+    let second = keys(RandomState::new());
+    let k1 = if second[0] == first[0] {
+        assert_eq!(second[1], first[1].wrapping_add(1));
+        0
+    } else {
+        assert_eq!(second[0], first[0].wrapping_add(1));
+        assert_eq!(second[1], first[1]);
+        1
+    };
+    let k0 = 1 - k1;
+
+    // After a reseed both keys are drawn afresh instead of stepping the cached ones.
+    hash_map::reseed_random_keys();
+    let after = keys(RandomState::new());
+    assert_ne!(after[k1], second[k1]);
+    assert_ne!(after[k0], second[k0].wrapping_add(1));
+
+    // The fresh keys are cached in turn.
+    let next = keys(RandomState::new());
+    assert_eq!(next[k0], after[k0].wrapping_add(1));
+    assert_eq!(next[k1], after[k1]);
+}
@@ -1,4 +1,5 @@
-use rand::{ChaChaRng, RngCore, SeedableRng};
+use rand::{ChaChaRng, CryptoRng, Rng, RngCore, SeedableRng};
+use std::rand::TrustedRng;
 
 pub fn test_rand_cratesio() {
     let seed = [0u8; 32];
@@ -11,3 +12,61 @@ pub fn test_rand_cratesio() {
     ];
     assert_eq!(results, expected);
 }
+
+struct EnclaveRng(TrustedRng);
+
+impl RngCore for EnclaveRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0
+            .try_fill_bytes(dest)
+            .map_err(|_| rand::Error::new(rand::ErrorKind::Unavailable, "sgx_read_rand failed"))
+    }
+}
+
+impl CryptoRng for EnclaveRng {}
+
+pub fn test_rand_cratesio_trusted_rng() {
+    let mut rng = EnclaveRng(TrustedRng::new());
+    let mut buf = [0u8; 64];
+    rng.fill_bytes(&mut buf);
+    assert_ne!(buf, [0u8; 64]);
+    assert!(rng.gen_range(10u32, 20) >= 10);
+
+    // A ChaCha stream seeded from trusted entropy.
+    let mut chacha = ChaChaRng::from_rng(&mut rng).unwrap();
+    assert_ne!(chacha.next_u64() | chacha.next_u64(), 0);
+}
+
+fn fill_crypto<R: rand_core::RngCore + rand_core::CryptoRng>(rng: &mut R, dest: &mut [u8]) {
+    rng.try_fill_bytes(dest).unwrap();
+}
+
+pub fn test_rand_core_trusted_rng() {
+    use rand_core::RngCore;
+
+    let mut rng = TrustedRng::new();
+    let mut buf = [0u8; 64];
+    RngCore::fill_bytes(&mut rng, &mut buf);
+    assert_ne!(buf, [0u8; 64]);
+
+    let mut other = [0u8; 64];
+    fill_crypto(&mut rng, &mut other);
+    assert_ne!(buf, other);
+    // Through a `&mut R`, as most rand APIs take the generator.
+    fill_crypto(&mut &mut rng, &mut other);
+    assert_ne!(buf, other);
+
+    let a = RngCore::next_u64(&mut rng);
+    let b = RngCore::next_u64(&mut rng);
+    assert_ne!(a, b);
+    let _ = RngCore::next_u32(&mut rng);
+}
@@ -21,6 +21,7 @@ use std::vec::Vec;
 
 use sgx_trts::ascii::AsciiExt;
 use sgx_trts::c_str::*;
+use sgx_trts::ecall::*;
 use sgx_trts::enclave::*;
 use sgx_trts::error;
 use sgx_trts::libc;
@@ -28,6 +29,8 @@ use sgx_trts::memchr;
 use sgx_trts::trts::*;
 use sgx_trts::veh::*;
 
+use sgx_ecall_derive::sgx_ecall;
+
 //Only during dev
 //use core::mem;
 
@@ -375,3 +378,68 @@ pub fn test_cstr() {
         Cow::Owned(String::from("Hello �World")) as Cow<str>
     );
 }
+
+#[sgx_ecall]
+pub fn test_ecall_fill(name: &str, buf: &mut [u8], byte: u8) -> u64 {
+    for b in buf.iter_mut() {
+        *b = byte;
+    }
+    name.len() as u64
+}
+
+pub fn test_ecall_handler() {
+    let mut input = EcallWriter::new();
+    input.write_bytes(b"fill");
+    input.write_bytes(&[0; 3]);
+    input.write(&7_u8);
+
+    let mut output = EcallWriter::new();
+    test_ecall_fill::handle(input.as_bytes(), &mut output).unwrap();
+    let mut reader = EcallReader::new(output.as_bytes());
+    assert_eq!(reader.read::<u64>().unwrap(), 4);
+    assert_eq!(reader.read_bytes().unwrap(), vec![7; 3]);
+    assert!(reader.finish().is_ok());
+
+    // truncated and trailing input
+    let mut output = EcallWriter::new();
+    assert_eq!(
+        test_ecall_fill::handle(&input.as_bytes()[..8], &mut output),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+    let mut long = input.clone();
+    long.write(&0_u8);
+    assert_eq!(
+        test_ecall_fill::handle(long.as_bytes(), &mut output),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+    assert_eq!(
+        <test_ecall_fill as EcallHandler>::ID,
+        rsgx_ecall_id("test_ecall_fill")
+    );
+}
+
+pub fn test_ecall_dispatch_in_enclave() {
+    let input = [0_u8; 8];
+    let mut output = [0_u8; 8];
+    let mut ms = sgx_rust_ecall_ms_t {
+        ms_retval: sgx_status_t::SGX_SUCCESS,
+        ms_id: <test_ecall_fill as EcallHandler>::ID,
+        ms_input: input.as_ptr(),
+        ms_input_len: input.len(),
+        ms_output: output.as_mut_ptr(),
+        ms_output_size: output.len(),
+        ms_output_len: 0,
+    };
+    let handlers = [EcallEntry::new::<test_ecall_fill>()];
+    let ret = unsafe { rsgx_dispatch_ecall(&mut ms as *mut _ as *mut c_void, &handlers) };
+    assert_eq!(ret, sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+}
+
+pub fn test_ecall_cancel_flag_in_enclave() {
+    let flag = 0_u32;
+    assert_eq!(
+        EcallCancelFlag::from_raw(&flag).err(),
+        Some(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    );
+    assert!(EcallCancelFlag::from_raw(std::ptr::null()).is_err());
+}
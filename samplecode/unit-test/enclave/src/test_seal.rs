@@ -113,3 +113,550 @@ pub fn test_mac_aadata_slice() {
     let inner_slice = unsafe { slice::from_raw_parts(inner as *mut u8, 10) };
     assert_eq!(inner_slice, aad_data);
 }
+
+pub fn test_seal_stream() {
+    use std::io::{ErrorKind, Read, Write};
+
+    let mut data = vec![0_u8; 10000];
+    let mut rand = StdRng::new().unwrap();
+    rand.fill_bytes(&mut data);
+    let aad = b"stream aad";
+
+    let mut writer = SealWriter::with_chunk_size(Vec::new(), aad, 1024).unwrap();
+    writer.write_all(&data[..3000]).unwrap();
+    writer.flush().unwrap();
+    writer.write_all(&data[3000..]).unwrap();
+    let sealed = writer.finish().unwrap();
+
+    let mut reader = UnsealReader::new(sealed.as_slice()).unwrap();
+    assert_eq!(reader.get_additional_txt(), aad);
+    assert_eq!(reader.get_chunk_size(), 1024);
+    let mut out = Vec::new();
+    reader.read_to_end(&mut out).unwrap();
+    assert_eq!(out, data);
+
+    // An empty stream still carries a final record.
+    let sealed = SealWriter::new(Vec::new(), &[]).unwrap().finish().unwrap();
+    let mut out = Vec::new();
+    UnsealReader::new(sealed.as_slice())
+        .unwrap()
+        .read_to_end(&mut out)
+        .unwrap();
+    assert!(out.is_empty());
+
+    let mut writer = SealWriter::with_chunk_size(Vec::new(), &[], 1024).unwrap();
+    writer.write_all(&data).unwrap();
+    let sealed = writer.finish().unwrap();
+
+    // Flipping a ciphertext bit of the last record fails authentication.
+    let mut tampered = sealed.clone();
+    let n = tampered.len();
+    tampered[n - 20] ^= 1;
+    let mut out = Vec::new();
+    let mut reader = UnsealReader::new(tampered.as_slice()).unwrap();
+    assert!(reader.read_to_end(&mut out).is_err());
+    assert!(out.len() < data.len());
+
+    // Dropping the final record is detected.
+    let truncated = &sealed[..n - (10000 % 1024 + 20)];
+    let mut out = Vec::new();
+    let err = UnsealReader::new(truncated)
+        .unwrap()
+        .read_to_end(&mut out)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+    assert!(SealWriter::with_chunk_size(Vec::new(), &[], 0).is_err());
+}
+
+pub fn test_seal_policy() {
+    let data: [u8; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+    let aad: [u8; 0] = [0_u8; 0];
+
+    let policy = SealPolicy::mrenclave()
+        .bind_mrsigner(true)
+        .bind_isv_prod_id(false);
+    assert_eq!(
+        policy.get_key_policy(),
+        SGX_KEYPOLICY_MRENCLAVE | SGX_KEYPOLICY_MRSIGNER | SGX_KEYPOLICY_NOISVPRODID
+    );
+    let sealed_data = SgxSealedData::<[u8]>::seal_data_with_policy(&policy, &aad, &data)
+        .expect("error while sealing with policy");
+    assert_eq!(
+        sealed_data.get_key_request().key_policy,
+        policy.get_key_policy()
+    );
+    let unsealed_data = sealed_data
+        .unseal_data()
+        .expect("error while unsealing with policy");
+    assert_eq!(unsealed_data.get_decrypt_txt(), data);
+
+    // The key request records the ISVSVN the key was derived for.
+    let isv_svn = sealed_data.get_key_request().isv_svn;
+    if isv_svn > 0 {
+        let old = SealPolicy::mrsigner().isv_svn(isv_svn - 1);
+        let sealed_data = SgxSealedData::<[u8]>::seal_data_with_policy(&old, &aad, &data).unwrap();
+        assert_eq!(sealed_data.get_key_request().isv_svn, isv_svn - 1);
+        assert!(sealed_data.unseal_data().is_ok());
+    }
+    if isv_svn < u16::MAX {
+        let newer = SealPolicy::mrsigner().isv_svn(isv_svn + 1);
+        assert!(matches!(
+            SgxSealedData::<[u8]>::seal_data_with_policy(&newer, &aad, &data),
+            Err(sgx_status_t::SGX_ERROR_INVALID_ISVSVN)
+        ));
+    }
+
+    let unbound = SealPolicy::mrsigner().bind_mrsigner(false);
+    assert!(matches!(
+        SgxSealedData::<[u8]>::seal_data_with_policy(&unbound, &aad, &data),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    ));
+    let no_debug = SealPolicy::mrenclave().attribute_mask(sgx_attributes_t {
+        flags: SGX_FLAGS_INITTED,
+        xfrm: 0,
+    });
+    assert!(matches!(
+        SgxSealedData::<[u8]>::seal_data_with_policy(&no_debug, &aad, &data),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    ));
+
+    let mut k1 = [0_u8; 32];
+    let mut k2 = [0_u8; 32];
+    rsgx_derive_seal_key_with_policy(&SealPolicy::mrenclave(), b"label", &mut k1).unwrap();
+    rsgx_derive_seal_key_with_policy(&SealPolicy::mrsigner(), b"label", &mut k2).unwrap();
+    assert_ne!(k1, k2);
+}
+
+pub fn test_seal_blob_reseal() {
+    let data: [u8; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+    let aad = b"blob aad";
+
+    let policy = SealPolicy::mrsigner();
+    let blob = rsgx_seal_blob(&policy, aad, &data).unwrap();
+    assert_eq!(&blob[..4], &SGX_SEALED_BLOB_MAGIC);
+    assert_eq!(
+        rsgx_sealed_blob_version(&blob).unwrap(),
+        SGX_SEALED_BLOB_VERSION
+    );
+    assert!(!rsgx_needs_reseal(&blob, &policy).unwrap());
+    let unsealed = rsgx_unseal_blob(&blob).unwrap();
+    assert_eq!(&*unsealed.decrypt, &data);
+    assert_eq!(&*unsealed.additional, aad);
+
+    // Moving to an MRENCLAVE key keeps the contents.
+    let new_policy = SealPolicy::mrenclave();
+    assert!(rsgx_needs_reseal(&blob, &new_policy).unwrap());
+    let resealed = rsgx_reseal(&blob, &new_policy).unwrap();
+    assert_eq!(
+        rsgx_sealed_blob_key_request(&resealed).unwrap().key_policy,
+        SGX_KEYPOLICY_MRENCLAVE
+    );
+    assert!(!rsgx_needs_reseal(&resealed, &new_policy).unwrap());
+    let unsealed = rsgx_unseal_blob(&resealed).unwrap();
+    assert_eq!(&*unsealed.decrypt, &data);
+    assert_eq!(&*unsealed.additional, aad);
+
+    // A raw sgx_sealed_data_t is read as version 0 and upgraded.
+    let sealed_data = SgxSealedData::<[u8]>::seal_data(aad, &data).unwrap();
+    let size = SgxSealedData::<[u8]>::calc_raw_sealed_data_size(aad.len() as u32, 10) as usize;
+    let mut raw = vec![0_u64; (size + 7) / 8];
+    unsafe {
+        sealed_data
+            .to_raw_sealed_data_t(raw.as_mut_ptr() as *mut sgx_sealed_data_t, size as u32)
+            .unwrap();
+    }
+    let raw = unsafe { std::slice::from_raw_parts(raw.as_ptr() as *const u8, size) };
+    assert_eq!(rsgx_sealed_blob_version(raw).unwrap(), 0);
+    assert!(rsgx_needs_reseal(raw, &policy).unwrap());
+    let upgraded = rsgx_reseal(raw, &policy).unwrap();
+    assert_eq!(
+        rsgx_sealed_blob_version(&upgraded).unwrap(),
+        SGX_SEALED_BLOB_VERSION
+    );
+    assert_eq!(&*rsgx_unseal_blob(&upgraded).unwrap().decrypt, &data);
+
+    let mut bad = blob.clone();
+    bad[4] = 9;
+    assert!(matches!(
+        rsgx_unseal_blob(&bad),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    ));
+    let mut bad = blob;
+    let n = bad.len();
+    bad[n - 1] ^= 1;
+    assert!(matches!(
+        rsgx_reseal(&bad, &policy),
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    ));
+}
+
+pub fn test_mac_aadata_stream() {
+    let data: Vec<u8> = (0..10000_u32).map(|i| i as u8).collect();
+
+    let mut mac = SgxMacAadataStream::new().unwrap();
+    for chunk in data.chunks(777) {
+        mac.update(chunk).unwrap();
+    }
+    let mac = mac.finalize().unwrap();
+    assert_eq!(mac.get_aad_len(), data.len() as u64);
+
+    let bytes = mac.to_bytes();
+    let mac = SgxDetachedMac::from_bytes(&bytes).unwrap();
+    let mut check = SgxUnmacAadataStream::new(&mac).unwrap();
+    for chunk in data.chunks(1000) {
+        check.update(chunk).unwrap();
+    }
+    assert!(check.verify().is_ok());
+
+    let mut check = SgxUnmacAadataStream::new(&mac).unwrap();
+    check.update(&data[..9999]).unwrap();
+    assert!(matches!(
+        check.verify(),
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    ));
+
+    let mut tampered = data.clone();
+    tampered[5000] ^= 1;
+    let mut check = SgxUnmacAadataStream::new(&mac).unwrap();
+    check.update(&tampered).unwrap();
+    assert!(matches!(
+        check.verify(),
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    ));
+
+    let mut bad = bytes;
+    bad[0] ^= 1;
+    assert!(SgxDetachedMac::from_bytes(&bad).is_err());
+    assert!(SgxDetachedMac::from_bytes(&bytes[1..]).is_err());
+}
+
+pub fn test_seal_key_cache() {
+    let data: [u8; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+    let aad: [u8; 0] = [0_u8; 0];
+    let sealed_data = SgxSealedData::<[u8]>::seal_data(&aad, &data).unwrap();
+
+    assert!(rsgx_seal_key_cache_enable(0).is_err());
+    rsgx_seal_key_cache_enable(2).unwrap();
+    assert_eq!(rsgx_seal_key_cache_len(), 0);
+
+    // Only unsealing fills the cache; the second unseal is a hit.
+    for _ in 0..2 {
+        let unsealed_data = sealed_data.unseal_data().unwrap();
+        assert_eq!(unsealed_data.get_decrypt_txt(), data);
+        assert_eq!(rsgx_seal_key_cache_len(), 1);
+    }
+
+    let mut k1 = [0_u8; 16];
+    let mut k2 = [0_u8; 16];
+    rsgx_derive_seal_key(b"cache", &mut k1).unwrap();
+    rsgx_derive_seal_key(b"cache", &mut k2).unwrap();
+    assert_eq!(k1, k2);
+    assert_eq!(rsgx_seal_key_cache_len(), 2);
+
+    // The least recently used key is evicted.
+    let other = SgxSealedData::<[u8]>::seal_data(&aad, &data).unwrap();
+    other.unseal_data().unwrap();
+    assert_eq!(rsgx_seal_key_cache_len(), 2);
+
+    // Pinning another CPUSVN drops every key and keeps the cache empty.
+    let mut cpu_svn = sealed_data.get_key_request().cpu_svn;
+    cpu_svn.svn[0] ^= 0xff;
+    rsgx_seal_key_cache_pin_cpusvn(&cpu_svn);
+    assert_eq!(rsgx_seal_key_cache_len(), 0);
+    sealed_data.unseal_data().unwrap();
+    assert_eq!(rsgx_seal_key_cache_len(), 0);
+    rsgx_seal_key_cache_unpin_cpusvn();
+    sealed_data.unseal_data().unwrap();
+    assert_eq!(rsgx_seal_key_cache_len(), 1);
+
+    rsgx_seal_key_cache_flush();
+    assert_eq!(rsgx_seal_key_cache_len(), 0);
+    rsgx_seal_key_cache_disable();
+    sealed_data.unseal_data().unwrap();
+    assert_eq!(rsgx_seal_key_cache_len(), 0);
+}
+
+pub fn test_seal_context() {
+    let data: [u8; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+    let aad: [u8; 0] = [0_u8; 0];
+    let policy = SealPolicy::mrsigner();
+
+    let sealed_data =
+        SgxSealedData::<[u8]>::seal_data_with_context(&policy, b"subsystem a", &aad, &data)
+            .unwrap();
+    let unsealed_data = sealed_data
+        .unseal_data_with_context(b"subsystem a")
+        .unwrap();
+    assert_eq!(unsealed_data.get_decrypt_txt(), data);
+    assert!(matches!(
+        sealed_data.unseal_data_with_context(b"subsystem b"),
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    ));
+    assert!(matches!(
+        sealed_data.unseal_data_with_context(b""),
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    ));
+
+    // Data sealed without a context is not accepted in one.
+    let plain = SgxSealedData::<[u8]>::seal_data(&aad, &data).unwrap();
+    assert!(matches!(
+        plain.unseal_data_with_context(b"subsystem a"),
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    ));
+
+    let number: u64 = 123456789;
+    let sealed_number =
+        SgxSealedData::<u64>::seal_data_with_context(&policy, b"counter", &aad, &number).unwrap();
+    let unsealed_number = sealed_number.unseal_data_with_context(b"counter").unwrap();
+    assert_eq!(*unsealed_number.get_decrypt_txt(), number);
+}
+
+pub fn test_seal_state_rollback() {
+    let policy = SealPolicy::mrsigner();
+    let counter = SgxVolatileCounter::new(&[1_u8; 16], 0);
+    let other = SgxVolatileCounter::new(&[2_u8; 16], 1);
+
+    let v1 = rsgx_seal_state(&counter, &policy, b"aad", b"state 1").unwrap();
+    assert_eq!(counter.read().unwrap(), 1);
+    let unsealed = rsgx_unseal_state(&counter, &v1).unwrap();
+    assert_eq!(&*unsealed.decrypt, b"state 1");
+    assert_eq!(&*unsealed.additional, b"aad");
+
+    // Once a newer state is sealed, the old one is refused.
+    let v2 = rsgx_seal_state(&counter, &policy, b"aad", b"state 2").unwrap();
+    assert!(matches!(
+        rsgx_unseal_state(&counter, &v1),
+        Err(sgx_status_t::SGX_ERROR_INVALID_STATE)
+    ));
+    assert_eq!(
+        &*rsgx_unseal_state(&counter, &v2).unwrap().decrypt,
+        b"state 2"
+    );
+
+    // State of one counter is not checked against another, even at the same value.
+    other.increment().unwrap();
+    assert!(matches!(
+        rsgx_unseal_state(&other, &v2),
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    ));
+
+    // A plain sealed blob is not state.
+    let blob = rsgx_seal_blob(&policy, b"aad", b"state 2").unwrap();
+    assert!(matches!(
+        rsgx_unseal_state(&counter, &blob),
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    ));
+
+    let full = SgxVolatileCounter::new(&[3_u8; 16], u64::MAX);
+    assert!(matches!(
+        rsgx_seal_state(&full, &policy, b"", b"state"),
+        Err(sgx_status_t::SGX_ERROR_MC_USED_UP)
+    ));
+}
+
+pub fn test_seal_codec() {
+    #[derive(Serializable, DeSerializable, PartialEq, Debug)]
+    struct Config {
+        name: String,
+        keys: Vec<u64>,
+        enabled: bool,
+    }
+
+    let config = Config {
+        name: "enclave".to_string(),
+        keys: vec![1, 2, 3],
+        enabled: true,
+    };
+    let blob = rsgx_seal_serialize(1, &config).unwrap();
+    assert_eq!(SgxSealCodec::sealed_version(&blob).unwrap(), 1);
+    let restored: Config = rsgx_unseal_deserialize(1, &blob).unwrap();
+    assert_eq!(restored, config);
+
+    // Another type version is refused rather than misread.
+    assert!(matches!(
+        rsgx_unseal_deserialize::<Config>(2, &blob),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    ));
+
+    let codec = SgxSealCodec::new(1)
+        .policy(&SealPolicy::mrenclave())
+        .limit(16);
+    assert!(matches!(
+        codec.seal(&config),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    ));
+    assert!(matches!(
+        codec.unseal::<Config>(&blob),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    ));
+    let small = codec.seal(&7_u32).unwrap();
+    assert_eq!(codec.unseal::<u32>(&small).unwrap(), 7);
+}
+
+pub fn test_seal_exchange() {
+    // Both ends of the exchange run in this enclave, so a report targeted at it verifies.
+    let target = rsgx_secret_exchange_target();
+    let peer = SgxPeerIdentity::same_mrenclave();
+    let receiver = SgxSecretReceiver::new(&target).unwrap();
+
+    let request = SgxSecretRequest::from_bytes(&receiver.get_request().to_bytes()).unwrap();
+    let wrapped = rsgx_wrap_secret(&request, &peer, b"label", b"top secret").unwrap();
+    let wrapped = SgxWrappedSecret::from_bytes(&wrapped.to_bytes()).unwrap();
+    let unwrapped = receiver.receive(&wrapped, &peer).unwrap();
+    assert_eq!(unwrapped.get_decrypt_txt(), b"top secret");
+    assert_eq!(unwrapped.get_additional_txt(), b"label");
+
+    // A sealed blob is handed over and re-sealed by the receiver.
+    let blob = rsgx_seal_blob(&SealPolicy::mrenclave(), b"aad", b"blob secret").unwrap();
+    let wrapped = rsgx_wrap_sealed_blob(&request, &peer, &blob).unwrap();
+    let resealed = receiver
+        .receive_sealed(&wrapped, &peer, &SealPolicy::mrsigner())
+        .unwrap();
+    let unsealed = rsgx_unseal_blob(&resealed).unwrap();
+    assert_eq!(unsealed.get_decrypt_txt(), b"blob secret");
+    assert_eq!(unsealed.get_additional_txt(), b"aad");
+
+    // A tampered ciphertext does not authenticate.
+    let mut bytes = wrapped.to_bytes();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    let tampered = SgxWrappedSecret::from_bytes(&bytes).unwrap();
+    assert!(matches!(
+        receiver.receive(&tampered, &peer),
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    ));
+
+    // A wrapped secret is bound to the request it answers.
+    let other = SgxSecretReceiver::new(&target).unwrap();
+    assert!(matches!(
+        other.receive(&wrapped, &peer),
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    ));
+
+    // The peer must have the expected identity.
+    let stranger = SgxPeerIdentity::mrenclave(&sgx_measurement_t::default()).allow_debug(true);
+    assert!(matches!(
+        rsgx_wrap_secret(&request, &stranger, &[], b"x"),
+        Err(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE)
+    ));
+    let newer = peer.min_isv_svn(u16::MAX);
+    assert!(matches!(
+        receiver.receive(&wrapped, &newer),
+        Err(sgx_status_t::SGX_ERROR_INVALID_ISVSVN)
+    ));
+
+    assert!(matches!(
+        SgxWrappedSecret::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    ));
+}
+
+pub fn test_seal_allowlist() {
+    // The request of a receiver carries a report of this enclave.
+    let target = rsgx_secret_exchange_target();
+    let receiver = SgxSecretReceiver::new(&target).unwrap();
+    let body = receiver.get_request().get_report().body;
+    let stranger = SgxPeerIdentity::mrenclave(&sgx_measurement_t::default()).allow_debug(true);
+    let list = SgxIdentityAllowlist::new(1)
+        .allow(stranger)
+        .allow(SgxPeerIdentity::same_mrenclave());
+    list.check(&body).unwrap();
+    assert!(matches!(
+        SgxIdentityAllowlist::new(1).allow(stranger).check(&body),
+        Err(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE)
+    ));
+    // The error of an identity that matches is preferred over a mismatch.
+    let newer = SgxIdentityAllowlist::new(1)
+        .allow(stranger)
+        .allow(SgxPeerIdentity::same_mrenclave().min_isv_svn(u16::MAX));
+    assert!(matches!(
+        newer.check(&body),
+        Err(sgx_status_t::SGX_ERROR_INVALID_ISVSVN)
+    ));
+
+    let bytes = list.to_bytes().unwrap();
+    let parsed = SgxIdentityAllowlist::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed.get_version(), 1);
+    assert_eq!(parsed.get_identities().len(), 2);
+    parsed.check(&body).unwrap();
+    assert!(matches!(
+        SgxIdentityAllowlist::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    ));
+
+    // Only the latest sealed list unseals.
+    let counter = SgxVolatileCounter::new(&[4_u8; 16], 0);
+    let policy = SealPolicy::mrenclave();
+    let v1 = list.seal(&counter, &policy).unwrap();
+    let mut current = SgxIdentityAllowlist::unseal(&counter, &v1).unwrap();
+    current.check(&body).unwrap();
+
+    // Updates arrive through the exchange and must be newer.
+    let admin = SgxPeerIdentity::same_mrenclave();
+    let update = SgxIdentityAllowlist::new(2).allow(stranger);
+    let wrapped = rsgx_wrap_secret(
+        receiver.get_request(),
+        &admin,
+        &[],
+        &update.to_bytes().unwrap(),
+    )
+    .unwrap();
+    current.receive_update(&receiver, &wrapped, &admin).unwrap();
+    assert_eq!(current.get_version(), 2);
+    assert!(matches!(
+        current.check(&body),
+        Err(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE)
+    ));
+    assert!(matches!(
+        current.receive_update(&receiver, &wrapped, &admin),
+        Err(sgx_status_t::SGX_ERROR_INVALID_VERSION)
+    ));
+    assert!(matches!(
+        current.update(list.clone()),
+        Err(sgx_status_t::SGX_ERROR_INVALID_VERSION)
+    ));
+
+    let v2 = current.seal(&counter, &policy).unwrap();
+    assert!(matches!(
+        SgxIdentityAllowlist::unseal(&counter, &v1),
+        Err(sgx_status_t::SGX_ERROR_INVALID_STATE)
+    ));
+    assert_eq!(
+        SgxIdentityAllowlist::unseal(&counter, &v2)
+            .unwrap()
+            .get_version(),
+        2
+    );
+}
+
+pub fn test_sealed_metadata() {
+    let policy = SealPolicy::mrenclave().isv_svn(0);
+    let blob = rsgx_seal_blob(&policy, b"header", b"payload!").unwrap();
+    let meta = SealedMetadata::parse(&blob).unwrap();
+    assert_eq!(meta.get_version(), SGX_SEALED_BLOB_VERSION);
+    assert_eq!(meta.get_key_policy(), policy.get_key_policy());
+    assert_eq!(meta.get_isv_svn(), 0);
+    assert_eq!(meta.get_encrypt_txt_len(), 8);
+    assert_eq!(meta.get_add_mac_txt_len(), 6);
+    assert_eq!(meta.get_payload_size(), 14);
+    assert_eq!(
+        meta.get_key_request().key_id.id,
+        rsgx_sealed_blob_key_request(&blob).unwrap().key_id.id
+    );
+
+    // A raw sgx_sealed_data_t is version 0.
+    let raw = &blob[SGX_SEALED_BLOB_HEADER_SIZE..];
+    let meta = SealedMetadata::parse(raw).unwrap();
+    assert_eq!(meta.get_version(), 0);
+    assert_eq!(meta.get_payload_size(), 14);
+
+    assert!(matches!(
+        SealedMetadata::parse(&blob[..blob.len() - 1]),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    ));
+    assert!(matches!(
+        SealedMetadata::parse(&blob[..SGX_SEALED_BLOB_HEADER_SIZE + 16]),
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    ));
+}
@@ -387,3 +387,46 @@ pub fn test_serialize_base() {
     test_hash_map();
     test_tuples();
 }
+
+pub fn test_serialize_sgx_types() {
+    use sgx_types::{sgx_key_request_t, sgx_report_t, sgx_target_info_t};
+
+    #[derive(Serializable, DeSerializable)]
+    struct Attestation {
+        target_info: sgx_target_info_t,
+        report: sgx_report_t,
+        nonce: u64,
+    }
+
+    let mut report = [0_u8; 432];
+    report[320..384].copy_from_slice(&[0x5a; 64]);
+    report[416..432].copy_from_slice(&[0xa5; 16]);
+    let mut target_info = [0_u8; 512];
+    target_info[..32].copy_from_slice(&[0x11; 32]);
+    let a = Attestation {
+        target_info: sgx_target_info_t::from_bytes(&target_info).unwrap(),
+        report: sgx_report_t::from_bytes(&report).unwrap(),
+        nonce: 7,
+    };
+    let c = test_serialize_internal::<Attestation>(&a).unwrap();
+    assert_eq!(c.report.as_bytes(), &report[..]);
+    assert_eq!(c.target_info.as_bytes(), &target_info[..]);
+    assert_eq!(c.nonce, 7);
+
+    let mut key_request = sgx_key_request_t::default();
+    key_request.key_name = 4;
+    key_request.key_id.id = [0x33; 32];
+    let bytes = key_request.as_bytes();
+    assert_eq!(bytes.len(), 512);
+    assert_eq!(&bytes[..2], &4_u16.to_le_bytes());
+    let c = test_serialize_internal::<sgx_key_request_t>(&key_request).unwrap();
+    assert_eq!(c.as_bytes(), bytes);
+
+    // A truncated structure does not decode.
+    let data = SerializeHelper::new()
+        .encode(&report[..431].to_vec())
+        .unwrap();
+    assert!(DeSerializeHelper::<sgx_report_t>::new(data)
+        .decode()
+        .is_none());
+}
@@ -0,0 +1,46 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+use sgx_trts::trts::rsgx_raw_is_outside_enclave;
+use std::io::{ErrorKind, Read, ShmReader, ShmWriter, Write};
+
+pub fn test_shm_ring() {
+    let mut writer = ShmWriter::new(8).unwrap();
+    let base = writer.as_raw_ptr();
+    assert_eq!(base as usize % 4096, 0);
+    assert!(rsgx_raw_is_outside_enclave(base, writer.capacity()));
+    assert_eq!(writer.write(b"0123456789").unwrap(), 8);
+    assert_eq!(
+        writer.write(b"x").unwrap_err().kind(),
+        ErrorKind::WouldBlock
+    );
+
+    let mut reader = ShmReader::new(8).unwrap();
+    let mut buf = [0u8; 4];
+    assert_eq!(
+        reader.read(&mut buf).unwrap_err().kind(),
+        ErrorKind::WouldBlock
+    );
+
+    assert_eq!(
+        ShmWriter::new(0).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        ShmWriter::new(usize::MAX).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+}
@@ -10,7 +10,8 @@ use std::thread;
 use std::thread::sleep;
 use std::thread::Builder;
 use std::thread::ThreadId;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use std::untrusted::time::InstantEx;
 use std::u32;
 
 pub fn test_thread_unnamed_thread() {
@@ -226,6 +227,12 @@ pub fn test_thread_sleep_ms_smoke() {
     thread::sleep(Duration::from_millis(10));
 }
 
+pub fn test_thread_sleep_checked() {
+    let start = Instant::now();
+    thread::sleep_checked(Duration::from_millis(10), Duration::from_secs(10)).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(10));
+}
+
 pub fn test_thread_size_of_option_thread_id() {
     assert_eq!(
         mem::size_of::<Option<ThreadId>>(),
@@ -241,3 +248,195 @@ pub fn test_thread_id_not_equal() {
     let spawned_id = thread::spawn(|| thread::current().id()).join().unwrap();
     assert!(thread::current().id() != spawned_id);
 }
+
+pub fn test_thread_async_block_on() {
+    use std::io::{pipe, Read, Write};
+    use std::os::unix::io::AsRawFd;
+    use std::task;
+
+    let start = Instant::now();
+    let value = task::block_on(async {
+        task::sleep(Duration::from_millis(10)).await;
+        7
+    });
+    assert_eq!(value, 7);
+    assert!(start.elapsed() >= Duration::from_millis(10));
+
+    let (mut reader, mut writer) = pipe().unwrap();
+    let handle = task::spawn(async move {
+        task::sleep(Duration::from_millis(10)).await;
+        writer.write_all(b"ping").unwrap();
+    })
+    .unwrap();
+    task::block_on(async {
+        task::readable(reader.as_raw_fd()).await.unwrap();
+    });
+    let mut buf = [0_u8; 4];
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ping");
+    handle.join().unwrap();
+
+    let handle = task::spawn(async { panic!("task panicked") }).unwrap();
+    assert!(handle.join().is_err());
+}
+
+// Wakers run without the reactor locked, so waking may register a timer.
+pub fn test_thread_async_reentrant_waker() {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{self, Context, Poll, Wake, Waker};
+
+    struct Rearm(Waker);
+
+    impl Wake for Rearm {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref()
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            let mut sleep = task::sleep(Duration::from_secs(60));
+            let _ = Pin::new(&mut sleep).poll(&mut Context::from_waker(&self.0));
+            self.0.wake_by_ref();
+        }
+    }
+
+    struct WithRearm<F>(F);
+
+    impl<F: Future + Unpin> Future for WithRearm<F> {
+        type Output = F::Output;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+            let waker = Waker::from(Arc::new(Rearm(cx.waker().clone())));
+            Pin::new(&mut self.0).poll(&mut Context::from_waker(&waker))
+        }
+    }
+
+    let start = Instant::now();
+    task::block_on(WithRearm(task::sleep(Duration::from_millis(10))));
+    assert!(start.elapsed() >= Duration::from_millis(10));
+}
+
+pub fn test_thread_tcs() {
+    use std::io::ErrorKind;
+    use std::os::unix::thread::ThreadExt;
+
+    let main_tcs = thread::current().tcs().unwrap();
+    let main_index = thread::current().tcs_index().unwrap();
+    assert_eq!(thread::current().tcs(), Some(main_tcs));
+
+    let (tcs, index) = Builder::new()
+        .name("tcs worker".to_string())
+        .spawn(|| {
+            let current = thread::current();
+            assert_eq!(
+                current.set_name("renamed").unwrap_err().kind(),
+                ErrorKind::AlreadyExists
+            );
+            (current.tcs().unwrap(), current.tcs_index().unwrap())
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_ne!(tcs, main_tcs);
+    assert_ne!(index, main_index);
+
+    thread::spawn(|| {
+        let current = thread::current();
+        assert_eq!(
+            current.set_name("bad\0name").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+        current.set_name("ecall worker").unwrap();
+        assert_eq!(thread::current().name(), Some("ecall worker"));
+    })
+    .join()
+    .unwrap();
+}
+
+pub fn test_thread_once_lock_wait() {
+    use std::sync::{Arc, Once, OnceLock};
+
+    let cell = Arc::new(OnceLock::new());
+    let once = Arc::new(Once::new());
+    let waiter = {
+        let (cell, once) = (cell.clone(), once.clone());
+        thread::spawn(move || {
+            once.wait();
+            *cell.wait()
+        })
+    };
+
+    sleep(Duration::from_millis(50));
+    assert!(cell.get_or_try_init(|| Err(())).is_err());
+    assert_eq!(cell.get(), None);
+    once.call_once(|| {});
+    assert_eq!(cell.get_or_init(|| 7), &7);
+    assert_eq!(waiter.join().unwrap(), 7);
+}
+
+pub fn test_thread_scope_joins_dropped_handles() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let counter = AtomicUsize::new(0);
+    let mut data = [0_u32; 4];
+    thread::scope(|s| {
+        for slot in data.iter_mut() {
+            let counter = &counter;
+            // The handle is dropped right away; the scope still has to wait.
+            drop(s.spawn(move || {
+                sleep(Duration::from_millis(10));
+                *slot = 1;
+                counter.fetch_add(1, Ordering::SeqCst);
+            }));
+        }
+        let joined = s.spawn(|| counter.load(Ordering::SeqCst));
+        assert!(joined.join().unwrap() <= 4);
+    });
+    assert_eq!(counter.load(Ordering::SeqCst), 4);
+    assert_eq!(data, [1; 4]);
+}
+
+pub fn test_thread_semaphore() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier, Semaphore};
+
+    let sem = Arc::new(Semaphore::new(2));
+    let running = Arc::new(AtomicUsize::new(0));
+    let handles: Vec<_> = (0..6)
+        .map(|_| {
+            let (sem, running) = (sem.clone(), running.clone());
+            thread::spawn(move || {
+                let _permit = sem.acquire();
+                assert!(running.fetch_add(1, Ordering::SeqCst) < 2);
+                sleep(Duration::from_millis(10));
+                running.fetch_sub(1, Ordering::SeqCst);
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(sem.available_permits(), 2);
+
+    let permit = sem.try_acquire().unwrap();
+    sem.try_acquire().unwrap().forget();
+    assert!(sem.try_acquire().is_none());
+    assert!(sem.acquire_timeout(Duration::from_millis(10)).is_none());
+    drop(permit);
+    assert!(sem.acquire_timeout(Duration::from_millis(10)).is_some());
+    assert_eq!(sem.available_permits(), 1);
+
+    let barrier = Arc::new(Barrier::new(4));
+    let handles: Vec<_> = (0..3)
+        .map(|_| {
+            let barrier = barrier.clone();
+            thread::spawn(move || (0..10).filter(|_| barrier.wait().is_leader()).count())
+        })
+        .collect();
+    let mut leaders = (0..10).filter(|_| barrier.wait().is_leader()).count();
+    for handle in handles {
+        leaders += handle.join().unwrap();
+    }
+    assert_eq!(leaders, 10);
+}
@@ -108,3 +108,36 @@ pub fn test_std_time() {
         assert!(a < hundred_twenty_years);
     }
 }
+
+pub fn test_rate_limiter() {
+    use std::sync::RateLimiter;
+
+    let limiter = RateLimiter::new(3, Duration::from_secs(60));
+    assert_eq!(limiter.available(), 3);
+    assert!(limiter.try_acquire(2).is_ok());
+    assert!(limiter.try_acquire(1).is_ok());
+    assert_eq!(limiter.available(), 0);
+
+    let wait = limiter.try_acquire(1).unwrap_err();
+    assert!(wait > Duration::from_secs(50) && wait <= Duration::from_secs(60));
+    assert_eq!(limiter.try_acquire(4).unwrap_err(), Duration::MAX);
+
+    let mut switchless = 0;
+    for _ in 0..3 {
+        limiter.call_or_switchless(|| panic!("the bucket is empty"), || switchless += 1);
+    }
+    assert_eq!(switchless, 3);
+
+    // The whole bucket of this one refills after the end of the clock.
+    let limiter = RateLimiter::new(u32::MAX, Duration::from_secs(1 << 40));
+    assert!(limiter.try_acquire(1).is_ok());
+    assert!(limiter.try_acquire(1).is_ok());
+    assert_eq!(limiter.try_acquire(u32::MAX).unwrap_err(), Duration::MAX);
+    assert!(limiter.call_or_switchless(|| true, || false));
+
+    let limiter = RateLimiter::new(1, Duration::from_millis(10));
+    limiter.acquire(1);
+    let start = Instant::now();
+    limiter.acquire(1);
+    assert!(start.elapsed() >= Duration::from_millis(5));
+}
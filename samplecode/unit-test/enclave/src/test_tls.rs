@@ -0,0 +1,268 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, TlsProvider, TlsSession, TlsStream};
+use std::string::ToString;
+use std::thread;
+use std::vec::Vec;
+
+const HELLO: u8 = 1;
+const FINISHED: u8 = 2;
+const ALERT: u8 = 3;
+const DATA: u8 = 4;
+const CLOSE: u8 = 5;
+
+const TRUSTED_CERT: &[u8] = b"trusted server certificate";
+
+// A toy TLS session: the client sends its certificate in a hello record and
+// the server accepts it with a finished record or rejects it with an alert.
+// Application data is masked so that it is visibly not sent in the clear.
+struct MockSession {
+    server: bool,
+    handshaking: bool,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+    plaintext: Vec<u8>,
+    close_notify: bool,
+    eof: bool,
+}
+
+impl MockSession {
+    fn client(cert: &[u8]) -> MockSession {
+        let mut session = MockSession::new(false);
+        session.push(HELLO, cert);
+        session
+    }
+
+    fn server() -> MockSession {
+        MockSession::new(true)
+    }
+
+    fn new(server: bool) -> MockSession {
+        MockSession {
+            server,
+            handshaking: true,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+            plaintext: Vec::new(),
+            close_notify: false,
+            eof: false,
+        }
+    }
+
+    fn push(&mut self, ty: u8, payload: &[u8]) {
+        self.outgoing.push(ty);
+        self.outgoing.push(payload.len() as u8);
+        self.outgoing.extend_from_slice(payload);
+    }
+
+    fn check_cert(&mut self, cert: &[u8]) -> io::Result<()> {
+        let msg = if cert.is_empty() {
+            "peer sent no certificate"
+        } else if cert != TRUSTED_CERT {
+            "peer sent an untrusted certificate"
+        } else {
+            self.push(FINISHED, &[]);
+            self.handshaking = false;
+            return Ok(());
+        };
+        self.push(ALERT, &[]);
+        Err(io::Error::new(ErrorKind::InvalidData, msg))
+    }
+}
+
+impl TlsSession for MockSession {
+    fn wants_read(&self) -> bool {
+        !self.close_notify && self.plaintext.is_empty()
+    }
+
+    fn wants_write(&self) -> bool {
+        !self.outgoing.is_empty()
+    }
+
+    fn is_handshaking(&self) -> bool {
+        self.handshaking
+    }
+
+    fn read_tls(&mut self, rd: &mut dyn Read) -> io::Result<usize> {
+        let mut buf = [0; 512];
+        let n = rd.read(&mut buf)?;
+        self.eof |= n == 0;
+        self.incoming.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn write_tls(&mut self, wr: &mut dyn Write) -> io::Result<usize> {
+        let n = wr.write(&self.outgoing)?;
+        self.outgoing.drain(..n);
+        Ok(n)
+    }
+
+    fn process_new_packets(&mut self) -> io::Result<()> {
+        while self.incoming.len() >= 2 && self.incoming.len() >= 2 + self.incoming[1] as usize {
+            let record: Vec<u8> = self
+                .incoming
+                .drain(..2 + self.incoming[1] as usize)
+                .collect();
+            match (record[0], &record[2..]) {
+                (HELLO, cert) if self.server && self.handshaking => self.check_cert(cert)?,
+                (FINISHED, _) if !self.server && self.handshaking => self.handshaking = false,
+                (ALERT, _) => {
+                    return Err(io::Error::new(
+                        ErrorKind::ConnectionAborted,
+                        "peer aborted the handshake",
+                    ))
+                }
+                (DATA, data) if !self.handshaking => {
+                    self.plaintext.extend(data.iter().map(|b| b ^ 0x5a))
+                }
+                (CLOSE, _) => self.close_notify = true,
+                _ => return Err(io::Error::new(ErrorKind::InvalidData, "unexpected record")),
+            }
+        }
+        Ok(())
+    }
+
+    fn read_plaintext(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.plaintext.is_empty() {
+            let n = buf.len().min(self.plaintext.len());
+            buf[..n].copy_from_slice(&self.plaintext[..n]);
+            self.plaintext.drain(..n);
+            Ok(n)
+        } else if self.close_notify {
+            Ok(0)
+        } else if self.eof {
+            Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "peer closed without close_notify",
+            ))
+        } else {
+            Err(io::Error::new(ErrorKind::WouldBlock, "no plaintext yet"))
+        }
+    }
+
+    fn write_plaintext(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let buf = &buf[..buf.len().min(255)];
+        let masked: Vec<u8> = buf.iter().map(|b| b ^ 0x5a).collect();
+        self.push(DATA, &masked);
+        Ok(buf.len())
+    }
+
+    fn send_close_notify(&mut self) {
+        self.push(CLOSE, &[]);
+    }
+}
+
+// Runs `server` on the accepted end of a loopback connection, and returns
+// the client end with what `server` returned.
+fn serve<T, F>(server: F) -> (TcpStream, thread::JoinHandle<T>)
+where
+    T: Send + 'static,
+    F: FnOnce(TcpStream) -> T + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = thread::spawn(move || server(listener.accept().unwrap().0));
+    (TcpStream::connect(addr).unwrap(), handle)
+}
+
+pub fn test_tls_stream_handshake() {
+    let (sock, server) = serve(|sock| {
+        let mut tls = TlsStream::new(MockSession::server(), sock);
+        tls.handshake().unwrap();
+        let mut request = Vec::new();
+        tls.read_to_end(&mut request).unwrap();
+        tls.write_all(b"pong").unwrap();
+        tls.shutdown().unwrap();
+        request
+    });
+
+    let mut tls = TlsStream::new(MockSession::client(TRUSTED_CERT), sock);
+    assert!(tls.session().is_handshaking());
+    tls.handshake().unwrap();
+    assert!(!tls.session().is_handshaking());
+    tls.write_all(b"ping").unwrap();
+    tls.shutdown().unwrap();
+
+    let mut response = Vec::new();
+    tls.read_to_end(&mut response).unwrap();
+    assert_eq!(response, b"pong");
+    assert_eq!(server.join().unwrap(), b"ping");
+}
+
+pub fn test_tls_stream_bad_certificate() {
+    for cert in [&b""[..], &b"self-signed certificate"[..]] {
+        let (sock, server) = serve(|sock| {
+            let mut tls = TlsStream::new(MockSession::server(), sock);
+            tls.handshake().unwrap_err()
+        });
+
+        let mut tls = TlsStream::new(MockSession::client(cert), sock);
+        let err = tls.handshake().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionAborted);
+        assert!(tls.session().is_handshaking());
+        let err = tls.write(b"secret").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+        let err = server.join().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let expected = if cert.is_empty() {
+            "peer sent no certificate"
+        } else {
+            "peer sent an untrusted certificate"
+        };
+        assert_eq!(err.to_string(), expected);
+    }
+}
+
+pub fn test_tls_stream_truncated() {
+    // The peer goes away in the middle of the handshake.
+    let (sock, server) = serve(|mut sock| {
+        let mut hello = [0; 64];
+        sock.read(&mut hello).unwrap()
+    });
+    let mut tls = TlsStream::new(MockSession::client(TRUSTED_CERT), sock);
+    let err = tls.handshake().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(server.join().unwrap(), 2 + TRUSTED_CERT.len());
+
+    // The peer closes the TCP stream without a close_notify alert.
+    let (sock, server) = serve(|sock| {
+        let mut tls = TlsStream::new(MockSession::server(), sock);
+        tls.handshake().unwrap();
+        tls.write_all(b"partial").unwrap();
+        tls.flush().unwrap();
+    });
+    let mut tls = TlsStream::new(MockSession::client(TRUSTED_CERT), sock);
+    tls.handshake().unwrap();
+    server.join().unwrap();
+    let mut data = Vec::new();
+    let err = tls.read_to_end(&mut data).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(data, b"partial");
+}
+
+pub fn test_tls_provider() {
+    let provider = TlsProvider;
+    let mut a = [0u8; 32];
+    let mut b = [0u8; 32];
+    provider.fill(&mut a).unwrap();
+    provider.fill(&mut b).unwrap();
+    assert!(a != b);
+    assert!(provider.current_time().unwrap().as_secs() > 0);
+}
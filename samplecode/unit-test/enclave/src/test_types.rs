@@ -1,7 +1,9 @@
 use sgx_types::metadata::*;
+use sgx_types::quote::*;
 use sgx_types::*;
 
 use core::mem;
+use std::vec::Vec;
 
 pub fn check_metadata_size() {
     assert_eq!(mem::size_of::<layout_group_t>(), 32);
@@ -31,3 +33,104 @@ pub fn check_version() {
         MINOR_VERSION as u64
     );
 }
+
+fn sample_quote_v3(cert_data: &[u8]) -> Vec<u8> {
+    let mut quote = Vec::new();
+    quote.extend_from_slice(&3_u16.to_le_bytes());
+    quote.extend_from_slice(&2_u16.to_le_bytes());
+    quote.extend_from_slice(&[0; 4]);
+    quote.extend_from_slice(&7_u16.to_le_bytes());
+    quote.extend_from_slice(&11_u16.to_le_bytes());
+    quote.extend_from_slice(&[0x93; 16]);
+    quote.extend_from_slice(&[0; 20]);
+    let mut body = [0_u8; 384];
+    body[320..384].copy_from_slice(&[0x5a; 64]);
+    quote.extend_from_slice(&body);
+
+    let mut sig = Vec::new();
+    sig.extend_from_slice(&[1; 64]);
+    sig.extend_from_slice(&[2; 64]);
+    sig.extend_from_slice(&[0; 384]);
+    sig.extend_from_slice(&[3; 64]);
+    sig.extend_from_slice(&2_u16.to_le_bytes());
+    sig.extend_from_slice(&[4; 2]);
+    sig.extend_from_slice(&SGX_QL_CERT_TYPE_PCK_CERT_CHAIN.to_le_bytes());
+    sig.extend_from_slice(&(cert_data.len() as u32).to_le_bytes());
+    sig.extend_from_slice(cert_data);
+    quote.extend_from_slice(&(sig.len() as u32).to_le_bytes());
+    quote.extend_from_slice(&sig);
+    quote
+}
+
+pub fn check_quote_parse() {
+    let raw = sample_quote_v3(b"chain");
+    let quote = SgxQuote::parse(&raw).unwrap();
+    assert_eq!(quote.get_version(), 3);
+    assert_eq!(quote.get_tee_type(), SGX_QUOTE_TEE_TYPE_SGX);
+    assert_eq!(quote.get_qe_svn(), 7);
+    assert_eq!(quote.get_pce_svn(), Some(11));
+    assert_eq!(quote.get_qe_vendor_id(), &[0x93; 16]);
+    assert_eq!(quote.get_signed_data().len(), 48 + 384);
+    assert_eq!(quote.get_report_body().unwrap().report_data.d, [0x5a; 64]);
+    assert_eq!(quote.get_attest_key(), &[2; 64]);
+    assert_eq!(quote.get_qe_report_signature(), &[3; 64]);
+    assert_eq!(quote.get_auth_data(), &[4; 2]);
+    assert_eq!(quote.get_pck_cert_chain(), Some(&b"chain"[..]));
+
+    assert_eq!(
+        SgxQuote::parse(&raw[..raw.len() - 1]).err(),
+        Some(sgx_quote3_error_t::SGX_QL_QUOTE_FORMAT_UNSUPPORTED)
+    );
+    let mut trailing = raw.clone();
+    trailing.push(0);
+    assert!(SgxQuote::parse(&trailing).is_err());
+    let mut epid = raw;
+    epid[2] = 0;
+    assert!(SgxQuote::parse(&epid).is_err());
+}
+
+pub fn check_report_from_bytes() {
+    let mut bytes = [0_u8; 432];
+    bytes[320..384].copy_from_slice(&[0x5a; 64]);
+    bytes[416..432].copy_from_slice(&[0xa5; 16]);
+    let report = sgx_report_t::from_bytes(&bytes).unwrap();
+    assert_eq!(report.body.report_data.d, [0x5a; 64]);
+    assert_eq!(report.mac, [0xa5; 16]);
+    assert!(sgx_report_t::from_bytes(&bytes[..431]).is_none());
+    assert!(sgx_target_info_t::from_bytes(&[0; 512]).is_some());
+    assert!(sgx_report_body_t::from_bytes(&bytes).is_none());
+
+    let text = format!("{}", SgxDisplay(&report));
+    assert!(text.contains(&format!("report_data: {}", "5a".repeat(64))));
+}
+
+pub fn check_secinfo_flags() {
+    assert_eq!(mem::size_of::<secinfo_t>(), 64);
+    assert_eq!(mem::align_of::<secinfo_t>(), 64);
+
+    let secinfo = secinfo_t::new(
+        SecinfoFlags::R | SecinfoFlags::W | SecinfoFlags::PENDING,
+        SecinfoPageType::SS_FIRST,
+    );
+    assert_eq!(secinfo.flags, 0x50b);
+    assert_eq!(secinfo.page_type(), Some(SecinfoPageType::SS_FIRST));
+    assert!(secinfo.access().contains(SecinfoFlags::PENDING));
+    assert!(!secinfo
+        .access()
+        .intersects(SecinfoFlags::X | SecinfoFlags::PR));
+    assert_eq!(
+        secinfo_t::new(SecinfoFlags::R, SecinfoPageType::REG).flags,
+        SI_FLAGS_R
+    );
+
+    assert_eq!(
+        TcsFlags::from_bits(TCS_FLAG_AEXNOTIFY),
+        Some(TcsFlags::AEXNOTIFY)
+    );
+    assert!(TcsFlags::from_bits(0x4).is_none());
+    let attributes = SgxAttributesFlags::from_bits_truncate(0x1000 | SGX_FLAGS_AEX_NOTIFY);
+    assert_eq!(attributes, SgxAttributesFlags::AEX_NOTIFY);
+    assert!(SgxCpuidFeatures::from_bits_truncate(0x803).contains(SgxCpuidFeatures::EDECCSSA));
+    assert_eq!(EncluLeaf::from_repr(9), Some(EncluLeaf::EDECCSSA));
+    assert_eq!(EnclsLeaf::EUPDATESVN.from_key(), 0x18);
+}
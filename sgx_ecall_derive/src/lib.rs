@@ -0,0 +1,279 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Support for `#[sgx_ecall]`, which makes a trusted function a Rust-native ecall.
+//!
+//! The attribute keeps the function and adds a handler of the same name, in the type
+//! namespace, which implements `sgx_trts::ecall::EcallHandler`. The handlers are then
+//! listed in `sgx_trts::sgx_ecall_table!`.
+//!
+//! The function must not be generic, unsafe, async or extern. Its parameters are
+//! `&str`, `&[u8]`, `&mut [u8]`, or types implementing `sgx_trts::ecall::EcallValue`,
+//! as is its return type.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use sgx_ecall_derive::sgx_ecall;
+//!
+//! #[sgx_ecall]
+//! pub fn checksum(data: &[u8]) -> u32 {
+//!     data.iter().map(|b| *b as u32).sum()
+//! }
+//!
+//! sgx_trts::sgx_ecall_table!(checksum);
+//! ```
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+/// `sgx_ecall` adds the ecall handler of the annotated function.
+#[proc_macro_attribute]
+pub fn sgx_ecall(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let expanded = if attr.is_empty() {
+        parse_fn(item.clone()).map(|ecall| ecall.expand())
+    } else {
+        Err("sgx_ecall takes no arguments".to_owned())
+    };
+
+    let mut output = item;
+    match expanded {
+        Ok(handler) => output.extend(handler.parse::<TokenStream>().unwrap()),
+        Err(msg) => output.extend(
+            format!("compile_error!({:?});", msg)
+                .parse::<TokenStream>()
+                .unwrap(),
+        ),
+    }
+    output
+}
+
+enum ParamKind {
+    Str,
+    Bytes,
+    BytesMut,
+    Value(String),
+}
+
+struct Ecall {
+    vis: String,
+    name: String,
+    params: Vec<ParamKind>,
+    ret: Option<String>,
+}
+
+impl Ecall {
+    fn expand(&self) -> String {
+        let mut decode = String::new();
+        let mut args = Vec::new();
+        let mut write_back = String::new();
+        for (i, kind) in self.params.iter().enumerate() {
+            let var = format!("__sgx_arg_{}", i);
+            match kind {
+                ParamKind::Str => {
+                    decode += &format!("let {} = reader.read_str()?;\n", var);
+                    args.push(format!("&{}", var));
+                }
+                ParamKind::Bytes => {
+                    decode += &format!("let {} = reader.read_bytes()?;\n", var);
+                    args.push(format!("&{}", var));
+                }
+                ParamKind::BytesMut => {
+                    decode += &format!("let mut {} = reader.read_bytes()?;\n", var);
+                    args.push(format!("&mut {}[..]", var));
+                    write_back += &format!("output.write_bytes(&{});\n", var);
+                }
+                ParamKind::Value(ty) => {
+                    decode += &format!("let {}: {} = reader.read()?;\n", var, ty);
+                    args.push(var);
+                }
+            }
+        }
+
+        let call = format!("{}({})", self.name, args.join(", "));
+        let call = match self.ret {
+            Some(ref ty) => format!("let ret: {} = {};\noutput.write(&ret);\n", ty, call),
+            None => format!("{};\n", call),
+        };
+
+        format!(
+            "/// The ecall handler of `{name}`.
+            #[allow(non_camel_case_types)]
+            {vis} enum {name} {{}}
+
+            impl ::sgx_trts::ecall::EcallHandler for {name} {{
+                const NAME: &'static str = {name:?};
+
+                fn handle(
+                    input: &[u8],
+                    output: &mut ::sgx_trts::ecall::EcallWriter,
+                ) -> ::sgx_types::SgxError {{
+                    let mut reader = ::sgx_trts::ecall::EcallReader::new(input);
+                    {decode}
+                    reader.finish()?;
+                    {call}
+                    {write_back}
+                    Ok(())
+                }}
+            }}",
+            vis = self.vis,
+            name = self.name,
+            decode = decode,
+            call = call,
+            write_back = write_back,
+        )
+    }
+}
+
+fn parse_fn(item: TokenStream) -> Result<Ecall, String> {
+    let mut tokens = item.into_iter().peekable();
+    let mut vis = String::new();
+
+    // Outer attributes and visibility.
+    loop {
+        match tokens.peek() {
+            Some(TokenTree::Punct(p)) if p.as_char() == '#' => {
+                tokens.next();
+                tokens.next();
+            }
+            Some(TokenTree::Ident(i)) if i.to_string() == "pub" => {
+                vis = "pub".to_owned();
+                tokens.next();
+                if let Some(TokenTree::Group(g)) = tokens.peek() {
+                    if g.delimiter() == Delimiter::Parenthesis {
+                        vis += &g.to_string();
+                        tokens.next();
+                    }
+                }
+            }
+            _ => break,
+        }
+    }
+
+    match tokens.next() {
+        Some(TokenTree::Ident(i)) if i.to_string() == "fn" => {}
+        Some(TokenTree::Ident(i)) if i.to_string() == "const" => match tokens.next() {
+            Some(TokenTree::Ident(i)) if i.to_string() == "fn" => {}
+            _ => return Err("sgx_ecall only applies to functions".to_owned()),
+        },
+        _ => {
+            return Err(
+                "sgx_ecall only applies to safe, non-async, non-extern functions".to_owned(),
+            )
+        }
+    }
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(i)) => i.to_string(),
+        _ => return Err("sgx_ecall expects a function name".to_owned()),
+    };
+    let params = match tokens.next() {
+        Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => {
+            parse_params(g.stream())?
+        }
+        Some(TokenTree::Punct(p)) if p.as_char() == '<' => {
+            return Err("sgx_ecall does not support generic functions".to_owned())
+        }
+        _ => return Err("sgx_ecall expects function parameters".to_owned()),
+    };
+
+    let mut ret = None;
+    if let Some(TokenTree::Punct(p)) = tokens.peek() {
+        if p.as_char() == '-' {
+            tokens.next();
+            tokens.next();
+            let mut ty = TokenStream::new();
+            while let Some(token) = tokens.peek() {
+                match token {
+                    TokenTree::Group(g) if g.delimiter() == Delimiter::Brace => break,
+                    TokenTree::Ident(i) if i.to_string() == "where" => break,
+                    _ => ty.extend(tokens.next()),
+                }
+            }
+            let ty = ty.to_string();
+            if ty.starts_with('&') || ty.starts_with("impl") {
+                return Err(format!("sgx_ecall does not support the return type {}", ty));
+            }
+            if ty.replace(' ', "") != "()" {
+                ret = Some(ty);
+            }
+        }
+    }
+    match tokens.next() {
+        Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => {}
+        _ => return Err("sgx_ecall does not support where clauses".to_owned()),
+    }
+
+    Ok(Ecall {
+        vis,
+        name,
+        params,
+        ret,
+    })
+}
+
+fn parse_params(stream: TokenStream) -> Result<Vec<ParamKind>, String> {
+    let mut params = Vec::new();
+    let mut param = Vec::new();
+    let mut depth = 0_i32;
+    for token in stream {
+        match token {
+            TokenTree::Punct(ref p) if p.as_char() == ',' && depth == 0 => {
+                params.push(parse_param(param)?);
+                param = Vec::new();
+                continue;
+            }
+            TokenTree::Punct(ref p) if p.as_char() == '<' => depth += 1,
+            TokenTree::Punct(ref p) if p.as_char() == '>' => depth -= 1,
+            _ => {}
+        }
+        param.push(token);
+    }
+    if !param.is_empty() {
+        params.push(parse_param(param)?);
+    }
+    Ok(params)
+}
+
+fn parse_param(tokens: Vec<TokenTree>) -> Result<ParamKind, String> {
+    let mut tokens = tokens.into_iter().peekable();
+    if let Some(TokenTree::Ident(i)) = tokens.peek() {
+        if i.to_string() == "mut" {
+            tokens.next();
+        }
+    }
+    match tokens.next() {
+        Some(TokenTree::Ident(i)) if i.to_string() != "self" => {}
+        _ => return Err("sgx_ecall expects parameters of the form `name: Type`".to_owned()),
+    }
+    match tokens.next() {
+        Some(TokenTree::Punct(p)) if p.as_char() == ':' => {}
+        _ => return Err("sgx_ecall expects parameters of the form `name: Type`".to_owned()),
+    }
+
+    let ty = tokens.collect::<TokenStream>().to_string();
+    match ty.replace(' ', "").as_str() {
+        "&str" => Ok(ParamKind::Str),
+        "&[u8]" => Ok(ParamKind::Bytes),
+        "&mut[u8]" => Ok(ParamKind::BytesMut),
+        t if t.starts_with('&') || t.starts_with("impl") => Err(format!(
+            "sgx_ecall does not support the parameter type {}",
+            ty
+        )),
+        _ => Ok(ParamKind::Value(ty)),
+    }
+}
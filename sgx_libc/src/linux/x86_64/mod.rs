@@ -574,6 +574,7 @@ pub const NCCS: usize = 32;
 pub const O_TRUNC: c_int = 512;
 
 pub const O_CLOEXEC: c_int = 0x80000;
+pub const O_TMPFILE: c_int = 0x400000 | O_DIRECTORY;
 
 pub const O_RDONLY: c_int = 0;
 pub const O_WRONLY: c_int = 1;
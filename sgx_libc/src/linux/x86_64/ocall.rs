@@ -65,6 +65,13 @@ extern "C" {
         length: size_t,
         prot: c_int,
     ) -> sgx_status_t;
+    pub fn u_shm_ring_create_ocall(
+        result: *mut *mut c_void,
+        error: *mut c_int,
+        capacity: size_t,
+        direction: uint64_t,
+    ) -> sgx_status_t;
+    pub fn u_shm_ring_release_ocall(ring: *mut c_void) -> sgx_status_t;
     // env
     pub fn u_getuid_ocall(result: *mut uid_t) -> sgx_status_t;
     pub fn u_environ_ocall(result: *mut *const *const c_char) -> sgx_status_t;
@@ -194,6 +201,12 @@ extern "C" {
     ) -> sgx_status_t;
     pub fn u_fsync_ocall(result: *mut c_int, error: *mut c_int, fd: c_int) -> sgx_status_t;
     pub fn u_fdatasync_ocall(result: *mut c_int, error: *mut c_int, fd: c_int) -> sgx_status_t;
+    pub fn u_flock_ocall(
+        result: *mut c_int,
+        error: *mut c_int,
+        fd: c_int,
+        operation: c_int,
+    ) -> sgx_status_t;
     pub fn u_fchmod_ocall(
         result: *mut c_int,
         error: *mut c_int,
@@ -379,6 +392,14 @@ extern "C" {
         len: size_t,
         flags: c_uint,
     ) -> sgx_status_t;
+    pub fn u_copy_through_ocall(
+        result: *mut ssize_t,
+        errno: *mut c_int,
+        fd_in: c_int,
+        fd_out: c_int,
+        buf: *mut c_void,
+        count: size_t,
+    ) -> sgx_status_t;
     pub fn u_fcntl_arg0_ocall(
         result: *mut c_int,
         errno: *mut c_int,
@@ -685,6 +706,23 @@ extern "C" {
     pub fn u_raise_ocall(result: *mut c_int, signum: c_int) -> sgx_status_t;
     //process
     pub fn u_getpid_ocall(result: *mut pid_t) -> sgx_status_t;
+    pub fn u_run_command_ocall(
+        result: *mut c_int,
+        error: *mut c_int,
+        argv: *const c_char,
+        argv_len: size_t,
+        envp: *const c_char,
+        envp_len: size_t,
+        env_clear: c_int,
+        cwd: *const c_char,
+        out_buf: *mut u8,
+        out_cap: size_t,
+        out_len: *mut size_t,
+        err_buf: *mut u8,
+        err_cap: size_t,
+        err_len: *mut size_t,
+        wait_status: *mut c_int,
+    ) -> sgx_status_t;
 }
 
 pub unsafe fn malloc(size: size_t) -> *mut c_void {
@@ -774,6 +812,47 @@ pub unsafe fn munmap(start: *mut c_void, length: size_t) -> c_int {
     result
 }
 
+/// Asks the host to create a shared memory ring with `capacity` bytes of
+/// data. See `SGX_SHM_RING_*` in `sgx_types` for the layout.
+///
+/// Returns null on failure. The ring is checked to be outside the enclave.
+pub unsafe fn shm_ring_create(capacity: size_t, direction: uint64_t) -> *mut c_void {
+    let mut result: *mut c_void = ptr::null_mut();
+    let mut error: c_int = 0;
+
+    let len = match capacity.checked_add(SGX_SHM_RING_HEADER_SIZE) {
+        Some(len) => len,
+        None => {
+            set_errno(EINVAL);
+            return ptr::null_mut();
+        }
+    };
+
+    let status = u_shm_ring_create_ocall(
+        &mut result as *mut *mut c_void,
+        &mut error as *mut c_int,
+        capacity,
+        direction,
+    );
+
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result.is_null() {
+            set_errno(error);
+        } else if sgx_is_outside_enclave(result, len) == 0 {
+            set_errno(ESGX);
+            result = ptr::null_mut();
+        }
+    } else {
+        set_errno(ESGX);
+        result = ptr::null_mut();
+    }
+    result
+}
+
+pub unsafe fn shm_ring_release(ring: *mut c_void) {
+    let _ = u_shm_ring_release_ocall(ring);
+}
+
 pub unsafe fn msync(addr: *mut c_void, length: size_t, flags: c_int) -> c_int {
     let mut result: c_int = 0;
     let mut error: c_int = 0;
@@ -1364,6 +1443,27 @@ pub unsafe fn fdatasync(fd: c_int) -> c_int {
     result
 }
 
+pub unsafe fn flock(fd: c_int, operation: c_int) -> c_int {
+    let mut result: c_int = 0;
+    let mut error: c_int = 0;
+    let status = u_flock_ocall(
+        &mut result as *mut c_int,
+        &mut error as *mut c_int,
+        fd,
+        operation,
+    );
+
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
+
 pub unsafe fn fchmod(fd: c_int, mode: mode_t) -> c_int {
     let mut result: c_int = 0;
     let mut error: c_int = 0;
@@ -2423,6 +2523,42 @@ pub unsafe fn splice(
     result
 }
 
+/// Reads up to `count` bytes from `fd_in` into `buf`, which must be outside
+/// the enclave, and writes them all to `fd_out`, in a single ocall.
+///
+/// Returns the number of bytes copied, or 0 at end of file.
+pub unsafe fn copy_through(fd_in: c_int, fd_out: c_int, buf: *mut c_void, count: size_t) -> ssize_t {
+    let mut result: ssize_t = 0;
+    let mut error: c_int = 0;
+
+    if buf.is_null() || sgx_is_outside_enclave(buf, count) == 0 {
+        set_errno(EINVAL);
+        return -1;
+    }
+
+    let status = u_copy_through_ocall(
+        &mut result as *mut ssize_t,
+        &mut error as *mut c_int,
+        fd_in,
+        fd_out,
+        buf,
+        count,
+    );
+
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+        } else if result < -1 || result as size_t > count {
+            set_errno(ESGX);
+            result = -1;
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
+
 pub unsafe fn fcntl_arg0(fd: c_int, cmd: c_int) -> c_int {
     let mut result: c_int = 0;
     let mut error: c_int = 0;
@@ -3295,6 +3431,11 @@ pub unsafe fn getsockopt(
     let len_in: socklen_t = if !optlen.is_null() { *optlen } else { 0 };
     let mut len_out: socklen_t = 0;
 
+    if !optval.is_null() && sgx_is_within_enclave(optval, len_in as usize) == 0 {
+        set_errno(EINVAL);
+        return -1;
+    }
+
     let status = u_getsockopt_ocall(
         &mut result as *mut c_int,
         &mut error as *mut c_int,
@@ -3309,6 +3450,9 @@ pub unsafe fn getsockopt(
     if status == sgx_status_t::SGX_SUCCESS {
         if result == -1 {
             set_errno(error);
+        } else if len_out > len_in {
+            set_errno(ESGX);
+            result = -1;
         }
     } else {
         set_errno(ESGX);
@@ -3896,3 +4040,46 @@ pub unsafe fn getpid() -> pid_t {
     }
     result
 }
+
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn run_command(
+    argv: &[u8],
+    envp: &[u8],
+    env_clear: bool,
+    cwd: *const c_char,
+    out_buf: &mut [u8],
+    out_len: &mut size_t,
+    err_buf: &mut [u8],
+    err_len: &mut size_t,
+    wait_status: &mut c_int,
+) -> c_int {
+    let mut result: c_int = 0;
+    let mut error: c_int = 0;
+    let status = u_run_command_ocall(
+        &mut result as *mut c_int,
+        &mut error as *mut c_int,
+        argv.as_ptr() as *const c_char,
+        argv.len(),
+        envp.as_ptr() as *const c_char,
+        envp.len(),
+        env_clear as c_int,
+        cwd,
+        out_buf.as_mut_ptr(),
+        out_buf.len(),
+        out_len as *mut size_t,
+        err_buf.as_mut_ptr(),
+        err_buf.len(),
+        err_len as *mut size_t,
+        wait_status as *mut c_int,
+    );
+
+    if status == sgx_status_t::SGX_SUCCESS {
+        if result == -1 {
+            set_errno(error);
+        }
+    } else {
+        set_errno(ESGX);
+        result = -1;
+    }
+    result
+}
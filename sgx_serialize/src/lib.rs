@@ -32,4 +32,5 @@ pub use self::serialize::{Decoder, Encoder, DeSerializable, Serializable, Serial
 
 mod opaque;
 mod leb128;
+mod types;
 
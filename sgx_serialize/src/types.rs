@@ -0,0 +1,44 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Encoding of the SGX hardware structures as their byte layout
+//!
+
+use crate::serialize::{Decoder, DeSerializable, Encoder, Serializable};
+use sgx_types::{sgx_key_request_t, sgx_report_body_t, sgx_report_t, sgx_target_info_t};
+use std::vec::Vec;
+
+macro_rules! impl_serialize_bytes {
+    ($($t:ty),*) => {$(
+        impl Serializable for $t {
+            fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+                self.as_bytes().encode(s)
+            }
+        }
+
+        impl DeSerializable for $t {
+            fn decode<D: Decoder>(d: &mut D) -> Result<$t, D::Error> {
+                let bytes: Vec<u8> = DeSerializable::decode(d)?;
+                <$t>::from_bytes(&bytes)
+                    .ok_or_else(|| d.error(concat!("wrong length for ", stringify!($t))))
+            }
+        }
+    )*}
+}
+
+impl_serialize_bytes!(sgx_report_body_t, sgx_report_t, sgx_target_info_t, sgx_key_request_t);
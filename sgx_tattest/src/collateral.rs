@@ -0,0 +1,661 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! TCB info and QE identity collateral
+//!
+//! Intel(R) PCS publishes, per FMSPC, the TCB info listing the TCB levels of the
+//! platform and their status, and the identity of the Quoting Enclave with the
+//! status of its ISVSVNs. Both are JSON documents of the form
+//! `{"tcbInfo": {...}, "signature": "..."}` and `{"enclaveIdentity": {...},
+//! "signature": "..."}`, where the signature is an ECDSA P-256 signature over the
+//! exact bytes of the first member, made by the TCB signing key.
+//!
+use crate::json::Json;
+use crate::pck::{
+    pem_certificates, verify_chain, SGX_ROOT_CA_COMMON_NAME, SGX_TCB_SIGNING_COMMON_NAME,
+};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use sgx_tcrypto::{SgxEccHandle, SgxX509Certificate, SgxX509Crl, SgxX509PublicKey};
+use sgx_types::*;
+
+///
+/// A TCB level of the TCB info.
+///
+#[derive(Clone)]
+pub struct SgxTcbLevel {
+    components: [u8; 16],
+    pce_svn: u16,
+    tcb_date: u64,
+    status: sgx_ql_qv_result_t,
+    advisory_ids: Vec<String>,
+}
+
+impl SgxTcbLevel {
+    /// The minimum SVNs of the 16 SGX TCB components.
+    pub fn get_components(&self) -> &[u8; 16] {
+        &self.components
+    }
+
+    /// The minimum PCE SVN.
+    pub fn get_pce_svn(&self) -> u16 {
+        self.pce_svn
+    }
+
+    /// The date of the level, in seconds since the Unix epoch.
+    pub fn get_tcb_date(&self) -> u64 {
+        self.tcb_date
+    }
+
+    /// The status of platforms at this level.
+    pub fn get_status(&self) -> sgx_ql_qv_result_t {
+        self.status
+    }
+
+    /// The Intel security advisories that apply to this level.
+    pub fn get_advisory_ids(&self) -> &[String] {
+        &self.advisory_ids
+    }
+
+    ///
+    /// Whether a platform certified with the given TCB components and PCE SVN is at
+    /// this level or above.
+    ///
+    pub fn matches(&self, components: &[u8; 16], pce_svn: u16) -> bool {
+        components
+            .iter()
+            .zip(self.components.iter())
+            .all(|(c, l)| c >= l)
+            && pce_svn >= self.pce_svn
+    }
+}
+
+///
+/// The TCB info of an FMSPC, versions 2 and 3.
+///
+#[derive(Clone)]
+pub struct SgxTcbInfo {
+    version: u32,
+    issue_date: u64,
+    next_update: u64,
+    fmspc: [u8; 6],
+    pce_id: [u8; 2],
+    tcb_evaluation_data_number: u32,
+    levels: Vec<SgxTcbLevel>,
+    signed: Vec<u8>,
+    signature: [u8; 64],
+}
+
+impl SgxTcbInfo {
+    ///
+    /// Parses a signed TCB info document. The signature is not checked.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_QL_TCBINFO_UNSUPPORTED_FORMAT**
+    ///
+    /// The document is malformed, is of another version, or is not for SGX.
+    ///
+    pub fn parse(document: &[u8]) -> SgxQuote3Result<SgxTcbInfo> {
+        Self::from_json(document).ok_or(sgx_quote3_error_t::SGX_QL_TCBINFO_UNSUPPORTED_FORMAT)
+    }
+
+    fn from_json(document: &[u8]) -> Option<SgxTcbInfo> {
+        let doc = Json::parse(document)?;
+        let info = doc.get("tcbInfo")?;
+        let signature = signature(doc.get("signature")?)?;
+        let version = small(info.get("version")?)?;
+        if version != 2 && version != 3 {
+            return None;
+        }
+        if version == 3 && info.get("id")?.as_str()? != "SGX" {
+            return None;
+        }
+
+        let mut levels = Vec::new();
+        for level in info.get("tcbLevels")?.as_array()? {
+            let tcb = level.get("tcb")?;
+            let mut components = [0_u8; 16];
+            if version == 2 {
+                for (i, c) in components.iter_mut().enumerate() {
+                    let key = format!("sgxtcbcomp{:02}svn", i + 1);
+                    *c = small(tcb.get(&key)?)?.try_into().ok()?;
+                }
+            } else {
+                let list = tcb.get("sgxtcbcomponents")?.as_array()?;
+                if list.len() != components.len() {
+                    return None;
+                }
+                for (c, item) in components.iter_mut().zip(list) {
+                    *c = small(item.get("svn")?)?.try_into().ok()?;
+                }
+            }
+            levels.push(SgxTcbLevel {
+                components,
+                pce_svn: small(tcb.get("pcesvn")?)?.try_into().ok()?,
+                tcb_date: parse_date(level.get("tcbDate")?.as_str()?)?,
+                status: tcb_status(level.get("tcbStatus")?.as_str()?)?,
+                advisory_ids: advisory_ids(level.get("advisoryIDs"))?,
+            });
+        }
+
+        Some(SgxTcbInfo {
+            version,
+            issue_date: parse_date(info.get("issueDate")?.as_str()?)?,
+            next_update: parse_date(info.get("nextUpdate")?.as_str()?)?,
+            fmspc: hex_array(info.get("fmspc")?.as_str()?)?,
+            pce_id: hex_array(info.get("pceId")?.as_str()?)?,
+            tcb_evaluation_data_number: small(info.get("tcbEvaluationDataNumber")?)?,
+            levels,
+            signed: info.raw().to_vec(),
+            signature,
+        })
+    }
+
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    /// The issue date, in seconds since the Unix epoch.
+    pub fn get_issue_date(&self) -> u64 {
+        self.issue_date
+    }
+
+    /// The date by which a newer TCB info is published, in seconds since the Unix epoch.
+    pub fn get_next_update(&self) -> u64 {
+        self.next_update
+    }
+
+    pub fn get_fmspc(&self) -> &[u8; 6] {
+        &self.fmspc
+    }
+
+    pub fn get_pce_id(&self) -> &[u8; 2] {
+        &self.pce_id
+    }
+
+    pub fn get_tcb_evaluation_data_number(&self) -> u32 {
+        self.tcb_evaluation_data_number
+    }
+
+    /// The TCB levels, highest first.
+    pub fn get_levels(&self) -> &[SgxTcbLevel] {
+        &self.levels
+    }
+
+    ///
+    /// Returns the highest level a platform certified with the given TCB components
+    /// and PCE SVN is at.
+    ///
+    pub fn find_level(&self, components: &[u8; 16], pce_svn: u16) -> Option<&SgxTcbLevel> {
+        self.levels.iter().find(|l| l.matches(components, pce_svn))
+    }
+
+    // Checks the signature against the signing chain, see verify_signed.
+    pub(crate) fn verify(
+        &self,
+        issuer_chain: &[u8],
+        root: &SgxX509PublicKey,
+        root_ca_crl: &SgxX509Crl,
+        time: u64,
+    ) -> SgxQuote3Result<bool> {
+        verify_signed(
+            &self.signed,
+            &self.signature,
+            issuer_chain,
+            root,
+            root_ca_crl,
+            time,
+            sgx_quote3_error_t::SGX_QL_TCBINFO_CHAIN_ERROR,
+        )
+    }
+}
+
+///
+/// A TCB level of the QE identity.
+///
+#[derive(Clone)]
+pub struct SgxQeTcbLevel {
+    isv_svn: u16,
+    tcb_date: u64,
+    status: sgx_ql_qv_result_t,
+    advisory_ids: Vec<String>,
+}
+
+impl SgxQeTcbLevel {
+    pub fn get_isv_svn(&self) -> u16 {
+        self.isv_svn
+    }
+
+    pub fn get_tcb_date(&self) -> u64 {
+        self.tcb_date
+    }
+
+    pub fn get_status(&self) -> sgx_ql_qv_result_t {
+        self.status
+    }
+
+    pub fn get_advisory_ids(&self) -> &[String] {
+        &self.advisory_ids
+    }
+}
+
+///
+/// The identity of the Quoting Enclave, version 2.
+///
+#[derive(Clone)]
+pub struct SgxQeIdentity {
+    issue_date: u64,
+    next_update: u64,
+    misc_select: u32,
+    misc_select_mask: u32,
+    attributes: [u8; 16],
+    attributes_mask: [u8; 16],
+    mr_signer: [u8; 32],
+    isv_prod_id: u16,
+    levels: Vec<SgxQeTcbLevel>,
+    signed: Vec<u8>,
+    signature: [u8; 64],
+}
+
+impl SgxQeIdentity {
+    ///
+    /// Parses a signed QE identity document. The signature is not checked.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_QL_QEIDENTITY_UNSUPPORTED_FORMAT**
+    ///
+    /// The document is malformed or of another version.
+    ///
+    pub fn parse(document: &[u8]) -> SgxQuote3Result<SgxQeIdentity> {
+        Self::from_json(document).ok_or(sgx_quote3_error_t::SGX_QL_QEIDENTITY_UNSUPPORTED_FORMAT)
+    }
+
+    fn from_json(document: &[u8]) -> Option<SgxQeIdentity> {
+        let doc = Json::parse(document)?;
+        let identity = doc.get("enclaveIdentity")?;
+        let signature = signature(doc.get("signature")?)?;
+        if small(identity.get("version")?)? != 2 {
+            return None;
+        }
+        if let Some(id) = identity.get("id") {
+            if id.as_str()? != "QE" {
+                return None;
+            }
+        }
+
+        let mut levels = Vec::new();
+        for level in identity.get("tcbLevels")?.as_array()? {
+            levels.push(SgxQeTcbLevel {
+                isv_svn: small(level.get("tcb")?.get("isvsvn")?)?.try_into().ok()?,
+                tcb_date: parse_date(level.get("tcbDate")?.as_str()?)?,
+                status: tcb_status(level.get("tcbStatus")?.as_str()?)?,
+                advisory_ids: advisory_ids(level.get("advisoryIDs"))?,
+            });
+        }
+
+        let misc: [u8; 4] = hex_array(identity.get("miscselect")?.as_str()?)?;
+        let misc_mask: [u8; 4] = hex_array(identity.get("miscselectMask")?.as_str()?)?;
+        Some(SgxQeIdentity {
+            issue_date: parse_date(identity.get("issueDate")?.as_str()?)?,
+            next_update: parse_date(identity.get("nextUpdate")?.as_str()?)?,
+            // MISCSELECT is written as the big-endian hex of the 32-bit value.
+            misc_select: u32::from_be_bytes(misc),
+            misc_select_mask: u32::from_be_bytes(misc_mask),
+            // ATTRIBUTES are written in the byte order of the report.
+            attributes: hex_array(identity.get("attributes")?.as_str()?)?,
+            attributes_mask: hex_array(identity.get("attributesMask")?.as_str()?)?,
+            mr_signer: hex_array(identity.get("mrsigner")?.as_str()?)?,
+            isv_prod_id: small(identity.get("isvprodid")?)?.try_into().ok()?,
+            levels,
+            signed: identity.raw().to_vec(),
+            signature,
+        })
+    }
+
+    pub fn get_issue_date(&self) -> u64 {
+        self.issue_date
+    }
+
+    pub fn get_next_update(&self) -> u64 {
+        self.next_update
+    }
+
+    pub fn get_mr_signer(&self) -> &[u8; 32] {
+        &self.mr_signer
+    }
+
+    pub fn get_isv_prod_id(&self) -> u16 {
+        self.isv_prod_id
+    }
+
+    pub fn get_levels(&self) -> &[SgxQeTcbLevel] {
+        &self.levels
+    }
+
+    ///
+    /// Whether a QE report body has the MRSIGNER, ISVPRODID, and masked MISCSELECT
+    /// and ATTRIBUTES of the identity.
+    ///
+    pub fn matches(&self, body: &sgx_report_body_t) -> bool {
+        let mut attributes = [0_u8; 16];
+        attributes[..8].copy_from_slice(&body.attributes.flags.to_le_bytes());
+        attributes[8..].copy_from_slice(&body.attributes.xfrm.to_le_bytes());
+        let attributes_match = attributes
+            .iter()
+            .zip(self.attributes.iter())
+            .zip(self.attributes_mask.iter())
+            .all(|((a, e), m)| a & m == e & m);
+        attributes_match
+            && (body.misc_select & self.misc_select_mask)
+                == (self.misc_select & self.misc_select_mask)
+            && body.mr_signer.m == self.mr_signer
+            && body.isv_prod_id == self.isv_prod_id
+    }
+
+    /// Returns the highest level a QE with the given ISVSVN is at.
+    pub fn find_level(&self, isv_svn: u16) -> Option<&SgxQeTcbLevel> {
+        self.levels.iter().find(|l| isv_svn >= l.isv_svn)
+    }
+
+    pub(crate) fn verify(
+        &self,
+        issuer_chain: &[u8],
+        root: &SgxX509PublicKey,
+        root_ca_crl: &SgxX509Crl,
+        time: u64,
+    ) -> SgxQuote3Result<bool> {
+        verify_signed(
+            &self.signed,
+            &self.signature,
+            issuer_chain,
+            root,
+            root_ca_crl,
+            time,
+            sgx_quote3_error_t::SGX_QL_QEIDENTITY_CHAIN_ERROR,
+        )
+    }
+}
+
+// Checks that the PEM chain is the TCB Signing certificate and the root CA, that
+// it leads to root and that the signing certificate signed the data. Any other
+// chain, such as a PCK chain, fails with error, and so does a signing certificate
+// revoked by the root CA CRL. Returns whether the chain is valid at time.
+fn verify_signed(
+    signed: &[u8],
+    signature: &[u8; 64],
+    issuer_chain: &[u8],
+    root: &SgxX509PublicKey,
+    root_ca_crl: &SgxX509Crl,
+    time: u64,
+    error: sgx_quote3_error_t,
+) -> SgxQuote3Result<bool> {
+    let ders = pem_certificates(issuer_chain).ok_or(error)?;
+    let certs = match ders.as_slice() {
+        [signing, root] => [
+            SgxX509Certificate::parse(signing).map_err(|_| error)?,
+            SgxX509Certificate::parse(root).map_err(|_| error)?,
+        ],
+        _ => return Err(error),
+    };
+    if certs[0].subject().get_common_name() != Some(SGX_TCB_SIGNING_COMMON_NAME)
+        || certs[1].subject().get_common_name() != Some(SGX_ROOT_CA_COMMON_NAME)
+    {
+        return Err(error);
+    }
+    let valid = verify_chain(&certs, root, &[root_ca_crl], time, error).map_err(|e| match e {
+        sgx_quote3_error_t::SGX_QL_PCK_REVOKED => error,
+        e => e,
+    })?;
+    match certs[0].public_key() {
+        Some(SgxX509PublicKey::EcP256(key)) if ecdsa_p256_verify(signed, key, signature)? => {
+            Ok(valid)
+        }
+        _ => Err(error),
+    }
+}
+
+///
+/// Verifies an ECDSA P-256 signature given as big-endian r || s.
+///
+pub(crate) fn ecdsa_p256_verify(
+    data: &[u8],
+    key: &sgx_ec256_public_t,
+    signature: &[u8; 64],
+) -> SgxQuote3Result<bool> {
+    let mut sig = sgx_ec256_signature_t::default();
+    words_from_be(&signature[..32], &mut sig.x);
+    words_from_be(&signature[32..], &mut sig.y);
+    let handle = SgxEccHandle::new();
+    handle
+        .open()
+        .and_then(|_| handle.ecdsa_verify_slice(data, key, &sig))
+        .map_err(|_| sgx_quote3_error_t::SGX_QL_ERROR_UNEXPECTED)
+}
+
+// The SGX crypto library keeps big numbers as little-endian 32-bit words.
+fn words_from_be(be: &[u8], words: &mut [u32; 8]) {
+    for (i, w) in words.iter_mut().enumerate() {
+        let end = be.len() - 4 * i;
+        *w = u32::from_be_bytes([be[end - 4], be[end - 3], be[end - 2], be[end - 1]]);
+    }
+}
+
+///
+/// Converts a big-endian x || y P-256 point into the little-endian form of the SGX
+/// crypto library.
+///
+pub(crate) fn ec256_public_from_be(point: &[u8; 64]) -> sgx_ec256_public_t {
+    let mut key = sgx_ec256_public_t::default();
+    for i in 0..32 {
+        key.gx[i] = point[31 - i];
+        key.gy[i] = point[63 - i];
+    }
+    key
+}
+
+fn small(value: &Json) -> Option<u32> {
+    value.as_u64()?.try_into().ok()
+}
+
+fn advisory_ids(value: Option<&Json>) -> Option<Vec<String>> {
+    let mut ids = Vec::new();
+    if let Some(list) = value {
+        for id in list.as_array()? {
+            ids.push(String::from(id.as_str()?));
+        }
+    }
+    Some(ids)
+}
+
+// The TCB status named as in the collateral.
+pub(crate) fn tcb_status(status: &str) -> Option<sgx_ql_qv_result_t> {
+    Some(match status {
+        "UpToDate" => sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OK,
+        "SWHardeningNeeded" => sgx_ql_qv_result_t::SGX_QL_QV_RESULT_SW_HARDENING_NEEDED,
+        "ConfigurationNeeded" => sgx_ql_qv_result_t::SGX_QL_QV_RESULT_CONFIG_NEEDED,
+        "ConfigurationAndSWHardeningNeeded" => {
+            sgx_ql_qv_result_t::SGX_QL_QV_RESULT_CONFIG_AND_SW_HARDENING_NEEDED
+        }
+        "OutOfDate" => sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OUT_OF_DATE,
+        "OutOfDateConfigurationNeeded" => {
+            sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OUT_OF_DATE_CONFIG_NEEDED
+        }
+        "Revoked" => sgx_ql_qv_result_t::SGX_QL_QV_RESULT_REVOKED,
+        _ => return None,
+    })
+}
+
+// The name of a TCB status in the collateral.
+pub(crate) fn tcb_status_name(status: sgx_ql_qv_result_t) -> Option<&'static str> {
+    Some(match status {
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OK => "UpToDate",
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_SW_HARDENING_NEEDED => "SWHardeningNeeded",
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_CONFIG_NEEDED => "ConfigurationNeeded",
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_CONFIG_AND_SW_HARDENING_NEEDED => {
+            "ConfigurationAndSWHardeningNeeded"
+        }
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OUT_OF_DATE => "OutOfDate",
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OUT_OF_DATE_CONFIG_NEEDED => {
+            "OutOfDateConfigurationNeeded"
+        }
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_REVOKED => "Revoked",
+        _ => return None,
+    })
+}
+
+fn signature(value: &Json) -> Option<[u8; 64]> {
+    hex_array(value.as_str()?)
+}
+
+pub(crate) fn hex_array<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    let hex = hex.as_bytes();
+    if hex.len() != 2 * N {
+        return None;
+    }
+    let mut out = [0_u8; N];
+    for (o, pair) in out.iter_mut().zip(hex.chunks(2)) {
+        let hi = (pair[0] as char).to_digit(16)?;
+        let lo = (pair[1] as char).to_digit(16)?;
+        *o = (hi * 16 + lo) as u8;
+    }
+    Some(out)
+}
+
+///
+/// Parses an RFC 3339 UTC time such as `2023-06-14T10:36:13Z` into seconds since the
+/// Unix epoch. Fractions of a second are dropped.
+///
+pub(crate) fn parse_date(date: &str) -> Option<u64> {
+    let b = date.as_bytes();
+    if b.len() < 20
+        || b[4] != b'-'
+        || b[7] != b'-'
+        || b[10] != b'T'
+        || b[13] != b':'
+        || b[16] != b':'
+    {
+        return None;
+    }
+    let num = |range: core::ops::Range<usize>| -> Option<u64> {
+        b[range].iter().try_fold(0_u64, |acc, c| {
+            (*c as char).to_digit(10).map(|d| acc * 10 + d as u64)
+        })
+    };
+    let (year, month, day) = (num(0..4)?, num(5..7)?, num(8..10)?);
+    let (hour, minute, second) = (num(11..13)?, num(14..16)?, num(17..19)?);
+    let mut rest = &b[19..];
+    if rest[0] == b'.' {
+        let digits = rest[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        rest = &rest[1 + digits..];
+    }
+    if rest != b"Z" || year < 1970 || !(1..=12).contains(&month) || day == 0 || day > 31 {
+        return None;
+    }
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // Days from 1970-01-01 of the proleptic Gregorian calendar.
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+// Formats bytes in hex for the Debug impls.
+pub(crate) struct Hex<'a>(pub(crate) &'a [u8]);
+
+impl fmt::Debug for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.0 {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SgxTcbLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxTcbLevel")
+            .field("components", &self.components)
+            .field("pce_svn", &self.pce_svn)
+            .field("tcb_date", &self.tcb_date)
+            .field("status", &self.status)
+            .field("advisory_ids", &self.advisory_ids)
+            .finish()
+    }
+}
+
+impl fmt::Debug for SgxTcbInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxTcbInfo")
+            .field("version", &self.version)
+            .field("issue_date", &self.issue_date)
+            .field("next_update", &self.next_update)
+            .field("fmspc", &Hex(&self.fmspc))
+            .field("pce_id", &Hex(&self.pce_id))
+            .field(
+                "tcb_evaluation_data_number",
+                &self.tcb_evaluation_data_number,
+            )
+            .field("levels", &self.levels)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for SgxQeTcbLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxQeTcbLevel")
+            .field("isv_svn", &self.isv_svn)
+            .field("tcb_date", &self.tcb_date)
+            .field("status", &self.status)
+            .field("advisory_ids", &self.advisory_ids)
+            .finish()
+    }
+}
+
+impl fmt::Debug for SgxQeIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxQeIdentity")
+            .field("issue_date", &self.issue_date)
+            .field("next_update", &self.next_update)
+            .field("misc_select", &format_args!("{:#010x}", self.misc_select))
+            .field(
+                "misc_select_mask",
+                &format_args!("{:#010x}", self.misc_select_mask),
+            )
+            .field("attributes", &Hex(&self.attributes))
+            .field("attributes_mask", &Hex(&self.attributes_mask))
+            .field("mr_signer", &Hex(&self.mr_signer))
+            .field("isv_prod_id", &self.isv_prod_id)
+            .field("levels", &self.levels)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,417 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! EPID revocation lists and platform info
+//!
+//! A relying party still on EPID gets two blobs from the attestation service that the
+//! enclave has to act on: the signature revocation list (SigRL) of the EPID group,
+//! passed to the QE when quoting, and the platform info blob (PIB) of a verification
+//! report, which says whether the platform needs an update. Both are parsed here, in
+//! the enclave, so the decisions they drive are not left to untrusted code:
+//!
+//! * `SgxSigRl` checks that a SigRL is well formed, of the group of the platform and
+//!   not older than one seen before.
+//!
+//! * `SgxPlatformInfo` reads the evaluation flags of a PIB and reports the updates the
+//!   platform needs as a `SgxPlatformStatus`.
+//!
+//! Both blobs are signed by the attestation service. The signatures can be checked
+//! against the key of the service, which the application pins.
+//!
+use crate::collateral::{ecdsa_p256_verify, hex_array};
+use sgx_types::*;
+
+const SIGRL_SVER: u16 = 2;
+const SIGRL_BLOB_ID: u16 = 14;
+const SIGRL_HEADER_SIZE: usize = 16;
+const SIGNATURE_SIZE: usize = 64;
+
+/// The size of an entry of a SigRL, the B and K elements of a revoked signature.
+pub const SGX_SIGRL_ENTRY_SIZE: usize = 128;
+
+const PIB_TLV_TYPE: u8 = 21;
+const PIB_TLV_VERSION: u8 = 2;
+const PIB_TLV_HEADER_SIZE: usize = 4;
+const PIB_SIGNED_SIZE: usize = SGX_PLATFORM_INFO_SIZE - SIGNATURE_SIZE;
+
+/// The EPID group of the QE is revoked.
+pub const SGX_PIB_QE_EPID_GROUP_REVOKED: u8 = 0x01;
+/// A performance rekey is available for the EPID group.
+pub const SGX_PIB_PERF_REKEY_FOR_QE_EPID_GROUP_AVAILABLE: u8 = 0x02;
+/// The EPID group is out of date and the platform has to be provisioned again.
+pub const SGX_PIB_QE_EPID_GROUP_OUT_OF_DATE: u8 = 0x04;
+
+/// The CPU SVN of the quote is out of date.
+pub const SGX_PIB_QUOTE_CPUSVN_OUT_OF_DATE: u16 = 0x0001;
+/// The ISV SVN of the QE is out of date.
+pub const SGX_PIB_QUOTE_ISVSVN_QE_OUT_OF_DATE: u16 = 0x0002;
+/// The ISV SVN of the PCE is out of date.
+pub const SGX_PIB_QUOTE_ISVSVN_PCE_OUT_OF_DATE: u16 = 0x0004;
+/// The platform needs additional configuration.
+pub const SGX_PIB_PLATFORM_CONFIGURATION_NEEDED: u16 = 0x0008;
+
+/// The ISV SVN of the PSE is out of date.
+pub const SGX_PIB_PSE_ISVSVN_OUT_OF_DATE: u16 = 0x0001;
+/// The EPID group of the PS hardware is revoked.
+pub const SGX_PIB_EPID_GROUP_ID_BY_PS_HW_GID_REVOKED: u16 = 0x0002;
+/// The SVN of the PS hardware security info is out of date.
+pub const SGX_PIB_SVN_FROM_PS_HW_SEC_INFO_OUT_OF_DATE: u16 = 0x0004;
+/// The SigRL version of the PS hardware is out of date.
+pub const SGX_PIB_SIGRL_VER_FROM_PS_HW_SIG_RLVER_OUT_OF_DATE: u16 = 0x0008;
+/// The PrivRL version of the PS hardware is out of date.
+pub const SGX_PIB_PRIVRL_VER_FROM_PS_HW_PRV_KEY_RLVER_OUT_OF_DATE: u16 = 0x0010;
+
+///
+/// A parsed EPID signature revocation list.
+///
+/// The list is kept as the bytes it was parsed from, which are what the QE takes.
+///
+#[derive(Clone, Copy)]
+pub struct SgxSigRl<'a> {
+    raw: &'a [u8],
+}
+
+impl<'a> SgxSigRl<'a> {
+    ///
+    /// Parses a SigRL as returned by the attestation service, after base64 decoding.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The list is not an EPID 2.0 SigRL, or its entry count does not match its size.
+    ///
+    pub fn parse(raw: &'a [u8]) -> SgxResult<SgxSigRl<'a>> {
+        if raw.len() < SIGRL_HEADER_SIZE + SIGNATURE_SIZE
+            || u16::from_be_bytes([raw[0], raw[1]]) != SIGRL_SVER
+            || u16::from_be_bytes([raw[2], raw[3]]) != SIGRL_BLOB_ID
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let count = u32::from_be_bytes([raw[12], raw[13], raw[14], raw[15]]) as usize;
+        let size = count
+            .checked_mul(SGX_SIGRL_ENTRY_SIZE)
+            .and_then(|entries| entries.checked_add(SIGRL_HEADER_SIZE + SIGNATURE_SIZE));
+        if size != Some(raw.len()) {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(SgxSigRl { raw })
+    }
+
+    ///
+    /// Get the bytes of the list.
+    ///
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    ///
+    /// Get the EPID group of the list, in the little-endian order of msg1 and quotes.
+    ///
+    pub fn get_group_id(&self) -> sgx_epid_group_id_t {
+        [self.raw[7], self.raw[6], self.raw[5], self.raw[4]]
+    }
+
+    ///
+    /// Get the version of the list, which grows with every update.
+    ///
+    pub fn get_version(&self) -> u32 {
+        u32::from_be_bytes([self.raw[8], self.raw[9], self.raw[10], self.raw[11]])
+    }
+
+    ///
+    /// Get the number of revoked signatures.
+    ///
+    pub fn get_entry_count(&self) -> usize {
+        (self.raw.len() - SIGRL_HEADER_SIZE - SIGNATURE_SIZE) / SGX_SIGRL_ENTRY_SIZE
+    }
+
+    ///
+    /// Get the revoked signatures, SGX_SIGRL_ENTRY_SIZE bytes each.
+    ///
+    pub fn get_entries(&self) -> impl Iterator<Item = &'a [u8]> {
+        self.raw[SIGRL_HEADER_SIZE..self.raw.len() - SIGNATURE_SIZE].chunks(SGX_SIGRL_ENTRY_SIZE)
+    }
+
+    ///
+    /// Checks that the list is of the group of the platform and at least of the version
+    /// of a list seen before, so the host cannot roll it back to one that does not
+    /// revoke a signature.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The list is for another EPID group.
+    ///
+    /// **SGX_ERROR_INVALID_VERSION**
+    ///
+    /// The list is older than min_version.
+    ///
+    pub fn check(&self, group_id: &sgx_epid_group_id_t, min_version: u32) -> SgxError {
+        if self.get_group_id() != *group_id {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if self.get_version() < min_version {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_VERSION);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Verifies the signature of the attestation service over the list.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The crypto library failed.
+    ///
+    pub fn verify_signature(&self, key: &sgx_ec256_public_t) -> SgxResult<bool> {
+        let (signed, signature) = self.raw.split_at(self.raw.len() - SIGNATURE_SIZE);
+        verify(signed, signature, key)
+    }
+}
+
+///
+/// The updates a platform needs, from its platform info blob.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SgxPlatformUpdate {
+    /// The CPU microcode or BIOS is out of date.
+    pub ucode_update: bool,
+    /// The CSME firmware is out of date.
+    pub csme_fw_update: bool,
+    /// The platform software, or the provisioning of the EPID group, is out of date.
+    pub psw_update: bool,
+    /// The platform needs additional configuration.
+    pub configuration_needed: bool,
+}
+
+impl SgxPlatformUpdate {
+    ///
+    /// Whether any update is needed.
+    ///
+    pub fn is_needed(&self) -> bool {
+        self.ucode_update || self.csme_fw_update || self.psw_update || self.configuration_needed
+    }
+
+    ///
+    /// Converts to the update info of sgx_report_attestation_status.
+    ///
+    pub fn to_update_info_bit(&self) -> sgx_update_info_bit_t {
+        sgx_update_info_bit_t {
+            ucodeUpdate: self.ucode_update as i32,
+            csmeFwUpdate: self.csme_fw_update as i32,
+            pswUpdate: self.psw_update as i32,
+        }
+    }
+}
+
+///
+/// What a platform info blob says about the platform.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SgxPlatformStatus {
+    /// The platform is up to date.
+    UpToDate,
+    /// The platform is trusted until the updates are applied.
+    UpdateNeeded(SgxPlatformUpdate),
+    /// The EPID group of the platform is revoked. The updates, if any, may restore it.
+    Revoked(SgxPlatformUpdate),
+}
+
+///
+/// A parsed EPID platform info blob.
+///
+/// Like the rest of the blobs of the attestation service, its fields are big-endian.
+///
+#[derive(Clone, Copy)]
+pub struct SgxPlatformInfo {
+    info: sgx_platform_info_t,
+}
+
+impl SgxPlatformInfo {
+    ///
+    /// Parses a platform info blob, either with the TLV header of the attestation
+    /// service or as the bare sgx_platform_info_t.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The blob is not of the size of a platform info blob, or its TLV header is not of
+    /// a platform info blob.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<SgxPlatformInfo> {
+        let blob = match bytes.len() {
+            SGX_PLATFORM_INFO_SIZE => bytes,
+            n if n == PIB_TLV_HEADER_SIZE + SGX_PLATFORM_INFO_SIZE => {
+                if bytes[0] != PIB_TLV_TYPE
+                    || bytes[1] != PIB_TLV_VERSION
+                    || u16::from_be_bytes([bytes[2], bytes[3]]) as usize != SGX_PLATFORM_INFO_SIZE
+                {
+                    return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+                }
+                &bytes[PIB_TLV_HEADER_SIZE..]
+            }
+            _ => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        };
+        let mut info = sgx_platform_info_t::default();
+        info.platform_info.copy_from_slice(blob);
+        Ok(SgxPlatformInfo { info })
+    }
+
+    ///
+    /// Parses the hex `platformInfoBlob` of an attestation verification report.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The string is not the hex of a platform info blob with its TLV header.
+    ///
+    pub fn from_hex(hex: &str) -> SgxResult<SgxPlatformInfo> {
+        let bytes = hex_array::<{ PIB_TLV_HEADER_SIZE + SGX_PLATFORM_INFO_SIZE }>(hex)
+            .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+        Self::from_bytes(&bytes)
+    }
+
+    ///
+    /// Get the blob in the form sgx_report_attestation_status takes.
+    ///
+    pub fn get_platform_info(&self) -> &sgx_platform_info_t {
+        &self.info
+    }
+
+    ///
+    /// Get the EPID group flags, SGX_PIB_QE_EPID_GROUP_*.
+    ///
+    pub fn get_epid_group_flags(&self) -> u8 {
+        self.info.platform_info[0]
+    }
+
+    ///
+    /// Get the TCB evaluation flags, SGX_PIB_QUOTE_* and configuration needed.
+    ///
+    pub fn get_tcb_evaluation_flags(&self) -> u16 {
+        u16::from_be_bytes([self.info.platform_info[1], self.info.platform_info[2]])
+    }
+
+    ///
+    /// Get the PSE evaluation flags.
+    ///
+    pub fn get_pse_evaluation_flags(&self) -> u16 {
+        u16::from_be_bytes([self.info.platform_info[3], self.info.platform_info[4]])
+    }
+
+    ///
+    /// Get the latest equivalent TCB PSVN, the CPU SVN followed by the PCE SVN.
+    ///
+    pub fn get_latest_equivalent_tcb_psvn(&self) -> &[u8] {
+        &self.info.platform_info[5..23]
+    }
+
+    ///
+    /// Get the latest ISV SVN of the PSE.
+    ///
+    pub fn get_latest_pse_isvsvn(&self) -> u16 {
+        u16::from_be_bytes([self.info.platform_info[23], self.info.platform_info[24]])
+    }
+
+    ///
+    /// Get the latest PSDA SVN.
+    ///
+    pub fn get_latest_psda_svn(&self) -> u32 {
+        let b = &self.info.platform_info[25..29];
+        u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+    }
+
+    ///
+    /// Get the extended EPID group ID.
+    ///
+    pub fn get_xeid(&self) -> u32 {
+        let b = &self.info.platform_info[29..33];
+        u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+    }
+
+    ///
+    /// Get the EPID group of the platform, in the little-endian order of msg1 and quotes.
+    ///
+    pub fn get_group_id(&self) -> sgx_epid_group_id_t {
+        let b = &self.info.platform_info[33..37];
+        [b[3], b[2], b[1], b[0]]
+    }
+
+    ///
+    /// Get the updates the platform needs.
+    ///
+    pub fn get_update(&self) -> SgxPlatformUpdate {
+        let group = self.get_epid_group_flags();
+        let tcb = self.get_tcb_evaluation_flags();
+        let pse = self.get_pse_evaluation_flags();
+        SgxPlatformUpdate {
+            ucode_update: tcb & SGX_PIB_QUOTE_CPUSVN_OUT_OF_DATE != 0,
+            csme_fw_update: pse
+                & (SGX_PIB_EPID_GROUP_ID_BY_PS_HW_GID_REVOKED
+                    | SGX_PIB_SVN_FROM_PS_HW_SEC_INFO_OUT_OF_DATE
+                    | SGX_PIB_SIGRL_VER_FROM_PS_HW_SIG_RLVER_OUT_OF_DATE
+                    | SGX_PIB_PRIVRL_VER_FROM_PS_HW_PRV_KEY_RLVER_OUT_OF_DATE)
+                != 0,
+            psw_update: tcb
+                & (SGX_PIB_QUOTE_ISVSVN_QE_OUT_OF_DATE | SGX_PIB_QUOTE_ISVSVN_PCE_OUT_OF_DATE)
+                != 0
+                || pse & SGX_PIB_PSE_ISVSVN_OUT_OF_DATE != 0
+                || group & SGX_PIB_QE_EPID_GROUP_OUT_OF_DATE != 0,
+            configuration_needed: tcb & SGX_PIB_PLATFORM_CONFIGURATION_NEEDED != 0,
+        }
+    }
+
+    ///
+    /// Get the status of the platform.
+    ///
+    pub fn get_status(&self) -> SgxPlatformStatus {
+        let update = self.get_update();
+        if self.get_epid_group_flags() & SGX_PIB_QE_EPID_GROUP_REVOKED != 0 {
+            SgxPlatformStatus::Revoked(update)
+        } else if update.is_needed() {
+            SgxPlatformStatus::UpdateNeeded(update)
+        } else {
+            SgxPlatformStatus::UpToDate
+        }
+    }
+
+    ///
+    /// Verifies the signature of the attestation service over the blob.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The crypto library failed.
+    ///
+    pub fn verify_signature(&self, key: &sgx_ec256_public_t) -> SgxResult<bool> {
+        let (signed, signature) = self.info.platform_info.split_at(PIB_SIGNED_SIZE);
+        verify(signed, signature, key)
+    }
+}
+
+fn verify(signed: &[u8], signature: &[u8], key: &sgx_ec256_public_t) -> SgxResult<bool> {
+    let mut sig = [0_u8; SIGNATURE_SIZE];
+    sig.copy_from_slice(signature);
+    ecdsa_p256_verify(signed, key, &sig).map_err(|_| sgx_status_t::SGX_ERROR_UNEXPECTED)
+}
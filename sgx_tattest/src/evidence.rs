@@ -0,0 +1,435 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! Attestation evidence
+//!
+//! Protocols such as RA-TLS and secret provisioning carry evidence of an enclave
+//! without depending on its kind. SgxEvidence gives access to the claims every kind
+//! of SGX evidence has, which are those of the report body, and SgxAnyEvidence holds
+//! any of them.
+//!
+//! The claims are those the evidence states; nothing is verified here. A relying
+//! party verifies the evidence with the verifier of its kind before using them.
+//!
+//! Evidence is exchanged in an envelope that names its kind: the CBOR record form of
+//! the RATS conceptual message wrapper, as carried in EAT submodules, which is the
+//! two-element array `[media-type: tstr, evidence: bstr]`.
+//!
+use crate::verify::parse_quote_v3;
+use alloc::vec::Vec;
+use core::mem;
+use core::ptr;
+use core::slice;
+use sgx_types::*;
+
+///
+/// The kind of an evidence.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SgxEvidenceFormat {
+    /// A report, for verification on the same platform.
+    Report,
+    /// An EPID quote, version 1 or 2.
+    EpidQuote,
+    /// An ECDSA quote, version 3.
+    EcdsaQuote,
+}
+
+impl SgxEvidenceFormat {
+    ///
+    /// Get the media type of the envelope.
+    ///
+    pub fn media_type(self) -> &'static str {
+        match self {
+            SgxEvidenceFormat::Report => "application/vnd.intel.sgx.report",
+            SgxEvidenceFormat::EpidQuote => "application/vnd.intel.sgx.epid-quote",
+            SgxEvidenceFormat::EcdsaQuote => "application/vnd.intel.sgx.ecdsa-quote",
+        }
+    }
+
+    ///
+    /// Returns the format of a media type.
+    ///
+    pub fn from_media_type(media_type: &str) -> Option<SgxEvidenceFormat> {
+        [
+            SgxEvidenceFormat::Report,
+            SgxEvidenceFormat::EpidQuote,
+            SgxEvidenceFormat::EcdsaQuote,
+        ]
+        .into_iter()
+        .find(|f| f.media_type() == media_type)
+    }
+}
+
+///
+/// Evidence of an enclave.
+///
+pub trait SgxEvidence {
+    /// The kind of the evidence.
+    fn format(&self) -> SgxEvidenceFormat;
+
+    /// The evidence as produced by the platform.
+    fn as_bytes(&self) -> &[u8];
+
+    /// The report body of the enclave, as stated by the evidence.
+    fn report_body(&self) -> &sgx_report_body_t;
+
+    /// The report data of the enclave, as stated by the evidence.
+    fn report_data(&self) -> &sgx_report_data_t {
+        &self.report_body().report_data
+    }
+
+    /// The evidence in its envelope.
+    fn to_envelope(&self) -> Vec<u8> {
+        let media_type = self.format().media_type().as_bytes();
+        let evidence = self.as_bytes();
+        let mut out = Vec::with_capacity(media_type.len() + evidence.len() + 16);
+        out.push(0x82);
+        cbor_head(&mut out, CBOR_TEXT, media_type.len());
+        out.extend_from_slice(media_type);
+        cbor_head(&mut out, CBOR_BYTES, evidence.len());
+        out.extend_from_slice(evidence);
+        out
+    }
+}
+
+///
+/// A report as evidence.
+///
+#[derive(Clone)]
+pub struct SgxReportEvidence {
+    raw: Vec<u8>,
+    report: sgx_report_t,
+}
+
+impl SgxReportEvidence {
+    pub fn new(report: &sgx_report_t) -> SgxReportEvidence {
+        let raw = unsafe {
+            slice::from_raw_parts(
+                report as *const sgx_report_t as *const u8,
+                mem::size_of::<sgx_report_t>(),
+            )
+        };
+        SgxReportEvidence {
+            raw: raw.to_vec(),
+            report: *report,
+        }
+    }
+
+    ///
+    /// Parses a report.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The size is not that of sgx_report_t.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<SgxReportEvidence> {
+        if bytes.len() != mem::size_of::<sgx_report_t>() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let report = unsafe { ptr::read_unaligned(bytes.as_ptr() as *const sgx_report_t) };
+        Ok(SgxReportEvidence {
+            raw: bytes.to_vec(),
+            report,
+        })
+    }
+
+    pub fn get_report(&self) -> &sgx_report_t {
+        &self.report
+    }
+}
+
+impl SgxEvidence for SgxReportEvidence {
+    fn format(&self) -> SgxEvidenceFormat {
+        SgxEvidenceFormat::Report
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.raw
+    }
+
+    fn report_body(&self) -> &sgx_report_body_t {
+        &self.report.body
+    }
+}
+
+///
+/// An EPID quote as evidence.
+///
+#[derive(Clone)]
+pub struct SgxEpidQuoteEvidence {
+    raw: Vec<u8>,
+    body: sgx_report_body_t,
+}
+
+impl SgxEpidQuoteEvidence {
+    ///
+    /// Parses an EPID quote.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The quote is not of version 1 or 2, or its signature length does not match
+    /// its size.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<SgxEpidQuoteEvidence> {
+        let header_size = mem::size_of::<sgx_quote_t>();
+        if bytes.len() < header_size {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let quote = unsafe { ptr::read_unaligned(bytes.as_ptr() as *const sgx_quote_t) };
+        let (version, signature_len) = (quote.version, quote.signature_len);
+        if !(1..=2).contains(&version) || signature_len as usize != bytes.len() - header_size {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(SgxEpidQuoteEvidence {
+            raw: bytes.to_vec(),
+            body: quote.report_body,
+        })
+    }
+
+    ///
+    /// Get the linkable or unlinkable signature type.
+    ///
+    pub fn get_sign_type(&self) -> u16 {
+        u16::from_le_bytes([self.raw[2], self.raw[3]])
+    }
+
+    ///
+    /// Get the EPID group of the platform.
+    ///
+    pub fn get_epid_group_id(&self) -> sgx_epid_group_id_t {
+        [self.raw[4], self.raw[5], self.raw[6], self.raw[7]]
+    }
+}
+
+impl SgxEvidence for SgxEpidQuoteEvidence {
+    fn format(&self) -> SgxEvidenceFormat {
+        SgxEvidenceFormat::EpidQuote
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.raw
+    }
+
+    fn report_body(&self) -> &sgx_report_body_t {
+        &self.body
+    }
+}
+
+///
+/// An ECDSA quote as evidence.
+///
+#[derive(Clone)]
+pub struct SgxEcdsaQuoteEvidence {
+    raw: Vec<u8>,
+    body: sgx_report_body_t,
+}
+
+impl SgxEcdsaQuoteEvidence {
+    ///
+    /// Parses an ECDSA quote.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The quote is not a well formed version 3 ECDSA P-256 quote.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<SgxEcdsaQuoteEvidence> {
+        let quote = parse_quote_v3(bytes).map_err(|_| sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+        Ok(SgxEcdsaQuoteEvidence {
+            raw: bytes.to_vec(),
+            body: quote.get_report_body().unwrap(),
+        })
+    }
+}
+
+impl SgxEvidence for SgxEcdsaQuoteEvidence {
+    fn format(&self) -> SgxEvidenceFormat {
+        SgxEvidenceFormat::EcdsaQuote
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.raw
+    }
+
+    fn report_body(&self) -> &sgx_report_body_t {
+        &self.body
+    }
+}
+
+///
+/// Evidence of any kind.
+///
+#[derive(Clone)]
+pub enum SgxAnyEvidence {
+    Report(SgxReportEvidence),
+    EpidQuote(SgxEpidQuoteEvidence),
+    EcdsaQuote(SgxEcdsaQuoteEvidence),
+}
+
+impl SgxAnyEvidence {
+    ///
+    /// Parses evidence of the given kind.
+    ///
+    /// # Errors
+    ///
+    /// The errors of the from_bytes function of the kind.
+    ///
+    pub fn from_bytes(format: SgxEvidenceFormat, bytes: &[u8]) -> SgxResult<SgxAnyEvidence> {
+        Ok(match format {
+            SgxEvidenceFormat::Report => {
+                SgxAnyEvidence::Report(SgxReportEvidence::from_bytes(bytes)?)
+            }
+            SgxEvidenceFormat::EpidQuote => {
+                SgxAnyEvidence::EpidQuote(SgxEpidQuoteEvidence::from_bytes(bytes)?)
+            }
+            SgxEvidenceFormat::EcdsaQuote => {
+                SgxAnyEvidence::EcdsaQuote(SgxEcdsaQuoteEvidence::from_bytes(bytes)?)
+            }
+        })
+    }
+
+    ///
+    /// Parses evidence in its envelope.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The envelope is not a canonically encoded record of a known media type, or the
+    /// evidence is malformed.
+    ///
+    pub fn from_envelope(envelope: &[u8]) -> SgxResult<SgxAnyEvidence> {
+        let (format, evidence) =
+            open_envelope(envelope).ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+        Self::from_bytes(format, evidence)
+    }
+
+    fn inner(&self) -> &dyn SgxEvidence {
+        match self {
+            SgxAnyEvidence::Report(e) => e,
+            SgxAnyEvidence::EpidQuote(e) => e,
+            SgxAnyEvidence::EcdsaQuote(e) => e,
+        }
+    }
+}
+
+impl SgxEvidence for SgxAnyEvidence {
+    fn format(&self) -> SgxEvidenceFormat {
+        self.inner().format()
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        self.inner().as_bytes()
+    }
+
+    fn report_body(&self) -> &sgx_report_body_t {
+        self.inner().report_body()
+    }
+}
+
+impl From<SgxReportEvidence> for SgxAnyEvidence {
+    fn from(evidence: SgxReportEvidence) -> SgxAnyEvidence {
+        SgxAnyEvidence::Report(evidence)
+    }
+}
+
+impl From<SgxEpidQuoteEvidence> for SgxAnyEvidence {
+    fn from(evidence: SgxEpidQuoteEvidence) -> SgxAnyEvidence {
+        SgxAnyEvidence::EpidQuote(evidence)
+    }
+}
+
+impl From<SgxEcdsaQuoteEvidence> for SgxAnyEvidence {
+    fn from(evidence: SgxEcdsaQuoteEvidence) -> SgxAnyEvidence {
+        SgxAnyEvidence::EcdsaQuote(evidence)
+    }
+}
+
+const CBOR_BYTES: u8 = 2 << 5;
+const CBOR_TEXT: u8 = 3 << 5;
+
+fn cbor_head(out: &mut Vec<u8>, major: u8, len: usize) {
+    if len < 24 {
+        out.push(major | len as u8);
+    } else if len <= u8::MAX as usize {
+        out.extend_from_slice(&[major | 24, len as u8]);
+    } else if len <= u16::MAX as usize {
+        out.push(major | 25);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else if len <= u32::MAX as usize {
+        out.push(major | 26);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+}
+
+// Reads a definite length string of the major type, in the shortest encoding.
+fn cbor_string<'a>(input: &mut &'a [u8], major: u8) -> Option<&'a [u8]> {
+    let (&head, rest) = input.split_first()?;
+    if head & 0xe0 != major {
+        return None;
+    }
+    let (len, rest, min) = match head & 0x1f {
+        n @ 0..=23 => (n as u64, rest, 0),
+        24 => (*rest.first()? as u64, rest.get(1..)?, 24),
+        25 => (
+            u16::from_be_bytes(rest.get(..2)?.try_into().ok()?) as u64,
+            &rest[2..],
+            0x100,
+        ),
+        26 => (
+            u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as u64,
+            &rest[4..],
+            0x1_0000,
+        ),
+        27 => (
+            u64::from_be_bytes(rest.get(..8)?.try_into().ok()?),
+            &rest[8..],
+            0x1_0000_0000,
+        ),
+        _ => return None,
+    };
+    if len < min {
+        return None;
+    }
+    let len = usize::try_from(len).ok()?;
+    let value = rest.get(..len)?;
+    *input = &rest[len..];
+    Some(value)
+}
+
+fn open_envelope(envelope: &[u8]) -> Option<(SgxEvidenceFormat, &[u8])> {
+    let (&head, mut rest) = envelope.split_first()?;
+    if head != 0x82 {
+        return None;
+    }
+    let media_type = core::str::from_utf8(cbor_string(&mut rest, CBOR_TEXT)?).ok()?;
+    let format = SgxEvidenceFormat::from_media_type(media_type)?;
+    let evidence = cbor_string(&mut rest, CBOR_BYTES)?;
+    if !rest.is_empty() {
+        return None;
+    }
+    Some((format, evidence))
+}
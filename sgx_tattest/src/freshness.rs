@@ -0,0 +1,273 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! Attestation freshness
+//!
+//! A quote proves what an enclave is, not when it said so. Unless the report data
+//! binds a value the relying party chose for this exchange, a quote recorded once can
+//! be replayed forever. The helpers here bind a nonce of the relying party, a
+//! timestamp and the application data into the report data under a domain, so that a
+//! quote made for one protocol or one exchange is not accepted in another:
+//!
+//! 1. The relying party issues a nonce with `SgxFreshnessVerifier::issue_nonce`.
+//! 2. The enclave computes its report data with `SgxFreshness::bind` and has it quoted.
+//! 3. After verifying the quote, the relying party checks the report data with
+//!    `SgxFreshnessVerifier::check`, which accepts every nonce once and only within
+//!    the freshness window.
+//!
+//! The report data binds the domain, the nonce, the timestamp and the data with
+//! `SgxReportData`, in a domain of its own.
+//!
+use crate::report_data::SgxReportData;
+use alloc::vec::Vec;
+use sgx_tcrypto::*;
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_types::*;
+
+const FRESHNESS_DOMAIN: &[u8] = b"sgx_tattest freshness";
+
+/// The size of the nonces issued by `SgxFreshnessVerifier`.
+pub const SGX_FRESHNESS_NONCE_SIZE: usize = 32;
+/// The number of nonces a verifier keeps outstanding by default.
+pub const SGX_FRESHNESS_DEFAULT_CAPACITY: usize = 1024;
+
+///
+/// The freshness claim an enclave binds into its report data.
+///
+#[derive(Clone, Copy, Debug)]
+pub struct SgxFreshness<'a> {
+    domain: &'a [u8],
+    nonce: &'a [u8],
+    timestamp: u64,
+}
+
+impl<'a> SgxFreshness<'a> {
+    ///
+    /// Create a claim for the nonce of the relying party, in a domain that names the
+    /// protocol and its version.
+    ///
+    pub fn new(domain: &'a [u8], nonce: &'a [u8]) -> SgxFreshness<'a> {
+        SgxFreshness {
+            domain,
+            nonce,
+            timestamp: 0,
+        }
+    }
+
+    ///
+    /// Include a timestamp, in seconds since the Unix epoch. The enclave has no trusted
+    /// time, so this is the time of the caller; the relying party checks it against its
+    /// own clock.
+    ///
+    pub fn with_timestamp(mut self, timestamp: u64) -> SgxFreshness<'a> {
+        self.timestamp = timestamp;
+        self
+    }
+
+    ///
+    /// Get the domain of the claim.
+    ///
+    pub fn get_domain(&self) -> &'a [u8] {
+        self.domain
+    }
+
+    ///
+    /// Get the nonce of the claim.
+    ///
+    pub fn get_nonce(&self) -> &'a [u8] {
+        self.nonce
+    }
+
+    ///
+    /// Get the timestamp of the claim, 0 if it has none.
+    ///
+    pub fn get_timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    ///
+    /// Compute the report data that binds the claim and `data`.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The nonce is empty.
+    ///
+    /// The errors of SgxReportData::bind_in.
+    ///
+    pub fn bind(&self, data: &[u8]) -> SgxResult<sgx_report_data_t> {
+        if self.nonce.is_empty() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let timestamp = self.timestamp.to_le_bytes();
+        SgxReportData::bind_in(
+            FRESHNESS_DOMAIN,
+            &[self.domain, self.nonce, &timestamp, data],
+        )
+    }
+
+    ///
+    /// Check that the report data binds the claim and `data`.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The report data was made for another claim or other data.
+    ///
+    pub fn verify(&self, report_data: &sgx_report_data_t, data: &[u8]) -> SgxError {
+        if self.nonce.is_empty() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let timestamp = self.timestamp.to_le_bytes();
+        SgxReportData::verify_in(
+            FRESHNESS_DOMAIN,
+            report_data,
+            &[self.domain, self.nonce, &timestamp, data],
+        )
+    }
+}
+
+///
+/// The relying party side: issues nonces and accepts each of them once, within a
+/// freshness window.
+///
+/// Nonces older than the window are dropped, and when more than the capacity are
+/// outstanding the oldest are dropped first, so memory stays bounded however many are
+/// issued.
+///
+pub struct SgxFreshnessVerifier {
+    domain: Vec<u8>,
+    window: u64,
+    max_skew: u64,
+    capacity: usize,
+    outstanding: Vec<([u8; SGX_FRESHNESS_NONCE_SIZE], u64)>,
+}
+
+impl SgxFreshnessVerifier {
+    ///
+    /// Create a verifier for a domain that accepts evidence up to `window` seconds after
+    /// the nonce was issued.
+    ///
+    pub fn new(domain: &[u8], window: u64) -> SgxFreshnessVerifier {
+        SgxFreshnessVerifier {
+            domain: domain.to_vec(),
+            window,
+            max_skew: 0,
+            capacity: SGX_FRESHNESS_DEFAULT_CAPACITY,
+            outstanding: Vec::new(),
+        }
+    }
+
+    ///
+    /// Tolerate timestamps up to `seconds` ahead of the clock of the verifier, or before
+    /// the nonce was issued.
+    ///
+    pub fn max_skew(mut self, seconds: u64) -> SgxFreshnessVerifier {
+        self.max_skew = seconds;
+        self
+    }
+
+    ///
+    /// Set the number of nonces kept outstanding.
+    ///
+    pub fn capacity(mut self, capacity: usize) -> SgxFreshnessVerifier {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    ///
+    /// Get the number of nonces issued and not yet presented or expired.
+    ///
+    pub fn get_outstanding(&self) -> usize {
+        self.outstanding.len()
+    }
+
+    ///
+    /// Issue a nonce at `current_time`, in seconds since the Unix epoch.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The random number generator failed.
+    ///
+    pub fn issue_nonce(&mut self, current_time: u64) -> SgxResult<[u8; SGX_FRESHNESS_NONCE_SIZE]> {
+        self.expire(current_time);
+        let mut nonce = [0_u8; SGX_FRESHNESS_NONCE_SIZE];
+        rsgx_read_rand(&mut nonce)?;
+        if self.outstanding.len() >= self.capacity {
+            let excess = self.outstanding.len() + 1 - self.capacity;
+            self.outstanding.drain(..excess);
+        }
+        self.outstanding.push((nonce, current_time));
+        Ok(nonce)
+    }
+
+    ///
+    /// Check the report data of verified evidence at `current_time`.
+    ///
+    /// The nonce is consumed whatever the outcome, so it can be presented only once.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// The nonce was not issued by this verifier, was already presented, or expired.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The timestamp is before the nonce was issued or after `current_time`, beyond the
+    /// allowed skew.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The report data does not bind the nonce, the timestamp and `data`.
+    ///
+    pub fn check(
+        &mut self,
+        report_data: &sgx_report_data_t,
+        nonce: &[u8],
+        timestamp: u64,
+        data: &[u8],
+        current_time: u64,
+    ) -> SgxError {
+        self.expire(current_time);
+        let issued = self
+            .outstanding
+            .iter()
+            .position(|(n, _)| rsgx_ct_eq(n, nonce))
+            .map(|i| self.outstanding.remove(i).1)
+            .ok_or(sgx_status_t::SGX_ERROR_INVALID_STATE)?;
+
+        if timestamp.saturating_add(self.max_skew) < issued
+            || timestamp > current_time.saturating_add(self.max_skew)
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        SgxFreshness::new(&self.domain, nonce)
+            .with_timestamp(timestamp)
+            .verify(report_data, data)
+    }
+
+    fn expire(&mut self, current_time: u64) {
+        let window = self.window;
+        self.outstanding
+            .retain(|(_, issued)| current_time.saturating_sub(*issued) <= window);
+    }
+}
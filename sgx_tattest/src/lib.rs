@@ -0,0 +1,113 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! # Trusted Attestation Library
+//!
+//! The library provides functions for producing attestation evidence inside the enclave.
+//!
+//! * `SgxQuoteGenerator` and `rsgx_get_ecdsa_quote` produce an ECDSA (DCAP) quote over
+//!   caller supplied report data. The untrusted side is provided by sgx_urts with the
+//!   `quote` feature, and the enclave imports `sgx_quote.edl`. `SgxQuoteBackend` selects
+//!   AESM or, for hosts without AESM, the DCAP quote library.
+//!
+//! * `SgxQuoteVerifier` verifies an ECDSA quote inside the enclave against the PCK
+//!   certificate chain, TCB info and QE identity, rooted in a pinned root CA, and checks
+//!   revocation with the CRLs of the hierarchy. `SgxPckCertChain` validates a PCK
+//!   certificate chain on its own.
+//!   `rsgx_verify_quote_with_qve` has the QvE verify it and checks the QvE report; its
+//!   untrusted side is the `quote_verify` feature of sgx_urts.
+//!
+//! * `SgxRaTlsCertificate` issues a self-signed certificate for a fresh key with a quote
+//!   over the key in an extension, and `rsgx_ratls_verify_certificate` verifies one.
+//!
+//! * `SgxEvidence` abstracts over reports, EPID quotes and ECDSA quotes, and wraps them in
+//!   an envelope that names their kind.
+//!
+//! * Quotes are parsed with `sgx_types::quote::SgxQuote`. `SgxPckCertExtensions::from_quote`
+//!   reads the FMSPC and the PCE SVN from a quote, and the TCB info and QE identity
+//!   types format as Debug for inspection.
+//!
+//! * `SgxProvisionClient` and `SgxProvisionSession` provision secrets from a service to
+//!   an enclave that proves its identity with a quote, under a `SgxProvisionPolicy` of
+//!   the service.
+//!
+//! * `SgxTcbPolicy` decides whether a verification result is acceptable, by TCB status,
+//!   advisories and collateral age, under a policy that can be read from JSON.
+//!
+//! * `SgxReportData` binds public keys, transcripts or policy hashes into the report data
+//!   under a domain, and the relying party recomputes it to check them.
+//!
+//! * `SgxFreshness` binds a nonce of the relying party, a timestamp and application data
+//!   into the report data, and `SgxFreshnessVerifier` accepts each nonce once and only
+//!   within a freshness window, so that recorded quotes cannot be replayed.
+//!
+//! * `SgxSigRl` and `SgxPlatformInfo` parse the EPID signature revocation list and the
+//!   platform info blob of the attestation service, and report the updates a platform
+//!   needs as a `SgxPlatformStatus`.
+//!
+
+#![no_std]
+#![cfg_attr(
+    all(target_env = "sgx", target_vendor = "mesalock"),
+    feature(rustc_private)
+)]
+#![allow(non_camel_case_types)]
+#![allow(clippy::missing_safety_doc)]
+
+#[macro_use]
+extern crate alloc;
+
+extern crate sgx_tcrypto;
+extern crate sgx_trts;
+extern crate sgx_tse;
+extern crate sgx_tseal;
+extern crate sgx_types;
+
+mod json;
+
+mod quote;
+pub use self::quote::*;
+
+mod pck;
+pub use self::pck::*;
+
+mod collateral;
+pub use self::collateral::*;
+
+mod verify;
+pub use self::verify::*;
+
+mod ratls;
+pub use self::ratls::*;
+
+mod evidence;
+pub use self::evidence::*;
+
+mod provision;
+pub use self::provision::*;
+
+mod policy;
+pub use self::policy::*;
+
+mod report_data;
+pub use self::report_data::*;
+
+mod freshness;
+pub use self::freshness::*;
+
+mod epid;
+pub use self::epid::*;
@@ -0,0 +1,598 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! PCK certificates
+//!
+//! The Provisioning Certification Key (PCK) certificate of a platform is issued by
+//! the Intel(R) SGX PCK Platform or Processor CA, which chains to the Intel SGX Root
+//! CA. Its SGX extension carries the FMSPC and the TCB level of the platform, which
+//! select the applicable entry of the TCB info.
+//!
+//! SgxPckCertChain validates the chain along the lines of the Intel PCK hierarchy,
+//! with a pinned root key and the CRLs of the root CA and of the PCK CA, so that no
+//! chain building is left to the untrusted side.
+//!
+use crate::collateral::Hex;
+use alloc::vec::Vec;
+use core::fmt;
+use sgx_tcrypto::{SgxX509Certificate, SgxX509Crl, SgxX509PublicKey};
+use sgx_types::quote::SgxQuote;
+use sgx_types::*;
+
+/// The OID of the SGX extension of PCK certificates, 1.2.840.113741.1.13.1.
+pub const SGX_PCK_EXTENSION_OID: &[u64] = &[1, 2, 840, 113741, 1, 13, 1];
+
+/// The common name of the Intel SGX Root CA.
+pub const SGX_ROOT_CA_COMMON_NAME: &str = "Intel SGX Root CA";
+/// The common name of the CA issuing the PCK certificates of multi-package platforms.
+pub const SGX_PCK_PLATFORM_CA_COMMON_NAME: &str = "Intel SGX PCK Platform CA";
+/// The common name of the CA issuing the PCK certificates of single-package platforms.
+pub const SGX_PCK_PROCESSOR_CA_COMMON_NAME: &str = "Intel SGX PCK Processor CA";
+/// The common name of PCK certificates.
+pub const SGX_PCK_CERT_COMMON_NAME: &str = "Intel SGX PCK Certificate";
+/// The common name of the certificate signing TCB info and QE identities.
+pub const SGX_TCB_SIGNING_COMMON_NAME: &str = "Intel SGX TCB Signing";
+
+const OID_SGX_EXTENSION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01];
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_ENUMERATED: u8 = 0x0a;
+const TAG_SEQUENCE: u8 = 0x30;
+
+///
+/// The content of the SGX extension of a PCK certificate.
+///
+#[derive(Clone, Copy, Default)]
+pub struct SgxPckCertExtensions {
+    ppid: [u8; 16],
+    tcb_components: [u8; 16],
+    pce_svn: u16,
+    cpu_svn: sgx_cpu_svn_t,
+    pce_id: [u8; 2],
+    fmspc: [u8; 6],
+    sgx_type: u8,
+}
+
+impl SgxPckCertExtensions {
+    ///
+    /// Parses the SGX extension of a PCK certificate.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_QL_PCK_CERT_UNSUPPORTED_FORMAT**
+    ///
+    /// The certificate has no SGX extension, or it is malformed or incomplete.
+    ///
+    pub fn parse(cert: &SgxX509Certificate) -> SgxQuote3Result<SgxPckCertExtensions> {
+        let (_, value) = cert
+            .extension(SGX_PCK_EXTENSION_OID)
+            .ok_or(sgx_quote3_error_t::SGX_QL_PCK_CERT_UNSUPPORTED_FORMAT)?;
+        Self::from_der(value).ok_or(sgx_quote3_error_t::SGX_QL_PCK_CERT_UNSUPPORTED_FORMAT)
+    }
+
+    ///
+    /// Parses the SGX extension of the PCK certificate in the certification data of
+    /// a quote. The certificate chain is not verified.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_QL_QUOTE_CERTIFICATION_DATA_UNSUPPORTED**
+    ///
+    /// The quote does not carry the PCK certificate chain.
+    ///
+    /// **SGX_QL_PCK_CERT_CHAIN_ERROR**
+    ///
+    /// The certificate chain is malformed.
+    ///
+    /// **SGX_QL_PCK_CERT_UNSUPPORTED_FORMAT**
+    ///
+    /// The PCK certificate has no SGX extension, or it is malformed or incomplete.
+    ///
+    pub fn from_quote(quote: &SgxQuote) -> SgxQuote3Result<SgxPckCertExtensions> {
+        let chain = quote
+            .get_pck_cert_chain()
+            .ok_or(sgx_quote3_error_t::SGX_QL_QUOTE_CERTIFICATION_DATA_UNSUPPORTED)?;
+        let ders =
+            pem_certificates(chain).ok_or(sgx_quote3_error_t::SGX_QL_PCK_CERT_CHAIN_ERROR)?;
+        let cert = SgxX509Certificate::parse(&ders[0])
+            .map_err(|_| sgx_quote3_error_t::SGX_QL_PCK_CERT_CHAIN_ERROR)?;
+        Self::parse(&cert)
+    }
+
+    fn from_der(value: &[u8]) -> Option<SgxPckCertExtensions> {
+        let mut ext = SgxPckCertExtensions::default();
+        // Bit i is set once arc 1.2.840.113741.1.13.1.i has been seen.
+        let mut seen = 0_u32;
+        let mut outer = Der::new(value);
+        let mut list = Der::new(outer.read(TAG_SEQUENCE)?);
+        outer.finish()?;
+        while !list.is_empty() {
+            let mut entry = Der::new(list.read(TAG_SEQUENCE)?);
+            let arc = sub_arc(entry.read(TAG_OID)?, OID_SGX_EXTENSION);
+            match arc {
+                Some(1) => copy_exact(&mut ext.ppid, entry.read(TAG_OCTET_STRING)?)?,
+                Some(2) => ext.read_tcb(entry.read(TAG_SEQUENCE)?)?,
+                Some(3) => copy_exact(&mut ext.pce_id, entry.read(TAG_OCTET_STRING)?)?,
+                Some(4) => copy_exact(&mut ext.fmspc, entry.read(TAG_OCTET_STRING)?)?,
+                Some(5) => ext.sgx_type = small_uint(entry.read(TAG_ENUMERATED)?)? as u8,
+                // Later arcs, such as the platform instance ID, are not used here.
+                _ => {
+                    entry.skip()?;
+                }
+            }
+            entry.finish()?;
+            if let Some(arc) = arc.filter(|arc| *arc < 32) {
+                seen |= 1 << arc;
+            }
+        }
+        if seen & 0b11_1110 != 0b11_1110 {
+            return None;
+        }
+        Some(ext)
+    }
+
+    fn read_tcb(&mut self, content: &[u8]) -> Option<()> {
+        let tcb_oid = [OID_SGX_EXTENSION, &[0x02]].concat();
+        let mut seen = 0_u32;
+        let mut list = Der::new(content);
+        while !list.is_empty() {
+            let mut entry = Der::new(list.read(TAG_SEQUENCE)?);
+            match sub_arc(entry.read(TAG_OID)?, &tcb_oid)? {
+                i @ 1..=16 => {
+                    self.tcb_components[i as usize - 1] =
+                        small_uint(entry.read(TAG_INTEGER)?)?.try_into().ok()?;
+                    seen |= 1 << i;
+                }
+                17 => {
+                    self.pce_svn = small_uint(entry.read(TAG_INTEGER)?)?.try_into().ok()?;
+                    seen |= 1 << 17;
+                }
+                18 => {
+                    copy_exact(&mut self.cpu_svn.svn, entry.read(TAG_OCTET_STRING)?)?;
+                    seen |= 1 << 18;
+                }
+                _ => return None,
+            }
+            entry.finish()?;
+        }
+        if seen != 0x7fffe {
+            return None;
+        }
+        Some(())
+    }
+
+    /// The platform provisioning ID.
+    pub fn get_ppid(&self) -> &[u8; 16] {
+        &self.ppid
+    }
+
+    /// The 16 SGX TCB components of the certified TCB level.
+    pub fn get_tcb_components(&self) -> &[u8; 16] {
+        &self.tcb_components
+    }
+
+    /// The PCE SVN of the certified TCB level.
+    pub fn get_pce_svn(&self) -> u16 {
+        self.pce_svn
+    }
+
+    /// The raw CPUSVN of the certified TCB level.
+    pub fn get_cpu_svn(&self) -> &sgx_cpu_svn_t {
+        &self.cpu_svn
+    }
+
+    /// The PCE ID.
+    pub fn get_pce_id(&self) -> &[u8; 2] {
+        &self.pce_id
+    }
+
+    /// The FMSPC, the family-model-stepping-platform-custom SKU of the platform.
+    pub fn get_fmspc(&self) -> &[u8; 6] {
+        &self.fmspc
+    }
+
+    /// The SGX type: 0 for Standard, 1 for Scalable.
+    pub fn get_sgx_type(&self) -> u8 {
+        self.sgx_type
+    }
+}
+
+impl fmt::Debug for SgxPckCertExtensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxPckCertExtensions")
+            .field("ppid", &Hex(&self.ppid))
+            .field("tcb_components", &self.tcb_components)
+            .field("pce_svn", &self.pce_svn)
+            .field("cpu_svn", &Hex(&self.cpu_svn.svn))
+            .field("pce_id", &Hex(&self.pce_id))
+            .field("fmspc", &Hex(&self.fmspc))
+            .field("sgx_type", &self.sgx_type)
+            .finish()
+    }
+}
+
+///
+/// A PCK certificate chain: the PCK certificate, the Platform or Processor CA and
+/// the Intel SGX Root CA.
+///
+#[derive(Clone)]
+pub struct SgxPckCertChain {
+    certs: Vec<Vec<u8>>,
+    extensions: SgxPckCertExtensions,
+    pck_key: sgx_ec256_public_t,
+}
+
+impl SgxPckCertChain {
+    ///
+    /// Parses a PEM chain, leaf first, and checks that it has the shape of the PCK
+    /// hierarchy. Signatures are checked by verify.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_QL_PCK_CERT_CHAIN_ERROR**
+    ///
+    /// The chain is malformed, or is not made of a PCK certificate, a PCK CA and the
+    /// root CA.
+    ///
+    /// **SGX_QL_PCK_CERT_UNSUPPORTED_FORMAT**
+    ///
+    /// The PCK certificate has no ECDSA P-256 key, or its SGX extension is malformed.
+    ///
+    pub fn from_pem(pem: &[u8]) -> SgxQuote3Result<SgxPckCertChain> {
+        let error = sgx_quote3_error_t::SGX_QL_PCK_CERT_CHAIN_ERROR;
+        let ders = pem_certificates(pem).ok_or(error)?;
+        let (pck, ca, root) = match ders.as_slice() {
+            [pck, ca, root] => (
+                SgxX509Certificate::parse(pck).map_err(|_| error)?,
+                SgxX509Certificate::parse(ca).map_err(|_| error)?,
+                SgxX509Certificate::parse(root).map_err(|_| error)?,
+            ),
+            _ => return Err(error),
+        };
+        let ca_name = ca.subject().get_common_name();
+        if pck.subject().get_common_name() != Some(SGX_PCK_CERT_COMMON_NAME)
+            || (ca_name != Some(SGX_PCK_PLATFORM_CA_COMMON_NAME)
+                && ca_name != Some(SGX_PCK_PROCESSOR_CA_COMMON_NAME))
+            || root.subject().get_common_name() != Some(SGX_ROOT_CA_COMMON_NAME)
+        {
+            return Err(error);
+        }
+        let pck_key = match pck.public_key() {
+            Some(SgxX509PublicKey::EcP256(key)) => *key,
+            _ => return Err(sgx_quote3_error_t::SGX_QL_PCK_CERT_UNSUPPORTED_FORMAT),
+        };
+        let extensions = SgxPckCertExtensions::parse(&pck)?;
+        Ok(SgxPckCertChain {
+            certs: ders,
+            extensions,
+            pck_key,
+        })
+    }
+
+    ///
+    /// Verifies the chain at time, in seconds since the Unix epoch, against the pinned
+    /// root key and the CRLs, given in DER, PEM or hex. Each certificate must be
+    /// issued and signed by the next one, and the CAs must be marked as such.
+    ///
+    /// # Return value
+    ///
+    /// Whether all the certificates are valid and the CRLs current at time.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_QL_ROOT_CA_UNTRUSTED**
+    ///
+    /// The root certificate does not hold the pinned key.
+    ///
+    /// **SGX_QL_PCK_CERT_CHAIN_ERROR**
+    ///
+    /// A signature or a CA constraint does not verify, or a CRL is not the one of
+    /// its CA of the chain or is not signed by it.
+    ///
+    /// **SGX_QL_CRL_UNSUPPORTED_FORMAT**
+    ///
+    /// A CRL cannot be decoded.
+    ///
+    /// **SGX_QL_PCK_REVOKED**
+    ///
+    /// The PCK certificate or the PCK CA is revoked.
+    ///
+    pub fn verify(
+        &self,
+        root: &SgxX509PublicKey,
+        root_ca_crl: &[u8],
+        pck_crl: &[u8],
+        time: u64,
+    ) -> SgxQuote3Result<bool> {
+        let error = sgx_quote3_error_t::SGX_QL_PCK_CERT_CHAIN_ERROR;
+        let mut certs = Vec::with_capacity(self.certs.len());
+        for der in &self.certs {
+            certs.push(SgxX509Certificate::parse(der).map_err(|_| error)?);
+        }
+        let root_ca_crl_der = crl_der(root_ca_crl)?;
+        let pck_crl_der = crl_der(pck_crl)?;
+        let root_ca_crl = parse_crl(&root_ca_crl_der)?;
+        let pck_crl = parse_crl(&pck_crl_der)?;
+        // Each CRL must be the one of its CA, or a CA would go unchecked.
+        if pck_crl.issuer() != certs[1].subject() || root_ca_crl.issuer() != certs[2].subject() {
+            return Err(error);
+        }
+        verify_chain(&certs, root, &[&root_ca_crl, &pck_crl], time, error)
+    }
+
+    /// Get the DER certificates, leaf first.
+    pub fn get_certificates(&self) -> &[Vec<u8>] {
+        &self.certs
+    }
+
+    /// Get the SGX extension of the PCK certificate.
+    pub fn get_extensions(&self) -> &SgxPckCertExtensions {
+        &self.extensions
+    }
+
+    /// Get the PCK, which signs the QE report.
+    pub fn get_pck_key(&self) -> &sgx_ec256_public_t {
+        &self.pck_key
+    }
+}
+
+// Decodes the certificates of a PEM chain, leaf first. Text outside of the
+// certificate blocks, such as a terminating NUL, is ignored.
+pub(crate) fn pem_certificates(pem: &[u8]) -> Option<Vec<Vec<u8>>> {
+    const BEGIN: &[u8] = b"-----BEGIN CERTIFICATE-----";
+    const END: &[u8] = b"-----END CERTIFICATE-----";
+    let mut certs = Vec::new();
+    let mut rest = pem;
+    while let Some(start) = find(rest, BEGIN) {
+        rest = &rest[start + BEGIN.len()..];
+        let end = find(rest, END)?;
+        certs.push(base64_decode(&rest[..end])?);
+        rest = &rest[end + END.len()..];
+    }
+    if certs.is_empty() {
+        None
+    } else {
+        Some(certs)
+    }
+}
+
+// Checks that each certificate is issued and signed by the next one, a CA, and the
+// last one by root, whose key must be that of the last certificate. Each CRL must
+// be signed by a CA of the chain and revoke none of the certificates it issued.
+// Returns whether all of the certificates are valid and the CRLs current at time.
+pub(crate) fn verify_chain(
+    certs: &[SgxX509Certificate],
+    root: &SgxX509PublicKey,
+    crls: &[&SgxX509Crl],
+    time: u64,
+    error: sgx_quote3_error_t,
+) -> SgxQuote3Result<bool> {
+    let last = certs.last().ok_or(error)?;
+    if !last.public_key().map_or(false, |key| same_key(key, root)) {
+        return Err(sgx_quote3_error_t::SGX_QL_ROOT_CA_UNTRUSTED);
+    }
+    let mut valid = true;
+    for (i, cert) in certs.iter().enumerate() {
+        let issuer = match certs.get(i + 1) {
+            Some(next) => {
+                // i CAs lie below next.
+                let (ca, path_len) = next.basic_constraints().map_err(|_| error)?;
+                if cert.issuer() != next.subject()
+                    || !ca
+                    || path_len.map_or(false, |len| i as u32 > len)
+                {
+                    return Err(error);
+                }
+                next.public_key()
+            }
+            None => Some(root),
+        };
+        let issuer = issuer.ok_or(error)?;
+        if !cert.verify_signature(issuer).map_err(|_| error)? {
+            return Err(error);
+        }
+        valid &= cert.is_valid_at(time);
+    }
+    for crl in crls {
+        let ca = certs
+            .iter()
+            .position(|c| c.subject() == crl.issuer())
+            .ok_or(error)?;
+        let key = certs[ca].public_key().ok_or(error)?;
+        if !crl.verify_signature(key).map_err(|_| error)? {
+            return Err(error);
+        }
+        if certs[..ca]
+            .iter()
+            .any(|c| c.issuer() == crl.issuer() && crl.is_revoked(c.serial_number()))
+        {
+            return Err(sgx_quote3_error_t::SGX_QL_PCK_REVOKED);
+        }
+        valid &= crl.is_current_at(time);
+    }
+    Ok(valid)
+}
+
+// Parses a CRL decoded by crl_der.
+pub(crate) fn parse_crl(der: &[u8]) -> SgxQuote3Result<SgxX509Crl<'_>> {
+    SgxX509Crl::parse(der).map_err(|_| sgx_quote3_error_t::SGX_QL_CRL_UNSUPPORTED_FORMAT)
+}
+
+// Decodes a CRL given in DER, in PEM or as hex encoded DER, as the PCS and the
+// PCCS return them. A terminating NUL is ignored.
+pub(crate) fn crl_der(crl: &[u8]) -> SgxQuote3Result<Vec<u8>> {
+    const BEGIN: &[u8] = b"-----BEGIN X509 CRL-----";
+    const END: &[u8] = b"-----END X509 CRL-----";
+    let error = sgx_quote3_error_t::SGX_QL_CRL_UNSUPPORTED_FORMAT;
+    let crl = crl.strip_suffix(&[0]).unwrap_or(crl);
+    if let Some(start) = find(crl, BEGIN) {
+        let rest = &crl[start + BEGIN.len()..];
+        let end = find(rest, END).ok_or(error)?;
+        return base64_decode(&rest[..end]).ok_or(error);
+    }
+    if crl.first() == Some(&TAG_SEQUENCE) {
+        return Ok(crl.to_vec());
+    }
+    if crl.len() % 2 != 0 || !crl.iter().all(u8::is_ascii_hexdigit) {
+        return Err(error);
+    }
+    crl.chunks(2)
+        .map(|pair| {
+            let hex = core::str::from_utf8(pair).ok()?;
+            u8::from_str_radix(hex, 16).ok()
+        })
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(error)
+}
+
+pub(crate) fn same_key(a: &SgxX509PublicKey, b: &SgxX509PublicKey) -> bool {
+    match (a, b) {
+        (SgxX509PublicKey::EcP256(a), SgxX509PublicKey::EcP256(b)) => a.gx == b.gx && a.gy == b.gy,
+        (SgxX509PublicKey::EcP384(a), SgxX509PublicKey::EcP384(b)) => a.gx == b.gx && a.gy == b.gy,
+        _ => false,
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn base64_decode(text: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut acc = 0_u32;
+    let mut bits = 0;
+    let mut padding = 0;
+    for &c in text {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => {
+                padding += 1;
+                continue;
+            }
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => return None,
+        };
+        if padding > 0 {
+            return None;
+        }
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    if padding > 2 || acc & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+fn copy_exact(dst: &mut [u8], src: &[u8]) -> Option<()> {
+    if dst.len() != src.len() {
+        return None;
+    }
+    dst.copy_from_slice(src);
+    Some(())
+}
+
+// A non-negative DER INTEGER or ENUMERATED that fits in 32 bits.
+fn small_uint(content: &[u8]) -> Option<u32> {
+    let content = match content {
+        [0, rest @ ..] if !rest.is_empty() && rest[0] & 0x80 != 0 => rest,
+        [b, ..] if b & 0x80 == 0 => content,
+        _ => return None,
+    };
+    if content.len() > 4 {
+        return None;
+    }
+    Some(content.iter().fold(0, |acc, b| (acc << 8) | *b as u32))
+}
+
+// The single arc that oid adds to prefix.
+fn sub_arc(oid: &[u8], prefix: &[u8]) -> Option<u32> {
+    let rest = oid.strip_prefix(prefix)?;
+    let mut arc = 0_u32;
+    for (i, b) in rest.iter().enumerate() {
+        arc = arc.checked_mul(128)? | (b & 0x7f) as u32;
+        if b & 0x80 == 0 {
+            return if i + 1 == rest.len() { Some(arc) } else { None };
+        }
+    }
+    None
+}
+
+pub(crate) struct Der<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    pub fn new(data: &'a [u8]) -> Der<'a> {
+        Der { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn finish(&self) -> Option<()> {
+        if self.data.is_empty() {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn read_any(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.data.split_first()?;
+        let (&first, rest) = rest.split_first()?;
+        let (len, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let n = (first & 0x7f) as usize;
+            if n == 0 || n > 4 || rest.len() < n || rest[0] == 0 {
+                return None;
+            }
+            let len = rest[..n].iter().fold(0, |acc, b| (acc << 8) | *b as usize);
+            if len < 0x80 {
+                return None;
+            }
+            (len, &rest[n..])
+        };
+        if rest.len() < len {
+            return None;
+        }
+        self.data = &rest[len..];
+        Some((tag, &rest[..len]))
+    }
+
+    pub fn read(&mut self, tag: u8) -> Option<&'a [u8]> {
+        match self.read_any()? {
+            (t, content) if t == tag => Some(content),
+            _ => None,
+        }
+    }
+
+    pub fn skip(&mut self) -> Option<()> {
+        self.read_any().map(|_| ())
+    }
+}
@@ -0,0 +1,276 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! TCB status policy
+//!
+//! A quote verification tells the status of the platform, not whether the relying
+//! party trusts it. `SgxTcbPolicy` makes that decision: which statuses are accepted,
+//! which only with a given set of advisories, and how old the collateral may be.
+//!
+//! A policy is read from and written to JSON, so that it can be provisioned to the
+//! enclave, sealed or signed like any other configuration:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "accept": ["UpToDate"],
+//!   "acceptWithAdvisories": { "SWHardeningNeeded": ["INTEL-SA-00334"] },
+//!   "maxCollateralAge": 2592000,
+//!   "allowExpiredCollateral": false
+//! }
+//! ```
+//!
+//! Statuses are named as in the TCB info. Only `version` is required; a policy that
+//! lists nothing accepts nothing.
+//!
+use crate::collateral::{tcb_status, tcb_status_name};
+use crate::json::Json;
+use crate::verify::SgxQuoteVerificationResult;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use sgx_types::*;
+
+const POLICY_VERSION: u64 = 1;
+
+///
+/// The TCB statuses, advisories and collateral age a relying party accepts.
+///
+#[derive(Clone, Default, Debug)]
+pub struct SgxTcbPolicy {
+    accepted: Vec<sgx_ql_qv_result_t>,
+    conditional: Vec<(sgx_ql_qv_result_t, Vec<String>)>,
+    max_collateral_age: Option<u64>,
+    allow_expired_collateral: bool,
+}
+
+impl SgxTcbPolicy {
+    ///
+    /// Create a policy that accepts nothing.
+    ///
+    pub fn new() -> SgxTcbPolicy {
+        SgxTcbPolicy::default()
+    }
+
+    ///
+    /// Accept a TCB status whatever advisories apply.
+    ///
+    pub fn accept(mut self, status: sgx_ql_qv_result_t) -> SgxTcbPolicy {
+        self.conditional.retain(|(s, _)| *s != status);
+        if !self.accepted.contains(&status) {
+            self.accepted.push(status);
+        }
+        self
+    }
+
+    ///
+    /// Accept a TCB status only when every advisory that applies is in `advisory_ids`.
+    ///
+    pub fn accept_with_advisories<S: AsRef<str>>(
+        mut self,
+        status: sgx_ql_qv_result_t,
+        advisory_ids: &[S],
+    ) -> SgxTcbPolicy {
+        self.accepted.retain(|s| *s != status);
+        self.conditional.retain(|(s, _)| *s != status);
+        let ids = advisory_ids
+            .iter()
+            .map(|id| String::from(id.as_ref()))
+            .collect();
+        self.conditional.push((status, ids));
+        self
+    }
+
+    ///
+    /// Reject collateral issued more than `seconds` before the evaluation time.
+    ///
+    pub fn max_collateral_age(mut self, seconds: u64) -> SgxTcbPolicy {
+        self.max_collateral_age = Some(seconds);
+        self
+    }
+
+    ///
+    /// Whether a result whose collateral had expired at verification is accepted.
+    /// It is rejected by default.
+    ///
+    pub fn allow_expired_collateral(mut self, allow: bool) -> SgxTcbPolicy {
+        self.allow_expired_collateral = allow;
+        self
+    }
+
+    ///
+    /// Read a policy from its JSON form.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_QL_ERROR_INVALID_PARAMETER**
+    ///
+    /// The policy is malformed, of another version, or names an unknown status.
+    ///
+    pub fn from_json(json: &[u8]) -> SgxQuote3Result<SgxTcbPolicy> {
+        Self::parse(json).ok_or(sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER)
+    }
+
+    fn parse(json: &[u8]) -> Option<SgxTcbPolicy> {
+        let doc = Json::parse(json)?;
+        doc.as_object()?;
+        if doc.get("version")?.as_u64()? != POLICY_VERSION {
+            return None;
+        }
+
+        let mut policy = SgxTcbPolicy::new();
+        if let Some(accept) = doc.get("accept") {
+            for status in accept.as_array()? {
+                policy = policy.accept(tcb_status(status.as_str()?)?);
+            }
+        }
+        if let Some(conditional) = doc.get("acceptWithAdvisories") {
+            for (status, ids) in conditional.as_object()? {
+                let ids = ids
+                    .as_array()?
+                    .iter()
+                    .map(|id| id.as_str())
+                    .collect::<Option<Vec<&str>>>()?;
+                policy = policy.accept_with_advisories(tcb_status(status)?, &ids);
+            }
+        }
+        if let Some(age) = doc.get("maxCollateralAge") {
+            policy = policy.max_collateral_age(age.as_u64()?);
+        }
+        if let Some(allow) = doc.get("allowExpiredCollateral") {
+            policy = policy.allow_expired_collateral(allow.as_bool()?);
+        }
+        Some(policy)
+    }
+
+    ///
+    /// Write the policy in the JSON form read by `from_json`.
+    ///
+    pub fn to_json(&self) -> String {
+        let mut json = format!("{{\"version\":{},\"accept\":[", POLICY_VERSION);
+        for (i, status) in self.accepted.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            push_string(&mut json, tcb_status_name(*status).unwrap_or_default());
+        }
+        json.push_str("],\"acceptWithAdvisories\":{");
+        for (i, (status, ids)) in self.conditional.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            push_string(&mut json, tcb_status_name(*status).unwrap_or_default());
+            json.push_str(":[");
+            for (j, id) in ids.iter().enumerate() {
+                if j > 0 {
+                    json.push(',');
+                }
+                push_string(&mut json, id);
+            }
+            json.push(']');
+        }
+        json.push('}');
+        if let Some(age) = self.max_collateral_age {
+            let _ = write!(json, ",\"maxCollateralAge\":{}", age);
+        }
+        let _ = write!(
+            json,
+            ",\"allowExpiredCollateral\":{}}}",
+            self.allow_expired_collateral
+        );
+        json
+    }
+
+    ///
+    /// Decide whether a verification result is acceptable at `current_time`, in seconds
+    /// since the Unix epoch.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_QL_TCB_OUT_OF_DATE** and the other TCB status errors
+    ///
+    /// The status is not accepted, or is accepted only with advisories of which one does
+    /// not apply to the platform.
+    ///
+    /// **SGX_QL_SGX_TCB_INFO_EXPIRED**
+    ///
+    /// The collateral had expired at verification, or is older than the policy allows.
+    /// Collateral of unknown age is rejected when the policy limits the age.
+    ///
+    pub fn evaluate(
+        &self,
+        result: &SgxQuoteVerificationResult,
+        current_time: u64,
+    ) -> SgxQuote3Result<()> {
+        let status = result.get_tcb_status();
+        let accepted = self.accepted.contains(&status)
+            || self.conditional.iter().any(|(s, ids)| {
+                *s == status && result.get_advisory_ids().iter().all(|id| ids.contains(id))
+            });
+        if !accepted {
+            return Err(status_error(status));
+        }
+
+        if result.is_collateral_expired() && !self.allow_expired_collateral {
+            return Err(sgx_quote3_error_t::SGX_QL_SGX_TCB_INFO_EXPIRED);
+        }
+        if let Some(max_age) = self.max_collateral_age {
+            let issued = result.get_earliest_issue_date();
+            if issued == 0 || current_time.saturating_sub(issued) > max_age {
+                return Err(sgx_quote3_error_t::SGX_QL_SGX_TCB_INFO_EXPIRED);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn status_error(status: sgx_ql_qv_result_t) -> sgx_quote3_error_t {
+    match status {
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_CONFIG_NEEDED => {
+            sgx_quote3_error_t::SGX_QL_TCB_CONFIGURATION_NEEDED
+        }
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OUT_OF_DATE => {
+            sgx_quote3_error_t::SGX_QL_TCB_OUT_OF_DATE
+        }
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_OUT_OF_DATE_CONFIG_NEEDED => {
+            sgx_quote3_error_t::SGX_QL_TCB_OUT_OF_DATE_CONFIGURATION_NEEDED
+        }
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_SW_HARDENING_NEEDED => {
+            sgx_quote3_error_t::SGX_QL_TCB_SW_HARDENING_NEEDED
+        }
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_CONFIG_AND_SW_HARDENING_NEEDED => {
+            sgx_quote3_error_t::SGX_QL_TCB_CONFIGURATION_AND_SW_HARDENING_NEEDED
+        }
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_REVOKED => sgx_quote3_error_t::SGX_QL_TCB_REVOKED,
+        _ => sgx_quote3_error_t::SGX_QL_ERROR_UNEXPECTED,
+    }
+}
+
+fn push_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
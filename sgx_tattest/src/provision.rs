@@ -0,0 +1,565 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Secret provisioning
+//!
+//! A provisioning service hands secrets to enclaves that prove their identity with a
+//! quote. The exchange follows the one of sgx_tseal between local enclaves, with the
+//! quote in place of the local report and a signature of the service in place of its
+//! report:
+//!
+//! 1. The service creates a `SgxProvisionSession` and sends its `SgxProvisionChallenge`.
+//! 2. The enclave creates a `SgxProvisionClient` for the challenge and sends its
+//!    `SgxProvisionRequest`, a quote over the challenge and an ephemeral X25519 key.
+//! 3. The service has its `SgxProvisionPolicy` verify the quote and choose the secret,
+//!    and returns it wrapped in a `SgxProvisionResponse`, signed with its P-256 key.
+//! 4. The enclave checks the signature against the key of the service it expects and
+//!    unwraps the secret with `receive`, or seals it to itself with `receive_sealed`.
+//!
+//! The wrapping key is derived with HKDF-SHA256 from the shared secret, the challenge
+//! and both public keys. Every session uses new keys, so the key is never reused.
+//!
+use crate::quote::SgxQuoteGenerator;
+use alloc::vec::Vec;
+use sgx_tcrypto::*;
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_tseal::{rsgx_seal_blob, SealPolicy, SgxUnsealedData};
+use sgx_types::*;
+
+const PROVISION_MAGIC: [u8; 4] = *b"SGXP";
+const PROVISION_VERSION: u16 = 1;
+const KIND_CHALLENGE: u16 = 1;
+const KIND_REQUEST: u16 = 2;
+const KIND_RESPONSE: u16 = 3;
+const HEADER_SIZE: usize = 8;
+const PUBLIC_SIZE: usize = 32;
+const SIGNATURE_SIZE: usize = 64;
+const REQUEST_LABEL: &[u8] = b"sgx_tattest provision request";
+const RESPONSE_LABEL: &[u8] = b"sgx_tattest provision response";
+const KEY_SALT: &[u8] = b"sgx_tattest provision key";
+
+/// The size of the nonce of a challenge.
+pub const SGX_PROVISION_NONCE_SIZE: usize = 32;
+/// The size of a serialized SgxProvisionChallenge.
+pub const SGX_PROVISION_CHALLENGE_SIZE: usize = HEADER_SIZE + SGX_PROVISION_NONCE_SIZE;
+
+///
+/// Decides which enclaves the service provisions, and with what.
+///
+pub trait SgxProvisionPolicy {
+    ///
+    /// Verifies the quote of a client, for instance with SgxQuoteVerifier, and returns
+    /// the report body of the quoted enclave.
+    ///
+    fn verify_quote(&mut self, quote: &[u8]) -> SgxResult<sgx_report_body_t>;
+
+    ///
+    /// Returns the additional text and the secret for the enclave, or an error to
+    /// refuse it. The additional text is authenticated but sent in clear.
+    ///
+    fn secret_for(&mut self, enclave: &sgx_report_body_t) -> SgxResult<(Vec<u8>, Vec<u8>)>;
+}
+
+///
+/// The first message of a provisioning, from the service to the enclave.
+///
+#[derive(Clone, Copy)]
+pub struct SgxProvisionChallenge {
+    nonce: [u8; SGX_PROVISION_NONCE_SIZE],
+}
+
+impl SgxProvisionChallenge {
+    ///
+    /// Get the nonce the quote of the enclave must cover.
+    ///
+    pub fn get_nonce(&self) -> &[u8; SGX_PROVISION_NONCE_SIZE] {
+        &self.nonce
+    }
+
+    ///
+    /// Serializes the challenge.
+    ///
+    pub fn to_bytes(&self) -> [u8; SGX_PROVISION_CHALLENGE_SIZE] {
+        let mut out = [0_u8; SGX_PROVISION_CHALLENGE_SIZE];
+        write_header(&mut out, KIND_CHALLENGE);
+        out[HEADER_SIZE..].copy_from_slice(&self.nonce);
+        out
+    }
+
+    ///
+    /// Parses a challenge serialized by to_bytes.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The input has the wrong size, magic, version or kind.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<SgxProvisionChallenge> {
+        if bytes.len() != SGX_PROVISION_CHALLENGE_SIZE {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        check_header(bytes, KIND_CHALLENGE)?;
+        let mut nonce = [0_u8; SGX_PROVISION_NONCE_SIZE];
+        nonce.copy_from_slice(&bytes[HEADER_SIZE..]);
+        Ok(SgxProvisionChallenge { nonce })
+    }
+}
+
+///
+/// The second message of a provisioning, from the enclave to the service.
+///
+#[derive(Clone)]
+pub struct SgxProvisionRequest {
+    public: sgx_x25519_public_t,
+    quote: Vec<u8>,
+}
+
+impl SgxProvisionRequest {
+    ///
+    /// Get the quote of the enclave.
+    ///
+    pub fn get_quote(&self) -> &[u8] {
+        &self.quote
+    }
+
+    ///
+    /// Serializes the request.
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![0_u8; HEADER_SIZE];
+        write_header(&mut out, KIND_REQUEST);
+        out.extend_from_slice(&self.public);
+        out.extend_from_slice(&self.quote);
+        out
+    }
+
+    ///
+    /// Parses a request serialized by to_bytes.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The input is truncated, or has the wrong magic, version or kind.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<SgxProvisionRequest> {
+        if bytes.len() <= HEADER_SIZE + PUBLIC_SIZE {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        check_header(bytes, KIND_REQUEST)?;
+        let mut public = sgx_x25519_public_t::default();
+        public.copy_from_slice(&bytes[HEADER_SIZE..HEADER_SIZE + PUBLIC_SIZE]);
+        Ok(SgxProvisionRequest {
+            public,
+            quote: bytes[HEADER_SIZE + PUBLIC_SIZE..].to_vec(),
+        })
+    }
+}
+
+///
+/// The third message of a provisioning, from the service to the enclave.
+///
+#[derive(Clone)]
+pub struct SgxProvisionResponse {
+    public: sgx_x25519_public_t,
+    signature: [u8; SIGNATURE_SIZE],
+    additional: Vec<u8>,
+    ciphertext: Vec<u8>,
+    tag: sgx_aes_gcm_128bit_tag_t,
+}
+
+impl SgxProvisionResponse {
+    ///
+    /// Get the additional text. It is only authenticated once
+    /// SgxProvisionClient::receive has succeeded.
+    ///
+    pub fn get_additional_txt(&self) -> &[u8] {
+        &self.additional
+    }
+
+    ///
+    /// Serializes the response.
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![0_u8; HEADER_SIZE];
+        write_header(&mut out, KIND_RESPONSE);
+        out.extend_from_slice(&self.public);
+        out.extend_from_slice(&self.signature);
+        out.extend_from_slice(&(self.additional.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(self.ciphertext.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.additional);
+        out.extend_from_slice(&self.ciphertext);
+        out.extend_from_slice(&self.tag);
+        out
+    }
+
+    ///
+    /// Parses a response serialized by to_bytes.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The input is truncated, has trailing bytes, or has the wrong magic, version or
+    /// kind.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<SgxProvisionResponse> {
+        let fixed = HEADER_SIZE + PUBLIC_SIZE + SIGNATURE_SIZE + 8;
+        if bytes.len() < fixed + SGX_AESGCM_MAC_SIZE {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        check_header(bytes, KIND_RESPONSE)?;
+        let mut public = sgx_x25519_public_t::default();
+        public.copy_from_slice(&bytes[HEADER_SIZE..HEADER_SIZE + PUBLIC_SIZE]);
+        let pos = HEADER_SIZE + PUBLIC_SIZE;
+        let mut signature = [0_u8; SIGNATURE_SIZE];
+        signature.copy_from_slice(&bytes[pos..pos + SIGNATURE_SIZE]);
+        let pos = pos + SIGNATURE_SIZE;
+        let additional_len = read_u32(&bytes[pos..]) as usize;
+        let ciphertext_len = read_u32(&bytes[pos + 4..]) as usize;
+        let total = additional_len
+            .checked_add(ciphertext_len)
+            .and_then(|len| len.checked_add(fixed + SGX_AESGCM_MAC_SIZE))
+            .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+        if bytes.len() != total {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let ciphertext_pos = fixed + additional_len;
+        let tag_pos = ciphertext_pos + ciphertext_len;
+        let mut tag = sgx_aes_gcm_128bit_tag_t::default();
+        tag.copy_from_slice(&bytes[tag_pos..]);
+        Ok(SgxProvisionResponse {
+            public,
+            signature,
+            additional: bytes[fixed..ciphertext_pos].to_vec(),
+            ciphertext: bytes[ciphertext_pos..tag_pos].to_vec(),
+            tag,
+        })
+    }
+}
+
+///
+/// The enclave side of a provisioning. It holds the ephemeral private key of the
+/// enclave, which is erased when it is dropped.
+///
+pub struct SgxProvisionClient {
+    private: SgxX25519PrivateKey,
+    nonce: [u8; SGX_PROVISION_NONCE_SIZE],
+    request: SgxProvisionRequest,
+}
+
+impl SgxProvisionClient {
+    ///
+    /// Generates an ephemeral key and a request with a quote over the challenge and
+    /// the key.
+    ///
+    /// # Errors
+    ///
+    /// The errors of SgxX25519PrivateKey::generate and SgxQuoteGenerator::get_quote.
+    ///
+    pub fn new(
+        generator: &SgxQuoteGenerator,
+        challenge: &SgxProvisionChallenge,
+    ) -> SgxResult<SgxProvisionClient> {
+        let private = SgxX25519PrivateKey::generate()?;
+        let public = private.public_key();
+        let report_data = bind_report_data(&challenge.nonce, &public)?;
+        let quote = generator.get_quote(&report_data)?;
+        Ok(SgxProvisionClient {
+            private,
+            nonce: challenge.nonce,
+            request: SgxProvisionRequest { public, quote },
+        })
+    }
+
+    ///
+    /// Get the request to send to the service.
+    ///
+    pub fn get_request(&self) -> &SgxProvisionRequest {
+        &self.request
+    }
+
+    ///
+    /// Checks that the response is signed by the service with service_key, and
+    /// unwraps the secret.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_SIGNATURE**
+    ///
+    /// The response is not signed with service_key for this provisioning.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The secret does not authenticate.
+    ///
+    /// The errors of the crypto functions.
+    ///
+    pub fn receive<'a>(
+        &self,
+        response: &SgxProvisionResponse,
+        service_key: &sgx_ec256_public_t,
+    ) -> SgxResult<SgxUnsealedData<'a, [u8]>> {
+        let transcript = transcript(&self.nonce, &self.request.public, &response.public);
+        let handle = SgxEccHandle::new();
+        handle.open()?;
+        if !handle.ecdsa_verify_slice(
+            &transcript,
+            service_key,
+            &signature_from_bytes(&response.signature),
+        )? {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE);
+        }
+        let mut key = wrapping_key(&self.private, &response.public, &transcript)?;
+
+        let mut decrypt = vec![0_u8; response.ciphertext.len()];
+        let result = rsgx_rijndael128GCM_decrypt(
+            &key,
+            &response.ciphertext,
+            &[0_u8; SGX_AESGCM_IV_SIZE],
+            &response.additional,
+            &response.tag,
+            &mut decrypt,
+        );
+        rsgx_zeroize(&mut key);
+        if let Err(e) = result {
+            rsgx_zeroize(&mut decrypt);
+            return Err(e);
+        }
+        let mut unsealed = SgxUnsealedData::<[u8]>::default();
+        unsealed.payload_size = decrypt.len() as u32;
+        unsealed.decrypt = decrypt.into_boxed_slice();
+        unsealed.additional = response.additional.clone().into_boxed_slice();
+        Ok(unsealed)
+    }
+
+    ///
+    /// Receives the secret like receive, and seals it under policy as a versioned
+    /// sealed blob of the calling enclave.
+    ///
+    /// # Errors
+    ///
+    /// The errors of receive and rsgx_seal_blob.
+    ///
+    pub fn receive_sealed(
+        &self,
+        response: &SgxProvisionResponse,
+        service_key: &sgx_ec256_public_t,
+        policy: &SealPolicy,
+    ) -> SgxResult<Vec<u8>> {
+        let mut unsealed = self.receive(response, service_key)?;
+        let blob = rsgx_seal_blob(policy, &unsealed.additional, &unsealed.decrypt);
+        rsgx_zeroize(&mut unsealed.decrypt);
+        blob
+    }
+}
+
+///
+/// The service side of a provisioning, for one enclave.
+///
+pub struct SgxProvisionSession {
+    challenge: SgxProvisionChallenge,
+}
+
+impl SgxProvisionSession {
+    ///
+    /// Starts a provisioning with a fresh challenge.
+    ///
+    /// # Errors
+    ///
+    /// The errors of rsgx_read_rand.
+    ///
+    pub fn new() -> SgxResult<SgxProvisionSession> {
+        let mut nonce = [0_u8; SGX_PROVISION_NONCE_SIZE];
+        rsgx_read_rand(&mut nonce)?;
+        Ok(SgxProvisionSession {
+            challenge: SgxProvisionChallenge { nonce },
+        })
+    }
+
+    ///
+    /// Get the challenge to send to the enclave.
+    ///
+    pub fn get_challenge(&self) -> &SgxProvisionChallenge {
+        &self.challenge
+    }
+
+    ///
+    /// Has policy verify the quote of the request and choose the secret, checks that
+    /// the quote covers the challenge and the key of the request, and wraps the
+    /// secret for the enclave, signed with service_key.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The additional text or the secret is longer than u32::MAX bytes.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The quote does not cover this challenge and the key of the request.
+    ///
+    /// The errors of the policy, and of the crypto functions.
+    ///
+    pub fn respond<P: SgxProvisionPolicy + ?Sized>(
+        &self,
+        request: &SgxProvisionRequest,
+        policy: &mut P,
+        service_key: &sgx_ec256_private_t,
+    ) -> SgxResult<SgxProvisionResponse> {
+        let body = policy.verify_quote(&request.quote)?;
+        let expected = bind_report_data(&self.challenge.nonce, &request.public)?;
+        if !rsgx_ct_eq(&expected.d, &body.report_data.d) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        let (additional, mut secret) = policy.secret_for(&body)?;
+        let result = wrap(
+            &self.challenge.nonce,
+            request,
+            &additional,
+            &secret,
+            service_key,
+        );
+        rsgx_zeroize(&mut secret);
+        result
+    }
+}
+
+fn wrap(
+    nonce: &[u8; SGX_PROVISION_NONCE_SIZE],
+    request: &SgxProvisionRequest,
+    additional_text: &[u8],
+    encrypt_text: &[u8],
+    service_key: &sgx_ec256_private_t,
+) -> SgxResult<SgxProvisionResponse> {
+    if additional_text.len() > u32::MAX as usize || encrypt_text.len() > u32::MAX as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let private = SgxX25519PrivateKey::generate()?;
+    let public = private.public_key();
+    let transcript = transcript(nonce, &request.public, &public);
+    let handle = SgxEccHandle::new();
+    handle.open()?;
+    let signature = handle.ecdsa_sign_slice(&transcript, service_key)?;
+    let mut key = wrapping_key(&private, &request.public, &transcript)?;
+
+    let mut ciphertext = vec![0_u8; encrypt_text.len()];
+    let mut tag = sgx_aes_gcm_128bit_tag_t::default();
+    let result = rsgx_rijndael128GCM_encrypt(
+        &key,
+        encrypt_text,
+        &[0_u8; SGX_AESGCM_IV_SIZE],
+        additional_text,
+        &mut ciphertext,
+        &mut tag,
+    );
+    rsgx_zeroize(&mut key);
+    result?;
+    Ok(SgxProvisionResponse {
+        public,
+        signature: signature_to_bytes(&signature),
+        additional: additional_text.to_vec(),
+        ciphertext,
+        tag,
+    })
+}
+
+// The first half of the report data is the hash of the label, the nonce and the
+// public key of the enclave; the second half is zero.
+fn bind_report_data(
+    nonce: &[u8; SGX_PROVISION_NONCE_SIZE],
+    public: &sgx_x25519_public_t,
+) -> SgxResult<sgx_report_data_t> {
+    let sha = SgxShaHandle::new();
+    sha.init()?;
+    sha.update_slice(REQUEST_LABEL)?;
+    sha.update_slice(nonce)?;
+    sha.update_slice(&public[..])?;
+    let hash = sha.get_hash()?;
+    let mut report_data = sgx_report_data_t::default();
+    report_data.d[..SGX_SHA256_HASH_SIZE].copy_from_slice(&hash);
+    Ok(report_data)
+}
+
+// What the service signs, and the context of the wrapping key.
+fn transcript(
+    nonce: &[u8; SGX_PROVISION_NONCE_SIZE],
+    client_public: &sgx_x25519_public_t,
+    service_public: &sgx_x25519_public_t,
+) -> Vec<u8> {
+    let mut transcript =
+        Vec::with_capacity(RESPONSE_LABEL.len() + SGX_PROVISION_NONCE_SIZE + 2 * PUBLIC_SIZE);
+    transcript.extend_from_slice(RESPONSE_LABEL);
+    transcript.extend_from_slice(nonce);
+    transcript.extend_from_slice(client_public);
+    transcript.extend_from_slice(service_public);
+    transcript
+}
+
+fn wrapping_key(
+    private: &SgxX25519PrivateKey,
+    peer: &sgx_x25519_public_t,
+    transcript: &[u8],
+) -> SgxResult<sgx_aes_gcm_128bit_key_t> {
+    let shared = private.diffie_hellman(peer)?;
+    let mut key = sgx_aes_gcm_128bit_key_t::default();
+    rsgx_hkdf_sha256(KEY_SALT, shared.as_bytes(), transcript, &mut key)?;
+    Ok(key)
+}
+
+fn signature_to_bytes(signature: &sgx_ec256_signature_t) -> [u8; SIGNATURE_SIZE] {
+    let mut out = [0_u8; SIGNATURE_SIZE];
+    for (i, word) in signature.x.iter().chain(signature.y.iter()).enumerate() {
+        out[4 * i..4 * i + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+fn signature_from_bytes(bytes: &[u8; SIGNATURE_SIZE]) -> sgx_ec256_signature_t {
+    let mut signature = sgx_ec256_signature_t::default();
+    for (i, word) in signature
+        .x
+        .iter_mut()
+        .chain(signature.y.iter_mut())
+        .enumerate()
+    {
+        *word = read_u32(&bytes[4 * i..]);
+    }
+    signature
+}
+
+fn write_header(out: &mut [u8], kind: u16) {
+    out[..4].copy_from_slice(&PROVISION_MAGIC);
+    out[4..6].copy_from_slice(&PROVISION_VERSION.to_le_bytes());
+    out[6..8].copy_from_slice(&kind.to_le_bytes());
+}
+
+fn check_header(bytes: &[u8], kind: u16) -> SgxError {
+    if bytes[..4] != PROVISION_MAGIC
+        || u16::from_le_bytes([bytes[4], bytes[5]]) != PROVISION_VERSION
+        || u16::from_le_bytes([bytes[6], bytes[7]]) != kind
+    {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    Ok(())
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
@@ -0,0 +1,443 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! ECDSA quote generation
+//!
+//! An ECDSA quote is produced by the Quoting Enclave (QE) of the platform. The
+//! enclave creates a report targeted at the QE for its report data, the host passes
+//! that report to the QE through AESM, and the QE returns the quote together with a
+//! report of its own. That QE report is targeted back at the enclave and commits to
+//! a nonce chosen by the enclave and to the quote, so the enclave can check that the
+//! quote came from the QE it targeted and was not replayed or altered by the host.
+//!
+//! Where AESM is not available, as in most containers, the host can instead load the
+//! QE in process through the DCAP quote library. That QE returns no report to the
+//! enclave; the quote is still checked to be of the enclave's report and to carry a
+//! QE report of the targeted QE, whose signature the verifier checks against the PCK
+//! certificate. The backend is chosen with `SgxQuoteBackend` when the generator is
+//! created.
+//!
+use alloc::vec::Vec;
+use core::mem;
+use core::slice;
+use sgx_tcrypto::{rsgx_ct_eq, SgxShaHandle};
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_tse::*;
+use sgx_types::quote::SgxQuote;
+use sgx_types::*;
+
+/// The largest quote accepted from the host.
+pub const SGX_QUOTE_MAX_SIZE: u32 = 0x10_0000;
+/// The quote format version produced for ECDSA attestation keys.
+pub const SGX_QUOTE_ECDSA_VERSION: u16 = 3;
+
+extern "C" {
+    fn u_quote_ex_init_ocall(
+        result: *mut sgx_status_t,
+        att_key_id: *mut sgx_att_key_id_t,
+        qe_target_info: *mut sgx_target_info_t,
+    ) -> sgx_status_t;
+    fn u_quote_ex_get_quote_size_ocall(
+        result: *mut sgx_status_t,
+        att_key_id: *const sgx_att_key_id_t,
+        quote_size: *mut uint32_t,
+    ) -> sgx_status_t;
+    fn u_quote_ex_get_quote_ocall(
+        result: *mut sgx_status_t,
+        report: *const sgx_report_t,
+        att_key_id: *const sgx_att_key_id_t,
+        qe_report_info: *mut sgx_qe_report_info_t,
+        quote: *mut uint8_t,
+        quote_size: uint32_t,
+    ) -> sgx_status_t;
+    fn u_dcap_qe_get_target_info_ocall(
+        result: *mut sgx_quote3_error_t,
+        qe_target_info: *mut sgx_target_info_t,
+    ) -> sgx_status_t;
+    fn u_dcap_qe_get_quote_size_ocall(
+        result: *mut sgx_quote3_error_t,
+        quote_size: *mut uint32_t,
+    ) -> sgx_status_t;
+    fn u_dcap_qe_get_quote_ocall(
+        result: *mut sgx_quote3_error_t,
+        report: *const sgx_report_t,
+        quote: *mut uint8_t,
+        quote_size: uint32_t,
+    ) -> sgx_status_t;
+}
+
+///
+/// The host interface quotes are produced through.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SgxQuoteBackend {
+    /// The quote_ex interface of AESM.
+    Aesm,
+    /// The QE loaded in process by the DCAP quote library, without AESM.
+    Dcap,
+    /// AESM if it can be initialized, the DCAP quote library otherwise.
+    Auto,
+}
+
+///
+/// Generates ECDSA quotes for the calling enclave.
+///
+/// The generator holds the attestation key ID and the target info of the QE, so
+/// several quotes can be produced without initializing the quoting infrastructure
+/// again.
+///
+pub struct SgxQuoteGenerator {
+    backend: SgxQuoteBackend,
+    att_key_id: sgx_att_key_id_t,
+    qe_target_info: sgx_target_info_t,
+}
+
+impl SgxQuoteGenerator {
+    ///
+    /// Selects the ECDSA P-256 attestation key of the platform and fetches the target
+    /// info of its QE.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_UNSUPPORTED_ATT_KEY_ID**
+    ///
+    /// The platform has no ECDSA P-256 attestation key.
+    ///
+    /// **SGX_ERROR_SERVICE_UNAVAILABLE**
+    ///
+    /// AESM did not respond.
+    ///
+    /// The errors of the ocall itself, and the other errors of sgx_init_quote_ex.
+    ///
+    pub fn new() -> SgxResult<SgxQuoteGenerator> {
+        Self::with_backend(SgxQuoteBackend::Aesm)
+    }
+
+    ///
+    /// Initializes the quoting infrastructure through the given backend.
+    ///
+    /// # Errors
+    ///
+    /// The errors of new for AESM.
+    ///
+    /// **SGX_ERROR_SERVICE_UNAVAILABLE**
+    ///
+    /// The DCAP quote library is not installed on the host, or could not load the QE or
+    /// find the certification data of the platform.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The other errors of sgx_qe_get_target_info.
+    ///
+    /// With Auto, the errors of the DCAP quote library when AESM failed too.
+    ///
+    pub fn with_backend(backend: SgxQuoteBackend) -> SgxResult<SgxQuoteGenerator> {
+        match backend {
+            SgxQuoteBackend::Aesm => Self::init_aesm(),
+            SgxQuoteBackend::Dcap => Self::init_dcap(),
+            SgxQuoteBackend::Auto => Self::init_aesm().or_else(|_| Self::init_dcap()),
+        }
+    }
+
+    fn init_aesm() -> SgxResult<SgxQuoteGenerator> {
+        let mut result = sgx_status_t::SGX_SUCCESS;
+        let mut att_key_id = sgx_att_key_id_t::default();
+        let mut qe_target_info = sgx_target_info_t::default();
+        let status = unsafe {
+            u_quote_ex_init_ocall(
+                &mut result as *mut sgx_status_t,
+                &mut att_key_id as *mut sgx_att_key_id_t,
+                &mut qe_target_info as *mut sgx_target_info_t,
+            )
+        };
+        check_ocall(status, result)?;
+        Ok(SgxQuoteGenerator {
+            backend: SgxQuoteBackend::Aesm,
+            att_key_id,
+            qe_target_info,
+        })
+    }
+
+    fn init_dcap() -> SgxResult<SgxQuoteGenerator> {
+        let mut result = sgx_quote3_error_t::SGX_QL_SUCCESS;
+        let mut qe_target_info = sgx_target_info_t::default();
+        let status = unsafe {
+            u_dcap_qe_get_target_info_ocall(
+                &mut result as *mut sgx_quote3_error_t,
+                &mut qe_target_info as *mut sgx_target_info_t,
+            )
+        };
+        check_dcap_ocall(status, result)?;
+        Ok(SgxQuoteGenerator {
+            backend: SgxQuoteBackend::Dcap,
+            att_key_id: sgx_att_key_id_t::default(),
+            qe_target_info,
+        })
+    }
+
+    ///
+    /// Get the backend the quotes are produced through, Aesm or Dcap.
+    ///
+    pub fn get_backend(&self) -> SgxQuoteBackend {
+        self.backend
+    }
+
+    ///
+    /// Get the target info of the QE.
+    ///
+    pub fn get_qe_target_info(&self) -> &sgx_target_info_t {
+        &self.qe_target_info
+    }
+
+    ///
+    /// Get the attestation key ID the quotes are produced with. The DCAP backend has a
+    /// single ECDSA key and leaves it zero.
+    ///
+    pub fn get_att_key_id(&self) -> &sgx_att_key_id_t {
+        &self.att_key_id
+    }
+
+    ///
+    /// Creates a report of the calling enclave targeted at the QE.
+    ///
+    /// # Errors
+    ///
+    /// The errors of rsgx_create_report.
+    ///
+    pub fn create_report(&self, report_data: &sgx_report_data_t) -> SgxResult<sgx_report_t> {
+        rsgx_create_report(&self.qe_target_info, report_data)
+    }
+
+    ///
+    /// Produces a quote over report_data.
+    ///
+    /// # Errors
+    ///
+    /// The errors of create_report and get_quote_for_report.
+    ///
+    pub fn get_quote(&self, report_data: &sgx_report_data_t) -> SgxResult<Vec<u8>> {
+        let report = self.create_report(report_data)?;
+        self.get_quote_for_report(&report)
+    }
+
+    ///
+    /// Produces a quote for a report created by create_report.
+    ///
+    /// The QE report returned with the quote is verified, and the quote is checked to
+    /// be an ECDSA quote of exactly this report. With the DCAP backend, the QE report
+    /// in the quote is checked to be of the targeted QE instead.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The host returned a quote size of zero or larger than SGX_QUOTE_MAX_SIZE, or the
+    /// quote is not an ECDSA quote of the report.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The host returned another nonce than the one drawn for the quote, or the QE
+    /// report does not verify, comes from another enclave than the QE, or does not
+    /// commit to the nonce and the quote.
+    ///
+    /// The errors of the ocalls, and of sgx_get_quote_size_ex and sgx_get_quote_ex, or
+    /// of sgx_qe_get_quote_size and sgx_qe_get_quote mapped as in with_backend.
+    ///
+    pub fn get_quote_for_report(&self, report: &sgx_report_t) -> SgxResult<Vec<u8>> {
+        if self.backend == SgxQuoteBackend::Dcap {
+            return self.get_dcap_quote(report);
+        }
+        let mut result = sgx_status_t::SGX_SUCCESS;
+        let mut quote_size: u32 = 0;
+        let status = unsafe {
+            u_quote_ex_get_quote_size_ocall(
+                &mut result as *mut sgx_status_t,
+                &self.att_key_id as *const sgx_att_key_id_t,
+                &mut quote_size as *mut u32,
+            )
+        };
+        check_ocall(status, result)?;
+        if (quote_size as usize) < mem::size_of::<sgx_quote3_t>() || quote_size > SGX_QUOTE_MAX_SIZE
+        {
+            return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+        }
+
+        let mut nonce = sgx_quote_nonce_t::default();
+        rsgx_read_rand(&mut nonce.rand)?;
+        let mut qe_report_info = sgx_qe_report_info_t {
+            nonce,
+            app_enclave_target_info: self_target_info(),
+            ..Default::default()
+        };
+        let mut quote = vec![0_u8; quote_size as usize];
+        let status = unsafe {
+            u_quote_ex_get_quote_ocall(
+                &mut result as *mut sgx_status_t,
+                report as *const sgx_report_t,
+                &self.att_key_id as *const sgx_att_key_id_t,
+                &mut qe_report_info as *mut sgx_qe_report_info_t,
+                quote.as_mut_ptr(),
+                quote_size,
+            )
+        };
+        check_ocall(status, result)?;
+
+        self.check_qe_report(&qe_report_info, &nonce, &quote)?;
+        check_quote(report, &quote)?;
+        Ok(quote)
+    }
+
+    fn get_dcap_quote(&self, report: &sgx_report_t) -> SgxResult<Vec<u8>> {
+        let mut result = sgx_quote3_error_t::SGX_QL_SUCCESS;
+        let mut quote_size: u32 = 0;
+        let status = unsafe {
+            u_dcap_qe_get_quote_size_ocall(
+                &mut result as *mut sgx_quote3_error_t,
+                &mut quote_size as *mut u32,
+            )
+        };
+        check_dcap_ocall(status, result)?;
+        if (quote_size as usize) < mem::size_of::<sgx_quote3_t>() || quote_size > SGX_QUOTE_MAX_SIZE
+        {
+            return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+        }
+
+        let mut quote = vec![0_u8; quote_size as usize];
+        let status = unsafe {
+            u_dcap_qe_get_quote_ocall(
+                &mut result as *mut sgx_quote3_error_t,
+                report as *const sgx_report_t,
+                quote.as_mut_ptr(),
+                quote_size,
+            )
+        };
+        check_dcap_ocall(status, result)?;
+
+        check_quote(report, &quote)?;
+        let parsed = SgxQuote::parse(&quote).map_err(|_| sgx_status_t::SGX_ERROR_UNEXPECTED)?;
+        let qe_report = parsed.get_qe_report_body();
+        if qe_report.mr_enclave.m != self.qe_target_info.mr_enclave.m {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        Ok(quote)
+    }
+
+    fn check_qe_report(
+        &self,
+        qe_report_info: &sgx_qe_report_info_t,
+        nonce: &sgx_quote_nonce_t,
+        quote: &[u8],
+    ) -> SgxError {
+        // sgx_qe_report_info_t is packed, so its fields are copied out. The host
+        // writes the whole of it, so the nonce it returns is only compared with
+        // the one drawn here; a replayed quote would come back with an old one.
+        let qe_report = qe_report_info.qe_report;
+        let returned_nonce = qe_report_info.nonce;
+        if !rsgx_ct_eq(&returned_nonce.rand, &nonce.rand) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        rsgx_verify_report(&qe_report).map_err(|_| sgx_status_t::SGX_ERROR_MAC_MISMATCH)?;
+        if qe_report.body.mr_enclave.m != self.qe_target_info.mr_enclave.m {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+
+        // The QE report data is SHA256(nonce || quote) followed by 32 zero bytes.
+        let sha = SgxShaHandle::new();
+        sha.init()?;
+        sha.update_slice(&nonce.rand)?;
+        sha.update_slice(quote)?;
+        let hash = sha.get_hash()?;
+        let mut expected = sgx_report_data_t::default();
+        expected.d[..SGX_SHA256_HASH_SIZE].copy_from_slice(&hash);
+        if !rsgx_ct_eq(&expected.d, &qe_report.body.report_data.d) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        Ok(())
+    }
+}
+
+///
+/// rsgx_get_ecdsa_quote produces an ECDSA quote over report_data in one step.
+///
+/// # Errors
+///
+/// The errors of SgxQuoteGenerator::new and SgxQuoteGenerator::get_quote.
+///
+pub fn rsgx_get_ecdsa_quote(report_data: &sgx_report_data_t) -> SgxResult<Vec<u8>> {
+    SgxQuoteGenerator::new()?.get_quote(report_data)
+}
+
+fn check_ocall(status: sgx_status_t, result: sgx_status_t) -> SgxError {
+    if status != sgx_status_t::SGX_SUCCESS {
+        return Err(status);
+    }
+    if result != sgx_status_t::SGX_SUCCESS {
+        return Err(result);
+    }
+    Ok(())
+}
+
+fn check_dcap_ocall(status: sgx_status_t, result: sgx_quote3_error_t) -> SgxError {
+    if status != sgx_status_t::SGX_SUCCESS {
+        return Err(status);
+    }
+    match result {
+        sgx_quote3_error_t::SGX_QL_SUCCESS => Ok(()),
+        sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER => {
+            Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        }
+        sgx_quote3_error_t::SGX_QL_ERROR_OUT_OF_MEMORY => {
+            Err(sgx_status_t::SGX_ERROR_OUT_OF_MEMORY)
+        }
+        sgx_quote3_error_t::SGX_QL_PLATFORM_LIB_UNAVAILABLE
+        | sgx_quote3_error_t::SGX_QL_INTERFACE_UNAVAILABLE
+        | sgx_quote3_error_t::SGX_QL_ENCLAVE_LOAD_ERROR
+        | sgx_quote3_error_t::SGX_QL_NO_PLATFORM_CERT_DATA => {
+            Err(sgx_status_t::SGX_ERROR_SERVICE_UNAVAILABLE)
+        }
+        _ => Err(sgx_status_t::SGX_ERROR_UNEXPECTED),
+    }
+}
+
+fn self_target_info() -> sgx_target_info_t {
+    let body = rsgx_self_report().body;
+    sgx_target_info_t {
+        mr_enclave: body.mr_enclave,
+        attributes: body.attributes,
+        config_svn: body.config_svn,
+        misc_select: body.misc_select,
+        config_id: body.config_id,
+        ..Default::default()
+    }
+}
+
+fn check_quote(report: &sgx_report_t, quote: &[u8]) -> SgxError {
+    let header_size = mem::size_of::<sgx_quote_header_t>();
+    let body_size = mem::size_of::<sgx_report_body_t>();
+    let version = u16::from_le_bytes([quote[0], quote[1]]);
+    let att_key_type = u16::from_le_bytes([quote[2], quote[3]]);
+    if version != SGX_QUOTE_ECDSA_VERSION
+        || att_key_type != sgx_ql_attestation_algorithm_id_t::SGX_QL_ALG_ECDSA_P256 as u16
+    {
+        return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+    }
+    let body = unsafe { slice::from_raw_parts(&report.body as *const _ as *const u8, body_size) };
+    if quote[header_size..header_size + body_size] != *body {
+        return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+    }
+    Ok(())
+}
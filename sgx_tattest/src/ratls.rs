@@ -0,0 +1,277 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! RA-TLS certificates
+//!
+//! An RA-TLS certificate is a self-signed X.509 certificate whose key was generated
+//! inside the enclave and which carries an ECDSA quote of the enclave in an
+//! extension. The quote commits to the key: the first 32 bytes of its report data are
+//! the SHA-256 hash of the uncompressed public point (0x04 || x || y, big-endian) and
+//! the rest is zero. A peer that verifies the quote and the binding knows that the
+//! other end of the TLS session is the quoted enclave, without a CA.
+//!
+//! The certificate and the PKCS#8 private key are returned in DER, which is what
+//! `rustls::Certificate` and `rustls::PrivateKey` take. On the verifying side, a
+//! custom rustls certificate verifier calls one of the verification functions with
+//! the end-entity certificate and then checks the identity in the report body.
+//!
+use crate::quote::SgxQuoteGenerator;
+use crate::verify::{
+    rsgx_verify_quote_with_qve, SgxQuoteCollateral, SgxQuoteVerificationResult, SgxQuoteVerifier,
+};
+use alloc::vec::Vec;
+use sgx_tcrypto::*;
+use sgx_types::*;
+
+/// The extension carrying the quote, as used by Intel(R) RA-TLS.
+pub const SGX_RATLS_QUOTE_OID: &[u64] = &[1, 2, 840, 113741, 1337, 6];
+
+///
+/// A certificate and private key for the TLS server or client of an enclave.
+///
+/// The private key is zeroed when the value is dropped.
+///
+pub struct SgxRaTlsCertificate {
+    certificate: Vec<u8>,
+    private_key: Vec<u8>,
+    quote_len: usize,
+}
+
+impl SgxRaTlsCertificate {
+    ///
+    /// Generates a P-256 key pair, gets a quote over the public key from the QE of
+    /// generator, and issues a certificate for subject that is valid from not_before
+    /// to not_after, in seconds since the Unix epoch.
+    ///
+    /// # Errors
+    ///
+    /// The errors of the key generation, of SgxQuoteGenerator::get_quote, and of
+    /// SgxX509Builder::sign.
+    ///
+    pub fn new(
+        generator: &SgxQuoteGenerator,
+        subject: SgxX509Name,
+        not_before: u64,
+        not_after: u64,
+    ) -> SgxResult<SgxRaTlsCertificate> {
+        let handle = SgxEccHandle::new();
+        handle.open()?;
+        let (mut private, public) = handle.create_key_pair()?;
+
+        let result = (|| {
+            let point = uncompressed_point(&public);
+            let hash = rsgx_sha256_slice(&point)?;
+            let mut report_data = sgx_report_data_t::default();
+            report_data.d[..SGX_SHA256_HASH_SIZE].copy_from_slice(&hash);
+            let quote = generator.get_quote(&report_data)?;
+
+            let certificate = SgxX509Builder::new(subject, SgxX509PublicKey::EcP256(public))
+                .validity(not_before, not_after)
+                .extension(SGX_RATLS_QUOTE_OID, false, &quote)
+                .sign(SgxX509SigningKey::EcP256(&private))?;
+            Ok(SgxRaTlsCertificate {
+                certificate,
+                private_key: pkcs8_private_key(&private, &point),
+                quote_len: quote.len(),
+            })
+        })();
+        rsgx_zeroize(&mut private.r);
+        result
+    }
+
+    ///
+    /// Get the DER encoded certificate.
+    ///
+    pub fn get_certificate_der(&self) -> &[u8] {
+        &self.certificate
+    }
+
+    ///
+    /// Get the DER encoded PKCS#8 private key.
+    ///
+    pub fn get_private_key_der(&self) -> &[u8] {
+        &self.private_key
+    }
+
+    ///
+    /// Get the size of the embedded quote.
+    ///
+    pub fn get_quote_len(&self) -> usize {
+        self.quote_len
+    }
+}
+
+impl Drop for SgxRaTlsCertificate {
+    fn drop(&mut self) {
+        rsgx_zeroize(&mut self.private_key);
+    }
+}
+
+///
+/// rsgx_ratls_generate_certificate creates the quote generator and an RA-TLS
+/// certificate in one step.
+///
+/// # Errors
+///
+/// The errors of SgxQuoteGenerator::new and SgxRaTlsCertificate::new.
+///
+pub fn rsgx_ratls_generate_certificate(
+    subject: SgxX509Name,
+    not_before: u64,
+    not_after: u64,
+) -> SgxResult<SgxRaTlsCertificate> {
+    let generator = SgxQuoteGenerator::new()?;
+    SgxRaTlsCertificate::new(&generator, subject, not_before, not_after)
+}
+
+///
+/// rsgx_ratls_extract_quote returns the quote of an RA-TLS certificate, for
+/// instance to fetch the collateral matching its FMSPC. Nothing is verified.
+///
+/// # Errors
+///
+/// **SGX_QL_ERROR_INVALID_PARAMETER**
+///
+/// The certificate cannot be parsed or has no quote extension.
+///
+pub fn rsgx_ratls_extract_quote(certificate: &[u8]) -> SgxQuote3Result<&[u8]> {
+    let cert = SgxX509Certificate::parse(certificate)
+        .map_err(|_| sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER)?;
+    cert.extension(SGX_RATLS_QUOTE_OID)
+        .map(|(_, quote)| quote)
+        .ok_or(sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER)
+}
+
+///
+/// rsgx_ratls_verify_certificate verifies an RA-TLS certificate inside the enclave:
+/// the certificate must be self-signed and valid at current_time, its quote must
+/// verify against collateral, and the quote must commit to the certificate key.
+///
+/// # Errors
+///
+/// **SGX_QL_ERROR_INVALID_PARAMETER**
+///
+/// The certificate cannot be parsed, has no quote extension, has a key other than
+/// P-256, or is not valid at current_time.
+///
+/// **SGX_QL_INVALID_REPORT**
+///
+/// The certificate is not signed by its own key, or the report data of the quote
+/// does not match the key.
+///
+/// The errors of SgxQuoteVerifier::verify.
+///
+pub fn rsgx_ratls_verify_certificate(
+    verifier: &SgxQuoteVerifier,
+    certificate: &[u8],
+    collateral: &SgxQuoteCollateral,
+    current_time: u64,
+) -> SgxQuote3Result<SgxQuoteVerificationResult> {
+    let (quote, key) = check_certificate(certificate, current_time)?;
+    let result = verifier.verify(quote, collateral, current_time)?;
+    check_binding(&result, &key)?;
+    Ok(result)
+}
+
+///
+/// rsgx_ratls_verify_certificate_with_qve verifies an RA-TLS certificate like
+/// rsgx_ratls_verify_certificate, with the quote verified by the QvE of the host.
+///
+/// # Errors
+///
+/// The errors of rsgx_ratls_verify_certificate, and of rsgx_verify_quote_with_qve
+/// instead of those of SgxQuoteVerifier::verify.
+///
+pub fn rsgx_ratls_verify_certificate_with_qve(
+    certificate: &[u8],
+    current_time: u64,
+    qve_isvsvn_threshold: sgx_isv_svn_t,
+) -> SgxQuote3Result<SgxQuoteVerificationResult> {
+    let (quote, key) = check_certificate(certificate, current_time)?;
+    let time = current_time
+        .try_into()
+        .map_err(|_| sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER)?;
+    let result = rsgx_verify_quote_with_qve(quote, time, qve_isvsvn_threshold)?;
+    check_binding(&result, &key)?;
+    Ok(result)
+}
+
+fn check_certificate(
+    certificate: &[u8],
+    current_time: u64,
+) -> SgxQuote3Result<(&[u8], sgx_ec256_public_t)> {
+    let cert = SgxX509Certificate::parse(certificate)
+        .map_err(|_| sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER)?;
+    let key = match cert.public_key() {
+        Some(SgxX509PublicKey::EcP256(key)) => *key,
+        _ => return Err(sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER),
+    };
+    if !cert.is_valid_at(current_time) {
+        return Err(sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER);
+    }
+    let signed = cert.verify_signature(&SgxX509PublicKey::EcP256(key));
+    if !signed.unwrap_or(false) {
+        return Err(sgx_quote3_error_t::SGX_QL_INVALID_REPORT);
+    }
+    let (_, quote) = cert
+        .extension(SGX_RATLS_QUOTE_OID)
+        .ok_or(sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER)?;
+    Ok((quote, key))
+}
+
+fn check_binding(
+    result: &SgxQuoteVerificationResult,
+    key: &sgx_ec256_public_t,
+) -> SgxQuote3Result<()> {
+    let hash = rsgx_sha256_slice(&uncompressed_point(key))
+        .map_err(|_| sgx_quote3_error_t::SGX_QL_ERROR_UNEXPECTED)?;
+    let mut expected = sgx_report_data_t::default();
+    expected.d[..SGX_SHA256_HASH_SIZE].copy_from_slice(&hash);
+    if !rsgx_ct_eq(&expected.d, &result.get_report_body().report_data.d) {
+        return Err(sgx_quote3_error_t::SGX_QL_INVALID_REPORT);
+    }
+    Ok(())
+}
+
+// 0x04 || x || y, with the coordinates big-endian.
+fn uncompressed_point(key: &sgx_ec256_public_t) -> [u8; 65] {
+    let mut point = [0_u8; 65];
+    point[0] = 0x04;
+    for i in 0..32 {
+        point[1 + i] = key.gx[31 - i];
+        point[33 + i] = key.gy[31 - i];
+    }
+    point
+}
+
+// The PKCS#8 PrivateKeyInfo of an ECPrivateKey with the public key and without the
+// curve parameters, which are in the algorithm identifier. This is the form ring,
+// and so rustls, accepts.
+fn pkcs8_private_key(private: &sgx_ec256_private_t, point: &[u8; 65]) -> Vec<u8> {
+    const PREFIX: &[u8] = &[
+        0x30, 0x81, 0x87, 0x02, 0x01, 0x00, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d,
+        0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x04, 0x6d, 0x30,
+        0x6b, 0x02, 0x01, 0x01, 0x04, 0x20,
+    ];
+    const PUBLIC: &[u8] = &[0xa1, 0x44, 0x03, 0x42, 0x00];
+    let mut der = Vec::with_capacity(PREFIX.len() + 32 + PUBLIC.len() + point.len());
+    der.extend_from_slice(PREFIX);
+    der.extend(private.r.iter().rev());
+    der.extend_from_slice(PUBLIC);
+    der.extend_from_slice(point);
+    der
+}
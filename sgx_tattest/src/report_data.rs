@@ -0,0 +1,122 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! Report data binding
+//!
+//! The 64 bytes of report data are all an enclave has to tie its quote or report to
+//! the rest of a protocol: its public keys, the transcript of a session, the hash of
+//! the policy it runs under. Packing them in by hand invites ambiguous encodings, where
+//! two different sets of values give the same bytes, and hashes that mean one thing in
+//! one protocol and another in the next.
+//!
+//! `SgxReportData::bind` hashes any number of values into the report data with each
+//! value prefixed by its length, under a domain that names the protocol. The relying
+//! party recomputes it from the values it expects with `SgxReportData::verify`.
+//!
+//! The first half of the report data is the SHA-256 hash of a label, the domain and the
+//! values, all but the label prefixed with their length as a 32-bit little-endian
+//! integer; the second half is zero.
+//!
+use sgx_tcrypto::*;
+use sgx_types::*;
+
+const REPORT_DATA_LABEL: &[u8] = b"sgx_tattest report data";
+
+///
+/// Binds application data into report data.
+///
+pub struct SgxReportData;
+
+impl SgxReportData {
+    ///
+    /// Computes the report data for `values` with no domain.
+    ///
+    /// # Errors
+    ///
+    /// The errors of bind_in.
+    ///
+    pub fn bind(values: &[&[u8]]) -> SgxResult<sgx_report_data_t> {
+        Self::bind_in(&[], values)
+    }
+
+    ///
+    /// Computes the report data for `values` in `domain`. Values bound in one domain do
+    /// not verify in another.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The domain or a value is 4 GiB or larger.
+    ///
+    /// **SGX_ERROR_OUT_OF_MEMORY**
+    ///
+    /// Not enough memory is available to complete this operation.
+    ///
+    pub fn bind_in(domain: &[u8], values: &[&[u8]]) -> SgxResult<sgx_report_data_t> {
+        let sha = SgxShaHandle::new();
+        sha.init()?;
+        sha.update_slice(REPORT_DATA_LABEL)?;
+        for value in core::iter::once(&domain).chain(values.iter()) {
+            let len: u32 = value
+                .len()
+                .try_into()
+                .map_err(|_| sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+            sha.update_slice(&len.to_le_bytes())?;
+            // The hash functions reject empty input; an empty value is its length alone.
+            if !value.is_empty() {
+                sha.update_slice(value)?;
+            }
+        }
+        let hash = sha.get_hash()?;
+
+        let mut report_data = sgx_report_data_t::default();
+        report_data.d[..SGX_SHA256_HASH_SIZE].copy_from_slice(&hash);
+        Ok(report_data)
+    }
+
+    ///
+    /// Checks that `report_data` binds `values` with no domain.
+    ///
+    /// # Errors
+    ///
+    /// The errors of verify_in.
+    ///
+    pub fn verify(report_data: &sgx_report_data_t, values: &[&[u8]]) -> SgxError {
+        Self::verify_in(&[], report_data, values)
+    }
+
+    ///
+    /// Checks that `report_data` binds `values` in `domain`.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The report data binds other values, or the same values in another domain.
+    ///
+    /// The errors of bind_in.
+    ///
+    pub fn verify_in(domain: &[u8], report_data: &sgx_report_data_t, values: &[&[u8]]) -> SgxError {
+        let expected = Self::bind_in(domain, values)?;
+        if rsgx_ct_eq(&expected.d, &report_data.d) {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+        }
+    }
+}
@@ -0,0 +1,514 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! ECDSA quote verification
+//!
+//! A quote can be verified in two ways. SgxQuoteVerifier checks it entirely inside
+//! the enclave against collateral fetched from Intel(R) PCS or a PCCS: the PCK
+//! certificate chain, the TCB info and the QE identity, all rooted in a pinned root
+//! CA. rsgx_verify_quote_with_qve instead lets the Quote Verification Enclave do the
+//! work through the host, and then checks the report of the QvE so that the verdict
+//! does not have to be taken from the untrusted side.
+//!
+//! SgxQuoteVerifier checks the revocation lists of the collateral when it includes
+//! them, and refuses quotes certified by a revoked PCK certificate.
+//!
+use crate::collateral::{ec256_public_from_be, ecdsa_p256_verify, SgxQeIdentity, SgxTcbInfo};
+use crate::pck::{crl_der, parse_crl, SgxPckCertChain};
+use crate::quote::SGX_QUOTE_ECDSA_VERSION;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem;
+use core::ptr;
+use sgx_tcrypto::{rsgx_ct_eq, rsgx_sha256_slice, SgxX509Certificate, SgxX509PublicKey};
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_tse::rsgx_self_report;
+use sgx_types::quote::SgxQuote;
+use sgx_types::*;
+
+/// The largest supplemental data accepted from the QvE.
+pub const SGX_QV_SUPPLEMENTAL_MAX_SIZE: u32 = 0x1000;
+
+extern "C" {
+    fn u_qv_get_supplemental_data_size_ocall(
+        result: *mut sgx_quote3_error_t,
+        size: *mut uint32_t,
+    ) -> sgx_status_t;
+    fn u_qv_verify_quote_ocall(
+        result: *mut sgx_quote3_error_t,
+        quote: *const uint8_t,
+        quote_size: uint32_t,
+        expiration_check_date: time_t,
+        collateral_expiration_status: *mut uint32_t,
+        qv_result: *mut sgx_ql_qv_result_t,
+        qve_report_info: *mut sgx_ql_qe_report_info_t,
+        supplemental_data: *mut uint8_t,
+        supplemental_data_size: uint32_t,
+    ) -> sgx_status_t;
+}
+
+///
+/// The collateral a quote is verified against, as returned by Intel(R) PCS.
+///
+/// The certificate chains are PEM encoded, leaf first. The PCK certificate chain is
+/// only needed when the quote does not carry it in its certification data. The CRLs
+/// of the root CA and of the PCK CA, in DER, PEM or hex, are required: a quote is
+/// not verified without them.
+///
+#[derive(Clone, Default)]
+pub struct SgxQuoteCollateral {
+    pub pck_cert_chain: Option<Vec<u8>>,
+    pub root_ca_crl: Vec<u8>,
+    pub pck_crl: Vec<u8>,
+    pub tcb_info: Vec<u8>,
+    pub tcb_info_issuer_chain: Vec<u8>,
+    pub qe_identity: Vec<u8>,
+    pub qe_identity_issuer_chain: Vec<u8>,
+}
+
+///
+/// The outcome of a quote verification.
+///
+/// A quote whose signatures do not verify is an error; a valid quote of a platform
+/// that is not up to date is a result with that status. The relying party decides
+/// which statuses and advisories it accepts.
+///
+#[derive(Clone)]
+pub struct SgxQuoteVerificationResult {
+    tcb_status: sgx_ql_qv_result_t,
+    advisory_ids: Vec<String>,
+    collateral_expired: bool,
+    earliest_issue_date: u64,
+    report_body: sgx_report_body_t,
+}
+
+impl SgxQuoteVerificationResult {
+    ///
+    /// Get the TCB status of the platform and the QE, combined.
+    ///
+    pub fn get_tcb_status(&self) -> sgx_ql_qv_result_t {
+        self.tcb_status
+    }
+
+    ///
+    /// Get the Intel security advisories that apply to the platform or the QE.
+    ///
+    pub fn get_advisory_ids(&self) -> &[String] {
+        &self.advisory_ids
+    }
+
+    ///
+    /// Whether a certificate or the TCB info or QE identity was out of date at the
+    /// verification time. The status is then the one of the expired collateral.
+    ///
+    pub fn is_collateral_expired(&self) -> bool {
+        self.collateral_expired
+    }
+
+    ///
+    /// Get the earliest issue date of the collateral, in seconds since the Unix epoch,
+    /// or 0 if the QvE did not return it.
+    ///
+    pub fn get_earliest_issue_date(&self) -> u64 {
+        self.earliest_issue_date
+    }
+
+    ///
+    /// Get the report body of the quoted enclave.
+    ///
+    pub fn get_report_body(&self) -> &sgx_report_body_t {
+        &self.report_body
+    }
+}
+
+///
+/// Verifies ECDSA quotes inside the enclave.
+///
+pub struct SgxQuoteVerifier {
+    root_key: SgxX509PublicKey,
+}
+
+impl SgxQuoteVerifier {
+    ///
+    /// Creates a verifier that trusts the root CA certificate given in DER, normally
+    /// the Intel SGX Root CA.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_QL_ROOT_CA_UNTRUSTED**
+    ///
+    /// The certificate cannot be parsed or does not hold an ECDSA P-256 key.
+    ///
+    pub fn new(root_ca_der: &[u8]) -> SgxQuote3Result<SgxQuoteVerifier> {
+        let root = SgxX509Certificate::parse(root_ca_der)
+            .map_err(|_| sgx_quote3_error_t::SGX_QL_ROOT_CA_UNTRUSTED)?;
+        match root.public_key() {
+            Some(key @ SgxX509PublicKey::EcP256(_)) => Ok(SgxQuoteVerifier { root_key: *key }),
+            _ => Err(sgx_quote3_error_t::SGX_QL_ROOT_CA_UNTRUSTED),
+        }
+    }
+
+    ///
+    /// Verifies a quote against collateral at current_time, in seconds since the
+    /// Unix epoch.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_QL_QUOTE_FORMAT_UNSUPPORTED**
+    ///
+    /// The quote is not a well formed version 3 ECDSA P-256 quote.
+    ///
+    /// **SGX_QL_INVALID_REPORT**
+    ///
+    /// The quote signature does not verify with the attestation key.
+    ///
+    /// **SGX_QL_QE_REPORT_INVALID_SIGNATURE**
+    ///
+    /// The QE report is not signed by the PCK certificate, or does not commit to the
+    /// attestation key.
+    ///
+    /// **SGX_QL_PCK_CERT_CHAIN_ERROR**, **SGX_QL_TCBINFO_CHAIN_ERROR**,
+    /// **SGX_QL_QEIDENTITY_CHAIN_ERROR**, **SGX_QL_ROOT_CA_UNTRUSTED**
+    ///
+    /// A certificate chain or the signature of a collateral document does not verify,
+    /// or a collateral document is not signed by the Intel SGX TCB Signing certificate.
+    ///
+    /// **SGX_QL_PCK_REVOKED**, **SGX_QL_CRL_UNSUPPORTED_FORMAT**
+    ///
+    /// The PCK certificate or its CA is revoked, or a CRL is missing or cannot be
+    /// decoded.
+    ///
+    /// **SGX_QL_TCBINFO_MISMATCH**, **SGX_QL_QEIDENTITY_MISMATCH**
+    ///
+    /// The TCB info is for another platform, or the QE is not the one of the QE
+    /// identity.
+    ///
+    /// **SGX_QL_TCB_NOT_SUPPORTED**, **SGX_QL_QE_IDENTITY_OUT_OF_DATE**
+    ///
+    /// The platform or the QE is below all the TCB levels.
+    ///
+    /// The parse errors of the PCK certificate, the TCB info and the QE identity.
+    ///
+    pub fn verify(
+        &self,
+        quote: &[u8],
+        collateral: &SgxQuoteCollateral,
+        current_time: u64,
+    ) -> SgxQuote3Result<SgxQuoteVerificationResult> {
+        let parsed = parse_quote_v3(quote)?;
+
+        // The attestation key signs the header and the report body.
+        let attest_key = ec256_public_from_be(parsed.get_attest_key());
+        if !ecdsa_p256_verify(
+            parsed.get_signed_data(),
+            &attest_key,
+            parsed.get_signature(),
+        )? {
+            return Err(sgx_quote3_error_t::SGX_QL_INVALID_REPORT);
+        }
+
+        // The QE commits to the attestation key in its report data.
+        let mut hashed = Vec::with_capacity(64 + parsed.get_auth_data().len());
+        hashed.extend_from_slice(parsed.get_attest_key());
+        hashed.extend_from_slice(parsed.get_auth_data());
+        let hash =
+            rsgx_sha256_slice(&hashed).map_err(|_| sgx_quote3_error_t::SGX_QL_ERROR_UNEXPECTED)?;
+        let qe_report_body = parsed.get_qe_report_body();
+        let qe_report_data = &qe_report_body.report_data.d;
+        if qe_report_data[..32] != hash || qe_report_data[32..].iter().any(|b| *b != 0) {
+            return Err(sgx_quote3_error_t::SGX_QL_QE_REPORT_INVALID_SIGNATURE);
+        }
+
+        // The PCK certificate signs the QE report.
+        let chain = match parsed.get_pck_cert_chain() {
+            Some(chain) => chain,
+            None => collateral
+                .pck_cert_chain
+                .as_deref()
+                .ok_or(sgx_quote3_error_t::SGX_QL_QUOTE_CERTIFICATION_DATA_UNSUPPORTED)?,
+        };
+        let chain = SgxPckCertChain::from_pem(chain)?;
+        let mut valid = chain.verify(
+            &self.root_key,
+            &collateral.root_ca_crl,
+            &collateral.pck_crl,
+            current_time,
+        )?;
+        if !ecdsa_p256_verify(
+            parsed.get_qe_report(),
+            chain.get_pck_key(),
+            parsed.get_qe_report_signature(),
+        )? {
+            return Err(sgx_quote3_error_t::SGX_QL_QE_REPORT_INVALID_SIGNATURE);
+        }
+        let pck = chain.get_extensions();
+
+        // The root CA CRL also covers the TCB signing certificate.
+        let root_ca_crl_der = crl_der(&collateral.root_ca_crl)?;
+        let root_ca_crl = parse_crl(&root_ca_crl_der)?;
+
+        // The TCB level of the platform.
+        let tcb_info = SgxTcbInfo::parse(&collateral.tcb_info)?;
+        valid &= tcb_info.verify(
+            &collateral.tcb_info_issuer_chain,
+            &self.root_key,
+            &root_ca_crl,
+            current_time,
+        )?;
+        if tcb_info.get_fmspc() != pck.get_fmspc() || tcb_info.get_pce_id() != pck.get_pce_id() {
+            return Err(sgx_quote3_error_t::SGX_QL_TCBINFO_MISMATCH);
+        }
+        let level = tcb_info
+            .find_level(pck.get_tcb_components(), pck.get_pce_svn())
+            .ok_or(sgx_quote3_error_t::SGX_QL_TCB_NOT_SUPPORTED)?;
+
+        // The TCB level of the QE.
+        let qe_identity = SgxQeIdentity::parse(&collateral.qe_identity)?;
+        valid &= qe_identity.verify(
+            &collateral.qe_identity_issuer_chain,
+            &self.root_key,
+            &root_ca_crl,
+            current_time,
+        )?;
+        if !qe_identity.matches(&qe_report_body) {
+            return Err(sgx_quote3_error_t::SGX_QL_QEIDENTITY_MISMATCH);
+        }
+        let qe_level = qe_identity
+            .find_level(qe_report_body.isv_svn)
+            .ok_or(sgx_quote3_error_t::SGX_QL_QE_IDENTITY_OUT_OF_DATE)?;
+
+        valid &= current_time <= tcb_info.get_next_update()
+            && current_time <= qe_identity.get_next_update();
+        let mut advisory_ids = level.get_advisory_ids().to_vec();
+        for id in qe_level.get_advisory_ids() {
+            if !advisory_ids.contains(id) {
+                advisory_ids.push(id.clone());
+            }
+        }
+        Ok(SgxQuoteVerificationResult {
+            tcb_status: converge_status(level.get_status(), qe_level.get_status()),
+            advisory_ids,
+            collateral_expired: !valid,
+            earliest_issue_date: tcb_info.get_issue_date().min(qe_identity.get_issue_date()),
+            report_body: parsed.get_report_body().unwrap(),
+        })
+    }
+}
+
+// The platform status, lowered by the status of the QE.
+fn converge_status(platform: sgx_ql_qv_result_t, qe: sgx_ql_qv_result_t) -> sgx_ql_qv_result_t {
+    use sgx_ql_qv_result_t::*;
+    match (qe, platform) {
+        (SGX_QL_QV_RESULT_REVOKED, _) => SGX_QL_QV_RESULT_REVOKED,
+        (
+            SGX_QL_QV_RESULT_OUT_OF_DATE,
+            SGX_QL_QV_RESULT_OK | SGX_QL_QV_RESULT_SW_HARDENING_NEEDED,
+        ) => SGX_QL_QV_RESULT_OUT_OF_DATE,
+        (
+            SGX_QL_QV_RESULT_OUT_OF_DATE,
+            SGX_QL_QV_RESULT_CONFIG_NEEDED | SGX_QL_QV_RESULT_CONFIG_AND_SW_HARDENING_NEEDED,
+        ) => SGX_QL_QV_RESULT_OUT_OF_DATE_CONFIG_NEEDED,
+        _ => platform,
+    }
+}
+
+// Parses a version 3 ECDSA quote of an SGX enclave, the only quotes verified here.
+pub(crate) fn parse_quote_v3(quote: &[u8]) -> SgxQuote3Result<SgxQuote<'_>> {
+    let parsed = SgxQuote::parse(quote)?;
+    if parsed.get_version() != SGX_QUOTE_ECDSA_VERSION || parsed.get_report_body().is_none() {
+        return Err(sgx_quote3_error_t::SGX_QL_QUOTE_FORMAT_UNSUPPORTED);
+    }
+    Ok(parsed)
+}
+
+///
+/// rsgx_verify_quote_with_qve verifies a quote with the Quote Verification Enclave
+/// of the host, using the collateral the host's quote verification library fetches.
+///
+/// The QvE returns its verdict with a report targeted at this enclave. The report,
+/// its binding to the nonce, the quote and the verdict, and the identity of the QvE
+/// are checked here, rejecting QvEs with an ISVSVN below qve_isvsvn_threshold.
+/// The report body of the result is the one of the quote, which the QvE verified.
+///
+/// # Errors
+///
+/// **SGX_QL_QUOTE_FORMAT_UNSUPPORTED**
+///
+/// The quote is not a well formed version 3 ECDSA P-256 quote.
+///
+/// **SGX_QL_INVALID_REPORT**
+///
+/// The QvE found a signature of the quote or its certification to be invalid.
+///
+/// **SGX_QL_ERROR_UNEXPECTED**
+///
+/// The supplemental data size returned by the host is too large, or the QvE
+/// returned no verdict.
+///
+/// **SGX_QL_ERROR_REPORT**
+///
+/// The host returned another nonce than the one drawn for the verification.
+///
+/// **SGX_QL_QVEIDENTITY_MISMATCH**, **SGX_QL_QVE_OUT_OF_DATE**
+///
+/// The report does not come from a trusted QvE.
+///
+/// The errors of the ocalls, and of sgx_qv_verify_quote and
+/// sgx_tvl_verify_qve_report_and_identity.
+///
+pub fn rsgx_verify_quote_with_qve(
+    quote: &[u8],
+    current_time: i64,
+    qve_isvsvn_threshold: sgx_isv_svn_t,
+) -> SgxQuote3Result<SgxQuoteVerificationResult> {
+    let parsed = parse_quote_v3(quote)?;
+    let quote_size: u32 = quote
+        .len()
+        .try_into()
+        .map_err(|_| sgx_quote3_error_t::SGX_QL_QUOTE_FORMAT_UNSUPPORTED)?;
+
+    let mut result = sgx_quote3_error_t::SGX_QL_SUCCESS;
+    let mut supplemental_size: u32 = 0;
+    let status = unsafe {
+        u_qv_get_supplemental_data_size_ocall(
+            &mut result as *mut sgx_quote3_error_t,
+            &mut supplemental_size as *mut u32,
+        )
+    };
+    check_ocall(status, result)?;
+    if supplemental_size > SGX_QV_SUPPLEMENTAL_MAX_SIZE {
+        return Err(sgx_quote3_error_t::SGX_QL_ERROR_UNEXPECTED);
+    }
+
+    let mut nonce = sgx_quote_nonce_t::default();
+    rsgx_read_rand(&mut nonce.rand).map_err(|_| sgx_quote3_error_t::SGX_QL_ERROR_UNEXPECTED)?;
+    let body = rsgx_self_report().body;
+    let mut qve_report_info = sgx_ql_qe_report_info_t {
+        nonce,
+        app_enclave_target_info: sgx_target_info_t {
+            mr_enclave: body.mr_enclave,
+            attributes: body.attributes,
+            config_svn: body.config_svn,
+            misc_select: body.misc_select,
+            config_id: body.config_id,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut collateral_expiration_status: u32 = 1;
+    let mut qv_result = sgx_ql_qv_result_t::SGX_QL_QV_RESULT_UNSPECIFIED;
+    let mut supplemental = vec![0_u8; supplemental_size as usize];
+    let status = unsafe {
+        u_qv_verify_quote_ocall(
+            &mut result as *mut sgx_quote3_error_t,
+            quote.as_ptr(),
+            quote_size,
+            current_time,
+            &mut collateral_expiration_status as *mut u32,
+            &mut qv_result as *mut sgx_ql_qv_result_t,
+            &mut qve_report_info as *mut sgx_ql_qe_report_info_t,
+            supplemental.as_mut_ptr(),
+            supplemental_size,
+        )
+    };
+    check_ocall(status, result)?;
+    // The host writes the whole report info back, so the TVL would check the
+    // QvE report against a nonce of the host's choosing; a replayed verdict
+    // would come back with its old nonce.
+    let returned_nonce = qve_report_info.nonce;
+    if !rsgx_ct_eq(&returned_nonce.rand, &nonce.rand) {
+        return Err(sgx_quote3_error_t::SGX_QL_ERROR_REPORT);
+    }
+
+    let tvl = unsafe {
+        sgx_tvl_verify_qve_report_and_identity(
+            quote.as_ptr(),
+            quote_size,
+            &qve_report_info as *const sgx_ql_qe_report_info_t,
+            current_time,
+            collateral_expiration_status,
+            qv_result,
+            if supplemental.is_empty() {
+                ptr::null()
+            } else {
+                supplemental.as_ptr()
+            },
+            supplemental_size,
+            qve_isvsvn_threshold,
+        )
+    };
+    if tvl != sgx_quote3_error_t::SGX_QL_SUCCESS {
+        return Err(tvl);
+    }
+
+    match qv_result {
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_INVALID_SIGNATURE => {
+            return Err(sgx_quote3_error_t::SGX_QL_INVALID_REPORT)
+        }
+        sgx_ql_qv_result_t::SGX_QL_QV_RESULT_UNSPECIFIED => {
+            return Err(sgx_quote3_error_t::SGX_QL_ERROR_UNEXPECTED)
+        }
+        _ => (),
+    }
+    Ok(SgxQuoteVerificationResult {
+        tcb_status: qv_result,
+        advisory_ids: supplemental_advisory_ids(&supplemental),
+        collateral_expired: collateral_expiration_status != 0,
+        earliest_issue_date: supplemental_data(&supplemental)
+            .map_or(0, |data| data.earliest_issue_date.max(0) as u64),
+        report_body: parsed.get_report_body().unwrap(),
+    })
+}
+
+fn supplemental_data(supplemental: &[u8]) -> Option<sgx_ql_qv_supplemental_t> {
+    if supplemental.len() < mem::size_of::<sgx_ql_qv_supplemental_t>() {
+        return None;
+    }
+    Some(unsafe { ptr::read_unaligned(supplemental.as_ptr() as *const sgx_ql_qv_supplemental_t) })
+}
+
+// The advisory IDs are a comma separated list in the supplemental data from
+// version 3 on.
+fn supplemental_advisory_ids(supplemental: &[u8]) -> Vec<String> {
+    let mut ids = Vec::new();
+    let data = match supplemental_data(supplemental) {
+        Some(data) if u16::from_le_bytes([supplemental[0], supplemental[1]]) >= 3 => data,
+        _ => return ids,
+    };
+    let list: Vec<u8> = data
+        .sa_list
+        .iter()
+        .take_while(|c| **c != 0)
+        .map(|c| *c as u8)
+        .collect();
+    if let Ok(list) = core::str::from_utf8(&list) {
+        for id in list.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+            ids.push(String::from(id));
+        }
+    }
+    ids
+}
+
+fn check_ocall(status: sgx_status_t, result: sgx_quote3_error_t) -> SgxQuote3Result<()> {
+    if status != sgx_status_t::SGX_SUCCESS {
+        return Err(sgx_quote3_error_t::SGX_QL_ERROR_UNEXPECTED);
+    }
+    if result != sgx_quote3_error_t::SGX_QL_SUCCESS {
+        return Err(result);
+    }
+    Ok(())
+}
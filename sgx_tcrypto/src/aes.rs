@@ -0,0 +1,149 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! AES block cipher
+//!
+//! The AES-128, AES-192 and AES-256 block cipher on the AES-NI instructions, for the
+//! modes the SDK does not provide. Every SGX capable processor supports AES-NI, and
+//! CPUID cannot be used inside an enclave, so the instructions are used without a
+//! runtime check. The key schedule also runs on AES-NI, so no operation depends on
+//! table lookups indexed by secret data.
+//!
+use super::secret::rsgx_zeroize;
+use core::arch::x86_64::*;
+
+pub(crate) const BLOCK_SIZE: usize = 16;
+
+pub(crate) struct Aes {
+    rounds: usize,
+    enc: [[u8; BLOCK_SIZE]; 15],
+    dec: [[u8; BLOCK_SIZE]; 15],
+}
+
+impl Aes {
+    /// Expands a 16, 24 or 32 byte key. Returns None for any other length.
+    pub(crate) fn new(key: &[u8]) -> Option<Aes> {
+        let nk = match key.len() {
+            16 | 24 | 32 => key.len() / 4,
+            _ => return None,
+        };
+        let rounds = nk + 6;
+        let mut w = [0_u32; 60];
+        for (i, word) in key.chunks(4).enumerate() {
+            w[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        // Words are little-endian, so RotWord is a right rotation by one byte and
+        // the round constant goes into the low byte.
+        let mut rcon = 1_u32;
+        for i in nk..4 * (rounds + 1) {
+            let mut t = w[i - 1];
+            if i % nk == 0 {
+                t = unsafe { sub_word(t) }.rotate_right(8) ^ rcon;
+                rcon = xtime(rcon);
+            } else if nk == 8 && i % nk == 4 {
+                t = unsafe { sub_word(t) };
+            }
+            w[i] = w[i - nk] ^ t;
+        }
+
+        let mut aes = Aes {
+            rounds,
+            enc: [[0_u8; BLOCK_SIZE]; 15],
+            dec: [[0_u8; BLOCK_SIZE]; 15],
+        };
+        for r in 0..=rounds {
+            for j in 0..4 {
+                aes.enc[r][4 * j..4 * j + 4].copy_from_slice(&w[4 * r + j].to_le_bytes());
+            }
+        }
+        unsafe { aes.invert_schedule() };
+        for x in w.iter_mut() {
+            unsafe { core::ptr::write_volatile(x, 0) };
+        }
+        Some(aes)
+    }
+
+    #[target_feature(enable = "aes")]
+    unsafe fn invert_schedule(&mut self) {
+        let rounds = self.rounds;
+        self.dec[0] = self.enc[rounds];
+        for r in 1..rounds {
+            let k = load(&self.enc[rounds - r]);
+            store(&mut self.dec[r], _mm_aesimc_si128(k));
+        }
+        self.dec[rounds] = self.enc[0];
+    }
+
+    pub(crate) fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        unsafe { self.encrypt_block_ni(block) }
+    }
+
+    pub(crate) fn decrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        unsafe { self.decrypt_block_ni(block) }
+    }
+
+    #[target_feature(enable = "aes")]
+    unsafe fn encrypt_block_ni(&self, block: &mut [u8; BLOCK_SIZE]) {
+        let mut x = _mm_xor_si128(load(block), load(&self.enc[0]));
+        for r in 1..self.rounds {
+            x = _mm_aesenc_si128(x, load(&self.enc[r]));
+        }
+        x = _mm_aesenclast_si128(x, load(&self.enc[self.rounds]));
+        store(block, x);
+    }
+
+    #[target_feature(enable = "aes")]
+    unsafe fn decrypt_block_ni(&self, block: &mut [u8; BLOCK_SIZE]) {
+        let mut x = _mm_xor_si128(load(block), load(&self.dec[0]));
+        for r in 1..self.rounds {
+            x = _mm_aesdec_si128(x, load(&self.dec[r]));
+        }
+        x = _mm_aesdeclast_si128(x, load(&self.dec[self.rounds]));
+        store(block, x);
+    }
+}
+
+impl Drop for Aes {
+    fn drop(&mut self) {
+        for r in 0..15 {
+            rsgx_zeroize(&mut self.enc[r]);
+            rsgx_zeroize(&mut self.dec[r]);
+        }
+    }
+}
+
+fn xtime(x: u32) -> u32 {
+    ((x << 1) & 0xff) ^ (((x >> 7) & 1) * 0x1b)
+}
+
+// AESKEYGENASSIST applies the S-box to the second word of its input.
+#[target_feature(enable = "aes")]
+unsafe fn sub_word(w: u32) -> u32 {
+    let x = _mm_set_epi32(0, 0, w as i32, 0);
+    _mm_cvtsi128_si32(_mm_aeskeygenassist_si128(x, 0)) as u32
+}
+
+#[inline]
+unsafe fn load(block: &[u8; BLOCK_SIZE]) -> __m128i {
+    _mm_loadu_si128(block.as_ptr() as *const __m128i)
+}
+
+#[inline]
+unsafe fn store(block: &mut [u8; BLOCK_SIZE], x: __m128i) {
+    _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, x)
+}
@@ -0,0 +1,219 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Stateful AES-CTR and AES-CMAC contexts
+//!
+use crate::crypto::{rsgx_aes_ctr_encrypt, sgx_aes_ctr_128bit_ctr_t, SgxCmacHandle};
+use crate::policy::{check_algorithm, SgxAlgorithm};
+use crate::secret::rsgx_ct_eq;
+use core::ptr;
+use sgx_types::*;
+
+const BLOCK_SIZE: usize = 16;
+// The largest multiple of the block size one IPP call takes.
+const MAX_CALL_LEN: usize = (u32::MAX as usize) & !(BLOCK_SIZE - 1);
+
+///
+/// An AES-CTR keystream that carries its counter across calls.
+///
+/// # Description
+///
+/// rsgx_aes_ctr_encrypt advances the counter by one per block, including a final
+/// partial block, so splitting a stream into chunks that are not multiples of 16
+/// bytes desynchronizes it. SgxAesCtr keeps the unused part of the last keystream
+/// block, so a stream can be processed in chunks of any size and gives the same
+/// result as a single call over the whole stream.
+///
+/// Encryption and decryption are the same operation. The key is wiped when the
+/// context is dropped.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+pub struct SgxAesCtr {
+    key: sgx_aes_ctr_128bit_key_t,
+    ctr: sgx_aes_ctr_128bit_ctr_t,
+    ctr_inc_bits: u32,
+    keystream: [u8; BLOCK_SIZE],
+    // Bytes of keystream already used; BLOCK_SIZE when none is left.
+    used: usize,
+}
+
+impl SgxAesCtr {
+    ///
+    /// Creates a context with the given key, initial counter block and number of
+    /// counter bits to increment (1 to 128).
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// ctr_inc_bits is out of range.
+    ///
+    pub fn new(
+        key: &sgx_aes_ctr_128bit_key_t,
+        ctr: &sgx_aes_ctr_128bit_ctr_t,
+        ctr_inc_bits: u32,
+    ) -> SgxResult<SgxAesCtr> {
+        check_algorithm(SgxAlgorithm::AesCtr)?;
+        if ctr_inc_bits == 0 || ctr_inc_bits > 128 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(SgxAesCtr {
+            key: *key,
+            ctr: *ctr,
+            ctr_inc_bits,
+            keystream: [0; BLOCK_SIZE],
+            used: BLOCK_SIZE,
+        })
+    }
+
+    ///
+    /// Encrypts or decrypts src into dst, which must be at least as long as src.
+    ///
+    pub fn update(&mut self, src: &[u8], dst: &mut [u8]) -> SgxError {
+        if dst.len() < src.len() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        // Left-over keystream from the previous call.
+        let take = src.len().min(BLOCK_SIZE - self.used);
+        for i in 0..take {
+            dst[i] = src[i] ^ self.keystream[self.used + i];
+        }
+        self.used += take;
+        let (mut src, mut dst) = (&src[take..], &mut dst[take..]);
+
+        // Whole blocks.
+        while src.len() >= BLOCK_SIZE {
+            let len = (src.len() & !(BLOCK_SIZE - 1)).min(MAX_CALL_LEN);
+            rsgx_aes_ctr_encrypt(
+                &self.key,
+                &src[..len],
+                &mut self.ctr,
+                self.ctr_inc_bits,
+                dst,
+            )?;
+            src = &src[len..];
+            dst = &mut dst[len..];
+        }
+
+        // A partial block: keep the rest of its keystream for the next call.
+        if !src.is_empty() {
+            let zeros = [0_u8; BLOCK_SIZE];
+            rsgx_aes_ctr_encrypt(
+                &self.key,
+                &zeros,
+                &mut self.ctr,
+                self.ctr_inc_bits,
+                &mut self.keystream,
+            )?;
+            for i in 0..src.len() {
+                dst[i] = src[i] ^ self.keystream[i];
+            }
+            self.used = src.len();
+        }
+        Ok(())
+    }
+
+    ///
+    /// Returns the counter block that the next keystream block will be made from.
+    ///
+    pub fn counter(&self) -> sgx_aes_ctr_128bit_ctr_t {
+        self.ctr
+    }
+}
+
+impl Drop for SgxAesCtr {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::write_volatile(&mut self.key, sgx_aes_ctr_128bit_key_t::default());
+            ptr::write_volatile(&mut self.keystream, [0; BLOCK_SIZE]);
+        }
+    }
+}
+
+///
+/// An AES-CMAC computation over data fed in chunks.
+///
+/// # Description
+///
+/// SgxAesCmac owns an SgxCmacHandle for its whole lifetime, so it cannot be used
+/// before it is initialized or after it is closed. The IPP state holding the key
+/// is released when the context is finished or dropped.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+pub struct SgxAesCmac {
+    handle: SgxCmacHandle,
+}
+
+impl SgxAesCmac {
+    ///
+    /// Starts a CMAC computation with the given key.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_OUT_OF_MEMORY**
+    ///
+    /// Not enough memory is available to complete this operation.
+    ///
+    pub fn new(key: &sgx_cmac_128bit_key_t) -> SgxResult<SgxAesCmac> {
+        let handle = SgxCmacHandle::new();
+        handle.init(key)?;
+        Ok(SgxAesCmac { handle })
+    }
+
+    ///
+    /// Adds data to the message.
+    ///
+    pub fn update(&mut self, data: &[u8]) -> SgxError {
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.handle.update_slice(data)
+    }
+
+    ///
+    /// Returns the tag of the message.
+    ///
+    pub fn finalize(self) -> SgxResult<sgx_cmac_128bit_tag_t> {
+        self.handle.get_hash()
+    }
+
+    ///
+    /// Checks the tag of the message in constant time.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The tag does not match the message.
+    ///
+    pub fn verify(self, tag: &sgx_cmac_128bit_tag_t) -> SgxError {
+        let computed = self.handle.get_hash()?;
+        if rsgx_ct_eq(&computed, tag) {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+        }
+    }
+}
@@ -0,0 +1,252 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Streaming AES-GCM
+//!
+use crate::crypto::SgxAesHandle;
+use crate::secret::rsgx_ct_eq;
+use alloc::vec::Vec;
+use core::ptr;
+use sgx_types::*;
+
+// The most plaintext one key and IV may protect: 2^39 - 256 bits.
+const GCM_MAX_DATA_LEN: u64 = (1 << 36) - 32;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Encrypt,
+    Decrypt,
+}
+
+///
+/// An incremental AES-GCM encryption or decryption.
+///
+/// # Description
+///
+/// Unlike rsgx_rijndael128GCM_encrypt and rsgx_rijndael128GCM_decrypt, which need the
+/// whole message at once, SgxAesGcm processes a message in chunks of any size, so
+/// large streams can be handled with a bounded buffer. The additional authenticated
+/// data is fed with update_aad, which must be done before the first call to update.
+///
+/// An encryption is finished with finalize, which returns the tag. A decryption is
+/// finished with verify, which checks the tag of the whole message.
+///
+/// Decryption releases plaintext before the tag has been checked. The output of a
+/// decryption must not be used, and in particular must not leave the enclave, until
+/// verify has succeeded.
+///
+/// The IPP streaming interface only encrypts, so decryption runs the keystream over
+/// the ciphertext and re-encrypts the resulting plaintext in a second context, whose
+/// tag covers the original ciphertext. Decryption is therefore about half as fast as
+/// encryption.
+///
+/// # Requirements
+///
+/// Library: libsgx_tcrypto.a
+///
+/// # Examples
+///
+/// ```no_run
+/// use sgx_tcrypto::SgxAesGcm;
+///
+/// let key = [0_u8; 16];
+/// let iv = [0_u8; 12];
+/// let mut gcm = SgxAesGcm::new_encrypt(&key, &iv);
+/// gcm.update_aad(b"header").unwrap();
+/// let mut ciphertext = [0_u8; 8];
+/// gcm.update(b"chunk 1 ", &mut ciphertext).unwrap();
+/// let tag = gcm.finalize().unwrap();
+/// ```
+///
+pub struct SgxAesGcm {
+    direction: Direction,
+    key: sgx_aes_gcm_128bit_key_t,
+    iv: [u8; SGX_AESGCM_IV_SIZE],
+    aad: Vec<u8>,
+    cipher: SgxAesHandle,
+    // For decryption only: re-encrypts the plaintext to compute the tag.
+    mac: SgxAesHandle,
+    started: bool,
+    data_len: u64,
+}
+
+impl SgxAesGcm {
+    ///
+    /// Starts encrypting with the given key and 96-bit IV.
+    ///
+    /// An IV must never be used twice with the same key.
+    ///
+    pub fn new_encrypt(key: &sgx_aes_gcm_128bit_key_t, iv: &[u8; SGX_AESGCM_IV_SIZE]) -> SgxAesGcm {
+        SgxAesGcm::new(Direction::Encrypt, key, iv)
+    }
+
+    ///
+    /// Starts decrypting with the given key and 96-bit IV.
+    ///
+    pub fn new_decrypt(key: &sgx_aes_gcm_128bit_key_t, iv: &[u8; SGX_AESGCM_IV_SIZE]) -> SgxAesGcm {
+        SgxAesGcm::new(Direction::Decrypt, key, iv)
+    }
+
+    fn new(
+        direction: Direction,
+        key: &sgx_aes_gcm_128bit_key_t,
+        iv: &[u8; SGX_AESGCM_IV_SIZE],
+    ) -> SgxAesGcm {
+        SgxAesGcm {
+            direction,
+            key: *key,
+            iv: *iv,
+            aad: Vec::new(),
+            cipher: SgxAesHandle::new(),
+            mac: SgxAesHandle::new(),
+            started: false,
+            data_len: 0,
+        }
+    }
+
+    ///
+    /// Adds a chunk of additional authenticated data.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// Data has already been processed with update.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The additional authenticated data is longer than 4 GiB.
+    ///
+    pub fn update_aad(&mut self, aad: &[u8]) -> SgxError {
+        if self.started {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        if self.aad.len() + aad.len() > u32::MAX as usize {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        self.aad.extend_from_slice(aad);
+        Ok(())
+    }
+
+    ///
+    /// Encrypts or decrypts the next chunk of the message from src into dst.
+    ///
+    /// dst must be at least as long as src; the first src.len() bytes are written.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// dst is too short, or the message would exceed the GCM limit of 2^36 - 32 bytes.
+    ///
+    /// **SGX_ERROR_OUT_OF_MEMORY**
+    ///
+    /// Not enough memory is available to complete this operation.
+    ///
+    pub fn update(&mut self, src: &[u8], dst: &mut [u8]) -> SgxError {
+        if dst.len() < src.len() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        match self.data_len.checked_add(src.len() as u64) {
+            Some(len) if len <= GCM_MAX_DATA_LEN => self.data_len = len,
+            _ => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+        self.start()?;
+        if src.is_empty() {
+            return Ok(());
+        }
+
+        let dst = &mut dst[..src.len()];
+        self.cipher.update(src, dst)?;
+        if self.direction == Direction::Decrypt {
+            let mut scratch = [0_u8; 512];
+            for chunk in dst.chunks(scratch.len()) {
+                self.mac.update(chunk, &mut scratch[..chunk.len()])?;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Finishes an encryption and returns the tag.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// This is a decryption.
+    ///
+    pub fn finalize(mut self) -> SgxResult<sgx_aes_gcm_128bit_tag_t> {
+        if self.direction != Direction::Encrypt {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        self.start()?;
+        self.cipher.get_mac()
+    }
+
+    ///
+    /// Finishes a decryption and checks the tag of the whole message.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The message or the additional authenticated data was modified.
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// This is an encryption.
+    ///
+    pub fn verify(mut self, tag: &sgx_aes_gcm_128bit_tag_t) -> SgxError {
+        if self.direction != Direction::Decrypt {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        self.start()?;
+        let computed = self.mac.get_mac()?;
+        if rsgx_ct_eq(&computed, tag) {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+        }
+    }
+
+    // The IPP contexts take the AAD at init, so they are only created once
+    // the AAD is complete.
+    fn start(&mut self) -> SgxError {
+        if self.started {
+            return Ok(());
+        }
+        self.cipher.init(&self.key, &self.iv, &self.aad)?;
+        if self.direction == Direction::Decrypt {
+            self.mac.init(&self.key, &self.iv, &self.aad)?;
+        }
+        self.started = true;
+        self.wipe_key();
+        Ok(())
+    }
+
+    fn wipe_key(&mut self) {
+        unsafe { ptr::write_volatile(&mut self.key, sgx_aes_gcm_128bit_key_t::default()) };
+    }
+}
+
+impl Drop for SgxAesGcm {
+    fn drop(&mut self) {
+        self.wipe_key();
+    }
+}
@@ -0,0 +1,414 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! AES-GCM-SIV
+//!
+//! The nonce-misuse-resistant AEAD of RFC 8452, with 128-bit and 256-bit keys. A
+//! repeated nonce only reveals whether the same message was encrypted twice, so it
+//! suits long-lived keys for which unique nonces are hard to guarantee.
+//!
+use super::aes::{Aes, BLOCK_SIZE};
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::secret::{rsgx_ct_eq, rsgx_zeroize};
+use core::ptr;
+use sgx_types::*;
+
+// The most plaintext, and the most additional data, one message may have.
+const GCM_SIV_MAX_LEN: u64 = 1 << 36;
+
+// x^-1 in the POLYVAL field, whose bits are the coefficients of x^0 to x^127.
+const X_INV: u128 = 0xe1 << 120;
+
+// POLYVAL over a stream of bytes that is zero-padded to whole blocks when pad is
+// called.
+struct Polyval {
+    h: u128,
+    acc: u128,
+    buf: [u8; BLOCK_SIZE],
+    used: usize,
+}
+
+impl Polyval {
+    fn new(h: &[u8; BLOCK_SIZE]) -> Polyval {
+        Polyval {
+            h: u128::from_le_bytes(*h),
+            acc: 0,
+            buf: [0; BLOCK_SIZE],
+            used: 0,
+        }
+    }
+
+    // acc = (acc + x) * h * x^-128, one bit of h at a time without branches.
+    fn block(&mut self, x: &[u8; BLOCK_SIZE]) {
+        let a = self.acc ^ u128::from_le_bytes(*x);
+        let mut r = 0_u128;
+        for i in 0..128 {
+            r ^= a & 0_u128.wrapping_sub((self.h >> i) & 1);
+            r = (r >> 1) ^ (X_INV & 0_u128.wrapping_sub(r & 1));
+        }
+        self.acc = r;
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        if self.used > 0 {
+            let n = core::cmp::min(BLOCK_SIZE - self.used, data.len());
+            self.buf[self.used..self.used + n].copy_from_slice(&data[..n]);
+            self.used += n;
+            data = &data[n..];
+            if self.used < BLOCK_SIZE {
+                return;
+            }
+            let buf = self.buf;
+            self.block(&buf);
+            self.used = 0;
+        }
+        let mut blocks = data.chunks_exact(BLOCK_SIZE);
+        for block in &mut blocks {
+            let mut x = [0_u8; BLOCK_SIZE];
+            x.copy_from_slice(block);
+            self.block(&x);
+        }
+        let rest = blocks.remainder();
+        self.buf[..rest.len()].copy_from_slice(rest);
+        self.used = rest.len();
+    }
+
+    fn pad(&mut self) {
+        if self.used > 0 {
+            let mut x = [0_u8; BLOCK_SIZE];
+            x[..self.used].copy_from_slice(&self.buf[..self.used]);
+            self.block(&x);
+            self.used = 0;
+        }
+    }
+}
+
+impl Drop for Polyval {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::write_volatile(&mut self.h, 0);
+            ptr::write_volatile(&mut self.acc, 0);
+        }
+        rsgx_zeroize(&mut self.buf);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Encrypt,
+    Decrypt,
+}
+
+///
+/// An AES-GCM-SIV encryption or decryption.
+///
+/// # Description
+///
+/// The additional authenticated data is fed in chunks with update_aad, as with
+/// SgxAesGcm. GCM-SIV derives the counter of the encryption from a tag over the whole
+/// plaintext, so an encryption cannot release any ciphertext before it has seen all of
+/// the plaintext: it is finished with finalize, which takes the whole message and
+/// returns the tag.
+///
+/// A decryption knows the counter from the tag, which is passed to new_decrypt, so the
+/// ciphertext is processed in chunks of any size with update. It is finished with
+/// verify. As with SgxAesGcm, the output of a decryption must not be used until verify
+/// has succeeded.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sgx_tcrypto::SgxAesGcmSiv;
+///
+/// let key = [0_u8; 32];
+/// let nonce = [0_u8; 12];
+/// let mut siv = SgxAesGcmSiv::new_encrypt(&key, &nonce).unwrap();
+/// siv.update_aad(b"header").unwrap();
+/// let mut ciphertext = [0_u8; 7];
+/// let tag = siv.finalize(b"message", &mut ciphertext).unwrap();
+///
+/// let mut siv = SgxAesGcmSiv::new_decrypt(&key, &nonce, &tag).unwrap();
+/// siv.update_aad(b"header").unwrap();
+/// let mut plaintext = [0_u8; 7];
+/// siv.update(&ciphertext, &mut plaintext).unwrap();
+/// siv.verify().unwrap();
+/// ```
+///
+pub struct SgxAesGcmSiv {
+    direction: Direction,
+    cipher: Aes,
+    polyval: Polyval,
+    nonce: [u8; SGX_AESGCM_IV_SIZE],
+    // For decryption only: the tag to check.
+    tag: sgx_aes_gcm_128bit_tag_t,
+    counter: [u8; BLOCK_SIZE],
+    keystream: [u8; BLOCK_SIZE],
+    used: usize,
+    started: bool,
+    aad_len: u64,
+    data_len: u64,
+}
+
+impl SgxAesGcmSiv {
+    ///
+    /// Starts encrypting with a 16 or 32 byte key and a 96-bit nonce.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The key has another length.
+    ///
+    pub fn new_encrypt(key: &[u8], nonce: &[u8; SGX_AESGCM_IV_SIZE]) -> SgxResult<SgxAesGcmSiv> {
+        check_algorithm(SgxAlgorithm::AesGcmSiv)?;
+        SgxAesGcmSiv::new(Direction::Encrypt, key, nonce, &[0; SGX_AESGCM_MAC_SIZE])
+    }
+
+    ///
+    /// Starts decrypting with a 16 or 32 byte key, a 96-bit nonce and the tag of the
+    /// message.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The key has another length.
+    ///
+    pub fn new_decrypt(
+        key: &[u8],
+        nonce: &[u8; SGX_AESGCM_IV_SIZE],
+        tag: &sgx_aes_gcm_128bit_tag_t,
+    ) -> SgxResult<SgxAesGcmSiv> {
+        check_algorithm(SgxAlgorithm::AesGcmSiv)?;
+        SgxAesGcmSiv::new(Direction::Decrypt, key, nonce, tag)
+    }
+
+    fn new(
+        direction: Direction,
+        key: &[u8],
+        nonce: &[u8; SGX_AESGCM_IV_SIZE],
+        tag: &sgx_aes_gcm_128bit_tag_t,
+    ) -> SgxResult<SgxAesGcmSiv> {
+        if key.len() != 16 && key.len() != 32 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let kgk = Aes::new(key).ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+
+        // The per-nonce keys are the first halves of the encryptions of a counter
+        // and the nonce.
+        let mut keys = [0_u8; 48];
+        for i in 0..(2 + key.len() / 8) {
+            let mut block = [0_u8; BLOCK_SIZE];
+            block[..4].copy_from_slice(&(i as u32).to_le_bytes());
+            block[4..].copy_from_slice(nonce);
+            kgk.encrypt_block(&mut block);
+            keys[8 * i..8 * i + 8].copy_from_slice(&block[..8]);
+            rsgx_zeroize(&mut block);
+        }
+        let mut auth_key = [0_u8; BLOCK_SIZE];
+        auth_key.copy_from_slice(&keys[..16]);
+        let cipher = Aes::new(&keys[16..16 + key.len()]);
+        let polyval = Polyval::new(&auth_key);
+        rsgx_zeroize(&mut keys);
+        rsgx_zeroize(&mut auth_key);
+
+        let mut counter = *tag;
+        counter[15] |= 0x80;
+        Ok(SgxAesGcmSiv {
+            direction,
+            cipher: cipher.ok_or(sgx_status_t::SGX_ERROR_UNEXPECTED)?,
+            polyval,
+            nonce: *nonce,
+            tag: *tag,
+            counter,
+            keystream: [0; BLOCK_SIZE],
+            used: BLOCK_SIZE,
+            started: false,
+            aad_len: 0,
+            data_len: 0,
+        })
+    }
+
+    ///
+    /// Adds a chunk of additional authenticated data.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// Data has already been processed with update.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The additional authenticated data is longer than 2^36 bytes.
+    ///
+    pub fn update_aad(&mut self, aad: &[u8]) -> SgxError {
+        if self.started {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        self.aad_len = add_len(self.aad_len, aad.len())?;
+        self.polyval.update(aad);
+        Ok(())
+    }
+
+    ///
+    /// Decrypts the next chunk of the message from src into dst.
+    ///
+    /// dst must be at least as long as src; the first src.len() bytes are written.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// This is an encryption.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// dst is too short, or the message would be longer than 2^36 bytes.
+    ///
+    pub fn update(&mut self, src: &[u8], dst: &mut [u8]) -> SgxError {
+        if self.direction != Direction::Decrypt {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        if dst.len() < src.len() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        self.data_len = add_len(self.data_len, src.len())?;
+        self.start();
+        let dst = &mut dst[..src.len()];
+        self.apply_keystream(src, dst);
+        self.polyval.update(dst);
+        Ok(())
+    }
+
+    ///
+    /// Encrypts the whole message from src into dst and returns the tag.
+    ///
+    /// dst must be at least as long as src; the first src.len() bytes are written.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// This is a decryption.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// dst is too short, or the message is longer than 2^36 bytes.
+    ///
+    pub fn finalize(mut self, src: &[u8], dst: &mut [u8]) -> SgxResult<sgx_aes_gcm_128bit_tag_t> {
+        if self.direction != Direction::Encrypt {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        if dst.len() < src.len() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        self.data_len = add_len(0, src.len())?;
+        self.start();
+        self.polyval.update(src);
+        let tag = self.compute_tag();
+        self.counter = tag;
+        self.counter[15] |= 0x80;
+        self.apply_keystream(src, &mut dst[..src.len()]);
+        Ok(tag)
+    }
+
+    ///
+    /// Finishes a decryption and checks the tag of the whole message.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The message, the additional authenticated data or the tag was modified.
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// This is an encryption.
+    ///
+    pub fn verify(mut self) -> SgxError {
+        if self.direction != Direction::Decrypt {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        self.start();
+        let computed = self.compute_tag();
+        if rsgx_ct_eq(&computed, &self.tag) {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+        }
+    }
+
+    // Ends the additional data, which is padded apart from the message.
+    fn start(&mut self) {
+        if !self.started {
+            self.polyval.pad();
+            self.started = true;
+        }
+    }
+
+    fn compute_tag(&mut self) -> sgx_aes_gcm_128bit_tag_t {
+        self.polyval.pad();
+        let mut lengths = [0_u8; BLOCK_SIZE];
+        lengths[..8].copy_from_slice(&(self.aad_len * 8).to_le_bytes());
+        lengths[8..].copy_from_slice(&(self.data_len * 8).to_le_bytes());
+        self.polyval.update(&lengths);
+
+        let mut s = self.polyval.acc.to_le_bytes();
+        for (x, n) in s.iter_mut().zip(self.nonce.iter()) {
+            *x ^= n;
+        }
+        s[15] &= 0x7f;
+        self.cipher.encrypt_block(&mut s);
+        s
+    }
+
+    // AES-CTR with a 32-bit little-endian counter in the first word of the block.
+    fn apply_keystream(&mut self, src: &[u8], dst: &mut [u8]) {
+        for (d, s) in dst.iter_mut().zip(src.iter()) {
+            if self.used == BLOCK_SIZE {
+                self.keystream = self.counter;
+                self.cipher.encrypt_block(&mut self.keystream);
+                let next = u32::from_le_bytes([
+                    self.counter[0],
+                    self.counter[1],
+                    self.counter[2],
+                    self.counter[3],
+                ])
+                .wrapping_add(1);
+                self.counter[..4].copy_from_slice(&next.to_le_bytes());
+                self.used = 0;
+            }
+            *d = s ^ self.keystream[self.used];
+            self.used += 1;
+        }
+    }
+}
+
+impl Drop for SgxAesGcmSiv {
+    fn drop(&mut self) {
+        rsgx_zeroize(&mut self.keystream);
+    }
+}
+
+fn add_len(total: u64, len: usize) -> SgxResult<u64> {
+    match total.checked_add(len as u64) {
+        Some(total) if total <= GCM_SIV_MAX_LEN => Ok(total),
+        _ => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+    }
+}
@@ -0,0 +1,240 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! AES key wrap
+//!
+//! AES Key Wrap (RFC 3394, NIST SP 800-38F KW) and AES Key Wrap with Padding
+//! (RFC 5649, KWP) with 128, 192 and 256 bit key-encryption keys, for exchanging
+//! wrapped data keys with HSMs and key management services.
+//!
+use super::aes::{Aes, BLOCK_SIZE};
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::secret::{rsgx_ct_eq, rsgx_zeroize};
+use alloc::vec;
+use alloc::vec::Vec;
+use sgx_types::*;
+
+const KW_IV: [u8; 8] = [0xa6; 8];
+const KWP_IV_PREFIX: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+fn kek_cipher(kek: &[u8]) -> SgxResult<Aes> {
+    Aes::new(kek).ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+}
+
+// The wrapping process W of SP 800-38F; out holds the integrity check block
+// followed by the semiblocks to wrap.
+fn wrap_in_place(aes: &Aes, out: &mut [u8]) {
+    let n = out.len() / 8 - 1;
+    let mut block = [0_u8; BLOCK_SIZE];
+    for j in 0..6 {
+        for i in 1..=n {
+            block[..8].copy_from_slice(&out[..8]);
+            block[8..].copy_from_slice(&out[8 * i..8 * i + 8]);
+            aes.encrypt_block(&mut block);
+            let t = ((n * j + i) as u64).to_be_bytes();
+            for k in 0..8 {
+                out[k] = block[k] ^ t[k];
+            }
+            out[8 * i..8 * i + 8].copy_from_slice(&block[8..]);
+        }
+    }
+    rsgx_zeroize(&mut block);
+}
+
+// The unwrapping process W^-1, leaving the recovered integrity check block
+// in the first semiblock.
+fn unwrap_in_place(aes: &Aes, buf: &mut [u8]) {
+    let n = buf.len() / 8 - 1;
+    let mut block = [0_u8; BLOCK_SIZE];
+    for j in (0..6).rev() {
+        for i in (1..=n).rev() {
+            let t = ((n * j + i) as u64).to_be_bytes();
+            for k in 0..8 {
+                block[k] = buf[k] ^ t[k];
+            }
+            block[8..].copy_from_slice(&buf[8 * i..8 * i + 8]);
+            aes.decrypt_block(&mut block);
+            buf[..8].copy_from_slice(&block[..8]);
+            buf[8 * i..8 * i + 8].copy_from_slice(&block[8..]);
+        }
+    }
+    rsgx_zeroize(&mut block);
+}
+
+///
+/// rsgx_aes_kw_wrap wraps a key with AES Key Wrap (RFC 3394).
+///
+/// # Parameters
+///
+/// **kek**
+///
+/// The key-encryption key: 16, 24 or 32 bytes.
+///
+/// **key**
+///
+/// The key to wrap: a multiple of 8 bytes, at least 16 bytes long.
+///
+/// # Return value
+///
+/// The wrapped key, 8 bytes longer than the input.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The length of kek or key is not supported.
+///
+pub fn rsgx_aes_kw_wrap(kek: &[u8], key: &[u8]) -> SgxResult<Vec<u8>> {
+    check_algorithm(SgxAlgorithm::AesKw)?;
+    if key.len() < 16 || key.len() % 8 != 0 {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let aes = kek_cipher(kek)?;
+    let mut out = vec![0_u8; key.len() + 8];
+    out[..8].copy_from_slice(&KW_IV);
+    out[8..].copy_from_slice(key);
+    wrap_in_place(&aes, &mut out);
+    Ok(out)
+}
+
+///
+/// rsgx_aes_kw_unwrap unwraps and checks a key wrapped with AES Key Wrap (RFC 3394).
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The length of kek or wrapped is not supported.
+///
+/// **SGX_ERROR_MAC_MISMATCH**
+///
+/// The wrapped key was not produced with this key-encryption key, or was modified.
+///
+pub fn rsgx_aes_kw_unwrap(kek: &[u8], wrapped: &[u8]) -> SgxResult<Vec<u8>> {
+    check_algorithm(SgxAlgorithm::AesKw)?;
+    if wrapped.len() < 24 || wrapped.len() % 8 != 0 {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let aes = kek_cipher(kek)?;
+    let mut buf = wrapped.to_vec();
+    unwrap_in_place(&aes, &mut buf);
+    let ok = rsgx_ct_eq(&buf[..8], &KW_IV);
+    let result = if ok {
+        Ok(buf[8..].to_vec())
+    } else {
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    };
+    rsgx_zeroize(&mut buf);
+    result
+}
+
+///
+/// rsgx_aes_kwp_wrap wraps a key of any length with AES Key Wrap with Padding
+/// (RFC 5649).
+///
+/// # Parameters
+///
+/// **kek**
+///
+/// The key-encryption key: 16, 24 or 32 bytes.
+///
+/// **key**
+///
+/// The key to wrap: between 1 byte and 4 GiB - 1.
+///
+/// # Return value
+///
+/// The wrapped key: the input padded to a multiple of 8 bytes, plus 8 bytes.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The length of kek or key is not supported.
+///
+pub fn rsgx_aes_kwp_wrap(kek: &[u8], key: &[u8]) -> SgxResult<Vec<u8>> {
+    check_algorithm(SgxAlgorithm::AesKw)?;
+    if key.is_empty() || key.len() > u32::MAX as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let aes = kek_cipher(kek)?;
+    let padded = (key.len() + 7) / 8 * 8;
+    let mut out = vec![0_u8; padded + 8];
+    out[..4].copy_from_slice(&KWP_IV_PREFIX);
+    out[4..8].copy_from_slice(&(key.len() as u32).to_be_bytes());
+    out[8..8 + key.len()].copy_from_slice(key);
+    if padded == 8 {
+        let mut block = [0_u8; BLOCK_SIZE];
+        block.copy_from_slice(&out);
+        aes.encrypt_block(&mut block);
+        out.copy_from_slice(&block);
+        rsgx_zeroize(&mut block);
+    } else {
+        wrap_in_place(&aes, &mut out);
+    }
+    Ok(out)
+}
+
+///
+/// rsgx_aes_kwp_unwrap unwraps and checks a key wrapped with AES Key Wrap with
+/// Padding (RFC 5649).
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The length of kek or wrapped is not supported.
+///
+/// **SGX_ERROR_MAC_MISMATCH**
+///
+/// The wrapped key was not produced with this key-encryption key, or was modified.
+///
+pub fn rsgx_aes_kwp_unwrap(kek: &[u8], wrapped: &[u8]) -> SgxResult<Vec<u8>> {
+    check_algorithm(SgxAlgorithm::AesKw)?;
+    if wrapped.len() < 16 || wrapped.len() % 8 != 0 {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let aes = kek_cipher(kek)?;
+    let mut buf = wrapped.to_vec();
+    if buf.len() == 16 {
+        let mut block = [0_u8; BLOCK_SIZE];
+        block.copy_from_slice(&buf);
+        aes.decrypt_block(&mut block);
+        buf.copy_from_slice(&block);
+        rsgx_zeroize(&mut block);
+    } else {
+        unwrap_in_place(&aes, &mut buf);
+    }
+
+    // Check the prefix, the length and the padding without branching on any of them.
+    let padded = buf.len() - 8;
+    let mli = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]) as usize;
+    let mut bad = !rsgx_ct_eq(&buf[..4], &KWP_IV_PREFIX) as u8;
+    bad |= (mli > padded) as u8;
+    bad |= (mli + 8 <= padded) as u8;
+    for (i, b) in buf[8..].iter().enumerate() {
+        bad |= ((i >= mli) as u8) & ((*b != 0) as u8);
+    }
+    let result = if bad == 0 {
+        Ok(buf[8..8 + mli].to_vec())
+    } else {
+        Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+    };
+    rsgx_zeroize(&mut buf);
+    result
+}
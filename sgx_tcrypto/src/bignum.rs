@@ -0,0 +1,654 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Constant-time big integers
+//!
+//! Unsigned integers of any size and arithmetic modulo an odd number, for protocols
+//! such as Paillier encryption or RSA blind signatures. Numbers are little-endian
+//! 64 bit limbs. The time and memory accesses of every operation depend only on the
+//! sizes of the numbers, never on their values, except where a function is marked
+//! vartime or returns an error that depends on the values.
+//!
+use super::secret::rsgx_zeroize;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ptr;
+use core::slice;
+use sgx_types::*;
+
+const WINDOW: usize = 4;
+
+// r = a + b + carry, returning the carry out. b may be shorter than r.
+fn add_in(r: &mut [u64], b: &[u64]) -> u64 {
+    let mut carry = 0_u64;
+    for (i, x) in r.iter_mut().enumerate() {
+        let y = b.get(i).copied().unwrap_or(0);
+        let s = *x as u128 + y as u128 + carry as u128;
+        *x = s as u64;
+        carry = (s >> 64) as u64;
+    }
+    carry
+}
+
+// r = r - b, returning the borrow out. b may be shorter than r.
+fn sub_in(r: &mut [u64], b: &[u64]) -> u64 {
+    let mut borrow = 0_u64;
+    for (i, x) in r.iter_mut().enumerate() {
+        let y = b.get(i).copied().unwrap_or(0);
+        let (d1, b1) = x.overflowing_sub(y);
+        let (d2, b2) = d1.overflowing_sub(borrow);
+        *x = d2;
+        borrow = (b1 | b2) as u64;
+    }
+    borrow
+}
+
+// a = b when choice is 1, unchanged when it is 0.
+fn assign_if(a: &mut [u64], b: &[u64], choice: u64) {
+    let mask = 0_u64.wrapping_sub(choice);
+    for (x, y) in a.iter_mut().zip(b.iter()) {
+        *x ^= mask & (*x ^ y);
+    }
+}
+
+fn is_zero(a: &[u64]) -> u64 {
+    let acc = a.iter().fold(0, |acc, x| acc | x);
+    1 ^ ((acc | acc.wrapping_neg()) >> 63)
+}
+
+// a >>= 1, shifting top into the highest bit.
+fn shr1(a: &mut [u64], top: u64) {
+    let mut carry = top;
+    for x in a.iter_mut().rev() {
+        let low = *x & 1;
+        *x = (*x >> 1) | (carry << 63);
+        carry = low;
+    }
+}
+
+// The low r.len() limbs of a * b.
+fn mul_low(a: &[u64], b: &[u64], r: &mut [u64]) {
+    for x in r.iter_mut() {
+        *x = 0;
+    }
+    let n = r.len();
+    for (i, bi) in b.iter().enumerate().take(n) {
+        let mut carry = 0_u64;
+        for j in 0..(n - i) {
+            let aj = a.get(j).copied().unwrap_or(0);
+            let s = r[i + j] as u128 + (aj as u128) * (*bi as u128) + carry as u128;
+            r[i + j] = s as u64;
+            carry = (s >> 64) as u64;
+        }
+    }
+}
+
+// -x^-1 mod 2^64 for odd x.
+fn neg_inv64(x: u64) -> u64 {
+    let mut inv = 1_u64;
+    for _ in 0..6 {
+        inv = inv.wrapping_mul(2_u64.wrapping_sub(x.wrapping_mul(inv)));
+    }
+    inv.wrapping_neg()
+}
+
+///
+/// An unsigned integer of any size.
+///
+/// # Description
+///
+/// A number has a width, a whole number of 64 bit limbs, which is public: it is set
+/// by the encoding the number was made from or by the operation that returned it, and
+/// it is the only thing that operations on the number may leak. The value is wiped
+/// when the number is dropped, and is not shown by Debug.
+///
+#[derive(Clone)]
+pub struct SgxBigUint {
+    limbs: Vec<u64>,
+}
+
+impl SgxBigUint {
+    fn zero(width: usize) -> SgxBigUint {
+        SgxBigUint {
+            limbs: vec![0; core::cmp::max(width, 1)],
+        }
+    }
+
+    /// Reads a big-endian number. The width is that of the encoding.
+    pub fn from_be_bytes(bytes: &[u8]) -> SgxBigUint {
+        let mut n = SgxBigUint::zero((bytes.len() + 7) / 8);
+        for (i, byte) in bytes.iter().rev().enumerate() {
+            n.limbs[i / 8] |= (*byte as u64) << ((i % 8) * 8);
+        }
+        n
+    }
+
+    /// Reads a little-endian number. The width is that of the encoding.
+    pub fn from_le_bytes(bytes: &[u8]) -> SgxBigUint {
+        let mut n = SgxBigUint::zero((bytes.len() + 7) / 8);
+        for (i, byte) in bytes.iter().enumerate() {
+            n.limbs[i / 8] |= (*byte as u64) << ((i % 8) * 8);
+        }
+        n
+    }
+
+    pub fn from_u64(value: u64) -> SgxBigUint {
+        SgxBigUint { limbs: vec![value] }
+    }
+
+    ///
+    /// Writes the number in big-endian order, filling all of out.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The number does not fit into out.
+    ///
+    pub fn to_be_bytes(&self, out: &mut [u8]) -> SgxError {
+        self.to_le_bytes(out)?;
+        out.reverse();
+        Ok(())
+    }
+
+    ///
+    /// Writes the number in little-endian order, filling all of out.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The number does not fit into out.
+    ///
+    pub fn to_le_bytes(&self, out: &mut [u8]) -> SgxError {
+        let mut rest = 0_u64;
+        for k in 0..core::cmp::max(out.len(), 8 * self.limbs.len()) {
+            let byte = if k < 8 * self.limbs.len() {
+                (self.limbs[k / 8] >> ((k % 8) * 8)) as u8
+            } else {
+                0
+            };
+            match out.get_mut(k) {
+                Some(o) => *o = byte,
+                None => rest |= byte as u64,
+            }
+        }
+        if rest == 0 {
+            Ok(())
+        } else {
+            rsgx_zeroize(out);
+            Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        }
+    }
+
+    /// The width in bytes, eight for every limb.
+    pub fn width_bytes(&self) -> usize {
+        8 * self.limbs.len()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        is_zero(&self.limbs) == 1
+    }
+
+    /// Compares the values of two numbers of any widths.
+    pub fn ct_eq(&self, other: &SgxBigUint) -> bool {
+        let width = core::cmp::max(self.limbs.len(), other.limbs.len());
+        let mut diff = 0_u64;
+        for i in 0..width {
+            diff |=
+                self.limbs.get(i).copied().unwrap_or(0) ^ other.limbs.get(i).copied().unwrap_or(0);
+        }
+        is_zero(&[diff]) == 1
+    }
+
+    /// The number of significant bits. This leaks the size of the value.
+    pub fn bits_vartime(&self) -> usize {
+        match self.limbs.iter().rposition(|x| *x != 0) {
+            Some(i) => 64 * i + 64 - self.limbs[i].leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    /// The sum, one limb wider than the wider operand.
+    pub fn add(&self, other: &SgxBigUint) -> SgxBigUint {
+        let mut r = SgxBigUint::zero(core::cmp::max(self.limbs.len(), other.limbs.len()) + 1);
+        add_in(&mut r.limbs, &self.limbs);
+        add_in(&mut r.limbs, &other.limbs);
+        r
+    }
+
+    ///
+    /// The difference, as wide as the wider operand.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// other is greater than self.
+    ///
+    pub fn sub(&self, other: &SgxBigUint) -> SgxResult<SgxBigUint> {
+        let mut r = SgxBigUint::zero(core::cmp::max(self.limbs.len(), other.limbs.len()));
+        add_in(&mut r.limbs, &self.limbs);
+        let mut borrow = sub_in(&mut r.limbs, &other.limbs);
+        if other.limbs.len() > r.limbs.len() {
+            borrow |= 1 ^ is_zero(&other.limbs[r.limbs.len()..]);
+        }
+        if borrow == 0 {
+            Ok(r)
+        } else {
+            Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        }
+    }
+
+    /// The product, as wide as both operands together.
+    pub fn mul(&self, other: &SgxBigUint) -> SgxBigUint {
+        let mut r = SgxBigUint::zero(self.limbs.len() + other.limbs.len());
+        mul_low(&self.limbs, &other.limbs, &mut r.limbs);
+        r
+    }
+
+    ///
+    /// Divides by an odd number that is known to divide self, as wide as self.
+    ///
+    /// This is the L function of Paillier decryption. The quotient is computed as self
+    /// times the inverse of the divisor modulo 2^(64 * width) and then checked, so no
+    /// long division is needed.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The divisor is even or does not divide self.
+    ///
+    pub fn div_exact(&self, divisor: &SgxBigUint) -> SgxResult<SgxBigUint> {
+        if divisor.limbs[0] & 1 == 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let width = self.limbs.len();
+        // Newton's iteration doubles the number of correct low bits of the inverse.
+        let mut inv = SgxBigUint::zero(width);
+        inv.limbs[0] = neg_inv64(divisor.limbs[0]).wrapping_neg();
+        let mut t = vec![0_u64; width];
+        let mut bits = 64;
+        while bits < 64 * width {
+            // inv = inv * (2 - divisor * inv)
+            mul_low(&divisor.limbs, &inv.limbs, &mut t);
+            let mut two = vec![0_u64; width];
+            two[0] = 2;
+            sub_in(&mut two, &t);
+            mul_low(&inv.limbs, &two, &mut t);
+            inv.limbs.copy_from_slice(&t);
+            bits *= 2;
+        }
+
+        let mut q = SgxBigUint::zero(width);
+        mul_low(&self.limbs, &inv.limbs, &mut q.limbs);
+        let product = q.mul(divisor);
+        if product.ct_eq(self) {
+            Ok(q)
+        } else {
+            Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        }
+    }
+}
+
+impl fmt::Debug for SgxBigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SgxBigUint(..)")
+    }
+}
+
+impl Drop for SgxBigUint {
+    fn drop(&mut self) {
+        for x in self.limbs.iter_mut() {
+            unsafe { ptr::write_volatile(x, 0) };
+        }
+    }
+}
+
+///
+/// Arithmetic modulo an odd number, in Montgomery representation.
+///
+/// # Description
+///
+/// The context precomputes the constants of the modulus once, so that many operations
+/// with the same modulus are cheap. Inputs of any width are reduced first, and every
+/// result is reduced and as wide as the modulus. The modulus itself is treated as
+/// public.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sgx_tcrypto::{SgxBigUint, SgxMontgomery};
+///
+/// let n = SgxBigUint::from_u64(3233);
+/// let mont = SgxMontgomery::new(&n).unwrap();
+/// let c = mont.mod_exp_vartime(&SgxBigUint::from_u64(65), &SgxBigUint::from_u64(17));
+/// let m = mont.mod_exp(&c, &SgxBigUint::from_u64(413));
+/// ```
+///
+#[derive(Clone)]
+pub struct SgxMontgomery {
+    m: Vec<u64>,
+    // -m^-1 mod 2^64
+    n0: u64,
+    // 2^(128N) mod m
+    r2: Vec<u64>,
+}
+
+impl SgxMontgomery {
+    ///
+    /// Prepares arithmetic modulo the given number.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The modulus is even or one.
+    ///
+    pub fn new(modulus: &SgxBigUint) -> SgxResult<SgxMontgomery> {
+        let len = match modulus.limbs.iter().rposition(|x| *x != 0) {
+            Some(i) => i + 1,
+            None => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        };
+        let m = modulus.limbs[..len].to_vec();
+        if m[0] & 1 == 0 || (len == 1 && m[0] == 1) {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        // r2 = 2^(128N) mod m, by doubling 1 one bit at a time.
+        let mut r = vec![0_u64; len];
+        r[0] = 1;
+        let mut d = vec![0_u64; len];
+        for _ in 0..128 * len {
+            d.copy_from_slice(&r);
+            let carry = add_in(&mut r, &d);
+            d.copy_from_slice(&r);
+            let borrow = sub_in(&mut d, &m);
+            assign_if(&mut r, &d, carry | (1 ^ borrow));
+        }
+
+        Ok(SgxMontgomery {
+            n0: neg_inv64(m[0]),
+            m,
+            r2: r,
+        })
+    }
+
+    /// The modulus.
+    pub fn modulus(&self) -> SgxBigUint {
+        SgxBigUint {
+            limbs: self.m.clone(),
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.m.len()
+    }
+
+    // a - m if a (with an extra top word hi) is at least m, else a.
+    fn reduce_once(&self, a: &mut [u64], hi: u64) {
+        let mut d = a.to_vec();
+        let borrow = sub_in(&mut d, &self.m);
+        assign_if(a, &d, hi | (1 ^ borrow));
+        rsgx_zeroize(as_bytes_mut(&mut d));
+    }
+
+    // Montgomery multiplication: a * b / 2^(64N) mod m, for a, b below m.
+    fn mont_mul(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let n = self.width();
+        let mut t = vec![0_u64; n];
+        let mut t_hi = 0_u64;
+        for bi in b.iter() {
+            let mut carry = 0_u64;
+            for j in 0..n {
+                let s = t[j] as u128 + (a[j] as u128) * (*bi as u128) + carry as u128;
+                t[j] = s as u64;
+                carry = (s >> 64) as u64;
+            }
+            let (s, c1) = t_hi.overflowing_add(carry);
+            t_hi = s;
+            let t_top = c1 as u64;
+
+            let q = t[0].wrapping_mul(self.n0);
+            let s = t[0] as u128 + (q as u128) * (self.m[0] as u128);
+            let mut carry = (s >> 64) as u64;
+            for j in 1..n {
+                let s = t[j] as u128 + (q as u128) * (self.m[j] as u128) + carry as u128;
+                t[j - 1] = s as u64;
+                carry = (s >> 64) as u64;
+            }
+            let s = t_hi as u128 + carry as u128;
+            t[n - 1] = s as u64;
+            t_hi = t_top + (s >> 64) as u64;
+        }
+        self.reduce_once(&mut t, t_hi);
+        t
+    }
+
+    fn mont_form(&self, a: &[u64]) -> Vec<u64> {
+        self.mont_mul(a, &self.r2)
+    }
+
+    fn normal_form(&self, a: &[u64]) -> Vec<u64> {
+        let mut one = vec![0_u64; self.width()];
+        one[0] = 1;
+        self.mont_mul(a, &one)
+    }
+
+    fn wrap(&self, limbs: Vec<u64>) -> SgxBigUint {
+        SgxBigUint { limbs }
+    }
+
+    // a mod m, a chunk of N limbs at a time from the top: r = r * 2^(64N) + chunk.
+    fn reduce_limbs(&self, a: &[u64]) -> Vec<u64> {
+        let n = self.width();
+        let mut r = vec![0_u64; n];
+        let mut chunk = vec![0_u64; n];
+        let chunks = (a.len() + n - 1) / n;
+        for c in (0..chunks).rev() {
+            for (i, x) in chunk.iter_mut().enumerate() {
+                *x = a.get(c * n + i).copied().unwrap_or(0);
+            }
+            // mont_form(x) is x * 2^(64N) mod m for any x below m, and the normal
+            // form of mont_form(chunk) is chunk mod m for any chunk below 2^(64N).
+            let shifted = self.mont_form(&r);
+            let low = self.normal_form(&self.mont_form(&chunk));
+            r = self.add_reduced(&shifted, &low);
+        }
+        rsgx_zeroize(as_bytes_mut(&mut chunk));
+        r
+    }
+
+    fn add_reduced(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let mut r = a.to_vec();
+        let carry = add_in(&mut r, b);
+        self.reduce_once(&mut r, carry);
+        r
+    }
+
+    fn sub_reduced(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let mut r = a.to_vec();
+        let borrow = sub_in(&mut r, b);
+        let mut m = self.m.clone();
+        for x in m.iter_mut() {
+            *x &= 0_u64.wrapping_sub(borrow);
+        }
+        add_in(&mut r, &m);
+        r
+    }
+
+    /// a mod m.
+    pub fn reduce(&self, a: &SgxBigUint) -> SgxBigUint {
+        self.wrap(self.reduce_limbs(&a.limbs))
+    }
+
+    /// a + b mod m.
+    pub fn add(&self, a: &SgxBigUint, b: &SgxBigUint) -> SgxBigUint {
+        self.wrap(self.add_reduced(&self.reduce_limbs(&a.limbs), &self.reduce_limbs(&b.limbs)))
+    }
+
+    /// a - b mod m.
+    pub fn sub(&self, a: &SgxBigUint, b: &SgxBigUint) -> SgxBigUint {
+        self.wrap(self.sub_reduced(&self.reduce_limbs(&a.limbs), &self.reduce_limbs(&b.limbs)))
+    }
+
+    /// a * b mod m.
+    pub fn mul(&self, a: &SgxBigUint, b: &SgxBigUint) -> SgxBigUint {
+        let t = self.mont_mul(&self.reduce_limbs(&a.limbs), &self.reduce_limbs(&b.limbs));
+        // t is a * b / 2^(64N), and mont_form multiplies by 2^(64N).
+        self.wrap(self.mont_form(&t))
+    }
+
+    ///
+    /// base^exp mod m for a secret exponent.
+    ///
+    /// The exponent is processed in windows of four bits with a table lookup that
+    /// reads every entry, so the time depends only on the width of exp.
+    ///
+    pub fn mod_exp(&self, base: &SgxBigUint, exp: &SgxBigUint) -> SgxBigUint {
+        let n = self.width();
+        let one = self.mont_form(&self.reduce_limbs(&[1]));
+        let mut table = vec![0_u64; n << WINDOW];
+        table[..n].copy_from_slice(&one);
+        let b = self.mont_form(&self.reduce_limbs(&base.limbs));
+        for i in 1..(1 << WINDOW) {
+            let next = self.mont_mul(&table[(i - 1) * n..i * n], &b);
+            table[i * n..(i + 1) * n].copy_from_slice(&next);
+        }
+
+        let mut r = one;
+        let mut entry = vec![0_u64; n];
+        for i in (0..64 * exp.limbs.len() / WINDOW).rev() {
+            for _ in 0..WINDOW {
+                r = self.mont_mul(&r, &r);
+            }
+            let bits = (exp.limbs[i * WINDOW / 64] >> (i * WINDOW % 64)) & ((1 << WINDOW) - 1);
+            for (k, candidate) in table.chunks(n).enumerate() {
+                assign_if(&mut entry, candidate, is_zero(&[k as u64 ^ bits]));
+            }
+            r = self.mont_mul(&r, &entry);
+        }
+        rsgx_zeroize(as_bytes_mut(&mut table));
+        rsgx_zeroize(as_bytes_mut(&mut entry));
+        self.wrap(self.normal_form(&r))
+    }
+
+    ///
+    /// base^exp mod m for a public exponent, such as an RSA public exponent.
+    ///
+    /// Only squarings and multiplications for the significant bits of exp are done,
+    /// so the time depends on the value of exp, but not on base.
+    ///
+    pub fn mod_exp_vartime(&self, base: &SgxBigUint, exp: &SgxBigUint) -> SgxBigUint {
+        let b = self.mont_form(&self.reduce_limbs(&base.limbs));
+        let mut r = self.mont_form(&self.reduce_limbs(&[1]));
+        for i in (0..exp.bits_vartime()).rev() {
+            r = self.mont_mul(&r, &r);
+            if (exp.limbs[i / 64] >> (i % 64)) & 1 == 1 {
+                r = self.mont_mul(&r, &b);
+            }
+        }
+        self.wrap(self.normal_form(&r))
+    }
+
+    ///
+    /// a^-1 mod m.
+    ///
+    /// The inverse is found with the binary extended Euclidean algorithm, run for a
+    /// fixed number of steps with every branch replaced by selection, so the modulus
+    /// need not be prime.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// a has no inverse: it shares a factor with the modulus.
+    ///
+    pub fn mod_inv(&self, a: &SgxBigUint) -> SgxResult<SgxBigUint> {
+        let n = self.width();
+        // a * x = u and a * y = v modulo m throughout.
+        let mut u = self.reduce_limbs(&a.limbs);
+        let mut v = self.m.clone();
+        let mut x = vec![0_u64; n];
+        x[0] = 1;
+        let mut y = vec![0_u64; n];
+        let mut t = vec![0_u64; n];
+
+        // Each step removes at least one bit from u or v.
+        for _ in 0..128 * n {
+            // If both are odd, subtract the smaller from the larger.
+            let both_odd = u[0] & v[0] & 1;
+            t.copy_from_slice(&u);
+            let u_less = sub_in(&mut t, &v);
+            assign_if(&mut u, &t, both_odd & (1 ^ u_less));
+            let x_minus_y = self.sub_reduced(&x, &y);
+            assign_if(&mut x, &x_minus_y, both_odd & (1 ^ u_less));
+            t.copy_from_slice(&v);
+            sub_in(&mut t, &u);
+            assign_if(&mut v, &t, both_odd & u_less);
+            let y_minus_x = self.sub_reduced(&y, &x);
+            assign_if(&mut y, &y_minus_x, both_odd & u_less);
+
+            // Now one of them is even: halve it and its coefficient.
+            let u_even = 1 ^ (u[0] & 1);
+            t.copy_from_slice(&u);
+            shr1(&mut t, 0);
+            assign_if(&mut u, &t, u_even);
+            let half_x = self.half(&x);
+            assign_if(&mut x, &half_x, u_even);
+            t.copy_from_slice(&v);
+            shr1(&mut t, 0);
+            assign_if(&mut v, &t, 1 ^ u_even);
+            let half_y = self.half(&y);
+            assign_if(&mut y, &half_y, 1 ^ u_even);
+        }
+
+        // v is now gcd(a, m).
+        let mut one = vec![0_u64; n];
+        one[0] = 1;
+        sub_in(&mut v, &one);
+        let invertible = is_zero(&v) == 1;
+        rsgx_zeroize(as_bytes_mut(&mut u));
+        rsgx_zeroize(as_bytes_mut(&mut x));
+        rsgx_zeroize(as_bytes_mut(&mut t));
+        if invertible {
+            Ok(self.wrap(y))
+        } else {
+            rsgx_zeroize(as_bytes_mut(&mut y));
+            Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        }
+    }
+
+    // a / 2 mod m: (a + m) / 2 when a is odd.
+    fn half(&self, a: &[u64]) -> Vec<u64> {
+        let mut r = a.to_vec();
+        let mut m = self.m.clone();
+        for x in m.iter_mut() {
+            *x &= 0_u64.wrapping_sub(a[0] & 1);
+        }
+        let carry = add_in(&mut r, &m);
+        shr1(&mut r, carry);
+        r
+    }
+}
+
+fn as_bytes_mut(limbs: &mut [u64]) -> &mut [u8] {
+    unsafe { slice::from_raw_parts_mut(limbs.as_mut_ptr() as *mut u8, 8 * limbs.len()) }
+}
@@ -0,0 +1,435 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! ChaCha20-Poly1305
+//!
+//! The AEAD of RFC 8439. It is built from additions, rotations and exclusive-ors
+//! only, so it is fast without dedicated instructions and runs in constant time.
+//!
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::secret::{rsgx_ct_eq, rsgx_zeroize};
+use core::ptr;
+use sgx_types::*;
+
+pub type sgx_chacha20_poly1305_key_t = [uint8_t; 32];
+pub type sgx_chacha20_poly1305_nonce_t = [uint8_t; 12];
+pub type sgx_chacha20_poly1305_tag_t = [uint8_t; 16];
+
+// The block counter is 32 bits and block 0 makes the Poly1305 key.
+const CHACHA_MAX_DATA_LEN: u64 = ((1 << 32) - 1) * 64;
+
+const M44: u64 = (1 << 44) - 1;
+const M42: u64 = (1 << 42) - 1;
+
+macro_rules! quarter_round {
+    ($x:ident, $a:expr, $b:expr, $c:expr, $d:expr) => {
+        $x[$a] = $x[$a].wrapping_add($x[$b]);
+        $x[$d] = ($x[$d] ^ $x[$a]).rotate_left(16);
+        $x[$c] = $x[$c].wrapping_add($x[$d]);
+        $x[$b] = ($x[$b] ^ $x[$c]).rotate_left(12);
+        $x[$a] = $x[$a].wrapping_add($x[$b]);
+        $x[$d] = ($x[$d] ^ $x[$a]).rotate_left(8);
+        $x[$c] = $x[$c].wrapping_add($x[$d]);
+        $x[$b] = ($x[$b] ^ $x[$c]).rotate_left(7);
+    };
+}
+
+// Writes the keystream block of the state and advances its counter.
+fn chacha20_block(state: &mut [u32; 16], out: &mut [u8; 64]) {
+    let mut x = *state;
+    for _ in 0..10 {
+        quarter_round!(x, 0, 4, 8, 12);
+        quarter_round!(x, 1, 5, 9, 13);
+        quarter_round!(x, 2, 6, 10, 14);
+        quarter_round!(x, 3, 7, 11, 15);
+        quarter_round!(x, 0, 5, 10, 15);
+        quarter_round!(x, 1, 6, 11, 12);
+        quarter_round!(x, 2, 7, 8, 13);
+        quarter_round!(x, 3, 4, 9, 14);
+    }
+    for (i, chunk) in out.chunks_mut(4).enumerate() {
+        chunk.copy_from_slice(&x[i].wrapping_add(state[i]).to_le_bytes());
+    }
+    state[12] = state[12].wrapping_add(1);
+    unsafe { ptr::write_volatile(&mut x, [0; 16]) };
+}
+
+fn le64(b: &[u8]) -> u64 {
+    let mut x = [0_u8; 8];
+    x.copy_from_slice(&b[..8]);
+    u64::from_le_bytes(x)
+}
+
+// Poly1305 in radix 2^44, over a stream of bytes that is zero-padded to whole blocks
+// when pad is called, as the AEAD construction requires.
+struct Poly1305 {
+    r: [u64; 3],
+    s: [u64; 2],
+    h: [u64; 3],
+    buf: [u8; 16],
+    used: usize,
+}
+
+impl Poly1305 {
+    fn new(key: &[u8; 32]) -> Poly1305 {
+        let t0 = le64(&key[0..8]);
+        let t1 = le64(&key[8..16]);
+        Poly1305 {
+            r: [
+                t0 & 0xffc_0fff_ffff,
+                ((t0 >> 44) | (t1 << 20)) & 0xfff_ffc0_ffff,
+                (t1 >> 24) & 0x00f_ffff_fc0f,
+            ],
+            s: [le64(&key[16..24]), le64(&key[24..32])],
+            h: [0; 3],
+            buf: [0; 16],
+            used: 0,
+        }
+    }
+
+    fn block(&mut self, m: &[u8]) {
+        let [r0, r1, r2] = self.r;
+        let s1 = r1 * (5 << 2);
+        let s2 = r2 * (5 << 2);
+        let t0 = le64(&m[0..8]);
+        let t1 = le64(&m[8..16]);
+        let h0 = self.h[0] + (t0 & M44);
+        let h1 = self.h[1] + (((t0 >> 44) | (t1 << 20)) & M44);
+        let h2 = self.h[2] + (((t1 >> 24) & M42) | (1 << 40));
+
+        let m = |a: u64, b: u64| u128::from(a) * u128::from(b);
+        let d0 = m(h0, r0) + m(h1, s2) + m(h2, s1);
+        let mut d1 = m(h0, r1) + m(h1, r0) + m(h2, s2);
+        let mut d2 = m(h0, r2) + m(h1, r1) + m(h2, r0);
+
+        d1 += d0 >> 44;
+        let h0 = (d0 as u64) & M44;
+        d2 += d1 >> 44;
+        let h1 = (d1 as u64) & M44;
+        let c = (d2 >> 42) as u64;
+        let h2 = (d2 as u64) & M42;
+        let h0 = h0 + c * 5;
+        self.h = [h0 & M44, h1 + (h0 >> 44), h2];
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        if self.used > 0 {
+            let n = core::cmp::min(16 - self.used, data.len());
+            self.buf[self.used..self.used + n].copy_from_slice(&data[..n]);
+            self.used += n;
+            data = &data[n..];
+            if self.used < 16 {
+                return;
+            }
+            let buf = self.buf;
+            self.block(&buf);
+            self.used = 0;
+        }
+        let mut blocks = data.chunks_exact(16);
+        for block in &mut blocks {
+            self.block(block);
+        }
+        let rest = blocks.remainder();
+        self.buf[..rest.len()].copy_from_slice(rest);
+        self.used = rest.len();
+    }
+
+    fn pad(&mut self) {
+        if self.used > 0 {
+            let mut block = [0_u8; 16];
+            block[..self.used].copy_from_slice(&self.buf[..self.used]);
+            self.block(&block);
+            self.used = 0;
+        }
+    }
+
+    fn finish(&mut self) -> sgx_chacha20_poly1305_tag_t {
+        let [mut h0, mut h1, mut h2] = self.h;
+        for _ in 0..2 {
+            h2 += h1 >> 44;
+            h1 &= M44;
+            h0 += (h2 >> 42) * 5;
+            h2 &= M42;
+            h1 += h0 >> 44;
+            h0 &= M44;
+        }
+
+        // h - p, kept if it does not borrow.
+        let mut g0 = h0 + 5;
+        let mut g1 = h1 + (g0 >> 44);
+        g0 &= M44;
+        let g2 = (h2 + (g1 >> 44)).wrapping_sub(1 << 42);
+        g1 &= M44;
+        let mask = (g2 >> 63).wrapping_sub(1);
+        h0 = (h0 & !mask) | (g0 & mask);
+        h1 = (h1 & !mask) | (g1 & mask);
+        h2 = (h2 & !mask) | (g2 & mask & M42);
+
+        let [s0, s1] = self.s;
+        h0 += s0 & M44;
+        h1 += (((s0 >> 44) | (s1 << 20)) & M44) + (h0 >> 44);
+        h0 &= M44;
+        h2 += ((s1 >> 24) & M42) + (h1 >> 44);
+        h1 &= M44;
+        h2 &= M42;
+
+        let mut tag = [0_u8; 16];
+        tag[..8].copy_from_slice(&(h0 | (h1 << 44)).to_le_bytes());
+        tag[8..].copy_from_slice(&((h1 >> 20) | (h2 << 24)).to_le_bytes());
+        tag
+    }
+}
+
+impl Drop for Poly1305 {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::write_volatile(&mut self.r, [0; 3]);
+            ptr::write_volatile(&mut self.s, [0; 2]);
+            ptr::write_volatile(&mut self.h, [0; 3]);
+        }
+        rsgx_zeroize(&mut self.buf);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Encrypt,
+    Decrypt,
+}
+
+///
+/// An incremental ChaCha20-Poly1305 encryption or decryption.
+///
+/// # Description
+///
+/// SgxChaCha20Poly1305 has the same interface as SgxAesGcm: the additional
+/// authenticated data is fed with update_aad before the first call to update, which
+/// processes the message in chunks of any size. An encryption is finished with
+/// finalize, which returns the tag, and a decryption with verify.
+///
+/// It does not use the AES instructions, so it is a good choice where those are slow
+/// or unavailable. As with SgxAesGcm, a nonce must never be used twice with the same
+/// key, and the output of a decryption must not be used until verify has succeeded.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sgx_tcrypto::SgxChaCha20Poly1305;
+///
+/// let key = [0_u8; 32];
+/// let nonce = [0_u8; 12];
+/// let mut aead = SgxChaCha20Poly1305::new_encrypt(&key, &nonce);
+/// aead.update_aad(b"header").unwrap();
+/// let mut ciphertext = [0_u8; 8];
+/// aead.update(b"chunk 1 ", &mut ciphertext).unwrap();
+/// let tag = aead.finalize().unwrap();
+/// ```
+///
+pub struct SgxChaCha20Poly1305 {
+    direction: Direction,
+    state: [u32; 16],
+    keystream: [u8; 64],
+    used: usize,
+    poly: Poly1305,
+    started: bool,
+    aad_len: u64,
+    data_len: u64,
+}
+
+impl SgxChaCha20Poly1305 {
+    ///
+    /// Starts encrypting with the given key and 96-bit nonce.
+    ///
+    pub fn new_encrypt(
+        key: &sgx_chacha20_poly1305_key_t,
+        nonce: &sgx_chacha20_poly1305_nonce_t,
+    ) -> SgxChaCha20Poly1305 {
+        SgxChaCha20Poly1305::new(Direction::Encrypt, key, nonce)
+    }
+
+    ///
+    /// Starts decrypting with the given key and 96-bit nonce.
+    ///
+    pub fn new_decrypt(
+        key: &sgx_chacha20_poly1305_key_t,
+        nonce: &sgx_chacha20_poly1305_nonce_t,
+    ) -> SgxChaCha20Poly1305 {
+        SgxChaCha20Poly1305::new(Direction::Decrypt, key, nonce)
+    }
+
+    fn new(
+        direction: Direction,
+        key: &sgx_chacha20_poly1305_key_t,
+        nonce: &sgx_chacha20_poly1305_nonce_t,
+    ) -> SgxChaCha20Poly1305 {
+        let mut state = [0_u32; 16];
+        state[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+        for (i, word) in key.chunks(4).enumerate() {
+            state[4 + i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for (i, word) in nonce.chunks(4).enumerate() {
+            state[13 + i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+
+        // Block 0 keys Poly1305; the message starts at block 1.
+        let mut block = [0_u8; 64];
+        chacha20_block(&mut state, &mut block);
+        let mut poly_key = [0_u8; 32];
+        poly_key.copy_from_slice(&block[..32]);
+        let poly = Poly1305::new(&poly_key);
+        rsgx_zeroize(&mut block);
+        rsgx_zeroize(&mut poly_key);
+
+        SgxChaCha20Poly1305 {
+            direction,
+            state,
+            keystream: [0; 64],
+            used: 64,
+            poly,
+            started: false,
+            aad_len: 0,
+            data_len: 0,
+        }
+    }
+
+    ///
+    /// Adds a chunk of additional authenticated data.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// Data has already been processed with update.
+    ///
+    pub fn update_aad(&mut self, aad: &[u8]) -> SgxError {
+        if self.started {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        self.aad_len += aad.len() as u64;
+        self.poly.update(aad);
+        Ok(())
+    }
+
+    ///
+    /// Encrypts or decrypts the next chunk of the message from src into dst.
+    ///
+    /// dst must be at least as long as src; the first src.len() bytes are written.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// dst is too short, or the message would exceed the limit of 2^38 - 64 bytes.
+    ///
+    pub fn update(&mut self, src: &[u8], dst: &mut [u8]) -> SgxError {
+        check_algorithm(SgxAlgorithm::ChaCha20Poly1305)?;
+        if dst.len() < src.len() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        match self.data_len.checked_add(src.len() as u64) {
+            Some(len) if len <= CHACHA_MAX_DATA_LEN => self.data_len = len,
+            _ => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+        self.start();
+
+        let dst = &mut dst[..src.len()];
+        if self.direction == Direction::Decrypt {
+            self.poly.update(src);
+        }
+        for (d, s) in dst.iter_mut().zip(src.iter()) {
+            if self.used == 64 {
+                chacha20_block(&mut self.state, &mut self.keystream);
+                self.used = 0;
+            }
+            *d = s ^ self.keystream[self.used];
+            self.used += 1;
+        }
+        if self.direction == Direction::Encrypt {
+            self.poly.update(dst);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Finishes an encryption and returns the tag.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// This is a decryption.
+    ///
+    pub fn finalize(mut self) -> SgxResult<sgx_chacha20_poly1305_tag_t> {
+        check_algorithm(SgxAlgorithm::ChaCha20Poly1305)?;
+        if self.direction != Direction::Encrypt {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        Ok(self.compute_tag())
+    }
+
+    ///
+    /// Finishes a decryption and checks the tag of the whole message.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The message or the additional authenticated data was modified.
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// This is an encryption.
+    ///
+    pub fn verify(mut self, tag: &sgx_chacha20_poly1305_tag_t) -> SgxError {
+        check_algorithm(SgxAlgorithm::ChaCha20Poly1305)?;
+        if self.direction != Direction::Decrypt {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        let computed = self.compute_tag();
+        if rsgx_ct_eq(&computed, tag) {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+        }
+    }
+
+    // Ends the additional data, which is padded apart from the message.
+    fn start(&mut self) {
+        if !self.started {
+            self.poly.pad();
+            self.started = true;
+        }
+    }
+
+    fn compute_tag(&mut self) -> sgx_chacha20_poly1305_tag_t {
+        self.start();
+        self.poly.pad();
+        let mut lengths = [0_u8; 16];
+        lengths[..8].copy_from_slice(&self.aad_len.to_le_bytes());
+        lengths[8..].copy_from_slice(&self.data_len.to_le_bytes());
+        self.poly.update(&lengths);
+        self.poly.finish()
+    }
+}
+
+impl Drop for SgxChaCha20Poly1305 {
+    fn drop(&mut self) {
+        unsafe { ptr::write_volatile(&mut self.state, [0; 16]) };
+        rsgx_zeroize(&mut self.keystream);
+    }
+}
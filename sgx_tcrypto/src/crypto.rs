@@ -18,6 +18,7 @@
 //!
 //! Cryptographic Functions
 //!
+use crate::policy::{check_algorithm, SgxAlgorithm};
 use core::cell::{Cell, RefCell};
 use core::mem;
 use core::ops::{DerefMut, Drop};
@@ -70,6 +71,7 @@ pub fn rsgx_sha256_msg<T>(src: &T) -> SgxResult<sgx_sha256_hash_t>
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::Sha256)?;
     let size = mem::size_of::<T>();
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -99,6 +101,7 @@ pub fn rsgx_sha256_slice<T>(src: &[T]) -> SgxResult<sgx_sha256_hash_t>
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::Sha256)?;
     let size = mem::size_of_val(src);
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -172,6 +175,7 @@ pub fn rsgx_sha384_msg<T>(src: &T) -> SgxResult<sgx_sha384_hash_t>
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::Sha384)?;
     let size = mem::size_of::<T>();
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -201,6 +205,7 @@ pub fn rsgx_sha384_slice<T>(src: &[T]) -> SgxResult<sgx_sha384_hash_t>
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::Sha384)?;
     let size = mem::size_of_val(src);
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -271,6 +276,7 @@ pub fn rsgx_sha1_msg<T>(src: &T) -> SgxResult<sgx_sha1_hash_t>
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::Sha1)?;
     let size = mem::size_of::<T>();
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -297,6 +303,7 @@ pub fn rsgx_sha1_slice<T>(src: &[T]) -> SgxResult<sgx_sha1_hash_t>
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::Sha1)?;
     let size = mem::size_of_val(src);
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -419,6 +426,7 @@ impl SgxShaHandle {
     /// The SHA256 state is not initialized properly due to an internal cryptography library failure.
     ///
     pub fn init(&self) -> SgxError {
+        check_algorithm(SgxAlgorithm::Sha256)?;
         if self.initflag.get() {
             return Ok(());
         }
@@ -632,6 +640,7 @@ impl SgxSha384Handle {
     /// init returns an allocated and initialized SHA384 algorithm context state.
     ///
     pub fn init(&self) -> SgxError {
+        check_algorithm(SgxAlgorithm::Sha384)?;
         if self.initflag.get() {
             return Ok(());
         }
@@ -755,6 +764,7 @@ impl SgxSha1Handle {
     }
 
     pub fn init(&self) -> SgxError {
+        check_algorithm(SgxAlgorithm::Sha1)?;
         if self.initflag.get() {
             return Ok(());
         }
@@ -933,6 +943,7 @@ pub fn rsgx_rijndael128GCM_encrypt(
     dst: &mut [u8],
     mac: &mut sgx_aes_gcm_128bit_tag_t,
 ) -> SgxError {
+    check_algorithm(SgxAlgorithm::AesGcm)?;
     let src_len = src.len();
     if src_len > u32::MAX as usize {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -1068,6 +1079,7 @@ pub fn rsgx_rijndael128GCM_decrypt(
     mac: &sgx_aes_gcm_128bit_tag_t,
     dst: &mut [u8],
 ) -> SgxError {
+    check_algorithm(SgxAlgorithm::AesGcm)?;
     let src_len = src.len();
     if src_len > u32::MAX as usize {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -1170,6 +1182,7 @@ pub fn rsgx_rijndael128_cmac_msg<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::AesCmac)?;
     let size = mem::size_of::<T>();
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -1200,6 +1213,7 @@ pub fn rsgx_rijndael128_align_cmac_msg<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::AesCmac)?;
     let size = mem::size_of::<T>();
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -1233,6 +1247,7 @@ pub fn rsgx_rijndael128_cmac_slice<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::AesCmac)?;
     let size = mem::size_of_val(src);
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -1263,6 +1278,7 @@ pub fn rsgx_rijndael128_align_cmac_slice<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::AesCmac)?;
     let size = mem::size_of_val(src);
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -1407,6 +1423,7 @@ impl SgxCmacHandle {
     /// An internal cryptography library failure occurred.
     ///
     pub fn init(&self, key: &sgx_cmac_128bit_key_t) -> SgxError {
+        check_algorithm(SgxAlgorithm::AesCmac)?;
         if self.initflag.get() {
             return Ok(());
         }
@@ -1619,6 +1636,7 @@ pub fn rsgx_hmac_sha256_msg<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::Hmac)?;
     let size = mem::size_of::<T>();
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -1651,6 +1669,7 @@ pub fn rsgx_align_hmac_sha256_msg<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::Hmac)?;
     let size = mem::size_of::<T>();
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -1683,6 +1702,7 @@ pub fn rsgx_hmac_sha256_slice<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::Hmac)?;
     let size = mem::size_of_val(src);
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -1715,6 +1735,7 @@ pub fn rsgx_align_hmac_sha256_slice<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::Hmac)?;
     let size = mem::size_of_val(src);
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -1818,6 +1839,7 @@ impl SgxHmacHandle {
     }
 
     pub fn init(&self, key: &sgx_hmac_256bit_key_t) -> SgxError {
+        check_algorithm(SgxAlgorithm::Hmac)?;
         if self.initflag.get() {
             return Ok(());
         }
@@ -1993,6 +2015,7 @@ pub fn rsgx_aes_ctr_encrypt(
     ctr_inc_bits: u32,
     dst: &mut [u8],
 ) -> SgxError {
+    check_algorithm(SgxAlgorithm::AesCtr)?;
     let src_len = src.len();
     if src_len > u32::MAX as usize {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -2089,6 +2112,7 @@ pub fn rsgx_aes_ctr_decrypt(
     ctr_inc_bits: u32,
     dst: &mut [u8],
 ) -> SgxError {
+    check_algorithm(SgxAlgorithm::AesCtr)?;
     let src_len = src.len();
     if src_len > u32::MAX as usize {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -2699,6 +2723,7 @@ impl SgxEccHandle {
         private_b: &sgx_ec256_private_t,
         public_ga: &sgx_ec256_public_t,
     ) -> SgxResult<sgx_ec256_dh_shared_t> {
+        check_algorithm(SgxAlgorithm::EcdhP256)?;
         if !self.initflag.get() {
             return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
         }
@@ -2721,6 +2746,7 @@ impl SgxEccHandle {
         private_b: &sgx_ec256_private_t,
         public_ga: &sgx_ec256_public_t,
     ) -> SgxResult<sgx_align_ec256_dh_shared_t> {
+        check_algorithm(SgxAlgorithm::EcdhP256)?;
         if !self.initflag.get() {
             return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
         }
@@ -2821,6 +2847,7 @@ impl SgxEccHandle {
     where
         T: Copy + ContiguousMemory,
     {
+        check_algorithm(SgxAlgorithm::EcdsaP256)?;
         if !self.initflag.get() {
             return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
         }
@@ -2844,6 +2871,7 @@ impl SgxEccHandle {
     where
         T: Copy + ContiguousMemory,
     {
+        check_algorithm(SgxAlgorithm::EcdsaP256)?;
         if !self.initflag.get() {
             return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
         }
@@ -2925,6 +2953,7 @@ impl SgxEccHandle {
     where
         T: Copy + ContiguousMemory,
     {
+        check_algorithm(SgxAlgorithm::EcdsaP256)?;
         if !self.initflag.get() {
             return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
         }
@@ -2953,6 +2982,7 @@ impl SgxEccHandle {
     where
         T: Copy + ContiguousMemory,
     {
+        check_algorithm(SgxAlgorithm::EcdsaP256)?;
         if !self.initflag.get() {
             return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
         }
@@ -2975,6 +3005,7 @@ impl SgxEccHandle {
         public: &sgx_ec256_public_t,
         signature: &sgx_ec256_signature_t,
     ) -> SgxResult<bool> {
+        check_algorithm(SgxAlgorithm::EcdsaP256)?;
         if !self.initflag.get() {
             return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
         }
@@ -3102,6 +3133,7 @@ pub fn rsgx_rsa3072_sign_msg<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::RsaPkcs1v15)?;
     let size = mem::size_of::<T>();
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -3135,6 +3167,7 @@ pub fn rsgx_rsa3072_sign_slice<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::RsaPkcs1v15)?;
     let size = mem::size_of_val(data);
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -3217,6 +3250,7 @@ pub fn rsgx_rsa3072_sign_msg_ex<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::RsaPkcs1v15)?;
     let size = mem::size_of::<T>();
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -3253,6 +3287,7 @@ pub fn rsgx_rsa3072_sign_slice_ex<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::RsaPkcs1v15)?;
     let size = mem::size_of_val(data);
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -3337,6 +3372,7 @@ pub fn rsgx_rsa3072_verify_msg<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::RsaPkcs1v15)?;
     let size = mem::size_of::<T>();
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -3375,6 +3411,7 @@ pub fn rsgx_rsa3072_verify_slice<T>(
 where
     T: Copy + ContiguousMemory,
 {
+    check_algorithm(SgxAlgorithm::RsaPkcs1v15)?;
     let size = mem::size_of_val(data);
     if size == 0 {
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -3752,6 +3789,7 @@ impl SgxRsaPrivKey {
         out_len: &mut usize,
         in_data: &[u8],
     ) -> SgxError {
+        check_algorithm(SgxAlgorithm::RsaOaep)?;
         if !self.createflag.get() {
             return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
         }
@@ -3846,6 +3884,7 @@ impl SgxRsaPubKey {
         out_len: &mut usize,
         in_data: &[u8],
     ) -> SgxError {
+        check_algorithm(SgxAlgorithm::RsaOaep)?;
         if !self.createflag.get() {
             return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
         }
@@ -4143,6 +4182,7 @@ impl SgxAesHandle {
     }
 
     pub fn init(&self, key: &sgx_aes_gcm_128bit_key_t, iv: &[u8], aad: &[u8]) -> SgxError {
+        check_algorithm(SgxAlgorithm::AesGcm)?;
         if self.initflag.get() {
             return Ok(());
         }
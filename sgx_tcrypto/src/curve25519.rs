@@ -0,0 +1,619 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Arithmetic on Curve25519 and edwards25519
+//!
+//! Field elements use five 51 bit limbs. Everything that may see secret data runs in
+//! constant time: there are no secret-dependent branches or table indices.
+//!
+use core::ptr;
+
+const MASK51: u64 = (1 << 51) - 1;
+
+#[derive(Clone, Copy)]
+pub(crate) struct Fe([u64; 5]);
+
+impl Fe {
+    pub(crate) const ZERO: Fe = Fe([0, 0, 0, 0, 0]);
+    pub(crate) const ONE: Fe = Fe([1, 0, 0, 0, 0]);
+
+    const D: Fe = Fe([
+        929955233495203,
+        466365720129213,
+        1662059464998953,
+        2033849074728123,
+        1442794654840575,
+    ]);
+    const D2: Fe = Fe([
+        1859910466990425,
+        932731440258426,
+        1072319116312658,
+        1815898335770999,
+        633789495995903,
+    ]);
+    const SQRT_M1: Fe = Fe([
+        1718705420411056,
+        234908883556509,
+        2233514472574048,
+        2117202627021982,
+        765476049583133,
+    ]);
+    // 2^((p + 3) / 8) and sqrt(-486664), the constants c2 of RFC 9380, appendix G.2.1,
+    // and c1 of appendix G.2.2.
+    const ELL2_C2: Fe = Fe([
+        1718705420411057,
+        234908883556509,
+        2233514472574048,
+        2117202627021982,
+        765476049583133,
+    ]);
+    const ELL2_EDWARDS_C1: Fe = Fe([
+        1693982333959686,
+        608509411481997,
+        2235573344831311,
+        947681270984193,
+        266558006233600,
+    ]);
+
+    pub(crate) fn from_u64(x: u64) -> Fe {
+        Fe([x & MASK51, x >> 51, 0, 0, 0])
+    }
+
+    /// Decodes 32 little-endian bytes, ignoring the top bit.
+    pub(crate) fn from_bytes(b: &[u8; 32]) -> Fe {
+        let load = |i: usize| {
+            let mut w = [0_u8; 8];
+            w.copy_from_slice(&b[i..i + 8]);
+            u64::from_le_bytes(w)
+        };
+        Fe([
+            load(0) & MASK51,
+            (load(6) >> 3) & MASK51,
+            (load(12) >> 6) & MASK51,
+            (load(19) >> 1) & MASK51,
+            (load(24) >> 12) & MASK51,
+        ])
+    }
+
+    /// Reduces a 384 bit big-endian number, the output size of hash_to_field.
+    pub(crate) fn from_be_bytes_wide(b: &[u8; 48]) -> Fe {
+        let half = |chunk: &[u8]| {
+            let mut le = [0_u8; 32];
+            for (d, s) in le.iter_mut().zip(chunk.iter().rev()) {
+                *d = *s;
+            }
+            Fe::from_bytes(&le)
+        };
+        let mut shift = [0_u8; 32];
+        shift[24] = 1;
+        half(&b[..24])
+            .mul(&Fe::from_bytes(&shift))
+            .add(&half(&b[24..]))
+    }
+
+    /// Encodes the canonical representative in 32 little-endian bytes.
+    pub(crate) fn to_bytes(self) -> [u8; 32] {
+        let mut l = self.carry().0;
+        // q is 1 exactly when the value is at least p.
+        let mut q = (l[0] + 19) >> 51;
+        q = (l[1] + q) >> 51;
+        q = (l[2] + q) >> 51;
+        q = (l[3] + q) >> 51;
+        q = (l[4] + q) >> 51;
+        l[0] += 19 * q;
+        l[1] += l[0] >> 51;
+        l[0] &= MASK51;
+        l[2] += l[1] >> 51;
+        l[1] &= MASK51;
+        l[3] += l[2] >> 51;
+        l[2] &= MASK51;
+        l[4] += l[3] >> 51;
+        l[3] &= MASK51;
+        l[4] &= MASK51;
+
+        let mut out = [0_u8; 32];
+        let words = [
+            l[0] | (l[1] << 51),
+            (l[1] >> 13) | (l[2] << 38),
+            (l[2] >> 26) | (l[3] << 25),
+            (l[3] >> 39) | (l[4] << 12),
+        ];
+        for (chunk, word) in out.chunks_exact_mut(8).zip(words.iter()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        out
+    }
+
+    fn carry(self) -> Fe {
+        let l = self.0;
+        let c = [l[0] >> 51, l[1] >> 51, l[2] >> 51, l[3] >> 51, l[4] >> 51];
+        Fe([
+            (l[0] & MASK51) + c[4] * 19,
+            (l[1] & MASK51) + c[0],
+            (l[2] & MASK51) + c[1],
+            (l[3] & MASK51) + c[2],
+            (l[4] & MASK51) + c[3],
+        ])
+    }
+
+    pub(crate) fn add(&self, b: &Fe) -> Fe {
+        let (a, b) = (&self.0, &b.0);
+        Fe([
+            a[0] + b[0],
+            a[1] + b[1],
+            a[2] + b[2],
+            a[3] + b[3],
+            a[4] + b[4],
+        ])
+        .carry()
+    }
+
+    pub(crate) fn sub(&self, b: &Fe) -> Fe {
+        // Add 16p first so that no limb underflows.
+        let (a, b) = (&self.0, &b.0);
+        Fe([
+            (a[0] + 36028797018963664) - b[0],
+            (a[1] + 36028797018963952) - b[1],
+            (a[2] + 36028797018963952) - b[2],
+            (a[3] + 36028797018963952) - b[3],
+            (a[4] + 36028797018963952) - b[4],
+        ])
+        .carry()
+    }
+
+    pub(crate) fn neg(&self) -> Fe {
+        Fe::ZERO.sub(self)
+    }
+
+    pub(crate) fn mul(&self, b: &Fe) -> Fe {
+        let m = |x: u64, y: u64| (x as u128) * (y as u128);
+        let (a, b) = (&self.0, &b.0);
+        let b1 = b[1] * 19;
+        let b2 = b[2] * 19;
+        let b3 = b[3] * 19;
+        let b4 = b[4] * 19;
+
+        let c0 = m(a[0], b[0]) + m(a[4], b1) + m(a[3], b2) + m(a[2], b3) + m(a[1], b4);
+        let mut c1 = m(a[1], b[0]) + m(a[0], b[1]) + m(a[4], b2) + m(a[3], b3) + m(a[2], b4);
+        let mut c2 = m(a[2], b[0]) + m(a[1], b[1]) + m(a[0], b[2]) + m(a[4], b3) + m(a[3], b4);
+        let mut c3 = m(a[3], b[0]) + m(a[2], b[1]) + m(a[1], b[2]) + m(a[0], b[3]) + m(a[4], b4);
+        let mut c4 = m(a[4], b[0]) + m(a[3], b[1]) + m(a[2], b[2]) + m(a[1], b[3]) + m(a[0], b[4]);
+
+        c1 += c0 >> 51;
+        c2 += c1 >> 51;
+        c3 += c2 >> 51;
+        c4 += c3 >> 51;
+        let mut l0 = (c0 as u64 & MASK51) + (c4 >> 51) as u64 * 19;
+        let l1 = (c1 as u64 & MASK51) + (l0 >> 51);
+        l0 &= MASK51;
+        Fe([
+            l0,
+            l1,
+            c2 as u64 & MASK51,
+            c3 as u64 & MASK51,
+            c4 as u64 & MASK51,
+        ])
+    }
+
+    pub(crate) fn square(&self) -> Fe {
+        self.mul(self)
+    }
+
+    fn pow2k(&self, k: u32) -> Fe {
+        let mut r = *self;
+        for _ in 0..k {
+            r = r.square();
+        }
+        r
+    }
+
+    // Returns (z^(2^250 - 1), z^11).
+    fn pow22501(&self) -> (Fe, Fe) {
+        let t0 = self.square();
+        let t1 = t0.pow2k(2);
+        let t2 = self.mul(&t1);
+        let t3 = t0.mul(&t2);
+        let t4 = t3.square();
+        let t5 = t2.mul(&t4);
+        let t7 = t5.pow2k(5).mul(&t5);
+        let t9 = t7.pow2k(10).mul(&t7);
+        let t11 = t9.pow2k(20).mul(&t9);
+        let t13 = t11.pow2k(10).mul(&t7);
+        let t15 = t13.pow2k(50).mul(&t13);
+        let t17 = t15.pow2k(100).mul(&t15);
+        let t19 = t17.pow2k(50).mul(&t13);
+        (t19, t3)
+    }
+
+    pub(crate) fn invert(&self) -> Fe {
+        let (t19, t3) = self.pow22501();
+        t19.pow2k(5).mul(&t3)
+    }
+
+    // z^((p - 5) / 8)
+    pub(crate) fn pow_p58(&self) -> Fe {
+        let (t19, _) = self.pow22501();
+        t19.pow2k(2).mul(self)
+    }
+
+    pub(crate) fn is_negative(&self) -> u8 {
+        self.to_bytes()[0] & 1
+    }
+
+    pub(crate) fn ct_eq(&self, b: &Fe) -> bool {
+        let (x, y) = (self.to_bytes(), b.to_bytes());
+        x.iter().zip(y.iter()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+    }
+
+    /// Returns b when choice is 1 and self when choice is 0.
+    pub(crate) fn select(&self, b: &Fe, choice: u8) -> Fe {
+        let mask = 0_u64.wrapping_sub(choice as u64);
+        let mut r = self.0;
+        for (x, y) in r.iter_mut().zip(b.0.iter()) {
+            *x ^= mask & (*x ^ y);
+        }
+        Fe(r)
+    }
+
+    pub(crate) fn cswap(a: &mut Fe, b: &mut Fe, choice: u8) {
+        let mask = 0_u64.wrapping_sub(choice as u64);
+        for (x, y) in a.0.iter_mut().zip(b.0.iter_mut()) {
+            let t = mask & (*x ^ *y);
+            *x ^= t;
+            *y ^= t;
+        }
+    }
+
+    pub(crate) fn wipe(&mut self) {
+        unsafe { ptr::write_volatile(&mut self.0, [0_u64; 5]) };
+    }
+}
+
+/// A point on edwards25519 in extended coordinates (X : Y : Z : T) with x = X/Z, y = Y/Z
+/// and xy = T/Z.
+#[derive(Clone, Copy)]
+pub(crate) struct EdwardsPoint {
+    x: Fe,
+    y: Fe,
+    z: Fe,
+    t: Fe,
+}
+
+impl EdwardsPoint {
+    pub(crate) const IDENTITY: EdwardsPoint = EdwardsPoint {
+        x: Fe::ZERO,
+        y: Fe::ONE,
+        z: Fe::ONE,
+        t: Fe::ZERO,
+    };
+
+    pub(crate) const BASE: EdwardsPoint = EdwardsPoint {
+        x: Fe([
+            1738742601995546,
+            1146398526822698,
+            2070867633025821,
+            562264141797630,
+            587772402128613,
+        ]),
+        y: Fe([
+            1801439850948184,
+            1351079888211148,
+            450359962737049,
+            900719925474099,
+            1801439850948198,
+        ]),
+        z: Fe::ONE,
+        t: Fe([
+            1841354044333475,
+            16398895984059,
+            755974180946558,
+            900171276175154,
+            1821297809914039,
+        ]),
+    };
+
+    /// Decodes a point as specified in RFC 8032, section 5.1.3.
+    pub(crate) fn decompress(b: &[u8; 32]) -> Option<EdwardsPoint> {
+        let sign = b[31] >> 7;
+        let y = Fe::from_bytes(b);
+        let mut canonical = *b;
+        canonical[31] &= 0x7f;
+        if y.to_bytes() != canonical {
+            return None;
+        }
+
+        let yy = y.square();
+        let u = yy.sub(&Fe::ONE);
+        let v = yy.mul(&Fe::D).add(&Fe::ONE);
+        let v3 = v.square().mul(&v);
+        let v7 = v3.square().mul(&v);
+        let mut x = u.mul(&v3).mul(&u.mul(&v7).pow_p58());
+
+        let vxx = v.mul(&x.square());
+        if vxx.ct_eq(&u) {
+        } else if vxx.ct_eq(&u.neg()) {
+            x = x.mul(&Fe::SQRT_M1);
+        } else {
+            return None;
+        }
+
+        let x_zero = x.ct_eq(&Fe::ZERO);
+        if x_zero && sign == 1 {
+            return None;
+        }
+        if x.is_negative() != sign {
+            x = x.neg();
+        }
+        Some(EdwardsPoint {
+            x,
+            y,
+            z: Fe::ONE,
+            t: x.mul(&y),
+        })
+    }
+
+    pub(crate) fn compress(&self) -> [u8; 32] {
+        let zinv = self.z.invert();
+        let x = self.x.mul(&zinv);
+        let y = self.y.mul(&zinv);
+        let mut out = y.to_bytes();
+        out[31] |= x.is_negative() << 7;
+        out
+    }
+
+    pub(crate) fn neg(&self) -> EdwardsPoint {
+        EdwardsPoint {
+            x: self.x.neg(),
+            y: self.y,
+            z: self.z,
+            t: self.t.neg(),
+        }
+    }
+
+    pub(crate) fn add(&self, q: &EdwardsPoint) -> EdwardsPoint {
+        let a = self.y.sub(&self.x).mul(&q.y.sub(&q.x));
+        let b = self.y.add(&self.x).mul(&q.y.add(&q.x));
+        let c = self.t.mul(&Fe::D2).mul(&q.t);
+        let zz = self.z.mul(&q.z);
+        let d = zz.add(&zz);
+        let e = b.sub(&a);
+        let f = d.sub(&c);
+        let g = d.add(&c);
+        let h = b.add(&a);
+        EdwardsPoint {
+            x: e.mul(&f),
+            y: g.mul(&h),
+            z: f.mul(&g),
+            t: e.mul(&h),
+        }
+    }
+
+    pub(crate) fn double(&self) -> EdwardsPoint {
+        let a = self.x.square();
+        let b = self.y.square();
+        let zz = self.z.square();
+        let c = zz.add(&zz);
+        let h = a.add(&b);
+        let e = h.sub(&self.x.add(&self.y).square());
+        let g = a.sub(&b);
+        let f = c.add(&g);
+        EdwardsPoint {
+            x: e.mul(&f),
+            y: g.mul(&h),
+            z: f.mul(&g),
+            t: e.mul(&h),
+        }
+    }
+
+    fn select(&self, q: &EdwardsPoint, choice: u8) -> EdwardsPoint {
+        EdwardsPoint {
+            x: self.x.select(&q.x, choice),
+            y: self.y.select(&q.y, choice),
+            z: self.z.select(&q.z, choice),
+            t: self.t.select(&q.t, choice),
+        }
+    }
+
+    /// Multiplies by a little-endian 256 bit scalar with a fixed sequence of operations.
+    pub(crate) fn mul(&self, scalar: &[u8; 32]) -> EdwardsPoint {
+        let mut r = EdwardsPoint::IDENTITY;
+        for i in (0..256).rev() {
+            let bit = (scalar[i >> 3] >> (i & 7)) & 1;
+            r = r.double();
+            let sum = r.add(self);
+            r = r.select(&sum, bit);
+        }
+        r
+    }
+
+    pub(crate) fn mul_by_cofactor(&self) -> EdwardsPoint {
+        self.double().double().double()
+    }
+
+    pub(crate) fn is_identity(&self) -> bool {
+        self.x.ct_eq(&Fe::ZERO) && self.y.ct_eq(&self.z)
+    }
+
+    /// Returns the u coordinate (1 + y) / (1 - y) of the birationally equivalent point
+    /// on Curve25519. The identity maps to 0.
+    pub(crate) fn montgomery_u(&self) -> [u8; 32] {
+        self.z
+            .add(&self.y)
+            .mul(&self.z.sub(&self.y).invert())
+            .to_bytes()
+    }
+
+    /// The Elligator 2 map of RFC 9380 to edwards25519, in the straight-line form of
+    /// appendix G.2.2. The cofactor is not cleared.
+    pub(crate) fn map_to_curve_elligator2(u: &Fe) -> EdwardsPoint {
+        // map_to_curve_elligator2_curve25519, appendix G.2.1
+        let j = Fe::from_u64(486662);
+        let tv1 = u.square();
+        let tv1 = tv1.add(&tv1);
+        let xd = tv1.add(&Fe::ONE);
+        let x1n = j.neg();
+        let tv2 = xd.square();
+        let gxd = tv2.mul(&xd);
+        let gx1 = j.mul(&tv1).mul(&x1n).add(&tv2).mul(&x1n);
+        let tv3 = gxd.square();
+        let tv2 = tv3.square();
+        let tv3 = tv3.mul(&gxd).mul(&gx1);
+        let tv2 = tv2.mul(&tv3);
+        let y11 = tv2.pow_p58().mul(&tv3);
+        let y12 = y11.mul(&Fe::SQRT_M1);
+        let e1 = y11.square().mul(&gxd).ct_eq(&gx1);
+        let y1 = y12.select(&y11, e1 as u8);
+        let x2n = x1n.mul(&tv1);
+        let y21 = y11.mul(u).mul(&Fe::ELL2_C2);
+        let y22 = y21.mul(&Fe::SQRT_M1);
+        let gx2 = gx1.mul(&tv1);
+        let e2 = y21.square().mul(&gxd).ct_eq(&gx2);
+        let y2 = y22.select(&y21, e2 as u8);
+        let e3 = y1.square().mul(&gxd).ct_eq(&gx1);
+        let xmn = x2n.select(&x1n, e3 as u8);
+        let y = y2.select(&y1, e3 as u8);
+        let ymn = y.select(&y.neg(), (e3 as u8) ^ y.is_negative());
+        let (xmd, ymd) = (xd, Fe::ONE);
+
+        // The rational map to edwards25519, appendix G.2.2
+        let xn = xmn.mul(&ymd).mul(&Fe::ELL2_EDWARDS_C1);
+        let xd = xmd.mul(&ymn);
+        let yn = xmn.sub(&xmd);
+        let yd = xmn.add(&xmd);
+        let e = xd.mul(&yd).ct_eq(&Fe::ZERO) as u8;
+        let xn = xn.select(&Fe::ZERO, e);
+        let xd = xd.select(&Fe::ONE, e);
+        let yn = yn.select(&Fe::ONE, e);
+        let yd = yd.select(&Fe::ONE, e);
+        EdwardsPoint {
+            x: xn.mul(&yd),
+            y: yn.mul(&xd),
+            z: xd.mul(&yd),
+            t: xn.mul(&yn),
+        }
+    }
+
+    pub(crate) fn wipe(&mut self) {
+        self.x.wipe();
+        self.y.wipe();
+        self.z.wipe();
+        self.t.wipe();
+    }
+}
+
+// The group order L = 2^252 + 27742317777372353535851937790883648493.
+const L: [u64; 4] = [
+    0x5812_631a_5cf5_d3ed,
+    0x14de_f9de_a2f7_9cd6,
+    0x0000_0000_0000_0000,
+    0x1000_0000_0000_0000,
+];
+
+/// Reduces a little-endian 512 bit number modulo L, one bit at a time in constant time.
+pub(crate) fn scalar_reduce(wide: &[u64; 8]) -> [u8; 32] {
+    let mut acc = [0_u64; 4];
+    for i in (0..512).rev() {
+        let bit = (wide[i >> 6] >> (i & 63)) & 1;
+        // acc < L < 2^253, so shifting in one bit cannot overflow.
+        acc[3] = (acc[3] << 1) | (acc[2] >> 63);
+        acc[2] = (acc[2] << 1) | (acc[1] >> 63);
+        acc[1] = (acc[1] << 1) | (acc[0] >> 63);
+        acc[0] = (acc[0] << 1) | bit;
+
+        let mut diff = [0_u64; 4];
+        let mut borrow = 0_u64;
+        for j in 0..4 {
+            let (d1, b1) = acc[j].overflowing_sub(L[j]);
+            let (d2, b2) = d1.overflowing_sub(borrow);
+            diff[j] = d2;
+            borrow = (b1 | b2) as u64;
+        }
+        // Keep the difference unless it borrowed.
+        let mask = borrow.wrapping_sub(1);
+        for j in 0..4 {
+            acc[j] = (diff[j] & mask) | (acc[j] & !mask);
+        }
+    }
+
+    let mut out = [0_u8; 32];
+    for (chunk, word) in out.chunks_exact_mut(8).zip(acc.iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+/// Reduces a 64 byte little-endian number, such as a SHA-512 digest, modulo L.
+pub(crate) fn scalar_reduce_bytes(b: &[u8; 64]) -> [u8; 32] {
+    let mut wide = [0_u64; 8];
+    for (word, chunk) in wide.iter_mut().zip(b.chunks_exact(8)) {
+        let mut w = [0_u8; 8];
+        w.copy_from_slice(chunk);
+        *word = u64::from_le_bytes(w);
+    }
+    let r = scalar_reduce(&wide);
+    unsafe { ptr::write_volatile(&mut wide, [0_u64; 8]) };
+    r
+}
+
+/// Computes (a * b + c) mod L for little-endian 256 bit inputs with c < L.
+pub(crate) fn scalar_mul_add(a: &[u8; 32], b: &[u8; 32], c: &[u8; 32]) -> [u8; 32] {
+    let load = |s: &[u8; 32]| {
+        let mut l = [0_u64; 4];
+        for (word, chunk) in l.iter_mut().zip(s.chunks_exact(8)) {
+            let mut w = [0_u8; 8];
+            w.copy_from_slice(chunk);
+            *word = u64::from_le_bytes(w);
+        }
+        l
+    };
+    let (a, b, c) = (load(a), load(b), load(c));
+
+    let mut wide = [0_u64; 8];
+    for i in 0..4 {
+        let mut carry = 0_u128;
+        for j in 0..4 {
+            let t = (a[i] as u128) * (b[j] as u128) + wide[i + j] as u128 + carry;
+            wide[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        wide[i + 4] = carry as u64;
+    }
+    let mut carry = 0_u128;
+    for (i, w) in wide.iter_mut().enumerate() {
+        let t = *w as u128 + if i < 4 { c[i] as u128 } else { 0 } + carry;
+        *w = t as u64;
+        carry = t >> 64;
+    }
+
+    let r = scalar_reduce(&wide);
+    unsafe { ptr::write_volatile(&mut wide, [0_u64; 8]) };
+    r
+}
+
+/// Checks that a little-endian 256 bit scalar is below L.
+pub(crate) fn scalar_is_canonical(s: &[u8; 32]) -> bool {
+    let mut borrow = 0_u64;
+    for (j, chunk) in s.chunks_exact(8).enumerate() {
+        let mut w = [0_u8; 8];
+        w.copy_from_slice(chunk);
+        let (d1, b1) = u64::from_le_bytes(w).overflowing_sub(L[j]);
+        let (_, b2) = d1.overflowing_sub(borrow);
+        borrow = (b1 | b2) as u64;
+    }
+    borrow == 1
+}
@@ -0,0 +1,465 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Elliptic curve arithmetic for threshold protocols
+//!
+//! Scalars and points of P-256 and P-384, for MPC and threshold signing protocols
+//! that have to combine secret shares and curve points themselves. Arithmetic on
+//! scalars and scalar multiplication run in constant time; only functions that take
+//! public values, such as party indices, depend on their inputs.
+//!
+//! The multiplicative-to-additive (MtA) share conversion that threshold ECDSA needs
+//! is not implemented here: it is supplied by the caller through the SgxP256Mta and
+//! SgxP384Mta traits, so that any oblivious transfer or homomorphic encryption
+//! backend can be used.
+//!
+use super::modular::*;
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::weierstrass::*;
+use alloc::vec::Vec;
+use core::fmt;
+use sgx_types::*;
+
+fn words_from<const N: usize>(a: &Limbs<N>, out: &mut [u32]) {
+    for (i, w) in out.iter_mut().enumerate() {
+        *w = (a[i / 2] >> ((i % 2) * 32)) as u32;
+    }
+}
+
+macro_rules! impl_curve {
+    (
+        $(#[$scalar_doc:meta])* $scalar:ident,
+        $(#[$point_doc:meta])* $point:ident,
+        $(#[$mta_doc:meta])* $mta:ident,
+        $curve:ident, $n:expr, $len:expr, $wide:expr,
+        $public:ident, $signature:ident, $hash:ident, $combine:ident, $algorithm:ident
+    ) => {
+        $(#[$scalar_doc])*
+        #[derive(Clone)]
+        pub struct $scalar(Limbs<$n>);
+
+        impl $scalar {
+            pub fn zero() -> $scalar {
+                $scalar([0; $n])
+            }
+
+            pub fn one() -> $scalar {
+                $scalar::from_u64(1)
+            }
+
+            pub fn from_u64(value: u64) -> $scalar {
+                let mut k = [0_u64; $n];
+                k[0] = value;
+                $scalar(k)
+            }
+
+            ///
+            /// Reads a big-endian scalar.
+            ///
+            /// # Errors
+            ///
+            /// **SGX_ERROR_INVALID_PARAMETER**
+            ///
+            /// The value is not below the group order.
+            ///
+            pub fn from_be_bytes(bytes: &[u8; $len]) -> SgxResult<$scalar> {
+                let mut k = from_be_bytes::<$n>(bytes);
+                if $curve.n.is_reduced(&k) {
+                    Ok($scalar(k))
+                } else {
+                    wipe(&mut k);
+                    Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+                }
+            }
+
+            /// Reduces a big-endian number of twice the scalar size modulo the group
+            /// order, for example a hash output, with a negligible bias.
+            pub fn from_be_bytes_reduced(bytes: &[u8; $wide]) -> $scalar {
+                let mut hi = from_be_bytes::<$n>(&bytes[..$len]);
+                let mut lo = from_be_bytes::<$n>(&bytes[$len..]);
+                let k = $curve.n.reduce_wide(&lo, &hi);
+                wipe(&mut hi);
+                wipe(&mut lo);
+                $scalar(k)
+            }
+
+            pub fn to_be_bytes(&self) -> [u8; $len] {
+                let mut out = [0_u8; $len];
+                to_be_bytes(&self.0, &mut out);
+                out
+            }
+
+            ///
+            /// Draws a uniformly distributed nonzero scalar.
+            ///
+            /// # Errors
+            ///
+            /// **SGX_ERROR_UNEXPECTED**
+            ///
+            /// The random number generator failed.
+            ///
+            pub fn random() -> SgxResult<$scalar> {
+                $curve.random_scalar().map($scalar)
+            }
+
+            pub fn add(&self, other: &$scalar) -> $scalar {
+                $scalar($curve.n.add(&self.0, &other.0))
+            }
+
+            pub fn sub(&self, other: &$scalar) -> $scalar {
+                $scalar($curve.n.sub(&self.0, &other.0))
+            }
+
+            pub fn neg(&self) -> $scalar {
+                $scalar($curve.n.sub(&[0; $n], &self.0))
+            }
+
+            pub fn mul(&self, other: &$scalar) -> $scalar {
+                // Montgomery multiplication by a Montgomery form factor gives the
+                // product in normal form.
+                let n = &$curve.n;
+                let mut b = n.mont_form(&other.0);
+                let r = n.mul(&self.0, &b);
+                wipe(&mut b);
+                $scalar(r)
+            }
+
+            ///
+            /// Computes the inverse modulo the group order.
+            ///
+            /// # Errors
+            ///
+            /// **SGX_ERROR_INVALID_PARAMETER**
+            ///
+            /// The scalar is zero.
+            ///
+            pub fn invert(&self) -> SgxResult<$scalar> {
+                if self.is_zero() {
+                    return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+                }
+                let n = &$curve.n;
+                let mut a = n.mont_form(&self.0);
+                let mut inv = n.invert(&a);
+                let r = n.normal_form(&inv);
+                wipe(&mut a);
+                wipe(&mut inv);
+                Ok($scalar(r))
+            }
+
+            pub fn is_zero(&self) -> bool {
+                is_zero(&self.0) == 1
+            }
+
+            pub fn ct_eq(&self, other: &$scalar) -> bool {
+                ct_eq(&self.0, &other.0) == 1
+            }
+
+            ///
+            /// Computes the Lagrange coefficient of the party with the given index for
+            /// interpolating a Shamir secret sharing at zero from the parties in indices.
+            ///
+            /// A share multiplied by its coefficient is an additive share of the
+            /// secret. Indices are public, so this does not run in constant time.
+            ///
+            /// # Errors
+            ///
+            /// **SGX_ERROR_INVALID_PARAMETER**
+            ///
+            /// An index is zero or repeated, or index is not one of indices.
+            ///
+            pub fn lagrange_coefficient(index: u64, indices: &[u64]) -> SgxResult<$scalar> {
+                if index == 0 || !indices.contains(&index) {
+                    return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+                }
+                let xi = $scalar::from_u64(index);
+                let mut num = $scalar::one();
+                let mut den = $scalar::one();
+                for (k, &j) in indices.iter().enumerate() {
+                    if j == 0 || indices[..k].contains(&j) {
+                        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+                    }
+                    if j != index {
+                        let xj = $scalar::from_u64(j);
+                        num = num.mul(&xj);
+                        den = den.mul(&xj.sub(&xi));
+                    }
+                }
+                Ok(num.mul(&den.invert()?))
+            }
+
+            ///
+            /// Computes this party's additive share of a * b, where a and b are shared
+            /// additively among the parties and this party holds a and b.
+            ///
+            /// The share is a * b plus the results of cross_terms with every peer.
+            /// Each peer must run share_product with the same set of parties.
+            ///
+            pub fn share_product<M: $mta>(
+                a: &$scalar,
+                b: &$scalar,
+                peers: &mut [M],
+            ) -> SgxResult<$scalar> {
+                let mut share = a.mul(b);
+                for peer in peers.iter_mut() {
+                    share = share.add(&peer.cross_terms(a, b)?);
+                }
+                Ok(share)
+            }
+        }
+
+        impl fmt::Debug for $scalar {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(concat!(stringify!($scalar), "(..)"))
+            }
+        }
+
+        impl Drop for $scalar {
+            fn drop(&mut self) {
+                wipe(&mut self.0);
+            }
+        }
+
+        $(#[$point_doc])*
+        #[derive(Clone, Copy)]
+        pub struct $point(pub(crate) Point<$n>);
+
+        impl $point {
+            pub fn identity() -> $point {
+                $point($curve.identity())
+            }
+
+            pub fn generator() -> $point {
+                $point($curve.generator())
+            }
+
+            /// Multiplies the generator by k.
+            pub fn mul_base(k: &$scalar) -> $point {
+                $point($curve.mul(&$curve.generator(), &k.0))
+            }
+
+            ///
+            /// Reads a point in the compressed or uncompressed encoding of SEC 1.
+            ///
+            /// # Errors
+            ///
+            /// **SGX_ERROR_INVALID_PARAMETER**
+            ///
+            /// The encoding is malformed or the point is not on the curve.
+            ///
+            pub fn from_sec1(bytes: &[u8]) -> SgxResult<$point> {
+                let pt = match (bytes.first(), bytes.len()) {
+                    (Some(4), len) if len == 1 + 2 * $len => $curve.point_from_affine(
+                        &from_be_bytes(&bytes[1..1 + $len]),
+                        &from_be_bytes(&bytes[1 + $len..]),
+                    ),
+                    (Some(&tag @ (2 | 3)), len) if len == 1 + $len => {
+                        $curve.point_from_x(&from_be_bytes(&bytes[1..]), u64::from(tag & 1))
+                    }
+                    _ => None,
+                };
+                pt.map($point).ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+            }
+
+            ///
+            /// Encodes the point as in SEC 1, compressed or uncompressed.
+            ///
+            /// # Errors
+            ///
+            /// **SGX_ERROR_INVALID_PARAMETER**
+            ///
+            /// The point is the identity, which has no encoding.
+            ///
+            pub fn to_sec1(&self, compressed: bool) -> SgxResult<Vec<u8>> {
+                let (x, y) = $curve
+                    .to_affine(&self.0)
+                    .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+                let mut out = alloc::vec![0_u8; if compressed { 1 + $len } else { 1 + 2 * $len }];
+                to_be_bytes(&x, &mut out[1..1 + $len]);
+                if compressed {
+                    out[0] = 2 | (y[0] & 1) as u8;
+                } else {
+                    out[0] = 4;
+                    to_be_bytes(&y, &mut out[1 + $len..]);
+                }
+                Ok(out)
+            }
+
+            ///
+            /// Converts a public key of the SDK, which is little-endian.
+            ///
+            /// # Errors
+            ///
+            /// **SGX_ERROR_INVALID_PARAMETER**
+            ///
+            /// The point is not on the curve.
+            ///
+            pub fn from_public(public: &$public) -> SgxResult<$point> {
+                $curve
+                    .point_from_affine(&from_le_bytes(&public.gx), &from_le_bytes(&public.gy))
+                    .map($point)
+                    .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+            }
+
+            ///
+            /// Converts the point to a public key of the SDK.
+            ///
+            /// # Errors
+            ///
+            /// **SGX_ERROR_INVALID_PARAMETER**
+            ///
+            /// The point is the identity.
+            ///
+            pub fn to_public(&self) -> SgxResult<$public> {
+                let (x, y) = $curve
+                    .to_affine(&self.0)
+                    .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+                let mut public = $public::default();
+                to_le_bytes(&x, &mut public.gx);
+                to_le_bytes(&y, &mut public.gy);
+                Ok(public)
+            }
+
+            pub fn add(&self, other: &$point) -> $point {
+                $point($curve.add(&self.0, &other.0))
+            }
+
+            pub fn sub(&self, other: &$point) -> $point {
+                $point($curve.add(&self.0, &$curve.neg(&other.0)))
+            }
+
+            pub fn double(&self) -> $point {
+                $point($curve.double(&self.0))
+            }
+
+            pub fn neg(&self) -> $point {
+                $point($curve.neg(&self.0))
+            }
+
+            /// Multiplies the point by k with a fixed sequence of operations.
+            pub fn mul(&self, k: &$scalar) -> $point {
+                $point($curve.mul(&self.0, &k.0))
+            }
+
+            pub fn is_identity(&self) -> bool {
+                $curve.is_identity(&self.0) == 1
+            }
+
+            pub fn ct_eq(&self, other: &$point) -> bool {
+                $curve.point_eq(&self.0, &other.0) == 1
+            }
+        }
+
+        impl fmt::Debug for $point {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.to_sec1(true) {
+                    Ok(bytes) => {
+                        write!(f, concat!(stringify!($point), "("))?;
+                        for b in bytes.iter() {
+                            write!(f, "{:02x}", b)?;
+                        }
+                        write!(f, ")")
+                    }
+                    Err(_) => write!(f, concat!(stringify!($point), "(identity)")),
+                }
+            }
+        }
+
+        $(#[$mta_doc])*
+        pub trait $mta {
+            /// Given this party's shares a and b, returns its additive share of
+            /// a * b' + a' * b, where a' and b' are the peer's shares. The peer calls
+            /// cross_terms with its own shares, and the two results add up to the sum.
+            fn cross_terms(&mut self, a: &$scalar, b: &$scalar) -> SgxResult<$scalar>;
+        }
+
+        ///
+        /// Combines the signature shares of a threshold ECDSA signature and checks the
+        /// result against the group's public key.
+        ///
+        /// r_point is the combined nonce point R, whose x coordinate gives r, and
+        /// s_shares are the additive shares of s computed by the parties.
+        ///
+        /// # Errors
+        ///
+        /// **SGX_ERROR_INVALID_SIGNATURE**
+        ///
+        /// The combined signature does not verify, so a party sent a wrong share.
+        ///
+        /// **SGX_ERROR_INVALID_PARAMETER**
+        ///
+        /// r_point is the identity.
+        ///
+        pub fn $combine(
+            r_point: &$point,
+            s_shares: &[$scalar],
+            hash: &$hash,
+            public: &$point,
+        ) -> SgxResult<$signature> {
+            check_algorithm(SgxAlgorithm::$algorithm)?;
+            let n = &$curve.n;
+            let (x, _) = $curve
+                .to_affine(&r_point.0)
+                .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+            let r = n.normal_form(&n.reduce_mont_form(&x));
+            let s = s_shares.iter().fold($scalar::zero(), |s, share| s.add(share));
+            let e = from_be_bytes::<$n>(hash);
+            if !$curve.ecdsa_verify(&public.0, &e, &r, &s.0) {
+                return Err(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE);
+            }
+            let mut signature = $signature::default();
+            words_from(&r, &mut signature.x);
+            words_from(&s.0, &mut signature.y);
+            Ok(signature)
+        }
+    };
+}
+
+impl_curve!(
+    /// A scalar modulo the order of P-256. The value is wiped when it is dropped.
+    SgxP256Scalar,
+    /// A point on P-256.
+    SgxP256Point,
+    /// The MtA share conversion of P-256 scalars with one peer.
+    SgxP256Mta,
+    P256,
+    4,
+    32,
+    64,
+    sgx_ec256_public_t,
+    sgx_ec256_signature_t,
+    sgx_sha256_hash_t,
+    rsgx_ecdsa256_combine_shares,
+    EcdsaP256
+);
+impl_curve!(
+    /// A scalar modulo the order of P-384. The value is wiped when it is dropped.
+    SgxP384Scalar,
+    /// A point on P-384.
+    SgxP384Point,
+    /// The MtA share conversion of P-384 scalars with one peer.
+    SgxP384Mta,
+    P384,
+    6,
+    48,
+    96,
+    sgx_ec384_public_t,
+    sgx_ec384_signature_t,
+    sgx_sha384_hash_t,
+    rsgx_ecdsa384_combine_shares,
+    EcdsaP384
+);
@@ -0,0 +1,348 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! ECDSA over NIST P-384 and deterministic ECDSA nonces
+//!
+//! The SDK cryptography library only supports P-256 and always draws ECDSA nonces from
+//! the RNG. This module adds P-384 key generation, ECDH and ECDSA with SHA-384, and lets
+//! P-256 and P-384 signatures use RFC 6979 deterministic nonces instead. Keys,
+//! coordinates and signatures are little-endian, as with the P-256 types of the SDK.
+//!
+use super::crypto::{rsgx_sha256_slice, rsgx_sha384_slice};
+use super::kdf::{HmacDigest, Sha256, Sha384, MAX_HASH_SIZE};
+use super::modular::*;
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::weierstrass::*;
+use core::ptr;
+use core::slice;
+use sgx_types::marker::ContiguousMemory;
+use sgx_types::*;
+
+///
+/// How the per-signature nonce of ECDSA is chosen.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SgxEcdsaNonce {
+    /// A fresh nonce from the trusted random number generator.
+    Random,
+    /// A nonce derived from the private key and the message hash as specified in RFC 6979.
+    /// Signing the same message twice gives the same signature, and a weak RNG cannot leak
+    /// the private key.
+    Deterministic,
+}
+
+fn slice_as_bytes<T: Copy + ContiguousMemory>(src: &T) -> &[T] {
+    slice::from_ref(src)
+}
+
+fn private_from(r: &[u8]) -> Limbs<6> {
+    from_le_bytes(r)
+}
+
+fn public_point(public: &sgx_ec384_public_t) -> Option<Point<6>> {
+    P384.point_from_affine(&from_le_bytes(&public.gx), &from_le_bytes(&public.gy))
+}
+
+fn words_from<const N: usize>(a: &Limbs<N>, out: &mut [u32]) {
+    for (i, w) in out.iter_mut().enumerate() {
+        *w = (a[i / 2] >> ((i % 2) * 32)) as u32;
+    }
+}
+
+fn limbs_from_words<const N: usize>(words: &[u32]) -> Limbs<N> {
+    let mut r = [0_u64; N];
+    for (i, w) in words.iter().enumerate().take(2 * N) {
+        r[i / 2] |= (*w as u64) << ((i % 2) * 32);
+    }
+    r
+}
+
+fn sign<const N: usize, H: HmacDigest>(
+    curve: &Curve<N>,
+    d: &Limbs<N>,
+    hash: &[u8],
+    nonce: SgxEcdsaNonce,
+) -> SgxResult<(Limbs<N>, Limbs<N>)> {
+    let e = from_be_bytes::<N>(hash);
+    match nonce {
+        SgxEcdsaNonce::Random => curve.ecdsa_sign(d, &e, || curve.random_scalar()),
+        SgxEcdsaNonce::Deterministic => {
+            let len = hash.len();
+            let mut x = [0_u8; MAX_HASH_SIZE];
+            let mut h1 = [0_u8; MAX_HASH_SIZE];
+            to_be_bytes(d, &mut x[..len]);
+            to_be_bytes(
+                &curve.n.normal_form(&curve.n.reduce_mont_form(&e)),
+                &mut h1[..len],
+            );
+            let drbg = Rfc6979::<H>::new(&x[..len], &h1[..len]);
+            unsafe { ptr::write_volatile(&mut x, [0_u8; MAX_HASH_SIZE]) };
+            let mut drbg = drbg?;
+            curve.ecdsa_sign(d, &e, || drbg.next(curve))
+        }
+    }
+}
+
+///
+/// rsgx_ecc384_create_key_pair generates a private/public key pair on the NIST P-384 curve.
+///
+/// # Errors
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// The random number generator failed.
+///
+pub fn rsgx_ecc384_create_key_pair() -> SgxResult<(sgx_ec384_private_t, sgx_ec384_public_t)> {
+    let mut d = P384.random_scalar()?;
+    let mut private = sgx_ec384_private_t::default();
+    to_le_bytes(&d, &mut private.r);
+    wipe(&mut d);
+    let public = rsgx_ecc384_pub_from_priv(&private)?;
+    Ok((private, public))
+}
+
+///
+/// rsgx_ecc384_pub_from_priv computes the public key of a P-384 private key.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The private key is zero or not below the group order.
+///
+pub fn rsgx_ecc384_pub_from_priv(private: &sgx_ec384_private_t) -> SgxResult<sgx_ec384_public_t> {
+    let mut d = private_from(&private.r);
+    if !P384.is_valid_scalar(&d) {
+        wipe(&mut d);
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let mut pt = P384.mul(&P384.generator(), &d);
+    wipe(&mut d);
+    let (x, y) = P384
+        .to_affine(&pt)
+        .ok_or(sgx_status_t::SGX_ERROR_UNEXPECTED)?;
+    pt.wipe();
+
+    let mut public = sgx_ec384_public_t::default();
+    to_le_bytes(&x, &mut public.gx);
+    to_le_bytes(&y, &mut public.gy);
+    Ok(public)
+}
+
+///
+/// rsgx_ecc384_check_point checks whether a public key is a valid point on the P-384 curve.
+///
+pub fn rsgx_ecc384_check_point(public: &sgx_ec384_public_t) -> SgxResult<bool> {
+    Ok(public_point(public).is_some())
+}
+
+///
+/// rsgx_ecc384_compute_shared_dhkey computes the ECDH shared secret, the x coordinate of
+/// the product of the private key and the peer's public key.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The private key is out of range or the public key is not on the curve.
+///
+pub fn rsgx_ecc384_compute_shared_dhkey(
+    private: &sgx_ec384_private_t,
+    public: &sgx_ec384_public_t,
+) -> SgxResult<sgx_ec384_dh_shared_t> {
+    check_algorithm(SgxAlgorithm::EcdhP384)?;
+    let q = public_point(public).ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+    let mut d = private_from(&private.r);
+    if !P384.is_valid_scalar(&d) {
+        wipe(&mut d);
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let mut pt = P384.mul(&q, &d);
+    wipe(&mut d);
+    let result = P384.to_affine(&pt);
+    pt.wipe();
+    let (mut x, _) = result.ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+
+    let mut shared = sgx_ec384_dh_shared_t::default();
+    to_le_bytes(&x, &mut shared.s);
+    wipe(&mut x);
+    Ok(shared)
+}
+
+///
+/// rsgx_ecdsa384_sign_hash signs a SHA-384 hash with a P-384 private key.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The private key is zero or not below the group order.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// The random number generator or an internal cryptography library function failed.
+///
+pub fn rsgx_ecdsa384_sign_hash(
+    hash: &sgx_sha384_hash_t,
+    private: &sgx_ec384_private_t,
+    nonce: SgxEcdsaNonce,
+) -> SgxResult<sgx_ec384_signature_t> {
+    check_algorithm(SgxAlgorithm::EcdsaP384)?;
+    let mut d = private_from(&private.r);
+    let result = sign::<6, Sha384>(&P384, &d, hash, nonce);
+    wipe(&mut d);
+    let (r, s) = result?;
+
+    let mut signature = sgx_ec384_signature_t::default();
+    words_from(&r, &mut signature.x);
+    words_from(&s, &mut signature.y);
+    Ok(signature)
+}
+
+///
+/// rsgx_ecdsa384_sign_msg computes an ECDSA P-384 signature over the SHA-384 hash of data.
+///
+pub fn rsgx_ecdsa384_sign_msg<T>(
+    data: &T,
+    private: &sgx_ec384_private_t,
+    nonce: SgxEcdsaNonce,
+) -> SgxResult<sgx_ec384_signature_t>
+where
+    T: Copy + ContiguousMemory,
+{
+    rsgx_ecdsa384_sign_slice(slice_as_bytes(data), private, nonce)
+}
+
+///
+/// rsgx_ecdsa384_sign_slice computes an ECDSA P-384 signature over the SHA-384 hash of data.
+///
+pub fn rsgx_ecdsa384_sign_slice<T>(
+    data: &[T],
+    private: &sgx_ec384_private_t,
+    nonce: SgxEcdsaNonce,
+) -> SgxResult<sgx_ec384_signature_t>
+where
+    T: Copy + ContiguousMemory,
+{
+    let hash = rsgx_sha384_slice(data)?;
+    rsgx_ecdsa384_sign_hash(&hash, private, nonce)
+}
+
+///
+/// rsgx_ecdsa384_verify_hash verifies an ECDSA P-384 signature of a SHA-384 hash.
+///
+/// # Return value
+///
+/// **true**
+///
+/// The signature is valid.
+///
+/// **false**
+///
+/// The signature or the public key is invalid.
+///
+pub fn rsgx_ecdsa384_verify_hash(
+    hash: &sgx_sha384_hash_t,
+    public: &sgx_ec384_public_t,
+    signature: &sgx_ec384_signature_t,
+) -> SgxResult<bool> {
+    check_algorithm(SgxAlgorithm::EcdsaP384)?;
+    let q = match public_point(public) {
+        Some(q) => q,
+        None => return Ok(false),
+    };
+    let e = from_be_bytes::<6>(hash);
+    let r = limbs_from_words::<6>(&signature.x);
+    let s = limbs_from_words::<6>(&signature.y);
+    Ok(P384.ecdsa_verify(&q, &e, &r, &s))
+}
+
+///
+/// rsgx_ecdsa384_verify_msg verifies an ECDSA P-384 signature over data.
+///
+pub fn rsgx_ecdsa384_verify_msg<T>(
+    data: &T,
+    public: &sgx_ec384_public_t,
+    signature: &sgx_ec384_signature_t,
+) -> SgxResult<bool>
+where
+    T: Copy + ContiguousMemory,
+{
+    rsgx_ecdsa384_verify_slice(slice_as_bytes(data), public, signature)
+}
+
+///
+/// rsgx_ecdsa384_verify_slice verifies an ECDSA P-384 signature over data.
+///
+pub fn rsgx_ecdsa384_verify_slice<T>(
+    data: &[T],
+    public: &sgx_ec384_public_t,
+    signature: &sgx_ec384_signature_t,
+) -> SgxResult<bool>
+where
+    T: Copy + ContiguousMemory,
+{
+    let hash = rsgx_sha384_slice(data)?;
+    rsgx_ecdsa384_verify_hash(&hash, public, signature)
+}
+
+///
+/// rsgx_ecdsa256_sign_deterministic_slice computes an ECDSA P-256 signature over the
+/// SHA-256 hash of data with an RFC 6979 nonce.
+///
+/// The signature verifies with SgxEccHandle::ecdsa_verify_slice like any other.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The private key is zero or not below the group order, or data is empty.
+///
+pub fn rsgx_ecdsa256_sign_deterministic_slice<T>(
+    data: &[T],
+    private: &sgx_ec256_private_t,
+) -> SgxResult<sgx_ec256_signature_t>
+where
+    T: Copy + ContiguousMemory,
+{
+    check_algorithm(SgxAlgorithm::EcdsaP256)?;
+    let hash = rsgx_sha256_slice(data)?;
+    let mut d = from_le_bytes::<4>(&private.r);
+    let result = sign::<4, Sha256>(&P256, &d, &hash, SgxEcdsaNonce::Deterministic);
+    wipe(&mut d);
+    let (r, s) = result?;
+
+    let mut signature = sgx_ec256_signature_t::default();
+    words_from(&r, &mut signature.x);
+    words_from(&s, &mut signature.y);
+    Ok(signature)
+}
+
+///
+/// rsgx_ecdsa256_sign_deterministic_msg computes an ECDSA P-256 signature over the
+/// SHA-256 hash of data with an RFC 6979 nonce.
+///
+pub fn rsgx_ecdsa256_sign_deterministic_msg<T>(
+    data: &T,
+    private: &sgx_ec256_private_t,
+) -> SgxResult<sgx_ec256_signature_t>
+where
+    T: Copy + ContiguousMemory,
+{
+    rsgx_ecdsa256_sign_deterministic_slice(slice_as_bytes(data), private)
+}
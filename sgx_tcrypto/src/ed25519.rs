@@ -0,0 +1,193 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Ed25519 signatures
+//!
+//! Ed25519 as specified in RFC 8032, implemented in Rust because the SDK cryptography
+//! library only covers the NIST P-256 curve. Signing runs in constant time, and the
+//! private key is wiped when it is dropped.
+//!
+use super::curve25519::*;
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::sha512::Sha512;
+use core::fmt;
+use core::ptr;
+use sgx_types::*;
+
+pub type sgx_ed25519_public_t = [uint8_t; 32];
+pub type sgx_ed25519_signature_t = [uint8_t; 64];
+
+///
+/// An Ed25519 private key.
+///
+/// The key is kept as the 32 byte seed together with the expanded scalar and prefix.
+/// Debug output does not show any key material.
+///
+pub struct SgxEd25519PrivateKey {
+    seed: [u8; 32],
+    scalar: [u8; 32],
+    prefix: [u8; 32],
+    public: sgx_ed25519_public_t,
+}
+
+impl SgxEd25519PrivateKey {
+    ///
+    /// generate creates a new private key from the trusted random number generator.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The random number generator failed.
+    ///
+    pub fn generate() -> SgxResult<SgxEd25519PrivateKey> {
+        check_algorithm(SgxAlgorithm::Ed25519)?;
+        let mut seed = [0_u8; 32];
+        let ret = unsafe { sgx_read_rand(seed.as_mut_ptr(), seed.len()) };
+        if ret != sgx_status_t::SGX_SUCCESS {
+            return Err(ret);
+        }
+        let key = SgxEd25519PrivateKey::from_seed(&seed);
+        unsafe { ptr::write_volatile(&mut seed, [0_u8; 32]) };
+        Ok(key)
+    }
+
+    ///
+    /// from_seed expands a 32 byte private key as specified in RFC 8032, section 5.1.5.
+    ///
+    pub fn from_seed(seed: &[u8; 32]) -> SgxEd25519PrivateKey {
+        let mut h = Sha512::digest(&[seed]);
+        let mut scalar = [0_u8; 32];
+        let mut prefix = [0_u8; 32];
+        scalar.copy_from_slice(&h[..32]);
+        prefix.copy_from_slice(&h[32..]);
+        unsafe { ptr::write_volatile(&mut h, [0_u8; 64]) };
+        scalar[0] &= 248;
+        scalar[31] &= 127;
+        scalar[31] |= 64;
+
+        let mut point = EdwardsPoint::BASE.mul(&scalar);
+        let public = point.compress();
+        point.wipe();
+
+        SgxEd25519PrivateKey {
+            seed: *seed,
+            scalar,
+            prefix,
+            public,
+        }
+    }
+
+    ///
+    /// seed returns the 32 byte private key, for example to seal it.
+    ///
+    pub fn seed(&self) -> &[u8; 32] {
+        &self.seed
+    }
+
+    ///
+    /// public_key returns the encoded public key.
+    ///
+    pub fn public_key(&self) -> sgx_ed25519_public_t {
+        self.public
+    }
+
+    // The clamped secret scalar and the nonce prefix of the expanded key.
+    pub(crate) fn expanded(&self) -> (&[u8; 32], &[u8; 32]) {
+        (&self.scalar, &self.prefix)
+    }
+
+    ///
+    /// sign computes the deterministic Ed25519 signature of msg.
+    ///
+    pub fn sign(&self, msg: &[u8]) -> sgx_ed25519_signature_t {
+        let mut h = Sha512::digest(&[&self.prefix, msg]);
+        let mut r = scalar_reduce_bytes(&h);
+        let mut point = EdwardsPoint::BASE.mul(&r);
+        let big_r = point.compress();
+        point.wipe();
+
+        let k = scalar_reduce_bytes(&Sha512::digest(&[&big_r, &self.public, msg]));
+        let s = scalar_mul_add(&k, &self.scalar, &r);
+        unsafe {
+            ptr::write_volatile(&mut h, [0_u8; 64]);
+            ptr::write_volatile(&mut r, [0_u8; 32]);
+        }
+
+        let mut sig = [0_u8; 64];
+        sig[..32].copy_from_slice(&big_r);
+        sig[32..].copy_from_slice(&s);
+        sig
+    }
+}
+
+impl fmt::Debug for SgxEd25519PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxEd25519PrivateKey")
+            .field("public", &self.public)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SgxEd25519PrivateKey {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::write_volatile(&mut self.seed, [0_u8; 32]);
+            ptr::write_volatile(&mut self.scalar, [0_u8; 32]);
+            ptr::write_volatile(&mut self.prefix, [0_u8; 32]);
+        }
+    }
+}
+
+///
+/// rsgx_ed25519_verify checks an Ed25519 signature.
+///
+/// Signatures with a non-canonical S and public keys that do not decode are rejected.
+///
+/// # Return value
+///
+/// **true**
+///
+/// The signature is valid.
+///
+/// **false**
+///
+/// The signature or the public key is invalid.
+///
+pub fn rsgx_ed25519_verify(
+    public: &sgx_ed25519_public_t,
+    msg: &[u8],
+    signature: &sgx_ed25519_signature_t,
+) -> SgxResult<bool> {
+    check_algorithm(SgxAlgorithm::Ed25519)?;
+    let mut big_r = [0_u8; 32];
+    let mut s = [0_u8; 32];
+    big_r.copy_from_slice(&signature[..32]);
+    s.copy_from_slice(&signature[32..]);
+    if !scalar_is_canonical(&s) {
+        return Ok(false);
+    }
+    let a = match EdwardsPoint::decompress(public) {
+        Some(a) => a,
+        None => return Ok(false),
+    };
+
+    let k = scalar_reduce_bytes(&Sha512::digest(&[&big_r, public, msg]));
+    let check = EdwardsPoint::BASE.mul(&s).add(&a.neg().mul(&k));
+    Ok(check.compress() == big_r)
+}
@@ -0,0 +1,262 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Hashing to elliptic curves
+//!
+//! The hash_to_curve and encode_to_curve functions of RFC 9380 for the suites
+//! P256_XMD:SHA-256_SSWU and edwards25519_XMD:SHA-512_ELL2, and the Curve25519 suite
+//! computed through the birational map. The random oracle variants (`_RO_` suites)
+//! are uniformly distributed and fit most protocols; the nonuniform variants (`_NU_`)
+//! are about twice as fast. Both run in constant time in the message.
+//!
+//! Each protocol must use its own domain separation tag, for example
+//! "MYAPP-V01-CS01-with-P256_XMD:SHA-256_SSWU_RO_".
+//!
+use super::curve25519::{EdwardsPoint, Fe};
+use super::ec::SgxP256Point;
+use super::ed25519::sgx_ed25519_public_t;
+use super::kdf::{HmacDigest, Sha256};
+use super::modular::*;
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::sha512::Sha512;
+use super::weierstrass::{Point, P256};
+use super::x25519::sgx_x25519_public_t;
+use sgx_types::*;
+
+// Z = -10 and sqrt(-Z) of P256_XMD:SHA-256_SSWU, RFC 9380, section 8.2.
+const P256_Z: Limbs<4> =
+    limbs_from_hex("ffffffff00000001000000000000000000000000fffffffffffffffffffffff5");
+const P256_SQRT_MINUS_Z: Limbs<4> =
+    limbs_from_hex("da538e3be1d89b99c978fc675180aab27b8d1ff84c55d5b62ccd3427e433c47f");
+
+// L of hash_to_field for both curves: ceil((ceil(log2(p)) + 128) / 8).
+const FIELD_HASH_LEN: usize = 48;
+
+#[derive(Clone, Copy)]
+enum Xmd {
+    Sha256,
+    Sha512,
+}
+
+impl Xmd {
+    fn output_size(self) -> usize {
+        match self {
+            Xmd::Sha256 => 32,
+            Xmd::Sha512 => 64,
+        }
+    }
+
+    fn block_size(self) -> usize {
+        match self {
+            Xmd::Sha256 => 64,
+            Xmd::Sha512 => 128,
+        }
+    }
+
+    fn digest(self, parts: &[&[u8]], out: &mut [u8; 64]) -> SgxError {
+        match self {
+            Xmd::Sha256 => Sha256::digest(parts, &mut out[..32]),
+            Xmd::Sha512 => {
+                *out = Sha512::digest(parts);
+                Ok(())
+            }
+        }
+    }
+}
+
+// expand_message_xmd of RFC 9380, section 5.3.1, over a message given in parts.
+fn expand_message_xmd(hash: Xmd, msg: &[&[u8]], dst: &[u8], out: &mut [u8]) -> SgxError {
+    let b_len = hash.output_size();
+    let ell = (out.len() + b_len - 1) / b_len;
+    if dst.is_empty() || ell > 255 || out.len() > 0xffff {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    // Longer tags are replaced by their hash, section 5.3.3.
+    let mut long_dst = [0_u8; 64];
+    let dst = if dst.len() > 255 {
+        hash.digest(&[b"H2C-OVERSIZE-DST-", dst], &mut long_dst)?;
+        &long_dst[..b_len]
+    } else {
+        dst
+    };
+    let dst_len = [dst.len() as u8];
+
+    let z_pad = [0_u8; 128];
+    let len = (out.len() as u16).to_be_bytes();
+    let mut parts: [&[u8]; 8] = [
+        &z_pad[..hash.block_size()],
+        &[],
+        &[],
+        &[],
+        &[],
+        &[],
+        &[],
+        &[],
+    ];
+    let mut count = 1;
+    for part in msg {
+        if count + 4 > parts.len() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        parts[count] = part;
+        count += 1;
+    }
+    parts[count] = &len;
+    parts[count + 1] = &[0];
+    parts[count + 2] = dst;
+    parts[count + 3] = &dst_len;
+    let mut b0 = [0_u8; 64];
+    hash.digest(&parts[..count + 4], &mut b0)?;
+
+    let mut bi = [0_u8; 64];
+    for (i, chunk) in out.chunks_mut(b_len).enumerate() {
+        let mut input = [0_u8; 64];
+        for (x, (a, b)) in input.iter_mut().zip(b0.iter().zip(bi.iter())) {
+            *x = if i == 0 { *a } else { a ^ b };
+        }
+        let counter = [i as u8 + 1];
+        hash.digest(&[&input[..b_len], &counter, dst, &dst_len], &mut bi)?;
+        chunk.copy_from_slice(&bi[..chunk.len()]);
+    }
+    Ok(())
+}
+
+// Maps the message onto P-256 with count field elements, 1 for encode_to_curve and 2
+// for hash_to_curve. P-256 has cofactor 1.
+pub(crate) fn p256_map(msg: &[&[u8]], dst: &[u8], count: usize) -> SgxResult<Point<4>> {
+    let mut uniform = [0_u8; 2 * FIELD_HASH_LEN];
+    let uniform = &mut uniform[..count * FIELD_HASH_LEN];
+    expand_message_xmd(Xmd::Sha256, msg, dst, uniform)?;
+    let mut pt = P256.identity();
+    for chunk in uniform.chunks_exact(FIELD_HASH_LEN) {
+        let hi = from_be_bytes::<4>(&chunk[..16]);
+        let lo = from_be_bytes::<4>(&chunk[16..]);
+        let u = P256.p.reduce_wide(&lo, &hi);
+        pt = P256.add(
+            &pt,
+            &P256.map_to_curve_sswu(&u, &P256_Z, &P256_SQRT_MINUS_Z),
+        );
+    }
+    Ok(pt)
+}
+
+// As p256_map, for edwards25519 with the cofactor cleared.
+pub(crate) fn edwards25519_map(msg: &[&[u8]], dst: &[u8], count: usize) -> SgxResult<EdwardsPoint> {
+    let mut uniform = [0_u8; 2 * FIELD_HASH_LEN];
+    let uniform = &mut uniform[..count * FIELD_HASH_LEN];
+    expand_message_xmd(Xmd::Sha512, msg, dst, uniform)?;
+    let mut pt = EdwardsPoint::IDENTITY;
+    for chunk in uniform.chunks_exact(FIELD_HASH_LEN) {
+        let mut u = [0_u8; FIELD_HASH_LEN];
+        u.copy_from_slice(chunk);
+        pt = pt.add(&EdwardsPoint::map_to_curve_elligator2(
+            &Fe::from_be_bytes_wide(&u),
+        ));
+    }
+    Ok(pt.mul_by_cofactor())
+}
+
+///
+/// Hashes a message to a point of P-256 with the suite P256_XMD:SHA-256_SSWU_RO_.
+///
+/// # Parameters
+///
+/// **msg**
+///
+/// The message to hash.
+///
+/// **dst**
+///
+/// The domain separation tag of the protocol. Tags longer than 255 bytes are hashed
+/// as required by RFC 9380.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The domain separation tag is empty.
+///
+/// **SGX_ERROR_FEATURE_NOT_SUPPORTED**
+///
+/// Hashing to curves is not allowed by the algorithm policy.
+///
+pub fn rsgx_p256_hash_to_curve(msg: &[u8], dst: &[u8]) -> SgxResult<SgxP256Point> {
+    check_algorithm(SgxAlgorithm::HashToCurve)?;
+    p256_map(&[msg], dst, 2).map(SgxP256Point)
+}
+
+///
+/// Encodes a message as a point of P-256 with the nonuniform suite
+/// P256_XMD:SHA-256_SSWU_NU_.
+///
+/// The errors are those of rsgx_p256_hash_to_curve.
+///
+pub fn rsgx_p256_encode_to_curve(msg: &[u8], dst: &[u8]) -> SgxResult<SgxP256Point> {
+    check_algorithm(SgxAlgorithm::HashToCurve)?;
+    p256_map(&[msg], dst, 1).map(SgxP256Point)
+}
+
+///
+/// Hashes a message to a point of edwards25519 with the suite
+/// edwards25519_XMD:SHA-512_ELL2_RO_, and returns it in the encoding of Ed25519
+/// public keys.
+///
+/// The errors are those of rsgx_p256_hash_to_curve.
+///
+pub fn rsgx_edwards25519_hash_to_curve(msg: &[u8], dst: &[u8]) -> SgxResult<sgx_ed25519_public_t> {
+    check_algorithm(SgxAlgorithm::HashToCurve)?;
+    edwards25519_map(&[msg], dst, 2).map(|pt| pt.compress())
+}
+
+///
+/// Encodes a message as a point of edwards25519 with the nonuniform suite
+/// edwards25519_XMD:SHA-512_ELL2_NU_.
+///
+/// The errors are those of rsgx_p256_hash_to_curve.
+///
+pub fn rsgx_edwards25519_encode_to_curve(
+    msg: &[u8],
+    dst: &[u8],
+) -> SgxResult<sgx_ed25519_public_t> {
+    check_algorithm(SgxAlgorithm::HashToCurve)?;
+    edwards25519_map(&[msg], dst, 1).map(|pt| pt.compress())
+}
+
+///
+/// Hashes a message to a point of Curve25519 with the suite
+/// curve25519_XMD:SHA-512_ELL2_RO_, and returns its u coordinate in the encoding of
+/// X25519 public keys.
+///
+/// The errors are those of rsgx_p256_hash_to_curve.
+///
+pub fn rsgx_curve25519_hash_to_curve(msg: &[u8], dst: &[u8]) -> SgxResult<sgx_x25519_public_t> {
+    check_algorithm(SgxAlgorithm::HashToCurve)?;
+    edwards25519_map(&[msg], dst, 2).map(|pt| pt.montgomery_u())
+}
+
+///
+/// Encodes a message as a point of Curve25519 with the nonuniform suite
+/// curve25519_XMD:SHA-512_ELL2_NU_.
+///
+/// The errors are those of rsgx_p256_hash_to_curve.
+///
+pub fn rsgx_curve25519_encode_to_curve(msg: &[u8], dst: &[u8]) -> SgxResult<sgx_x25519_public_t> {
+    check_algorithm(SgxAlgorithm::HashToCurve)?;
+    edwards25519_map(&[msg], dst, 1).map(|pt| pt.montgomery_u())
+}
@@ -0,0 +1,199 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Incremental and key-committing HMAC
+//!
+//! HMAC-SHA256 and HMAC-SHA384 over messages fed in chunks, with keys of any length.
+//!
+//! A key-committing MAC also covers a fingerprint of its key: the MAC is HMAC(key,
+//! fingerprint(key) || message). The fingerprint can be stored next to the data, so
+//! data protected by one key is recognised as such before it is trusted, even by code
+//! that holds several keys, such as successive versions of an enclave.
+//!
+use super::kdf::{Hmac, HmacDigest, Sha256, Sha384};
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::secret::{rsgx_ct_eq, rsgx_zeroize};
+use sgx_types::*;
+
+pub type sgx_hmac_384bit_tag_t = [uint8_t; SGX_SHA384_HASH_SIZE];
+
+const KEY_FINGERPRINT_LABEL: &[u8] = b"sgx_tcrypto key fingerprint v1";
+
+struct HmacContext<H: HmacDigest> {
+    key: Hmac<H>,
+    inner: H::Handle,
+}
+
+impl<H: HmacDigest> HmacContext<H> {
+    fn new(key: &[u8]) -> SgxResult<HmacContext<H>> {
+        check_algorithm(SgxAlgorithm::Hmac)?;
+        let key = Hmac::<H>::new(key)?;
+        let inner = key.start()?;
+        Ok(HmacContext { key, inner })
+    }
+
+    fn new_committed(key: &[u8]) -> SgxResult<HmacContext<H>> {
+        let mut fingerprint = [0_u8; SGX_SHA384_HASH_SIZE];
+        let context = fingerprint_into::<H>(key, &mut fingerprint[..H::HASH_SIZE])
+            .and_then(|_| HmacContext::new(key))
+            .and_then(|context| {
+                context.update(&fingerprint[..H::HASH_SIZE])?;
+                Ok(context)
+            });
+        rsgx_zeroize(&mut fingerprint);
+        context
+    }
+
+    fn update(&self, data: &[u8]) -> SgxError {
+        H::update(&self.inner, data)
+    }
+
+    fn finalize(&self, out: &mut [u8]) -> SgxError {
+        self.key.finish(&self.inner, out)
+    }
+}
+
+fn fingerprint_into<H: HmacDigest>(key: &[u8], out: &mut [u8]) -> SgxError {
+    check_algorithm(SgxAlgorithm::Hmac)?;
+    H::digest(&[KEY_FINGERPRINT_LABEL, key], out)
+}
+
+macro_rules! impl_hmac {
+    (
+        $(#[$doc:meta])*
+        $name:ident,
+        $hash:ident,
+        $tag:ident,
+        $digest:ident,
+        $size:expr,
+        $fingerprint:ident,
+        $committed:ident,
+        $fingerprint_doc:expr,
+        $committed_doc:expr
+    ) => {
+        $(#[$doc])*
+        pub struct $name(HmacContext<$hash>);
+
+        impl $name {
+            ///
+            /// Starts a MAC with the given key.
+            ///
+            /// # Errors
+            ///
+            /// **SGX_ERROR_OUT_OF_MEMORY**
+            ///
+            /// Not enough memory is available to complete this operation.
+            ///
+            /// **SGX_ERROR_UNEXPECTED**
+            ///
+            /// An internal cryptography library failure occurred.
+            ///
+            pub fn new(key: &[u8]) -> SgxResult<$name> {
+                HmacContext::new(key).map($name)
+            }
+
+            ///
+            /// Starts a key-committing MAC with the given key, one that also covers the
+            /// fingerprint of the key.
+            ///
+            pub fn new_committed(key: &[u8]) -> SgxResult<$name> {
+                HmacContext::new_committed(key).map($name)
+            }
+
+            ///
+            /// Adds the next chunk of the message.
+            ///
+            pub fn update(&mut self, data: &[u8]) -> SgxError {
+                self.0.update(data)
+            }
+
+            ///
+            /// Finishes the MAC and returns the tag.
+            ///
+            pub fn finalize(self) -> SgxResult<$tag> {
+                let mut tag = [0_u8; $size];
+                self.0.finalize(&mut tag)?;
+                Ok(tag)
+            }
+
+            ///
+            /// Finishes the MAC and compares it with tag in constant time.
+            ///
+            /// # Errors
+            ///
+            /// **SGX_ERROR_MAC_MISMATCH**
+            ///
+            /// The tag does not match.
+            ///
+            pub fn verify(self, tag: &$tag) -> SgxError {
+                let mut computed = [0_u8; $size];
+                self.0.finalize(&mut computed)?;
+                let equal = rsgx_ct_eq(&computed, tag);
+                rsgx_zeroize(&mut computed);
+                if equal {
+                    Ok(())
+                } else {
+                    Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+                }
+            }
+        }
+
+        #[doc = $fingerprint_doc]
+        ///
+        /// The fingerprint is a hash of the key under a fixed label, so it reveals
+        /// nothing about the key and can be stored in the clear.
+        ///
+        pub fn $fingerprint(key: &[u8]) -> SgxResult<$digest> {
+            let mut fingerprint = [0_u8; $size];
+            fingerprint_into::<$hash>(key, &mut fingerprint)?;
+            Ok(fingerprint)
+        }
+
+        #[doc = $committed_doc]
+        pub fn $committed(key: &[u8], msg: &[u8]) -> SgxResult<$tag> {
+            let mut hmac = $name::new_committed(key)?;
+            hmac.update(msg)?;
+            hmac.finalize()
+        }
+    };
+}
+
+impl_hmac!(
+    /// An incremental HMAC-SHA256.
+    SgxHmacSha256,
+    Sha256,
+    sgx_hmac_256bit_tag_t,
+    sgx_sha256_hash_t,
+    SGX_SHA256_HASH_SIZE,
+    rsgx_key_fingerprint_sha256,
+    rsgx_hmac_sha256_committed,
+    "rsgx_key_fingerprint_sha256 computes the SHA-256 fingerprint of a MAC key.",
+    "rsgx_hmac_sha256_committed computes the key-committing HMAC-SHA256 of msg."
+);
+impl_hmac!(
+    /// An incremental HMAC-SHA384.
+    SgxHmacSha384,
+    Sha384,
+    sgx_hmac_384bit_tag_t,
+    sgx_sha384_hash_t,
+    SGX_SHA384_HASH_SIZE,
+    rsgx_key_fingerprint_sha384,
+    rsgx_hmac_sha384_committed,
+    "rsgx_key_fingerprint_sha384 computes the SHA-384 fingerprint of a MAC key.",
+    "rsgx_hmac_sha384_committed computes the key-committing HMAC-SHA384 of msg."
+);
@@ -0,0 +1,400 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! HKDF and PBKDF2
+//!
+//! HKDF (RFC 5869) and PBKDF2 (RFC 8018) over HMAC-SHA256 and HMAC-SHA384. The HMAC
+//! is built on the SHA handles of the SDK so that keys of any length can be used.
+//!
+use super::crypto::{SgxSha384Handle, SgxShaHandle};
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::secret::rsgx_zeroize;
+use sgx_types::*;
+
+const SHA256_BLOCK_SIZE: usize = 64;
+const SHA384_BLOCK_SIZE: usize = 128;
+pub(crate) const MAX_HASH_SIZE: usize = SGX_SHA384_HASH_SIZE;
+const MAX_BLOCK_SIZE: usize = SHA384_BLOCK_SIZE;
+
+pub(crate) trait HmacDigest {
+    const HASH_SIZE: usize;
+    const BLOCK_SIZE: usize;
+
+    type Handle;
+
+    fn start() -> SgxResult<Self::Handle>;
+    fn update(handle: &Self::Handle, data: &[u8]) -> SgxError;
+    fn finish(handle: &Self::Handle, out: &mut [u8]) -> SgxError;
+
+    fn digest(parts: &[&[u8]], out: &mut [u8]) -> SgxError {
+        let handle = Self::start()?;
+        for part in parts {
+            Self::update(&handle, part)?;
+        }
+        Self::finish(&handle, out)
+    }
+}
+
+pub(crate) enum Sha256 {}
+pub(crate) enum Sha384 {}
+
+macro_rules! impl_hmac_digest {
+    ($name:ident, $handle:ident, $hash_size:expr, $block_size:expr) => {
+        impl HmacDigest for $name {
+            const HASH_SIZE: usize = $hash_size;
+            const BLOCK_SIZE: usize = $block_size;
+
+            type Handle = $handle;
+
+            fn start() -> SgxResult<$handle> {
+                let handle = $handle::new();
+                handle.init()?;
+                Ok(handle)
+            }
+
+            fn update(handle: &$handle, data: &[u8]) -> SgxError {
+                // The SDK rejects empty updates.
+                if data.is_empty() {
+                    Ok(())
+                } else {
+                    handle.update_slice(data)
+                }
+            }
+
+            fn finish(handle: &$handle, out: &mut [u8]) -> SgxError {
+                let mut hash = handle.get_hash()?;
+                out.copy_from_slice(&hash);
+                rsgx_zeroize(&mut hash);
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_hmac_digest!(
+    Sha256,
+    SgxShaHandle,
+    SGX_SHA256_HASH_SIZE,
+    SHA256_BLOCK_SIZE
+);
+impl_hmac_digest!(
+    Sha384,
+    SgxSha384Handle,
+    SGX_SHA384_HASH_SIZE,
+    SHA384_BLOCK_SIZE
+);
+
+// HMAC with the key already padded to the block size.
+pub(crate) struct Hmac<H: HmacDigest> {
+    ipad: [u8; MAX_BLOCK_SIZE],
+    opad: [u8; MAX_BLOCK_SIZE],
+    _hash: core::marker::PhantomData<H>,
+}
+
+impl<H: HmacDigest> Hmac<H> {
+    pub(crate) fn new(key: &[u8]) -> SgxResult<Self> {
+        let mut block = [0_u8; MAX_BLOCK_SIZE];
+        if key.len() > H::BLOCK_SIZE {
+            H::digest(&[key], &mut block[..H::HASH_SIZE])?;
+        } else {
+            block[..key.len()].copy_from_slice(key);
+        }
+
+        let mut hmac = Hmac {
+            ipad: [0_u8; MAX_BLOCK_SIZE],
+            opad: [0_u8; MAX_BLOCK_SIZE],
+            _hash: core::marker::PhantomData,
+        };
+        for (i, b) in block.iter().enumerate().take(H::BLOCK_SIZE) {
+            hmac.ipad[i] = b ^ 0x36;
+            hmac.opad[i] = b ^ 0x5c;
+        }
+        rsgx_zeroize(&mut block);
+        Ok(hmac)
+    }
+
+    pub(crate) fn mac(&self, parts: &[&[u8]], out: &mut [u8]) -> SgxError {
+        let mut inner = [0_u8; MAX_HASH_SIZE];
+        let mut input: [&[u8]; 6] = [&self.ipad[..H::BLOCK_SIZE], &[], &[], &[], &[], &[]];
+        if parts.len() > input.len() - 1 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        input[1..=parts.len()].copy_from_slice(parts);
+        let result = H::digest(&input[..=parts.len()], &mut inner[..H::HASH_SIZE]).and_then(|_| {
+            H::digest(
+                &[&self.opad[..H::BLOCK_SIZE], &inner[..H::HASH_SIZE]],
+                &mut out[..H::HASH_SIZE],
+            )
+        });
+        rsgx_zeroize(&mut inner);
+        result
+    }
+    // Starts an inner hash that has absorbed the padded key.
+    pub(crate) fn start(&self) -> SgxResult<H::Handle> {
+        let handle = H::start()?;
+        H::update(&handle, &self.ipad[..H::BLOCK_SIZE])?;
+        Ok(handle)
+    }
+
+    // Finishes the inner hash of start and computes the MAC from it.
+    pub(crate) fn finish(&self, inner: &H::Handle, out: &mut [u8]) -> SgxError {
+        let mut hash = [0_u8; MAX_HASH_SIZE];
+        let result = H::finish(inner, &mut hash[..H::HASH_SIZE]).and_then(|_| {
+            H::digest(
+                &[&self.opad[..H::BLOCK_SIZE], &hash[..H::HASH_SIZE]],
+                &mut out[..H::HASH_SIZE],
+            )
+        });
+        rsgx_zeroize(&mut hash);
+        result
+    }
+}
+
+impl<H: HmacDigest> Drop for Hmac<H> {
+    fn drop(&mut self) {
+        rsgx_zeroize(&mut self.ipad);
+        rsgx_zeroize(&mut self.opad);
+    }
+}
+
+fn hkdf_extract<H: HmacDigest>(salt: &[u8], ikm: &[u8], prk: &mut [u8]) -> SgxError {
+    check_algorithm(SgxAlgorithm::Hkdf)?;
+    // An absent salt is the same as a string of HashLen zeros.
+    let zeros = [0_u8; MAX_HASH_SIZE];
+    let salt = if salt.is_empty() {
+        &zeros[..H::HASH_SIZE]
+    } else {
+        salt
+    };
+    Hmac::<H>::new(salt)?.mac(&[ikm], prk)
+}
+
+fn hkdf_expand<H: HmacDigest>(prk: &[u8], info: &[u8], okm: &mut [u8]) -> SgxError {
+    check_algorithm(SgxAlgorithm::Hkdf)?;
+    if prk.len() < H::HASH_SIZE || okm.is_empty() || okm.len() > 255 * H::HASH_SIZE {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let hmac = Hmac::<H>::new(prk)?;
+    let mut t = [0_u8; MAX_HASH_SIZE];
+    let mut t_len = 0;
+    let mut result = Ok(());
+    for (i, chunk) in okm.chunks_mut(H::HASH_SIZE).enumerate() {
+        let counter = [(i + 1) as u8];
+        let prev = t;
+        result = hmac.mac(&[&prev[..t_len], info, &counter], &mut t);
+        if result.is_err() {
+            break;
+        }
+        t_len = H::HASH_SIZE;
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+    rsgx_zeroize(&mut t);
+    if result.is_err() {
+        rsgx_zeroize(okm);
+    }
+    result
+}
+
+fn hkdf<H: HmacDigest>(salt: &[u8], ikm: &[u8], info: &[u8], okm: &mut [u8]) -> SgxError {
+    let mut prk = [0_u8; MAX_HASH_SIZE];
+    let result = hkdf_extract::<H>(salt, ikm, &mut prk[..H::HASH_SIZE])
+        .and_then(|_| hkdf_expand::<H>(&prk[..H::HASH_SIZE], info, okm));
+    rsgx_zeroize(&mut prk);
+    result
+}
+
+fn pbkdf2<H: HmacDigest>(password: &[u8], salt: &[u8], iterations: u32, dk: &mut [u8]) -> SgxError {
+    check_algorithm(SgxAlgorithm::Pbkdf2)?;
+    if iterations == 0
+        || dk.is_empty()
+        || (dk.len() as u64) > (u32::MAX as u64) * (H::HASH_SIZE as u64)
+    {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let hmac = Hmac::<H>::new(password)?;
+    let mut u = [0_u8; MAX_HASH_SIZE];
+    let mut t = [0_u8; MAX_HASH_SIZE];
+    let mut result = Ok(());
+    'blocks: for (i, chunk) in dk.chunks_mut(H::HASH_SIZE).enumerate() {
+        let index = ((i + 1) as u32).to_be_bytes();
+        result = hmac.mac(&[salt, &index], &mut u);
+        if result.is_err() {
+            break;
+        }
+        t = u;
+        for _ in 1..iterations {
+            let prev = u;
+            result = hmac.mac(&[&prev[..H::HASH_SIZE]], &mut u);
+            if result.is_err() {
+                break 'blocks;
+            }
+            t.iter_mut().zip(u.iter()).for_each(|(a, b)| *a ^= b);
+        }
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+    rsgx_zeroize(&mut u);
+    rsgx_zeroize(&mut t);
+    if result.is_err() {
+        rsgx_zeroize(dk);
+    }
+    result
+}
+
+///
+/// rsgx_hkdf_sha256_extract computes the HKDF-Extract pseudorandom key from the input key material.
+///
+/// An empty salt is treated as a salt of 32 zero bytes, as RFC 5869 specifies.
+///
+/// # Errors
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_hkdf_sha256_extract(salt: &[u8], ikm: &[u8]) -> SgxResult<sgx_sha256_hash_t> {
+    let mut prk = sgx_sha256_hash_t::default();
+    hkdf_extract::<Sha256>(salt, ikm, &mut prk).map(|_| prk)
+}
+
+///
+/// rsgx_hkdf_sha256_expand fills okm with HKDF-Expand output for the given context information.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// prk is shorter than 32 bytes, or okm is empty or longer than 255 * 32 bytes.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_hkdf_sha256_expand(prk: &[u8], info: &[u8], okm: &mut [u8]) -> SgxError {
+    hkdf_expand::<Sha256>(prk, info, okm)
+}
+
+///
+/// rsgx_hkdf_sha256 runs HKDF-Extract followed by HKDF-Expand. The intermediate key is wiped.
+///
+pub fn rsgx_hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8], okm: &mut [u8]) -> SgxError {
+    hkdf::<Sha256>(salt, ikm, info, okm)
+}
+
+///
+/// rsgx_hkdf_sha384_extract computes the HKDF-Extract pseudorandom key with HMAC-SHA384.
+///
+/// An empty salt is treated as a salt of 48 zero bytes.
+///
+pub fn rsgx_hkdf_sha384_extract(salt: &[u8], ikm: &[u8]) -> SgxResult<sgx_sha384_hash_t> {
+    let mut prk: sgx_sha384_hash_t = [0_u8; SGX_SHA384_HASH_SIZE];
+    hkdf_extract::<Sha384>(salt, ikm, &mut prk).map(|_| prk)
+}
+
+///
+/// rsgx_hkdf_sha384_expand fills okm with HKDF-Expand output using HMAC-SHA384.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// prk is shorter than 48 bytes, or okm is empty or longer than 255 * 48 bytes.
+///
+pub fn rsgx_hkdf_sha384_expand(prk: &[u8], info: &[u8], okm: &mut [u8]) -> SgxError {
+    hkdf_expand::<Sha384>(prk, info, okm)
+}
+
+///
+/// rsgx_hkdf_sha384 runs HKDF-Extract followed by HKDF-Expand using HMAC-SHA384.
+///
+pub fn rsgx_hkdf_sha384(salt: &[u8], ikm: &[u8], info: &[u8], okm: &mut [u8]) -> SgxError {
+    hkdf::<Sha384>(salt, ikm, info, okm)
+}
+
+///
+/// rsgx_hkdf_sha256_from_dh_shared derives keys from an ECDH shared secret.
+///
+/// The shared secret is used as the input key material exactly as the DH functions return it,
+/// i.e. the little-endian x coordinate. Both sides must use the same salt and info.
+///
+pub fn rsgx_hkdf_sha256_from_dh_shared(
+    shared: &sgx_ec256_dh_shared_t,
+    salt: &[u8],
+    info: &[u8],
+    okm: &mut [u8],
+) -> SgxError {
+    hkdf::<Sha256>(salt, &shared.s, info, okm)
+}
+
+///
+/// rsgx_hkdf_sha256_from_key128 derives keys from a 128 bit key such as a sealing key or
+/// a key exchanged with sgx_tkey_exchange.
+///
+pub fn rsgx_hkdf_sha256_from_key128(
+    key: &sgx_key_128bit_t,
+    salt: &[u8],
+    info: &[u8],
+    okm: &mut [u8],
+) -> SgxError {
+    hkdf::<Sha256>(salt, key, info, okm)
+}
+
+///
+/// rsgx_pbkdf2_hmac_sha256 derives a key from a password with PBKDF2-HMAC-SHA256.
+///
+/// # Parameters
+///
+/// **iterations**
+///
+/// The iteration count. It should be chosen as large as the latency budget allows.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// iterations is zero, or dk is empty.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_pbkdf2_hmac_sha256(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    dk: &mut [u8],
+) -> SgxError {
+    pbkdf2::<Sha256>(password, salt, iterations, dk)
+}
+
+///
+/// rsgx_pbkdf2_hmac_sha384 derives a key from a password with PBKDF2-HMAC-SHA384.
+///
+pub fn rsgx_pbkdf2_hmac_sha384(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    dk: &mut [u8],
+) -> SgxError {
+    pbkdf2::<Sha384>(password, salt, iterations, dk)
+}
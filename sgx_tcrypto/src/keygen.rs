@@ -0,0 +1,224 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Key generation
+//!
+//! Keys, scalars and bounded integers drawn from the trusted random number generator.
+//! Scalars and integers below a bound are drawn by rejection sampling, so every value
+//! in the range is equally likely. Reducing a random number modulo the bound instead
+//! makes small values more likely, which is enough to recover an ECDSA key from a few
+//! signatures when it is done for nonces.
+//!
+use super::curve25519::scalar_is_canonical;
+use super::modular::{to_le_bytes, wipe};
+use super::secret::{rsgx_zeroize, SgxSecret};
+use super::weierstrass::*;
+use core::mem;
+use core::slice;
+use sgx_types::marker::ContiguousMemory;
+use sgx_types::*;
+
+///
+/// The curves whose scalars rsgx_generate_scalar draws.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SgxCurve {
+    /// NIST P-256, as in sgx_ec256_private_t.
+    P256,
+    /// NIST P-384, as in sgx_ec384_private_t.
+    P384,
+    /// The prime-order subgroup of Curve25519 used by Ed25519.
+    Ed25519,
+    /// The SM2 curve of GB/T 32918.
+    #[cfg(feature = "sm")]
+    Sm2,
+}
+
+impl SgxCurve {
+    /// The length of a scalar in bytes.
+    pub fn scalar_size(self) -> usize {
+        match self {
+            SgxCurve::P256 | SgxCurve::Ed25519 => 32,
+            SgxCurve::P384 => 48,
+            #[cfg(feature = "sm")]
+            SgxCurve::Sm2 => 32,
+        }
+    }
+}
+
+///
+/// rsgx_generate_scalar draws a uniformly distributed scalar in [1, n), where n is the
+/// order of the base point of curve.
+///
+/// # Description
+///
+/// The scalar is written little-endian, the byte order of the SDK private key types,
+/// so a P-256 private key is generated with
+/// `rsgx_generate_scalar(SgxCurve::P256, &mut private.r)`.
+///
+/// # Parameters
+///
+/// **curve**
+///
+/// The curve whose group order bounds the scalar.
+///
+/// **scalar**
+///
+/// Receives the scalar. It must be curve.scalar_size() bytes long.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// scalar has another length.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// The random number generator failed.
+///
+pub fn rsgx_generate_scalar(curve: SgxCurve, scalar: &mut [u8]) -> SgxError {
+    if scalar.len() != curve.scalar_size() {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    match curve {
+        SgxCurve::P256 => weierstrass_scalar(&P256, scalar),
+        SgxCurve::P384 => weierstrass_scalar(&P384, scalar),
+        SgxCurve::Ed25519 => ed25519_scalar(scalar),
+        #[cfg(feature = "sm")]
+        SgxCurve::Sm2 => weierstrass_scalar(&SM2, scalar),
+    }
+}
+
+fn weierstrass_scalar<const N: usize>(curve: &Curve<N>, out: &mut [u8]) -> SgxError {
+    let mut k = curve.random_scalar()?;
+    to_le_bytes(&k, out);
+    wipe(&mut k);
+    Ok(())
+}
+
+// L is a little above 2^252, so about half of the 253-bit candidates are accepted.
+fn ed25519_scalar(out: &mut [u8]) -> SgxError {
+    let mut k = [0_u8; 32];
+    let result = loop {
+        if let Err(e) = read_rand(&mut k) {
+            break Err(e);
+        }
+        k[31] &= 0x1f;
+        if scalar_is_canonical(&k) && k.iter().any(|&b| b != 0) {
+            out.copy_from_slice(&k);
+            break Ok(());
+        }
+    };
+    rsgx_zeroize(&mut k);
+    result
+}
+
+///
+/// rsgx_generate_symmetric_key fills key with bytes from the trusted random number
+/// generator.
+///
+/// Every byte string is a valid key for the symmetric algorithms of this crate, so no
+/// rejection is needed. Use rsgx_generate_key for a key of a fixed-size key type.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// key is empty.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// The random number generator failed.
+///
+pub fn rsgx_generate_symmetric_key(key: &mut [u8]) -> SgxError {
+    if key.is_empty() {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    read_rand(key)
+}
+
+///
+/// rsgx_generate_key returns a random key of a plain-data key type such as
+/// sgx_aes_gcm_128bit_key_t, kept in an SgxSecret so that it is wiped on drop.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sgx_tcrypto::rsgx_generate_key;
+/// use sgx_types::sgx_aes_gcm_128bit_key_t;
+///
+/// let key = rsgx_generate_key::<sgx_aes_gcm_128bit_key_t>().unwrap();
+/// assert_eq!(key.expose().len(), 16);
+/// ```
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// T has a size of zero.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// The random number generator failed.
+///
+pub fn rsgx_generate_key<T: Copy + ContiguousMemory + Default>() -> SgxResult<SgxSecret<T>> {
+    let mut key = SgxSecret::new(T::default());
+    let bytes = unsafe {
+        slice::from_raw_parts_mut(key.expose_mut() as *mut T as *mut u8, mem::size_of::<T>())
+    };
+    rsgx_generate_symmetric_key(bytes)?;
+    Ok(key)
+}
+
+///
+/// rsgx_random_below returns a uniformly distributed integer in [0, bound).
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// bound is zero.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// The random number generator failed.
+///
+pub fn rsgx_random_below(bound: u64) -> SgxResult<u64> {
+    if bound == 0 {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    // The 2^64 mod bound smallest values would make x % bound biased, so they are
+    // rejected. That leaves a multiple of bound values.
+    let threshold = bound.wrapping_neg() % bound;
+    loop {
+        let mut buf = [0_u8; 8];
+        read_rand(&mut buf)?;
+        let x = u64::from_le_bytes(buf);
+        if x >= threshold {
+            return Ok(x % bound);
+        }
+    }
+}
+
+fn read_rand(buf: &mut [u8]) -> SgxError {
+    let ret = unsafe { sgx_read_rand(buf.as_mut_ptr(), buf.len()) };
+    match ret {
+        sgx_status_t::SGX_SUCCESS => Ok(()),
+        _ => Err(ret),
+    }
+}
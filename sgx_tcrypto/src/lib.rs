@@ -27,7 +27,110 @@
 #![allow(non_snake_case)]
 #![allow(clippy::too_many_arguments)]
 
+extern crate alloc;
+extern crate sgx_trts;
 extern crate sgx_types;
 
 mod crypto;
 pub use self::crypto::*;
+
+mod secret;
+pub use self::secret::*;
+
+mod policy;
+pub use self::policy::*;
+
+mod aes_gcm;
+pub use self::aes_gcm::*;
+
+mod aes_ctr;
+pub use self::aes_ctr::*;
+
+mod aes;
+
+mod aes_kw;
+pub use self::aes_kw::*;
+
+mod aes_gcm_siv;
+pub use self::aes_gcm_siv::*;
+
+mod chacha20_poly1305;
+pub use self::chacha20_poly1305::*;
+
+mod sha3;
+pub use self::sha3::*;
+
+mod sha256_mb;
+pub use self::sha256_mb::*;
+
+mod kdf;
+pub use self::kdf::*;
+
+mod hmac;
+pub use self::hmac::*;
+
+mod curve25519;
+mod sha512;
+
+mod ed25519;
+pub use self::ed25519::*;
+
+mod x25519;
+pub use self::x25519::*;
+
+mod modular;
+mod weierstrass;
+
+mod bignum;
+pub use self::bignum::*;
+
+mod ecdsa;
+pub use self::ecdsa::*;
+
+mod ec;
+pub use self::ec::*;
+
+mod keygen;
+pub use self::keygen::*;
+
+mod hash_to_curve;
+pub use self::hash_to_curve::*;
+
+mod vrf;
+pub use self::vrf::*;
+
+mod rsa;
+pub use self::rsa::*;
+
+mod x509;
+pub use self::x509::*;
+
+#[cfg(feature = "sm")]
+mod sm2;
+#[cfg(feature = "sm")]
+pub use self::sm2::*;
+#[cfg(feature = "sm")]
+mod sm3;
+#[cfg(feature = "sm")]
+pub use self::sm3::*;
+#[cfg(feature = "sm")]
+mod sm4;
+#[cfg(feature = "sm")]
+pub use self::sm4::*;
+
+#[cfg(feature = "selftest")]
+mod selftest;
+#[cfg(feature = "selftest")]
+pub use self::selftest::*;
+
+#[cfg(feature = "rustcrypto")]
+pub use aead;
+#[cfg(feature = "rustcrypto")]
+pub use digest;
+#[cfg(feature = "rustcrypto")]
+pub use signature;
+
+#[cfg(feature = "rustcrypto")]
+mod rustcrypto;
+#[cfg(feature = "rustcrypto")]
+pub use self::rustcrypto::*;
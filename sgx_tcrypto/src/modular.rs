@@ -0,0 +1,332 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Fixed-size modular arithmetic
+//!
+//! Numbers are arrays of N little-endian 64 bit limbs. Multiplication uses the
+//! Montgomery representation. All operations run in constant time with respect to the
+//! values; only the modulus and exponents passed to pow_vartime may affect timing.
+//!
+use core::ptr;
+
+pub(crate) type Limbs<const N: usize> = [u64; N];
+
+#[derive(Clone, Copy)]
+pub(crate) struct Modulus<const N: usize> {
+    pub(crate) m: Limbs<N>,
+    // -m^-1 mod 2^64
+    n0: u64,
+    // 2^(128N) mod m
+    r2: Limbs<N>,
+}
+
+pub(crate) const fn limbs_from_hex<const N: usize>(s: &str) -> Limbs<N> {
+    let b = s.as_bytes();
+    let mut r = [0_u64; N];
+    let mut i = 0;
+    while i < b.len() {
+        let c = b[b.len() - 1 - i];
+        let v = match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            b'A'..=b'F' => c - b'A' + 10,
+            _ => panic!("invalid hex digit"),
+        } as u64;
+        r[i / 16] |= v << ((i % 16) * 4);
+        i += 1;
+    }
+    r
+}
+
+impl<const N: usize> Modulus<N> {
+    /// Precomputes the Montgomery constants of an odd modulus.
+    pub(crate) const fn new(m: Limbs<N>) -> Modulus<N> {
+        let mut inv = 1_u64;
+        let mut i = 0;
+        while i < 6 {
+            inv = inv.wrapping_mul(2_u64.wrapping_sub(m[0].wrapping_mul(inv)));
+            i += 1;
+        }
+
+        // r2 = 2^(128N) mod m, by doubling 1 one bit at a time.
+        let mut r = [0_u64; N];
+        r[0] = 1;
+        let mut k = 0;
+        while k < 128 * N {
+            let mut carry = 0_u64;
+            let mut j = 0;
+            while j < N {
+                let hi = r[j] >> 63;
+                r[j] = (r[j] << 1) | carry;
+                carry = hi;
+                j += 1;
+            }
+            // subtract m if the doubled value is at least m
+            let mut d = [0_u64; N];
+            let mut borrow = 0_u64;
+            j = 0;
+            while j < N {
+                let (d1, b1) = r[j].overflowing_sub(m[j]);
+                let (d2, b2) = d1.overflowing_sub(borrow);
+                d[j] = d2;
+                borrow = (b1 | b2) as u64;
+                j += 1;
+            }
+            if carry == 1 || borrow == 0 {
+                r = d;
+            }
+            k += 1;
+        }
+
+        Modulus {
+            m,
+            n0: inv.wrapping_neg(),
+            r2: r,
+        }
+    }
+
+    pub(crate) fn bits(&self) -> usize {
+        let mut i = N;
+        while i > 0 && self.m[i - 1] == 0 {
+            i -= 1;
+        }
+        if i == 0 {
+            0
+        } else {
+            64 * i - self.m[i - 1].leading_zeros() as usize
+        }
+    }
+
+    /// Returns a - m if a (with an extra top word hi) is at least m, else a.
+    fn reduce_once(&self, a: &Limbs<N>, hi: u64) -> Limbs<N> {
+        let (d, borrow) = sub_limbs(a, &self.m);
+        // keep the difference when hi is set or nothing was borrowed
+        let keep_a = (borrow & !hi) & 1;
+        select(&d, a, keep_a)
+    }
+
+    pub(crate) fn add(&self, a: &Limbs<N>, b: &Limbs<N>) -> Limbs<N> {
+        let (s, carry) = add_limbs(a, b);
+        self.reduce_once(&s, carry)
+    }
+
+    pub(crate) fn sub(&self, a: &Limbs<N>, b: &Limbs<N>) -> Limbs<N> {
+        let (d, borrow) = sub_limbs(a, b);
+        let mask = 0_u64.wrapping_sub(borrow);
+        let mut m = self.m;
+        for x in m.iter_mut() {
+            *x &= mask;
+        }
+        add_limbs(&d, &m).0
+    }
+
+    /// Montgomery multiplication: a * b / 2^(64N) mod m. Requires a * b < 2^(64N) * m.
+    pub(crate) fn mul(&self, a: &Limbs<N>, b: &Limbs<N>) -> Limbs<N> {
+        let mut t = [0_u64; N];
+        let mut t_hi = 0_u64;
+        for bi in b.iter() {
+            let mut carry = 0_u64;
+            for j in 0..N {
+                let s = t[j] as u128 + (a[j] as u128) * (*bi as u128) + carry as u128;
+                t[j] = s as u64;
+                carry = (s >> 64) as u64;
+            }
+            let (s, c1) = t_hi.overflowing_add(carry);
+            t_hi = s;
+            let t_top = c1 as u64;
+
+            let q = t[0].wrapping_mul(self.n0);
+            let s = t[0] as u128 + (q as u128) * (self.m[0] as u128);
+            let mut carry = (s >> 64) as u64;
+            for j in 1..N {
+                let s = t[j] as u128 + (q as u128) * (self.m[j] as u128) + carry as u128;
+                t[j - 1] = s as u64;
+                carry = (s >> 64) as u64;
+            }
+            let s = t_hi as u128 + carry as u128;
+            t[N - 1] = s as u64;
+            t_hi = t_top + (s >> 64) as u64;
+        }
+        self.reduce_once(&t, t_hi)
+    }
+
+    pub(crate) fn square(&self, a: &Limbs<N>) -> Limbs<N> {
+        self.mul(a, a)
+    }
+
+    pub(crate) fn mont_form(&self, a: &Limbs<N>) -> Limbs<N> {
+        self.mul(a, &self.r2)
+    }
+
+    pub(crate) fn normal_form(&self, a: &Limbs<N>) -> Limbs<N> {
+        let mut one = [0_u64; N];
+        one[0] = 1;
+        self.mul(a, &one)
+    }
+
+    pub(crate) fn one(&self) -> Limbs<N> {
+        let mut one = [0_u64; N];
+        one[0] = 1;
+        self.mont_form(&one)
+    }
+
+    /// Raises a Montgomery form value to a public exponent.
+    pub(crate) fn pow_vartime(&self, a: &Limbs<N>, e: &Limbs<N>) -> Limbs<N> {
+        let mut r = self.one();
+        let top = (0..64 * N).rev().find(|i| (e[i / 64] >> (i % 64)) & 1 == 1);
+        if let Some(top) = top {
+            for i in (0..=top).rev() {
+                r = self.square(&r);
+                if (e[i / 64] >> (i % 64)) & 1 == 1 {
+                    r = self.mul(&r, a);
+                }
+            }
+        }
+        r
+    }
+
+    /// Raises a Montgomery form value to a secret exponent. Every bit of the exponent
+    /// costs one squaring and one multiplication.
+    pub(crate) fn pow_ct(&self, a: &Limbs<N>, e: &Limbs<N>) -> Limbs<N> {
+        let mut r = self.one();
+        for i in (0..64 * N).rev() {
+            r = self.square(&r);
+            let t = self.mul(&r, a);
+            r = select(&r, &t, (e[i / 64] >> (i % 64)) & 1);
+        }
+        r
+    }
+
+    /// Reduces the 128N bit number hi * 2^(64N) + lo modulo m, in normal form.
+    pub(crate) fn reduce_wide(&self, lo: &Limbs<N>, hi: &Limbs<N>) -> Limbs<N> {
+        // mul(x, r2) is x * 2^(64N) mod m, so its normal form is x mod m.
+        let hi = self.mul(hi, &self.r2);
+        let lo = self.normal_form(&self.mul(lo, &self.r2));
+        self.add(&hi, &lo)
+    }
+
+    /// Inverts a Montgomery form value modulo a prime modulus. The inverse of zero is zero.
+    pub(crate) fn invert(&self, a: &Limbs<N>) -> Limbs<N> {
+        let mut two = [0_u64; N];
+        two[0] = 2;
+        let (e, _) = sub_limbs(&self.m, &two);
+        self.pow_vartime(a, &e)
+    }
+
+    /// Reduces a number below 2^(64N) modulo m, returning it in Montgomery form.
+    pub(crate) fn reduce_mont_form(&self, a: &Limbs<N>) -> Limbs<N> {
+        // a * r2 / R = a * R mod m, and a * r2 < R * m holds for any a below R.
+        self.mul(a, &self.r2)
+    }
+
+    /// Checks a < m.
+    pub(crate) fn is_reduced(&self, a: &Limbs<N>) -> bool {
+        sub_limbs(a, &self.m).1 == 1
+    }
+}
+
+pub(crate) fn add_limbs<const N: usize>(a: &Limbs<N>, b: &Limbs<N>) -> (Limbs<N>, u64) {
+    let mut r = [0_u64; N];
+    let mut carry = 0_u64;
+    for i in 0..N {
+        let s = a[i] as u128 + b[i] as u128 + carry as u128;
+        r[i] = s as u64;
+        carry = (s >> 64) as u64;
+    }
+    (r, carry)
+}
+
+pub(crate) fn sub_limbs<const N: usize>(a: &Limbs<N>, b: &Limbs<N>) -> (Limbs<N>, u64) {
+    let mut r = [0_u64; N];
+    let mut borrow = 0_u64;
+    for i in 0..N {
+        let (d1, b1) = a[i].overflowing_sub(b[i]);
+        let (d2, b2) = d1.overflowing_sub(borrow);
+        r[i] = d2;
+        borrow = (b1 | b2) as u64;
+    }
+    (r, borrow)
+}
+
+/// Returns b when choice is 1 and a when choice is 0.
+pub(crate) fn select<const N: usize>(a: &Limbs<N>, b: &Limbs<N>, choice: u64) -> Limbs<N> {
+    let mask = 0_u64.wrapping_sub(choice);
+    let mut r = *a;
+    for (x, y) in r.iter_mut().zip(b.iter()) {
+        *x ^= mask & (*x ^ y);
+    }
+    r
+}
+
+/// Returns 1 when a is zero, in constant time.
+pub(crate) fn is_zero<const N: usize>(a: &Limbs<N>) -> u64 {
+    let acc = a.iter().fold(0, |acc, x| acc | x);
+    1 ^ ((acc | acc.wrapping_neg()) >> 63)
+}
+
+pub(crate) fn ct_eq<const N: usize>(a: &Limbs<N>, b: &Limbs<N>) -> u64 {
+    let mut d = [0_u64; N];
+    for i in 0..N {
+        d[i] = a[i] ^ b[i];
+    }
+    is_zero(&d)
+}
+
+pub(crate) fn from_be_bytes<const N: usize>(b: &[u8]) -> Limbs<N> {
+    let mut r = [0_u64; N];
+    for (i, byte) in b.iter().rev().enumerate().take(8 * N) {
+        r[i / 8] |= (*byte as u64) << ((i % 8) * 8);
+    }
+    r
+}
+
+pub(crate) fn from_le_bytes<const N: usize>(b: &[u8]) -> Limbs<N> {
+    let mut r = [0_u64; N];
+    for (i, byte) in b.iter().enumerate().take(8 * N) {
+        r[i / 8] |= (*byte as u64) << ((i % 8) * 8);
+    }
+    r
+}
+
+/// Writes the low out.len() bytes of a in big-endian order.
+pub(crate) fn to_be_bytes<const N: usize>(a: &Limbs<N>, out: &mut [u8]) {
+    let len = out.len();
+    for (i, byte) in out.iter_mut().enumerate() {
+        let k = len - 1 - i;
+        *byte = if k < 8 * N {
+            (a[k / 8] >> ((k % 8) * 8)) as u8
+        } else {
+            0
+        };
+    }
+}
+
+/// Writes the low out.len() bytes of a in little-endian order.
+pub(crate) fn to_le_bytes<const N: usize>(a: &Limbs<N>, out: &mut [u8]) {
+    for (k, byte) in out.iter_mut().enumerate() {
+        *byte = if k < 8 * N {
+            (a[k / 8] >> ((k % 8) * 8)) as u8
+        } else {
+            0
+        };
+    }
+}
+
+pub(crate) fn wipe<const N: usize>(a: &mut Limbs<N>) {
+    unsafe { ptr::write_volatile(a, [0_u64; N]) };
+}
@@ -0,0 +1,236 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Algorithm policy
+//!
+//! An allowlist of the algorithms this crate may run, for enclaves that must keep to
+//! an approved set, for example on the way to a certification. The policy starts with
+//! every algorithm allowed, or with SgxAlgorithmPolicy::approved() when the crate is
+//! built with the approved_only feature, and can then be narrowed at init time with
+//! rsgx_crypto_restrict_policy. An algorithm that has been disallowed can never be
+//! allowed again.
+//!
+//! The fallible entry points of each algorithm check the policy and fail with
+//! SGX_ERROR_FEATURE_NOT_SUPPORTED when the algorithm is disallowed. Functions that
+//! cannot fail, such as the incremental SHA-3 and SM3 hashes, the SM4 block cipher,
+//! rsgx_x25519 and Ed25519 signing with an existing key, are not checked.
+//!
+//! When a self test fails (see rsgx_crypto_self_test), the crate enters an error state
+//! in which every checked function fails with SGX_ERROR_INVALID_STATE.
+//!
+use core::sync::atomic::{AtomicU32, Ordering};
+use sgx_types::*;
+
+///
+/// The algorithms the policy controls.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SgxAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    /// SHA-3, SHAKE and Keccak-256.
+    Sha3,
+    /// HMAC with any hash function.
+    Hmac,
+    Hkdf,
+    Pbkdf2,
+    AesGcm,
+    AesGcmSiv,
+    AesCtr,
+    AesCmac,
+    /// AES key wrap, with and without padding.
+    AesKw,
+    ChaCha20Poly1305,
+    EcdsaP256,
+    EcdsaP384,
+    EcdhP256,
+    EcdhP384,
+    Ed25519,
+    X25519,
+    /// Hashing to P-256, edwards25519 and Curve25519 (RFC 9380).
+    HashToCurve,
+    /// ECVRF on P-256 and edwards25519 (RFC 9381).
+    Ecvrf,
+    /// RSASSA-PKCS1-v1_5 signatures.
+    RsaPkcs1v15,
+    RsaPss,
+    RsaOaep,
+    Sm2,
+    Sm3,
+    Sm4,
+}
+
+const ALGORITHMS: [SgxAlgorithm; 27] = [
+    SgxAlgorithm::Sha1,
+    SgxAlgorithm::Sha256,
+    SgxAlgorithm::Sha384,
+    SgxAlgorithm::Sha3,
+    SgxAlgorithm::Hmac,
+    SgxAlgorithm::Hkdf,
+    SgxAlgorithm::Pbkdf2,
+    SgxAlgorithm::AesGcm,
+    SgxAlgorithm::AesGcmSiv,
+    SgxAlgorithm::AesCtr,
+    SgxAlgorithm::AesCmac,
+    SgxAlgorithm::AesKw,
+    SgxAlgorithm::ChaCha20Poly1305,
+    SgxAlgorithm::EcdsaP256,
+    SgxAlgorithm::EcdsaP384,
+    SgxAlgorithm::EcdhP256,
+    SgxAlgorithm::EcdhP384,
+    SgxAlgorithm::Ed25519,
+    SgxAlgorithm::X25519,
+    SgxAlgorithm::HashToCurve,
+    SgxAlgorithm::Ecvrf,
+    SgxAlgorithm::RsaPkcs1v15,
+    SgxAlgorithm::RsaPss,
+    SgxAlgorithm::RsaOaep,
+    SgxAlgorithm::Sm2,
+    SgxAlgorithm::Sm3,
+    SgxAlgorithm::Sm4,
+];
+
+impl SgxAlgorithm {
+    const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+///
+/// A set of allowed algorithms.
+///
+/// The constructors are const, so a policy can be fixed at compile time:
+///
+/// ```no_run
+/// use sgx_tcrypto::{rsgx_crypto_restrict_policy, SgxAlgorithm, SgxAlgorithmPolicy};
+///
+/// const POLICY: SgxAlgorithmPolicy = SgxAlgorithmPolicy::approved()
+///     .deny(SgxAlgorithm::RsaPkcs1v15)
+///     .deny(SgxAlgorithm::Pbkdf2);
+///
+/// rsgx_crypto_restrict_policy(POLICY);
+/// ```
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SgxAlgorithmPolicy {
+    allowed: u32,
+}
+
+impl SgxAlgorithmPolicy {
+    pub const fn allow_all() -> SgxAlgorithmPolicy {
+        SgxAlgorithmPolicy {
+            allowed: (1 << ALGORITHMS.len()) - 1,
+        }
+    }
+
+    pub const fn deny_all() -> SgxAlgorithmPolicy {
+        SgxAlgorithmPolicy { allowed: 0 }
+    }
+
+    ///
+    /// The algorithms NIST approves for new applications: SHA-2, SHA-3, HMAC, HKDF,
+    /// PBKDF2, AES in GCM, CTR, CMAC and key wrap modes, ECDSA and ECDH on P-256 and
+    /// P-384, Ed25519 and RSA. This does not mean that this implementation has been
+    /// validated.
+    ///
+    pub const fn approved() -> SgxAlgorithmPolicy {
+        SgxAlgorithmPolicy::allow_all()
+            .deny(SgxAlgorithm::Sha1)
+            .deny(SgxAlgorithm::AesGcmSiv)
+            .deny(SgxAlgorithm::ChaCha20Poly1305)
+            .deny(SgxAlgorithm::X25519)
+            .deny(SgxAlgorithm::HashToCurve)
+            .deny(SgxAlgorithm::Ecvrf)
+            .deny(SgxAlgorithm::Sm2)
+            .deny(SgxAlgorithm::Sm3)
+            .deny(SgxAlgorithm::Sm4)
+    }
+
+    pub const fn allow(self, algorithm: SgxAlgorithm) -> SgxAlgorithmPolicy {
+        SgxAlgorithmPolicy {
+            allowed: self.allowed | algorithm.bit(),
+        }
+    }
+
+    pub const fn deny(self, algorithm: SgxAlgorithm) -> SgxAlgorithmPolicy {
+        SgxAlgorithmPolicy {
+            allowed: self.allowed & !algorithm.bit(),
+        }
+    }
+
+    pub const fn is_allowed(&self, algorithm: SgxAlgorithm) -> bool {
+        self.allowed & algorithm.bit() != 0
+    }
+}
+
+#[cfg(not(feature = "approved_only"))]
+const INITIAL_POLICY: SgxAlgorithmPolicy = SgxAlgorithmPolicy::allow_all();
+#[cfg(feature = "approved_only")]
+const INITIAL_POLICY: SgxAlgorithmPolicy = SgxAlgorithmPolicy::approved();
+
+static ALLOWED: AtomicU32 = AtomicU32::new(INITIAL_POLICY.allowed);
+// One more than the index of the algorithm whose self test failed, or 0.
+static FAILED: AtomicU32 = AtomicU32::new(0);
+
+///
+/// Disallows every algorithm that policy does not allow.
+///
+/// The enclave policy becomes the intersection of the current policy and policy, so
+/// this can only remove algorithms. It is meant to be called once at enclave init,
+/// before any other thread uses this crate.
+///
+pub fn rsgx_crypto_restrict_policy(policy: SgxAlgorithmPolicy) {
+    ALLOWED.fetch_and(policy.allowed, Ordering::SeqCst);
+}
+
+///
+/// Returns the current enclave policy.
+///
+pub fn rsgx_crypto_policy() -> SgxAlgorithmPolicy {
+    SgxAlgorithmPolicy {
+        allowed: ALLOWED.load(Ordering::SeqCst),
+    }
+}
+
+/// Fails unless algorithm is allowed and no self test has failed.
+pub(crate) fn check_algorithm(algorithm: SgxAlgorithm) -> SgxError {
+    if FAILED.load(Ordering::SeqCst) != 0 {
+        Err(sgx_status_t::SGX_ERROR_INVALID_STATE)
+    } else if rsgx_crypto_policy().is_allowed(algorithm) {
+        Ok(())
+    } else {
+        Err(sgx_status_t::SGX_ERROR_FEATURE_NOT_SUPPORTED)
+    }
+}
+
+/// Enters the error state after the self test of algorithm failed. The first failure
+/// is kept.
+#[cfg(feature = "selftest")]
+pub(crate) fn enter_error_state(algorithm: SgxAlgorithm) {
+    let _ = FAILED.compare_exchange(0, algorithm as u32 + 1, Ordering::SeqCst, Ordering::SeqCst);
+}
+
+/// Returns the algorithm whose self test failed, if any.
+#[cfg(feature = "selftest")]
+pub(crate) fn failed_algorithm() -> Option<SgxAlgorithm> {
+    match FAILED.load(Ordering::SeqCst) {
+        0 => None,
+        i => Some(ALGORITHMS[i as usize - 1]),
+    }
+}
@@ -0,0 +1,720 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! RSA-OAEP and RSA-PSS
+//!
+//! OAEP encryption (RFC 8017, section 7.1) and PSS signatures (RFC 8017, section 8.1)
+//! for 2048, 3072 and 4096 bit keys, with SHA-1 (OAEP only), SHA-256 or SHA-384 for
+//! both the hash and MGF1. The RSA operations are done in Rust: private key operations
+//! use the CRT with constant-time exponentiation and are checked against the public key
+//! before the result is released.
+//!
+//! Key components are little-endian byte strings, in the same layout as
+//! rsgx_create_rsa_key_pair produces them. Ciphertexts and signatures are big-endian
+//! octet strings as in RFC 8017, so they interoperate with other implementations.
+//!
+use super::crypto::rsgx_create_rsa_key_pair;
+use super::crypto::{SgxSha1Handle, SgxSha384Handle, SgxShaHandle};
+use super::modular::*;
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::secret::rsgx_zeroize;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use sgx_types::*;
+
+///
+/// The hash function used by OAEP or PSS, and by MGF1.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SgxRsaHash {
+    /// SHA-1. Only accepted for OAEP, for compatibility with older peers.
+    Sha1,
+    Sha256,
+    Sha384,
+}
+
+impl SgxRsaHash {
+    pub fn output_size(self) -> usize {
+        match self {
+            SgxRsaHash::Sha1 => SGX_SHA1_HASH_SIZE,
+            SgxRsaHash::Sha256 => SGX_SHA256_HASH_SIZE,
+            SgxRsaHash::Sha384 => SGX_SHA384_HASH_SIZE,
+        }
+    }
+}
+
+const MAX_HASH: usize = SGX_SHA384_HASH_SIZE;
+
+enum HashState {
+    Sha1(SgxSha1Handle),
+    Sha256(SgxShaHandle),
+    Sha384(SgxSha384Handle),
+}
+
+impl HashState {
+    fn new(hash: SgxRsaHash) -> SgxResult<HashState> {
+        let state = match hash {
+            SgxRsaHash::Sha1 => HashState::Sha1(SgxSha1Handle::new()),
+            SgxRsaHash::Sha256 => HashState::Sha256(SgxShaHandle::new()),
+            SgxRsaHash::Sha384 => HashState::Sha384(SgxSha384Handle::new()),
+        };
+        match state {
+            HashState::Sha1(ref h) => h.init()?,
+            HashState::Sha256(ref h) => h.init()?,
+            HashState::Sha384(ref h) => h.init()?,
+        }
+        Ok(state)
+    }
+
+    fn update(&self, data: &[u8]) -> SgxError {
+        // The SDK rejects empty updates.
+        if data.is_empty() {
+            return Ok(());
+        }
+        match self {
+            HashState::Sha1(h) => h.update_slice(data),
+            HashState::Sha256(h) => h.update_slice(data),
+            HashState::Sha384(h) => h.update_slice(data),
+        }
+    }
+
+    fn finish(&self, out: &mut [u8]) -> SgxError {
+        match self {
+            HashState::Sha1(h) => out.copy_from_slice(&h.get_hash()?),
+            HashState::Sha256(h) => out.copy_from_slice(&h.get_hash()?),
+            HashState::Sha384(h) => out.copy_from_slice(&h.get_hash()?),
+        }
+        Ok(())
+    }
+}
+
+fn digest(hash: SgxRsaHash, parts: &[&[u8]], out: &mut [u8]) -> SgxError {
+    let state = HashState::new(hash)?;
+    for part in parts {
+        state.update(part)?;
+    }
+    state.finish(&mut out[..hash.output_size()])
+}
+
+// out ^= MGF1(seed, out.len())
+fn mgf1_xor(hash: SgxRsaHash, seed: &[u8], out: &mut [u8]) -> SgxError {
+    let hlen = hash.output_size();
+    let mut mask = [0_u8; MAX_HASH];
+    for (counter, chunk) in out.chunks_mut(hlen).enumerate() {
+        digest(hash, &[seed, &(counter as u32).to_be_bytes()], &mut mask)?;
+        chunk.iter_mut().zip(mask.iter()).for_each(|(a, b)| *a ^= b);
+    }
+    Ok(())
+}
+
+fn read_rand(buf: &mut [u8]) -> SgxError {
+    let ret = unsafe { sgx_read_rand(buf.as_mut_ptr(), buf.len()) };
+    match ret {
+        sgx_status_t::SGX_SUCCESS => Ok(()),
+        _ => Err(ret),
+    }
+}
+
+fn check_modulus_size(n: &[u8]) -> SgxError {
+    match n.len() {
+        256 | 384 | 512 => Ok(()),
+        _ => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+    }
+}
+
+fn modulus_bits(n: &[u8]) -> usize {
+    match n.iter().rposition(|b| *b != 0) {
+        Some(i) => 8 * i + 8 - n[i].leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+fn public_op<const N: usize>(n: &[u8], e: &[u8], input: &[u8], out: &mut [u8]) -> SgxError {
+    let m = Modulus::<N>::new(from_le_bytes(n));
+    let x = from_be_bytes::<N>(input);
+    if !m.is_reduced(&x) {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let y = m.normal_form(&m.pow_vartime(&m.mont_form(&x), &from_le_bytes(e)));
+    to_be_bytes(&y, out);
+    Ok(())
+}
+
+fn rsa_public(n: &[u8], e: &[u8], input: &[u8], out: &mut [u8]) -> SgxError {
+    match n.len() {
+        256 => public_op::<32>(n, e, input, out),
+        384 => public_op::<48>(n, e, input, out),
+        512 => public_op::<64>(n, e, input, out),
+        _ => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+    }
+}
+
+fn split<const N: usize, const M: usize>(a: &Limbs<N>) -> (Limbs<M>, Limbs<M>) {
+    let mut lo = [0_u64; M];
+    let mut hi = [0_u64; M];
+    lo.copy_from_slice(&a[..M]);
+    hi.copy_from_slice(&a[M..]);
+    (lo, hi)
+}
+
+fn private_op<const N: usize, const M: usize>(
+    key: &SgxRsaPrivateKey,
+    input: &[u8],
+    out: &mut [u8],
+) -> SgxError {
+    let n = Modulus::<N>::new(from_le_bytes(&key.n));
+    let c = from_be_bytes::<N>(input);
+    if !n.is_reduced(&c) {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let p = Modulus::<M>::new(from_le_bytes(&key.p));
+    let q = Modulus::<M>::new(from_le_bytes(&key.q));
+    let mut dp = from_le_bytes::<M>(&key.dmp1);
+    let mut dq = from_le_bytes::<M>(&key.dmq1);
+    let mut qinv = from_le_bytes::<M>(&key.iqmp);
+
+    let (c_lo, c_hi) = split::<N, M>(&c);
+    let mut m1 = p.normal_form(&p.pow_ct(&p.mont_form(&p.reduce_wide(&c_lo, &c_hi)), &dp));
+    let mut m2 = q.normal_form(&q.pow_ct(&q.mont_form(&q.reduce_wide(&c_lo, &c_hi)), &dq));
+    // h = qinv * (m1 - m2) mod p, m = m2 + h * q
+    let mut h = p.mul(
+        &p.mont_form(&qinv),
+        &p.sub(&m1, &p.reduce_wide(&m2, &[0_u64; M])),
+    );
+    let mut m = [0_u64; N];
+    for i in 0..M {
+        let mut carry = 0_u128;
+        for j in 0..M {
+            let t = (h[i] as u128) * (q.m[j] as u128) + m[i + j] as u128 + carry;
+            m[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        m[i + M] = carry as u64;
+    }
+    let mut carry = 0_u128;
+    for (i, x) in m.iter_mut().enumerate() {
+        let t = *x as u128 + if i < M { m2[i] as u128 } else { 0 } + carry;
+        *x = t as u64;
+        carry = t >> 64;
+    }
+
+    // Do not release a result corrupted by a fault: check m^e = c.
+    let check = n.normal_form(&n.pow_vartime(&n.mont_form(&m), &from_le_bytes(&key.e)));
+    let ok = ct_eq(&check, &c) == 1;
+    if ok {
+        to_be_bytes(&m, out);
+    }
+
+    wipe(&mut dp);
+    wipe(&mut dq);
+    wipe(&mut qinv);
+    wipe(&mut m1);
+    wipe(&mut m2);
+    wipe(&mut h);
+    wipe(&mut m);
+    if ok {
+        Ok(())
+    } else {
+        Err(sgx_status_t::SGX_ERROR_UNEXPECTED)
+    }
+}
+
+///
+/// An RSA public key for OAEP encryption and PSS verification.
+///
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SgxRsaPublicKey {
+    n: Vec<u8>,
+    e: Vec<u8>,
+}
+
+impl SgxRsaPublicKey {
+    ///
+    /// new creates a public key from the little-endian modulus and public exponent.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The modulus is not 256, 384 or 512 bytes long, or the exponent is empty or longer
+    /// than the modulus.
+    ///
+    pub fn new(n: &[u8], e: &[u8]) -> SgxResult<SgxRsaPublicKey> {
+        check_modulus_size(n)?;
+        if e.is_empty() || e.len() > n.len() || n[0] & 1 == 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(SgxRsaPublicKey {
+            n: n.to_vec(),
+            e: e.to_vec(),
+        })
+    }
+
+    pub fn n(&self) -> &[u8] {
+        &self.n
+    }
+
+    pub fn e(&self) -> &[u8] {
+        &self.e
+    }
+
+    ///
+    /// size returns the modulus length in bytes, which is also the length of ciphertexts
+    /// and signatures.
+    ///
+    pub fn size(&self) -> usize {
+        self.n.len()
+    }
+
+    ///
+    /// oaep_encrypt encrypts msg with RSAES-OAEP.
+    ///
+    /// # Parameters
+    ///
+    /// **label**
+    ///
+    /// The optional OAEP label. Most peers use an empty label.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// msg is longer than size() - 2 * hash.output_size() - 2 bytes.
+    ///
+    pub fn oaep_encrypt(&self, hash: SgxRsaHash, label: &[u8], msg: &[u8]) -> SgxResult<Vec<u8>> {
+        check_algorithm(SgxAlgorithm::RsaOaep)?;
+        let k = self.size();
+        let hlen = hash.output_size();
+        if msg.len() + 2 * hlen + 2 > k {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let mut em = vec![0_u8; k];
+        {
+            let (seed, db) = em[1..].split_at_mut(hlen);
+            digest(hash, &[label], db)?;
+            db[k - hlen - 2 - msg.len()] = 0x01;
+            db[k - hlen - 1 - msg.len()..].copy_from_slice(msg);
+            read_rand(seed)?;
+            mgf1_xor(hash, seed, db)?;
+            mgf1_xor(hash, db, seed)?;
+        }
+
+        let mut out = vec![0_u8; k];
+        let result = rsa_public(&self.n, &self.e, &em, &mut out);
+        rsgx_zeroize(&mut em);
+        result.map(|_| out)
+    }
+
+    ///
+    /// pss_verify checks an RSASSA-PSS signature over msg. The salt length must equal
+    /// the hash length, which is what pss_sign and most other implementations use.
+    ///
+    pub fn pss_verify(&self, hash: SgxRsaHash, msg: &[u8], signature: &[u8]) -> SgxResult<bool> {
+        let verifier = self.pss_verifier(hash)?;
+        verifier.update(msg)?;
+        verifier.verify(signature)
+    }
+
+    ///
+    /// pss_verify_digest checks an RSASSA-PSS signature over a precomputed message hash.
+    ///
+    pub fn pss_verify_digest(
+        &self,
+        hash: SgxRsaHash,
+        digest_in: &[u8],
+        signature: &[u8],
+    ) -> SgxResult<bool> {
+        check_algorithm(SgxAlgorithm::RsaPss)?;
+        let hlen = hash.output_size();
+        if hash == SgxRsaHash::Sha1 || digest_in.len() != hlen {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let k = self.size();
+        if signature.len() != k {
+            return Ok(false);
+        }
+        let mut m = vec![0_u8; k];
+        if rsa_public(&self.n, &self.e, signature, &mut m).is_err() {
+            return Ok(false);
+        }
+
+        // emLen = ceil((modBits - 1) / 8); a leading byte of m outside EM must be zero.
+        let em_bits = modulus_bits(&self.n) - 1;
+        let em_len = (em_bits + 7) / 8;
+        if m[..k - em_len].iter().any(|b| *b != 0) {
+            return Ok(false);
+        }
+        let em = &mut m[k - em_len..];
+        if em_len < 2 * hlen + 2 || em[em_len - 1] != 0xbc {
+            return Ok(false);
+        }
+        let top_mask = 0xff_u8 >> (8 * em_len - em_bits);
+        if em[0] & !top_mask != 0 {
+            return Ok(false);
+        }
+        let (db, h) = em[..em_len - 1].split_at_mut(em_len - hlen - 1);
+        mgf1_xor(hash, h, db)?;
+        db[0] &= top_mask;
+        let ps_len = em_len - 2 * hlen - 2;
+        if db[..ps_len].iter().any(|b| *b != 0) || db[ps_len] != 0x01 {
+            return Ok(false);
+        }
+
+        let mut h2 = [0_u8; MAX_HASH];
+        digest(hash, &[&[0_u8; 8], digest_in, &db[ps_len + 1..]], &mut h2)?;
+        Ok(h2[..hlen] == *h)
+    }
+
+    ///
+    /// pss_verifier starts a PSS verification whose message is passed in pieces.
+    ///
+    pub fn pss_verifier(&self, hash: SgxRsaHash) -> SgxResult<SgxRsaPssVerifier<'_>> {
+        check_algorithm(SgxAlgorithm::RsaPss)?;
+        if hash == SgxRsaHash::Sha1 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(SgxRsaPssVerifier {
+            key: self,
+            hash,
+            state: HashState::new(hash)?,
+        })
+    }
+}
+
+///
+/// An RSA private key in CRT form for OAEP decryption and PSS signing.
+///
+/// Debug output does not show the private components, which are wiped on drop.
+///
+#[derive(Clone)]
+pub struct SgxRsaPrivateKey {
+    n: Vec<u8>,
+    e: Vec<u8>,
+    p: Vec<u8>,
+    q: Vec<u8>,
+    dmp1: Vec<u8>,
+    dmq1: Vec<u8>,
+    iqmp: Vec<u8>,
+}
+
+impl SgxRsaPrivateKey {
+    ///
+    /// new creates a private key from little-endian components. p, q, dmp1, dmq1 and
+    /// iqmp must each be half as long as n.
+    ///
+    #[allow(clippy::many_single_char_names)]
+    pub fn new(
+        n: &[u8],
+        e: &[u8],
+        p: &[u8],
+        q: &[u8],
+        dmp1: &[u8],
+        dmq1: &[u8],
+        iqmp: &[u8],
+    ) -> SgxResult<SgxRsaPrivateKey> {
+        SgxRsaPublicKey::new(n, e)?;
+        let half = n.len() / 2;
+        if [p, q, dmp1, dmq1, iqmp].iter().any(|c| c.len() != half)
+            || p[0] & 1 == 0
+            || q[0] & 1 == 0
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(SgxRsaPrivateKey {
+            n: n.to_vec(),
+            e: e.to_vec(),
+            p: p.to_vec(),
+            q: q.to_vec(),
+            dmp1: dmp1.to_vec(),
+            dmq1: dmq1.to_vec(),
+            iqmp: iqmp.to_vec(),
+        })
+    }
+
+    ///
+    /// generate creates a new key pair with the SDK key generator.
+    ///
+    /// # Parameters
+    ///
+    /// **mod_size**
+    ///
+    /// The modulus length in bytes: 256, 384 or 512.
+    ///
+    /// **e**
+    ///
+    /// The public exponent, usually 65537.
+    ///
+    pub fn generate(mod_size: usize, e: u32) -> SgxResult<SgxRsaPrivateKey> {
+        if !matches!(mod_size, 256 | 384 | 512) {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let half = mod_size / 2;
+        let mut n = vec![0_u8; mod_size];
+        let mut d = vec![0_u8; mod_size];
+        let mut e = e.to_le_bytes();
+        let mut key = SgxRsaPrivateKey {
+            n: Vec::new(),
+            e: Vec::new(),
+            p: vec![0_u8; half],
+            q: vec![0_u8; half],
+            dmp1: vec![0_u8; half],
+            dmq1: vec![0_u8; half],
+            iqmp: vec![0_u8; half],
+        };
+        let result = rsgx_create_rsa_key_pair(
+            mod_size as i32,
+            e.len() as i32,
+            &mut n,
+            &mut d,
+            &mut e,
+            &mut key.p,
+            &mut key.q,
+            &mut key.dmp1,
+            &mut key.dmq1,
+            &mut key.iqmp,
+        );
+        rsgx_zeroize(&mut d);
+        result?;
+        key.n = n;
+        key.e = e.to_vec();
+        Ok(key)
+    }
+
+    pub fn public_key(&self) -> SgxRsaPublicKey {
+        SgxRsaPublicKey {
+            n: self.n.clone(),
+            e: self.e.clone(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.n.len()
+    }
+
+    fn private_op(&self, input: &[u8], out: &mut [u8]) -> SgxError {
+        match self.n.len() {
+            256 => private_op::<32, 16>(self, input, out),
+            384 => private_op::<48, 24>(self, input, out),
+            512 => private_op::<64, 32>(self, input, out),
+            _ => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+    }
+
+    ///
+    /// oaep_decrypt decrypts an RSAES-OAEP ciphertext.
+    ///
+    /// The padding is checked in constant time and every kind of malformed ciphertext
+    /// gives the same error.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The ciphertext is not a valid OAEP encryption for this key, hash and label.
+    ///
+    pub fn oaep_decrypt(
+        &self,
+        hash: SgxRsaHash,
+        label: &[u8],
+        ciphertext: &[u8],
+    ) -> SgxResult<Vec<u8>> {
+        check_algorithm(SgxAlgorithm::RsaOaep)?;
+        let k = self.size();
+        let hlen = hash.output_size();
+        if ciphertext.len() != k || k < 2 * hlen + 2 {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        let mut em = vec![0_u8; k];
+        self.private_op(ciphertext, &mut em)
+            .map_err(|_| sgx_status_t::SGX_ERROR_MAC_MISMATCH)?;
+
+        let mut lhash = [0_u8; MAX_HASH];
+        digest(hash, &[label], &mut lhash)?;
+        let (y, rest) = em.split_at_mut(1);
+        let (seed, db) = rest.split_at_mut(hlen);
+        mgf1_xor(hash, db, seed)?;
+        mgf1_xor(hash, seed, db)?;
+
+        // bad collects every failure; index is the position of the 0x01 separator.
+        let mut bad = y[0];
+        bad |= db[..hlen]
+            .iter()
+            .zip(lhash.iter())
+            .fold(0, |acc, (a, b)| acc | (a ^ b));
+        let mut found = 0_u8;
+        let mut index = 0_usize;
+        for (i, b) in db[hlen..].iter().enumerate() {
+            let is_zero = ct_byte_is_zero(*b);
+            let is_one = ct_byte_is_zero(*b ^ 1);
+            let first_one = is_one & !found & 1;
+            index |= (0_usize.wrapping_sub(first_one as usize)) & i;
+            found |= first_one;
+            // before the separator only zeros are allowed
+            bad |= !found & !is_zero & 1;
+        }
+        bad |= !found & 1;
+
+        let result = if bad == 0 {
+            Ok(db[hlen + index + 1..].to_vec())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+        };
+        rsgx_zeroize(&mut em);
+        result
+    }
+
+    ///
+    /// pss_sign computes an RSASSA-PSS signature over msg with a random salt as long as
+    /// the hash output.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// hash is SgxRsaHash::Sha1.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The random number generator failed, or the private key is inconsistent.
+    ///
+    pub fn pss_sign(&self, hash: SgxRsaHash, msg: &[u8]) -> SgxResult<Vec<u8>> {
+        let signer = self.pss_signer(hash)?;
+        signer.update(msg)?;
+        signer.sign()
+    }
+
+    ///
+    /// pss_sign_digest computes an RSASSA-PSS signature over a precomputed message hash.
+    ///
+    pub fn pss_sign_digest(&self, hash: SgxRsaHash, digest_in: &[u8]) -> SgxResult<Vec<u8>> {
+        check_algorithm(SgxAlgorithm::RsaPss)?;
+        let hlen = hash.output_size();
+        if hash == SgxRsaHash::Sha1 || digest_in.len() != hlen {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let k = self.size();
+        let em_bits = modulus_bits(&self.n) - 1;
+        let em_len = (em_bits + 7) / 8;
+
+        let mut salt = [0_u8; MAX_HASH];
+        read_rand(&mut salt[..hlen])?;
+        let mut m = vec![0_u8; k];
+        {
+            let em = &mut m[k - em_len..];
+            let (db, rest) = em.split_at_mut(em_len - hlen - 1);
+            let h = &mut rest[..hlen];
+            digest(hash, &[&[0_u8; 8], digest_in, &salt[..hlen]], h)?;
+            let ps_len = em_len - 2 * hlen - 2;
+            db[ps_len] = 0x01;
+            db[ps_len + 1..].copy_from_slice(&salt[..hlen]);
+            mgf1_xor(hash, h, db)?;
+            db[0] &= 0xff_u8 >> (8 * em_len - em_bits);
+            rest[hlen] = 0xbc;
+        }
+
+        let mut signature = vec![0_u8; k];
+        let result = self.private_op(&m, &mut signature);
+        rsgx_zeroize(&mut m);
+        rsgx_zeroize(&mut salt);
+        result.map(|_| signature)
+    }
+
+    ///
+    /// pss_signer starts a PSS signature whose message is passed in pieces.
+    ///
+    pub fn pss_signer(&self, hash: SgxRsaHash) -> SgxResult<SgxRsaPssSigner<'_>> {
+        check_algorithm(SgxAlgorithm::RsaPss)?;
+        if hash == SgxRsaHash::Sha1 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(SgxRsaPssSigner {
+            key: self,
+            hash,
+            state: HashState::new(hash)?,
+        })
+    }
+}
+
+fn ct_byte_is_zero(b: u8) -> u8 {
+    (((b as u32).wrapping_sub(1)) >> 31) as u8
+}
+
+impl fmt::Debug for SgxRsaPrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxRsaPrivateKey")
+            .field("n", &self.n)
+            .field("e", &self.e)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SgxRsaPrivateKey {
+    fn drop(&mut self) {
+        rsgx_zeroize(&mut self.p);
+        rsgx_zeroize(&mut self.q);
+        rsgx_zeroize(&mut self.dmp1);
+        rsgx_zeroize(&mut self.dmq1);
+        rsgx_zeroize(&mut self.iqmp);
+    }
+}
+
+///
+/// A PSS signature in progress, created by SgxRsaPrivateKey::pss_signer.
+///
+pub struct SgxRsaPssSigner<'a> {
+    key: &'a SgxRsaPrivateKey,
+    hash: SgxRsaHash,
+    state: HashState,
+}
+
+impl<'a> SgxRsaPssSigner<'a> {
+    pub fn update(&self, data: &[u8]) -> SgxError {
+        self.state.update(data)
+    }
+
+    pub fn sign(self) -> SgxResult<Vec<u8>> {
+        let mut h = [0_u8; MAX_HASH];
+        let hlen = self.hash.output_size();
+        self.state.finish(&mut h[..hlen])?;
+        self.key.pss_sign_digest(self.hash, &h[..hlen])
+    }
+}
+
+///
+/// A PSS verification in progress, created by SgxRsaPublicKey::pss_verifier.
+///
+pub struct SgxRsaPssVerifier<'a> {
+    key: &'a SgxRsaPublicKey,
+    hash: SgxRsaHash,
+    state: HashState,
+}
+
+impl<'a> SgxRsaPssVerifier<'a> {
+    pub fn update(&self, data: &[u8]) -> SgxError {
+        self.state.update(data)
+    }
+
+    pub fn verify(self, signature: &[u8]) -> SgxResult<bool> {
+        let mut h = [0_u8; MAX_HASH];
+        let hlen = self.hash.output_size();
+        self.state.finish(&mut h[..hlen])?;
+        self.key.pss_verify_digest(self.hash, &h[..hlen], signature)
+    }
+}
@@ -0,0 +1,402 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! RustCrypto trait implementations
+//!
+//! With the `rustcrypto` feature, the hashes, AES-GCM and the signature schemes of
+//! this crate implement the traits of the `digest`, `aead` and `signature` crates, so
+//! code written against those traits runs on the SDK primitives unchanged.
+//!
+//! The traits have no way to report an SDK failure from a hash update or from key
+//! setup. The SHA-1 and SHA-2 hashes therefore panic if the SDK cannot allocate or
+//! update their state, which only happens when the enclave is out of memory.
+//!
+use super::aes_gcm::SgxAesGcm;
+use super::crypto::*;
+use super::ecdsa::*;
+use super::ed25519::*;
+use super::secret::rsgx_zeroize;
+use super::sha3::*;
+use aead::consts::{U0, U12, U16};
+use aead::{AeadCore, AeadInPlace, Key, KeyInit, KeySizeUser, Nonce, Tag};
+use core::ptr;
+use digest::consts::{U20, U32, U48, U64};
+use digest::{
+    ExtendableOutput, FixedOutput, FixedOutputReset, HashMarker, Output, OutputSizeUser, Reset,
+    Update, XofReader,
+};
+use sgx_types::*;
+use signature::{Signer, Verifier};
+
+macro_rules! impl_sdk_digest {
+    ($(#[$doc:meta])* $name:ident, $handle:ident, $size:ty) => {
+        $(#[$doc])*
+        pub struct $name($handle);
+
+        impl $name {
+            pub fn new() -> $name {
+                let handle = $handle::new();
+                handle.init().expect("cannot initialize the SDK hash state");
+                $name(handle)
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl HashMarker for $name {}
+
+        impl OutputSizeUser for $name {
+            type OutputSize = $size;
+        }
+
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                // The SDK rejects empty updates.
+                if !data.is_empty() {
+                    self.0.update_slice(data).expect("cannot update the SDK hash state");
+                }
+            }
+        }
+
+        impl FixedOutput for $name {
+            fn finalize_into(self, out: &mut Output<Self>) {
+                out.copy_from_slice(&self.0.get_hash().expect("cannot finish the SDK hash"));
+            }
+        }
+
+        impl Reset for $name {
+            fn reset(&mut self) {
+                *self = Self::new();
+            }
+        }
+
+        impl FixedOutputReset for $name {
+            fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+                out.copy_from_slice(&self.0.get_hash().expect("cannot finish the SDK hash"));
+                self.reset();
+            }
+        }
+    };
+}
+
+impl_sdk_digest!(
+    /// SHA-1 computed by the SDK, as a `digest::Digest`.
+    SgxSha1,
+    SgxSha1Handle,
+    U20
+);
+impl_sdk_digest!(
+    /// SHA-256 computed by the SDK, as a `digest::Digest`.
+    SgxSha256,
+    SgxShaHandle,
+    U32
+);
+impl_sdk_digest!(
+    /// SHA-384 computed by the SDK, as a `digest::Digest`.
+    SgxSha384,
+    SgxSha384Handle,
+    U48
+);
+
+macro_rules! impl_sha3_digest {
+    ($name:ident, $size:ty) => {
+        impl HashMarker for $name {}
+
+        impl OutputSizeUser for $name {
+            type OutputSize = $size;
+        }
+
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                self.update_slice(data);
+            }
+        }
+
+        impl FixedOutput for $name {
+            fn finalize_into(self, out: &mut Output<Self>) {
+                out.copy_from_slice(&self.finalize());
+            }
+        }
+
+        impl Reset for $name {
+            fn reset(&mut self) {
+                *self = Self::new();
+            }
+        }
+
+        impl FixedOutputReset for $name {
+            fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+                let done = core::mem::take(self);
+                out.copy_from_slice(&done.finalize());
+            }
+        }
+    };
+}
+
+impl_sha3_digest!(SgxSha3_256, U32);
+impl_sha3_digest!(SgxSha3_384, U48);
+impl_sha3_digest!(SgxSha3_512, U64);
+impl_sha3_digest!(SgxKeccak256, U32);
+
+macro_rules! impl_shake_xof {
+    ($name:ident) => {
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                self.update_slice(data);
+            }
+        }
+
+        impl ExtendableOutput for $name {
+            type Reader = SgxShakeReader;
+
+            fn finalize_xof(self) -> SgxShakeReader {
+                $name::finalize_xof(self)
+            }
+        }
+    };
+}
+
+impl_shake_xof!(SgxShake128);
+impl_shake_xof!(SgxShake256);
+
+impl XofReader for SgxShakeReader {
+    fn read(&mut self, buffer: &mut [u8]) {
+        self.squeeze(buffer);
+    }
+}
+
+///
+/// AES-128-GCM with a 96-bit nonce and a 128-bit tag, as an `aead::Aead`.
+///
+/// Each call runs the SDK's AES-GCM over the whole message, so an empty message with
+/// empty associated data is accepted too. A failed decryption leaves the buffer zeroed.
+///
+pub struct SgxAes128Gcm {
+    key: sgx_aes_gcm_128bit_key_t,
+}
+
+impl KeySizeUser for SgxAes128Gcm {
+    type KeySize = U16;
+}
+
+impl KeyInit for SgxAes128Gcm {
+    fn new(key: &Key<Self>) -> Self {
+        let mut k = sgx_aes_gcm_128bit_key_t::default();
+        k.copy_from_slice(key);
+        SgxAes128Gcm { key: k }
+    }
+}
+
+impl AeadCore for SgxAes128Gcm {
+    type NonceSize = U12;
+    type TagSize = U16;
+    type CiphertextOverhead = U0;
+}
+
+// SgxAesGcm does not work in place, so the buffer goes through a scratch block.
+fn gcm_in_place(gcm: &mut SgxAesGcm, buffer: &mut [u8]) -> SgxError {
+    let mut scratch = [0_u8; 512];
+    let mut result = Ok(());
+    for chunk in buffer.chunks_mut(scratch.len()) {
+        let src = &mut scratch[..chunk.len()];
+        src.copy_from_slice(chunk);
+        result = gcm.update(src, chunk);
+        if result.is_err() {
+            break;
+        }
+    }
+    unsafe { ptr::write_volatile(&mut scratch, [0_u8; 512]) };
+    result
+}
+
+fn gcm_iv(nonce: &[u8]) -> [u8; SGX_AESGCM_IV_SIZE] {
+    let mut iv = [0_u8; SGX_AESGCM_IV_SIZE];
+    iv.copy_from_slice(nonce);
+    iv
+}
+
+impl AeadInPlace for SgxAes128Gcm {
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> aead::Result<Tag<Self>> {
+        let mut gcm = SgxAesGcm::new_encrypt(&self.key, &gcm_iv(nonce));
+        gcm.update_aad(associated_data).map_err(|_| aead::Error)?;
+        gcm_in_place(&mut gcm, buffer).map_err(|_| aead::Error)?;
+        let tag = gcm.finalize().map_err(|_| aead::Error)?;
+        Ok(Tag::<Self>::clone_from_slice(&tag))
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag<Self>,
+    ) -> aead::Result<()> {
+        let mut expected = sgx_aes_gcm_128bit_tag_t::default();
+        expected.copy_from_slice(tag);
+        let mut gcm = SgxAesGcm::new_decrypt(&self.key, &gcm_iv(nonce));
+        let result = gcm
+            .update_aad(associated_data)
+            .and_then(|_| gcm_in_place(&mut gcm, buffer))
+            .and_then(|_| gcm.verify(&expected));
+        if result.is_err() {
+            rsgx_zeroize(buffer);
+            return Err(aead::Error);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SgxAes128Gcm {
+    fn drop(&mut self) {
+        unsafe { ptr::write_volatile(&mut self.key, sgx_aes_gcm_128bit_key_t::default()) };
+    }
+}
+
+impl Signer<sgx_ed25519_signature_t> for SgxEd25519PrivateKey {
+    fn try_sign(&self, msg: &[u8]) -> Result<sgx_ed25519_signature_t, signature::Error> {
+        Ok(self.sign(msg))
+    }
+}
+
+///
+/// An Ed25519 public key, as a `signature::Verifier`.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SgxEd25519VerifyingKey(pub sgx_ed25519_public_t);
+
+impl Verifier<sgx_ed25519_signature_t> for SgxEd25519VerifyingKey {
+    fn verify(
+        &self,
+        msg: &[u8],
+        signature: &sgx_ed25519_signature_t,
+    ) -> Result<(), signature::Error> {
+        match rsgx_ed25519_verify(&self.0, msg, signature) {
+            Ok(true) => Ok(()),
+            _ => Err(signature::Error::new()),
+        }
+    }
+}
+
+///
+/// An ECDSA P-256 private key, as a `signature::Signer` over SHA-256, using the SDK
+/// implementation. The SDK rejects empty messages.
+///
+pub struct SgxEcdsa256SigningKey(sgx_ec256_private_t);
+
+impl SgxEcdsa256SigningKey {
+    pub fn new(private: &sgx_ec256_private_t) -> SgxEcdsa256SigningKey {
+        SgxEcdsa256SigningKey(*private)
+    }
+}
+
+impl Signer<sgx_ec256_signature_t> for SgxEcdsa256SigningKey {
+    fn try_sign(&self, msg: &[u8]) -> Result<sgx_ec256_signature_t, signature::Error> {
+        let ecc = SgxEccHandle::new();
+        ecc.open().map_err(|_| signature::Error::new())?;
+        ecc.ecdsa_sign_slice(msg, &self.0)
+            .map_err(|_| signature::Error::new())
+    }
+}
+
+impl Drop for SgxEcdsa256SigningKey {
+    fn drop(&mut self) {
+        unsafe { ptr::write_volatile(&mut self.0, sgx_ec256_private_t::default()) };
+    }
+}
+
+///
+/// An ECDSA P-256 public key, as a `signature::Verifier` over SHA-256.
+///
+#[derive(Clone, Copy)]
+pub struct SgxEcdsa256VerifyingKey(pub sgx_ec256_public_t);
+
+impl Verifier<sgx_ec256_signature_t> for SgxEcdsa256VerifyingKey {
+    fn verify(
+        &self,
+        msg: &[u8],
+        signature: &sgx_ec256_signature_t,
+    ) -> Result<(), signature::Error> {
+        let mut hash = [0_u8; SGX_SHA256_HASH_SIZE];
+        hash.copy_from_slice(&<SgxSha256 as digest::Digest>::digest(msg));
+        let ecc = SgxEccHandle::new();
+        ecc.open().map_err(|_| signature::Error::new())?;
+        match ecc.ecdsa_verify_hash(&hash, &self.0, signature) {
+            Ok(true) => Ok(()),
+            _ => Err(signature::Error::new()),
+        }
+    }
+}
+
+///
+/// An ECDSA P-384 private key, as a `signature::Signer` over SHA-384 with random
+/// nonces.
+///
+pub struct SgxEcdsa384SigningKey(sgx_ec384_private_t);
+
+impl SgxEcdsa384SigningKey {
+    pub fn new(private: &sgx_ec384_private_t) -> SgxEcdsa384SigningKey {
+        SgxEcdsa384SigningKey(*private)
+    }
+}
+
+fn sha384_of(msg: &[u8]) -> sgx_sha384_hash_t {
+    let mut hash = [0_u8; SGX_SHA384_HASH_SIZE];
+    hash.copy_from_slice(&<SgxSha384 as digest::Digest>::digest(msg));
+    hash
+}
+
+impl Signer<sgx_ec384_signature_t> for SgxEcdsa384SigningKey {
+    fn try_sign(&self, msg: &[u8]) -> Result<sgx_ec384_signature_t, signature::Error> {
+        rsgx_ecdsa384_sign_hash(&sha384_of(msg), &self.0, SgxEcdsaNonce::Random)
+            .map_err(|_| signature::Error::new())
+    }
+}
+
+impl Drop for SgxEcdsa384SigningKey {
+    fn drop(&mut self) {
+        unsafe { ptr::write_volatile(&mut self.0, sgx_ec384_private_t::default()) };
+    }
+}
+
+///
+/// An ECDSA P-384 public key, as a `signature::Verifier` over SHA-384.
+///
+#[derive(Clone, Copy)]
+pub struct SgxEcdsa384VerifyingKey(pub sgx_ec384_public_t);
+
+impl Verifier<sgx_ec384_signature_t> for SgxEcdsa384VerifyingKey {
+    fn verify(
+        &self,
+        msg: &[u8],
+        signature: &sgx_ec384_signature_t,
+    ) -> Result<(), signature::Error> {
+        match rsgx_ecdsa384_verify_hash(&sha384_of(msg), &self.0, signature) {
+            Ok(true) => Ok(()),
+            _ => Err(signature::Error::new()),
+        }
+    }
+}
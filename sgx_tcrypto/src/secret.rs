@@ -0,0 +1,157 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Constant-time comparison and secret wiping
+//!
+//! Helpers for the patterns that code handling keys inside an enclave needs over and
+//! over: comparing MACs without leaking where they differ, wiping buffers in a way the
+//! optimizer cannot remove, holding secrets that are wiped on drop and never printed,
+//! and fencing speculative execution around secret-dependent code.
+//!
+use core::fmt;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{self, Ordering};
+use sgx_types::marker::ContiguousMemory;
+
+///
+/// rsgx_ct_eq compares two byte slices in time that depends only on their lengths.
+///
+/// # Description
+///
+/// Use it to check MACs, tags and other secret-dependent values, where an early exit
+/// would tell an attacker how many leading bytes were right. Slices of different
+/// lengths compare unequal; the lengths themselves are not treated as secret.
+///
+pub fn rsgx_ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a
+        .iter()
+        .zip(b.iter())
+        .fold(0_u8, |acc, (x, y)| acc | (x ^ y));
+    // Keep the compiler from turning the loop into an early-exit comparison.
+    unsafe { ptr::read_volatile(&diff) == 0 }
+}
+
+///
+/// rsgx_zeroize overwrites a buffer with zeros.
+///
+/// The writes are volatile and followed by a compiler fence, so they are neither
+/// removed as dead stores nor moved after a later release of the memory.
+///
+pub fn rsgx_zeroize(buf: &mut [u8]) {
+    for b in buf.iter_mut() {
+        unsafe { ptr::write_volatile(b, 0) };
+    }
+    atomic::compiler_fence(Ordering::SeqCst);
+}
+
+///
+/// The fences of sgx_trts, so that code fencing around secrets only needs this crate.
+/// rsgx_lfence stops speculative execution until all earlier instructions have
+/// completed; place it after a bounds or permission check that guards a
+/// secret-dependent load (Spectre variant 1). rsgx_mfence orders all earlier loads
+/// and stores before all later ones.
+///
+pub use sgx_trts::trts::{rsgx_lfence, rsgx_mfence};
+
+///
+/// A secret value that is wiped when it is dropped.
+///
+/// # Description
+///
+/// SgxSecret keeps a plain-data value such as a key or a shared secret. Its Debug
+/// output never shows the value, it does not implement Clone, and its memory is
+/// overwritten with zeros on drop. The value is reached with expose and expose_mut,
+/// which makes every use of the secret easy to find.
+///
+/// Copies made from expose are not tracked, so callers should keep borrows short
+/// instead of copying the value out.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sgx_tcrypto::SgxSecret;
+///
+/// let key = SgxSecret::new([0x42_u8; 16]);
+/// assert_eq!(key.expose().len(), 16);
+/// ```
+///
+pub struct SgxSecret<T: Copy + ContiguousMemory> {
+    value: T,
+}
+
+impl<T: Copy + ContiguousMemory> SgxSecret<T> {
+    pub fn new(value: T) -> SgxSecret<T> {
+        SgxSecret { value }
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.value
+    }
+
+    pub fn expose_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    ///
+    /// ct_eq compares two secrets in constant time.
+    ///
+    pub fn ct_eq(&self, other: &SgxSecret<T>) -> bool {
+        rsgx_ct_eq(self.as_bytes(), other.as_bytes())
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts(&self.value as *const T as *const u8, mem::size_of::<T>())
+        }
+    }
+}
+
+impl<T: Copy + ContiguousMemory + Default> Default for SgxSecret<T> {
+    fn default() -> Self {
+        SgxSecret::new(T::default())
+    }
+}
+
+impl<T: Copy + ContiguousMemory> From<T> for SgxSecret<T> {
+    fn from(value: T) -> Self {
+        SgxSecret::new(value)
+    }
+}
+
+impl<T: Copy + ContiguousMemory> fmt::Debug for SgxSecret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SgxSecret(..)")
+    }
+}
+
+impl<T: Copy + ContiguousMemory> Drop for SgxSecret<T> {
+    fn drop(&mut self) {
+        // T is Copy, so it has no drop glue and the zeroed value is never read.
+        let bytes = unsafe {
+            core::slice::from_raw_parts_mut(
+                &mut self.value as *mut T as *mut u8,
+                mem::size_of::<T>(),
+            )
+        };
+        rsgx_zeroize(bytes);
+    }
+}
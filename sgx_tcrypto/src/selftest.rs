@@ -0,0 +1,756 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Power-on self tests
+//!
+//! rsgx_crypto_self_test checks every algorithm the policy allows against a known
+//! answer, and checks randomized signatures by signing and verifying. It is meant to
+//! run once at enclave init, before the enclave uses this crate for anything else, as
+//! FIPS 140-3 requires of a certified module.
+//!
+//! A failed self test puts the crate into an error state in which every checked
+//! function fails with SGX_ERROR_INVALID_STATE, until the enclave is destroyed.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use sgx_tcrypto::{rsgx_crypto_restrict_policy, rsgx_crypto_self_test, SgxAlgorithm};
+//! use sgx_tcrypto::SgxAlgorithmPolicy;
+//!
+//! // At enclave init: keep to the approved algorithms and test them.
+//! rsgx_crypto_restrict_policy(SgxAlgorithmPolicy::approved().deny(SgxAlgorithm::RsaPkcs1v15));
+//! rsgx_crypto_self_test().unwrap();
+//! ```
+//!
+use super::policy::{enter_error_state, failed_algorithm};
+use super::*;
+use alloc::vec::Vec;
+use sgx_types::*;
+
+type SelfTest = fn() -> SgxResult<bool>;
+
+const TESTS: &[(SgxAlgorithm, SelfTest)] = &[
+    (SgxAlgorithm::Sha1, test_sha1),
+    (SgxAlgorithm::Sha256, test_sha256),
+    (SgxAlgorithm::Sha384, test_sha384),
+    (SgxAlgorithm::Sha3, test_sha3),
+    (SgxAlgorithm::Hmac, test_hmac),
+    (SgxAlgorithm::Hkdf, test_hkdf),
+    (SgxAlgorithm::Pbkdf2, test_pbkdf2),
+    (SgxAlgorithm::AesGcm, test_aes_gcm),
+    (SgxAlgorithm::AesGcmSiv, test_aes_gcm_siv),
+    (SgxAlgorithm::AesCtr, test_aes_ctr),
+    (SgxAlgorithm::AesCmac, test_aes_cmac),
+    (SgxAlgorithm::AesKw, test_aes_kw),
+    (SgxAlgorithm::ChaCha20Poly1305, test_chacha20_poly1305),
+    (SgxAlgorithm::EcdsaP256, test_ecdsa_p256),
+    (SgxAlgorithm::EcdsaP384, test_ecdsa_p384),
+    (SgxAlgorithm::EcdhP256, test_ecdh_p256),
+    (SgxAlgorithm::EcdhP384, test_ecdh_p384),
+    (SgxAlgorithm::Ed25519, test_ed25519),
+    (SgxAlgorithm::X25519, test_x25519),
+    (SgxAlgorithm::HashToCurve, test_hash_to_curve),
+    (SgxAlgorithm::Ecvrf, test_ecvrf),
+    (SgxAlgorithm::RsaPkcs1v15, test_rsa_pkcs1v15),
+    (SgxAlgorithm::RsaPss, test_rsa_pss),
+    (SgxAlgorithm::RsaOaep, test_rsa_oaep),
+    #[cfg(feature = "sm")]
+    (SgxAlgorithm::Sm2, test_sm2),
+    #[cfg(feature = "sm")]
+    (SgxAlgorithm::Sm3, test_sm3),
+    #[cfg(feature = "sm")]
+    (SgxAlgorithm::Sm4, test_sm4),
+];
+
+///
+/// Runs the self test of every algorithm the current policy allows.
+///
+/// Algorithms the policy disallows are skipped, as are tests that need a disallowed
+/// algorithm, such as the RSA tests when SHA-256 is disallowed. Restrict the policy
+/// before calling this function.
+///
+/// # Errors
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// A self test failed. The crate is now in the error state, and
+/// rsgx_crypto_self_test_failure returns the algorithm that failed.
+///
+/// **SGX_ERROR_INVALID_STATE**
+///
+/// A self test has failed before.
+///
+pub fn rsgx_crypto_self_test() -> SgxError {
+    if failed_algorithm().is_some() {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+    }
+    let policy = rsgx_crypto_policy();
+    for &(algorithm, test) in TESTS {
+        if !policy.is_allowed(algorithm) {
+            continue;
+        }
+        match test() {
+            Ok(true) | Err(sgx_status_t::SGX_ERROR_FEATURE_NOT_SUPPORTED) => (),
+            _ => {
+                enter_error_state(algorithm);
+                return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+            }
+        }
+    }
+    Ok(())
+}
+
+///
+/// Returns the algorithm whose self test failed, or None if no self test has failed.
+///
+pub fn rsgx_crypto_self_test_failure() -> Option<SgxAlgorithm> {
+    failed_algorithm()
+}
+
+fn hex(s: &str) -> Vec<u8> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let nibble = |c: u8| match c {
+                b'0'..=b'9' => c - b'0',
+                b'a'..=b'f' => c - b'a' + 10,
+                _ => c - b'A' + 10,
+            };
+            nibble(pair[0]) << 4 | nibble(pair[1])
+        })
+        .collect()
+}
+
+// The SDK keeps EC coordinates and RSA key components little-endian.
+fn le(s: &str) -> Vec<u8> {
+    let mut bytes = hex(s);
+    bytes.reverse();
+    bytes
+}
+
+fn array<const N: usize>(s: &str) -> [u8; N] {
+    let mut bytes = [0_u8; N];
+    bytes.copy_from_slice(&hex(s));
+    bytes
+}
+
+fn le_array<const N: usize>(s: &str) -> [u8; N] {
+    let mut bytes = [0_u8; N];
+    bytes.copy_from_slice(&le(s));
+    bytes
+}
+
+fn le_words<const N: usize>(s: &str) -> [u32; N] {
+    let mut words = [0_u32; N];
+    for (word, bytes) in words.iter_mut().zip(le(s).chunks(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    words
+}
+
+fn test_sha1() -> SgxResult<bool> {
+    let expected = "a9993e364706816aba3e25717850c26c9cd0d89d";
+    Ok(rsgx_sha1_slice(b"abc")?[..] == hex(expected)[..])
+}
+
+fn test_sha256() -> SgxResult<bool> {
+    let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    Ok(rsgx_sha256_slice(b"abc")?[..] == hex(expected)[..])
+}
+
+fn test_sha384() -> SgxResult<bool> {
+    let expected = concat!(
+        "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded163",
+        "1a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7"
+    );
+    Ok(rsgx_sha384_slice(b"abc")?[..] == hex(expected)[..])
+}
+
+fn test_sha3() -> SgxResult<bool> {
+    let expected = "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532";
+    Ok(rsgx_sha3_256_slice(b"abc")?[..] == hex(expected)[..])
+}
+
+fn test_hmac() -> SgxResult<bool> {
+    let key = array("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+    let expected = "f0133729c4163dede81e21cd47839256da58171238c8a0d874397c73b14e1e47";
+    if rsgx_hmac_sha256_slice(&key, b"abc")?[..] != hex(expected)[..] {
+        return Ok(false);
+    }
+
+    // RFC 4231, test case 2
+    let mut mac = SgxHmacSha384::new(b"Jefe")?;
+    mac.update(b"what do ya want for nothing?")?;
+    let expected = concat!(
+        "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47",
+        "e42ec3736322445e8e2240ca5e69e2c78b3239ecfab21649"
+    );
+    Ok(mac.finalize()?[..] == hex(expected)[..])
+}
+
+// RFC 5869, test case 1
+fn test_hkdf() -> SgxResult<bool> {
+    let ikm = [0x0b_u8; 22];
+    let salt = hex("000102030405060708090a0b0c");
+    let info = hex("f0f1f2f3f4f5f6f7f8f9");
+    let mut okm = [0_u8; 42];
+    rsgx_hkdf_sha256(&salt, &ikm, &info, &mut okm)?;
+    let expected = concat!(
+        "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf",
+        "34007208d5b887185865"
+    );
+    Ok(okm[..] == hex(expected)[..])
+}
+
+// RFC 7914, section 11
+fn test_pbkdf2() -> SgxResult<bool> {
+    let mut dk = [0_u8; 64];
+    rsgx_pbkdf2_hmac_sha256(b"passwd", b"salt", 1, &mut dk)?;
+    let expected = concat!(
+        "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc",
+        "49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+    );
+    Ok(dk[..] == hex(expected)[..])
+}
+
+// The GCM specification, test case 2
+fn test_aes_gcm() -> SgxResult<bool> {
+    let key = [0_u8; 16];
+    let iv = [0_u8; 12];
+    let plaintext = [0_u8; 16];
+    let mut ciphertext = [0_u8; 16];
+    let mut tag = [0_u8; 16];
+    rsgx_rijndael128GCM_encrypt(&key, &plaintext, &iv, &[], &mut ciphertext, &mut tag)?;
+    if ciphertext[..] != hex("0388dace60b6a392f328c2b971b2fe78")[..]
+        || tag[..] != hex("ab6e47d42cec13bdf53a67b21257bddf")[..]
+    {
+        return Ok(false);
+    }
+
+    let mut decrypted = [0xff_u8; 16];
+    tag[0] ^= 1;
+    if rsgx_rijndael128GCM_decrypt(&key, &ciphertext, &iv, &[], &tag, &mut decrypted).is_ok() {
+        return Ok(false);
+    }
+    tag[0] ^= 1;
+    rsgx_rijndael128GCM_decrypt(&key, &ciphertext, &iv, &[], &tag, &mut decrypted)?;
+    Ok(decrypted == plaintext)
+}
+
+// RFC 8452, appendix C.1
+fn test_aes_gcm_siv() -> SgxResult<bool> {
+    let key = hex("01000000000000000000000000000000");
+    let nonce = array("030000000000000000000000");
+    let plaintext = hex("0100000000000000");
+    let mut ciphertext = [0_u8; 8];
+    let tag = SgxAesGcmSiv::new_encrypt(&key, &nonce)?.finalize(&plaintext, &mut ciphertext)?;
+    if ciphertext[..] != hex("b5d839330ac7b786")[..]
+        || tag[..] != hex("578782fff6013b815b287c22493a364c")[..]
+    {
+        return Ok(false);
+    }
+
+    let mut siv = SgxAesGcmSiv::new_decrypt(&key, &nonce, &tag)?;
+    let mut decrypted = [0_u8; 8];
+    siv.update(&ciphertext, &mut decrypted)?;
+    siv.verify()?;
+    Ok(decrypted[..] == plaintext[..])
+}
+
+// NIST SP 800-38A, F.5.1
+fn test_aes_ctr() -> SgxResult<bool> {
+    let key = array("2b7e151628aed2a6abf7158809cf4f3c");
+    let mut ctr = array("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+    let plaintext = hex("6bc1bee22e409f96e93d7e117393172a");
+    let mut ciphertext = [0_u8; 16];
+    rsgx_aes_ctr_encrypt(&key, &plaintext, &mut ctr, 128, &mut ciphertext)?;
+    Ok(ciphertext[..] == hex("874d6191b620e3261bef6864990db6ce")[..])
+}
+
+// NIST SP 800-38B, D.1 example 2
+fn test_aes_cmac() -> SgxResult<bool> {
+    let key = array("2b7e151628aed2a6abf7158809cf4f3c");
+    let msg = hex("6bc1bee22e409f96e93d7e117393172a");
+    Ok(rsgx_rijndael128_cmac_slice(&key, &msg)?[..] == hex("070a16b46b4d4144f79bdd9dd04a287c")[..])
+}
+
+// RFC 3394, section 4.1
+fn test_aes_kw() -> SgxResult<bool> {
+    let kek = hex("000102030405060708090a0b0c0d0e0f");
+    let key = hex("00112233445566778899aabbccddeeff");
+    let wrapped = rsgx_aes_kw_wrap(&kek, &key)?;
+    let expected = "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5";
+    Ok(wrapped == hex(expected) && rsgx_aes_kw_unwrap(&kek, &wrapped)? == key)
+}
+
+// RFC 8439, section 2.8.2
+fn test_chacha20_poly1305() -> SgxResult<bool> {
+    let key = array("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+    let nonce = array("070000004041424344454647");
+    let aad = hex("50515253c0c1c2c3c4c5c6c7");
+    let plaintext: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+        only one tip for the future, sunscreen would be it.";
+    let mut ciphertext = [0_u8; 114];
+    let mut chacha = SgxChaCha20Poly1305::new_encrypt(&key, &nonce);
+    chacha.update_aad(&aad)?;
+    chacha.update(plaintext, &mut ciphertext)?;
+    let tag = chacha.finalize()?;
+    if ciphertext[..16] != hex("d31a8d34648e60db7b86afbc53ef7ec2")[..]
+        || tag[..] != hex("1ae10b594f09e26a7e902ecbd0600691")[..]
+    {
+        return Ok(false);
+    }
+
+    let mut chacha = SgxChaCha20Poly1305::new_decrypt(&key, &nonce);
+    chacha.update_aad(&aad)?;
+    let mut decrypted = [0_u8; 114];
+    chacha.update(&ciphertext, &mut decrypted)?;
+    chacha.verify(&tag)?;
+    Ok(decrypted[..] == plaintext[..])
+}
+
+// RFC 6979, appendix A.2.5, with SHA-256 and the message "sample"
+fn test_ecdsa_p256() -> SgxResult<bool> {
+    let private = sgx_ec256_private_t {
+        r: le_array("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721"),
+    };
+    let public = sgx_ec256_public_t {
+        gx: le_array("60fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6"),
+        gy: le_array("7903fe1008b8bc99a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462299"),
+    };
+    let expected = sgx_ec256_signature_t {
+        x: le_words("efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716"),
+        y: le_words("f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8"),
+    };
+    let signature = rsgx_ecdsa256_sign_deterministic_slice(b"sample", &private)?;
+    if signature.x != expected.x || signature.y != expected.y {
+        return Ok(false);
+    }
+
+    let ecc = SgxEccHandle::new();
+    ecc.open()?;
+    if !ecc.ecdsa_verify_slice(b"sample", &public, &expected)?
+        || ecc.ecdsa_verify_slice(b"simple", &public, &expected)?
+    {
+        return Ok(false);
+    }
+    let signature = ecc.ecdsa_sign_slice(b"sample", &private)?;
+    ecc.ecdsa_verify_slice(b"sample", &public, &signature)
+}
+
+// RFC 6979, appendix A.2.6, with SHA-384 and the message "sample"
+fn test_ecdsa_p384() -> SgxResult<bool> {
+    let private = sgx_ec384_private_t {
+        r: le_array(concat!(
+            "6b9d3dad2e1b8c1c05b19875b6659f4de23c3b667bf297ba",
+            "9aa47740787137d896d5724e4c70a825f872c9ea60d2edf5"
+        )),
+    };
+    let public = sgx_ec384_public_t {
+        gx: le_array(concat!(
+            "ec3a4e415b4e19a4568618029f427fa5da9a8bc4ae92e02e",
+            "06aae5286b300c64def8f0ea9055866064a254515480bc13"
+        )),
+        gy: le_array(concat!(
+            "8015d9b72d7d57244ea8ef9ac0c621896708a59367f9dfb9",
+            "f54ca84b3f1c9db1288b231c3ae0d4fe7344fd2533264720"
+        )),
+    };
+    let expected = sgx_ec384_signature_t {
+        x: le_words(concat!(
+            "94edbb92a5ecb8aad4736e56c691916b3f88140666ce9fa7",
+            "3d64c4ea95ad133c81a648152e44acf96e36dd1e80fabe46"
+        )),
+        y: le_words(concat!(
+            "99ef4aeb15f178cea1fe40db2603138f130e740a19624526",
+            "203b6351d0a3a94fa329c145786e679e7b82c71a38628ac8"
+        )),
+    };
+    let signature = rsgx_ecdsa384_sign_slice(b"sample", &private, SgxEcdsaNonce::Deterministic)?;
+    if signature.x != expected.x || signature.y != expected.y {
+        return Ok(false);
+    }
+    if !rsgx_ecdsa384_verify_slice(b"sample", &public, &expected)?
+        || rsgx_ecdsa384_verify_slice(b"simple", &public, &expected)?
+    {
+        return Ok(false);
+    }
+    let signature = rsgx_ecdsa384_sign_slice(b"sample", &private, SgxEcdsaNonce::Random)?;
+    rsgx_ecdsa384_verify_slice(b"sample", &public, &signature)
+}
+
+// The shared x coordinate of d and the base point is the x coordinate of the public
+// key of d, here from the ECDSA tests above.
+fn test_ecdh_p256() -> SgxResult<bool> {
+    let private = sgx_ec256_private_t {
+        r: le_array("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721"),
+    };
+    let base = sgx_ec256_public_t {
+        gx: le_array("6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296"),
+        gy: le_array("4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5"),
+    };
+    let ecc = SgxEccHandle::new();
+    ecc.open()?;
+    let shared = ecc.compute_shared_dhkey(&private, &base)?;
+    let expected = le("60fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6");
+    Ok(shared.s[..] == expected[..])
+}
+
+fn test_ecdh_p384() -> SgxResult<bool> {
+    let private = sgx_ec384_private_t {
+        r: le_array(concat!(
+            "6b9d3dad2e1b8c1c05b19875b6659f4de23c3b667bf297ba",
+            "9aa47740787137d896d5724e4c70a825f872c9ea60d2edf5"
+        )),
+    };
+    let base = sgx_ec384_public_t {
+        gx: le_array(concat!(
+            "aa87ca22be8b05378eb1c71ef320ad746e1d3b628ba79b98",
+            "59f741e082542a385502f25dbf55296c3a545e3872760ab7"
+        )),
+        gy: le_array(concat!(
+            "3617de4a96262c6f5d9e98bf9292dc29f8f41dbd289a147c",
+            "e9da3113b5f0b8c00a60b1ce1d7e819d7a431d7c90ea0e5f"
+        )),
+    };
+    let shared = rsgx_ecc384_compute_shared_dhkey(&private, &base)?;
+    let expected = le(concat!(
+        "ec3a4e415b4e19a4568618029f427fa5da9a8bc4ae92e02e",
+        "06aae5286b300c64def8f0ea9055866064a254515480bc13"
+    ));
+    Ok(shared.s[..] == expected[..])
+}
+
+// RFC 8032, section 7.1, test 1
+fn test_ed25519() -> SgxResult<bool> {
+    let key = SgxEd25519PrivateKey::from_seed(&array(
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+    ));
+    let public = key.public_key();
+    let expected: [u8; 64] = array(concat!(
+        "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155",
+        "5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+    ));
+    if public[..] != hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")[..]
+        || key.sign(b"")[..] != expected[..]
+    {
+        return Ok(false);
+    }
+    Ok(rsgx_ed25519_verify(&public, b"", &expected)?
+        && !rsgx_ed25519_verify(&public, b"x", &expected)?)
+}
+
+// RFC 7748, section 5.2
+fn test_x25519() -> SgxResult<bool> {
+    let key = SgxX25519PrivateKey::from_bytes(&array(
+        "a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4",
+    ));
+    let u = array("e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c");
+    let expected = "c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552";
+    Ok(key.diffie_hellman(&u)?.as_bytes()[..] == hex(expected)[..])
+}
+
+// RFC 9380, appendices J.1.1 and J.5.1, with the empty message
+fn test_hash_to_curve() -> SgxResult<bool> {
+    let point = rsgx_p256_hash_to_curve(b"", b"QUUX-V01-CS02-with-P256_XMD:SHA-256_SSWU_RO_")?;
+    let expected = concat!(
+        "042c15230b26dbc6fc9a37051158c95b79656e17a1a920b11394ca91c44247d3e4",
+        "8a7a74985cc5c776cdfe4b1f19884970453912e9d31528c060be9ab5c43e8415"
+    );
+    if point.to_sec1(false)?[..] != hex(expected)[..] {
+        return Ok(false);
+    }
+    let point = rsgx_edwards25519_hash_to_curve(
+        b"",
+        b"QUUX-V01-CS02-with-edwards25519_XMD:SHA-512_ELL2_RO_",
+    )?;
+    Ok(point[..] == hex("21dc15e10253796df23a7699c8a383ea624cce88c52431f6be220b1a56c8a609")[..])
+}
+
+// RFC 9381, appendices B.2 and B.3, with the keys of RFC 6979 and RFC 8032
+fn test_ecvrf() -> SgxResult<bool> {
+    let private = sgx_ec256_private_t {
+        r: le_array("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721"),
+    };
+    let public = sgx_ec256_public_t {
+        gx: le_array("60fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6"),
+        gy: le_array("7903fe1008b8bc99a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462299"),
+    };
+    let expected: [u8; 81] = array(concat!(
+        "0331d984ca8fece9cbb9a144c0d53df3c4c7a33080c1e02ddb1a96a365394c7888",
+        "782fffde7b842c38c20c08de6ec6c2e7027a97000f2c9fa4425d5c03e639fb48",
+        "fde58114d755985498d7eb234cf4aed9"
+    ));
+    let beta = hex("21e66dc9747430f17ed9efeda054cf4a264b097b9e8956a1787526ed00dc664b");
+    if rsgx_ecvrf_p256_prove(&private, b"sample")?[..] != expected[..]
+        || rsgx_ecvrf_p256_verify(&public, b"sample", &expected)?[..] != beta[..]
+        || rsgx_ecvrf_p256_verify(&public, b"simple", &expected).is_ok()
+    {
+        return Ok(false);
+    }
+
+    let key = SgxEd25519PrivateKey::from_seed(&array(
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+    ));
+    let expected: [u8; 80] = array(concat!(
+        "7d9c633ffeee27349264cf5c667579fc583b4bda63ab71d001f89c10003ab46f",
+        "14adf9a3cd8b8412d9038531e865c341cafa73589b023d14311c331a9ad15ff2",
+        "fb37831e00f0acaa6d73bc9997b06501"
+    ));
+    let beta = hex(concat!(
+        "9d574bf9b8302ec0fc1e21c3ec5368269527b87b462ce36dab2d14ccf80c53cc",
+        "cf6758f058c5b1c856b116388152bbe509ee3b9ecfe63d93c3b4346c1fbc6c54"
+    ));
+    Ok(rsgx_ecvrf_ed25519_prove(&key, b"")?[..] == expected[..]
+        && rsgx_ecvrf_ed25519_verify(&key.public_key(), b"", &expected)?[..] == beta[..])
+}
+
+// A 3072-bit key with e = 65537, big-endian, with a PSS-SHA256 signature of "abc" with
+// a 32-byte salt and an OAEP-SHA256 encryption of "abc" with no label.
+const RSA_N: &str = concat!(
+    "dca4e969c03818523f7e655df20e30dc09fe7418ca7e72698602be0baac42878",
+    "5e01fa397033f556a07eedc1e8858e99fca24b2792207b6be3cb65b24874c826",
+    "8695362d7b10a8c203e2d205548a2bbc3e65dae601cb9174f2efd51ce9f7dff4",
+    "ad019f8695cc4729b96f7aa629d9e08850cc5df819ee9629a4642c62b76ce6f2",
+    "6b63fcb7b81cee119d60e2500ab82ec9faa76febc9da2ca217e934df2fb42581",
+    "253c231f13164f95f68c394ed3ce0e3581230943cba1201334dc75b29d53c308",
+    "85540dee20c72c4278c63c53804b2e5f1ce0e4c5ad14e20826b2e49a191c4ace",
+    "aa48a53e87d50b93b6adee6e32f520cda923878564a6491fa6817e5302a6f5d2",
+    "5658946ac529b49f106c186c3186b4051703c514ffb9eb4d5374e9432185a22c",
+    "b0d59aecddb17bc1b11f4c3154422389df92d3cf3f245513f0fe603452e7267a",
+    "48aa149d927773bf5c23166d53f177b1872d2f603946834e3a995614c5f8f65e",
+    "67d7f39262b79c2b424e1e72d539ee02b87879ca211c7822b496a07658d91ff7",
+);
+
+const RSA_D: &str = concat!(
+    "10f895997ee19c56a085477a6136b5370a2fbfb1f3caa3b5e3cb0c8a1786eca3",
+    "f6d073ca278c0a38065d424ab2bdff18c4aff3a990aef33f4faf4d31ad800a05",
+    "bde94bd85609bdd3e524f4f42dab4e5a3579c920a837b106f69ea5878aa42308",
+    "e3511224f1c5b0c83aa2a2a39594296b38126d673abb640d1794bae896e4dca3",
+    "531e46df175d6649b03a0ba8e36cda45ca83f173089216f27020b86288529f65",
+    "de6825e815ceec02840b0767684430f498410a77e3f98f343051dab3093085a4",
+    "daa5cc65113ad267863f6a0123b4ca65f319a8e5b1c241892d53a312ad74ce1b",
+    "339c93837dc1eaa70b839fd0cab119e0fe9c3acaaa5a681183c165a0e7c33fc6",
+    "2c894d81622be686e68317f638af9bba1890f2c35eec21b86640fe677a2ea28d",
+    "f96d4c3871a9673f29ca3e28ed991e1b973e752d97e6c8b068fcb227f094257f",
+    "a42b320bc6179aef25ac09ff6224c730a4641cd0650e43b68f33f51b91072816",
+    "ed9e11727567de3050c14d2246e6159d255e70368c2db036d01c657dc0f2c45d",
+);
+
+const RSA_P: &str = concat!(
+    "ff73fb820595da6ba9051f40f15796afb2d06bf3adade9e2b9faf5d6deb3ec48",
+    "a62898ab200e003722d2356f4b7cc9c0436d494b26c4866517f1e524bb84d9c7",
+    "1056fbf255a7578a8ba67df06c03fb3a3623b518940afacd6d7dd32b8c880efb",
+    "a19e8af0d90b83cbf71239352622aa7cef23bd389a6dbaead9757c71db43a3ff",
+    "13c08d3f8314e5fcb5721e912d7787720449967c4e9a861870edb5317f4d691b",
+    "37ba1ef0841bb2774a21f95d1a7a92c755b409737c803c9a61fff89479849a15",
+);
+
+const RSA_Q: &str = concat!(
+    "dd1dd99e09ea13fd8fb9c9a5830317f936bd83adc7a47d3950681c9f65cdf86d",
+    "88f71e49b8195d06b9a01e2c3449c886f1a3a6f5e2c951ebf8ae637a18e92b3f",
+    "e9ddb4896a0d6523c4e7cf03672907f2a75dfe3eea1cf55d6cc5875ebea31f74",
+    "782624b9f60353335ea69e1199338a1cbdae198a349819ddfae51997f4e56aa3",
+    "d163cf0b3d400d63478d75f0340b7258d72dfcb9ab8324e40b71e759e2f7a39a",
+    "895ad886444c63095e2059f4f22946df50222229ea48bd13c102a99149a810db",
+);
+
+const RSA_DMP1: &str = concat!(
+    "067d6ad95dccf5d4a061a9ee9b730f37fd82000c1bd07c4daf4de9eb00936fa0",
+    "54e0592897d2e33c835cf5d093096392c7e3c1e5efe1344ce333d90f7ad82034",
+    "477cfe3fa4df7080eb955a43be6ac77a2e1ff01ce4bdf6ab3b69e3f08a4c769c",
+    "fbf92c2c7c47279d09f4e3912f1bd85afe34593d53e70fc5a1aab373ad0f65de",
+    "38213f356ade200db08a6aae638a52dd99b048eb758fc5725f841a835c699a0e",
+    "fff6718cc8be6f654a73dc82fa063d2e654602ab75714898df7e82513b591c15",
+);
+
+const RSA_DMQ1: &str = concat!(
+    "7964c5f0a9e8c508d43b5fa56a83ec4979f019b6f2802fefa36145358da2c2dc",
+    "fff10bc3d53ab783d5ad3e636e6183a1f24fb27e14000ae5ce258f9f9cc58665",
+    "14740f85d19f81035e3b15bffc86de422df8c078b1f72c0e1a856788708905df",
+    "df86b16b16d14480535a8e3583327484caee263f66188b3aff5f7fe9991dcaaa",
+    "1d6efa60654421157c570184da4dde7d3fc593962bbf58258ad301fcab4ae19b",
+    "1a05b21224b4fb3a4d5edd8448deb876297c53dd472e9fff945b6dd4c1e76f39",
+);
+
+const RSA_IQMP: &str = concat!(
+    "fcb36f8462e4e5e8d6436712baf6888d7c6db3fcb5d9ba3fb5c9b5f682450dc0",
+    "0540ac7a0888bce419b0d39958a99bc045f0f44577258f8507c3980d62219823",
+    "07ea6a22787611ea99647615a07a7a9a08ab84b1a3ed8e02c3bb2f218c2fddbb",
+    "20fd22c4d84aa28e1128d61d1adca4a77254d486ef9a66fbe45a468fc9da49fe",
+    "49bd7152bbdc84d9f719e4f87803c63f28041401c09eec1cb563a35f2a0af56f",
+    "15739c287faa97fcce89be5d5d325708ac5635d70dcac3ea1af71cc91375f90a",
+);
+
+const RSA_PSS_SHA256: &str = concat!(
+    "3f797ba8e8eaea505940b4cd99598e63edad37c5e169b0af8eebecff30a4bca8",
+    "119b74addd5cc6ca28c2058636e69630b22e5d554b990a3336639c7c05c7faf9",
+    "a8b05bf574dbaeeda5f1ab57c2359891dbb406b05fd4c6964d2a06759f54c7a2",
+    "51df7592b5decfd897d3b189419a10f4ad28ea4a122aa0cc9680a94b85dd2c72",
+    "368d232db2b56671427793928beb649802d8d377cd100318b2527f1d4bbee2f2",
+    "c1be5cdf7d066d991224be66df9c334474967cb5d2aa1f60c93c95d03fe6787f",
+    "9245d23271ff788d73e0d017622ee23a124a2c0ab5dbe989796175e7aa9acbec",
+    "65a38fc69fc93a46a08df872843edd66cc8bf1c2fe1b2a1cacbd4c02176836dc",
+    "33fbd5bf7198c823448ff6778b6c807a95a8f4fb0cb2122df72a95e82a55362a",
+    "2b401935e962119fe5a8dec78e8f3f9ef4700c3d1bbaba75cad35a4d38b99052",
+    "7e4b9102561d3a822c09243cd76bfcff64ba79d251b44d821ebf578724afe02b",
+    "cb74d72d8bfe89db50231bccd36e14f5d829c603cbe55ef1634baae6f3cf97ce",
+);
+
+const RSA_OAEP_SHA256: &str = concat!(
+    "8a38a3717d3e87dda6c47800e13620c9a3f01c680c3ba8fc00b9e64f9998ac5a",
+    "759cb653eaf4af087c000f622225fcc973e7f76e3915721ba6177ea6171236fe",
+    "6ae7bfe4eb7035fac8383d2fc16ec0e18e79a3abb6364dddf8774966cf5b2fa7",
+    "04dd344ed582c7110bb259618e1f09f655b8c9ac205abab97de0da6d02975fe3",
+    "05a7cd1e03e20e5f70fb845810aa4ffc5ee8e772753b2e8822f03bebbe4353bd",
+    "aa5375313386c54435189a8730eef149bcd4e5e9c226f836d82c243e05e12316",
+    "977283356e5e1df8403b9971b00ee99efa239d20fa29fe0fa2bbad7d79e832cf",
+    "0a0aa083641a132a44be831c48305cafdbb8d6470128643323af2f5047304e19",
+    "314a7c5e17fa26dea6faf5b57cc92ee74cf97a1699595714368902032798dc5a",
+    "1017354642baf5e2511690ec75056b18a3c3b4d9ab922d0368c9939e93cdb53e",
+    "c116445f5b48a0999d549792d6a2c2b22bf98289b89350cbf0212c5a87aedc53",
+    "86c4ebf7ee1ec10b69f33cae359d05a79fe5df050e6edf3c6de8a0865e406f17",
+);
+
+fn rsa_key() -> SgxResult<SgxRsaPrivateKey> {
+    SgxRsaPrivateKey::new(
+        &le(RSA_N),
+        &65537_u32.to_le_bytes(),
+        &le(RSA_P),
+        &le(RSA_Q),
+        &le(RSA_DMP1),
+        &le(RSA_DMQ1),
+        &le(RSA_IQMP),
+    )
+}
+
+fn test_rsa_pkcs1v15() -> SgxResult<bool> {
+    let key = sgx_rsa3072_key_t {
+        modulus: le_array(RSA_N),
+        d: le_array(RSA_D),
+        e: 65537_u32.to_le_bytes(),
+    };
+    let public = sgx_rsa3072_public_key_t {
+        modulus: key.modulus,
+        exponent: key.e,
+    };
+    let signature = rsgx_rsa3072_sign_slice(b"abc", &key)?;
+    Ok(rsgx_rsa3072_verify_slice(b"abc", &public, &signature)?
+        && !rsgx_rsa3072_verify_slice(b"abd", &public, &signature)?)
+}
+
+fn test_rsa_pss() -> SgxResult<bool> {
+    let key = rsa_key()?;
+    let public = key.public_key();
+    let signature = hex(RSA_PSS_SHA256);
+    if !public.pss_verify(SgxRsaHash::Sha256, b"abc", &signature)?
+        || public.pss_verify(SgxRsaHash::Sha256, b"abd", &signature)?
+    {
+        return Ok(false);
+    }
+    let signature = key.pss_sign(SgxRsaHash::Sha256, b"abc")?;
+    public.pss_verify(SgxRsaHash::Sha256, b"abc", &signature)
+}
+
+fn test_rsa_oaep() -> SgxResult<bool> {
+    let ciphertext = hex(RSA_OAEP_SHA256);
+    if rsa_key()?.oaep_decrypt(SgxRsaHash::Sha256, b"", &ciphertext)? != b"abc" {
+        return Ok(false);
+    }
+
+    let sdk_key = SgxRsaPrivKey::new();
+    sdk_key.create(
+        384,
+        4,
+        &65537_u32.to_le_bytes(),
+        &le(RSA_P),
+        &le(RSA_Q),
+        &le(RSA_DMP1),
+        &le(RSA_DMQ1),
+        &le(RSA_IQMP),
+    )?;
+    let mut plaintext = [0_u8; 384];
+    let mut len = plaintext.len();
+    sdk_key.decrypt_sha256(&mut plaintext, &mut len, &ciphertext)?;
+    Ok(plaintext[..len] == b"abc"[..])
+}
+
+// A signature made by OpenSSL with the default ID, and a fresh signature.
+#[cfg(feature = "sm")]
+fn test_sm2() -> SgxResult<bool> {
+    let public = sgx_ec256_public_t {
+        gx: le_array("34c0ef209090e6009a25d309cbe557a072be88add7b9b8952310e3f567fe72cc"),
+        gy: le_array("cfb4864cf93152681fefadfd73752954687180955a5d8062d2fcde57616fba41"),
+    };
+    let signature = sgx_ec256_signature_t {
+        x: le_words("3478890bdf98886fd99f5a3632617799773e2a6c12ce4d797ceaf17036dd8d67"),
+        y: le_words("1287bc76f09a3fcde0ebaaac2f48516860327a8f142235ca500a96538fd326f5"),
+    };
+    let msg = b"message digest";
+    if !rsgx_sm2_verify_slice(SGX_SM2_DEFAULT_ID, msg, &public, &signature)?
+        || rsgx_sm2_verify_slice(SGX_SM2_DEFAULT_ID, b"message digesT", &public, &signature)?
+    {
+        return Ok(false);
+    }
+
+    let (private, public) = rsgx_sm2_create_key_pair()?;
+    let signature = rsgx_sm2_sign_slice(SGX_SM2_DEFAULT_ID, msg, &private)?;
+    rsgx_sm2_verify_slice(SGX_SM2_DEFAULT_ID, msg, &public, &signature)
+}
+
+// GB/T 32905-2016, appendix A.1
+#[cfg(feature = "sm")]
+fn test_sm3() -> SgxResult<bool> {
+    let expected = "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0";
+    Ok(rsgx_sm3_slice(b"abc")?[..] == hex(expected)[..])
+}
+
+// GB/T 32907-2016, appendix A.1, and RFC 8998, appendix A.1
+#[cfg(feature = "sm")]
+fn test_sm4() -> SgxResult<bool> {
+    let key = array("0123456789abcdeffedcba9876543210");
+    let mut block = key;
+    SgxSm4::new(&key).encrypt_block(&mut block);
+    if block[..] != hex("681edf34d206965e86b3e94f536e4246")[..] {
+        return Ok(false);
+    }
+
+    let iv = array("00001234567800000000abcd");
+    let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+    let plaintext = hex(concat!(
+        "aaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbccccccccccccccccdddddddddddddddd",
+        "eeeeeeeeeeeeeeeeffffffffffffffffeeeeeeeeeeeeeeeeaaaaaaaaaaaaaaaa"
+    ));
+    let mut ciphertext = [0_u8; 64];
+    let mut gcm = SgxSm4Gcm::new_encrypt(&key, &iv);
+    gcm.update_aad(&aad)?;
+    gcm.update(&plaintext, &mut ciphertext)?;
+    let tag = gcm.finalize()?;
+    if ciphertext[..16] != hex("17f399f08c67d5ee19d0dc9969c4bb7d")[..]
+        || tag[..] != hex("83de3541e4c2b58177e065a9bf7b62ec")[..]
+    {
+        return Ok(false);
+    }
+
+    let mut gcm = SgxSm4Gcm::new_decrypt(&key, &iv);
+    gcm.update_aad(&aad)?;
+    let mut decrypted = [0_u8; 64];
+    gcm.update(&ciphertext, &mut decrypted)?;
+    gcm.verify(&tag)?;
+    Ok(decrypted[..] == plaintext[..])
+}
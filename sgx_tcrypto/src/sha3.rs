@@ -0,0 +1,326 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! SHA-3, SHAKE and Keccak
+//!
+//! IPP in the Intel(R) SGX SDK has no SHA-3, so these are implemented in Rust on top
+//! of Keccak-f[1600] as specified in FIPS 202. The permutation has no secret-dependent
+//! branches or memory accesses.
+//!
+use super::policy::{check_algorithm, SgxAlgorithm};
+use core::mem;
+use core::ptr;
+use core::slice;
+use sgx_types::marker::ContiguousMemory;
+use sgx_types::*;
+
+pub type sgx_sha3_256_hash_t = [uint8_t; 32];
+pub type sgx_sha3_384_hash_t = [uint8_t; 48];
+pub type sgx_sha3_512_hash_t = [uint8_t; 64];
+pub type sgx_keccak256_hash_t = [uint8_t; 32];
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000_0000_0000_0001,
+    0x0000_0000_0000_8082,
+    0x8000_0000_0000_808a,
+    0x8000_0000_8000_8000,
+    0x0000_0000_0000_808b,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8009,
+    0x0000_0000_0000_008a,
+    0x0000_0000_0000_0088,
+    0x0000_0000_8000_8009,
+    0x0000_0000_8000_000a,
+    0x0000_0000_8000_808b,
+    0x8000_0000_0000_008b,
+    0x8000_0000_0000_8089,
+    0x8000_0000_0000_8003,
+    0x8000_0000_0000_8002,
+    0x8000_0000_0000_0080,
+    0x0000_0000_0000_800a,
+    0x8000_0000_8000_000a,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8080,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8008,
+];
+
+// Rotation offsets and lane order of the combined rho and pi steps.
+const RHO: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+const PI: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+fn keccak_f(a: &mut [u64; 25]) {
+    for rc in ROUND_CONSTANTS.iter() {
+        // theta
+        let mut c = [0_u64; 5];
+        for x in 0..5 {
+            c[x] = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                a[x + 5 * y] ^= d;
+            }
+        }
+
+        // rho and pi
+        let mut last = a[1];
+        for i in 0..24 {
+            let tmp = a[PI[i]];
+            a[PI[i]] = last.rotate_left(RHO[i]);
+            last = tmp;
+        }
+
+        // chi
+        for y in 0..5 {
+            let row = [
+                a[5 * y],
+                a[5 * y + 1],
+                a[5 * y + 2],
+                a[5 * y + 3],
+                a[5 * y + 4],
+            ];
+            for x in 0..5 {
+                a[5 * y + x] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+
+        // iota
+        a[0] ^= rc;
+    }
+}
+
+// A Keccak sponge with a rate of `rate` bytes and the given domain padding byte.
+#[derive(Clone)]
+struct Sponge {
+    state: [u64; 25],
+    rate: usize,
+    pad: u8,
+    // Bytes absorbed into, or squeezed from, the current block.
+    pos: usize,
+}
+
+impl Sponge {
+    fn new(rate: usize, pad: u8) -> Sponge {
+        Sponge {
+            state: [0; 25],
+            rate,
+            pad,
+            pos: 0,
+        }
+    }
+
+    fn xor_byte(&mut self, i: usize, b: u8) {
+        self.state[i / 8] ^= u64::from(b) << (8 * (i % 8));
+    }
+
+    fn byte(&self, i: usize) -> u8 {
+        (self.state[i / 8] >> (8 * (i % 8))) as u8
+    }
+
+    fn absorb(&mut self, data: &[u8]) {
+        for &b in data {
+            self.xor_byte(self.pos, b);
+            self.pos += 1;
+            if self.pos == self.rate {
+                keccak_f(&mut self.state);
+                self.pos = 0;
+            }
+        }
+    }
+
+    // Pads the message and switches to squeezing.
+    fn finish(&mut self) {
+        self.xor_byte(self.pos, self.pad);
+        self.xor_byte(self.rate - 1, 0x80);
+        keccak_f(&mut self.state);
+        self.pos = 0;
+    }
+
+    fn squeeze(&mut self, out: &mut [u8]) {
+        for b in out {
+            if self.pos == self.rate {
+                keccak_f(&mut self.state);
+                self.pos = 0;
+            }
+            *b = self.byte(self.pos);
+            self.pos += 1;
+        }
+    }
+}
+
+impl Drop for Sponge {
+    fn drop(&mut self) {
+        unsafe { ptr::write_volatile(&mut self.state, [0; 25]) };
+    }
+}
+
+fn as_bytes<T: Copy + ContiguousMemory>(src: &[T]) -> &[u8] {
+    unsafe { slice::from_raw_parts(src.as_ptr() as *const u8, mem::size_of_val(src)) }
+}
+
+macro_rules! impl_sha3 {
+    ($(#[$doc:meta])* $name:ident, $hash:ident, $len:expr, $pad:expr, $msg:ident, $slice:ident) => {
+        $(#[$doc])*
+        #[derive(Clone)]
+        pub struct $name(Sponge);
+
+        impl $name {
+            pub fn new() -> $name {
+                $name(Sponge::new(200 - 2 * $len, $pad))
+            }
+
+            pub fn update_msg<T: Copy + ContiguousMemory>(&mut self, src: &T) {
+                self.update_slice(core::slice::from_ref(src))
+            }
+
+            pub fn update_slice<T: Copy + ContiguousMemory>(&mut self, src: &[T]) {
+                self.0.absorb(as_bytes(src))
+            }
+
+            pub fn finalize(mut self) -> $hash {
+                let mut hash = [0_u8; $len];
+                self.0.finish();
+                self.0.squeeze(&mut hash);
+                hash
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        pub fn $msg<T: Copy + ContiguousMemory>(src: &T) -> SgxResult<$hash> {
+            $slice(core::slice::from_ref(src))
+        }
+
+        pub fn $slice<T: Copy + ContiguousMemory>(src: &[T]) -> SgxResult<$hash> {
+            check_algorithm(SgxAlgorithm::Sha3)?;
+            let mut h = $name::new();
+            h.update_slice(src);
+            Ok(h.finalize())
+        }
+    };
+}
+
+impl_sha3!(
+    /// An incremental SHA3-256 hash.
+    SgxSha3_256,
+    sgx_sha3_256_hash_t,
+    32,
+    0x06,
+    rsgx_sha3_256_msg,
+    rsgx_sha3_256_slice
+);
+impl_sha3!(
+    /// An incremental SHA3-384 hash.
+    SgxSha3_384,
+    sgx_sha3_384_hash_t,
+    48,
+    0x06,
+    rsgx_sha3_384_msg,
+    rsgx_sha3_384_slice
+);
+impl_sha3!(
+    /// An incremental SHA3-512 hash.
+    SgxSha3_512,
+    sgx_sha3_512_hash_t,
+    64,
+    0x06,
+    rsgx_sha3_512_msg,
+    rsgx_sha3_512_slice
+);
+impl_sha3!(
+    /// An incremental Keccak-256 hash, the pre-standard padding of SHA3-256 used
+    /// by Ethereum.
+    SgxKeccak256,
+    sgx_keccak256_hash_t,
+    32,
+    0x01,
+    rsgx_keccak256_msg,
+    rsgx_keccak256_slice
+);
+
+macro_rules! impl_shake {
+    ($(#[$doc:meta])* $name:ident, $security:expr, $oneshot:ident) => {
+        $(#[$doc])*
+        #[derive(Clone)]
+        pub struct $name(Sponge);
+
+        impl $name {
+            pub fn new() -> $name {
+                $name(Sponge::new(200 - 2 * $security, 0x1f))
+            }
+
+            pub fn update_slice<T: Copy + ContiguousMemory>(&mut self, src: &[T]) {
+                self.0.absorb(as_bytes(src))
+            }
+
+            /// Ends the input and returns a reader for output of any length.
+            pub fn finalize_xof(mut self) -> SgxShakeReader {
+                self.0.finish();
+                SgxShakeReader(self.0)
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        /// Fills `out` with the output of the function over `src`.
+        pub fn $oneshot<T: Copy + ContiguousMemory>(src: &[T], out: &mut [u8]) -> SgxError {
+            check_algorithm(SgxAlgorithm::Sha3)?;
+            let mut h = $name::new();
+            h.update_slice(src);
+            h.finalize_xof().squeeze(out);
+            Ok(())
+        }
+    };
+}
+
+impl_shake!(
+    /// An incremental SHAKE128 extendable-output function.
+    SgxShake128,
+    16,
+    rsgx_shake128
+);
+impl_shake!(
+    /// An incremental SHAKE256 extendable-output function.
+    SgxShake256,
+    32,
+    rsgx_shake256
+);
+
+/// The output stream of a SHAKE function.
+pub struct SgxShakeReader(Sponge);
+
+impl SgxShakeReader {
+    /// Reads the next `out.len()` bytes of output.
+    pub fn squeeze(&mut self, out: &mut [u8]) {
+        self.0.squeeze(out)
+    }
+}
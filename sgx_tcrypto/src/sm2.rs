@@ -0,0 +1,597 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! SM2 signatures and key exchange
+//!
+//! The elliptic curve algorithms of GB/T 32918-2016 over the recommended SM2 curve,
+//! with SM3 as the hash. Keys and signatures use the 256-bit types of the SDK and are
+//! little-endian, as for P-256. Scalar multiplications run in constant time.
+//!
+use super::modular::*;
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::secret::{rsgx_ct_eq, rsgx_zeroize};
+use super::sm3::{sgx_sm3_hash_t, SgxSm3};
+use super::weierstrass::*;
+use core::slice;
+use sgx_types::marker::ContiguousMemory;
+use sgx_types::*;
+
+/// The signer ID used when the parties have not agreed on another one.
+pub const SGX_SM2_DEFAULT_ID: &[u8] = b"1234567812345678";
+
+// ENTL, the bit length of the ID, is a 16-bit field.
+const SM2_MAX_ID_LEN: usize = 0xffff / 8;
+
+fn slice_as_bytes<T: Copy + ContiguousMemory>(src: &T) -> &[T] {
+    slice::from_ref(src)
+}
+
+fn be_bytes(a: &Limbs<4>) -> [u8; 32] {
+    let mut out = [0_u8; 32];
+    to_be_bytes(a, &mut out);
+    out
+}
+
+fn public_point(public: &sgx_ec256_public_t) -> Option<Point<4>> {
+    SM2.point_from_affine(&from_le_bytes(&public.gx), &from_le_bytes(&public.gy))
+}
+
+fn public_from_point(pt: &Point<4>) -> SgxResult<sgx_ec256_public_t> {
+    let (x, y) = SM2
+        .to_affine(pt)
+        .ok_or(sgx_status_t::SGX_ERROR_UNEXPECTED)?;
+    let mut public = sgx_ec256_public_t::default();
+    to_le_bytes(&x, &mut public.gx);
+    to_le_bytes(&y, &mut public.gy);
+    Ok(public)
+}
+
+fn words_from(a: &Limbs<4>, out: &mut [u32; 8]) {
+    for (i, w) in out.iter_mut().enumerate() {
+        *w = (a[i / 2] >> ((i % 2) * 32)) as u32;
+    }
+}
+
+fn limbs_from_words(words: &[u32; 8]) -> Limbs<4> {
+    let mut r = [0_u64; 4];
+    for (i, w) in words.iter().enumerate() {
+        r[i / 2] |= (*w as u64) << ((i % 2) * 32);
+    }
+    r
+}
+
+// The private key d, which must lie in [1, n - 2] so that 1 + d is invertible.
+fn private_scalar(private: &sgx_ec256_private_t) -> SgxResult<Limbs<4>> {
+    let mut d = from_le_bytes::<4>(&private.r);
+    let n = &SM2.n;
+    if !SM2.is_valid_scalar(&d) || is_zero(&n.add(&n.mont_form(&d), &n.one())) == 1 {
+        wipe(&mut d);
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    Ok(d)
+}
+
+///
+/// rsgx_sm2_create_key_pair generates a private/public key pair on the SM2 curve.
+///
+/// # Errors
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// The random number generator failed.
+///
+pub fn rsgx_sm2_create_key_pair() -> SgxResult<(sgx_ec256_private_t, sgx_ec256_public_t)> {
+    loop {
+        let mut d = SM2.random_scalar()?;
+        let mut private = sgx_ec256_private_t::default();
+        to_le_bytes(&d, &mut private.r);
+        wipe(&mut d);
+        match rsgx_sm2_pub_from_priv(&private) {
+            Ok(public) => return Ok((private, public)),
+            // n - 1 is not a valid SM2 private key.
+            Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+///
+/// rsgx_sm2_pub_from_priv computes the public key of an SM2 private key.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The private key is not in [1, n - 2].
+///
+pub fn rsgx_sm2_pub_from_priv(private: &sgx_ec256_private_t) -> SgxResult<sgx_ec256_public_t> {
+    let mut d = private_scalar(private)?;
+    let mut pt = SM2.mul(&SM2.generator(), &d);
+    wipe(&mut d);
+    let public = public_from_point(&pt);
+    pt.wipe();
+    public
+}
+
+///
+/// rsgx_sm2_check_point checks whether a public key is a valid point on the SM2 curve.
+///
+pub fn rsgx_sm2_check_point(public: &sgx_ec256_public_t) -> SgxResult<bool> {
+    Ok(public_point(public).is_some())
+}
+
+///
+/// rsgx_sm2_compute_za computes Z, the hash of a party's ID, the curve and its public
+/// key, which is hashed in front of the message when signing.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The ID is longer than 8191 bytes.
+///
+pub fn rsgx_sm2_compute_za(id: &[u8], public: &sgx_ec256_public_t) -> SgxResult<sgx_sm3_hash_t> {
+    if id.len() > SM2_MAX_ID_LEN {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let (a, _) = sub_limbs(&SM2.p.m, &[3, 0, 0, 0]);
+    let mut x = public.gx;
+    let mut y = public.gy;
+    x.reverse();
+    y.reverse();
+
+    let mut h = SgxSm3::new();
+    h.update_slice(&((id.len() * 8) as u16).to_be_bytes());
+    h.update_slice(id);
+    h.update_slice(&be_bytes(&a));
+    h.update_slice(&be_bytes(&SM2.b));
+    h.update_slice(&be_bytes(&SM2.gx));
+    h.update_slice(&be_bytes(&SM2.gy));
+    h.update_slice(&x);
+    h.update_slice(&y);
+    Ok(h.finalize())
+}
+
+///
+/// rsgx_sm2_sign_hash signs e, the SM3 hash of Z followed by the message.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The private key is not in [1, n - 2].
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// The random number generator failed.
+///
+pub fn rsgx_sm2_sign_hash(
+    hash: &sgx_sm3_hash_t,
+    private: &sgx_ec256_private_t,
+) -> SgxResult<sgx_ec256_signature_t> {
+    check_algorithm(SgxAlgorithm::Sm2)?;
+    let n = &SM2.n;
+    let mut d_m = n.mont_form(&private_scalar(private)?);
+    let mut d1_inv = n.invert(&n.add(&d_m, &n.one()));
+    let e_m = n.reduce_mont_form(&from_be_bytes::<4>(hash));
+    let g = SM2.generator();
+    let result = loop {
+        let mut k = match SM2.random_scalar() {
+            Ok(k) => k,
+            Err(e) => break Err(e),
+        };
+        let mut kg = SM2.mul(&g, &k);
+        let x1 = SM2.to_affine(&kg).map(|(x, _)| x);
+        kg.wipe();
+        let x1 = match x1 {
+            Some(x1) => x1,
+            None => continue,
+        };
+        // r = e + x1, and r + k must not be n.
+        let r_m = n.add(&e_m, &n.reduce_mont_form(&x1));
+        let mut k_m = n.mont_form(&k);
+        if is_zero(&r_m) == 1 || is_zero(&n.add(&r_m, &k_m)) == 1 {
+            continue;
+        }
+        // s = (1 + d)^-1 (k - r d)
+        let s = n.normal_form(&n.mul(&d1_inv, &n.sub(&k_m, &n.mul(&r_m, &d_m))));
+        wipe(&mut k);
+        wipe(&mut k_m);
+        if is_zero(&s) == 1 {
+            continue;
+        }
+        break Ok((n.normal_form(&r_m), s));
+    };
+    wipe(&mut d_m);
+    wipe(&mut d1_inv);
+    let (r, s) = result?;
+
+    let mut signature = sgx_ec256_signature_t::default();
+    words_from(&r, &mut signature.x);
+    words_from(&s, &mut signature.y);
+    Ok(signature)
+}
+
+///
+/// rsgx_sm2_sign_slice computes an SM2 signature over data for the signer ID.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The private key is not in [1, n - 2], or the ID is longer than 8191 bytes.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// The random number generator failed.
+///
+pub fn rsgx_sm2_sign_slice<T>(
+    id: &[u8],
+    data: &[T],
+    private: &sgx_ec256_private_t,
+) -> SgxResult<sgx_ec256_signature_t>
+where
+    T: Copy + ContiguousMemory,
+{
+    let public = rsgx_sm2_pub_from_priv(private)?;
+    let mut h = SgxSm3::new();
+    h.update_slice(&rsgx_sm2_compute_za(id, &public)?);
+    h.update_slice(data);
+    rsgx_sm2_sign_hash(&h.finalize(), private)
+}
+
+///
+/// rsgx_sm2_sign_msg computes an SM2 signature over data for the signer ID.
+///
+pub fn rsgx_sm2_sign_msg<T>(
+    id: &[u8],
+    data: &T,
+    private: &sgx_ec256_private_t,
+) -> SgxResult<sgx_ec256_signature_t>
+where
+    T: Copy + ContiguousMemory,
+{
+    rsgx_sm2_sign_slice(id, slice_as_bytes(data), private)
+}
+
+///
+/// rsgx_sm2_verify_hash verifies an SM2 signature of e, the SM3 hash of Z followed by
+/// the message.
+///
+/// # Return value
+///
+/// **true**
+///
+/// The signature is valid.
+///
+/// **false**
+///
+/// The signature or the public key is invalid.
+///
+pub fn rsgx_sm2_verify_hash(
+    hash: &sgx_sm3_hash_t,
+    public: &sgx_ec256_public_t,
+    signature: &sgx_ec256_signature_t,
+) -> SgxResult<bool> {
+    check_algorithm(SgxAlgorithm::Sm2)?;
+    let q = match public_point(public) {
+        Some(q) => q,
+        None => return Ok(false),
+    };
+    let r = limbs_from_words(&signature.x);
+    let s = limbs_from_words(&signature.y);
+    if !SM2.is_valid_scalar(&r) || !SM2.is_valid_scalar(&s) {
+        return Ok(false);
+    }
+    let n = &SM2.n;
+    let t = n.normal_form(&n.add(&n.mont_form(&r), &n.mont_form(&s)));
+    if is_zero(&t) == 1 {
+        return Ok(false);
+    }
+    let pt = SM2.add(&SM2.mul(&SM2.generator(), &s), &SM2.mul(&q, &t));
+    let x1 = match SM2.to_affine(&pt) {
+        Some((x1, _)) => x1,
+        None => return Ok(false),
+    };
+    let e_m = n.reduce_mont_form(&from_be_bytes::<4>(hash));
+    let expected = n.normal_form(&n.add(&e_m, &n.reduce_mont_form(&x1)));
+    Ok(ct_eq(&expected, &r) == 1)
+}
+
+///
+/// rsgx_sm2_verify_slice verifies an SM2 signature over data for the signer ID.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The ID is longer than 8191 bytes.
+///
+pub fn rsgx_sm2_verify_slice<T>(
+    id: &[u8],
+    data: &[T],
+    public: &sgx_ec256_public_t,
+    signature: &sgx_ec256_signature_t,
+) -> SgxResult<bool>
+where
+    T: Copy + ContiguousMemory,
+{
+    let mut h = SgxSm3::new();
+    h.update_slice(&rsgx_sm2_compute_za(id, public)?);
+    h.update_slice(data);
+    rsgx_sm2_verify_hash(&h.finalize(), public, signature)
+}
+
+///
+/// rsgx_sm2_verify_msg verifies an SM2 signature over data for the signer ID.
+///
+pub fn rsgx_sm2_verify_msg<T>(
+    id: &[u8],
+    data: &T,
+    public: &sgx_ec256_public_t,
+    signature: &sgx_ec256_signature_t,
+) -> SgxResult<bool>
+where
+    T: Copy + ContiguousMemory,
+{
+    rsgx_sm2_verify_slice(id, slice_as_bytes(data), public, signature)
+}
+
+///
+/// The side a party takes in an SM2 key exchange.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SgxSm2Role {
+    /// Party A, which sends its ephemeral key first.
+    Initiator,
+    /// Party B.
+    Responder,
+}
+
+///
+/// One side of the SM2 key exchange protocol.
+///
+/// # Description
+///
+/// Each party creates an SgxSm2KeyExchange with its static key pair and ID, and sends
+/// its ephemeral public key to the other. compute_shared_key then derives the shared
+/// key from the peer's ID, static public key and ephemeral public key.
+///
+/// The optional key confirmation of the protocol is returned as SgxSm2Confirmation:
+/// each party sends its tag to the other and checks the tag it receives with verify.
+/// The responder sends its tag first, with its ephemeral key.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sgx_tcrypto::*;
+///
+/// let (a_private, a_public) = rsgx_sm2_create_key_pair().unwrap();
+/// let (b_private, b_public) = rsgx_sm2_create_key_pair().unwrap();
+/// let a = SgxSm2KeyExchange::new(SgxSm2Role::Initiator, b"alice", &a_private).unwrap();
+/// let b = SgxSm2KeyExchange::new(SgxSm2Role::Responder, b"bob", &b_private).unwrap();
+/// let (a_ephemeral, b_ephemeral) = (*a.ephemeral_public(), *b.ephemeral_public());
+///
+/// let mut a_key = [0_u8; 16];
+/// let mut b_key = [0_u8; 16];
+/// let b_confirm = b.compute_shared_key(b"alice", &a_public, &a_ephemeral, &mut b_key).unwrap();
+/// let a_confirm = a.compute_shared_key(b"bob", &b_public, &b_ephemeral, &mut a_key).unwrap();
+/// a_confirm.verify(b_confirm.tag()).unwrap();
+/// b_confirm.verify(a_confirm.tag()).unwrap();
+/// ```
+///
+pub struct SgxSm2KeyExchange {
+    role: SgxSm2Role,
+    d: Limbs<4>,
+    z: sgx_sm3_hash_t,
+    r: Limbs<4>,
+    ephemeral: sgx_ec256_public_t,
+}
+
+// 2^w + (x mod 2^w) with w = 127, half the bit length of n rounded up, minus one.
+fn truncated_x(x: &Limbs<4>) -> Limbs<4> {
+    [x[0], (x[1] & (u64::MAX >> 1)) | (1 << 63), 0, 0]
+}
+
+impl SgxSm2KeyExchange {
+    ///
+    /// Starts a key exchange with a static key pair and generates the ephemeral key.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The private key is not in [1, n - 2], or the ID is longer than 8191 bytes.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The random number generator failed.
+    ///
+    pub fn new(
+        role: SgxSm2Role,
+        id: &[u8],
+        private: &sgx_ec256_private_t,
+    ) -> SgxResult<SgxSm2KeyExchange> {
+        check_algorithm(SgxAlgorithm::Sm2)?;
+        let public = rsgx_sm2_pub_from_priv(private)?;
+        let z = rsgx_sm2_compute_za(id, &public)?;
+        let mut r = SM2.random_scalar()?;
+        let mut pt = SM2.mul(&SM2.generator(), &r);
+        let ephemeral = public_from_point(&pt);
+        pt.wipe();
+        let ephemeral = match ephemeral {
+            Ok(ephemeral) => ephemeral,
+            Err(e) => {
+                wipe(&mut r);
+                return Err(e);
+            }
+        };
+        Ok(SgxSm2KeyExchange {
+            role,
+            d: private_scalar(private)?,
+            z,
+            r,
+            ephemeral,
+        })
+    }
+
+    /// The ephemeral public key to send to the peer.
+    pub fn ephemeral_public(&self) -> &sgx_ec256_public_t {
+        &self.ephemeral
+    }
+
+    ///
+    /// Completes the exchange and fills key with the shared key.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// key is empty, the peer's ID is longer than 8191 bytes, or a public key of the
+    /// peer is not on the curve.
+    ///
+    pub fn compute_shared_key(
+        mut self,
+        peer_id: &[u8],
+        peer_public: &sgx_ec256_public_t,
+        peer_ephemeral: &sgx_ec256_public_t,
+        key: &mut [u8],
+    ) -> SgxResult<SgxSm2Confirmation> {
+        if key.is_empty() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let peer_z = rsgx_sm2_compute_za(peer_id, peer_public)?;
+        let p = public_point(peer_public).ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+        let r = public_point(peer_ephemeral).ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+
+        // t = d + x r, and U = t (P + x' R).
+        let n = &SM2.n;
+        let own_x = truncated_x(&from_le_bytes(&self.ephemeral.gx));
+        let peer_x = truncated_x(&from_le_bytes(&peer_ephemeral.gx));
+        let mut t_m = n.add(
+            &n.mont_form(&self.d),
+            &n.mul(&n.mont_form(&own_x), &n.mont_form(&self.r)),
+        );
+        let mut t = n.normal_form(&t_m);
+        let mut u = SM2.mul(&SM2.add(&p, &SM2.mul(&r, &peer_x)), &t);
+        wipe(&mut t_m);
+        wipe(&mut t);
+        let affine = SM2.to_affine(&u);
+        u.wipe();
+        let (mut xu, mut yu) = affine.ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+
+        let (za, zb, ra, rb) = match self.role {
+            SgxSm2Role::Initiator => (self.z, peer_z, &self.ephemeral, peer_ephemeral),
+            SgxSm2Role::Responder => (peer_z, self.z, peer_ephemeral, &self.ephemeral),
+        };
+        let mut xu_bytes = be_bytes(&xu);
+        let mut yu_bytes = be_bytes(&yu);
+        wipe(&mut xu);
+        wipe(&mut yu);
+        kdf(&[&xu_bytes, &yu_bytes, &za, &zb], key);
+
+        let mut h = SgxSm3::new();
+        h.update_slice(&xu_bytes);
+        h.update_slice(&za);
+        h.update_slice(&zb);
+        for pt in [ra, rb].iter() {
+            let mut x = pt.gx;
+            let mut y = pt.gy;
+            x.reverse();
+            y.reverse();
+            h.update_slice(&x);
+            h.update_slice(&y);
+        }
+        let inner = h.finalize();
+        let confirm = |prefix: u8| {
+            let mut h = SgxSm3::new();
+            h.update_slice(&[prefix]);
+            h.update_slice(&yu_bytes);
+            h.update_slice(&inner);
+            h.finalize()
+        };
+        let (a_tag, b_tag) = (confirm(0x03), confirm(0x02));
+        rsgx_zeroize(&mut xu_bytes);
+        rsgx_zeroize(&mut yu_bytes);
+        wipe(&mut self.r);
+
+        Ok(match self.role {
+            SgxSm2Role::Initiator => SgxSm2Confirmation {
+                tag: a_tag,
+                expected: b_tag,
+            },
+            SgxSm2Role::Responder => SgxSm2Confirmation {
+                tag: b_tag,
+                expected: a_tag,
+            },
+        })
+    }
+}
+
+impl Drop for SgxSm2KeyExchange {
+    fn drop(&mut self) {
+        wipe(&mut self.d);
+        wipe(&mut self.r);
+    }
+}
+
+///
+/// The key confirmation values of a completed SM2 key exchange.
+///
+pub struct SgxSm2Confirmation {
+    tag: sgx_sm3_hash_t,
+    expected: sgx_sm3_hash_t,
+}
+
+impl SgxSm2Confirmation {
+    /// The tag to send to the peer: S_B for the responder, S_A for the initiator.
+    pub fn tag(&self) -> &sgx_sm3_hash_t {
+        &self.tag
+    }
+
+    ///
+    /// Checks the tag received from the peer.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The peer did not derive the same key.
+    ///
+    pub fn verify(&self, peer_tag: &sgx_sm3_hash_t) -> SgxError {
+        if rsgx_ct_eq(&self.expected, peer_tag) {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+        }
+    }
+}
+
+// The key derivation function of SM2: SM3(Z || counter) for counters from 1.
+fn kdf(z: &[&[u8]], out: &mut [u8]) {
+    for (i, chunk) in out.chunks_mut(32).enumerate() {
+        let mut h = SgxSm3::new();
+        for part in z.iter() {
+            h.update_slice(part);
+        }
+        h.update_slice(&(i as u32 + 1).to_be_bytes());
+        let mut block = h.finalize();
+        chunk.copy_from_slice(&block[..chunk.len()]);
+        rsgx_zeroize(&mut block);
+    }
+}
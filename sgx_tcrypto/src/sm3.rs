@@ -0,0 +1,185 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! SM3 hash
+//!
+//! The 256-bit hash of GB/T 32905-2016, used by SM2 and by regulated deployments in
+//! place of SHA-256.
+//!
+use super::policy::{check_algorithm, SgxAlgorithm};
+use core::mem;
+use core::ptr;
+use core::slice;
+use sgx_types::marker::ContiguousMemory;
+use sgx_types::*;
+
+pub type sgx_sm3_hash_t = [uint8_t; 32];
+
+const IV: [u32; 8] = [
+    0x7380_166f,
+    0x4914_b2b9,
+    0x1724_42d7,
+    0xda8a_0600,
+    0xa96f_30bc,
+    0x1631_38aa,
+    0xe38d_ee4d,
+    0xb0fb_0e4e,
+];
+
+fn p0(x: u32) -> u32 {
+    x ^ x.rotate_left(9) ^ x.rotate_left(17)
+}
+
+fn p1(x: u32) -> u32 {
+    x ^ x.rotate_left(15) ^ x.rotate_left(23)
+}
+
+fn compress(v: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0_u32; 68];
+    for (i, word) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for j in 16..68 {
+        w[j] = p1(w[j - 16] ^ w[j - 9] ^ w[j - 3].rotate_left(15))
+            ^ w[j - 13].rotate_left(7)
+            ^ w[j - 6];
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *v;
+    for j in 0..64 {
+        let t: u32 = if j < 16 { 0x79cc_4519 } else { 0x7a87_9d8a };
+        let ss1 = a
+            .rotate_left(12)
+            .wrapping_add(e)
+            .wrapping_add(t.rotate_left(j as u32 % 32))
+            .rotate_left(7);
+        let ss2 = ss1 ^ a.rotate_left(12);
+        let (ff, gg) = if j < 16 {
+            (a ^ b ^ c, e ^ f ^ g)
+        } else {
+            ((a & b) | (a & c) | (b & c), (e & f) | (!e & g))
+        };
+        let tt1 = ff
+            .wrapping_add(d)
+            .wrapping_add(ss2)
+            .wrapping_add(w[j] ^ w[j + 4]);
+        let tt2 = gg.wrapping_add(h).wrapping_add(ss1).wrapping_add(w[j]);
+        d = c;
+        c = b.rotate_left(9);
+        b = a;
+        a = tt1;
+        h = g;
+        g = f.rotate_left(19);
+        f = e;
+        e = p0(tt2);
+    }
+    for (x, y) in v.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+        *x ^= y;
+    }
+    unsafe { ptr::write_volatile(&mut w, [0; 68]) };
+}
+
+/// An incremental SM3 hash.
+#[derive(Clone)]
+pub struct SgxSm3 {
+    v: [u32; 8],
+    buf: [u8; 64],
+    used: usize,
+    len: u64,
+}
+
+impl SgxSm3 {
+    pub fn new() -> SgxSm3 {
+        SgxSm3 {
+            v: IV,
+            buf: [0; 64],
+            used: 0,
+            len: 0,
+        }
+    }
+
+    pub fn update_msg<T: Copy + ContiguousMemory>(&mut self, src: &T) {
+        self.update_slice(core::slice::from_ref(src))
+    }
+
+    pub fn update_slice<T: Copy + ContiguousMemory>(&mut self, src: &[T]) {
+        let mut data =
+            unsafe { slice::from_raw_parts(src.as_ptr() as *const u8, mem::size_of_val(src)) };
+        self.len = self.len.wrapping_add(data.len() as u64);
+        if self.used > 0 {
+            let n = core::cmp::min(64 - self.used, data.len());
+            self.buf[self.used..self.used + n].copy_from_slice(&data[..n]);
+            self.used += n;
+            data = &data[n..];
+            if self.used < 64 {
+                return;
+            }
+            let buf = self.buf;
+            compress(&mut self.v, &buf);
+            self.used = 0;
+        }
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            compress(&mut self.v, block);
+        }
+        let rest = blocks.remainder();
+        self.buf[..rest.len()].copy_from_slice(rest);
+        self.used = rest.len();
+    }
+
+    pub fn finalize(mut self) -> sgx_sm3_hash_t {
+        let bits = self.len.wrapping_mul(8);
+        let mut pad = [0_u8; 72];
+        pad[0] = 0x80;
+        let zeros = (119 - self.used) % 64;
+        pad[1 + zeros..9 + zeros].copy_from_slice(&bits.to_be_bytes());
+        self.update_slice(&pad[..9 + zeros]);
+
+        let mut hash = [0_u8; 32];
+        for (out, word) in hash.chunks_mut(4).zip(self.v.iter()) {
+            out.copy_from_slice(&word.to_be_bytes());
+        }
+        hash
+    }
+}
+
+impl Default for SgxSm3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SgxSm3 {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::write_volatile(&mut self.v, [0; 8]);
+            ptr::write_volatile(&mut self.buf, [0; 64]);
+        }
+    }
+}
+
+pub fn rsgx_sm3_msg<T: Copy + ContiguousMemory>(src: &T) -> SgxResult<sgx_sm3_hash_t> {
+    rsgx_sm3_slice(core::slice::from_ref(src))
+}
+
+pub fn rsgx_sm3_slice<T: Copy + ContiguousMemory>(src: &[T]) -> SgxResult<sgx_sm3_hash_t> {
+    check_algorithm(SgxAlgorithm::Sm3)?;
+    let mut h = SgxSm3::new();
+    h.update_slice(src);
+    Ok(h.finalize())
+}
@@ -0,0 +1,460 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! SM4 block cipher and SM4-GCM
+//!
+//! The 128-bit block cipher of GB/T 32907-2016 and its GCM mode of RFC 8998. The SM4
+//! S-box is affine equivalent to inversion in GF(2^8), as is the AES S-box, so it is
+//! computed with AESKEYGENASSIST between two affine maps instead of a table lookup
+//! indexed by secret data. GHASH is computed bit by bit without branches.
+//!
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::secret::{rsgx_ct_eq, rsgx_zeroize};
+use core::arch::x86_64::*;
+use core::ptr;
+use sgx_types::*;
+
+pub type sgx_sm4_key_t = [uint8_t; 16];
+pub type sgx_sm4_block_t = [uint8_t; 16];
+pub type sgx_sm4_gcm_iv_t = [uint8_t; 12];
+pub type sgx_sm4_gcm_tag_t = [uint8_t; 16];
+
+// The most plaintext one key and IV may protect, as for AES-GCM.
+const GCM_MAX_DATA_LEN: u64 = (1 << 36) - 32;
+
+const FK: [u32; 4] = [0xa3b1_bac6, 0x56aa_3350, 0x677d_9197, 0xb270_22dc];
+
+// The SM4 S-box is post(aes_sbox(pre(x))). The affine maps combine the SM4 affine
+// transform with the isomorphism from the SM4 field GF(2)[x]/(x^8+x^7+x^6+x^5+x^4+x^2+1)
+// to the AES field, and the inverse of the AES affine transform.
+const PRE_CONST: u32 = 0x3e;
+const PRE_COLUMNS: [u32; 8] = [0x8c, 0x30, 0x85, 0x9f, 0xdc, 0x2e, 0xc5, 0x08];
+const POST_CONST: u32 = 0x6c;
+const POST_COLUMNS: [u32; 8] = [0xb8, 0xca, 0x3e, 0x67, 0xe0, 0x50, 0x9d, 0xc0];
+
+// Applies an affine map of GF(2)^8 to each byte of x. Each bit is spread to a whole
+// byte lane by a multiplication, which cannot carry between lanes.
+fn affine(x: u32, columns: &[u32; 8], constant: u32) -> u32 {
+    let mut y = constant * 0x0101_0101;
+    for (j, column) in columns.iter().enumerate() {
+        y ^= ((x >> j) & 0x0101_0101) * column;
+    }
+    y
+}
+
+// AESKEYGENASSIST applies the AES S-box to the second word of its input.
+#[target_feature(enable = "aes")]
+unsafe fn aes_sub_word(w: u32) -> u32 {
+    let x = _mm_set_epi32(0, 0, w as i32, 0);
+    _mm_cvtsi128_si32(_mm_aeskeygenassist_si128(x, 0)) as u32
+}
+
+// The nonlinear transform tau: the S-box on each byte.
+fn tau(x: u32) -> u32 {
+    let y = unsafe { aes_sub_word(affine(x, &PRE_COLUMNS, PRE_CONST)) };
+    affine(y, &POST_COLUMNS, POST_CONST)
+}
+
+fn round_function(x: u32) -> u32 {
+    let b = tau(x);
+    b ^ b.rotate_left(2) ^ b.rotate_left(10) ^ b.rotate_left(18) ^ b.rotate_left(24)
+}
+
+fn key_function(x: u32) -> u32 {
+    let b = tau(x);
+    b ^ b.rotate_left(13) ^ b.rotate_left(23)
+}
+
+fn load_words(block: &[u8; 16]) -> [u32; 4] {
+    let mut x = [0_u32; 4];
+    for (i, word) in block.chunks(4).enumerate() {
+        x[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    x
+}
+
+///
+/// The SM4 block cipher.
+///
+/// # Description
+///
+/// SgxSm4 encrypts and decrypts single blocks, to build modes the library does not
+/// provide. SgxSm4Gcm should be used to protect messages.
+///
+pub struct SgxSm4 {
+    rk: [u32; 32],
+}
+
+impl SgxSm4 {
+    pub fn new(key: &sgx_sm4_key_t) -> SgxSm4 {
+        let mut k = load_words(key);
+        for (w, fk) in k.iter_mut().zip(FK.iter()) {
+            *w ^= fk;
+        }
+        let mut rk = [0_u32; 32];
+        for i in 0..32 {
+            // CK_i has bytes (4i + j) * 7 mod 256.
+            let ck = (0..4).fold(0_u32, |ck, j| (ck << 8) | (((4 * i + j) * 7) % 256) as u32);
+            rk[i] = k[i % 4] ^ key_function(k[(i + 1) % 4] ^ k[(i + 2) % 4] ^ k[(i + 3) % 4] ^ ck);
+            k[i % 4] = rk[i];
+        }
+        unsafe { ptr::write_volatile(&mut k, [0; 4]) };
+        SgxSm4 { rk }
+    }
+
+    pub fn encrypt_block(&self, block: &mut sgx_sm4_block_t) {
+        self.crypt(block, false)
+    }
+
+    pub fn decrypt_block(&self, block: &mut sgx_sm4_block_t) {
+        self.crypt(block, true)
+    }
+
+    fn crypt(&self, block: &mut sgx_sm4_block_t, reverse: bool) {
+        let mut x = load_words(block);
+        for i in 0..32 {
+            let rk = if reverse { self.rk[31 - i] } else { self.rk[i] };
+            x[i % 4] ^= round_function(x[(i + 1) % 4] ^ x[(i + 2) % 4] ^ x[(i + 3) % 4] ^ rk);
+        }
+        for (i, out) in block.chunks_mut(4).enumerate() {
+            out.copy_from_slice(&x[3 - i].to_be_bytes());
+        }
+        unsafe { ptr::write_volatile(&mut x, [0; 4]) };
+    }
+}
+
+impl Drop for SgxSm4 {
+    fn drop(&mut self) {
+        unsafe { ptr::write_volatile(&mut self.rk, [0; 32]) };
+    }
+}
+
+// The GCM reduction constant. Blocks are big-endian, and their bits are the
+// coefficients of x^0 to x^127 from the most significant bit down.
+const GCM_R: u128 = 0xe1 << 120;
+
+// GHASH over a stream of bytes that is zero-padded to whole blocks when pad is called.
+struct Ghash {
+    h: u128,
+    acc: u128,
+    buf: [u8; 16],
+    used: usize,
+}
+
+impl Ghash {
+    fn new(h: &[u8; 16]) -> Ghash {
+        Ghash {
+            h: u128::from_be_bytes(*h),
+            acc: 0,
+            buf: [0; 16],
+            used: 0,
+        }
+    }
+
+    // acc = (acc + x) * h, one bit of the product at a time without branches.
+    fn block(&mut self, x: &[u8; 16]) {
+        let a = self.acc ^ u128::from_be_bytes(*x);
+        let mut v = self.h;
+        let mut z = 0_u128;
+        for i in (0..128).rev() {
+            z ^= v & 0_u128.wrapping_sub((a >> i) & 1);
+            v = (v >> 1) ^ (GCM_R & 0_u128.wrapping_sub(v & 1));
+        }
+        self.acc = z;
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        if self.used > 0 {
+            let n = core::cmp::min(16 - self.used, data.len());
+            self.buf[self.used..self.used + n].copy_from_slice(&data[..n]);
+            self.used += n;
+            data = &data[n..];
+            if self.used < 16 {
+                return;
+            }
+            let buf = self.buf;
+            self.block(&buf);
+            self.used = 0;
+        }
+        let mut blocks = data.chunks_exact(16);
+        for block in &mut blocks {
+            let mut x = [0_u8; 16];
+            x.copy_from_slice(block);
+            self.block(&x);
+        }
+        let rest = blocks.remainder();
+        self.buf[..rest.len()].copy_from_slice(rest);
+        self.used = rest.len();
+    }
+
+    fn pad(&mut self) {
+        if self.used > 0 {
+            let mut x = [0_u8; 16];
+            x[..self.used].copy_from_slice(&self.buf[..self.used]);
+            self.block(&x);
+            self.used = 0;
+        }
+    }
+}
+
+impl Drop for Ghash {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::write_volatile(&mut self.h, 0);
+            ptr::write_volatile(&mut self.acc, 0);
+        }
+        rsgx_zeroize(&mut self.buf);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Encrypt,
+    Decrypt,
+}
+
+///
+/// An incremental SM4-GCM encryption or decryption.
+///
+/// # Description
+///
+/// SgxSm4Gcm has the same interface as SgxAesGcm: the additional authenticated data
+/// is fed with update_aad before the first call to update, which processes the
+/// message in chunks of any size. An encryption is finished with finalize, which
+/// returns the tag, and a decryption with verify.
+///
+/// An IV must never be used twice with the same key, and the output of a decryption
+/// must not be used until verify has succeeded.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sgx_tcrypto::SgxSm4Gcm;
+///
+/// let key = [0_u8; 16];
+/// let iv = [0_u8; 12];
+/// let mut gcm = SgxSm4Gcm::new_encrypt(&key, &iv);
+/// gcm.update_aad(b"header").unwrap();
+/// let mut ciphertext = [0_u8; 8];
+/// gcm.update(b"chunk 1 ", &mut ciphertext).unwrap();
+/// let tag = gcm.finalize().unwrap();
+/// ```
+///
+pub struct SgxSm4Gcm {
+    direction: Direction,
+    cipher: SgxSm4,
+    ghash: Ghash,
+    // The encrypted first counter block masks the tag.
+    tag_mask: [u8; 16],
+    counter: [u8; 16],
+    keystream: [u8; 16],
+    used: usize,
+    started: bool,
+    aad_len: u64,
+    data_len: u64,
+}
+
+impl SgxSm4Gcm {
+    ///
+    /// Starts encrypting with the given key and 96-bit IV.
+    ///
+    pub fn new_encrypt(key: &sgx_sm4_key_t, iv: &sgx_sm4_gcm_iv_t) -> SgxSm4Gcm {
+        SgxSm4Gcm::new(Direction::Encrypt, key, iv)
+    }
+
+    ///
+    /// Starts decrypting with the given key and 96-bit IV.
+    ///
+    pub fn new_decrypt(key: &sgx_sm4_key_t, iv: &sgx_sm4_gcm_iv_t) -> SgxSm4Gcm {
+        SgxSm4Gcm::new(Direction::Decrypt, key, iv)
+    }
+
+    fn new(direction: Direction, key: &sgx_sm4_key_t, iv: &sgx_sm4_gcm_iv_t) -> SgxSm4Gcm {
+        let cipher = SgxSm4::new(key);
+        let mut h = [0_u8; 16];
+        cipher.encrypt_block(&mut h);
+        let ghash = Ghash::new(&h);
+        rsgx_zeroize(&mut h);
+
+        let mut counter = [0_u8; 16];
+        counter[..12].copy_from_slice(iv);
+        counter[15] = 1;
+        let mut tag_mask = counter;
+        cipher.encrypt_block(&mut tag_mask);
+
+        SgxSm4Gcm {
+            direction,
+            cipher,
+            ghash,
+            tag_mask,
+            counter,
+            keystream: [0; 16],
+            used: 16,
+            started: false,
+            aad_len: 0,
+            data_len: 0,
+        }
+    }
+
+    ///
+    /// Adds a chunk of additional authenticated data.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// Data has already been processed with update.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The additional authenticated data is longer than 2^61 - 1 bytes.
+    ///
+    pub fn update_aad(&mut self, aad: &[u8]) -> SgxError {
+        if self.started {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        match self.aad_len.checked_add(aad.len() as u64) {
+            Some(len) if len < 1 << 61 => self.aad_len = len,
+            _ => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+        self.ghash.update(aad);
+        Ok(())
+    }
+
+    ///
+    /// Encrypts or decrypts the next chunk of the message from src into dst.
+    ///
+    /// dst must be at least as long as src; the first src.len() bytes are written.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// dst is too short, or the message would exceed the GCM limit of 2^36 - 32 bytes.
+    ///
+    pub fn update(&mut self, src: &[u8], dst: &mut [u8]) -> SgxError {
+        check_algorithm(SgxAlgorithm::Sm4)?;
+        if dst.len() < src.len() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        match self.data_len.checked_add(src.len() as u64) {
+            Some(len) if len <= GCM_MAX_DATA_LEN => self.data_len = len,
+            _ => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+        self.start();
+
+        let dst = &mut dst[..src.len()];
+        if self.direction == Direction::Decrypt {
+            self.ghash.update(src);
+        }
+        for (d, s) in dst.iter_mut().zip(src.iter()) {
+            if self.used == 16 {
+                self.next_keystream();
+            }
+            *d = s ^ self.keystream[self.used];
+            self.used += 1;
+        }
+        if self.direction == Direction::Encrypt {
+            self.ghash.update(dst);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Finishes an encryption and returns the tag.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// This is a decryption.
+    ///
+    pub fn finalize(mut self) -> SgxResult<sgx_sm4_gcm_tag_t> {
+        check_algorithm(SgxAlgorithm::Sm4)?;
+        if self.direction != Direction::Encrypt {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        Ok(self.compute_tag())
+    }
+
+    ///
+    /// Finishes a decryption and checks the tag of the whole message.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The message or the additional authenticated data was modified.
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// This is an encryption.
+    ///
+    pub fn verify(mut self, tag: &sgx_sm4_gcm_tag_t) -> SgxError {
+        check_algorithm(SgxAlgorithm::Sm4)?;
+        if self.direction != Direction::Decrypt {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        let computed = self.compute_tag();
+        if rsgx_ct_eq(&computed, tag) {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+        }
+    }
+
+    // Ends the additional data, which is padded apart from the message.
+    fn start(&mut self) {
+        if !self.started {
+            self.ghash.pad();
+            self.started = true;
+        }
+    }
+
+    // Increments the low 32 bits of the counter and encrypts it.
+    fn next_keystream(&mut self) {
+        let mut low = [0_u8; 4];
+        low.copy_from_slice(&self.counter[12..]);
+        let next = u32::from_be_bytes(low).wrapping_add(1);
+        self.counter[12..].copy_from_slice(&next.to_be_bytes());
+        self.keystream = self.counter;
+        self.cipher.encrypt_block(&mut self.keystream);
+        self.used = 0;
+    }
+
+    fn compute_tag(&mut self) -> sgx_sm4_gcm_tag_t {
+        self.start();
+        self.ghash.pad();
+        let mut lengths = [0_u8; 16];
+        lengths[..8].copy_from_slice(&(self.aad_len * 8).to_be_bytes());
+        lengths[8..].copy_from_slice(&(self.data_len * 8).to_be_bytes());
+        self.ghash.update(&lengths);
+        let mut tag = self.ghash.acc.to_be_bytes();
+        for (t, m) in tag.iter_mut().zip(self.tag_mask.iter()) {
+            *t ^= m;
+        }
+        tag
+    }
+}
+
+impl Drop for SgxSm4Gcm {
+    fn drop(&mut self) {
+        rsgx_zeroize(&mut self.tag_mask);
+        rsgx_zeroize(&mut self.keystream);
+    }
+}
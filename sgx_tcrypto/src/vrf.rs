@@ -0,0 +1,404 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Verifiable random functions
+//!
+//! ECVRF as specified in RFC 9381, for the suites ECVRF-P256-SHA256-SSWU (suite
+//! string 0x02) and ECVRF-EDWARDS25519-SHA512-ELL2 (0x04). The prover derives a
+//! pseudorandom output from its private key and an input alpha, together with a
+//! proof that lets anyone holding the public key check the output. Unlike a
+//! signature, the output is unique: a prover cannot choose among several valid
+//! outputs for the same input, which is what randomness beacons and committee
+//! selection rely on.
+//!
+//! Proving runs in constant time in the private key and the nonce.
+//!
+use super::curve25519::*;
+use super::ed25519::{sgx_ed25519_public_t, SgxEd25519PrivateKey};
+use super::hash_to_curve::{edwards25519_map, p256_map};
+use super::kdf::{HmacDigest, Sha256};
+use super::modular::*;
+use super::policy::{check_algorithm, SgxAlgorithm};
+use super::sha512::Sha512;
+use super::weierstrass::{Point, Rfc6979, P256};
+use core::ptr;
+use sgx_types::*;
+
+pub type sgx_ecvrf_p256_proof_t = [uint8_t; 81];
+pub type sgx_ecvrf_p256_output_t = [uint8_t; 32];
+pub type sgx_ecvrf_ed25519_proof_t = [uint8_t; 80];
+pub type sgx_ecvrf_ed25519_output_t = [uint8_t; 64];
+
+const P256_SUITE: u8 = 0x02;
+const P256_DST: &[u8] = b"ECVRF_P256_XMD:SHA-256_SSWU_NU_\x02";
+const ED25519_SUITE: u8 = 0x04;
+const ED25519_DST: &[u8] = b"ECVRF_edwards25519_XMD:SHA-512_ELL2_NU_\x04";
+
+// Compressed SEC 1 encoding; None for the identity.
+fn p256_encode(pt: &Point<4>) -> Option<[u8; 33]> {
+    let (x, y) = P256.to_affine(pt)?;
+    let mut out = [0_u8; 33];
+    out[0] = 2 | (y[0] & 1) as u8;
+    to_be_bytes(&x, &mut out[1..]);
+    Some(out)
+}
+
+fn p256_decode(bytes: &[u8]) -> Option<Point<4>> {
+    match bytes[0] {
+        tag @ (2 | 3) => P256.point_from_x(&from_be_bytes(&bytes[1..]), u64::from(tag & 1)),
+        _ => None,
+    }
+}
+
+// The first 16 bytes of the challenge hash, section 5.4.3.
+fn p256_challenge(points: &[&[u8; 33]; 5]) -> SgxResult<[u8; 16]> {
+    let mut hash = [0_u8; 32];
+    let [y, h, gamma, u, v] = points;
+    Sha256::digest(
+        &[&[P256_SUITE, 0x02], *y, *h, *gamma, *u, *v, &[0]],
+        &mut hash,
+    )?;
+    let mut c = [0_u8; 16];
+    c.copy_from_slice(&hash[..16]);
+    Ok(c)
+}
+
+fn ed25519_challenge(points: &[&[u8; 32]; 5]) -> [u8; 32] {
+    let [y, h, gamma, u, v] = points;
+    let hash = Sha512::digest(&[&[ED25519_SUITE, 0x02], *y, *h, *gamma, *u, *v, &[0]]);
+    let mut c = [0_u8; 32];
+    c[..16].copy_from_slice(&hash[..16]);
+    c
+}
+
+// Decodes the proof as in section 5.4.4, returning Gamma, c and s.
+fn p256_decode_proof(proof: &sgx_ecvrf_p256_proof_t) -> SgxResult<(Point<4>, Limbs<4>, Limbs<4>)> {
+    let gamma = p256_decode(&proof[..33]).ok_or(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE)?;
+    let c = from_be_bytes::<4>(&proof[33..49]);
+    let s = from_be_bytes::<4>(&proof[49..]);
+    if !P256.n.is_reduced(&s) {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE);
+    }
+    Ok((gamma, c, s))
+}
+
+fn ed25519_decode_proof(
+    proof: &sgx_ecvrf_ed25519_proof_t,
+) -> SgxResult<(EdwardsPoint, [u8; 32], [u8; 32])> {
+    let mut gamma = [0_u8; 32];
+    let mut c = [0_u8; 32];
+    let mut s = [0_u8; 32];
+    gamma.copy_from_slice(&proof[..32]);
+    c[..16].copy_from_slice(&proof[32..48]);
+    s.copy_from_slice(&proof[48..]);
+    let gamma =
+        EdwardsPoint::decompress(&gamma).ok_or(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE)?;
+    if !scalar_is_canonical(&s) {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE);
+    }
+    Ok((gamma, c, s))
+}
+
+///
+/// Computes the ECVRF-P256-SHA256-SSWU proof for the input alpha.
+///
+/// The output of the VRF is rsgx_ecvrf_p256_proof_to_hash of the proof. Proofs are
+/// deterministic, so proving the same input twice gives the same proof.
+///
+/// # Parameters
+///
+/// **private**
+///
+/// The private key, in the little-endian format of the SDK.
+///
+/// **alpha**
+///
+/// The input of the VRF.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The private key is zero or not below the group order.
+///
+/// **SGX_ERROR_FEATURE_NOT_SUPPORTED**
+///
+/// ECVRF is not allowed by the algorithm policy.
+///
+pub fn rsgx_ecvrf_p256_prove(
+    private: &sgx_ec256_private_t,
+    alpha: &[u8],
+) -> SgxResult<sgx_ecvrf_p256_proof_t> {
+    check_algorithm(SgxAlgorithm::Ecvrf)?;
+    let mut x = from_le_bytes::<4>(&private.r);
+    if !P256.is_valid_scalar(&x) {
+        wipe(&mut x);
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let result = p256_prove(&x, alpha);
+    wipe(&mut x);
+    result
+}
+
+fn p256_prove(x: &Limbs<4>, alpha: &[u8]) -> SgxResult<sgx_ecvrf_p256_proof_t> {
+    let encode = |pt: &Point<4>| p256_encode(pt).ok_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
+    let n = &P256.n;
+    let g = P256.generator();
+    let y = encode(&P256.mul(&g, x))?;
+    let h = p256_map(&[&y, alpha], P256_DST, 1)?;
+    let h_string = encode(&h)?;
+    let gamma = encode(&P256.mul(&h, x))?;
+
+    // The nonce of section 5.4.2.1: RFC 6979 over the hash of h_string.
+    let mut h1 = [0_u8; 32];
+    Sha256::digest(&[&h_string], &mut h1)?;
+    let h1 = from_be_bytes::<4>(&h1);
+    let mut x_bytes = [0_u8; 32];
+    to_be_bytes(x, &mut x_bytes);
+    let mut h1_bytes = [0_u8; 32];
+    to_be_bytes(&n.normal_form(&n.reduce_mont_form(&h1)), &mut h1_bytes);
+    let nonce = Rfc6979::<Sha256>::new(&x_bytes, &h1_bytes).and_then(|mut drbg| drbg.next(&P256));
+    unsafe { ptr::write_volatile(&mut x_bytes, [0_u8; 32]) };
+    let mut k = nonce?;
+
+    let mut kg = P256.mul(&g, &k);
+    let mut kh = P256.mul(&h, &k);
+    let u = encode(&kg)?;
+    let v = encode(&kh)?;
+    kg.wipe();
+    kh.wipe();
+    let c = p256_challenge(&[&y, &h_string, &gamma, &u, &v])?;
+
+    // s = k + c * x mod n
+    let mut k_m = n.mont_form(&k);
+    let mut x_m = n.mont_form(x);
+    let s = n.normal_form(&n.add(&k_m, &n.mul(&n.mont_form(&from_be_bytes(&c)), &x_m)));
+    wipe(&mut k);
+    wipe(&mut k_m);
+    wipe(&mut x_m);
+
+    let mut proof = [0_u8; 81];
+    proof[..33].copy_from_slice(&gamma);
+    proof[33..49].copy_from_slice(&c);
+    to_be_bytes(&s, &mut proof[49..]);
+    Ok(proof)
+}
+
+///
+/// Verifies an ECVRF-P256-SHA256-SSWU proof for the input alpha and returns the
+/// output of the VRF.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_SIGNATURE**
+///
+/// The proof is malformed or does not verify.
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The public key is not on the curve.
+///
+/// **SGX_ERROR_FEATURE_NOT_SUPPORTED**
+///
+/// ECVRF is not allowed by the algorithm policy.
+///
+pub fn rsgx_ecvrf_p256_verify(
+    public: &sgx_ec256_public_t,
+    alpha: &[u8],
+    proof: &sgx_ecvrf_p256_proof_t,
+) -> SgxResult<sgx_ecvrf_p256_output_t> {
+    check_algorithm(SgxAlgorithm::Ecvrf)?;
+    let y_point = P256
+        .point_from_affine(&from_le_bytes(&public.gx), &from_le_bytes(&public.gy))
+        .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+    let y = p256_encode(&y_point).ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+    let (gamma, c, s) = p256_decode_proof(proof)?;
+    let h = p256_map(&[&y, alpha], P256_DST, 1)?;
+    let h_string = p256_encode(&h).ok_or(sgx_status_t::SGX_ERROR_UNEXPECTED)?;
+
+    // U = s * B - c * Y and V = s * H - c * Gamma
+    let u = P256.add(
+        &P256.mul(&P256.generator(), &s),
+        &P256.neg(&P256.mul(&y_point, &c)),
+    );
+    let v = P256.add(&P256.mul(&h, &s), &P256.neg(&P256.mul(&gamma, &c)));
+    let (u, v) = match (p256_encode(&u), p256_encode(&v)) {
+        (Some(u), Some(v)) => (u, v),
+        _ => return Err(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE),
+    };
+    let mut gamma_string = [0_u8; 33];
+    gamma_string.copy_from_slice(&proof[..33]);
+    if p256_challenge(&[&y, &h_string, &gamma_string, &u, &v])?[..] != proof[33..49] {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE);
+    }
+    p256_proof_hash(&gamma_string)
+}
+
+///
+/// Returns the VRF output of an ECVRF-P256-SHA256-SSWU proof without verifying it.
+///
+/// Only use this on proofs computed by rsgx_ecvrf_p256_prove, or already checked
+/// by rsgx_ecvrf_p256_verify.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_SIGNATURE**
+///
+/// The proof is malformed.
+///
+pub fn rsgx_ecvrf_p256_proof_to_hash(
+    proof: &sgx_ecvrf_p256_proof_t,
+) -> SgxResult<sgx_ecvrf_p256_output_t> {
+    check_algorithm(SgxAlgorithm::Ecvrf)?;
+    p256_decode_proof(proof)?;
+    let mut gamma = [0_u8; 33];
+    gamma.copy_from_slice(&proof[..33]);
+    p256_proof_hash(&gamma)
+}
+
+// P-256 has cofactor 1, so Gamma is hashed as it is.
+fn p256_proof_hash(gamma: &[u8; 33]) -> SgxResult<sgx_ecvrf_p256_output_t> {
+    let mut beta = [0_u8; 32];
+    Sha256::digest(&[&[P256_SUITE, 0x03], gamma, &[0]], &mut beta)?;
+    Ok(beta)
+}
+
+///
+/// Computes the ECVRF-EDWARDS25519-SHA512-ELL2 proof for the input alpha, with an
+/// Ed25519 key.
+///
+/// The output of the VRF is rsgx_ecvrf_ed25519_proof_to_hash of the proof.
+///
+/// # Errors
+///
+/// **SGX_ERROR_FEATURE_NOT_SUPPORTED**
+///
+/// ECVRF is not allowed by the algorithm policy.
+///
+pub fn rsgx_ecvrf_ed25519_prove(
+    key: &SgxEd25519PrivateKey,
+    alpha: &[u8],
+) -> SgxResult<sgx_ecvrf_ed25519_proof_t> {
+    check_algorithm(SgxAlgorithm::Ecvrf)?;
+    let (x, prefix) = key.expanded();
+    let y = key.public_key();
+    let h = edwards25519_map(&[&y, alpha], ED25519_DST, 1)?;
+    let h_string = h.compress();
+    let mut gamma_point = h.mul(x);
+    let gamma = gamma_point.compress();
+    gamma_point.wipe();
+
+    // The nonce of section 5.4.2.2, as in Ed25519 signing.
+    let mut digest = Sha512::digest(&[prefix, &h_string]);
+    let mut k = scalar_reduce_bytes(&digest);
+    let mut kb = EdwardsPoint::BASE.mul(&k);
+    let mut kh = h.mul(&k);
+    let u = kb.compress();
+    let v = kh.compress();
+    kb.wipe();
+    kh.wipe();
+    let c = ed25519_challenge(&[&y, &h_string, &gamma, &u, &v]);
+    let s = scalar_mul_add(&c, x, &k);
+    unsafe {
+        ptr::write_volatile(&mut digest, [0_u8; 64]);
+        ptr::write_volatile(&mut k, [0_u8; 32]);
+    }
+
+    let mut proof = [0_u8; 80];
+    proof[..32].copy_from_slice(&gamma);
+    proof[32..48].copy_from_slice(&c[..16]);
+    proof[48..].copy_from_slice(&s);
+    Ok(proof)
+}
+
+///
+/// Verifies an ECVRF-EDWARDS25519-SHA512-ELL2 proof for the input alpha and returns
+/// the output of the VRF.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_SIGNATURE**
+///
+/// The proof is malformed or does not verify.
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The public key is not a valid point, or has small order.
+///
+/// **SGX_ERROR_FEATURE_NOT_SUPPORTED**
+///
+/// ECVRF is not allowed by the algorithm policy.
+///
+pub fn rsgx_ecvrf_ed25519_verify(
+    public: &sgx_ed25519_public_t,
+    alpha: &[u8],
+    proof: &sgx_ecvrf_ed25519_proof_t,
+) -> SgxResult<sgx_ecvrf_ed25519_output_t> {
+    check_algorithm(SgxAlgorithm::Ecvrf)?;
+    // ECVRF_validate_key of section 5.4.5
+    let y_point = EdwardsPoint::decompress(public)
+        .filter(|pt| !pt.mul_by_cofactor().is_identity())
+        .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+    let (gamma, c, s) = ed25519_decode_proof(proof)?;
+    let h = edwards25519_map(&[public, alpha], ED25519_DST, 1)?;
+
+    // U = s * B - c * Y and V = s * H - c * Gamma
+    let u = EdwardsPoint::BASE.mul(&s).add(&y_point.mul(&c).neg());
+    let v = h.mul(&s).add(&gamma.mul(&c).neg());
+    let gamma_string = gamma.compress();
+    let expected = ed25519_challenge(&[
+        public,
+        &h.compress(),
+        &gamma_string,
+        &u.compress(),
+        &v.compress(),
+    ]);
+    if expected != c {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE);
+    }
+    Ok(ed25519_proof_hash(&gamma))
+}
+
+///
+/// Returns the VRF output of an ECVRF-EDWARDS25519-SHA512-ELL2 proof without
+/// verifying it.
+///
+/// Only use this on proofs computed by rsgx_ecvrf_ed25519_prove, or already checked
+/// by rsgx_ecvrf_ed25519_verify.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_SIGNATURE**
+///
+/// The proof is malformed.
+///
+pub fn rsgx_ecvrf_ed25519_proof_to_hash(
+    proof: &sgx_ecvrf_ed25519_proof_t,
+) -> SgxResult<sgx_ecvrf_ed25519_output_t> {
+    check_algorithm(SgxAlgorithm::Ecvrf)?;
+    let (gamma, _, _) = ed25519_decode_proof(proof)?;
+    Ok(ed25519_proof_hash(&gamma))
+}
+
+fn ed25519_proof_hash(gamma: &EdwardsPoint) -> sgx_ecvrf_ed25519_output_t {
+    Sha512::digest(&[
+        &[ED25519_SUITE, 0x03],
+        &gamma.mul_by_cofactor().compress(),
+        &[0],
+    ])
+}
@@ -0,0 +1,501 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Short Weierstrass curves with a = -3
+//!
+//! Points use projective coordinates and the complete addition formulas of Renes,
+//! Costello and Batina, so scalar multiplication needs no special cases and runs in
+//! constant time. ECDSA nonces come either from the trusted RNG or from RFC 6979.
+//!
+use super::kdf::{Hmac, HmacDigest, MAX_HASH_SIZE};
+use super::modular::*;
+use core::ptr;
+use sgx_types::*;
+
+pub(crate) struct Curve<const N: usize> {
+    pub(crate) p: Modulus<N>,
+    pub(crate) n: Modulus<N>,
+    pub(crate) b: Limbs<N>,
+    pub(crate) gx: Limbs<N>,
+    pub(crate) gy: Limbs<N>,
+}
+
+pub(crate) const P256: Curve<4> = Curve {
+    p: Modulus::new(limbs_from_hex(
+        "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff",
+    )),
+    n: Modulus::new(limbs_from_hex(
+        "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551",
+    )),
+    b: limbs_from_hex("5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b"),
+    gx: limbs_from_hex("6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296"),
+    gy: limbs_from_hex("4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5"),
+};
+
+pub(crate) const P384: Curve<6> = Curve {
+    p: Modulus::new(limbs_from_hex(
+        "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffeffffffff0000000000000000ffffffff",
+    )),
+    n: Modulus::new(limbs_from_hex(
+        "ffffffffffffffffffffffffffffffffffffffffffffffffc7634d81f4372ddf581a0db248b0a77aecec196accc52973",
+    )),
+    b: limbs_from_hex(
+        "b3312fa7e23ee7e4988e056be3f82d19181d9c6efe8141120314088f5013875ac656398d8a2ed19d2a85c8edd3ec2aef",
+    ),
+    gx: limbs_from_hex(
+        "aa87ca22be8b05378eb1c71ef320ad746e1d3b628ba79b9859f741e082542a385502f25dbf55296c3a545e3872760ab7",
+    ),
+    gy: limbs_from_hex(
+        "3617de4a96262c6f5d9e98bf9292dc29f8f41dbd289a147ce9da3113b5f0b8c00a60b1ce1d7e819d7a431d7c90ea0e5f",
+    ),
+};
+
+/// The SM2 curve of GB/T 32918.5-2017.
+#[cfg(feature = "sm")]
+pub(crate) const SM2: Curve<4> = Curve {
+    p: Modulus::new(limbs_from_hex(
+        "fffffffeffffffffffffffffffffffffffffffff00000000ffffffffffffffff",
+    )),
+    n: Modulus::new(limbs_from_hex(
+        "fffffffeffffffffffffffffffffffff7203df6b21c6052b53bbf40939d54123",
+    )),
+    b: limbs_from_hex("28e9fa9e9d9f5e344d5a9e4bcf6509a7f39789f515ab8f92ddbcbd414d940e93"),
+    gx: limbs_from_hex("32c4ae2c1f1981195f9904466a39c9948fe30bbff2660be1715a4589334c74c7"),
+    gy: limbs_from_hex("bc3736a2f4f6779c59bdcee36b692153d0a9877cc62a474002df32e52139f0a0"),
+};
+
+/// A point in projective coordinates with Montgomery form field elements.
+#[derive(Clone, Copy)]
+pub(crate) struct Point<const N: usize> {
+    x: Limbs<N>,
+    y: Limbs<N>,
+    z: Limbs<N>,
+}
+
+impl<const N: usize> Point<N> {
+    pub(crate) fn wipe(&mut self) {
+        wipe(&mut self.x);
+        wipe(&mut self.y);
+        wipe(&mut self.z);
+    }
+}
+
+impl<const N: usize> Curve<N> {
+    pub(crate) fn identity(&self) -> Point<N> {
+        Point {
+            x: [0_u64; N],
+            y: self.p.one(),
+            z: [0_u64; N],
+        }
+    }
+
+    pub(crate) fn generator(&self) -> Point<N> {
+        Point {
+            x: self.p.mont_form(&self.gx),
+            y: self.p.mont_form(&self.gy),
+            z: self.p.one(),
+        }
+    }
+
+    /// Builds a point from affine coordinates, checking that it lies on the curve.
+    pub(crate) fn point_from_affine(&self, x: &Limbs<N>, y: &Limbs<N>) -> Option<Point<N>> {
+        if !self.p.is_reduced(x) || !self.p.is_reduced(y) {
+            return None;
+        }
+        let f = &self.p;
+        let xm = f.mont_form(x);
+        let ym = f.mont_form(y);
+        // y^2 = x^3 - 3x + b
+        let x3 = f.mul(&f.square(&xm), &xm);
+        let three_x = f.add(&f.add(&xm, &xm), &xm);
+        let rhs = f.add(&f.sub(&x3, &three_x), &f.mont_form(&self.b));
+        if ct_eq(&f.square(&ym), &rhs) == 0 {
+            return None;
+        }
+        Some(Point {
+            x: xm,
+            y: ym,
+            z: f.one(),
+        })
+    }
+
+    /// Builds a point from its x coordinate and the parity of y, for curves with
+    /// p = 3 mod 4.
+    pub(crate) fn point_from_x(&self, x: &Limbs<N>, odd: u64) -> Option<Point<N>> {
+        if !self.p.is_reduced(x) {
+            return None;
+        }
+        let f = &self.p;
+        let xm = f.mont_form(x);
+        let x3 = f.mul(&f.square(&xm), &xm);
+        let three_x = f.add(&f.add(&xm, &xm), &xm);
+        let rhs = f.add(&f.sub(&x3, &three_x), &f.mont_form(&self.b));
+        // y = rhs^((p + 1) / 4), which is a square root if rhs has one.
+        let mut one = [0_u64; N];
+        one[0] = 1;
+        let (mut e, carry) = add_limbs(&f.m, &one);
+        for i in 0..N {
+            let next = if i + 1 < N { e[i + 1] } else { carry };
+            e[i] = (e[i] >> 2) | (next << 62);
+        }
+        let ym = f.pow_vartime(&rhs, &e);
+        if ct_eq(&f.square(&ym), &rhs) == 0 {
+            return None;
+        }
+        let y = f.normal_form(&ym);
+        let neg_y = f.normal_form(&f.sub(&[0_u64; N], &ym));
+        let y = select(&y, &neg_y, (y[0] & 1) ^ odd);
+        self.point_from_affine(x, &y)
+    }
+
+    /// The simplified SWU map of RFC 9380, section 6.6.2, in the constant-time form of
+    /// appendix F.2 for p = 3 mod 4. u, z and c2 = sqrt(-z) are in normal form.
+    pub(crate) fn map_to_curve_sswu(&self, u: &Limbs<N>, z: &Limbs<N>, c2: &Limbs<N>) -> Point<N> {
+        let f = &self.p;
+        let zero = [0_u64; N];
+        let one = f.one();
+        let a = f.sub(&zero, &f.add(&f.add(&one, &one), &one));
+        let b = f.mont_form(&self.b);
+        let z = f.mont_form(z);
+        let u = f.mont_form(u);
+
+        let tv1 = f.mul(&z, &f.square(&u));
+        let tv2 = f.add(&f.square(&tv1), &tv1);
+        let tv3 = f.mul(&b, &f.add(&tv2, &one));
+        let tv4 = f.mul(&a, &select(&z, &f.sub(&zero, &tv2), 1 ^ is_zero(&tv2)));
+        let tv6 = f.square(&tv4);
+        let tv5 = f.mul(&a, &tv6);
+        let tv2 = f.mul(&f.add(&f.square(&tv3), &tv5), &tv3);
+        let tv6 = f.mul(&tv6, &tv4);
+        let tv2 = f.add(&tv2, &f.mul(&b, &tv6));
+        let x = f.mul(&tv1, &tv3);
+        let (is_square, y1) = self.sqrt_ratio(&tv2, &tv6, &f.mont_form(c2));
+        let y = f.mul(&f.mul(&tv1, &u), &y1);
+        let x = select(&x, &tv3, is_square);
+        let y = select(&y, &y1, is_square);
+        let same_sign = 1 ^ ((f.normal_form(&u)[0] ^ f.normal_form(&y)[0]) & 1);
+        let y = select(&f.sub(&zero, &y), &y, same_sign);
+        Point {
+            x: f.mul(&x, &f.invert(&tv4)),
+            y,
+            z: one,
+        }
+    }
+
+    // sqrt_ratio of RFC 9380, appendix F.2.1.2, on Montgomery form values: returns
+    // (1, sqrt(u / v)) if u / v is square and (0, sqrt(z * u / v)) otherwise.
+    fn sqrt_ratio(&self, u: &Limbs<N>, v: &Limbs<N>, c2: &Limbs<N>) -> (u64, Limbs<N>) {
+        let f = &self.p;
+        // c1 = (p - 3) / 4
+        let mut c1 = f.m;
+        for i in 0..N {
+            let next = if i + 1 < N { c1[i + 1] } else { 0 };
+            c1[i] = (c1[i] >> 2) | (next << 62);
+        }
+        let tv2 = f.mul(u, v);
+        let tv1 = f.mul(&f.square(v), &tv2);
+        let y1 = f.mul(&f.pow_vartime(&tv1, &c1), &tv2);
+        let y2 = f.mul(&y1, c2);
+        let is_qr = ct_eq(&f.mul(&f.square(&y1), v), u);
+        (is_qr, select(&y2, &y1, is_qr))
+    }
+
+    /// Returns the affine coordinates in normal form, or None for the point at infinity.
+    pub(crate) fn to_affine(&self, pt: &Point<N>) -> Option<(Limbs<N>, Limbs<N>)> {
+        if is_zero(&pt.z) == 1 {
+            return None;
+        }
+        let f = &self.p;
+        let zinv = f.invert(&pt.z);
+        Some((
+            f.normal_form(&f.mul(&pt.x, &zinv)),
+            f.normal_form(&f.mul(&pt.y, &zinv)),
+        ))
+    }
+
+    pub(crate) fn add(&self, a: &Point<N>, b: &Point<N>) -> Point<N> {
+        let f = &self.p;
+        let eb = f.mont_form(&self.b);
+        let xx = f.mul(&a.x, &b.x);
+        let yy = f.mul(&a.y, &b.y);
+        let zz = f.mul(&a.z, &b.z);
+        let xy_pairs = f.sub(
+            &f.mul(&f.add(&a.x, &a.y), &f.add(&b.x, &b.y)),
+            &f.add(&xx, &yy),
+        );
+        let yz_pairs = f.sub(
+            &f.mul(&f.add(&a.y, &a.z), &f.add(&b.y, &b.z)),
+            &f.add(&yy, &zz),
+        );
+        let xz_pairs = f.sub(
+            &f.mul(&f.add(&a.x, &a.z), &f.add(&b.x, &b.z)),
+            &f.add(&xx, &zz),
+        );
+        let bzz_part = f.sub(&xz_pairs, &f.mul(&eb, &zz));
+        let bzz3_part = f.add(&f.add(&bzz_part, &bzz_part), &bzz_part);
+        let yy_m_bzz3 = f.sub(&yy, &bzz3_part);
+        let yy_p_bzz3 = f.add(&yy, &bzz3_part);
+        let zz3 = f.add(&f.add(&zz, &zz), &zz);
+        let bxz_part = f.sub(&f.mul(&eb, &xz_pairs), &f.add(&zz3, &xx));
+        let bxz3_part = f.add(&f.add(&bxz_part, &bxz_part), &bxz_part);
+        let xx3_m_zz3 = f.sub(&f.add(&f.add(&xx, &xx), &xx), &zz3);
+        Point {
+            x: f.sub(&f.mul(&yy_p_bzz3, &xy_pairs), &f.mul(&yz_pairs, &bxz3_part)),
+            y: f.add(
+                &f.mul(&yy_p_bzz3, &yy_m_bzz3),
+                &f.mul(&xx3_m_zz3, &bxz3_part),
+            ),
+            z: f.add(&f.mul(&yy_m_bzz3, &yz_pairs), &f.mul(&xy_pairs, &xx3_m_zz3)),
+        }
+    }
+
+    pub(crate) fn double(&self, a: &Point<N>) -> Point<N> {
+        let f = &self.p;
+        let eb = f.mont_form(&self.b);
+        let xx = f.square(&a.x);
+        let yy = f.square(&a.y);
+        let zz = f.square(&a.z);
+        let xy = f.mul(&a.x, &a.y);
+        let xy2 = f.add(&xy, &xy);
+        let xz = f.mul(&a.x, &a.z);
+        let xz2 = f.add(&xz, &xz);
+        let bzz_part = f.sub(&f.mul(&eb, &zz), &xz2);
+        let bzz3_part = f.add(&f.add(&bzz_part, &bzz_part), &bzz_part);
+        let yy_m_bzz3 = f.sub(&yy, &bzz3_part);
+        let yy_p_bzz3 = f.add(&yy, &bzz3_part);
+        let y_frag = f.mul(&yy_p_bzz3, &yy_m_bzz3);
+        let x_frag = f.mul(&yy_m_bzz3, &xy2);
+        let zz3 = f.add(&f.add(&zz, &zz), &zz);
+        let bxz2_part = f.sub(&f.mul(&eb, &xz2), &f.add(&zz3, &xx));
+        let bxz6_part = f.add(&f.add(&bxz2_part, &bxz2_part), &bxz2_part);
+        let xx3_m_zz3 = f.sub(&f.add(&f.add(&xx, &xx), &xx), &zz3);
+        let yz = f.mul(&a.y, &a.z);
+        let yz2 = f.add(&yz, &yz);
+        let yz2yy = f.mul(&yz2, &yy);
+        let yz4yy = f.add(&yz2yy, &yz2yy);
+        Point {
+            x: f.sub(&x_frag, &f.mul(&bxz6_part, &yz2)),
+            y: f.add(&y_frag, &f.mul(&xx3_m_zz3, &bxz6_part)),
+            z: f.add(&yz4yy, &yz4yy),
+        }
+    }
+
+    pub(crate) fn neg(&self, a: &Point<N>) -> Point<N> {
+        Point {
+            x: a.x,
+            y: self.p.sub(&[0_u64; N], &a.y),
+            z: a.z,
+        }
+    }
+
+    /// Returns 1 if a and b are the same point, comparing X1 Z2 = X2 Z1 and Y1 Z2 = Y2 Z1.
+    pub(crate) fn point_eq(&self, a: &Point<N>, b: &Point<N>) -> u64 {
+        let f = &self.p;
+        ct_eq(&f.mul(&a.x, &b.z), &f.mul(&b.x, &a.z))
+            & ct_eq(&f.mul(&a.y, &b.z), &f.mul(&b.y, &a.z))
+    }
+
+    pub(crate) fn is_identity(&self, a: &Point<N>) -> u64 {
+        is_zero(&a.z)
+    }
+
+    fn select(&self, a: &Point<N>, b: &Point<N>, choice: u64) -> Point<N> {
+        Point {
+            x: select(&a.x, &b.x, choice),
+            y: select(&a.y, &b.y, choice),
+            z: select(&a.z, &b.z, choice),
+        }
+    }
+
+    /// Multiplies by a scalar below 2^bits(n), with a fixed sequence of operations.
+    pub(crate) fn mul(&self, pt: &Point<N>, k: &Limbs<N>) -> Point<N> {
+        let mut r = self.identity();
+        for i in (0..self.n.bits()).rev() {
+            let bit = (k[i / 64] >> (i % 64)) & 1;
+            r = self.double(&r);
+            let sum = self.add(&r, pt);
+            r = self.select(&r, &sum, bit);
+        }
+        r
+    }
+
+    /// Checks 0 < k < n.
+    pub(crate) fn is_valid_scalar(&self, k: &Limbs<N>) -> bool {
+        is_zero(k) == 0 && self.n.is_reduced(k)
+    }
+
+    /// Draws a uniformly distributed scalar in [1, n) by rejection sampling.
+    pub(crate) fn random_scalar(&self) -> SgxResult<Limbs<N>> {
+        let bits = self.n.bits();
+        let mut buf = [0_u8; 8 * 9];
+        let len = (bits + 7) / 8;
+        loop {
+            let ret = unsafe { sgx_read_rand(buf.as_mut_ptr(), len) };
+            if ret != sgx_status_t::SGX_SUCCESS {
+                return Err(ret);
+            }
+            if bits % 8 != 0 {
+                buf[0] &= (1_u8 << (bits % 8)) - 1;
+            }
+            let mut k = from_be_bytes::<N>(&buf[..len]);
+            unsafe { ptr::write_volatile(&mut buf, [0_u8; 8 * 9]) };
+            if self.is_valid_scalar(&k) {
+                return Ok(k);
+            }
+            wipe(&mut k);
+        }
+    }
+
+    /// Computes the ECDSA signature (r, s) of the hash value e with private key d.
+    pub(crate) fn ecdsa_sign<F>(
+        &self,
+        d: &Limbs<N>,
+        e: &Limbs<N>,
+        mut next_k: F,
+    ) -> SgxResult<(Limbs<N>, Limbs<N>)>
+    where
+        F: FnMut() -> SgxResult<Limbs<N>>,
+    {
+        if !self.is_valid_scalar(d) {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let n = &self.n;
+        let g = self.generator();
+        let e_m = n.reduce_mont_form(e);
+        let mut d_m = n.mont_form(d);
+        loop {
+            let mut k = match next_k() {
+                Ok(k) => k,
+                Err(err) => {
+                    wipe(&mut d_m);
+                    return Err(err);
+                }
+            };
+            // Every path that draws another nonce wipes this one first.
+            let mut kg = self.mul(&g, &k);
+            let affine = self.to_affine(&kg);
+            kg.wipe();
+            let x = match affine {
+                Some((x, mut y)) => {
+                    wipe(&mut y);
+                    x
+                }
+                None => {
+                    wipe(&mut k);
+                    continue;
+                }
+            };
+            let r_m = n.reduce_mont_form(&x);
+            let r = n.normal_form(&r_m);
+            if is_zero(&r) == 1 {
+                wipe(&mut k);
+                continue;
+            }
+            let mut k_m = n.mont_form(&k);
+            let mut kinv = n.invert(&k_m);
+            let s = n.normal_form(&n.mul(&kinv, &n.add(&e_m, &n.mul(&r_m, &d_m))));
+            wipe(&mut k);
+            wipe(&mut k_m);
+            wipe(&mut kinv);
+            if is_zero(&s) == 1 {
+                continue;
+            }
+            wipe(&mut d_m);
+            return Ok((r, s));
+        }
+    }
+
+    pub(crate) fn ecdsa_verify(
+        &self,
+        q: &Point<N>,
+        e: &Limbs<N>,
+        r: &Limbs<N>,
+        s: &Limbs<N>,
+    ) -> bool {
+        if !self.is_valid_scalar(r) || !self.is_valid_scalar(s) {
+            return false;
+        }
+        let n = &self.n;
+        let w = n.invert(&n.mont_form(s));
+        let u1 = n.normal_form(&n.mul(&n.reduce_mont_form(e), &w));
+        let u2 = n.normal_form(&n.mul(&n.mont_form(r), &w));
+        let pt = self.add(&self.mul(&self.generator(), &u1), &self.mul(q, &u2));
+        match self.to_affine(&pt) {
+            Some((x, _)) => ct_eq(&n.normal_form(&n.reduce_mont_form(&x)), r) == 1,
+            None => false,
+        }
+    }
+}
+
+/// The HMAC_DRBG nonce generator of RFC 6979, section 3.2, for curves whose order has
+/// as many bits as the hash output.
+pub(crate) struct Rfc6979<H: HmacDigest> {
+    k: [u8; MAX_HASH_SIZE],
+    v: [u8; MAX_HASH_SIZE],
+    _hash: core::marker::PhantomData<H>,
+}
+
+impl<H: HmacDigest> Rfc6979<H> {
+    /// x is the private key and h1 the reduced message hash, both big-endian, hash sized.
+    pub(crate) fn new(x: &[u8], h1: &[u8]) -> SgxResult<Self> {
+        let hlen = H::HASH_SIZE;
+        let mut drbg = Rfc6979 {
+            k: [0_u8; MAX_HASH_SIZE],
+            v: [0_u8; MAX_HASH_SIZE],
+            _hash: core::marker::PhantomData,
+        };
+        drbg.v[..hlen].iter_mut().for_each(|b| *b = 1);
+        for tag in [0_u8, 1_u8].iter() {
+            let v = drbg.v;
+            Hmac::<H>::new(&drbg.k[..hlen])?.mac(&[&v[..hlen], &[*tag], x, h1], &mut drbg.k)?;
+            drbg.update_v()?;
+        }
+        Ok(drbg)
+    }
+
+    fn update_v(&mut self) -> SgxError {
+        let hlen = H::HASH_SIZE;
+        let v = self.v;
+        Hmac::<H>::new(&self.k[..hlen])?.mac(&[&v[..hlen]], &mut self.v)
+    }
+
+    /// Returns the next candidate scalar accepted by the curve.
+    pub(crate) fn next<const N: usize>(&mut self, curve: &Curve<N>) -> SgxResult<Limbs<N>> {
+        let hlen = H::HASH_SIZE;
+        loop {
+            self.update_v()?;
+            let k = from_be_bytes::<N>(&self.v[..hlen]);
+            // Prepare the state for the next call whether or not k is used.
+            let v = self.v;
+            Hmac::<H>::new(&self.k[..hlen])?.mac(&[&v[..hlen], &[0_u8]], &mut self.k)?;
+            self.update_v()?;
+            if curve.is_valid_scalar(&k) {
+                return Ok(k);
+            }
+        }
+    }
+}
+
+impl<H: HmacDigest> Drop for Rfc6979<H> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::write_volatile(&mut self.k, [0_u8; MAX_HASH_SIZE]);
+            ptr::write_volatile(&mut self.v, [0_u8; MAX_HASH_SIZE]);
+        }
+    }
+}
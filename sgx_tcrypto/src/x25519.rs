@@ -0,0 +1,196 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! X25519 key agreement
+//!
+//! X25519 as specified in RFC 7748. The Montgomery ladder runs in constant time, and the
+//! private key and shared secret are wiped when they are dropped.
+//!
+use super::curve25519::Fe;
+use super::policy::{check_algorithm, SgxAlgorithm};
+use core::fmt;
+use core::ptr;
+use sgx_types::*;
+
+pub type sgx_x25519_public_t = [uint8_t; 32];
+
+const BASE_POINT: sgx_x25519_public_t = [
+    9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+///
+/// rsgx_x25519 computes the X25519 function of a scalar and a u-coordinate.
+///
+/// The scalar is clamped as RFC 7748 requires. Most callers should use
+/// SgxX25519PrivateKey instead.
+///
+pub fn rsgx_x25519(scalar: &[u8; 32], u: &[u8; 32]) -> [u8; 32] {
+    let mut k = *scalar;
+    k[0] &= 248;
+    k[31] &= 127;
+    k[31] |= 64;
+
+    let x1 = Fe::from_bytes(u);
+    let mut x2 = Fe::ONE;
+    let mut z2 = Fe::ZERO;
+    let mut x3 = x1;
+    let mut z3 = Fe::ONE;
+    let a24 = Fe::from_u64(121665);
+    let mut swap = 0_u8;
+
+    for t in (0..255).rev() {
+        let bit = (k[t >> 3] >> (t & 7)) & 1;
+        swap ^= bit;
+        Fe::cswap(&mut x2, &mut x3, swap);
+        Fe::cswap(&mut z2, &mut z3, swap);
+        swap = bit;
+
+        let a = x2.add(&z2);
+        let aa = a.square();
+        let b = x2.sub(&z2);
+        let bb = b.square();
+        let e = aa.sub(&bb);
+        let c = x3.add(&z3);
+        let d = x3.sub(&z3);
+        let da = d.mul(&a);
+        let cb = c.mul(&b);
+        x3 = da.add(&cb).square();
+        z3 = x1.mul(&da.sub(&cb).square());
+        x2 = aa.mul(&bb);
+        z2 = e.mul(&aa.add(&a24.mul(&e)));
+    }
+    Fe::cswap(&mut x2, &mut x3, swap);
+    Fe::cswap(&mut z2, &mut z3, swap);
+
+    let out = x2.mul(&z2.invert()).to_bytes();
+    x2.wipe();
+    z2.wipe();
+    x3.wipe();
+    z3.wipe();
+    unsafe { ptr::write_volatile(&mut k, [0_u8; 32]) };
+    out
+}
+
+///
+/// An X25519 private key. Debug output does not show any key material.
+///
+pub struct SgxX25519PrivateKey {
+    scalar: [u8; 32],
+}
+
+impl SgxX25519PrivateKey {
+    ///
+    /// generate creates a new private key from the trusted random number generator.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The random number generator failed.
+    ///
+    pub fn generate() -> SgxResult<SgxX25519PrivateKey> {
+        check_algorithm(SgxAlgorithm::X25519)?;
+        let mut scalar = [0_u8; 32];
+        let ret = unsafe { sgx_read_rand(scalar.as_mut_ptr(), scalar.len()) };
+        match ret {
+            sgx_status_t::SGX_SUCCESS => Ok(SgxX25519PrivateKey { scalar }),
+            _ => Err(ret),
+        }
+    }
+
+    ///
+    /// from_bytes uses 32 bytes as the private key.
+    ///
+    pub fn from_bytes(bytes: &[u8; 32]) -> SgxX25519PrivateKey {
+        SgxX25519PrivateKey { scalar: *bytes }
+    }
+
+    ///
+    /// to_bytes returns the private key, for example to seal it.
+    ///
+    pub fn to_bytes(&self) -> &[u8; 32] {
+        &self.scalar
+    }
+
+    ///
+    /// public_key returns the public key that is sent to the peer.
+    ///
+    pub fn public_key(&self) -> sgx_x25519_public_t {
+        rsgx_x25519(&self.scalar, &BASE_POINT)
+    }
+
+    ///
+    /// diffie_hellman computes the shared secret with the peer's public key.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The peer's public key is a low order point, so the result would be all zero.
+    ///
+    pub fn diffie_hellman(&self, peer: &sgx_x25519_public_t) -> SgxResult<SgxX25519SharedSecret> {
+        check_algorithm(SgxAlgorithm::X25519)?;
+        let shared = SgxX25519SharedSecret {
+            s: rsgx_x25519(&self.scalar, peer),
+        };
+        if shared.s.iter().fold(0, |acc, b| acc | b) == 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(shared)
+    }
+}
+
+impl fmt::Debug for SgxX25519PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxX25519PrivateKey")
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SgxX25519PrivateKey {
+    fn drop(&mut self) {
+        unsafe { ptr::write_volatile(&mut self.scalar, [0_u8; 32]) };
+    }
+}
+
+///
+/// The result of an X25519 key agreement. It should be passed through a KDF such as
+/// rsgx_hkdf_sha256 before use.
+///
+pub struct SgxX25519SharedSecret {
+    s: [u8; 32],
+}
+
+impl SgxX25519SharedSecret {
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.s
+    }
+}
+
+impl fmt::Debug for SgxX25519SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxX25519SharedSecret")
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SgxX25519SharedSecret {
+    fn drop(&mut self) {
+        unsafe { ptr::write_volatile(&mut self.s, [0_u8; 32]) };
+    }
+}
@@ -0,0 +1,1274 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! X.509 certificates
+//!
+//! A small builder and parser for the X.509 v3 certificates of RA-TLS and of client
+//! authentication, with ECDSA P-256 and P-384 keys. The builder signs with the enclave
+//! keys directly; the parser checks the DER encoding strictly and gives access to the
+//! names, the validity, the key, the subject alternative names and any other extension.
+//! Certificate revocation lists are parsed likewise.
+//!
+//! Times are seconds since the Unix epoch. The enclave has no trusted clock, so
+//! checking the validity of a certificate is left to the caller.
+//!
+use super::crypto::SgxEccHandle;
+use super::ecdsa::{rsgx_ecdsa384_sign_slice, rsgx_ecdsa384_verify_slice, SgxEcdsaNonce};
+use alloc::string::String;
+use alloc::vec::Vec;
+use sgx_types::*;
+
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const OID_PRIME256V1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_SECP384R1: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x22];
+const OID_ECDSA_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+const OID_ECDSA_WITH_SHA384: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03];
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+const OID_COUNTRY: &[u8] = &[0x55, 0x04, 0x06];
+const OID_ORGANIZATION: &[u8] = &[0x55, 0x04, 0x0a];
+const OID_ORGANIZATIONAL_UNIT: &[u8] = &[0x55, 0x04, 0x0b];
+
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_UTF8_STRING: u8 = 0x0c;
+const TAG_PRINTABLE_STRING: u8 = 0x13;
+const TAG_T61_STRING: u8 = 0x14;
+const TAG_IA5_STRING: u8 = 0x16;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_BMP_STRING: u8 = 0x1e;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_VERSION: u8 = 0xa0;
+const TAG_EXTENSIONS: u8 = 0xa3;
+const TAG_DNS_NAME: u8 = 0x82;
+const TAG_URI: u8 = 0x86;
+const TAG_IP_ADDRESS: u8 = 0x87;
+
+// 9999-12-31T23:59:59Z, which RFC 5280 reserves for certificates that do not expire.
+const NO_EXPIRY: u64 = 253_402_300_799;
+
+fn malformed<T>() -> SgxResult<T> {
+    Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(content.len() + 6);
+    out.push(tag);
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = (len as u32).to_be_bytes();
+        let skip = (len as u32).leading_zeros() as usize / 8;
+        out.push(0x80 | (4 - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn constructed(tag: u8, parts: &[&[u8]]) -> Vec<u8> {
+    tlv(tag, &parts.concat())
+}
+
+// A DER INTEGER holding the big-endian unsigned value.
+fn unsigned_integer(be: &[u8]) -> Vec<u8> {
+    let start = be.iter().position(|b| *b != 0).unwrap_or(be.len() - 1);
+    let mut content = Vec::with_capacity(be.len() + 1);
+    if be[start] & 0x80 != 0 {
+        content.push(0);
+    }
+    content.extend_from_slice(&be[start..]);
+    tlv(TAG_INTEGER, &content)
+}
+
+fn encode_oid(arcs: &[u64]) -> Vec<u8> {
+    let mut out = Vec::new();
+    if arcs.len() < 2 {
+        return out;
+    }
+    let mut push = |mut arc: u64| {
+        let mut tmp = [0_u8; 10];
+        let mut n = 0;
+        loop {
+            tmp[n] = (arc & 0x7f) as u8 | if n > 0 { 0x80 } else { 0 };
+            n += 1;
+            arc >>= 7;
+            if arc == 0 {
+                break;
+            }
+        }
+        out.extend(tmp[..n].iter().rev());
+    };
+    push(arcs[0] * 40 + arcs[1]);
+    for arc in &arcs[2..] {
+        push(*arc);
+    }
+    out
+}
+
+// A strict DER reader over the contents of one constructed value.
+struct Der<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn new(data: &'a [u8]) -> Der<'a> {
+        Der { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    // Returns the tag, the contents and the whole encoding of the next value.
+    fn read_any(&mut self) -> SgxResult<(u8, &'a [u8], &'a [u8])> {
+        let data = self.data;
+        if data.len() < 2 || data[0] & 0x1f == 0x1f {
+            return malformed();
+        }
+        let (len, header) = match data[1] {
+            n if n < 0x80 => (n as usize, 2),
+            n @ 0x81..=0x84 => {
+                let count = (n & 0x7f) as usize;
+                if data.len() < 2 + count || data[2] == 0 {
+                    return malformed();
+                }
+                let len = data[2..2 + count]
+                    .iter()
+                    .fold(0_usize, |acc, b| (acc << 8) | *b as usize);
+                if len < 0x80 {
+                    return malformed();
+                }
+                (len, 2 + count)
+            }
+            _ => return malformed(),
+        };
+        if data.len() - header < len {
+            return malformed();
+        }
+        self.data = &data[header + len..];
+        Ok((data[0], &data[header..header + len], &data[..header + len]))
+    }
+
+    fn read(&mut self, tag: u8) -> SgxResult<&'a [u8]> {
+        match self.read_any()? {
+            (t, content, _) if t == tag => Ok(content),
+            _ => malformed(),
+        }
+    }
+
+    fn read_optional(&mut self, tag: u8) -> SgxResult<Option<&'a [u8]>> {
+        if self.peek_tag() == Some(tag) {
+            self.read(tag).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn finish(&self) -> SgxError {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            malformed()
+        }
+    }
+}
+
+// The big-endian magnitude of a non-negative, minimally encoded INTEGER.
+fn read_unsigned<'a>(der: &mut Der<'a>) -> SgxResult<&'a [u8]> {
+    let content = der.read(TAG_INTEGER)?;
+    match content {
+        [] => malformed(),
+        [b, ..] if b & 0x80 != 0 => malformed(),
+        [0, b, ..] if b & 0x80 == 0 => malformed(),
+        [0, rest @ ..] if !rest.is_empty() => Ok(rest),
+        _ => Ok(content),
+    }
+}
+
+fn days_from_civil(y: u64, m: u64, d: u64) -> u64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let mp = if m > 2 { m - 3 } else { m + 9 };
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(z: u64) -> (u64, u64, u64) {
+    let z = z + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + u64::from(m <= 2);
+    (y, m, d)
+}
+
+fn days_in_month(y: u64, m: u64) -> u64 {
+    match m {
+        2 if y % 4 == 0 && (y % 100 != 0 || y % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// UTCTime up to 2049, as RFC 5280 requires, and GeneralizedTime after.
+fn encode_time(time: u64) -> Vec<u8> {
+    let time = core::cmp::min(time, NO_EXPIRY);
+    let (y, mo, d) = civil_from_days(time / 86400);
+    let secs = time % 86400;
+    let mut digits = Vec::with_capacity(15);
+    let mut push = |v: u64, n: u32| {
+        for i in (0..n).rev() {
+            digits.push(b'0' + (v / 10_u64.pow(i) % 10) as u8);
+        }
+    };
+    let tag = if y < 2050 {
+        push(y % 100, 2);
+        TAG_UTC_TIME
+    } else {
+        push(y, 4);
+        TAG_GENERALIZED_TIME
+    };
+    push(mo, 2);
+    push(d, 2);
+    push(secs / 3600, 2);
+    push(secs / 60 % 60, 2);
+    push(secs % 60, 2);
+    digits.push(b'Z');
+    tlv(tag, &digits)
+}
+
+fn read_time(der: &mut Der) -> SgxResult<u64> {
+    let (tag, content, _) = der.read_any()?;
+    let (year_digits, len) = match tag {
+        TAG_UTC_TIME => (2, 13),
+        TAG_GENERALIZED_TIME => (4, 15),
+        _ => return malformed(),
+    };
+    if content.len() != len
+        || content[len - 1] != b'Z'
+        || !content[..len - 1].iter().all(u8::is_ascii_digit)
+    {
+        return malformed();
+    }
+    let num = |from: usize, n: usize| {
+        content[from..from + n]
+            .iter()
+            .fold(0_u64, |acc, c| acc * 10 + u64::from(c - b'0'))
+    };
+    let mut y = num(0, year_digits);
+    if year_digits == 2 {
+        y += if y < 50 { 2000 } else { 1900 };
+    }
+    let f = year_digits;
+    let (mo, d, h, mi, s) = (
+        num(f, 2),
+        num(f + 2, 2),
+        num(f + 4, 2),
+        num(f + 6, 2),
+        num(f + 8, 2),
+    );
+    if y < 1970
+        || mo == 0
+        || mo > 12
+        || d == 0
+        || d > days_in_month(y, mo)
+        || h > 23
+        || mi > 59
+        || s > 59
+    {
+        return malformed();
+    }
+    Ok(days_from_civil(y, mo, d) * 86400 + h * 3600 + mi * 60 + s)
+}
+
+fn read_string(tag: u8, content: &[u8]) -> SgxResult<String> {
+    match tag {
+        TAG_UTF8_STRING | TAG_PRINTABLE_STRING | TAG_IA5_STRING | TAG_T61_STRING => {
+            String::from_utf8(content.to_vec()).or_else(|_| malformed())
+        }
+        TAG_BMP_STRING if content.len() % 2 == 0 => {
+            let units = content.chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]]));
+            core::char::decode_utf16(units)
+                .collect::<Result<String, _>>()
+                .or_else(|_| malformed())
+        }
+        _ => malformed(),
+    }
+}
+
+fn be_from_le(le: &[u8]) -> Vec<u8> {
+    le.iter().rev().copied().collect()
+}
+
+fn le_from_be(be: &[u8], out: &mut [u8]) -> SgxError {
+    if be.len() > out.len() {
+        return malformed();
+    }
+    for (o, b) in out.iter_mut().zip(be.iter().rev()) {
+        *o = *b;
+    }
+    Ok(())
+}
+
+fn words_le_bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|w| w.to_le_bytes()).collect()
+}
+
+fn words_from_be(be: &[u8], out: &mut [u32]) -> SgxError {
+    let mut le = [0_u8; 48];
+    le_from_be(be, &mut le[..4 * out.len()])?;
+    for (w, chunk) in out.iter_mut().zip(le.chunks(4)) {
+        *w = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    Ok(())
+}
+
+///
+/// A distinguished name, the issuer or subject of a certificate.
+///
+/// Each attribute is its own relative distinguished name, in the order added.
+///
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SgxX509Name {
+    entries: Vec<(Vec<u8>, String)>,
+}
+
+impl SgxX509Name {
+    pub fn new() -> SgxX509Name {
+        SgxX509Name::default()
+    }
+
+    /// Adds a commonName (2.5.4.3).
+    pub fn common_name(self, value: &str) -> SgxX509Name {
+        self.push(OID_COMMON_NAME, value)
+    }
+
+    /// Adds an organizationName (2.5.4.10).
+    pub fn organization(self, value: &str) -> SgxX509Name {
+        self.push(OID_ORGANIZATION, value)
+    }
+
+    /// Adds an organizationalUnitName (2.5.4.11).
+    pub fn organizational_unit(self, value: &str) -> SgxX509Name {
+        self.push(OID_ORGANIZATIONAL_UNIT, value)
+    }
+
+    /// Adds a countryName (2.5.4.6), a two letter code.
+    pub fn country(self, value: &str) -> SgxX509Name {
+        self.push(OID_COUNTRY, value)
+    }
+
+    /// Adds an attribute of any type, given by the arcs of its object identifier.
+    pub fn attribute(self, oid: &[u64], value: &str) -> SgxX509Name {
+        self.push(&encode_oid(oid), value)
+    }
+
+    /// Returns the first attribute of the given type.
+    pub fn get(&self, oid: &[u64]) -> Option<&str> {
+        self.find(&encode_oid(oid))
+    }
+
+    /// Returns the first commonName.
+    pub fn get_common_name(&self) -> Option<&str> {
+        self.find(OID_COMMON_NAME)
+    }
+
+    fn push(mut self, oid: &[u8], value: &str) -> SgxX509Name {
+        self.entries.push((oid.to_vec(), String::from(value)));
+        self
+    }
+
+    fn find(&self, oid: &[u8]) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(o, _)| o.as_slice() == oid)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        let rdns: Vec<Vec<u8>> = self
+            .entries
+            .iter()
+            .map(|(oid, value)| {
+                let tag = if oid.as_slice() == OID_COUNTRY {
+                    TAG_PRINTABLE_STRING
+                } else {
+                    TAG_UTF8_STRING
+                };
+                let attr = constructed(
+                    TAG_SEQUENCE,
+                    &[&tlv(TAG_OID, oid), &tlv(tag, value.as_bytes())],
+                );
+                tlv(TAG_SET, &attr)
+            })
+            .collect();
+        tlv(TAG_SEQUENCE, &rdns.concat())
+    }
+
+    fn from_der(content: &[u8]) -> SgxResult<SgxX509Name> {
+        let mut name = SgxX509Name::new();
+        let mut rdns = Der::new(content);
+        while !rdns.is_empty() {
+            let mut set = Der::new(rdns.read(TAG_SET)?);
+            if set.is_empty() {
+                return malformed();
+            }
+            while !set.is_empty() {
+                let mut attr = Der::new(set.read(TAG_SEQUENCE)?);
+                let oid = attr.read(TAG_OID)?;
+                let (tag, value, _) = attr.read_any()?;
+                attr.finish()?;
+                name.entries.push((oid.to_vec(), read_string(tag, value)?));
+            }
+        }
+        Ok(name)
+    }
+}
+
+///
+/// A subject alternative name.
+///
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SgxX509AltName {
+    /// A DNS host name.
+    Dns(String),
+    /// A URI.
+    Uri(String),
+    /// An IPv4 or IPv6 address, as 4 or 16 bytes in network order.
+    Ip(Vec<u8>),
+}
+
+///
+/// The public key of a certificate.
+///
+#[derive(Clone, Copy)]
+pub enum SgxX509PublicKey {
+    EcP256(sgx_ec256_public_t),
+    EcP384(sgx_ec384_public_t),
+}
+
+impl SgxX509PublicKey {
+    fn to_der(self) -> Vec<u8> {
+        let (curve, x, y) = match self {
+            SgxX509PublicKey::EcP256(k) => (OID_PRIME256V1, be_from_le(&k.gx), be_from_le(&k.gy)),
+            SgxX509PublicKey::EcP384(k) => (OID_SECP384R1, be_from_le(&k.gx), be_from_le(&k.gy)),
+        };
+        let algorithm = constructed(
+            TAG_SEQUENCE,
+            &[&tlv(TAG_OID, OID_EC_PUBLIC_KEY), &tlv(TAG_OID, curve)],
+        );
+        let point = [&[0_u8, 0x04][..], &x, &y].concat();
+        constructed(TAG_SEQUENCE, &[&algorithm, &tlv(TAG_BIT_STRING, &point)])
+    }
+
+    // Returns None for key types other than P-256 and P-384.
+    fn from_der(content: &[u8]) -> SgxResult<Option<SgxX509PublicKey>> {
+        let mut spki = Der::new(content);
+        let mut algorithm = Der::new(spki.read(TAG_SEQUENCE)?);
+        let bits = spki.read(TAG_BIT_STRING)?;
+        spki.finish()?;
+        if algorithm.read(TAG_OID)? != OID_EC_PUBLIC_KEY {
+            return Ok(None);
+        }
+        let curve = algorithm.read(TAG_OID)?;
+        algorithm.finish()?;
+        let point = match bits {
+            [0, 0x04, point @ ..] => point,
+            _ => return Ok(None),
+        };
+        if curve == OID_PRIME256V1 && point.len() == 64 {
+            let mut key = sgx_ec256_public_t::default();
+            le_from_be(&point[..32], &mut key.gx)?;
+            le_from_be(&point[32..], &mut key.gy)?;
+            Ok(Some(SgxX509PublicKey::EcP256(key)))
+        } else if curve == OID_SECP384R1 && point.len() == 96 {
+            let mut key = sgx_ec384_public_t::default();
+            le_from_be(&point[..48], &mut key.gx)?;
+            le_from_be(&point[48..], &mut key.gy)?;
+            Ok(Some(SgxX509PublicKey::EcP384(key)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+///
+/// The private key that signs a certificate.
+///
+/// P-256 certificates are signed with ecdsa-with-SHA256 and P-384 certificates with
+/// ecdsa-with-SHA384.
+///
+#[derive(Clone, Copy)]
+pub enum SgxX509SigningKey<'a> {
+    EcP256(&'a sgx_ec256_private_t),
+    EcP384(&'a sgx_ec384_private_t),
+}
+
+///
+/// Builds and signs an X.509 v3 certificate.
+///
+/// # Description
+///
+/// A certificate needs a subject and a public key; everything else has a default.
+/// The issuer defaults to the subject, which makes a self-signed certificate as used
+/// by RA-TLS. The serial number defaults to 16 random bytes, and the validity to the
+/// whole range from the Unix epoch to the 9999-12-31 value that RFC 5280 reserves for
+/// certificates without an expiry date.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sgx_tcrypto::*;
+///
+/// let handle = SgxEccHandle::new();
+/// handle.open().unwrap();
+/// let (private, public) = handle.create_key_pair().unwrap();
+/// let cert = SgxX509Builder::new(
+///     SgxX509Name::new().common_name("enclave"),
+///     SgxX509PublicKey::EcP256(public),
+/// )
+/// .subject_alt_name(SgxX509AltName::Dns("enclave.example".into()))
+/// .extension(&[2, 16, 840, 1, 113730, 1, 13], false, b"attestation report")
+/// .sign(SgxX509SigningKey::EcP256(&private))
+/// .unwrap();
+/// ```
+///
+#[derive(Clone)]
+pub struct SgxX509Builder {
+    subject: SgxX509Name,
+    issuer: Option<SgxX509Name>,
+    public_key: SgxX509PublicKey,
+    serial: Option<Vec<u8>>,
+    not_before: u64,
+    not_after: u64,
+    alt_names: Vec<SgxX509AltName>,
+    extensions: Vec<(Vec<u8>, bool, Vec<u8>)>,
+}
+
+impl SgxX509Builder {
+    pub fn new(subject: SgxX509Name, public_key: SgxX509PublicKey) -> SgxX509Builder {
+        SgxX509Builder {
+            subject,
+            issuer: None,
+            public_key,
+            serial: None,
+            not_before: 0,
+            not_after: NO_EXPIRY,
+            alt_names: Vec::new(),
+            extensions: Vec::new(),
+        }
+    }
+
+    /// Sets the issuer, for a certificate signed by a key other than its own.
+    pub fn issuer(mut self, issuer: SgxX509Name) -> SgxX509Builder {
+        self.issuer = Some(issuer);
+        self
+    }
+
+    /// Sets the serial number, a big-endian unsigned number of at most 20 bytes.
+    pub fn serial_number(mut self, serial: &[u8]) -> SgxX509Builder {
+        self.serial = Some(serial.to_vec());
+        self
+    }
+
+    /// Sets the validity period.
+    pub fn validity(mut self, not_before: u64, not_after: u64) -> SgxX509Builder {
+        self.not_before = not_before;
+        self.not_after = not_after;
+        self
+    }
+
+    /// Adds a subject alternative name.
+    pub fn subject_alt_name(mut self, name: SgxX509AltName) -> SgxX509Builder {
+        self.alt_names.push(name);
+        self
+    }
+
+    /// Adds an extension with the DER encoded value, such as an attestation report.
+    pub fn extension(mut self, oid: &[u64], critical: bool, value: &[u8]) -> SgxX509Builder {
+        self.extensions
+            .push((encode_oid(oid), critical, value.to_vec()));
+        self
+    }
+
+    ///
+    /// Signs the certificate and returns its DER encoding.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The serial number is empty or longer than 20 bytes, an extension has an invalid
+    /// object identifier or appears twice, the validity ends before it starts, or the
+    /// private key is invalid.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The random number generator or the signature failed.
+    ///
+    pub fn sign(&self, key: SgxX509SigningKey) -> SgxResult<Vec<u8>> {
+        let serial = match &self.serial {
+            Some(serial) if !serial.is_empty() && serial.len() <= 20 => serial.clone(),
+            Some(_) => return malformed(),
+            None => {
+                let mut serial = [0_u8; 16];
+                let ret = unsafe { sgx_read_rand(serial.as_mut_ptr(), serial.len()) };
+                if ret != sgx_status_t::SGX_SUCCESS {
+                    return Err(ret);
+                }
+                serial[0] = (serial[0] & 0x7f) | 0x40;
+                serial.to_vec()
+            }
+        };
+        if self.not_after < self.not_before {
+            return malformed();
+        }
+
+        let mut extensions = Vec::new();
+        if !self.alt_names.is_empty() {
+            let names: Vec<Vec<u8>> = self
+                .alt_names
+                .iter()
+                .map(|name| match name {
+                    SgxX509AltName::Dns(dns) => tlv(TAG_DNS_NAME, dns.as_bytes()),
+                    SgxX509AltName::Uri(uri) => tlv(TAG_URI, uri.as_bytes()),
+                    SgxX509AltName::Ip(ip) => tlv(TAG_IP_ADDRESS, ip),
+                })
+                .collect();
+            let value = tlv(TAG_SEQUENCE, &names.concat());
+            extensions.push((OID_SUBJECT_ALT_NAME.to_vec(), false, value));
+        }
+        extensions.extend(self.extensions.iter().cloned());
+        for (i, (oid, _, _)) in extensions.iter().enumerate() {
+            if oid.is_empty() || extensions[..i].iter().any(|(o, _, _)| o == oid) {
+                return malformed();
+            }
+        }
+        let extensions: Vec<Vec<u8>> = extensions
+            .iter()
+            .map(|(oid, critical, value)| {
+                let critical = if *critical {
+                    tlv(TAG_BOOLEAN, &[0xff])
+                } else {
+                    Vec::new()
+                };
+                constructed(
+                    TAG_SEQUENCE,
+                    &[&tlv(TAG_OID, oid), &critical, &tlv(TAG_OCTET_STRING, value)],
+                )
+            })
+            .collect();
+
+        let algorithm_oid = match key {
+            SgxX509SigningKey::EcP256(_) => OID_ECDSA_WITH_SHA256,
+            SgxX509SigningKey::EcP384(_) => OID_ECDSA_WITH_SHA384,
+        };
+        let algorithm = constructed(TAG_SEQUENCE, &[&tlv(TAG_OID, algorithm_oid)]);
+        let issuer = self.issuer.as_ref().unwrap_or(&self.subject);
+        let validity = constructed(
+            TAG_SEQUENCE,
+            &[&encode_time(self.not_before), &encode_time(self.not_after)],
+        );
+        let extensions = if extensions.is_empty() {
+            Vec::new()
+        } else {
+            tlv(TAG_EXTENSIONS, &tlv(TAG_SEQUENCE, &extensions.concat()))
+        };
+        let tbs = constructed(
+            TAG_SEQUENCE,
+            &[
+                &tlv(TAG_VERSION, &tlv(TAG_INTEGER, &[2])),
+                &unsigned_integer(&serial),
+                &algorithm,
+                &issuer.to_der(),
+                &validity,
+                &self.subject.to_der(),
+                &self.public_key.to_der(),
+                &extensions,
+            ],
+        );
+
+        let (r, s) = match key {
+            SgxX509SigningKey::EcP256(private) => {
+                let handle = SgxEccHandle::new();
+                handle.open()?;
+                let signature = handle.ecdsa_sign_slice(&tbs, private)?;
+                (words_le_bytes(&signature.x), words_le_bytes(&signature.y))
+            }
+            SgxX509SigningKey::EcP384(private) => {
+                let signature = rsgx_ecdsa384_sign_slice(&tbs, private, SgxEcdsaNonce::Random)?;
+                (words_le_bytes(&signature.x), words_le_bytes(&signature.y))
+            }
+        };
+        let signature = constructed(
+            TAG_SEQUENCE,
+            &[
+                &unsigned_integer(&be_from_le(&r)),
+                &unsigned_integer(&be_from_le(&s)),
+            ],
+        );
+        let signature = [&[0_u8][..], &signature].concat();
+        Ok(constructed(
+            TAG_SEQUENCE,
+            &[&tbs, &algorithm, &tlv(TAG_BIT_STRING, &signature)],
+        ))
+    }
+}
+
+///
+/// A parsed X.509 certificate.
+///
+/// # Description
+///
+/// Parsing checks that the certificate is strict DER and has the X.509 structure, but
+/// not its signature, which is checked with verify_signature once the issuer key is
+/// known, nor its validity period. Extensions may appear at most once each. Keys
+/// other than P-256 and P-384 are accepted, but have no public_key.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sgx_tcrypto::*;
+///
+/// # let der: &[u8] = &[];
+/// let cert = SgxX509Certificate::parse(der).unwrap();
+/// let key = cert.public_key().unwrap();
+/// assert!(cert.verify_signature(key).unwrap());
+/// let report = cert.extension(&[2, 16, 840, 1, 113730, 1, 13]);
+/// ```
+///
+pub struct SgxX509Certificate<'a> {
+    der: &'a [u8],
+    tbs: &'a [u8],
+    serial: &'a [u8],
+    signature_algorithm: &'a [u8],
+    issuer: SgxX509Name,
+    subject: SgxX509Name,
+    not_before: u64,
+    not_after: u64,
+    public_key: Option<SgxX509PublicKey>,
+    extensions: Vec<(&'a [u8], bool, &'a [u8])>,
+    signature: &'a [u8],
+}
+
+impl<'a> SgxX509Certificate<'a> {
+    ///
+    /// Parses a DER encoded certificate.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// der is not a well-formed X.509 certificate.
+    ///
+    pub fn parse(der: &'a [u8]) -> SgxResult<SgxX509Certificate<'a>> {
+        let mut outer = Der::new(der);
+        let mut cert = Der::new(outer.read(TAG_SEQUENCE)?);
+        outer.finish()?;
+        let (tag, tbs_content, tbs) = cert.read_any()?;
+        if tag != TAG_SEQUENCE {
+            return malformed();
+        }
+        let signature_algorithm = cert.read(TAG_SEQUENCE)?;
+        let signature = match cert.read(TAG_BIT_STRING)? {
+            [0, signature @ ..] => signature,
+            _ => return malformed(),
+        };
+        cert.finish()?;
+
+        let mut tbs_der = Der::new(tbs_content);
+        let version = match tbs_der.read_optional(TAG_VERSION)? {
+            Some(version) => {
+                let mut version = Der::new(version);
+                let v = version.read(TAG_INTEGER)?;
+                version.finish()?;
+                match v {
+                    [1] | [2] => v[0],
+                    _ => return malformed(),
+                }
+            }
+            None => 0,
+        };
+        let serial = read_unsigned(&mut tbs_der)?;
+        if tbs_der.read(TAG_SEQUENCE)? != signature_algorithm {
+            return malformed();
+        }
+        let issuer = SgxX509Name::from_der(tbs_der.read(TAG_SEQUENCE)?)?;
+        let mut validity = Der::new(tbs_der.read(TAG_SEQUENCE)?);
+        let not_before = read_time(&mut validity)?;
+        let not_after = read_time(&mut validity)?;
+        validity.finish()?;
+        let subject = SgxX509Name::from_der(tbs_der.read(TAG_SEQUENCE)?)?;
+        let public_key = SgxX509PublicKey::from_der(tbs_der.read(TAG_SEQUENCE)?)?;
+        // The issuer and subject unique identifiers.
+        tbs_der.read_optional(0x81)?;
+        tbs_der.read_optional(0x82)?;
+
+        let mut extensions = Vec::new();
+        if let Some(content) = tbs_der.read_optional(TAG_EXTENSIONS)? {
+            if version != 2 {
+                return malformed();
+            }
+            let mut wrapper = Der::new(content);
+            let mut list = Der::new(wrapper.read(TAG_SEQUENCE)?);
+            wrapper.finish()?;
+            while !list.is_empty() {
+                let mut ext = Der::new(list.read(TAG_SEQUENCE)?);
+                let oid = ext.read(TAG_OID)?;
+                let critical = match ext.read_optional(TAG_BOOLEAN)? {
+                    Some([0xff]) => true,
+                    None => false,
+                    // DER leaves out a FALSE default.
+                    Some(_) => return malformed(),
+                };
+                let value = ext.read(TAG_OCTET_STRING)?;
+                ext.finish()?;
+                if extensions.iter().any(|(o, _, _)| *o == oid) {
+                    return malformed();
+                }
+                extensions.push((oid, critical, value));
+            }
+        }
+        tbs_der.finish()?;
+
+        Ok(SgxX509Certificate {
+            der,
+            tbs,
+            serial,
+            signature_algorithm,
+            issuer,
+            subject,
+            not_before,
+            not_after,
+            public_key,
+            extensions,
+            signature,
+        })
+    }
+
+    /// The whole DER encoding.
+    pub fn der(&self) -> &'a [u8] {
+        self.der
+    }
+
+    /// The DER encoding of the signed part, the TBSCertificate.
+    pub fn tbs_certificate(&self) -> &'a [u8] {
+        self.tbs
+    }
+
+    /// The serial number, big-endian and without leading zeros.
+    pub fn serial_number(&self) -> &'a [u8] {
+        self.serial
+    }
+
+    pub fn issuer(&self) -> &SgxX509Name {
+        &self.issuer
+    }
+
+    pub fn subject(&self) -> &SgxX509Name {
+        &self.subject
+    }
+
+    pub fn not_before(&self) -> u64 {
+        self.not_before
+    }
+
+    pub fn not_after(&self) -> u64 {
+        self.not_after
+    }
+
+    /// Whether time lies within the validity period.
+    pub fn is_valid_at(&self, time: u64) -> bool {
+        self.not_before <= time && time <= self.not_after
+    }
+
+    /// The subject public key, if it is a P-256 or P-384 key.
+    pub fn public_key(&self) -> Option<&SgxX509PublicKey> {
+        self.public_key.as_ref()
+    }
+
+    /// Returns whether the extension is critical and its DER encoded value.
+    pub fn extension(&self, oid: &[u64]) -> Option<(bool, &'a [u8])> {
+        let oid = encode_oid(oid);
+        self.extensions
+            .iter()
+            .find(|(o, _, _)| *o == oid.as_slice())
+            .map(|(_, critical, value)| (*critical, *value))
+    }
+
+    ///
+    /// Returns the DNS, URI and IP subject alternative names. Other kinds are skipped.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The extension is malformed.
+    ///
+    pub fn subject_alt_names(&self) -> SgxResult<Vec<SgxX509AltName>> {
+        let mut names = Vec::new();
+        let value = match self
+            .extensions
+            .iter()
+            .find(|(o, _, _)| *o == OID_SUBJECT_ALT_NAME)
+        {
+            Some((_, _, value)) => *value,
+            None => return Ok(names),
+        };
+        let mut outer = Der::new(value);
+        let mut list = Der::new(outer.read(TAG_SEQUENCE)?);
+        outer.finish()?;
+        while !list.is_empty() {
+            let (tag, content, _) = list.read_any()?;
+            match tag {
+                TAG_DNS_NAME => {
+                    names.push(SgxX509AltName::Dns(read_string(TAG_IA5_STRING, content)?))
+                }
+                TAG_URI => names.push(SgxX509AltName::Uri(read_string(TAG_IA5_STRING, content)?)),
+                TAG_IP_ADDRESS if content.len() == 4 || content.len() == 16 => {
+                    names.push(SgxX509AltName::Ip(content.to_vec()))
+                }
+                TAG_IP_ADDRESS => return malformed(),
+                _ => {}
+            }
+        }
+        Ok(names)
+    }
+
+    ///
+    /// Returns whether the basic constraints extension marks the subject as a CA, and
+    /// its path length constraint. A certificate without the extension is no CA.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The extension is malformed.
+    ///
+    pub fn basic_constraints(&self) -> SgxResult<(bool, Option<u32>)> {
+        let value = match self
+            .extensions
+            .iter()
+            .find(|(o, _, _)| *o == OID_BASIC_CONSTRAINTS)
+        {
+            Some((_, _, value)) => *value,
+            None => return Ok((false, None)),
+        };
+        let mut outer = Der::new(value);
+        let mut constraints = Der::new(outer.read(TAG_SEQUENCE)?);
+        outer.finish()?;
+        let ca = match constraints.read_optional(TAG_BOOLEAN)? {
+            Some([0xff]) => true,
+            None => false,
+            Some(_) => return malformed(),
+        };
+        let path_len = if constraints.is_empty() {
+            None
+        } else {
+            let len = read_unsigned(&mut constraints)?;
+            if len.len() > 4 {
+                return malformed();
+            }
+            Some(len.iter().fold(0, |acc, b| (acc << 8) | *b as u32))
+        };
+        constraints.finish()?;
+        Ok((ca, path_len))
+    }
+
+    ///
+    /// Checks the signature with the public key of the issuer, which is the key of the
+    /// certificate itself if it is self-signed.
+    ///
+    /// # Return value
+    ///
+    /// **true**
+    ///
+    /// The signature is valid.
+    ///
+    /// **false**
+    ///
+    /// The signature is invalid, or its algorithm does not match the key.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_OUT_OF_MEMORY**
+    ///
+    /// Not enough memory is available to complete this operation.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// An internal cryptography library function failed.
+    ///
+    pub fn verify_signature(&self, issuer_key: &SgxX509PublicKey) -> SgxResult<bool> {
+        verify_signed(
+            self.tbs,
+            self.signature_algorithm,
+            self.signature,
+            issuer_key,
+        )
+    }
+}
+
+// Checks an ECDSA signature over the signed part of a certificate or a CRL.
+fn verify_signed(
+    tbs: &[u8],
+    signature_algorithm: &[u8],
+    signature: &[u8],
+    issuer_key: &SgxX509PublicKey,
+) -> SgxResult<bool> {
+    let mut algorithm = Der::new(signature_algorithm);
+    let oid = algorithm.read(TAG_OID)?;
+    if !algorithm.is_empty() {
+        return Ok(false);
+    }
+    let mut outer = Der::new(signature);
+    let mut sig = match outer.read(TAG_SEQUENCE) {
+        Ok(sig) if outer.is_empty() => Der::new(sig),
+        _ => return Ok(false),
+    };
+    let (r, s) = match (read_unsigned(&mut sig), read_unsigned(&mut sig)) {
+        (Ok(r), Ok(s)) if sig.is_empty() => (r, s),
+        _ => return Ok(false),
+    };
+
+    match issuer_key {
+        SgxX509PublicKey::EcP256(key) if oid == OID_ECDSA_WITH_SHA256 => {
+            let mut signature = sgx_ec256_signature_t::default();
+            if words_from_be(r, &mut signature.x).is_err()
+                || words_from_be(s, &mut signature.y).is_err()
+            {
+                return Ok(false);
+            }
+            let handle = SgxEccHandle::new();
+            handle.open()?;
+            handle.ecdsa_verify_slice(tbs, key, &signature)
+        }
+        SgxX509PublicKey::EcP384(key) if oid == OID_ECDSA_WITH_SHA384 => {
+            let mut signature = sgx_ec384_signature_t::default();
+            if words_from_be(r, &mut signature.x).is_err()
+                || words_from_be(s, &mut signature.y).is_err()
+            {
+                return Ok(false);
+            }
+            rsgx_ecdsa384_verify_slice(tbs, key, &signature)
+        }
+        _ => Ok(false),
+    }
+}
+
+///
+/// A parsed X.509 v2 certificate revocation list, borrowing the DER encoding.
+///
+/// Entries and extensions are checked for well-formedness; only the serial numbers
+/// of the revoked certificates are kept.
+///
+pub struct SgxX509Crl<'a> {
+    der: &'a [u8],
+    tbs: &'a [u8],
+    signature_algorithm: &'a [u8],
+    issuer: SgxX509Name,
+    this_update: u64,
+    next_update: Option<u64>,
+    revoked: Vec<&'a [u8]>,
+    signature: &'a [u8],
+}
+
+impl<'a> SgxX509Crl<'a> {
+    ///
+    /// Parses a DER encoded CRL.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// der is not a well-formed X.509 CRL.
+    ///
+    pub fn parse(der: &'a [u8]) -> SgxResult<SgxX509Crl<'a>> {
+        let mut outer = Der::new(der);
+        let mut crl = Der::new(outer.read(TAG_SEQUENCE)?);
+        outer.finish()?;
+        let (tag, tbs_content, tbs) = crl.read_any()?;
+        if tag != TAG_SEQUENCE {
+            return malformed();
+        }
+        let signature_algorithm = crl.read(TAG_SEQUENCE)?;
+        let signature = match crl.read(TAG_BIT_STRING)? {
+            [0, signature @ ..] => signature,
+            _ => return malformed(),
+        };
+        crl.finish()?;
+
+        let mut tbs_der = Der::new(tbs_content);
+        let version = match tbs_der.read_optional(TAG_INTEGER)? {
+            Some([1]) => 1,
+            Some(_) => return malformed(),
+            None => 0,
+        };
+        if tbs_der.read(TAG_SEQUENCE)? != signature_algorithm {
+            return malformed();
+        }
+        let issuer = SgxX509Name::from_der(tbs_der.read(TAG_SEQUENCE)?)?;
+        let this_update = read_time(&mut tbs_der)?;
+        let next_update = match tbs_der.peek_tag() {
+            Some(TAG_UTC_TIME) | Some(TAG_GENERALIZED_TIME) => Some(read_time(&mut tbs_der)?),
+            _ => None,
+        };
+
+        let mut revoked = Vec::new();
+        if let Some(list) = tbs_der.read_optional(TAG_SEQUENCE)? {
+            let mut list = Der::new(list);
+            while !list.is_empty() {
+                let mut entry = Der::new(list.read(TAG_SEQUENCE)?);
+                revoked.push(read_unsigned(&mut entry)?);
+                read_time(&mut entry)?;
+                if let Some(extensions) = entry.read_optional(TAG_SEQUENCE)? {
+                    if version != 1 {
+                        return malformed();
+                    }
+                    check_extensions(extensions)?;
+                }
+                entry.finish()?;
+            }
+        }
+        // The CRL extensions are explicitly tagged [0].
+        if let Some(content) = tbs_der.read_optional(TAG_VERSION)? {
+            if version != 1 {
+                return malformed();
+            }
+            let mut wrapper = Der::new(content);
+            check_extensions(wrapper.read(TAG_SEQUENCE)?)?;
+            wrapper.finish()?;
+        }
+        tbs_der.finish()?;
+
+        Ok(SgxX509Crl {
+            der,
+            tbs,
+            signature_algorithm,
+            issuer,
+            this_update,
+            next_update,
+            revoked,
+            signature,
+        })
+    }
+
+    /// The whole DER encoding.
+    pub fn der(&self) -> &'a [u8] {
+        self.der
+    }
+
+    /// The DER encoding of the signed part, the TBSCertList.
+    pub fn tbs_cert_list(&self) -> &'a [u8] {
+        self.tbs
+    }
+
+    pub fn issuer(&self) -> &SgxX509Name {
+        &self.issuer
+    }
+
+    pub fn this_update(&self) -> u64 {
+        self.this_update
+    }
+
+    pub fn next_update(&self) -> Option<u64> {
+        self.next_update
+    }
+
+    /// Whether time lies between this update and the next update.
+    pub fn is_current_at(&self, time: u64) -> bool {
+        self.this_update <= time && self.next_update.map_or(true, |next| time <= next)
+    }
+
+    /// The serial numbers of the revoked certificates, as from serial_number.
+    pub fn revoked_serial_numbers(&self) -> &[&'a [u8]] {
+        &self.revoked
+    }
+
+    /// Whether the list revokes the certificate with the given serial number.
+    pub fn is_revoked(&self, serial: &[u8]) -> bool {
+        self.revoked.iter().any(|r| *r == serial)
+    }
+
+    ///
+    /// Checks the signature with the public key of the issuer.
+    ///
+    /// # Return value
+    ///
+    /// **true**
+    ///
+    /// The signature is valid.
+    ///
+    /// **false**
+    ///
+    /// The signature is invalid, or its algorithm does not match the key.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_OUT_OF_MEMORY**
+    ///
+    /// Not enough memory is available to complete this operation.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// An internal cryptography library function failed.
+    ///
+    pub fn verify_signature(&self, issuer_key: &SgxX509PublicKey) -> SgxResult<bool> {
+        verify_signed(
+            self.tbs,
+            self.signature_algorithm,
+            self.signature,
+            issuer_key,
+        )
+    }
+}
+
+// Checks the encoding of a list of extensions.
+fn check_extensions(content: &[u8]) -> SgxError {
+    let mut list = Der::new(content);
+    while !list.is_empty() {
+        let mut ext = Der::new(list.read(TAG_SEQUENCE)?);
+        ext.read(TAG_OID)?;
+        match ext.read_optional(TAG_BOOLEAN)? {
+            Some([0xff]) | None => {}
+            Some(_) => return malformed(),
+        }
+        ext.read(TAG_OCTET_STRING)?;
+        ext.finish()?;
+    }
+    Ok(())
+}
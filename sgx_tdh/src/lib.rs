@@ -19,6 +19,9 @@
 //!
 //! These functions allow an ISV to establish secure session between two enclaves using the EC DH Key exchange protocol.
 //!
+//! `SgxLocalAttestation` runs the whole exchange over a caller supplied transport, checks the peer
+//! against an `SgxLaPolicy`, and returns an `SgxLaChannel` for authenticated encryption.
+//!
 
 #![no_std]
 #![cfg_attr(
@@ -41,4 +44,7 @@ extern crate sgx_types;
 mod dh;
 pub use self::dh::*;
 
+mod session;
+pub use self::session::*;
+
 mod ecp;
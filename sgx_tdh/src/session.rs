@@ -0,0 +1,408 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! Local attestation sessions
+//!
+//! A helper over the DH session establishment that runs the three messages over a
+//! transport of the caller, checks the identity of the peer against a policy, and
+//! returns an AES-GCM channel keyed from the session.
+//!
+//! The accepting enclave is the DH responder and speaks first; the initiating
+//! enclave is the DH initiator. Both sides check the other one: the initiator with
+//! the report in msg3, the responder with the report in msg2.
+//!
+//! The channel derives one key per direction from the AEK and numbers the messages
+//! of each direction, using the number as the nonce. Messages must therefore be
+//! opened in the order they were sealed, and a replayed, dropped or reordered message
+//! fails to open.
+//!
+use crate::dh::*;
+use alloc::vec::Vec;
+use core::mem;
+use core::ptr;
+use sgx_tcrypto::*;
+use sgx_types::*;
+
+const I2R_LABEL: [u8; 3] = *b"I2R";
+const R2I_LABEL: [u8; 3] = *b"R2I";
+
+///
+/// Moves the session establishment messages and the channel messages between the
+/// two enclaves.
+///
+/// Each send must arrive as one recv on the other side, so a stream transport has to
+/// frame the messages.
+///
+pub trait SgxLaTransport {
+    /// Sends a message to the peer.
+    fn send(&mut self, msg: &[u8]) -> SgxError;
+
+    /// Receives the next message from the peer.
+    fn recv(&mut self) -> SgxResult<Vec<u8>>;
+}
+
+///
+/// The enclaves a session is accepted with.
+///
+/// A peer is accepted if its MRENCLAVE or its MRSIGNER is allowed, it has the product
+/// ID and at least the ISVSVN set, and it is not a debug enclave unless allowed. An
+/// empty policy accepts no peer.
+///
+#[derive(Clone, Default)]
+pub struct SgxLaPolicy {
+    mr_enclaves: Vec<sgx_measurement_t>,
+    mr_signers: Vec<sgx_measurement_t>,
+    isv_prod_id: Option<sgx_prod_id_t>,
+    min_isv_svn: sgx_isv_svn_t,
+    allow_debug: bool,
+}
+
+impl SgxLaPolicy {
+    pub fn new() -> SgxLaPolicy {
+        SgxLaPolicy::default()
+    }
+
+    /// Allows the enclave with this MRENCLAVE.
+    pub fn allow_mr_enclave(mut self, mr_enclave: &sgx_measurement_t) -> SgxLaPolicy {
+        self.mr_enclaves.push(*mr_enclave);
+        self
+    }
+
+    /// Allows the enclaves signed by the key with this MRSIGNER.
+    pub fn allow_mr_signer(mut self, mr_signer: &sgx_measurement_t) -> SgxLaPolicy {
+        self.mr_signers.push(*mr_signer);
+        self
+    }
+
+    /// Requires the ISVPRODID.
+    pub fn isv_prod_id(mut self, isv_prod_id: sgx_prod_id_t) -> SgxLaPolicy {
+        self.isv_prod_id = Some(isv_prod_id);
+        self
+    }
+
+    /// Requires at least the ISVSVN.
+    pub fn min_isv_svn(mut self, isv_svn: sgx_isv_svn_t) -> SgxLaPolicy {
+        self.min_isv_svn = isv_svn;
+        self
+    }
+
+    /// Accepts debug enclaves, which any host can inspect.
+    pub fn allow_debug(mut self, allow: bool) -> SgxLaPolicy {
+        self.allow_debug = allow;
+        self
+    }
+
+    ///
+    /// Checks a peer identity against the policy.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_ENCLAVE**
+    ///
+    /// Neither the MRENCLAVE nor the MRSIGNER is allowed, or the ISVPRODID differs.
+    ///
+    /// **SGX_ERROR_INVALID_ISVSVN**
+    ///
+    /// The ISVSVN is too low.
+    ///
+    /// **SGX_ERROR_INVALID_ATTRIBUTE**
+    ///
+    /// The peer is a debug enclave and debug enclaves are not allowed.
+    ///
+    pub fn check(&self, identity: &sgx_dh_session_enclave_identity_t) -> SgxError {
+        let allowed = self
+            .mr_enclaves
+            .iter()
+            .any(|m| m.m == identity.mr_enclave.m)
+            || self.mr_signers.iter().any(|m| m.m == identity.mr_signer.m);
+        if !allowed
+            || self
+                .isv_prod_id
+                .map_or(false, |id| id != identity.isv_prod_id)
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE);
+        }
+        if identity.isv_svn < self.min_isv_svn {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_ISVSVN);
+        }
+        if !self.allow_debug && identity.attributes.flags & SGX_FLAGS_DEBUG != 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_ATTRIBUTE);
+        }
+        Ok(())
+    }
+}
+
+///
+/// Establishes local attestation sessions.
+///
+pub struct SgxLocalAttestation;
+
+impl SgxLocalAttestation {
+    ///
+    /// Establishes a session as the initiator: receives msg1, sends msg2 and receives
+    /// msg3, then checks the responder against policy.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// A message from the peer has the wrong size.
+    ///
+    /// The errors of the transport, of SgxDhInitiator::proc_msg1 and
+    /// SgxDhInitiator::proc_msg3, and of SgxLaPolicy::check.
+    ///
+    pub fn initiate<T: SgxLaTransport + ?Sized>(
+        transport: &mut T,
+        policy: &SgxLaPolicy,
+    ) -> SgxResult<SgxLaChannel> {
+        let mut initiator = SgxDhInitiator::init_session();
+        let msg1: SgxDhMsg1 = from_bytes(&transport.recv()?)?;
+        let mut msg2 = SgxDhMsg2::default();
+        initiator.proc_msg1(&msg1, &mut msg2)?;
+        transport.send(as_bytes(&msg2))?;
+
+        let msg3 = msg3_from_bytes(&transport.recv()?)?;
+        let mut aek = sgx_key_128bit_t::default();
+        let mut identity = sgx_dh_session_enclave_identity_t::default();
+        initiator.proc_msg3(&msg3, &mut aek, &mut identity)?;
+        let channel = SgxLaChannel::new(&aek, identity, true);
+        rsgx_zeroize(&mut aek);
+        let channel = channel?;
+        policy.check(&identity)?;
+        Ok(channel)
+    }
+
+    ///
+    /// Establishes a session as the responder: sends msg1 and receives msg2, checks
+    /// the initiator against policy, then sends msg3.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// A message from the peer has the wrong size.
+    ///
+    /// The errors of the transport, of SgxDhResponder::gen_msg1 and
+    /// SgxDhResponder::proc_msg2, and of SgxLaPolicy::check.
+    ///
+    pub fn accept<T: SgxLaTransport + ?Sized>(
+        transport: &mut T,
+        policy: &SgxLaPolicy,
+    ) -> SgxResult<SgxLaChannel> {
+        let mut responder = SgxDhResponder::init_session();
+        let mut msg1 = SgxDhMsg1::default();
+        responder.gen_msg1(&mut msg1)?;
+        transport.send(as_bytes(&msg1))?;
+
+        let msg2: SgxDhMsg2 = from_bytes(&transport.recv()?)?;
+        let mut msg3 = SgxDhMsg3::new();
+        let mut aek = sgx_key_128bit_t::default();
+        let mut identity = sgx_dh_session_enclave_identity_t::default();
+        responder.proc_msg2(&msg2, &mut msg3, &mut aek, &mut identity)?;
+        let channel = SgxLaChannel::new(&aek, identity, false);
+        rsgx_zeroize(&mut aek);
+        let channel = channel?;
+        // The initiator learns nothing if it is rejected before msg3.
+        policy.check(&identity)?;
+        transport.send(&msg3_to_bytes(&msg3))?;
+        Ok(channel)
+    }
+}
+
+///
+/// An authenticated encryption channel to an attested peer enclave.
+///
+/// The keys are zeroed when the channel is dropped.
+///
+pub struct SgxLaChannel {
+    send_key: sgx_aes_gcm_128bit_key_t,
+    recv_key: sgx_aes_gcm_128bit_key_t,
+    send_seq: u64,
+    recv_seq: u64,
+    peer: sgx_dh_session_enclave_identity_t,
+}
+
+impl SgxLaChannel {
+    fn new(
+        aek: &sgx_key_128bit_t,
+        peer: sgx_dh_session_enclave_identity_t,
+        initiator: bool,
+    ) -> SgxResult<SgxLaChannel> {
+        let i2r = session_key(aek, &I2R_LABEL)?;
+        let r2i = session_key(aek, &R2I_LABEL)?;
+        let (send_key, recv_key) = if initiator { (i2r, r2i) } else { (r2i, i2r) };
+        Ok(SgxLaChannel {
+            send_key,
+            recv_key,
+            send_seq: 0,
+            recv_seq: 0,
+            peer,
+        })
+    }
+
+    ///
+    /// Get the identity of the peer enclave.
+    ///
+    pub fn get_peer_identity(&self) -> &sgx_dh_session_enclave_identity_t {
+        &self.peer
+    }
+
+    ///
+    /// Encrypts the next message to the peer. The result is the ciphertext followed by
+    /// the 16-byte tag.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// 2^64 messages were sent.
+    ///
+    /// The errors of rsgx_rijndael128GCM_encrypt.
+    ///
+    pub fn seal(&mut self, aad: &[u8], plaintext: &[u8]) -> SgxResult<Vec<u8>> {
+        let iv = nonce(self.send_seq);
+        let next = self
+            .send_seq
+            .checked_add(1)
+            .ok_or(sgx_status_t::SGX_ERROR_UNEXPECTED)?;
+        let mut out = vec![0_u8; plaintext.len() + SGX_AESGCM_MAC_SIZE];
+        let mut tag = sgx_aes_gcm_128bit_tag_t::default();
+        rsgx_rijndael128GCM_encrypt(
+            &self.send_key,
+            plaintext,
+            &iv,
+            aad,
+            &mut out[..plaintext.len()],
+            &mut tag,
+        )?;
+        out[plaintext.len()..].copy_from_slice(&tag);
+        self.send_seq = next;
+        Ok(out)
+    }
+
+    ///
+    /// Decrypts the next message from the peer.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The message is shorter than a tag.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The message or aad was altered, or it is not the next message of the peer.
+    ///
+    /// The other errors of rsgx_rijndael128GCM_decrypt.
+    ///
+    pub fn open(&mut self, aad: &[u8], message: &[u8]) -> SgxResult<Vec<u8>> {
+        if message.len() < SGX_AESGCM_MAC_SIZE {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let iv = nonce(self.recv_seq);
+        let next = self
+            .recv_seq
+            .checked_add(1)
+            .ok_or(sgx_status_t::SGX_ERROR_UNEXPECTED)?;
+        let (ciphertext, tag) = message.split_at(message.len() - SGX_AESGCM_MAC_SIZE);
+        let mut mac = sgx_aes_gcm_128bit_tag_t::default();
+        mac.copy_from_slice(tag);
+        let mut out = vec![0_u8; ciphertext.len()];
+        rsgx_rijndael128GCM_decrypt(&self.recv_key, ciphertext, &iv, aad, &mac, &mut out)?;
+        self.recv_seq = next;
+        Ok(out)
+    }
+
+    ///
+    /// Seals a message and sends it over transport.
+    ///
+    /// # Errors
+    ///
+    /// The errors of seal and of the transport.
+    ///
+    pub fn send<T: SgxLaTransport + ?Sized>(&mut self, transport: &mut T, msg: &[u8]) -> SgxError {
+        let sealed = self.seal(&[], msg)?;
+        transport.send(&sealed)
+    }
+
+    ///
+    /// Receives a message from transport and opens it.
+    ///
+    /// # Errors
+    ///
+    /// The errors of the transport and of open.
+    ///
+    pub fn recv<T: SgxLaTransport + ?Sized>(&mut self, transport: &mut T) -> SgxResult<Vec<u8>> {
+        let sealed = transport.recv()?;
+        self.open(&[], &sealed)
+    }
+}
+
+impl Drop for SgxLaChannel {
+    fn drop(&mut self) {
+        rsgx_zeroize(&mut self.send_key);
+        rsgx_zeroize(&mut self.recv_key);
+    }
+}
+
+// The key derivation of the DH session, keyed with the AEK.
+fn session_key(aek: &sgx_key_128bit_t, label: &[u8; 3]) -> SgxResult<sgx_aes_gcm_128bit_key_t> {
+    // counter(0x01) || label || 0x00 || output_key_len(0x0080)
+    let buffer = [0x01, label[0], label[1], label[2], 0x00, 0x80, 0x00];
+    rsgx_rijndael128_cmac_slice(aek, &buffer)
+}
+
+fn nonce(seq: u64) -> [u8; SGX_AESGCM_IV_SIZE] {
+    let mut iv = [0_u8; SGX_AESGCM_IV_SIZE];
+    iv[4..].copy_from_slice(&seq.to_be_bytes());
+    iv
+}
+
+fn as_bytes<T: Copy>(value: &T) -> &[u8] {
+    unsafe { core::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+fn from_bytes<T: Copy>(bytes: &[u8]) -> SgxResult<T> {
+    if bytes.len() != mem::size_of::<T>() {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    Ok(unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) })
+}
+
+// msg3 is the CMAC, the report and the additional properties, without the length
+// field of sgx_dh_msg3_t; the message size gives it.
+fn msg3_to_bytes(msg3: &SgxDhMsg3) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(
+        SGX_DH_MAC_SIZE + mem::size_of::<sgx_report_t>() + msg3.msg3_body.additional_prop.len(),
+    );
+    bytes.extend_from_slice(&msg3.cmac);
+    bytes.extend_from_slice(as_bytes(&msg3.msg3_body.report));
+    bytes.extend_from_slice(&msg3.msg3_body.additional_prop);
+    bytes
+}
+
+fn msg3_from_bytes(bytes: &[u8]) -> SgxResult<SgxDhMsg3> {
+    let report_end = SGX_DH_MAC_SIZE + mem::size_of::<sgx_report_t>();
+    if bytes.len() < report_end {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let mut msg3 = SgxDhMsg3::new();
+    msg3.cmac.copy_from_slice(&bytes[..SGX_DH_MAC_SIZE]);
+    msg3.msg3_body.report = from_bytes(&bytes[SGX_DH_MAC_SIZE..report_end])?;
+    msg3.msg3_body.additional_prop = bytes[report_end..].to_vec().into_boxed_slice();
+    Ok(msg3)
+}
@@ -20,13 +20,22 @@
 //! The library allow an ISV to exchange secrets between its server and its enclaves. They are used in
 //! concert with untrusted Key Exchange functions.
 //!
+//! `SgxRaSpSession` implements the service provider side of the exchange, for a service
+//! provider that runs in an enclave.
+//!
 
 #![no_std]
 #![cfg_attr(target_env = "sgx", feature(rustc_private))]
 
+extern crate alloc;
+
+extern crate sgx_tcrypto;
 extern crate sgx_types;
 use sgx_types::*;
 
+mod sp;
+pub use self::sp::*;
+
 ///
 /// The rsgx_ra_init function creates a context for the remote attestation and key exchange process.
 ///
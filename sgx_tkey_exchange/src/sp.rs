@@ -0,0 +1,337 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! Service provider side of the key exchange
+//!
+//! The counterpart of rsgx_ra_init and rsgx_ra_proc_msg2, for a service provider
+//! that runs in an enclave itself. The session takes msg1 from the attesting enclave,
+//! produces msg2 signed with the long-term key of the service provider, and checks
+//! msg3 and the binding of its quote to the key exchange. The quote must then be
+//! verified, for an EPID quote by the Intel Attestation Service, before the keys are
+//! used.
+//!
+//! The keys are derived with the KDF of the SDK (KDF ID 1): KDK = AES-CMAC(0, g_ab.x)
+//! and SMK, SK, MK and VK = AES-CMAC(KDK, 0x01 || label || 0x00 || 0x80 || 0x00).
+//!
+use alloc::vec::Vec;
+use core::mem;
+use core::ptr;
+use core::slice;
+use sgx_tcrypto::*;
+use sgx_types::*;
+
+/// The only extended EPID group, the one of Intel.
+pub const SGX_RA_EXTENDED_EPID_GROUP_ID: u32 = 0;
+/// The KDF ID of the SDK key derivation.
+pub const SGX_RA_KDF_ID: u16 = 1;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum SgxRaSpState {
+    WaitMsg1,
+    WaitMsg3,
+    Active,
+    Error,
+}
+
+///
+/// A remote attestation and key exchange session of the service provider.
+///
+/// The session keys and the ephemeral private key are zeroed when the session is
+/// dropped, and when a message fails to verify.
+///
+pub struct SgxRaSpSession {
+    state: SgxRaSpState,
+    sign_key: sgx_ec256_private_t,
+    spid: sgx_spid_t,
+    quote_type: sgx_quote_sign_type_t,
+    g_a: sgx_ec256_public_t,
+    g_b: sgx_ec256_public_t,
+    b: sgx_ec256_private_t,
+    gid: sgx_epid_group_id_t,
+    smk: sgx_ec_key_128bit_t,
+    sk: sgx_ec_key_128bit_t,
+    mk: sgx_ec_key_128bit_t,
+    vk: sgx_ec_key_128bit_t,
+}
+
+impl SgxRaSpSession {
+    ///
+    /// Creates a session for the service provider with the private key whose public
+    /// key the attesting enclave passes to rsgx_ra_init, the SPID registered with
+    /// Intel, and the quote type of that registration.
+    ///
+    pub fn new(
+        sign_key: &sgx_ec256_private_t,
+        spid: &sgx_spid_t,
+        quote_type: sgx_quote_sign_type_t,
+    ) -> SgxRaSpSession {
+        SgxRaSpSession {
+            state: SgxRaSpState::WaitMsg1,
+            sign_key: *sign_key,
+            spid: *spid,
+            quote_type,
+            g_a: sgx_ec256_public_t::default(),
+            g_b: sgx_ec256_public_t::default(),
+            b: sgx_ec256_private_t::default(),
+            gid: sgx_epid_group_id_t::default(),
+            smk: sgx_ec_key_128bit_t::default(),
+            sk: sgx_ec_key_128bit_t::default(),
+            mk: sgx_ec_key_128bit_t::default(),
+            vk: sgx_ec_key_128bit_t::default(),
+        }
+    }
+
+    ///
+    /// Checks the extended EPID group ID of msg0.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The group is not SGX_RA_EXTENDED_EPID_GROUP_ID.
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// msg1 was already processed.
+    ///
+    pub fn proc_msg0(&mut self, extended_epid_group_id: u32) -> SgxError {
+        if self.state != SgxRaSpState::WaitMsg1 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        if extended_epid_group_id != SGX_RA_EXTENDED_EPID_GROUP_ID {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Processes msg1 and returns msg2, a sgx_ra_msg2_t followed by the SigRL.
+    ///
+    /// sig_rl is the signature revocation list of the EPID group in msg1, as returned
+    /// by the Intel Attestation Service for that group; it is empty if no member of
+    /// the group was revoked.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// msg1 was already processed.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// g_a is not a point on the curve, or sig_rl is too large.
+    ///
+    /// The errors of the ECC functions of sgx_tcrypto.
+    ///
+    pub fn proc_msg1(&mut self, msg1: &sgx_ra_msg1_t, sig_rl: &[u8]) -> SgxResult<Vec<u8>> {
+        if self.state != SgxRaSpState::WaitMsg1 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        let result = self.gen_msg2(msg1, sig_rl);
+        self.state = if result.is_ok() {
+            SgxRaSpState::WaitMsg3
+        } else {
+            self.clear();
+            SgxRaSpState::Error
+        };
+        result
+    }
+
+    fn gen_msg2(&mut self, msg1: &sgx_ra_msg1_t, sig_rl: &[u8]) -> SgxResult<Vec<u8>> {
+        let sig_rl_size: u32 = sig_rl
+            .len()
+            .try_into()
+            .map_err(|_| sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+        let handle = SgxEccHandle::new();
+        handle.open()?;
+        if !handle.check_point(&msg1.g_a)? {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let (b, g_b) = handle.create_key_pair()?;
+        self.b = b;
+        self.g_a = msg1.g_a;
+        self.g_b = g_b;
+        self.gid = msg1.gid;
+
+        let mut shared = handle.compute_shared_dhkey(&self.b, &self.g_a)?;
+        let derived = self.derive_keys(&shared);
+        rsgx_zeroize(&mut shared.s);
+        derived?;
+
+        let mut gb_ga = [0_u8; 2 * mem::size_of::<sgx_ec256_public_t>()];
+        gb_ga[..64].copy_from_slice(as_bytes(&self.g_b));
+        gb_ga[64..].copy_from_slice(as_bytes(&self.g_a));
+        let mut msg2 = sgx_ra_msg2_t {
+            g_b: self.g_b,
+            spid: self.spid,
+            quote_type: self.quote_type as u16,
+            kdf_id: SGX_RA_KDF_ID,
+            sign_gb_ga: handle.ecdsa_sign_slice(&gb_ga, &self.sign_key)?,
+            mac: sgx_mac_t::default(),
+            sig_rl_size,
+            sig_rl: [],
+        };
+        // The MAC covers g_b through sign_gb_ga.
+        let mac_offset =
+            mem::size_of::<sgx_ra_msg2_t>() - mem::size_of::<sgx_mac_t>() - mem::size_of::<u32>();
+        msg2.mac = rsgx_rijndael128_cmac_slice(&self.smk, &as_bytes(&msg2)[..mac_offset])?;
+
+        let mut out = Vec::with_capacity(mem::size_of::<sgx_ra_msg2_t>() + sig_rl.len());
+        out.extend_from_slice(as_bytes(&msg2));
+        out.extend_from_slice(sig_rl);
+        Ok(out)
+    }
+
+    fn derive_keys(&mut self, shared: &sgx_ec256_dh_shared_t) -> SgxError {
+        let zero = sgx_cmac_128bit_key_t::default();
+        let mut kdk = rsgx_rijndael128_cmac_slice(&zero, &shared.s)?;
+        let result = (|| {
+            self.smk = derive_key(&kdk, b"SMK")?;
+            self.sk = derive_key(&kdk, b"SK")?;
+            self.mk = derive_key(&kdk, b"MK")?;
+            self.vk = derive_key(&kdk, b"VK")?;
+            Ok(())
+        })();
+        rsgx_zeroize(&mut kdk);
+        result
+    }
+
+    ///
+    /// Processes msg3 and returns the quote it carries.
+    ///
+    /// The MAC of msg3, its g_a, and the report data of the quote, which must be
+    /// SHA256(g_a || g_b || VK) followed by zeros, are checked, as are the EPID group
+    /// and the signature type of the quote. The quote itself is not verified.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// msg1 was not processed, or msg3 was already processed.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// msg3 is too short to hold a quote.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The MAC or g_a is wrong, or the quote is not bound to the key exchange.
+    ///
+    pub fn proc_msg3(&mut self, msg3: &[u8]) -> SgxResult<Vec<u8>> {
+        if self.state != SgxRaSpState::WaitMsg3 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        let result = self.check_msg3(msg3);
+        self.state = if result.is_ok() {
+            SgxRaSpState::Active
+        } else {
+            self.clear();
+            SgxRaSpState::Error
+        };
+        result
+    }
+
+    fn check_msg3(&self, msg3: &[u8]) -> SgxResult<Vec<u8>> {
+        let header_size = mem::size_of::<sgx_ra_msg3_t>();
+        if msg3.len() < header_size + mem::size_of::<sgx_quote_t>() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let header = unsafe { ptr::read_unaligned(msg3.as_ptr() as *const sgx_ra_msg3_t) };
+        if !rsgx_ct_eq(as_bytes(&header.g_a), as_bytes(&self.g_a)) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        // The MAC covers everything after it.
+        let mac_size = mem::size_of::<sgx_mac_t>();
+        let mac = rsgx_rijndael128_cmac_slice(&self.smk, &msg3[mac_size..])?;
+        if !rsgx_ct_eq(&mac, &header.mac) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+
+        let quote = &msg3[header_size..];
+        let parsed = unsafe { ptr::read_unaligned(quote.as_ptr() as *const sgx_quote_t) };
+        let (sign_type, gid) = (parsed.sign_type, parsed.epid_group_id);
+        if sign_type != self.quote_type as u16 || gid != self.gid {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        let mut bound = [0_u8; 2 * mem::size_of::<sgx_ec256_public_t>() + SGX_CMAC_KEY_SIZE];
+        bound[..64].copy_from_slice(as_bytes(&self.g_a));
+        bound[64..128].copy_from_slice(as_bytes(&self.g_b));
+        bound[128..].copy_from_slice(&self.vk);
+        let hash = rsgx_sha256_slice(&bound)?;
+        let mut expected = sgx_report_data_t::default();
+        expected.d[..SGX_SHA256_HASH_SIZE].copy_from_slice(&hash);
+        let report_data = parsed.report_body.report_data;
+        if !rsgx_ct_eq(&expected.d, &report_data.d) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        Ok(quote.to_vec())
+    }
+
+    ///
+    /// Get the EPID group of the attesting platform, from msg1.
+    ///
+    pub fn get_epid_group_id(&self) -> &sgx_epid_group_id_t {
+        &self.gid
+    }
+
+    ///
+    /// Get a negotiated key. The keys are only available once msg3 was processed.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// msg3 was not processed successfully.
+    ///
+    pub fn get_key(&self, key_type: sgx_ra_key_type_t) -> SgxResult<sgx_ra_key_128_t> {
+        if self.state != SgxRaSpState::Active {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        Ok(match key_type {
+            sgx_ra_key_type_t::SGX_RA_KEY_SK => self.sk,
+            sgx_ra_key_type_t::SGX_RA_KEY_MK => self.mk,
+        })
+    }
+
+    fn clear(&mut self) {
+        rsgx_zeroize(&mut self.b.r);
+        rsgx_zeroize(&mut self.smk);
+        rsgx_zeroize(&mut self.sk);
+        rsgx_zeroize(&mut self.mk);
+        rsgx_zeroize(&mut self.vk);
+    }
+}
+
+impl Drop for SgxRaSpSession {
+    fn drop(&mut self) {
+        self.clear();
+        rsgx_zeroize(&mut self.sign_key.r);
+    }
+}
+
+// AES-CMAC(KDK, 0x01 || label || 0x00 || 0x80 || 0x00)
+fn derive_key(kdk: &sgx_cmac_128bit_key_t, label: &[u8]) -> SgxResult<sgx_ec_key_128bit_t> {
+    let mut buffer = Vec::with_capacity(label.len() + 4);
+    buffer.push(0x01);
+    buffer.extend_from_slice(label);
+    buffer.extend_from_slice(&[0x00, 0x80, 0x00]);
+    rsgx_rijndael128_cmac_slice(kdk, &buffer)
+}
+
+fn as_bytes<T: Copy>(value: &T) -> &[u8] {
+    unsafe { slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
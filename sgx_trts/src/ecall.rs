@@ -0,0 +1,546 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Rust-native ecalls, without EDL.
+//!
+//! A trusted function annotated with `#[sgx_ecall]` from sgx_ecall_derive gets a
+//! handler that decodes its arguments from untrusted memory and encodes its result.
+//! `sgx_ecall_table!` then defines the ecall table of the enclave, which replaces the
+//! one generated by edger8r, with the handlers it lists:
+//!
+//! ```rust,ignore
+//! use sgx_ecall_derive::sgx_ecall;
+//!
+//! #[sgx_ecall]
+//! fn add(a: u32, b: u32) -> u32 {
+//!     a + b
+//! }
+//!
+//! #[sgx_ecall]
+//! fn fill(buf: &mut [u8], byte: u8) {
+//!     buf.iter_mut().for_each(|b| *b = byte);
+//! }
+//!
+//! sgx_trts::sgx_ecall_table!(add, fill);
+//! ```
+//!
+//! The application calls them through the proxies declared by `sgx_ecall_proxy!` in
+//! sgx_urts. An ecall is identified by the name of its function, which must be unique
+//! in the enclave.
+//!
+//! Parameters of type `&str` and `&[u8]` are copied into the enclave, parameters of
+//! type `&mut [u8]` are copied in and then back out, and other parameters and the
+//! return value are passed by value and must implement `EcallValue`. Pointers of the
+//! marshalling structure are checked to be outside the enclave before they are used.
+//!
+//! The enclave calls back the application with rsgx_rust_ocall, which is handled by the
+//! closures registered with `sgx_urts::SgxOcalls`.
+//!
+//! **Note**
+//!
+//! Such an enclave has no other ocalls, so the sgx_tstd features that need ocalls are
+//! not available to it.
+//!
+//! `EcallCancelFlag` reads the cancellation flag of an asynchronous ecall made with
+//! `SgxEnclave::call_async`, whether or not the ecall is Rust-native.
+//!
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem;
+use core::ptr;
+use core::slice;
+use core::sync::atomic::{AtomicU32, Ordering};
+use sgx_types::*;
+
+use crate::trts::{rsgx_lfence, rsgx_raw_is_outside_enclave};
+
+///
+/// The identifier of the ecall of a function, from its name.
+///
+pub const fn rsgx_ecall_id(name: &str) -> u32 {
+    // FNV-1a
+    let bytes = name.as_bytes();
+    let mut hash: u32 = 0x811c_9dc5;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    hash
+}
+
+///
+/// The encoded arguments or results of an ecall.
+///
+#[derive(Clone, Debug, Default)]
+pub struct EcallWriter {
+    buf: Vec<u8>,
+}
+
+impl EcallWriter {
+    pub fn new() -> EcallWriter {
+        EcallWriter::default()
+    }
+
+    pub fn write<T: EcallValue>(&mut self, value: &T) {
+        value.encode(self);
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buf
+            .extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn write_raw(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+///
+/// A reader of encoded arguments or results of an ecall.
+///
+/// # Errors
+///
+/// The read functions return **SGX_ERROR_INVALID_PARAMETER** when the encoding is
+/// truncated or malformed.
+///
+#[derive(Clone, Debug)]
+pub struct EcallReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> EcallReader<'a> {
+    pub fn new(buf: &'a [u8]) -> EcallReader<'a> {
+        EcallReader { buf }
+    }
+
+    pub fn read<T: EcallValue>(&mut self) -> SgxResult<T> {
+        T::decode(self)
+    }
+
+    pub fn read_bytes(&mut self) -> SgxResult<Vec<u8>> {
+        let len = u64::from_le_bytes(self.read_array()?);
+        if len > self.buf.len() as u64 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(self.read_raw(len as usize)?.to_vec())
+    }
+
+    pub fn read_str(&mut self) -> SgxResult<String> {
+        String::from_utf8(self.read_bytes()?).map_err(|_| sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    }
+
+    pub fn read_raw(&mut self, len: usize) -> SgxResult<&'a [u8]> {
+        if len > self.buf.len() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> SgxResult<[u8; N]> {
+        let mut array = [0_u8; N];
+        array.copy_from_slice(self.read_raw(N)?);
+        Ok(array)
+    }
+
+    ///
+    /// Checks that everything was read.
+    ///
+    pub fn finish(&self) -> SgxError {
+        if self.buf.is_empty() {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        }
+    }
+}
+
+///
+/// A type passed by value through a Rust-native ecall.
+///
+/// The encoding must match the one of `sgx_urts::EcallValue`.
+///
+pub trait EcallValue: Sized {
+    fn encode(&self, writer: &mut EcallWriter);
+    fn decode(reader: &mut EcallReader) -> SgxResult<Self>;
+}
+
+macro_rules! impl_ecall_value_int {
+    ($($t:ty)*) => {$(
+        impl EcallValue for $t {
+            fn encode(&self, writer: &mut EcallWriter) {
+                writer.write_raw(&self.to_le_bytes());
+            }
+
+            fn decode(reader: &mut EcallReader) -> SgxResult<$t> {
+                reader.read_array().map(<$t>::from_le_bytes)
+            }
+        }
+    )*}
+}
+
+impl_ecall_value_int! { u8 u16 u32 u64 i8 i16 i32 i64 }
+
+impl EcallValue for usize {
+    fn encode(&self, writer: &mut EcallWriter) {
+        (*self as u64).encode(writer);
+    }
+
+    fn decode(reader: &mut EcallReader) -> SgxResult<usize> {
+        usize::try_from(u64::decode(reader)?).map_err(|_| sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    }
+}
+
+impl EcallValue for bool {
+    fn encode(&self, writer: &mut EcallWriter) {
+        (*self as u8).encode(writer);
+    }
+
+    fn decode(reader: &mut EcallReader) -> SgxResult<bool> {
+        match u8::decode(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+    }
+}
+
+impl EcallValue for () {
+    fn encode(&self, _writer: &mut EcallWriter) {}
+
+    fn decode(_reader: &mut EcallReader) -> SgxResult<()> {
+        Ok(())
+    }
+}
+
+impl EcallValue for Vec<u8> {
+    fn encode(&self, writer: &mut EcallWriter) {
+        writer.write_bytes(self);
+    }
+
+    fn decode(reader: &mut EcallReader) -> SgxResult<Vec<u8>> {
+        reader.read_bytes()
+    }
+}
+
+impl EcallValue for String {
+    fn encode(&self, writer: &mut EcallWriter) {
+        writer.write_bytes(self.as_bytes());
+    }
+
+    fn decode(reader: &mut EcallReader) -> SgxResult<String> {
+        reader.read_str()
+    }
+}
+
+///
+/// The handler of a Rust-native ecall, implemented by `#[sgx_ecall]`.
+///
+pub trait EcallHandler {
+    const NAME: &'static str;
+    const ID: u32 = rsgx_ecall_id(Self::NAME);
+
+    ///
+    /// Decodes the arguments from input, makes the call, and encodes the return value
+    /// and then the in/out parameters to output.
+    ///
+    fn handle(input: &[u8], output: &mut EcallWriter) -> SgxError;
+}
+
+///
+/// An entry of the handlers given to `rsgx_dispatch_ecall`.
+///
+#[derive(Clone, Copy)]
+pub struct EcallEntry {
+    pub id: u32,
+    pub handle: fn(&[u8], &mut EcallWriter) -> SgxError,
+}
+
+impl EcallEntry {
+    pub fn new<H: EcallHandler>() -> EcallEntry {
+        EcallEntry {
+            id: H::ID,
+            handle: H::handle,
+        }
+    }
+}
+
+///
+/// rsgx_dispatch_ecall makes the Rust-native ecall described by the marshalling
+/// structure pms with the handler of its identifier.
+///
+/// # Description
+///
+/// This is the body of the ecall defined by `sgx_ecall_table!`. The result of the
+/// handler is returned in ms_retval. The input is copied into the enclave before it is
+/// decoded, and the output is only written if it fits in ms_output_size bytes;
+/// ms_output_len is set to its length in both cases.
+///
+/// # Return value
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// pms or one of its buffers is not outside the enclave.
+///
+/// **SGX_ERROR_INVALID_FUNCTION**
+///
+/// No handler has the identifier.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// The output does not fit in the output buffer.
+///
+/// # Safety
+///
+/// pms must be the marshalling structure passed to the ecall.
+///
+pub unsafe fn rsgx_dispatch_ecall(pms: *mut c_void, handlers: &[EcallEntry]) -> sgx_status_t {
+    let size = mem::size_of::<sgx_rust_ecall_ms_t>();
+    if pms.is_null() || !rsgx_raw_is_outside_enclave(pms as *const u8, size) {
+        return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
+    }
+    rsgx_lfence();
+
+    let p_ms = pms as *mut sgx_rust_ecall_ms_t;
+    let ms = ptr::read_unaligned(p_ms);
+    if !is_outside_buffer(ms.ms_input, ms.ms_input_len)
+        || !is_outside_buffer(ms.ms_output, ms.ms_output_size)
+    {
+        return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
+    }
+    rsgx_lfence();
+
+    let handler = match handlers.iter().find(|entry| entry.id == ms.ms_id) {
+        Some(entry) => entry,
+        None => return sgx_status_t::SGX_ERROR_INVALID_FUNCTION,
+    };
+    let input = if ms.ms_input_len > 0 {
+        slice::from_raw_parts(ms.ms_input, ms.ms_input_len).to_vec()
+    } else {
+        Vec::new()
+    };
+
+    let mut output = EcallWriter::new();
+    let retval = match (handler.handle)(&input, &mut output) {
+        Ok(()) => sgx_status_t::SGX_SUCCESS,
+        Err(e) => e,
+    };
+    let output = output.as_bytes();
+
+    ptr::write_unaligned(ptr::addr_of_mut!((*p_ms).ms_output_len), output.len());
+    if retval == sgx_status_t::SGX_SUCCESS {
+        if output.len() > ms.ms_output_size {
+            return sgx_status_t::SGX_ERROR_OUT_OF_MEMORY;
+        }
+        ptr::copy_nonoverlapping(output.as_ptr(), ms.ms_output, output.len());
+    }
+    ptr::write_unaligned(ptr::addr_of_mut!((*p_ms).ms_retval), retval);
+    sgx_status_t::SGX_SUCCESS
+}
+
+fn is_outside_buffer(p: *const u8, len: usize) -> bool {
+    len == 0 || (!p.is_null() && rsgx_raw_is_outside_enclave(p, len))
+}
+
+///
+/// rsgx_rust_ocall makes the Rust-native ocall id with the encoded arguments input,
+/// and returns the encoded output, read back into the enclave.
+///
+/// # Description
+///
+/// The ocall is handled by the closure the application registered for id with
+/// `sgx_urts::SgxOcalls`. It is only available during Rust-native ecalls. The
+/// marshalling structure and both buffers are allocated on the untrusted stack, and
+/// the output is only read from the buffer the enclave allocated.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The buffers are too large.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// The untrusted stack is too small, or the output is longer than output_size.
+///
+/// **SGX_ERROR_INVALID_FUNCTION**
+///
+/// The application has no handler for id.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// The application returned a malformed output length.
+///
+/// The errors of the ocall, and of the handler.
+///
+pub fn rsgx_rust_ocall(id: u32, input: &[u8], output_size: usize) -> SgxResult<Vec<u8>> {
+    let ms_size = mem::size_of::<sgx_rust_ocall_ms_t>();
+    let size = ms_size
+        .checked_add(input.len())
+        .and_then(|size| size.checked_add(output_size))
+        .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+
+    unsafe {
+        let p_ms = sgx_ocalloc(size) as *mut u8;
+        if p_ms.is_null() {
+            sgx_ocfree();
+            return Err(sgx_status_t::SGX_ERROR_OUT_OF_MEMORY);
+        }
+        let p_input = p_ms.add(ms_size);
+        let p_output = p_input.add(input.len());
+        ptr::copy_nonoverlapping(input.as_ptr(), p_input, input.len());
+        ptr::write_unaligned(
+            p_ms as *mut sgx_rust_ocall_ms_t,
+            sgx_rust_ocall_ms_t {
+                ms_retval: sgx_status_t::SGX_ERROR_UNEXPECTED,
+                ms_id: id,
+                ms_input: p_input,
+                ms_input_len: input.len(),
+                ms_output: p_output,
+                ms_output_size: output_size,
+                ms_output_len: 0,
+            },
+        );
+
+        let ret = sgx_ocall(SGX_RUST_OCALL_INDEX, p_ms as *mut c_void);
+        let ms = ptr::read_unaligned(p_ms as *const sgx_rust_ocall_ms_t);
+        let result = if ret != sgx_status_t::SGX_SUCCESS {
+            Err(ret)
+        } else if ms.ms_retval != sgx_status_t::SGX_SUCCESS {
+            Err(ms.ms_retval)
+        } else if ms.ms_output_len > output_size {
+            Err(sgx_status_t::SGX_ERROR_UNEXPECTED)
+        } else {
+            rsgx_lfence();
+            Ok(slice::from_raw_parts(p_output, ms.ms_output_len).to_vec())
+        };
+        sgx_ocfree();
+        result
+    }
+}
+
+///
+/// The cancellation flag of an asynchronous ecall, which sgx_urts passes as a
+/// [user_check] pointer to untrusted memory.
+///
+/// The flag is only valid during the ecall it was passed to.
+///
+#[derive(Clone, Copy, Debug)]
+pub struct EcallCancelFlag {
+    flag: *const AtomicU32,
+}
+
+impl EcallCancelFlag {
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// flag is null, misaligned or not outside the enclave.
+    ///
+    pub fn from_raw(flag: *const u32) -> SgxResult<EcallCancelFlag> {
+        if flag.is_null()
+            || flag as usize % mem::align_of::<AtomicU32>() != 0
+            || !rsgx_raw_is_outside_enclave(flag as *const u8, mem::size_of::<u32>())
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        rsgx_lfence();
+        Ok(EcallCancelFlag {
+            flag: flag as *const AtomicU32,
+        })
+    }
+
+    ///
+    /// Whether the application cancelled the ecall. A long ecall should check it from
+    /// time to time and return early when it is set.
+    ///
+    pub fn is_cancelled(&self) -> bool {
+        unsafe { (*self.flag).load(Ordering::Acquire) != 0 }
+    }
+}
+
+///
+/// The ecall table of an enclave with one Rust-native ecall.
+///
+#[repr(C)]
+pub struct EcallTable {
+    pub nr_ecall: size_t,
+    pub ecall_addr: unsafe extern "C" fn(*mut c_void) -> sgx_status_t,
+    pub is_priv: uint8_t,
+    pub is_switchless: uint8_t,
+}
+
+unsafe impl Sync for EcallTable {}
+
+///
+/// The dynamic entry table of an enclave with one Rust-native ocall, during which
+/// no ecall is allowed.
+///
+#[repr(C)]
+pub struct DynEntryTable {
+    pub nr_ocall: size_t,
+    pub entry_table: [[uint8_t; 1]; 1],
+}
+
+///
+/// Defines the ecall table of the enclave, whose only ecall dispatches to the
+/// handlers of the functions annotated with `#[sgx_ecall]` given as arguments.
+///
+/// The enclave must not also be linked with the trusted bridge generated by edger8r.
+///
+#[macro_export]
+macro_rules! sgx_ecall_table {
+    ($($handler:path),* $(,)?) => {
+        #[doc(hidden)]
+        unsafe extern "C" fn __sgx_rust_ecall(
+            pms: *mut ::sgx_types::c_void,
+        ) -> ::sgx_types::sgx_status_t {
+            let handlers: &[$crate::ecall::EcallEntry] =
+                &[$($crate::ecall::EcallEntry::new::<$handler>()),*];
+            $crate::ecall::rsgx_dispatch_ecall(pms, handlers)
+        }
+
+        #[doc(hidden)]
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static g_ecall_table: $crate::ecall::EcallTable = $crate::ecall::EcallTable {
+            nr_ecall: 1,
+            ecall_addr: __sgx_rust_ecall,
+            is_priv: 0,
+            is_switchless: 0,
+        };
+
+        #[doc(hidden)]
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static g_dyn_entry_table: $crate::ecall::DynEntryTable =
+            $crate::ecall::DynEntryTable {
+                nr_ocall: 1,
+                entry_table: [[0]],
+            };
+    };
+}
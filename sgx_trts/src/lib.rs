@@ -91,6 +91,7 @@ pub mod ascii;
 pub mod c_str;
 pub mod cpu_feature;
 pub mod cpuid;
+pub mod ecall;
 pub mod emm;
 pub mod enclave;
 pub mod memchr;
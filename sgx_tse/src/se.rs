@@ -233,3 +233,28 @@ pub fn rsgx_get_align_key(key_request: &sgx_key_request_t) -> SgxResult<sgx_alig
 pub fn rsgx_self_report() -> sgx_report_t {
     unsafe { *sgx_self_report() }
 }
+
+///
+/// rsgx_is_kss_enabled returns whether the enclave was launched with Key Separation and
+/// Sharing (KSS), so that its CONFIGID and CONFIGSVN are set by the loader and enter its
+/// identity and key derivation.
+///
+pub fn rsgx_is_kss_enabled() -> bool {
+    rsgx_self_report().body.attributes.flags & SGX_FLAGS_KSS != 0
+}
+
+///
+/// rsgx_get_config_id returns the CONFIGID the enclave was launched with. It is all zero
+/// if the loader gave none or KSS is not enabled.
+///
+pub fn rsgx_get_config_id() -> sgx_config_id_t {
+    rsgx_self_report().body.config_id
+}
+
+///
+/// rsgx_get_config_svn returns the CONFIGSVN the enclave was launched with, 0 if the
+/// loader gave none or KSS is not enabled.
+///
+pub fn rsgx_get_config_svn() -> sgx_config_svn_t {
+    rsgx_self_report().body.config_svn
+}
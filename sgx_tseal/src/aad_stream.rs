@@ -0,0 +1,317 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Streaming MAC of additional data
+//!
+//! `SgxMacAadata` needs the whole additional text inside one sealed data structure.
+//! `SgxMacAadataStream` instead authenticates data of any length fed in pieces, for
+//! example a large index kept in untrusted memory, and produces a detached
+//! `SgxDetachedMac` that is stored next to the data. `SgxUnmacAadataStream` checks
+//! the data against it again, piece by piece.
+//!
+//! The MAC is AES-CMAC over the data followed by its 64-bit little-endian length,
+//! keyed with a seal key that has a fresh random key ID for every MAC.
+//!
+use crate::key_cache::rsgx_get_cached_key;
+use crate::policy::SealPolicy;
+use core::mem;
+use core::ptr;
+use sgx_tcrypto::*;
+use sgx_trts::trts::*;
+use sgx_types::*;
+
+/// The magic at the start of a serialized detached MAC.
+pub const SGX_DETACHED_MAC_MAGIC: [u8; 4] = *b"SGXM";
+/// The format version of a serialized detached MAC.
+pub const SGX_DETACHED_MAC_VERSION: u16 = 1;
+/// The size of a serialized detached MAC.
+pub const SGX_DETACHED_MAC_SIZE: usize =
+    8 + mem::size_of::<sgx_key_request_t>() + 8 + SGX_CMAC_MAC_SIZE;
+
+///
+/// A MAC over additional data, kept apart from the data itself.
+///
+#[derive(Clone, Copy)]
+pub struct SgxDetachedMac {
+    key_request: sgx_key_request_t,
+    aad_len: u64,
+    tag: sgx_cmac_128bit_tag_t,
+}
+
+impl SgxDetachedMac {
+    ///
+    /// Get the key request of the seal key the MAC was computed with.
+    ///
+    pub fn get_key_request(&self) -> &sgx_key_request_t {
+        &self.key_request
+    }
+
+    ///
+    /// Get the length of the data the MAC covers.
+    ///
+    pub fn get_aad_len(&self) -> u64 {
+        self.aad_len
+    }
+
+    ///
+    /// Serializes the MAC: the magic, the 16-bit little-endian version, two reserved
+    /// bytes, the key request, the 64-bit little-endian data length and the tag.
+    ///
+    pub fn to_bytes(&self) -> [u8; SGX_DETACHED_MAC_SIZE] {
+        let mut out = [0_u8; SGX_DETACHED_MAC_SIZE];
+        let request_size = mem::size_of::<sgx_key_request_t>();
+        out[..4].copy_from_slice(&SGX_DETACHED_MAC_MAGIC);
+        out[4..6].copy_from_slice(&SGX_DETACHED_MAC_VERSION.to_le_bytes());
+        unsafe {
+            ptr::copy_nonoverlapping(
+                &self.key_request as *const _ as *const u8,
+                out[8..].as_mut_ptr(),
+                request_size,
+            );
+        }
+        let pos = 8 + request_size;
+        out[pos..pos + 8].copy_from_slice(&self.aad_len.to_le_bytes());
+        out[pos + 8..].copy_from_slice(&self.tag);
+        out
+    }
+
+    ///
+    /// Parses a MAC serialized by to_bytes.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The input has the wrong size, magic or version.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<SgxDetachedMac> {
+        if bytes.len() != SGX_DETACHED_MAC_SIZE
+            || bytes[..4] != SGX_DETACHED_MAC_MAGIC
+            || u16::from_le_bytes([bytes[4], bytes[5]]) != SGX_DETACHED_MAC_VERSION
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let request_size = mem::size_of::<sgx_key_request_t>();
+        let mut key_request = sgx_key_request_t::default();
+        unsafe {
+            ptr::copy_nonoverlapping(
+                bytes[8..].as_ptr(),
+                &mut key_request as *mut _ as *mut u8,
+                request_size,
+            );
+        }
+        let pos = 8 + request_size;
+        let mut len = [0_u8; 8];
+        len.copy_from_slice(&bytes[pos..pos + 8]);
+        let mut tag = [0_u8; SGX_CMAC_MAC_SIZE];
+        tag.copy_from_slice(&bytes[pos + 8..]);
+        Ok(SgxDetachedMac {
+            key_request,
+            aad_len: u64::from_le_bytes(len),
+            tag,
+        })
+    }
+}
+
+// A CMAC keyed with the seal key of a key request.
+struct AadMac {
+    handle: SgxCmacHandle,
+    len: u64,
+}
+
+impl AadMac {
+    fn new(key_request: &sgx_key_request_t) -> SgxResult<AadMac> {
+        let mut seal_key = rsgx_get_cached_key(key_request)?;
+        let handle = SgxCmacHandle::new();
+        let result = handle.init(&seal_key.key);
+        seal_key.key = sgx_key_128bit_t::default();
+        result?;
+        Ok(AadMac { handle, len: 0 })
+    }
+
+    fn update(&mut self, data: &[u8]) -> SgxError {
+        if data.is_empty() {
+            return Ok(());
+        }
+        if !rsgx_slice_is_within_enclave(data) && !rsgx_slice_is_outside_enclave(data) {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        self.len = self
+            .len
+            .checked_add(data.len() as u64)
+            .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+        self.handle.update_slice(data)
+    }
+
+    fn tag(&self) -> SgxResult<sgx_cmac_128bit_tag_t> {
+        self.handle.update_slice(&self.len.to_le_bytes())?;
+        self.handle.get_hash()
+    }
+}
+
+///
+/// Computes a detached MAC over data fed in pieces.
+///
+pub struct SgxMacAadataStream {
+    key_request: sgx_key_request_t,
+    mac: AadMac,
+}
+
+impl SgxMacAadataStream {
+    ///
+    /// Starts a MAC with the seal key that mac_aadata uses: MRSIGNER policy, plus the
+    /// KSS fields in an enclave with KSS enabled.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The RDRAND instruction failed, or a crypto library failure occurred.
+    ///
+    /// The errors of rsgx_get_align_key.
+    ///
+    pub fn new() -> SgxResult<SgxMacAadataStream> {
+        Self::with_policy(&SealPolicy::seal_default())
+    }
+
+    ///
+    /// Starts a MAC with the seal key selected by policy.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The policy is invalid (see SealPolicy::key_request).
+    ///
+    /// The errors of new.
+    ///
+    pub fn with_policy(policy: &SealPolicy) -> SgxResult<SgxMacAadataStream> {
+        let mut key_id = sgx_key_id_t::default();
+        rsgx_read_rand(&mut key_id.id)?;
+        let key_request = policy.key_request(&key_id)?;
+        let mac = AadMac::new(&key_request)?;
+        Ok(SgxMacAadataStream { key_request, mac })
+    }
+
+    ///
+    /// Adds a piece of data. It may be within or outside the enclave, but must not
+    /// cross the enclave boundary.
+    ///
+    pub fn update(&mut self, data: &[u8]) -> SgxError {
+        self.mac.update(data)
+    }
+
+    ///
+    /// Ends the data and returns its MAC.
+    ///
+    pub fn finalize(self) -> SgxResult<SgxDetachedMac> {
+        Ok(SgxDetachedMac {
+            key_request: self.key_request,
+            aad_len: self.mac.len,
+            tag: self.mac.tag()?,
+        })
+    }
+}
+
+///
+/// Checks data fed in pieces against a detached MAC.
+///
+pub struct SgxUnmacAadataStream {
+    expected: SgxDetachedMac,
+    mac: AadMac,
+}
+
+impl SgxUnmacAadataStream {
+    ///
+    /// Starts checking data against mac.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_CPUSVN**
+    ///
+    /// The CPUSVN of the MAC is beyond the platform CPUSVN value.
+    ///
+    /// **SGX_ERROR_INVALID_ISVSVN**
+    ///
+    /// The ISVSVN of the MAC is greater than the enclave's.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The key request of the MAC is not a seal key request.
+    ///
+    /// The other errors of rsgx_get_align_key.
+    ///
+    pub fn new(mac: &SgxDetachedMac) -> SgxResult<SgxUnmacAadataStream> {
+        if mac.key_request.key_name != SGX_KEYSELECT_SEAL {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        Ok(SgxUnmacAadataStream {
+            expected: *mac,
+            mac: AadMac::new(&mac.key_request)?,
+        })
+    }
+
+    ///
+    /// Adds a piece of data. It may be within or outside the enclave, but must not
+    /// cross the enclave boundary.
+    ///
+    pub fn update(&mut self, data: &[u8]) -> SgxError {
+        self.mac.update(data)
+    }
+
+    ///
+    /// Ends the data and checks it against the MAC.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The data or the MAC was modified, or the MAC was computed by another enclave.
+    ///
+    pub fn verify(self) -> SgxError {
+        let tag = self.mac.tag()?;
+        if self.mac.len != self.expected.aad_len || !rsgx_ct_eq(&tag, &self.expected.tag) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for SgxMacAadataStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for SgxUnmacAadataStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
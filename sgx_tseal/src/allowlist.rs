@@ -0,0 +1,253 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! Enclave identity allowlists
+//!
+//! A service that accepts several enclaves as peers keeps a list of the identities it
+//! trusts, and that list changes as enclaves are released and retired. It has to
+//! survive restarts without the host being able to bring back an old list, and it has
+//! to be updated without rebuilding the service enclave.
+//!
+//! `SgxIdentityAllowlist` is such a list of `SgxPeerIdentity`:
+//!
+//! * A report is accepted if any identity of the list accepts it.
+//! * The list is sealed with `rsgx_seal_state`, so only the latest sealed list unseals.
+//! * Every list carries a version. An update is only applied if it is newer than the
+//!   current list, so an old update cannot be replayed to roll the list back. Updates
+//!   are received from an administrative enclave through the secret exchange of this
+//!   crate with `receive_update`, which checks the identity of the sender.
+//!
+//! After an update the caller seals the list again; until it does, a restart returns to
+//! the list sealed before.
+//!
+use crate::exchange::*;
+use crate::policy::SealPolicy;
+use crate::rollback::*;
+use alloc::vec::Vec;
+use sgx_types::*;
+
+const ALLOWLIST_MAGIC: [u8; 4] = *b"SGXL";
+const ALLOWLIST_FORMAT: u16 = 1;
+const ALLOWLIST_HEADER_SIZE: usize = 16;
+
+/// The most identities an allowlist holds.
+pub const SGX_ALLOWLIST_MAX_IDENTITIES: usize = u16::MAX as usize;
+
+///
+/// A versioned list of the enclave identities a service accepts.
+///
+#[derive(Clone)]
+pub struct SgxIdentityAllowlist {
+    version: u64,
+    identities: Vec<SgxPeerIdentity>,
+}
+
+impl SgxIdentityAllowlist {
+    ///
+    /// Creates an empty list, which accepts no enclave.
+    ///
+    pub fn new(version: u64) -> SgxIdentityAllowlist {
+        SgxIdentityAllowlist {
+            version,
+            identities: Vec::new(),
+        }
+    }
+
+    ///
+    /// Also accepts enclaves matching identity.
+    ///
+    pub fn allow(mut self, identity: SgxPeerIdentity) -> SgxIdentityAllowlist {
+        self.identities.push(identity);
+        self
+    }
+
+    ///
+    /// Get the version of the list.
+    ///
+    pub fn get_version(&self) -> u64 {
+        self.version
+    }
+
+    ///
+    /// Get the identities of the list.
+    ///
+    pub fn get_identities(&self) -> &[SgxPeerIdentity] {
+        &self.identities
+    }
+
+    ///
+    /// Checks the body of a verified report against the list.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_ENCLAVE**
+    ///
+    /// No identity of the list matches the enclave.
+    ///
+    /// **SGX_ERROR_INVALID_ISVSVN** or **SGX_ERROR_INVALID_ATTRIBUTE**
+    ///
+    /// An identity matches the enclave, but its ISVSVN is too low or it is a debug
+    /// enclave that the identity does not allow.
+    ///
+    pub fn check(&self, body: &sgx_report_body_t) -> SgxError {
+        let mut error = sgx_status_t::SGX_ERROR_INVALID_ENCLAVE;
+        for identity in &self.identities {
+            match identity.check(body) {
+                Ok(()) => return Ok(()),
+                Err(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE) => (),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+
+    ///
+    /// Replaces the list with update if update is newer.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_VERSION**
+    ///
+    /// The version of update is not higher than the version of the list.
+    ///
+    pub fn update(&mut self, update: SgxIdentityAllowlist) -> SgxError {
+        if update.version <= self.version {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_VERSION);
+        }
+        *self = update;
+        Ok(())
+    }
+
+    ///
+    /// Receives a list from a sender matching admin through the secret exchange, and
+    /// applies it with update.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The secret is not a serialized allowlist.
+    ///
+    /// The errors of SgxSecretReceiver::receive and of update.
+    ///
+    pub fn receive_update(
+        &mut self,
+        receiver: &SgxSecretReceiver,
+        wrapped: &SgxWrappedSecret,
+        admin: &SgxPeerIdentity,
+    ) -> SgxError {
+        let unsealed = receiver.receive(wrapped, admin)?;
+        let update = SgxIdentityAllowlist::from_bytes(&unsealed.decrypt)?;
+        self.update(update)
+    }
+
+    ///
+    /// Serializes the list, for sealing or for sending as an update.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The list has more than SGX_ALLOWLIST_MAX_IDENTITIES identities.
+    ///
+    pub fn to_bytes(&self) -> SgxResult<Vec<u8>> {
+        if self.identities.len() > SGX_ALLOWLIST_MAX_IDENTITIES {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let mut out =
+            Vec::with_capacity(ALLOWLIST_HEADER_SIZE + self.identities.len() * PEER_IDENTITY_SIZE);
+        out.extend_from_slice(&ALLOWLIST_MAGIC);
+        out.extend_from_slice(&ALLOWLIST_FORMAT.to_le_bytes());
+        out.extend_from_slice(&(self.identities.len() as u16).to_le_bytes());
+        out.extend_from_slice(&self.version.to_le_bytes());
+        for identity in &self.identities {
+            out.extend_from_slice(&identity.to_bytes());
+        }
+        Ok(out)
+    }
+
+    ///
+    /// Parses a list serialized with to_bytes.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The bytes are not a serialized allowlist of this format.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<SgxIdentityAllowlist> {
+        if bytes.len() < ALLOWLIST_HEADER_SIZE
+            || bytes[..4] != ALLOWLIST_MAGIC
+            || u16::from_le_bytes([bytes[4], bytes[5]]) != ALLOWLIST_FORMAT
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let count = u16::from_le_bytes([bytes[6], bytes[7]]) as usize;
+        let entries = &bytes[ALLOWLIST_HEADER_SIZE..];
+        if entries.len() != count * PEER_IDENTITY_SIZE {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let mut version = [0_u8; 8];
+        version.copy_from_slice(&bytes[8..16]);
+        let identities = entries
+            .chunks(PEER_IDENTITY_SIZE)
+            .map(SgxPeerIdentity::from_bytes)
+            .collect::<Option<Vec<_>>>()
+            .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+        Ok(SgxIdentityAllowlist {
+            version: u64::from_le_bytes(version),
+            identities,
+        })
+    }
+
+    ///
+    /// Seals the list as the latest state of counter.
+    ///
+    /// # Errors
+    ///
+    /// The errors of to_bytes and rsgx_seal_state.
+    ///
+    pub fn seal<C: SgxMonotonicCounter + ?Sized>(
+        &self,
+        counter: &C,
+        policy: &SealPolicy,
+    ) -> SgxResult<Vec<u8>> {
+        rsgx_seal_state(counter, policy, &ALLOWLIST_MAGIC, &self.to_bytes()?)
+    }
+
+    ///
+    /// Unseals a list sealed with seal, if it is the latest state of counter.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The blob is sealed state, but not an allowlist.
+    ///
+    /// The errors of rsgx_unseal_state.
+    ///
+    pub fn unseal<C: SgxMonotonicCounter + ?Sized>(
+        counter: &C,
+        blob: &[u8],
+    ) -> SgxResult<SgxIdentityAllowlist> {
+        let unsealed = rsgx_unseal_state(counter, blob)?;
+        if *unsealed.additional != ALLOWLIST_MAGIC {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        SgxIdentityAllowlist::from_bytes(&unsealed.decrypt)
+    }
+}
@@ -0,0 +1,239 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Versioned sealed blobs and re-sealing
+//!
+//! A sealed blob is the byte form of a sealed data structure as it is stored outside
+//! the enclave. Blobs written by these functions start with an 8-byte header: the
+//! magic `SGXB`, a 16-bit little-endian format version and the 16-bit header size,
+//! followed by an ordinary `sgx_sealed_data_t`. A raw `sgx_sealed_data_t` without the
+//! header, as written by `to_raw_sealed_data_t`, is accepted as version 0.
+//!
+//! After a TCB recovery raises the CPUSVN or ISVSVN, data sealed under the old
+//! versions can still be unsealed, but should be moved to a key of the new versions.
+//! `rsgx_reseal` does this in one step: the plaintext only exists inside the enclave
+//! while it runs, and the old blob is left untouched until the caller replaces it with
+//! the returned one.
+//!
+use crate::internal::*;
+use crate::policy::SealPolicy;
+use crate::seal::SgxUnsealedData;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem;
+use core::slice;
+use sgx_tcrypto::rsgx_zeroize;
+use sgx_types::*;
+
+/// The magic at the start of a versioned sealed blob.
+pub const SGX_SEALED_BLOB_MAGIC: [u8; 4] = *b"SGXB";
+/// The format version written by `rsgx_seal_blob` and `rsgx_reseal`.
+pub const SGX_SEALED_BLOB_VERSION: u16 = 1;
+/// The size of the versioned sealed blob header.
+pub const SGX_SEALED_BLOB_HEADER_SIZE: usize = 8;
+
+///
+/// rsgx_sealed_blob_version returns the format version of a sealed blob.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The blob is too short, or its header is malformed.
+///
+pub fn rsgx_sealed_blob_version(blob: &[u8]) -> SgxResult<u16> {
+    split_blob(blob).map(|(version, _)| version)
+}
+
+///
+/// rsgx_seal_blob seals encrypt_text under policy and returns it as a versioned blob.
+///
+/// # Errors
+///
+/// The errors of SgxSealedData::seal_data_with_policy.
+///
+pub fn rsgx_seal_blob(
+    policy: &SealPolicy,
+    additional_text: &[u8],
+    encrypt_text: &[u8],
+) -> SgxResult<Vec<u8>> {
+    let sealed =
+        SgxInternalSealedData::seal_data_with_policy(policy, additional_text, encrypt_text)?;
+    to_blob(&sealed)
+}
+
+///
+/// rsgx_unseal_blob unseals a blob of any supported version.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The blob is malformed, or its version is not supported.
+///
+/// The errors of SgxSealedData::unseal_data.
+///
+pub fn rsgx_unseal_blob<'a>(blob: &[u8]) -> SgxResult<SgxUnsealedData<'a, [u8]>> {
+    let sealed = from_blob(blob)?;
+    let unsealed = sealed.unseal_data()?;
+    Ok(SgxUnsealedData {
+        payload_size: unsealed.payload_size,
+        decrypt: unsealed.decrypt,
+        additional: unsealed.additional,
+        marker: PhantomData,
+    })
+}
+
+///
+/// rsgx_sealed_blob_key_request returns the key request a blob was sealed with.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The blob is malformed, or its version is not supported.
+///
+pub fn rsgx_sealed_blob_key_request(blob: &[u8]) -> SgxResult<sgx_key_request_t> {
+    from_blob(blob).map(|sealed| *sealed.get_key_request())
+}
+
+///
+/// rsgx_needs_reseal checks whether a blob was sealed with a different key request than
+/// policy selects now, that is, with another key policy, other masks, or older
+/// security versions. It also returns true for blobs of an older format version.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The blob is malformed, its version is not supported, or the policy is invalid.
+///
+pub fn rsgx_needs_reseal(blob: &[u8], policy: &SealPolicy) -> SgxResult<bool> {
+    let version = rsgx_sealed_blob_version(blob)?;
+    let old = rsgx_sealed_blob_key_request(blob)?;
+    let new = policy.key_request(&old.key_id)?;
+    Ok(version != SGX_SEALED_BLOB_VERSION
+        || old.key_policy != new.key_policy
+        || old.isv_svn != new.isv_svn
+        || old.cpu_svn.svn != new.cpu_svn.svn
+        || old.attribute_mask.flags != new.attribute_mask.flags
+        || old.attribute_mask.xfrm != new.attribute_mask.xfrm
+        || old.misc_mask != new.misc_mask
+        || old.config_svn != new.config_svn)
+}
+
+///
+/// rsgx_reseal unseals a blob with the key request stored in it and seals the contents
+/// again under new_policy, with the same additional text.
+///
+/// # Parameters
+///
+/// **blob**
+///
+/// The sealed blob, of any supported version.
+///
+/// **new_policy**
+///
+/// The policy of the new seal key, normally with the current security versions.
+///
+/// # Return value
+///
+/// The re-sealed blob, in the current format version.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The blob is malformed, its version is not supported, or the policy is invalid.
+///
+/// **SGX_ERROR_MAC_MISMATCH**
+///
+/// The blob cannot be unsealed by this enclave, or was modified.
+///
+/// The other errors of SgxSealedData::unseal_data and SgxSealedData::seal_data_with_policy.
+///
+pub fn rsgx_reseal(blob: &[u8], new_policy: &SealPolicy) -> SgxResult<Vec<u8>> {
+    let sealed = from_blob(blob)?;
+    let mut unsealed = sealed.unseal_data()?;
+    let result = SgxInternalSealedData::seal_data_with_policy(
+        new_policy,
+        &unsealed.additional,
+        &unsealed.decrypt,
+    )
+    .and_then(|resealed| to_blob(&resealed));
+    rsgx_zeroize(&mut unsealed.decrypt);
+    result
+}
+
+// Returns the version and the sgx_sealed_data_t bytes of a blob.
+pub(crate) fn split_blob(blob: &[u8]) -> SgxResult<(u16, &[u8])> {
+    if blob.len() < SGX_SEALED_BLOB_MAGIC.len() || blob[..4] != SGX_SEALED_BLOB_MAGIC {
+        return Ok((0, blob));
+    }
+    if blob.len() < SGX_SEALED_BLOB_HEADER_SIZE {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let version = u16::from_le_bytes([blob[4], blob[5]]);
+    let header_size = u16::from_le_bytes([blob[6], blob[7]]) as usize;
+    if version != SGX_SEALED_BLOB_VERSION || header_size != SGX_SEALED_BLOB_HEADER_SIZE {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    Ok((version, &blob[header_size..]))
+}
+
+pub(crate) fn from_blob(blob: &[u8]) -> SgxResult<SgxInternalSealedData> {
+    let (_, raw) = split_blob(blob)?;
+    if raw.len() < mem::size_of::<sgx_sealed_data_t>() || raw.len() > u32::MAX as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    // sgx_sealed_data_t must be 8-byte aligned.
+    let mut aligned = vec![0_u64; (raw.len() + 7) / 8];
+    let bytes = unsafe { slice::from_raw_parts_mut(aligned.as_mut_ptr() as *mut u8, raw.len()) };
+    bytes.copy_from_slice(raw);
+    unsafe {
+        SgxInternalSealedData::from_raw_sealed_data_t(
+            aligned.as_mut_ptr() as *mut sgx_sealed_data_t,
+            raw.len() as u32,
+        )
+    }
+    .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+}
+
+fn to_blob(sealed: &SgxInternalSealedData) -> SgxResult<Vec<u8>> {
+    let size = SgxInternalSealedData::calc_raw_sealed_data_size(
+        sealed.get_add_mac_txt_len(),
+        sealed.get_encrypt_txt_len(),
+    );
+    if size == u32::MAX {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let mut aligned = vec![0_u64; (size as usize + 7) / 8];
+    unsafe {
+        sealed
+            .to_raw_sealed_data_t(aligned.as_mut_ptr() as *mut sgx_sealed_data_t, size)
+            .ok_or(sgx_status_t::SGX_ERROR_UNEXPECTED)?;
+    }
+    let raw = unsafe { slice::from_raw_parts(aligned.as_ptr() as *const u8, size as usize) };
+
+    let mut blob = Vec::with_capacity(SGX_SEALED_BLOB_HEADER_SIZE + raw.len());
+    blob.extend_from_slice(&SGX_SEALED_BLOB_MAGIC);
+    blob.extend_from_slice(&SGX_SEALED_BLOB_VERSION.to_le_bytes());
+    blob.extend_from_slice(&(SGX_SEALED_BLOB_HEADER_SIZE as u16).to_le_bytes());
+    blob.extend_from_slice(raw);
+    Ok(blob)
+}
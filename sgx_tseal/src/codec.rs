@@ -0,0 +1,194 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Sealing serializable types
+//!
+//! `SgxSealCodec` seals any type that implements `sgx_serialize::Serializable`, and
+//! unseals it back into the type, so callers do not convert values to byte slices by
+//! hand. Values are encoded with the compact binary encoding of sgx_serialize and kept
+//! in a versioned sealed blob.
+//!
+//! The MAC text of the blob carries a format tag and a caller-chosen type version, so
+//! a blob written for one layout of a type is refused, rather than misread, by code
+//! that expects another. Both directions are bounded by a size limit.
+//!
+use crate::blob::*;
+use crate::policy::SealPolicy;
+use sgx_serialize::{DeSerializable, DeSerializeHelper, Serializable, SerializeHelper};
+use sgx_tcrypto::rsgx_zeroize;
+use sgx_types::*;
+use std::vec::Vec;
+
+/// The size limit used by `SgxSealCodec::new`.
+pub const SGX_SEAL_CODEC_DEFAULT_LIMIT: usize = 16 * 1024 * 1024;
+
+const CODEC_MAGIC: [u8; 4] = *b"SGXS";
+// The opaque encoding of sgx_serialize.
+const CODEC_FORMAT: u16 = 1;
+const CODEC_HEADER_SIZE: usize = 12;
+
+///
+/// Seals and unseals values of a serializable type.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[derive(Serializable, DeSerializable)]
+/// struct Config {
+///     name: String,
+///     keys: Vec<[u8; 16]>,
+/// }
+///
+/// let codec = SgxSealCodec::new(1);
+/// let blob = codec.seal(&config)?;
+/// let config: Config = codec.unseal(&blob)?;
+/// ```
+///
+#[derive(Clone, Copy)]
+pub struct SgxSealCodec {
+    version: u32,
+    policy: SealPolicy,
+    limit: usize,
+}
+
+impl SgxSealCodec {
+    ///
+    /// A codec for version of a type, with the seal_data key policy and a size limit of
+    /// SGX_SEAL_CODEC_DEFAULT_LIMIT.
+    ///
+    pub fn new(version: u32) -> SgxSealCodec {
+        SgxSealCodec {
+            version,
+            policy: SealPolicy::seal_default(),
+            limit: SGX_SEAL_CODEC_DEFAULT_LIMIT,
+        }
+    }
+
+    /// Sets the key policy used for sealing.
+    pub fn policy(mut self, policy: &SealPolicy) -> SgxSealCodec {
+        self.policy = *policy;
+        self
+    }
+
+    /// Sets the largest encoded value that is sealed or unsealed.
+    pub fn limit(mut self, limit: usize) -> SgxSealCodec {
+        self.limit = limit;
+        self
+    }
+
+    ///
+    /// Encodes value and seals it.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The value cannot be encoded, or its encoding is larger than the size limit.
+    ///
+    /// The errors of rsgx_seal_blob.
+    ///
+    pub fn seal<T: Serializable + ?Sized>(&self, value: &T) -> SgxResult<Vec<u8>> {
+        let mut data = SerializeHelper::new()
+            .encode(value)
+            .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+        let result = if data.len() > self.limit {
+            Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        } else {
+            rsgx_seal_blob(&self.policy, &self.header(), &data)
+        };
+        rsgx_zeroize(&mut data);
+        result
+    }
+
+    ///
+    /// Unseals a blob written by seal and decodes the value.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The blob is larger than the size limit, was not written by a codec for this
+    /// version, or does not decode to T.
+    ///
+    /// The errors of rsgx_unseal_blob.
+    ///
+    pub fn unseal<T: DeSerializable>(&self, blob: &[u8]) -> SgxResult<T> {
+        if from_blob(blob)?.get_encrypt_txt_len() as usize > self.limit {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let mut unsealed = rsgx_unseal_blob(blob)?;
+        let result = if *unsealed.additional == self.header() {
+            DeSerializeHelper::<T>::new(unsealed.decrypt.to_vec())
+                .decode()
+                .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        } else {
+            Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        };
+        rsgx_zeroize(&mut unsealed.decrypt);
+        result
+    }
+
+    ///
+    /// Returns the type version a blob was written for, without unsealing it. The
+    /// version is authenticated only when the blob is unsealed.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The blob was not written by a codec.
+    ///
+    pub fn sealed_version(blob: &[u8]) -> SgxResult<u32> {
+        let sealed = from_blob(blob)?;
+        let header = sealed.get_additional_txt();
+        if header.len() != CODEC_HEADER_SIZE
+            || header[..4] != CODEC_MAGIC
+            || header[4..6] != CODEC_FORMAT.to_le_bytes()
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(u32::from_le_bytes([
+            header[8], header[9], header[10], header[11],
+        ]))
+    }
+
+    fn header(&self) -> [u8; CODEC_HEADER_SIZE] {
+        let mut header = [0_u8; CODEC_HEADER_SIZE];
+        header[..4].copy_from_slice(&CODEC_MAGIC);
+        header[4..6].copy_from_slice(&CODEC_FORMAT.to_le_bytes());
+        header[8..].copy_from_slice(&self.version.to_le_bytes());
+        header
+    }
+}
+
+///
+/// rsgx_seal_serialize seals value with SgxSealCodec::new(version).
+///
+pub fn rsgx_seal_serialize<T: Serializable + ?Sized>(
+    version: u32,
+    value: &T,
+) -> SgxResult<Vec<u8>> {
+    SgxSealCodec::new(version).seal(value)
+}
+
+///
+/// rsgx_unseal_deserialize unseals a value with SgxSealCodec::new(version).
+///
+pub fn rsgx_unseal_deserialize<T: DeSerializable>(version: u32, blob: &[u8]) -> SgxResult<T> {
+    SgxSealCodec::new(version).unseal(blob)
+}
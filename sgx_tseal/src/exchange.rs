@@ -0,0 +1,694 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! Secret exchange between local enclaves
+//!
+//! A sealed blob can only be unsealed by the enclave identity it was sealed to. To
+//! hand a secret to another enclave on the same platform, the two enclaves agree on
+//! a wrapping key with ephemeral X25519 keys whose public halves are bound into
+//! local attestation reports, so the host only ever relays ciphertext:
+//!
+//! 1. The receiver creates a `SgxSecretReceiver` for the target info of the sender
+//!    and passes its `SgxSecretRequest` to the sender.
+//! 2. The sender checks the report of the request against the identity it expects,
+//!    and wraps the secret with `rsgx_wrap_secret` or `rsgx_wrap_sealed_blob`. The
+//!    `SgxWrappedSecret` carries a report targeted at the receiver.
+//! 3. The receiver checks that report against the identity it expects and unwraps
+//!    the secret with `receive`, or re-seals it to itself with `receive_sealed`.
+//!
+//! The wrapping key is derived with HKDF-SHA256 from the shared secret, both public
+//! keys and both MRENCLAVE values. Every wrap uses a new sender key, so the key is
+//! never reused.
+//!
+use crate::blob::*;
+use crate::policy::SealPolicy;
+use crate::seal::SgxUnsealedData;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem;
+use core::ptr;
+use sgx_tcrypto::*;
+use sgx_tse::*;
+use sgx_types::*;
+
+const EXCHANGE_MAGIC: [u8; 4] = *b"SGXX";
+const EXCHANGE_VERSION: u16 = 1;
+const KIND_REQUEST: u16 = 1;
+const KIND_WRAPPED: u16 = 2;
+const HEADER_SIZE: usize = 8;
+const REPORT_SIZE: usize = mem::size_of::<sgx_report_t>();
+const PUBLIC_SIZE: usize = mem::size_of::<sgx_x25519_public_t>();
+const REQUEST_LABEL: &[u8] = b"sgx_tseal exchange request";
+const WRAPPED_LABEL: &[u8] = b"sgx_tseal exchange wrapped";
+const KEY_SALT: &[u8] = b"sgx_tseal exchange key";
+
+/// The size of a serialized SgxSecretRequest.
+pub const SGX_SECRET_REQUEST_SIZE: usize = HEADER_SIZE + REPORT_SIZE + PUBLIC_SIZE;
+
+// A flags byte, MRENCLAVE, MRSIGNER, ISVPRODID and the minimum ISVSVN.
+pub(crate) const PEER_IDENTITY_SIZE: usize = 1 + 2 * SGX_HASH_SIZE + 4;
+const PEER_MRENCLAVE: u8 = 0x01;
+const PEER_MRSIGNER: u8 = 0x02;
+const PEER_ISV_PROD_ID: u8 = 0x04;
+const PEER_ALLOW_DEBUG: u8 = 0x08;
+
+///
+/// The enclave identity a peer of the exchange must have.
+///
+/// Debug enclaves are rejected unless allowed with allow_debug.
+///
+#[derive(Clone, Copy)]
+pub struct SgxPeerIdentity {
+    mr_enclave: Option<sgx_measurement_t>,
+    mr_signer: Option<sgx_measurement_t>,
+    isv_prod_id: Option<sgx_prod_id_t>,
+    min_isv_svn: sgx_isv_svn_t,
+    allow_debug: bool,
+}
+
+impl SgxPeerIdentity {
+    ///
+    /// A peer with the given MRENCLAVE.
+    ///
+    pub fn mrenclave(mr_enclave: &sgx_measurement_t) -> SgxPeerIdentity {
+        SgxPeerIdentity {
+            mr_enclave: Some(*mr_enclave),
+            mr_signer: None,
+            isv_prod_id: None,
+            min_isv_svn: 0,
+            allow_debug: false,
+        }
+    }
+
+    ///
+    /// A peer signed by the given MRSIGNER.
+    ///
+    pub fn mrsigner(mr_signer: &sgx_measurement_t) -> SgxPeerIdentity {
+        SgxPeerIdentity {
+            mr_enclave: None,
+            mr_signer: Some(*mr_signer),
+            isv_prod_id: None,
+            min_isv_svn: 0,
+            allow_debug: false,
+        }
+    }
+
+    ///
+    /// A peer with the MRENCLAVE of the calling enclave. Debug peers are allowed if the
+    /// calling enclave is a debug enclave.
+    ///
+    pub fn same_mrenclave() -> SgxPeerIdentity {
+        let body = rsgx_self_report().body;
+        SgxPeerIdentity::mrenclave(&body.mr_enclave).allow_debug(is_debug(&body))
+    }
+
+    ///
+    /// A peer with the MRSIGNER and ISVPRODID of the calling enclave, and an ISVSVN no
+    /// lower than its own. Debug peers are allowed if the calling enclave is a debug
+    /// enclave.
+    ///
+    pub fn same_mrsigner() -> SgxPeerIdentity {
+        let body = rsgx_self_report().body;
+        SgxPeerIdentity::mrsigner(&body.mr_signer)
+            .isv_prod_id(body.isv_prod_id)
+            .min_isv_svn(body.isv_svn)
+            .allow_debug(is_debug(&body))
+    }
+
+    ///
+    /// Also requires the peer to have the given MRSIGNER.
+    ///
+    pub fn signer(mut self, mr_signer: &sgx_measurement_t) -> SgxPeerIdentity {
+        self.mr_signer = Some(*mr_signer);
+        self
+    }
+
+    ///
+    /// Requires the peer to have the given ISVPRODID.
+    ///
+    pub fn isv_prod_id(mut self, isv_prod_id: sgx_prod_id_t) -> SgxPeerIdentity {
+        self.isv_prod_id = Some(isv_prod_id);
+        self
+    }
+
+    ///
+    /// Requires the ISVSVN of the peer to be at least isv_svn.
+    ///
+    pub fn min_isv_svn(mut self, isv_svn: sgx_isv_svn_t) -> SgxPeerIdentity {
+        self.min_isv_svn = isv_svn;
+        self
+    }
+
+    ///
+    /// Sets whether the peer may be a debug enclave.
+    ///
+    pub fn allow_debug(mut self, allow: bool) -> SgxPeerIdentity {
+        self.allow_debug = allow;
+        self
+    }
+
+    ///
+    /// Checks the body of a verified report against the identity.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_ENCLAVE**
+    ///
+    /// MRENCLAVE, MRSIGNER or ISVPRODID of the peer do not match.
+    ///
+    /// **SGX_ERROR_INVALID_ISVSVN**
+    ///
+    /// The ISVSVN of the peer is too low.
+    ///
+    /// **SGX_ERROR_INVALID_ATTRIBUTE**
+    ///
+    /// The peer is a debug enclave and debug peers are not allowed.
+    ///
+    pub fn check(&self, body: &sgx_report_body_t) -> SgxError {
+        if let Some(ref m) = self.mr_enclave {
+            if m.m != body.mr_enclave.m {
+                return Err(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE);
+            }
+        }
+        if let Some(ref m) = self.mr_signer {
+            if m.m != body.mr_signer.m {
+                return Err(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE);
+            }
+        }
+        if let Some(id) = self.isv_prod_id {
+            if id != body.isv_prod_id {
+                return Err(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE);
+            }
+        }
+        if body.isv_svn < self.min_isv_svn {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_ISVSVN);
+        }
+        if is_debug(body) && !self.allow_debug {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_ATTRIBUTE);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; PEER_IDENTITY_SIZE] {
+        let mut out = [0_u8; PEER_IDENTITY_SIZE];
+        let mut flags = 0;
+        if let Some(ref m) = self.mr_enclave {
+            flags |= PEER_MRENCLAVE;
+            out[1..1 + SGX_HASH_SIZE].copy_from_slice(&m.m);
+        }
+        if let Some(ref m) = self.mr_signer {
+            flags |= PEER_MRSIGNER;
+            out[1 + SGX_HASH_SIZE..1 + 2 * SGX_HASH_SIZE].copy_from_slice(&m.m);
+        }
+        if let Some(id) = self.isv_prod_id {
+            flags |= PEER_ISV_PROD_ID;
+            out[1 + 2 * SGX_HASH_SIZE..3 + 2 * SGX_HASH_SIZE].copy_from_slice(&id.to_le_bytes());
+        }
+        if self.allow_debug {
+            flags |= PEER_ALLOW_DEBUG;
+        }
+        out[0] = flags;
+        out[3 + 2 * SGX_HASH_SIZE..].copy_from_slice(&self.min_isv_svn.to_le_bytes());
+        out
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<SgxPeerIdentity> {
+        if bytes.len() != PEER_IDENTITY_SIZE
+            || bytes[0] & !(PEER_MRENCLAVE | PEER_MRSIGNER | PEER_ISV_PROD_ID | PEER_ALLOW_DEBUG)
+                != 0
+        {
+            return None;
+        }
+        let flags = bytes[0];
+        let measurement = |range: core::ops::Range<usize>| {
+            let mut m = sgx_measurement_t::default();
+            m.m.copy_from_slice(&bytes[range]);
+            m
+        };
+        let prod_id = 1 + 2 * SGX_HASH_SIZE;
+        Some(SgxPeerIdentity {
+            mr_enclave: (flags & PEER_MRENCLAVE != 0).then(|| measurement(1..1 + SGX_HASH_SIZE)),
+            mr_signer: (flags & PEER_MRSIGNER != 0)
+                .then(|| measurement(1 + SGX_HASH_SIZE..prod_id)),
+            isv_prod_id: (flags & PEER_ISV_PROD_ID != 0)
+                .then(|| u16::from_le_bytes([bytes[prod_id], bytes[prod_id + 1]])),
+            min_isv_svn: u16::from_le_bytes([bytes[prod_id + 2], bytes[prod_id + 3]]),
+            allow_debug: flags & PEER_ALLOW_DEBUG != 0,
+        })
+    }
+}
+
+///
+/// rsgx_secret_exchange_target returns the target info of the calling enclave, which a
+/// receiver needs to create a request for it.
+///
+pub fn rsgx_secret_exchange_target() -> sgx_target_info_t {
+    target_of(&rsgx_self_report().body)
+}
+
+///
+/// The first message of an exchange, from the receiver to the sender.
+///
+#[derive(Clone, Copy)]
+pub struct SgxSecretRequest {
+    report: sgx_report_t,
+    public: sgx_x25519_public_t,
+}
+
+impl SgxSecretRequest {
+    ///
+    /// Get the report of the receiver, targeted at the sender.
+    ///
+    pub fn get_report(&self) -> &sgx_report_t {
+        &self.report
+    }
+
+    ///
+    /// Serializes the request.
+    ///
+    pub fn to_bytes(&self) -> [u8; SGX_SECRET_REQUEST_SIZE] {
+        let mut out = [0_u8; SGX_SECRET_REQUEST_SIZE];
+        write_header(&mut out, KIND_REQUEST);
+        out[HEADER_SIZE..HEADER_SIZE + REPORT_SIZE].copy_from_slice(report_bytes(&self.report));
+        out[HEADER_SIZE + REPORT_SIZE..].copy_from_slice(&self.public);
+        out
+    }
+
+    ///
+    /// Parses a request serialized by to_bytes.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The input has the wrong size, magic, version or kind.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<SgxSecretRequest> {
+        if bytes.len() != SGX_SECRET_REQUEST_SIZE {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        check_header(bytes, KIND_REQUEST)?;
+        let mut public = sgx_x25519_public_t::default();
+        public.copy_from_slice(&bytes[HEADER_SIZE + REPORT_SIZE..]);
+        Ok(SgxSecretRequest {
+            report: read_report(&bytes[HEADER_SIZE..]),
+            public,
+        })
+    }
+}
+
+///
+/// The second message of an exchange, from the sender to the receiver.
+///
+#[derive(Clone)]
+pub struct SgxWrappedSecret {
+    report: sgx_report_t,
+    public: sgx_x25519_public_t,
+    additional: Vec<u8>,
+    ciphertext: Vec<u8>,
+    tag: sgx_aes_gcm_128bit_tag_t,
+}
+
+impl SgxWrappedSecret {
+    ///
+    /// Get the report of the sender, targeted at the receiver.
+    ///
+    pub fn get_report(&self) -> &sgx_report_t {
+        &self.report
+    }
+
+    ///
+    /// Get the additional text. It is only authenticated once
+    /// SgxSecretReceiver::receive has succeeded.
+    ///
+    pub fn get_additional_txt(&self) -> &[u8] {
+        &self.additional
+    }
+
+    ///
+    /// Serializes the wrapped secret.
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![0_u8; HEADER_SIZE];
+        write_header(&mut out, KIND_WRAPPED);
+        out.extend_from_slice(report_bytes(&self.report));
+        out.extend_from_slice(&self.public);
+        out.extend_from_slice(&(self.additional.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(self.ciphertext.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.additional);
+        out.extend_from_slice(&self.ciphertext);
+        out.extend_from_slice(&self.tag);
+        out
+    }
+
+    ///
+    /// Parses a wrapped secret serialized by to_bytes.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The input is truncated, has trailing bytes, or has the wrong magic, version or
+    /// kind.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<SgxWrappedSecret> {
+        let fixed = HEADER_SIZE + REPORT_SIZE + PUBLIC_SIZE + 8;
+        if bytes.len() < fixed + SGX_AESGCM_MAC_SIZE {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        check_header(bytes, KIND_WRAPPED)?;
+        let pos = HEADER_SIZE + REPORT_SIZE;
+        let mut public = sgx_x25519_public_t::default();
+        public.copy_from_slice(&bytes[pos..pos + PUBLIC_SIZE]);
+        let pos = pos + PUBLIC_SIZE;
+        let additional_len = read_u32(&bytes[pos..]) as usize;
+        let ciphertext_len = read_u32(&bytes[pos + 4..]) as usize;
+        let total = additional_len
+            .checked_add(ciphertext_len)
+            .and_then(|len| len.checked_add(fixed + SGX_AESGCM_MAC_SIZE))
+            .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+        if bytes.len() != total {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let ciphertext_pos = fixed + additional_len;
+        let tag_pos = ciphertext_pos + ciphertext_len;
+        let mut tag = sgx_aes_gcm_128bit_tag_t::default();
+        tag.copy_from_slice(&bytes[tag_pos..]);
+        Ok(SgxWrappedSecret {
+            report: read_report(&bytes[HEADER_SIZE..]),
+            public,
+            additional: bytes[fixed..ciphertext_pos].to_vec(),
+            ciphertext: bytes[ciphertext_pos..tag_pos].to_vec(),
+            tag,
+        })
+    }
+}
+
+///
+/// The receiving side of an exchange. It holds the ephemeral private key of the
+/// receiver, which is erased when it is dropped.
+///
+pub struct SgxSecretReceiver {
+    private: SgxX25519PrivateKey,
+    request: SgxSecretRequest,
+}
+
+impl SgxSecretReceiver {
+    ///
+    /// Generates an ephemeral key and a request for the sender described by
+    /// sender_target_info.
+    ///
+    /// # Errors
+    ///
+    /// The errors of SgxX25519PrivateKey::generate and rsgx_create_report.
+    ///
+    pub fn new(sender_target_info: &sgx_target_info_t) -> SgxResult<SgxSecretReceiver> {
+        let private = SgxX25519PrivateKey::generate()?;
+        let public = private.public_key();
+        let report_data = bind_report_data(REQUEST_LABEL, &[&public])?;
+        let report = rsgx_create_report(sender_target_info, &report_data)?;
+        Ok(SgxSecretReceiver {
+            private,
+            request: SgxSecretRequest { report, public },
+        })
+    }
+
+    ///
+    /// Get the request to pass to the sender.
+    ///
+    pub fn get_request(&self) -> &SgxSecretRequest {
+        &self.request
+    }
+
+    ///
+    /// Verifies the wrapped secret, checks that it comes from an enclave matching
+    /// sender, and unwraps it.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The report of the sender is not valid for this enclave, is not bound to this
+    /// exchange, or the secret does not authenticate.
+    ///
+    /// The errors of SgxPeerIdentity::check.
+    ///
+    pub fn receive<'a>(
+        &self,
+        wrapped: &SgxWrappedSecret,
+        sender: &SgxPeerIdentity,
+    ) -> SgxResult<SgxUnsealedData<'a, [u8]>> {
+        let body = verify_peer(
+            &wrapped.report,
+            sender,
+            WRAPPED_LABEL,
+            &[&wrapped.public, &self.request.public],
+        )?;
+        let mut key = wrapping_key(
+            &self.private,
+            &wrapped.public,
+            &wrapped.public,
+            &self.request.public,
+            &body.mr_enclave,
+            &self.request.report.body.mr_enclave,
+        )?;
+
+        let mut decrypt = vec![0_u8; wrapped.ciphertext.len()];
+        let result = rsgx_rijndael128GCM_decrypt(
+            &key,
+            &wrapped.ciphertext,
+            &[0_u8; SGX_AESGCM_IV_SIZE],
+            &wrapped.additional,
+            &wrapped.tag,
+            &mut decrypt,
+        );
+        rsgx_zeroize(&mut key);
+        if let Err(e) = result {
+            rsgx_zeroize(&mut decrypt);
+            return Err(e);
+        }
+
+        Ok(SgxUnsealedData {
+            payload_size: decrypt.len() as u32,
+            decrypt: decrypt.into_boxed_slice(),
+            additional: wrapped.additional.clone().into_boxed_slice(),
+            marker: PhantomData,
+        })
+    }
+
+    ///
+    /// Receives the secret like receive, and seals it under policy as a versioned
+    /// sealed blob of the calling enclave.
+    ///
+    /// # Errors
+    ///
+    /// The errors of receive and rsgx_seal_blob.
+    ///
+    pub fn receive_sealed(
+        &self,
+        wrapped: &SgxWrappedSecret,
+        sender: &SgxPeerIdentity,
+        policy: &SealPolicy,
+    ) -> SgxResult<Vec<u8>> {
+        let mut unsealed = self.receive(wrapped, sender)?;
+        let blob = rsgx_seal_blob(policy, &unsealed.additional, &unsealed.decrypt);
+        rsgx_zeroize(&mut unsealed.decrypt);
+        blob
+    }
+}
+
+///
+/// rsgx_wrap_secret verifies the request of a receiver matching receiver, and wraps
+/// encrypt_text for it. additional_text is authenticated but sent in clear.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// additional_text or encrypt_text is longer than u32::MAX bytes.
+///
+/// **SGX_ERROR_MAC_MISMATCH**
+///
+/// The report of the request is not valid for this enclave or is not bound to the
+/// public key of the request.
+///
+/// The errors of SgxPeerIdentity::check, and of the crypto and report functions.
+///
+pub fn rsgx_wrap_secret(
+    request: &SgxSecretRequest,
+    receiver: &SgxPeerIdentity,
+    additional_text: &[u8],
+    encrypt_text: &[u8],
+) -> SgxResult<SgxWrappedSecret> {
+    if additional_text.len() > u32::MAX as usize || encrypt_text.len() > u32::MAX as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let body = verify_peer(&request.report, receiver, REQUEST_LABEL, &[&request.public])?;
+
+    let private = SgxX25519PrivateKey::generate()?;
+    let public = private.public_key();
+    let mut key = wrapping_key(
+        &private,
+        &request.public,
+        &public,
+        &request.public,
+        &rsgx_self_report().body.mr_enclave,
+        &body.mr_enclave,
+    )?;
+
+    let mut ciphertext = vec![0_u8; encrypt_text.len()];
+    let mut tag = sgx_aes_gcm_128bit_tag_t::default();
+    let result = rsgx_rijndael128GCM_encrypt(
+        &key,
+        encrypt_text,
+        &[0_u8; SGX_AESGCM_IV_SIZE],
+        additional_text,
+        &mut ciphertext,
+        &mut tag,
+    );
+    rsgx_zeroize(&mut key);
+    result?;
+
+    let report_data = bind_report_data(WRAPPED_LABEL, &[&public, &request.public])?;
+    let report = rsgx_create_report(&target_of(&body), &report_data)?;
+    Ok(SgxWrappedSecret {
+        report,
+        public,
+        additional: additional_text.to_vec(),
+        ciphertext,
+        tag,
+    })
+}
+
+///
+/// rsgx_wrap_sealed_blob unseals a versioned sealed blob of the calling enclave and
+/// wraps its content for the receiver, keeping its additional text.
+///
+/// # Errors
+///
+/// The errors of rsgx_unseal_blob and rsgx_wrap_secret.
+///
+pub fn rsgx_wrap_sealed_blob(
+    request: &SgxSecretRequest,
+    receiver: &SgxPeerIdentity,
+    blob: &[u8],
+) -> SgxResult<SgxWrappedSecret> {
+    let mut unsealed = rsgx_unseal_blob(blob)?;
+    let wrapped = rsgx_wrap_secret(request, receiver, &unsealed.additional, &unsealed.decrypt);
+    rsgx_zeroize(&mut unsealed.decrypt);
+    wrapped
+}
+
+fn is_debug(body: &sgx_report_body_t) -> bool {
+    body.attributes.flags & SGX_FLAGS_DEBUG != 0
+}
+
+fn target_of(body: &sgx_report_body_t) -> sgx_target_info_t {
+    sgx_target_info_t {
+        mr_enclave: body.mr_enclave,
+        attributes: body.attributes,
+        config_svn: body.config_svn,
+        misc_select: body.misc_select,
+        config_id: body.config_id,
+        ..Default::default()
+    }
+}
+
+fn verify_peer(
+    report: &sgx_report_t,
+    peer: &SgxPeerIdentity,
+    label: &[u8],
+    keys: &[&sgx_x25519_public_t],
+) -> SgxResult<sgx_report_body_t> {
+    rsgx_verify_report(report).map_err(|_| sgx_status_t::SGX_ERROR_MAC_MISMATCH)?;
+    let expected = bind_report_data(label, keys)?;
+    if !rsgx_ct_eq(&expected.d, &report.body.report_data.d) {
+        return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+    }
+    peer.check(&report.body)?;
+    Ok(report.body)
+}
+
+// The first half of the report data is the hash of the label and the public keys;
+// the second half is zero.
+fn bind_report_data(label: &[u8], keys: &[&sgx_x25519_public_t]) -> SgxResult<sgx_report_data_t> {
+    let sha = SgxShaHandle::new();
+    sha.init()?;
+    sha.update_slice(label)?;
+    for key in keys {
+        sha.update_slice(&key[..])?;
+    }
+    let hash = sha.get_hash()?;
+    let mut report_data = sgx_report_data_t::default();
+    report_data.d[..SGX_SHA256_HASH_SIZE].copy_from_slice(&hash);
+    Ok(report_data)
+}
+
+fn wrapping_key(
+    private: &SgxX25519PrivateKey,
+    peer: &sgx_x25519_public_t,
+    sender_public: &sgx_x25519_public_t,
+    receiver_public: &sgx_x25519_public_t,
+    sender_mr_enclave: &sgx_measurement_t,
+    receiver_mr_enclave: &sgx_measurement_t,
+) -> SgxResult<sgx_aes_gcm_128bit_key_t> {
+    let shared = private.diffie_hellman(peer)?;
+    let mut info = Vec::with_capacity(2 * PUBLIC_SIZE + 2 * SGX_HASH_SIZE);
+    info.extend_from_slice(sender_public);
+    info.extend_from_slice(receiver_public);
+    info.extend_from_slice(&sender_mr_enclave.m);
+    info.extend_from_slice(&receiver_mr_enclave.m);
+    let mut key = sgx_aes_gcm_128bit_key_t::default();
+    rsgx_hkdf_sha256(KEY_SALT, shared.as_bytes(), &info, &mut key)?;
+    Ok(key)
+}
+
+fn write_header(out: &mut [u8], kind: u16) {
+    out[..4].copy_from_slice(&EXCHANGE_MAGIC);
+    out[4..6].copy_from_slice(&EXCHANGE_VERSION.to_le_bytes());
+    out[6..8].copy_from_slice(&kind.to_le_bytes());
+}
+
+fn check_header(bytes: &[u8], kind: u16) -> SgxError {
+    if bytes[..4] != EXCHANGE_MAGIC
+        || u16::from_le_bytes([bytes[4], bytes[5]]) != EXCHANGE_VERSION
+        || u16::from_le_bytes([bytes[6], bytes[7]]) != kind
+    {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    Ok(())
+}
+
+fn report_bytes(report: &sgx_report_t) -> &[u8] {
+    unsafe { core::slice::from_raw_parts(report as *const _ as *const u8, REPORT_SIZE) }
+}
+
+fn read_report(bytes: &[u8]) -> sgx_report_t {
+    let mut report = sgx_report_t::default();
+    unsafe {
+        ptr::copy_nonoverlapping(
+            bytes.as_ptr(),
+            &mut report as *mut _ as *mut u8,
+            REPORT_SIZE,
+        );
+    }
+    report
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
@@ -15,6 +15,8 @@
 // specific language governing permissions and limitations
 // under the License..
 
+use crate::key_cache::rsgx_get_cached_key;
+use crate::policy::SealPolicy;
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::mem;
@@ -25,9 +27,29 @@ use sgx_tse::*;
 use sgx_types::*;
 
 /* intel sgx sdk 2.4 */
-const KEY_POLICY_KSS: uint16_t =
+pub const KEY_POLICY_KSS: uint16_t =
     SGX_KEYPOLICY_CONFIGID | SGX_KEYPOLICY_ISVFAMILYID | SGX_KEYPOLICY_ISVEXTPRODID;
 
+const CONTEXT_NONCE_SIZE: usize = 16;
+const CONTEXT_LABEL: &[u8] = b"sgx_tseal context key id";
+
+// The key ID of a blob sealed in context: a random nonce followed by a hash of the
+// context and the nonce.
+fn context_key_id(context: &[u8], nonce: &[u8; CONTEXT_NONCE_SIZE]) -> SgxResult<sgx_key_id_t> {
+    let sha = SgxShaHandle::new();
+    sha.init()?;
+    sha.update_slice(CONTEXT_LABEL)?;
+    sha.update_slice(&(context.len() as u64).to_le_bytes())?;
+    sha.update_slice(context)?;
+    sha.update_slice(nonce)?;
+    let hash = sha.get_hash()?;
+
+    let mut key_id = sgx_key_id_t::default();
+    key_id.id[..CONTEXT_NONCE_SIZE].copy_from_slice(nonce);
+    key_id.id[CONTEXT_NONCE_SIZE..].copy_from_slice(&hash[..SGX_KEYID_SIZE - CONTEXT_NONCE_SIZE]);
+    Ok(key_id)
+}
+
 #[derive(Clone, Default)]
 pub struct SgxInternalUnsealedData {
     pub payload_size: u32,
@@ -48,14 +70,14 @@ impl SgxInternalUnsealedData {
     ///
     #[allow(dead_code)]
     pub fn get_decrypt_txt(&self) -> &[u8] {
-        &*self.decrypt
+        &self.decrypt
     }
     ///
     /// Get the pointer of additional buffer in SgxInternalUnsealedData.
     ///
     #[allow(dead_code)]
     pub fn get_additional_txt(&self) -> &[u8] {
-        &*self.additional
+        &self.additional
     }
 }
 
@@ -88,10 +110,10 @@ impl SgxInternalSealedData {
         &self.key_request
     }
     pub fn get_encrypt_txt(&self) -> &[u8] {
-        &*self.payload_data.encrypt
+        &self.payload_data.encrypt
     }
     pub fn get_additional_txt(&self) -> &[u8] {
-        &*self.payload_data.additional
+        &self.payload_data.additional
     }
 
     pub fn calc_raw_sealed_data_size(add_mac_txt_size: u32, encrypt_txt_size: u32) -> u32 {
@@ -226,12 +248,12 @@ impl SgxInternalSealedData {
 
         let encrypt: Vec<u8> = if encrypt_len > 0 {
             let mut temp: Vec<u8> = Vec::with_capacity(encrypt_len as usize);
-            temp.set_len(encrypt_len as usize);
             ptr::copy_nonoverlapping(
                 ptr_encrypt as *const u8,
                 temp.as_mut_ptr(),
                 encrypt_len as usize,
             );
+            temp.set_len(encrypt_len as usize);
             temp
         } else {
             Vec::new()
@@ -240,12 +262,12 @@ impl SgxInternalSealedData {
         let additional: Vec<u8> = if additional_len > 0 {
             let ptr_additional = ptr_encrypt.offset(encrypt_len as isize);
             let mut temp: Vec<u8> = Vec::with_capacity(additional_len as usize);
-            temp.set_len(additional_len as usize);
             ptr::copy_nonoverlapping(
                 ptr_additional as *const u8,
                 temp.as_mut_ptr(),
                 additional_len as usize,
             );
+            temp.set_len(additional_len as usize);
             temp
         } else {
             Vec::new()
@@ -292,18 +314,7 @@ impl SgxInternalSealedData {
         additional_text: &[u8],
         encrypt_text: &[u8],
     ) -> SgxResult<Self> {
-        let additional_len = additional_text.len();
-        let encrypt_len = encrypt_text.len();
-
-        if (additional_len >= u32::MAX as usize) || (encrypt_len >= u32::MAX as usize) {
-            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
-        }
-        if Self::calc_raw_sealed_data_size(additional_len as u32, encrypt_len as u32) == u32::MAX {
-            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
-        }
-        if encrypt_len == 0 {
-            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
-        }
+        Self::check_seal_text(additional_text, encrypt_text)?;
 
         if (key_policy
             & (!(SGX_KEYPOLICY_MRENCLAVE
@@ -321,17 +332,6 @@ impl SgxInternalSealedData {
             return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
         }
 
-        if !rsgx_slice_is_within_enclave(encrypt_text) {
-            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
-        }
-
-        if additional_len > 0
-            && !rsgx_slice_is_within_enclave(additional_text)
-            && !rsgx_slice_is_outside_enclave(additional_text)
-        {
-            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
-        }
-
         //let target_info = sgx_target_info_t::default();
         //let report_data = sgx_report_data_t::default();
         let mut key_id = sgx_key_id_t::default();
@@ -373,6 +373,89 @@ impl SgxInternalSealedData {
         result
     }
 
+    pub fn seal_data_with_policy(
+        policy: &SealPolicy,
+        additional_text: &[u8],
+        encrypt_text: &[u8],
+    ) -> SgxResult<Self> {
+        Self::check_seal_text(additional_text, encrypt_text)?;
+
+        let mut key_id = sgx_key_id_t::default();
+        rsgx_read_rand(&mut key_id.id)?;
+        let key_request = policy.key_request(&key_id)?;
+
+        let payload_iv = [0_u8; SGX_SEAL_IV_SIZE];
+        let mut result =
+            Self::seal_data_iv(additional_text, encrypt_text, &payload_iv, &key_request);
+
+        if let Ok(ref mut sealed_data) = result {
+            sealed_data.key_request = key_request
+        };
+
+        result
+    }
+
+    pub fn seal_data_with_context(
+        policy: &SealPolicy,
+        context: &[u8],
+        additional_text: &[u8],
+        encrypt_text: &[u8],
+    ) -> SgxResult<Self> {
+        Self::check_seal_text(additional_text, encrypt_text)?;
+
+        let mut nonce = [0_u8; CONTEXT_NONCE_SIZE];
+        rsgx_read_rand(&mut nonce)?;
+        let key_id = context_key_id(context, &nonce)?;
+        let key_request = policy.key_request(&key_id)?;
+
+        let payload_iv = [0_u8; SGX_SEAL_IV_SIZE];
+        let mut result =
+            Self::seal_data_iv(additional_text, encrypt_text, &payload_iv, &key_request);
+
+        if let Ok(ref mut sealed_data) = result {
+            sealed_data.key_request = key_request
+        };
+
+        result
+    }
+
+    pub fn unseal_data_with_context(&self, context: &[u8]) -> SgxResult<SgxInternalUnsealedData> {
+        let key_id = &self.key_request.key_id;
+        let mut nonce = [0_u8; CONTEXT_NONCE_SIZE];
+        nonce.copy_from_slice(&key_id.id[..CONTEXT_NONCE_SIZE]);
+        if context_key_id(context, &nonce)?.id != key_id.id {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        self.unseal_data()
+    }
+
+    fn check_seal_text(additional_text: &[u8], encrypt_text: &[u8]) -> SgxError {
+        let additional_len = additional_text.len();
+        let encrypt_len = encrypt_text.len();
+
+        if (additional_len >= u32::MAX as usize) || (encrypt_len >= u32::MAX as usize) {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if Self::calc_raw_sealed_data_size(additional_len as u32, encrypt_len as u32) == u32::MAX {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if encrypt_len == 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        if !rsgx_slice_is_within_enclave(encrypt_text) {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        if additional_len > 0
+            && !rsgx_slice_is_within_enclave(additional_text)
+            && !rsgx_slice_is_outside_enclave(additional_text)
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(())
+    }
+
     pub fn unseal_data(&self) -> SgxResult<SgxInternalUnsealedData> {
         let additional_len = self.get_add_mac_txt_len();
         let encrypt_len = self.get_encrypt_txt_len();
@@ -571,7 +654,7 @@ impl SgxInternalSealedData {
     }
 
     fn unseal_data_helper(&self) -> SgxResult<SgxInternalUnsealedData> {
-        let mut seal_key = rsgx_get_align_key(self.get_key_request()).map_err(|ret| {
+        let mut seal_key = rsgx_get_cached_key(self.get_key_request()).map_err(|ret| {
             if (ret == sgx_status_t::SGX_ERROR_INVALID_CPUSVN)
                 || (ret == sgx_status_t::SGX_ERROR_INVALID_ISVSVN)
                 || (ret == sgx_status_t::SGX_ERROR_OUT_OF_MEMORY)
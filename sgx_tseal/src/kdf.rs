@@ -0,0 +1,157 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Key derivation from the sealing key
+//!
+//! These functions derive application keys from the enclave sealing key with HKDF-SHA256.
+//! The key request uses a zero key ID, so the same enclave (or, with MRSIGNER policy, the
+//! same signer) gets the same keys again on the same platform and security version.
+//! Different keys are obtained by using different info strings.
+//!
+use crate::internal::KEY_POLICY_KSS;
+use crate::key_cache::rsgx_get_cached_key;
+use crate::policy::SealPolicy;
+use sgx_tcrypto::*;
+use sgx_tse::*;
+use sgx_types::*;
+
+///
+/// rsgx_derive_seal_key fills okm with a key derived from the MRSIGNER sealing key.
+///
+/// The key policy and masks are the same as the ones used by SgxSealedData::seal_data.
+///
+/// # Parameters
+///
+/// **info**
+///
+/// Application specific context, for example a key purpose label.
+///
+/// **okm**
+///
+/// The output key material. At most 255 * 32 bytes can be derived.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// okm is empty or too long.
+///
+/// **SGX_ERROR_INVALID_CPUSVN**
+///
+/// The CPUSVN in the key request is beyond the platform CPUSVN value.
+///
+/// **SGX_ERROR_INVALID_ISVSVN**
+///
+/// The ISVSVN in the key request is greater than the enclave's ISVSVN.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// The enclave is out of memory.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_derive_seal_key(info: &[u8], okm: &mut [u8]) -> SgxError {
+    let attribute_mask = sgx_attributes_t {
+        flags: TSEAL_DEFAULT_FLAGSMASK,
+        xfrm: 0,
+    };
+    let mut key_policy = SGX_KEYPOLICY_MRSIGNER;
+    let report = rsgx_self_report();
+    if (report.body.attributes.flags & SGX_FLAGS_KSS) != 0 {
+        key_policy = SGX_KEYPOLICY_MRSIGNER | KEY_POLICY_KSS;
+    }
+
+    rsgx_derive_seal_key_ex(
+        key_policy,
+        attribute_mask,
+        TSEAL_DEFAULT_MISCMASK,
+        info,
+        okm,
+    )
+}
+
+///
+/// rsgx_derive_seal_key_ex fills okm with a key derived from the sealing key selected by
+/// key_policy, attribute_mask and misc_mask.
+///
+/// The parameters are checked the same way as in SgxSealedData::seal_data_ex.
+///
+pub fn rsgx_derive_seal_key_ex(
+    key_policy: u16,
+    attribute_mask: sgx_attributes_t,
+    misc_mask: sgx_misc_select_t,
+    info: &[u8],
+    okm: &mut [u8],
+) -> SgxError {
+    if (key_policy
+        & (!(SGX_KEYPOLICY_MRENCLAVE
+            | SGX_KEYPOLICY_MRSIGNER
+            | KEY_POLICY_KSS
+            | SGX_KEYPOLICY_NOISVPRODID))
+        != 0)
+        || ((key_policy & (SGX_KEYPOLICY_MRENCLAVE | SGX_KEYPOLICY_MRSIGNER)) == 0)
+    {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if ((attribute_mask.flags & SGX_FLAGS_INITTED) == 0)
+        || ((attribute_mask.flags & SGX_FLAGS_DEBUG) == 0)
+    {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let report = rsgx_self_report();
+    let key_request = sgx_key_request_t {
+        key_name: SGX_KEYSELECT_SEAL,
+        key_policy,
+        isv_svn: report.body.isv_svn,
+        reserved1: 0_u16,
+        cpu_svn: report.body.cpu_svn,
+        attribute_mask,
+        key_id: sgx_key_id_t::default(),
+        misc_mask,
+        config_svn: report.body.config_svn,
+        reserved2: [0_u8; SGX_KEY_REQUEST_RESERVED2_BYTES],
+    };
+
+    let mut seal_key = rsgx_get_cached_key(&key_request)?;
+    let result = rsgx_hkdf_sha256_from_key128(&seal_key.key, &[], info, okm);
+    seal_key.key = sgx_key_128bit_t::default();
+    result
+}
+
+///
+/// rsgx_derive_seal_key_with_policy fills okm with a key derived from the sealing key
+/// selected by policy.
+///
+/// The errors are those of rsgx_derive_seal_key, plus **SGX_ERROR_INVALID_PARAMETER**
+/// if the policy is invalid (see SealPolicy::key_request).
+///
+pub fn rsgx_derive_seal_key_with_policy(
+    policy: &SealPolicy,
+    info: &[u8],
+    okm: &mut [u8],
+) -> SgxError {
+    let key_request = policy.key_request(&sgx_key_id_t::default())?;
+
+    let mut seal_key = rsgx_get_cached_key(&key_request)?;
+    let result = rsgx_hkdf_sha256_from_key128(&seal_key.key, &[], info, okm);
+    seal_key.key = sgx_key_128bit_t::default();
+    result
+}
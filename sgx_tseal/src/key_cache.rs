@@ -0,0 +1,247 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Seal key cache
+//!
+//! Unsealing derives the seal key with EGETKEY every time. With the cache enabled,
+//! keys derived while unsealing, verifying a detached MAC or deriving a key with
+//! rsgx_derive_seal_key are kept inside the enclave, indexed by the full key request,
+//! and the next request for the same key is answered without EGETKEY.
+//!
+//! Sealing picks a random key ID for every blob, so a key is only found again when the
+//! same blob, stream header or MAC is processed again, or for the KDF functions, which
+//! use a fixed key ID. Keys are never cached while sealing.
+//!
+//! The cache is disabled by default. Entries are zeroized when they are evicted or
+//! flushed. Pinning a CPUSVN limits the cache to keys of that CPUSVN, so that after a TCB
+//! recovery no key of the old CPUSVN stays resident.
+//!
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::hint;
+use core::mem;
+use core::ptr;
+use core::slice;
+use core::sync::atomic::{AtomicBool, Ordering};
+use sgx_tse::*;
+use sgx_types::*;
+
+/// The largest capacity of the seal key cache.
+pub const SGX_SEAL_KEY_CACHE_MAX_CAPACITY: usize = 4096;
+
+struct Entry {
+    request: sgx_key_request_t,
+    key: sgx_align_key_128bit_t,
+}
+
+struct Cache {
+    capacity: usize,
+    pinned_cpu_svn: Option<sgx_cpu_svn_t>,
+    // Least recently used first.
+    entries: Vec<Entry>,
+}
+
+impl Cache {
+    fn allows(&self, request: &sgx_key_request_t) -> bool {
+        match self.pinned_cpu_svn {
+            Some(ref svn) => svn.svn == request.cpu_svn.svn,
+            None => true,
+        }
+    }
+
+    fn remove(&mut self, index: usize) {
+        let mut entry = self.entries.remove(index);
+        wipe(&mut entry.key);
+    }
+
+    fn retain_allowed(&mut self) {
+        let mut i = 0;
+        while i < self.entries.len() {
+            if self.allows(&self.entries[i].request) {
+                i += 1;
+            } else {
+                self.remove(i);
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        while !self.entries.is_empty() {
+            self.remove(self.entries.len() - 1);
+        }
+    }
+}
+
+struct CacheLock {
+    locked: AtomicBool,
+    cache: UnsafeCell<Cache>,
+}
+
+unsafe impl Sync for CacheLock {}
+
+static CACHE: CacheLock = CacheLock {
+    locked: AtomicBool::new(false),
+    cache: UnsafeCell::new(Cache {
+        capacity: 0,
+        pinned_cpu_svn: None,
+        entries: Vec::new(),
+    }),
+};
+
+fn with_cache<R, F: FnOnce(&mut Cache) -> R>(f: F) -> R {
+    while CACHE
+        .locked
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        hint::spin_loop();
+    }
+    let result = f(unsafe { &mut *CACHE.cache.get() });
+    CACHE.locked.store(false, Ordering::Release);
+    result
+}
+
+fn wipe(key: &mut sgx_align_key_128bit_t) {
+    unsafe { ptr::write_volatile(&mut key.key, sgx_key_128bit_t::default()) };
+}
+
+fn request_bytes(request: &sgx_key_request_t) -> &[u8] {
+    unsafe {
+        slice::from_raw_parts(
+            request as *const _ as *const u8,
+            mem::size_of::<sgx_key_request_t>(),
+        )
+    }
+}
+
+///
+/// rsgx_seal_key_cache_enable enables the seal key cache with room for capacity keys,
+/// or changes the capacity of an enabled cache, evicting the least recently used keys.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// capacity is zero or greater than SGX_SEAL_KEY_CACHE_MAX_CAPACITY.
+///
+pub fn rsgx_seal_key_cache_enable(capacity: usize) -> SgxError {
+    if capacity == 0 || capacity > SGX_SEAL_KEY_CACHE_MAX_CAPACITY {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    with_cache(|cache| {
+        cache.capacity = capacity;
+        while cache.entries.len() > capacity {
+            cache.remove(0);
+        }
+    });
+    Ok(())
+}
+
+///
+/// rsgx_seal_key_cache_disable zeroizes all cached keys and disables the cache.
+///
+pub fn rsgx_seal_key_cache_disable() {
+    with_cache(|cache| {
+        cache.flush();
+        cache.capacity = 0;
+        cache.entries = Vec::new();
+    })
+}
+
+///
+/// rsgx_seal_key_cache_flush zeroizes all cached keys. The cache stays enabled.
+///
+pub fn rsgx_seal_key_cache_flush() {
+    with_cache(|cache| cache.flush())
+}
+
+///
+/// rsgx_seal_key_cache_pin_cpusvn limits the cache to keys derived for cpu_svn, and
+/// zeroizes the cached keys of any other CPUSVN.
+///
+pub fn rsgx_seal_key_cache_pin_cpusvn(cpu_svn: &sgx_cpu_svn_t) {
+    with_cache(|cache| {
+        cache.pinned_cpu_svn = Some(*cpu_svn);
+        cache.retain_allowed();
+    })
+}
+
+///
+/// rsgx_seal_key_cache_unpin_cpusvn lets the cache hold keys of any CPUSVN again.
+///
+pub fn rsgx_seal_key_cache_unpin_cpusvn() {
+    with_cache(|cache| cache.pinned_cpu_svn = None)
+}
+
+///
+/// rsgx_seal_key_cache_len returns the number of cached keys.
+///
+pub fn rsgx_seal_key_cache_len() -> usize {
+    with_cache(|cache| cache.entries.len())
+}
+
+// Returns the seal key of key_request, from the cache when possible.
+pub(crate) fn rsgx_get_cached_key(
+    key_request: &sgx_key_request_t,
+) -> SgxResult<sgx_align_key_128bit_t> {
+    let bytes = request_bytes(key_request);
+    let cached = with_cache(|cache| {
+        if cache.capacity == 0 || !cache.allows(key_request) {
+            return Some(None);
+        }
+        let index = cache
+            .entries
+            .iter()
+            .position(|e| request_bytes(&e.request) == bytes)?;
+        let entry = cache.entries.remove(index);
+        let key = entry.key;
+        cache.entries.push(entry);
+        Some(Some(key))
+    });
+
+    match cached {
+        // Hit.
+        Some(Some(key)) => Ok(key),
+        // Cache disabled, or the CPUSVN is not the pinned one.
+        Some(None) => rsgx_get_align_key(key_request),
+        // Miss.
+        None => {
+            let key = rsgx_get_align_key(key_request)?;
+            with_cache(|cache| {
+                if cache.capacity == 0 || !cache.allows(key_request) {
+                    return;
+                }
+                if cache
+                    .entries
+                    .iter()
+                    .any(|e| request_bytes(&e.request) == bytes)
+                {
+                    return;
+                }
+                if cache.entries.len() == cache.capacity {
+                    cache.remove(0);
+                }
+                cache.entries.push(Entry {
+                    request: *key_request,
+                    key,
+                });
+            });
+            Ok(key)
+        }
+    }
+}
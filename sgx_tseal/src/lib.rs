@@ -22,6 +22,11 @@
 //! * Exposes APIs to create sealed data which is both confidentiality andintegrity protected.
 //! * Exposes an API to unseal sealed data inside the enclave.
 //! * Provides APIs to authenticate and verify the input data with AES-GMAC.
+//! * Inspects the key request and sizes of sealed blobs without unsealing them.
+//! * Hands secrets between local enclaves, wrapped under a key agreed through local attestation reports.
+//! * Keeps an allowlist of peer enclave identities, sealed against rollback and updated through the secret exchange.
+//! * With the `std` feature, provides `SealWriter` and `UnsealReader` to seal and unseal streams of any length chunk by chunk.
+//! * With the `serialize` feature, provides `SgxSealCodec` to seal and unseal values of any type that implements the sgx_serialize traits.
 //!
 //! The library also provides APIs to help calculate the sealed data size, encrypt text length, and Message Authentication Code (MAC) text length.
 //!
@@ -89,10 +94,55 @@ extern crate sgx_trts;
 extern crate sgx_tse;
 extern crate sgx_types;
 
+#[cfg(all(not(target_env = "sgx"), feature = "std"))]
+extern crate sgx_tstd as std;
+#[cfg(all(target_env = "sgx", feature = "std"))]
+extern crate std;
+
+#[cfg(feature = "serialize")]
+extern crate sgx_serialize;
+
 mod seal;
 pub use self::seal::{SgxSealedData, SgxUnsealedData};
 
 mod aad;
 pub use self::aad::SgxMacAadata;
 
+mod aad_stream;
+pub use self::aad_stream::*;
+
 mod internal;
+
+mod policy;
+pub use self::policy::SealPolicy;
+
+mod blob;
+pub use self::blob::*;
+
+mod metadata;
+pub use self::metadata::SealedMetadata;
+
+mod rollback;
+pub use self::rollback::*;
+
+mod kdf;
+pub use self::kdf::*;
+
+mod key_cache;
+pub use self::key_cache::*;
+
+mod exchange;
+pub use self::exchange::*;
+
+mod allowlist;
+pub use self::allowlist::*;
+
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "std")]
+pub use self::stream::*;
+
+#[cfg(feature = "serialize")]
+mod codec;
+#[cfg(feature = "serialize")]
+pub use self::codec::*;
@@ -0,0 +1,150 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+//!
+//! Sealed blob inspection
+//!
+//! `SealedMetadata::parse` reads the key request and the sizes of a sealed blob
+//! without deriving its seal key, so large stores of sealed records can be sorted by
+//! key policy or security version, for example to find the ones that need
+//! re-sealing. Only the fixed part of the blob is read and nothing is authenticated:
+//! the values are those the blob claims until it is unsealed.
+//!
+use crate::blob::split_blob;
+use core::mem;
+use core::ptr;
+use sgx_types::*;
+
+///
+/// The metadata of a sealed blob.
+///
+#[derive(Clone, Copy)]
+pub struct SealedMetadata {
+    version: u16,
+    key_request: sgx_key_request_t,
+    payload_size: u32,
+    encrypt_txt_len: u32,
+}
+
+impl SealedMetadata {
+    ///
+    /// Parses the metadata of a versioned sealed blob, or of a raw sgx_sealed_data_t.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The blob has a malformed header, an unsupported version, inconsistent sizes, or
+    /// is shorter than its sizes claim.
+    ///
+    pub fn parse(blob: &[u8]) -> SgxResult<SealedMetadata> {
+        let (version, raw) = split_blob(blob)?;
+        let header_size = mem::size_of::<sgx_sealed_data_t>();
+        if raw.len() < header_size {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let header = unsafe { ptr::read_unaligned(raw.as_ptr() as *const sgx_sealed_data_t) };
+
+        let payload_size = header.aes_data.payload_size;
+        let encrypt_txt_len = header.plain_text_offset;
+        if encrypt_txt_len > payload_size || (raw.len() - header_size) < payload_size as usize {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(SealedMetadata {
+            version,
+            key_request: header.key_request,
+            payload_size,
+            encrypt_txt_len,
+        })
+    }
+
+    ///
+    /// Get the format version of the blob; 0 for a raw sgx_sealed_data_t.
+    ///
+    pub fn get_version(&self) -> u16 {
+        self.version
+    }
+
+    ///
+    /// Get the key request the blob was sealed with.
+    ///
+    pub fn get_key_request(&self) -> &sgx_key_request_t {
+        &self.key_request
+    }
+
+    ///
+    /// Get the key policy bits.
+    ///
+    pub fn get_key_policy(&self) -> u16 {
+        self.key_request.key_policy
+    }
+
+    ///
+    /// Get the attribute mask.
+    ///
+    pub fn get_attribute_mask(&self) -> sgx_attributes_t {
+        self.key_request.attribute_mask
+    }
+
+    ///
+    /// Get the misc select mask.
+    ///
+    pub fn get_misc_mask(&self) -> sgx_misc_select_t {
+        self.key_request.misc_mask
+    }
+
+    ///
+    /// Get the ISVSVN the seal key is derived for.
+    ///
+    pub fn get_isv_svn(&self) -> sgx_isv_svn_t {
+        self.key_request.isv_svn
+    }
+
+    ///
+    /// Get the CPUSVN the seal key is derived for.
+    ///
+    pub fn get_cpu_svn(&self) -> sgx_cpu_svn_t {
+        self.key_request.cpu_svn
+    }
+
+    ///
+    /// Get the CONFIGSVN the seal key is derived for.
+    ///
+    pub fn get_config_svn(&self) -> sgx_config_svn_t {
+        self.key_request.config_svn
+    }
+
+    ///
+    /// Get the payload size, that is, the encrypt text and additional text together.
+    ///
+    pub fn get_payload_size(&self) -> u32 {
+        self.payload_size
+    }
+
+    ///
+    /// Get the length of the encrypt text.
+    ///
+    pub fn get_encrypt_txt_len(&self) -> u32 {
+        self.encrypt_txt_len
+    }
+
+    ///
+    /// Get the length of the additional text.
+    ///
+    pub fn get_add_mac_txt_len(&self) -> u32 {
+        self.payload_size - self.encrypt_txt_len
+    }
+}
@@ -0,0 +1,240 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Seal key policy
+//!
+//! `SealPolicy` selects which enclave identity a seal key is bound to. It covers every
+//! field of the key request that the enclave controls: the key policy bits, the
+//! attribute and misc masks, and the ISVSVN, CPUSVN and CONFIGSVN the key is derived
+//! for. Lowering a security version yields the key of that older version, which lets
+//! an updated enclave read data sealed by its predecessors.
+//!
+use crate::internal::KEY_POLICY_KSS;
+use sgx_tse::*;
+use sgx_types::*;
+
+const KEY_POLICY_MASK: u16 =
+    SGX_KEYPOLICY_MRENCLAVE | SGX_KEYPOLICY_MRSIGNER | SGX_KEYPOLICY_NOISVPRODID | KEY_POLICY_KSS;
+
+///
+/// A builder for the key request used to seal data.
+///
+/// # Examples
+///
+/// Data that survives enclave updates but not a change of signer, and is not bound to
+/// the product ID:
+///
+/// ```ignore
+/// let policy = SealPolicy::mrsigner().bind_isv_prod_id(false);
+/// let sealed = SgxSealedData::<[u8]>::seal_data_with_policy(&policy, &aad, &secret)?;
+/// ```
+///
+#[derive(Clone, Copy)]
+pub struct SealPolicy {
+    key_policy: u16,
+    attribute_mask: sgx_attributes_t,
+    misc_mask: sgx_misc_select_t,
+    isv_svn: Option<sgx_isv_svn_t>,
+    cpu_svn: Option<sgx_cpu_svn_t>,
+    config_svn: Option<sgx_config_svn_t>,
+}
+
+impl SealPolicy {
+    fn new(key_policy: u16) -> SealPolicy {
+        SealPolicy {
+            key_policy,
+            attribute_mask: sgx_attributes_t {
+                flags: TSEAL_DEFAULT_FLAGSMASK,
+                xfrm: 0,
+            },
+            misc_mask: TSEAL_DEFAULT_MISCMASK,
+            isv_svn: None,
+            cpu_svn: None,
+            config_svn: None,
+        }
+    }
+
+    ///
+    /// A policy bound to the enclave measurement (MRENCLAVE), with the default masks.
+    ///
+    pub fn mrenclave() -> SealPolicy {
+        Self::new(SGX_KEYPOLICY_MRENCLAVE)
+    }
+
+    ///
+    /// A policy bound to the enclave signer (MRSIGNER) and product ID, with the default
+    /// masks.
+    ///
+    pub fn mrsigner() -> SealPolicy {
+        Self::new(SGX_KEYPOLICY_MRSIGNER)
+    }
+
+    // The policy used by seal_data and mac_aadata: MRSIGNER, plus the KSS fields in an
+    // enclave with KSS enabled.
+    pub(crate) fn seal_default() -> SealPolicy {
+        let report = rsgx_self_report();
+        if (report.body.attributes.flags & SGX_FLAGS_KSS) != 0 {
+            Self::new(SGX_KEYPOLICY_MRSIGNER | KEY_POLICY_KSS)
+        } else {
+            Self::new(SGX_KEYPOLICY_MRSIGNER)
+        }
+    }
+
+    fn set_bit(mut self, bit: u16, on: bool) -> SealPolicy {
+        if on {
+            self.key_policy |= bit;
+        } else {
+            self.key_policy &= !bit;
+        }
+        self
+    }
+
+    /// Binds the key to the enclave measurement (MRENCLAVE).
+    pub fn bind_mrenclave(self, on: bool) -> SealPolicy {
+        self.set_bit(SGX_KEYPOLICY_MRENCLAVE, on)
+    }
+
+    /// Binds the key to the enclave signer (MRSIGNER).
+    pub fn bind_mrsigner(self, on: bool) -> SealPolicy {
+        self.set_bit(SGX_KEYPOLICY_MRSIGNER, on)
+    }
+
+    /// Binds the key to the ISVPRODID. This is the default; turning it off sets
+    /// SGX_KEYPOLICY_NOISVPRODID.
+    pub fn bind_isv_prod_id(self, on: bool) -> SealPolicy {
+        self.set_bit(SGX_KEYPOLICY_NOISVPRODID, !on)
+    }
+
+    /// Binds the key to the CONFIGID. Requires an enclave with KSS enabled.
+    pub fn bind_config_id(self, on: bool) -> SealPolicy {
+        self.set_bit(SGX_KEYPOLICY_CONFIGID, on)
+    }
+
+    /// Binds the key to the ISVFAMILYID. Requires an enclave with KSS enabled.
+    pub fn bind_isv_family_id(self, on: bool) -> SealPolicy {
+        self.set_bit(SGX_KEYPOLICY_ISVFAMILYID, on)
+    }
+
+    /// Binds the key to the ISVEXTPRODID. Requires an enclave with KSS enabled.
+    pub fn bind_isv_ext_prod_id(self, on: bool) -> SealPolicy {
+        self.set_bit(SGX_KEYPOLICY_ISVEXTPRODID, on)
+    }
+
+    ///
+    /// Sets the attribute mask. The INITTED and DEBUG flags must be set in it.
+    ///
+    pub fn attribute_mask(mut self, attribute_mask: sgx_attributes_t) -> SealPolicy {
+        self.attribute_mask = attribute_mask;
+        self
+    }
+
+    /// Sets the misc mask.
+    pub fn misc_mask(mut self, misc_mask: sgx_misc_select_t) -> SealPolicy {
+        self.misc_mask = misc_mask;
+        self
+    }
+
+    ///
+    /// Derives the key for the given ISVSVN instead of the enclave's own. It must not
+    /// be greater than the enclave's ISVSVN.
+    ///
+    pub fn isv_svn(mut self, isv_svn: sgx_isv_svn_t) -> SealPolicy {
+        self.isv_svn = Some(isv_svn);
+        self
+    }
+
+    ///
+    /// Derives the key for the given CPUSVN instead of the platform's own. It must not
+    /// be beyond the platform CPUSVN.
+    ///
+    pub fn cpu_svn(mut self, cpu_svn: sgx_cpu_svn_t) -> SealPolicy {
+        self.cpu_svn = Some(cpu_svn);
+        self
+    }
+
+    ///
+    /// Derives the key for the given CONFIGSVN instead of the enclave's own. It must
+    /// not be greater than the enclave's CONFIGSVN.
+    ///
+    pub fn config_svn(mut self, config_svn: sgx_config_svn_t) -> SealPolicy {
+        self.config_svn = Some(config_svn);
+        self
+    }
+
+    /// Returns the key policy bits.
+    pub fn get_key_policy(&self) -> u16 {
+        self.key_policy
+    }
+
+    /// Returns the attribute mask.
+    pub fn get_attribute_mask(&self) -> sgx_attributes_t {
+        self.attribute_mask
+    }
+
+    /// Returns the misc mask.
+    pub fn get_misc_mask(&self) -> sgx_misc_select_t {
+        self.misc_mask
+    }
+
+    ///
+    /// Builds the seal key request for key_id.
+    ///
+    /// Security versions that were not set are taken from the enclave's own report.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// Indicates an error if the policy does not meet any of the following conditions:
+    ///
+    /// * At least one of MRENCLAVE and MRSIGNER is bound.
+    /// * CONFIGID, ISVFAMILYID and ISVEXTPRODID are only bound in an enclave with KSS enabled.
+    /// * The attribute mask has the INITTED and DEBUG flags set.
+    ///
+    pub fn key_request(&self, key_id: &sgx_key_id_t) -> SgxResult<sgx_key_request_t> {
+        let report = rsgx_self_report();
+        if (self.key_policy & !KEY_POLICY_MASK) != 0
+            || (self.key_policy & (SGX_KEYPOLICY_MRENCLAVE | SGX_KEYPOLICY_MRSIGNER)) == 0
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if (self.key_policy & KEY_POLICY_KSS) != 0
+            && (report.body.attributes.flags & SGX_FLAGS_KSS) == 0
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if ((self.attribute_mask.flags & SGX_FLAGS_INITTED) == 0)
+            || ((self.attribute_mask.flags & SGX_FLAGS_DEBUG) == 0)
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        Ok(sgx_key_request_t {
+            key_name: SGX_KEYSELECT_SEAL,
+            key_policy: self.key_policy,
+            isv_svn: self.isv_svn.unwrap_or(report.body.isv_svn),
+            reserved1: 0_u16,
+            cpu_svn: self.cpu_svn.unwrap_or(report.body.cpu_svn),
+            attribute_mask: self.attribute_mask,
+            key_id: *key_id,
+            misc_mask: self.misc_mask,
+            config_svn: self.config_svn.unwrap_or(report.body.config_svn),
+            reserved2: [0_u8; SGX_KEY_REQUEST_RESERVED2_BYTES],
+        })
+    }
+}
@@ -0,0 +1,203 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Rollback protection for sealed state
+//!
+//! Sealing protects confidentiality and integrity, but an old sealed blob is as valid
+//! as the latest one, so the host can roll enclave state back by handing it an old
+//! copy. `rsgx_seal_state` binds every blob to a new value (the epoch) of a monotonic
+//! counter, and `rsgx_unseal_state` only accepts the blob whose epoch is the current
+//! counter value.
+//!
+//! Counters are pluggable through `SgxMonotonicCounter`. The platform service
+//! counters were removed from the Intel(R) SGX SDK in version 2.8, so production
+//! deployments use a remote counter service, or a replicated one, implemented on top
+//! of that trait. `SgxVolatileCounter` keeps the counter in enclave memory; it only
+//! protects against rollback while the enclave is running.
+//!
+//! The counter is incremented before sealing. If the enclave stops after the increment
+//! but before the new blob is stored, the state cannot be unsealed any more; this errs
+//! on the side of refusing a possible rollback.
+//!
+use crate::blob::*;
+use crate::policy::SealPolicy;
+use crate::seal::SgxUnsealedData;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+use sgx_tcrypto::rsgx_zeroize;
+use sgx_types::*;
+
+const STATE_MAGIC: [u8; 8] = *b"SGXSTATE";
+/// The longest counter ID a sealed state can be bound to.
+pub const SGX_MONOTONIC_COUNTER_MAX_ID_SIZE: usize = 256;
+
+///
+/// A monotonic counter that sealed state is bound to.
+///
+pub trait SgxMonotonicCounter {
+    ///
+    /// An identifier of the counter, such as its UUID at a counter service. It is bound
+    /// into the sealed state, so state of one counter is never checked against another.
+    ///
+    fn id(&self) -> &[u8];
+
+    ///
+    /// Returns the current value of the counter.
+    ///
+    fn read(&self) -> SgxResult<u64>;
+
+    ///
+    /// Increments the counter and returns its new value. The increment must be durable
+    /// before this returns.
+    ///
+    fn increment(&self) -> SgxResult<u64>;
+}
+
+///
+/// A monotonic counter in enclave memory.
+///
+/// It only detects rollback to state sealed earlier during the lifetime of the
+/// enclave instance. It is meant for tests, and for state that is discarded when the
+/// enclave is destroyed.
+///
+pub struct SgxVolatileCounter {
+    id: [u8; 16],
+    value: AtomicU64,
+}
+
+impl SgxVolatileCounter {
+    ///
+    /// Creates a counter with the given ID and initial value.
+    ///
+    pub fn new(id: &[u8; 16], value: u64) -> SgxVolatileCounter {
+        SgxVolatileCounter {
+            id: *id,
+            value: AtomicU64::new(value),
+        }
+    }
+}
+
+impl SgxMonotonicCounter for SgxVolatileCounter {
+    fn id(&self) -> &[u8] {
+        &self.id
+    }
+
+    fn read(&self) -> SgxResult<u64> {
+        Ok(self.value.load(Ordering::SeqCst))
+    }
+
+    fn increment(&self) -> SgxResult<u64> {
+        self.value
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |v| v.checked_add(1))
+            .map(|v| v + 1)
+            .map_err(|_| sgx_status_t::SGX_ERROR_MC_USED_UP)
+    }
+}
+
+///
+/// rsgx_seal_state increments counter and seals encrypt_text under policy, bound to the
+/// new counter value. The result is a versioned sealed blob.
+///
+/// The counter ID and the epoch are kept in the MAC text of the blob, ahead of
+/// additional_text.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The counter ID is longer than SGX_MONOTONIC_COUNTER_MAX_ID_SIZE.
+///
+/// The errors of the counter, and of rsgx_seal_blob.
+///
+pub fn rsgx_seal_state<C: SgxMonotonicCounter + ?Sized>(
+    counter: &C,
+    policy: &SealPolicy,
+    additional_text: &[u8],
+    encrypt_text: &[u8],
+) -> SgxResult<Vec<u8>> {
+    let id = counter.id();
+    if id.len() > SGX_MONOTONIC_COUNTER_MAX_ID_SIZE {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let epoch = counter.increment()?;
+
+    let mut mac_txt = Vec::with_capacity(STATE_MAGIC.len() + 10 + id.len() + additional_text.len());
+    mac_txt.extend_from_slice(&STATE_MAGIC);
+    mac_txt.extend_from_slice(&(id.len() as u16).to_le_bytes());
+    mac_txt.extend_from_slice(id);
+    mac_txt.extend_from_slice(&epoch.to_le_bytes());
+    mac_txt.extend_from_slice(additional_text);
+    rsgx_seal_blob(policy, &mac_txt, encrypt_text)
+}
+
+///
+/// rsgx_unseal_state unseals a blob written by rsgx_seal_state and checks that it is
+/// the latest state of counter. The additional text returned is the one given to
+/// rsgx_seal_state.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_STATE**
+///
+/// The blob is bound to another value of the counter, that is, it is an old state, or
+/// the counter was reset.
+///
+/// **SGX_ERROR_MAC_MISMATCH**
+///
+/// The blob is not sealed state, or is bound to another counter.
+///
+/// The errors of the counter, and of rsgx_unseal_blob.
+///
+pub fn rsgx_unseal_state<'a, C: SgxMonotonicCounter + ?Sized>(
+    counter: &C,
+    blob: &[u8],
+) -> SgxResult<SgxUnsealedData<'a, [u8]>> {
+    let mut unsealed = rsgx_unseal_blob(blob)?;
+    let result = check_state(counter, &unsealed.additional);
+    match result {
+        Ok(offset) => {
+            unsealed.additional = unsealed.additional[offset..].into();
+            Ok(unsealed)
+        }
+        Err(e) => {
+            rsgx_zeroize(&mut unsealed.decrypt);
+            Err(e)
+        }
+    }
+}
+
+// Checks the counter binding in the MAC text and returns the offset of the caller's
+// additional text.
+fn check_state<C: SgxMonotonicCounter + ?Sized>(counter: &C, mac_txt: &[u8]) -> SgxResult<usize> {
+    let magic_len = STATE_MAGIC.len();
+    if mac_txt.len() < magic_len + 2 || mac_txt[..magic_len] != STATE_MAGIC {
+        return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+    }
+    let id_len = u16::from_le_bytes([mac_txt[magic_len], mac_txt[magic_len + 1]]) as usize;
+    let id_start = magic_len + 2;
+    let epoch_start = id_start + id_len;
+    if mac_txt.len() < epoch_start + 8 || mac_txt[id_start..epoch_start] != *counter.id() {
+        return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+    }
+    let mut epoch = [0_u8; 8];
+    epoch.copy_from_slice(&mac_txt[epoch_start..epoch_start + 8]);
+    if u64::from_le_bytes(epoch) != counter.read()? {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+    }
+    Ok(epoch_start + 8)
+}
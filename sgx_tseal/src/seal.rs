@@ -28,6 +28,7 @@
 //! The library also provides APIs to help calculate the sealed data size, encrypt text length, and Message Authentication Code (MAC) text length.
 //!
 use crate::internal::*;
+use crate::policy::SealPolicy;
 use alloc::boxed::Box;
 use alloc::slice;
 use core::marker::PhantomData;
@@ -40,7 +41,7 @@ pub struct SgxUnsealedData<'a, T: 'a + ?Sized> {
     pub payload_size: u32,
     pub decrypt: Box<T>,
     pub additional: Box<[u8]>,
-    marker: PhantomData<&'a T>,
+    pub(crate) marker: PhantomData<&'a T>,
 }
 
 impl<'a, T: 'a + ?Sized> SgxUnsealedData<'a, T> {
@@ -54,13 +55,13 @@ impl<'a, T: 'a + ?Sized> SgxUnsealedData<'a, T> {
     /// Get the pointer of decrypt buffer in SgxUnsealedData.
     ///
     pub fn get_decrypt_txt(&self) -> &T {
-        &*self.decrypt
+        &self.decrypt
     }
     ///
     /// Get the pointer of additional buffer in SgxUnsealedData.
     ///
     pub fn get_additional_txt(&self) -> &[u8] {
-        &*self.additional
+        &self.additional
     }
 }
 
@@ -302,6 +303,135 @@ impl<'a, T: 'a + Copy + ContiguousMemory> SgxSealedData<'a, T> {
         })
     }
 
+    ///
+    /// This function seals data with the key selected by a SealPolicy, such as one bound
+    /// to MRSIGNER and ISVFAMILYID, or one derived for an older ISVSVN, instead of
+    /// passing raw key policy bits and masks to seal_data_ex.
+    ///
+    /// # Parameters
+    ///
+    /// **policy**
+    ///
+    /// The key policy, masks and security versions of the seal key.
+    ///
+    /// **additional_text**
+    ///
+    /// Pointer to the additional Message Authentication Code (MAC) data.
+    /// This additional data is optional and no data is necessary.
+    ///
+    /// **encrypt_text**
+    ///
+    /// Pointer to the data stream to be encrypted, which is &T. Must not be NULL. Must be within the enclave.
+    ///
+    /// # Return value
+    ///
+    /// The sealed data in SgxSealedData.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The policy is invalid (see SealPolicy::key_request), or the text buffers do not
+    /// meet the conditions of seal_data_ex.
+    ///
+    /// **SGX_ERROR_INVALID_CPUSVN**
+    ///
+    /// The CPUSVN of the policy is beyond the platform CPUSVN value.
+    ///
+    /// **SGX_ERROR_INVALID_ISVSVN**
+    ///
+    /// The ISVSVN or CONFIGSVN of the policy is greater than the enclave's.
+    ///
+    /// **SGX_ERROR_OUT_OF_MEMORY**
+    ///
+    /// The enclave is out of memory.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// Indicates a crypto library failure or the RDRAND instruction fails to generate a
+    /// random number.
+    ///
+    pub fn seal_data_with_policy(
+        policy: &SealPolicy,
+        additional_text: &[u8],
+        encrypt_text: &'a T,
+    ) -> SgxResult<Self> {
+        let size = mem::size_of::<T>();
+        if size == 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let encrypt_slice: &[u8] = unsafe {
+            slice::from_raw_parts(
+                encrypt_text as *const _ as *const u8,
+                mem::size_of_val(encrypt_text),
+            )
+        };
+        let result =
+            SgxInternalSealedData::seal_data_with_policy(policy, additional_text, encrypt_slice);
+        result.map(|x| SgxSealedData {
+            inner: x,
+            marker: PhantomData,
+        })
+    }
+
+    ///
+    /// This function seals data in a context: a label, such as the name of a
+    /// subsystem, that is mixed into the key ID, so every context gets its own seal keys.
+    /// Data sealed in one context is only unsealed by unseal_data_with_context with the
+    /// same label. Note that unseal_data still opens it, so all code that must not read
+    /// a context's data has to use unseal_data_with_context.
+    ///
+    /// # Parameters
+    ///
+    /// **policy**
+    ///
+    /// The key policy, masks and security versions of the seal key.
+    ///
+    /// **context**
+    ///
+    /// The context label. It may be of any length, including empty.
+    ///
+    /// **additional_text**
+    ///
+    /// Pointer to the additional Message Authentication Code (MAC) data.
+    /// This additional data is optional and no data is necessary.
+    ///
+    /// **encrypt_text**
+    ///
+    /// Pointer to the data stream to be encrypted, which is &T. Must not be NULL. Must be within the enclave.
+    ///
+    /// # Errors
+    ///
+    /// The errors of seal_data_with_policy.
+    ///
+    pub fn seal_data_with_context(
+        policy: &SealPolicy,
+        context: &[u8],
+        additional_text: &[u8],
+        encrypt_text: &'a T,
+    ) -> SgxResult<Self> {
+        let size = mem::size_of::<T>();
+        if size == 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let encrypt_slice: &[u8] = unsafe {
+            slice::from_raw_parts(
+                encrypt_text as *const _ as *const u8,
+                mem::size_of_val(encrypt_text),
+            )
+        };
+        let result = SgxInternalSealedData::seal_data_with_context(
+            policy,
+            context,
+            additional_text,
+            encrypt_slice,
+        );
+        result.map(|x| SgxSealedData {
+            inner: x,
+            marker: PhantomData,
+        })
+    }
+
     ///
     /// This function is used to AES-GCM decrypt the input sealed data structure.
     /// Two output data sets result: one is the decrypted data; the second is the
@@ -351,6 +481,28 @@ impl<'a, T: 'a + Copy + ContiguousMemory> SgxSealedData<'a, T> {
     /// random number.
     ///
     pub fn unseal_data(&self) -> SgxResult<SgxUnsealedData<'a, T>> {
+        self.unseal_with(|inner| inner.unseal_data())
+    }
+
+    ///
+    /// This function unseals data that was sealed with seal_data_with_context.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The data was sealed in another context, or without a context.
+    ///
+    /// The other errors of unseal_data.
+    ///
+    pub fn unseal_data_with_context(&self, context: &[u8]) -> SgxResult<SgxUnsealedData<'a, T>> {
+        self.unseal_with(|inner| inner.unseal_data_with_context(context))
+    }
+
+    fn unseal_with<F>(&self, unseal: F) -> SgxResult<SgxUnsealedData<'a, T>>
+    where
+        F: FnOnce(&SgxInternalSealedData) -> SgxResult<SgxInternalUnsealedData>,
+    {
         let size = mem::size_of::<T>();
         if size == 0 {
             return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -359,7 +511,7 @@ impl<'a, T: 'a + Copy + ContiguousMemory> SgxSealedData<'a, T> {
         if size != encrypt_len {
             return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
         }
-        self.inner.unseal_data().map(|x| {
+        unseal(&self.inner).map(|x| {
             let ptr = Box::into_raw(x.decrypt);
             SgxUnsealedData {
                 payload_size: x.payload_size,
@@ -618,6 +770,130 @@ impl<'a, T: 'a + Copy + ContiguousMemory> SgxSealedData<'a, [T]> {
         })
     }
 
+    ///
+    /// This function seals data with the key selected by a SealPolicy, such as one bound
+    /// to MRSIGNER and ISVFAMILYID, or one derived for an older ISVSVN, instead of
+    /// passing raw key policy bits and masks to seal_data_ex.
+    ///
+    /// # Parameters
+    ///
+    /// **policy**
+    ///
+    /// The key policy, masks and security versions of the seal key.
+    ///
+    /// **additional_text**
+    ///
+    /// Pointer to the additional Message Authentication Code (MAC) data.
+    /// This additional data is optional and no data is necessary.
+    ///
+    /// **encrypt_text**
+    ///
+    /// Pointer to the data stream to be encrypted, which is &[T]. Must not be NULL. Must be within the enclave.
+    ///
+    /// # Return value
+    ///
+    /// The sealed data in SgxSealedData.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The policy is invalid (see SealPolicy::key_request), or the text buffers do not
+    /// meet the conditions of seal_data_ex.
+    ///
+    /// **SGX_ERROR_INVALID_CPUSVN**
+    ///
+    /// The CPUSVN of the policy is beyond the platform CPUSVN value.
+    ///
+    /// **SGX_ERROR_INVALID_ISVSVN**
+    ///
+    /// The ISVSVN or CONFIGSVN of the policy is greater than the enclave's.
+    ///
+    /// **SGX_ERROR_OUT_OF_MEMORY**
+    ///
+    /// The enclave is out of memory.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// Indicates a crypto library failure or the RDRAND instruction fails to generate a
+    /// random number.
+    ///
+    pub fn seal_data_with_policy(
+        policy: &SealPolicy,
+        additional_text: &[u8],
+        encrypt_text: &'a [T],
+    ) -> SgxResult<Self> {
+        let size = mem::size_of::<T>();
+        let len = mem::size_of_val(encrypt_text);
+        if size == 0 || len == 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let encrypt_slice: &[u8] =
+            unsafe { slice::from_raw_parts(encrypt_text.as_ptr() as *const u8, len) };
+
+        let result =
+            SgxInternalSealedData::seal_data_with_policy(policy, additional_text, encrypt_slice);
+        result.map(|x| SgxSealedData {
+            inner: x,
+            marker: PhantomData,
+        })
+    }
+
+    ///
+    /// This function seals data in a context: a label, such as the name of a
+    /// subsystem, that is mixed into the key ID, so every context gets its own seal keys.
+    /// Data sealed in one context is only unsealed by unseal_data_with_context with the
+    /// same label. Note that unseal_data still opens it, so all code that must not read
+    /// a context's data has to use unseal_data_with_context.
+    ///
+    /// # Parameters
+    ///
+    /// **policy**
+    ///
+    /// The key policy, masks and security versions of the seal key.
+    ///
+    /// **context**
+    ///
+    /// The context label. It may be of any length, including empty.
+    ///
+    /// **additional_text**
+    ///
+    /// Pointer to the additional Message Authentication Code (MAC) data.
+    /// This additional data is optional and no data is necessary.
+    ///
+    /// **encrypt_text**
+    ///
+    /// Pointer to the data stream to be encrypted, which is &[T]. Must not be NULL. Must be within the enclave.
+    ///
+    /// # Errors
+    ///
+    /// The errors of seal_data_with_policy.
+    ///
+    pub fn seal_data_with_context(
+        policy: &SealPolicy,
+        context: &[u8],
+        additional_text: &[u8],
+        encrypt_text: &'a [T],
+    ) -> SgxResult<Self> {
+        let size = mem::size_of::<T>();
+        let len = mem::size_of_val(encrypt_text);
+        if size == 0 || len == 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let encrypt_slice: &[u8] =
+            unsafe { slice::from_raw_parts(encrypt_text.as_ptr() as *const u8, len) };
+        let result = SgxInternalSealedData::seal_data_with_context(
+            policy,
+            context,
+            additional_text,
+            encrypt_slice,
+        );
+        result.map(|x| SgxSealedData {
+            inner: x,
+            marker: PhantomData,
+        })
+    }
+
     ///
     /// This function is used to AES-GCM decrypt the input sealed data structure.
     /// Two output data sets result: one is the decrypted data; the second is the
@@ -667,6 +943,28 @@ impl<'a, T: 'a + Copy + ContiguousMemory> SgxSealedData<'a, [T]> {
     /// random number.
     ///
     pub fn unseal_data(&self) -> SgxResult<SgxUnsealedData<'a, [T]>> {
+        self.unseal_with(|inner| inner.unseal_data())
+    }
+
+    ///
+    /// This function unseals data that was sealed with seal_data_with_context.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The data was sealed in another context, or without a context.
+    ///
+    /// The other errors of unseal_data.
+    ///
+    pub fn unseal_data_with_context(&self, context: &[u8]) -> SgxResult<SgxUnsealedData<'a, [T]>> {
+        self.unseal_with(|inner| inner.unseal_data_with_context(context))
+    }
+
+    fn unseal_with<F>(&self, unseal: F) -> SgxResult<SgxUnsealedData<'a, [T]>>
+    where
+        F: FnOnce(&SgxInternalSealedData) -> SgxResult<SgxInternalUnsealedData>,
+    {
         let size = mem::size_of::<T>();
         if size == 0 {
             return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
@@ -679,7 +977,7 @@ impl<'a, T: 'a + Copy + ContiguousMemory> SgxSealedData<'a, [T]> {
             return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
         }
 
-        self.inner.unseal_data().map(|x| {
+        unseal(&self.inner).map(|x| {
             let ptr = Box::into_raw(x.decrypt);
             let slice = unsafe { slice::from_raw_parts_mut(ptr as *mut T, encrypt_len / size) };
             SgxUnsealedData {
@@ -0,0 +1,441 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//!
+//! Streaming seal and unseal
+//!
+//! `seal_data` needs the whole plaintext and the whole sealed blob in enclave memory
+//! at once. `SealWriter` and `UnsealReader` instead seal a stream of any length in
+//! fixed-size chunks, so only a couple of chunks are resident at any time.
+//!
+//! A sealed stream starts with a header, which is an ordinary `sgx_sealed_data_t`
+//! blob prefixed by its 32-bit little-endian length. The header seals a random
+//! 128-bit stream key to the enclave, and carries the chunk size and the caller's
+//! additional text as its MAC text. The header is followed by records, each made of
+//! a 32-bit little-endian length word, the ciphertext and a 16-byte tag. Bit 31 of
+//! the length word marks the final record.
+//!
+//! Every record is encrypted with AES-GCM under the stream key. The IV is the record
+//! number followed by the final flag, and the additional data is the tag of the
+//! previous record, so records cannot be dropped, reordered or replayed, and a stream
+//! cut short before its final record is detected.
+//!
+use crate::internal::*;
+use sgx_tcrypto::*;
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_types::*;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::vec::Vec;
+
+/// The chunk size used by `SealWriter::new`.
+pub const SEAL_STREAM_DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+/// The largest chunk size a sealed stream may use.
+pub const SEAL_STREAM_MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+/// The largest additional text a sealed stream header may carry.
+pub const SEAL_STREAM_MAX_ADDITIONAL_SIZE: usize = 64 * 1024;
+
+const STREAM_MAGIC: [u8; 8] = *b"SGXSTRM1";
+const HEADER_MAC_TXT_SIZE: usize = 12;
+const FINAL_FLAG: u32 = 0x8000_0000;
+
+fn record_iv(counter: u64, last: bool) -> [u8; SGX_AESGCM_IV_SIZE] {
+    let mut iv = [0_u8; SGX_AESGCM_IV_SIZE];
+    iv[..8].copy_from_slice(&counter.to_be_bytes());
+    iv[SGX_AESGCM_IV_SIZE - 1] = last as u8;
+    iv
+}
+
+// The stream key and the plaintext of the current chunk, wiped on drop.
+struct ChunkState {
+    key: sgx_aes_gcm_128bit_key_t,
+    plain: Vec<u8>,
+}
+
+impl ChunkState {
+    fn new(chunk_size: usize) -> ChunkState {
+        ChunkState {
+            key: [0_u8; SGX_AESGCM_KEY_SIZE],
+            plain: Vec::with_capacity(chunk_size),
+        }
+    }
+
+    fn clear(&mut self) {
+        rsgx_zeroize(&mut self.plain);
+        self.plain.clear();
+    }
+}
+
+impl Drop for ChunkState {
+    fn drop(&mut self) {
+        rsgx_zeroize(&mut self.key);
+        rsgx_zeroize(&mut self.plain);
+    }
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+///
+/// Seals a byte stream of any length to the enclave, one chunk at a time.
+///
+/// Data written is buffered until a whole chunk is available, which is then
+/// encrypted and written to the inner writer as one record. `flush` writes any
+/// buffered data as a short record. The stream must be ended with `finish`; a
+/// writer that is dropped without it leaves a truncated stream that
+/// `UnsealReader` rejects.
+///
+/// The stream key is sealed with the MRSIGNER key policy, as `seal_data` does.
+///
+pub struct SealWriter<W: Write> {
+    inner: W,
+    state: ChunkState,
+    chunk_size: usize,
+    counter: u64,
+    prev_tag: sgx_aes_gcm_128bit_tag_t,
+    cipher: Vec<u8>,
+}
+
+impl<W: Write> SealWriter<W> {
+    ///
+    /// Starts a sealed stream on `inner` with the default chunk size.
+    ///
+    /// # Parameters
+    ///
+    /// **inner**
+    ///
+    /// The writer that receives the sealed stream.
+    ///
+    /// **additional_text**
+    ///
+    /// Data that is authenticated but not encrypted, returned by
+    /// `UnsealReader::get_additional_txt`.
+    ///
+    /// # Errors
+    ///
+    /// Any error of the inner writer, or of sealing the stream header.
+    ///
+    pub fn new(inner: W, additional_text: &[u8]) -> io::Result<SealWriter<W>> {
+        Self::with_chunk_size(inner, additional_text, SEAL_STREAM_DEFAULT_CHUNK_SIZE)
+    }
+
+    ///
+    /// Starts a sealed stream on `inner` that is split into records of
+    /// `chunk_size` bytes.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// `chunk_size` is zero or greater than `SEAL_STREAM_MAX_CHUNK_SIZE`, or
+    /// `additional_text` is longer than `SEAL_STREAM_MAX_ADDITIONAL_SIZE`.
+    ///
+    /// Any error of the inner writer, or of sealing the stream header.
+    ///
+    pub fn with_chunk_size(
+        mut inner: W,
+        additional_text: &[u8],
+        chunk_size: usize,
+    ) -> io::Result<SealWriter<W>> {
+        if chunk_size == 0
+            || chunk_size > SEAL_STREAM_MAX_CHUNK_SIZE
+            || additional_text.len() > SEAL_STREAM_MAX_ADDITIONAL_SIZE
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER.into());
+        }
+
+        let mut state = ChunkState::new(chunk_size);
+        rsgx_read_rand(&mut state.key)?;
+
+        let mut mac_txt = Vec::with_capacity(HEADER_MAC_TXT_SIZE + additional_text.len());
+        mac_txt.extend_from_slice(&STREAM_MAGIC);
+        mac_txt.extend_from_slice(&(chunk_size as u32).to_le_bytes());
+        mac_txt.extend_from_slice(additional_text);
+        let sealed = SgxInternalSealedData::seal_data(&mac_txt, &state.key)?;
+
+        let size = SgxInternalSealedData::calc_raw_sealed_data_size(
+            sealed.get_add_mac_txt_len(),
+            sealed.get_encrypt_txt_len(),
+        );
+        // sgx_sealed_data_t must be 8-byte aligned.
+        let mut raw = vec![0_u64; (size as usize + 7) / 8];
+        unsafe {
+            sealed
+                .to_raw_sealed_data_t(raw.as_mut_ptr() as *mut sgx_sealed_data_t, size)
+                .ok_or(sgx_status_t::SGX_ERROR_UNEXPECTED)?;
+        }
+        let raw_bytes =
+            unsafe { std::slice::from_raw_parts(raw.as_ptr() as *const u8, size as usize) };
+        inner.write_all(&size.to_le_bytes())?;
+        inner.write_all(raw_bytes)?;
+
+        Ok(SealWriter {
+            inner,
+            state,
+            chunk_size,
+            counter: 0,
+            prev_tag: [0_u8; SGX_AESGCM_MAC_SIZE],
+            cipher: vec![0_u8; chunk_size],
+        })
+    }
+
+    /// Returns a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    ///
+    /// Writes any buffered data as the final record and returns the inner writer.
+    ///
+    /// # Errors
+    ///
+    /// Any error of the inner writer, or of encrypting the record.
+    ///
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_record(true)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_record(&mut self, last: bool) -> io::Result<()> {
+        let len = self.state.plain.len();
+        let iv = record_iv(self.counter, last);
+        let mut tag = [0_u8; SGX_AESGCM_MAC_SIZE];
+        rsgx_rijndael128GCM_encrypt(
+            &self.state.key,
+            &self.state.plain,
+            &iv,
+            &self.prev_tag,
+            &mut self.cipher[..len],
+            &mut tag,
+        )?;
+        self.state.clear();
+
+        let mut word = len as u32;
+        if last {
+            word |= FINAL_FLAG;
+        }
+        self.inner.write_all(&word.to_le_bytes())?;
+        self.inner.write_all(&self.cipher[..len])?;
+        self.inner.write_all(&tag)?;
+
+        self.prev_tag = tag;
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| invalid_data("too many records in sealed stream"))?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for SealWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A full chunk is only written once more data arrives, so that the final
+        // record is never empty unless the whole stream is.
+        if self.state.plain.len() == self.chunk_size {
+            self.write_record(false)?;
+        }
+        let n = buf.len().min(self.chunk_size - self.state.plain.len());
+        self.state.plain.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.state.plain.is_empty() {
+            self.write_record(false)?;
+        }
+        self.inner.flush()
+    }
+}
+
+///
+/// Reads back a stream sealed by `SealWriter`.
+///
+/// Each record is authenticated before any of its plaintext is returned. Reading
+/// past the final record returns `Ok(0)`; an inner reader that ends before it
+/// yields an `UnexpectedEof` error. After any error the reader is unusable and
+/// every further read fails. Bytes following the final record are left unread in
+/// the inner reader.
+///
+pub struct UnsealReader<R: Read> {
+    inner: R,
+    state: ChunkState,
+    chunk_size: usize,
+    counter: u64,
+    prev_tag: sgx_aes_gcm_128bit_tag_t,
+    additional: Vec<u8>,
+    cipher: Vec<u8>,
+    pos: usize,
+    done: bool,
+    failed: bool,
+}
+
+impl<R: Read> UnsealReader<R> {
+    ///
+    /// Reads and unseals the header of a sealed stream.
+    ///
+    /// # Errors
+    ///
+    /// **InvalidData**
+    ///
+    /// The header is malformed.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The header was not sealed by this enclave, or was modified.
+    ///
+    /// Any other error of the inner reader, or of unsealing the header.
+    ///
+    pub fn new(mut inner: R) -> io::Result<UnsealReader<R>> {
+        let mut word = [0_u8; 4];
+        inner.read_exact(&mut word)?;
+        let size = u32::from_le_bytes(word);
+        let max_size = SgxInternalSealedData::calc_raw_sealed_data_size(
+            (HEADER_MAC_TXT_SIZE + SEAL_STREAM_MAX_ADDITIONAL_SIZE) as u32,
+            SGX_AESGCM_KEY_SIZE as u32,
+        );
+        if (size as usize) < mem::size_of::<sgx_sealed_data_t>() || size > max_size {
+            return Err(invalid_data("invalid sealed stream header size"));
+        }
+
+        let mut raw = vec![0_u64; (size as usize + 7) / 8];
+        let raw_bytes =
+            unsafe { std::slice::from_raw_parts_mut(raw.as_mut_ptr() as *mut u8, size as usize) };
+        inner.read_exact(raw_bytes)?;
+        let sealed = unsafe {
+            SgxInternalSealedData::from_raw_sealed_data_t(
+                raw.as_mut_ptr() as *mut sgx_sealed_data_t,
+                size,
+            )
+        }
+        .ok_or_else(|| invalid_data("invalid sealed stream header"))?;
+        let mut unsealed = sealed.unseal_data()?;
+
+        let key_ok = unsealed.decrypt.len() == SGX_AESGCM_KEY_SIZE;
+        let mac_txt = &unsealed.additional;
+        if !key_ok || mac_txt.len() < HEADER_MAC_TXT_SIZE || mac_txt[..8] != STREAM_MAGIC {
+            rsgx_zeroize(&mut unsealed.decrypt);
+            return Err(invalid_data("invalid sealed stream header"));
+        }
+        let mut chunk_word = [0_u8; 4];
+        chunk_word.copy_from_slice(&mac_txt[8..HEADER_MAC_TXT_SIZE]);
+        let chunk_size = u32::from_le_bytes(chunk_word) as usize;
+        if chunk_size == 0 || chunk_size > SEAL_STREAM_MAX_CHUNK_SIZE {
+            rsgx_zeroize(&mut unsealed.decrypt);
+            return Err(invalid_data("invalid sealed stream chunk size"));
+        }
+
+        let mut state = ChunkState::new(chunk_size);
+        state.key.copy_from_slice(&unsealed.decrypt);
+        rsgx_zeroize(&mut unsealed.decrypt);
+
+        Ok(UnsealReader {
+            inner,
+            state,
+            chunk_size,
+            counter: 0,
+            prev_tag: [0_u8; SGX_AESGCM_MAC_SIZE],
+            additional: mac_txt[HEADER_MAC_TXT_SIZE..].to_vec(),
+            cipher: vec![0_u8; chunk_size],
+            pos: 0,
+            done: false,
+            failed: false,
+        })
+    }
+
+    /// Returns the additional text given to `SealWriter`.
+    pub fn get_additional_txt(&self) -> &[u8] {
+        &self.additional
+    }
+
+    /// Returns the chunk size of the stream.
+    pub fn get_chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Returns a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the inner reader, positioned after the last record read.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn read_record(&mut self) -> io::Result<()> {
+        let mut word = [0_u8; 4];
+        self.inner.read_exact(&mut word)?;
+        let word = u32::from_le_bytes(word);
+        let last = word & FINAL_FLAG != 0;
+        let len = (word & !FINAL_FLAG) as usize;
+        if len > self.chunk_size {
+            return Err(invalid_data("invalid sealed stream record size"));
+        }
+
+        let mut tag = [0_u8; SGX_AESGCM_MAC_SIZE];
+        self.inner.read_exact(&mut self.cipher[..len])?;
+        self.inner.read_exact(&mut tag)?;
+
+        self.state.clear();
+        self.state.plain.resize(len, 0);
+        self.pos = 0;
+        let iv = record_iv(self.counter, last);
+        if let Err(e) = rsgx_rijndael128GCM_decrypt(
+            &self.state.key,
+            &self.cipher[..len],
+            &iv,
+            &self.prev_tag,
+            &tag,
+            &mut self.state.plain,
+        ) {
+            self.state.clear();
+            return Err(e.into());
+        }
+
+        self.prev_tag = tag;
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| invalid_data("too many records in sealed stream"))?;
+        self.done = last;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for UnsealReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.failed {
+            return Err(invalid_data("sealed stream is corrupt"));
+        }
+        while self.pos == self.state.plain.len() {
+            if self.done {
+                return Ok(0);
+            }
+            if let Err(e) = self.read_record() {
+                self.failed = true;
+                return Err(e);
+            }
+        }
+        let plain = &self.state.plain;
+        let n = buf.len().min(plain.len() - self.pos);
+        buf[..n].copy_from_slice(&plain[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
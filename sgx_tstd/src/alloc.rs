@@ -78,6 +78,33 @@ pub use alloc_crate::alloc::*;
 
 pub use sgx_alloc::System;
 
+#[cfg(feature = "large_page_alloc")]
+pub use crate::sys::large_page::LargePageConfig;
+
+/// Sets the tunables of the large-page allocator.
+///
+/// With the `large_page_alloc` feature, the default allocator serves
+/// allocations of at least [`LargePageConfig::threshold`] bytes from their
+/// own 2 MiB aligned EMM regions, committed on demand
+/// [`LargePageConfig::fault_ahead`] bytes at a time. This needs EDMM support;
+/// without it, or once the allocator runs out of regions, large allocations
+/// fall back to the regular heap.
+///
+/// The new configuration applies to allocations made after the call.
+/// Returns an error of kind [`InvalidInput`](crate::io::ErrorKind::InvalidInput)
+/// if `arena_size` is not a non-zero multiple of 2 MiB, `fault_ahead` is not
+/// a non-zero multiple of the page size, or `threshold` is zero.
+#[cfg(feature = "large_page_alloc")]
+pub fn set_large_page_config(config: LargePageConfig) -> crate::io::Result<()> {
+    crate::sys::large_page::set_config(config)
+}
+
+/// Returns the current tunables of the large-page allocator.
+#[cfg(feature = "large_page_alloc")]
+pub fn large_page_config() -> LargePageConfig {
+    crate::sys::large_page::config()
+}
+
 static HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
 
 /// Registers a custom allocation error hook, replacing any that was previously registered.
@@ -147,7 +174,9 @@ pub fn rust_oom(layout: Layout) -> ! {
 #[doc(hidden)]
 #[allow(unused_attributes)]
 pub mod __default_lib_allocator {
-    use super::{GlobalAlloc, Layout, System};
+    use super::Layout;
+    #[cfg(not(feature = "large_page_alloc"))]
+    use super::{GlobalAlloc, System};
     // These magic symbol names are used as a fallback for implementing the
     // `__rust_alloc` etc symbols (see `src/liballoc/alloc.rs`) when there is
     // no `#[global_allocator]` attribute.
@@ -163,6 +192,9 @@ pub mod __default_lib_allocator {
         // SAFETY: see the guarantees expected by `Layout::from_size_align` and
         // `GlobalAlloc::alloc`.
         let layout = Layout::from_size_align_unchecked(size, align);
+        #[cfg(feature = "large_page_alloc")]
+        return crate::sys::large_page::alloc(layout);
+        #[cfg(not(feature = "large_page_alloc"))]
         System.alloc(layout)
     }
 
@@ -170,7 +202,11 @@ pub mod __default_lib_allocator {
     pub unsafe extern "C" fn __rdl_dealloc(ptr: *mut u8, size: usize, align: usize) {
         // SAFETY: see the guarantees expected by `Layout::from_size_align` and
         // `GlobalAlloc::dealloc`.
-        System.dealloc(ptr, Layout::from_size_align_unchecked(size, align))
+        let layout = Layout::from_size_align_unchecked(size, align);
+        #[cfg(feature = "large_page_alloc")]
+        return crate::sys::large_page::dealloc(ptr, layout);
+        #[cfg(not(feature = "large_page_alloc"))]
+        System.dealloc(ptr, layout)
     }
 
     #[rustc_std_internal_symbol]
@@ -183,6 +219,9 @@ pub mod __default_lib_allocator {
         // SAFETY: see the guarantees expected by `Layout::from_size_align` and
         // `GlobalAlloc::realloc`.
         let old_layout = Layout::from_size_align_unchecked(old_size, align);
+        #[cfg(feature = "large_page_alloc")]
+        return crate::sys::large_page::realloc(ptr, old_layout, new_size);
+        #[cfg(not(feature = "large_page_alloc"))]
         System.realloc(ptr, old_layout, new_size)
     }
 
@@ -191,6 +230,9 @@ pub mod __default_lib_allocator {
         // SAFETY: see the guarantees expected by `Layout::from_size_align` and
         // `GlobalAlloc::alloc_zeroed`.
         let layout = Layout::from_size_align_unchecked(size, align);
+        #[cfg(feature = "large_page_alloc")]
+        return crate::sys::large_page::alloc_zeroed(layout);
+        #[cfg(not(feature = "large_page_alloc"))]
         System.alloc_zeroed(layout)
     }
 }
\ No newline at end of file
@@ -29,6 +29,7 @@ use crate::fmt::{self, Debug};
 use crate::hash::{BuildHasher, Hash, Hasher, SipHasher13};
 use crate::iter::FusedIterator;
 use crate::ops::Index;
+use crate::sync::atomic::{AtomicUsize, Ordering};
 use crate::sys;
 
 /// A [hash map] implemented with quadratic probing and SIMD lookup.
@@ -2981,18 +2982,42 @@ impl RandomState {
         // iteration order allows a form of DOS attack. To counter that we
         // increment one of the seeds on every RandomState creation, giving
         // every corresponding HashMap a different iteration order.
-        thread_local!(static KEYS: Cell<(u64, u64)> = {
-            Cell::new(sys::hashmap_random_keys())
+        //
+        // The keys come from the trusted RNG (RDRAND) and never from the host.
+        // They are re-drawn when `reseed_random_keys` bumps the epoch, e.g.
+        // after the enclave state has been duplicated.
+        thread_local!(static KEYS: Cell<(u64, u64, usize)> = {
+            let epoch = RESEED_EPOCH.load(Ordering::Acquire);
+            let (k0, k1) = sys::hashmap_random_keys();
+            Cell::new((k0, k1, epoch))
         });
 
         KEYS.with(|keys| {
-            let (k0, k1) = keys.get();
-            keys.set((k0.wrapping_add(1), k1));
+            let (mut k0, mut k1, mut epoch) = keys.get();
+            let current = RESEED_EPOCH.load(Ordering::Acquire);
+            if epoch != current {
+                (k0, k1) = sys::hashmap_random_keys();
+                epoch = current;
+            }
+            keys.set((k0.wrapping_add(1), k1, epoch));
             RandomState { k0, k1 }
         })
     }
 }
 
+static RESEED_EPOCH: AtomicUsize = AtomicUsize::new(0);
+
+/// Forces every thread to draw fresh keys from the trusted RNG for the
+/// [`RandomState`]s it creates from now on.
+///
+/// The per-thread keys are cached, so two enclaves created from the same
+/// snapshot of memory (for example when an enclave's state is cloned or
+/// restored) would otherwise keep producing predictable hashers. Already
+/// created `RandomState`s and the maps using them keep their keys.
+pub fn reseed_random_keys() {
+    RESEED_EPOCH.fetch_add(1, Ordering::AcqRel);
+}
+
 impl BuildHasher for RandomState {
     type Hasher = DefaultHasher;
     #[inline]
@@ -0,0 +1,144 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Panic containment at ecall boundaries.
+//!
+//! A panic must never unwind out of an ecall into the untrusted runtime.
+//! [`run`] and [`try_run`] wrap the body of an ecall, catch any panic that
+//! escapes it, keep the payload for later retrieval and turn it into a
+//! defined [`sgx_status_t`]. What happens to the following ecalls is decided
+//! by the [`PanicPolicy`].
+//!
+//! ```ignore
+//! #[no_mangle]
+//! pub extern "C" fn ecall_do_work(input: *const u8, len: usize) -> sgx_status_t {
+//!     std::ecall::run(|| {
+//!         let input = unsafe { std::slice::from_raw_parts(input, len) };
+//!         process(input)
+//!     })
+//! }
+//! ```
+
+use crate::any::Any;
+use crate::boxed::Box;
+use crate::panic::{self, UnwindSafe};
+use crate::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use crate::sync::{PoisonError, SgxMutex};
+use sgx_types::{sgx_status_t, SgxResult};
+
+/// What to do with the enclave after a panic was caught at an ecall root.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Report the panic to the caller and keep serving ecalls.
+    Continue,
+    /// Report the panic and refuse all further wrapped ecalls with
+    /// `SGX_ERROR_ENCLAVE_CRASHED`, since enclave state may be inconsistent.
+    Poison,
+    /// Abort the enclave.
+    Abort,
+}
+
+impl PanicPolicy {
+    fn from_u8(v: u8) -> PanicPolicy {
+        match v {
+            0 => PanicPolicy::Continue,
+            2 => PanicPolicy::Abort,
+            _ => PanicPolicy::Poison,
+        }
+    }
+}
+
+impl Default for PanicPolicy {
+    fn default() -> PanicPolicy {
+        PanicPolicy::Poison
+    }
+}
+
+static POLICY: AtomicU8 = AtomicU8::new(PanicPolicy::Poison as u8);
+static POISONED: AtomicBool = AtomicBool::new(false);
+static PAYLOAD: SgxMutex<Option<Box<dyn Any + Send>>> = SgxMutex::new(None);
+
+/// Sets the policy applied when a panic is caught at an ecall root.
+///
+/// The default policy is [`PanicPolicy::Poison`].
+pub fn set_panic_policy(policy: PanicPolicy) {
+    POLICY.store(policy as u8, Ordering::SeqCst);
+}
+
+/// Returns the policy applied when a panic is caught at an ecall root.
+pub fn panic_policy() -> PanicPolicy {
+    PanicPolicy::from_u8(POLICY.load(Ordering::SeqCst))
+}
+
+/// Returns `true` if a caught panic has poisoned the enclave.
+pub fn is_poisoned() -> bool {
+    POISONED.load(Ordering::SeqCst)
+}
+
+/// Takes the payload of the most recent panic caught at an ecall root.
+pub fn take_panic_payload() -> Option<Box<dyn Any + Send>> {
+    PAYLOAD
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+}
+
+/// Returns the message of the most recent caught panic, if its payload was a
+/// string, without taking it.
+pub fn panic_message() -> Option<crate::string::String> {
+    let payload = PAYLOAD.lock().unwrap_or_else(PoisonError::into_inner);
+    payload.as_ref().and_then(|p| {
+        p.downcast_ref::<&'static str>()
+            .map(|s| (*s).into())
+            .or_else(|| p.downcast_ref::<crate::string::String>().cloned())
+    })
+}
+
+/// Runs the body of an ecall, catching any panic escaping from it.
+///
+/// Returns the status produced by `f`, or `SGX_ERROR_UNEXPECTED` if it
+/// panicked. Once the enclave is poisoned `f` is not run at all and
+/// `SGX_ERROR_ENCLAVE_CRASHED` is returned.
+pub fn run<F>(f: F) -> sgx_status_t
+where
+    F: FnOnce() -> sgx_status_t + UnwindSafe,
+{
+    match try_run(f) {
+        Ok(status) => status,
+        Err(status) => status,
+    }
+}
+
+/// Like [`run`], for ecall bodies that produce a value.
+pub fn try_run<F, R>(f: F) -> SgxResult<R>
+where
+    F: FnOnce() -> R + UnwindSafe,
+{
+    if is_poisoned() {
+        return Err(sgx_status_t::SGX_ERROR_ENCLAVE_CRASHED);
+    }
+
+    panic::catch_unwind(f).map_err(|payload| {
+        *PAYLOAD.lock().unwrap_or_else(PoisonError::into_inner) = Some(payload);
+        match panic_policy() {
+            PanicPolicy::Continue => {}
+            PanicPolicy::Poison => POISONED.store(true, Ordering::SeqCst),
+            PanicPolicy::Abort => crate::sys::abort_internal(),
+        }
+        sgx_status_t::SGX_ERROR_UNEXPECTED
+    })
+}
@@ -19,7 +19,7 @@ use core::sync::atomic::{AtomicU64, Ordering};
 use crate::io;
 use crate::path::{Path, PathBuf};
 use crate::sync::SgxThreadSpinlock;
-use crate::untrusted::fs;
+use crate::fs;
 use sgx_trts::enclave;
 use sgx_types::*;
 
@@ -25,12 +25,21 @@
 //! counterpart ending in `os`. Those ending in `os` will return an [`OsString`]
 //! and those without will return a [`String`].
 
+//!
+//! With the `no_env` feature the host-backed parts of this module (the
+//! environment variables, the current directory and the well-known
+//! directories) are compiled out, so code that touches them fails to build
+//! instead of failing at runtime. Path list helpers keep working.
+
 #![allow(clippy::needless_doctest_main)]
 use crate::error::Error;
 use crate::ffi::{OsStr, OsString};
 use crate::fmt;
+#[cfg(not(feature = "no_env"))]
 use crate::io;
-use crate::path::{Path, PathBuf};
+#[cfg(not(feature = "no_env"))]
+use crate::path::Path;
+use crate::path::PathBuf;
 use crate::sys::os as os_imp;
 
 /// Returns the current working directory as a [`PathBuf`].
@@ -61,6 +70,7 @@ use crate::sys::os as os_imp;
 ///     Ok(())
 /// }
 /// ```
+#[cfg(not(feature = "no_env"))]
 pub fn current_dir() -> io::Result<PathBuf> {
     os_imp::getcwd()
 }
@@ -86,6 +96,7 @@ pub fn current_dir() -> io::Result<PathBuf> {
 /// assert!(env::set_current_dir(&root).is_ok());
 /// println!("Successfully changed working directory to {}!", root.display());
 /// ```
+#[cfg(not(feature = "no_env"))]
 pub fn set_current_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
     os_imp::chdir(path.as_ref())
 }
@@ -95,6 +106,7 @@ pub fn set_current_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
 /// This structure is created by [`env::vars()`]. See its documentation for more.
 ///
 /// [`env::vars()`]: vars
+#[cfg(not(feature = "no_env"))]
 pub struct Vars {
     inner: VarsOs,
 }
@@ -104,6 +116,7 @@ pub struct Vars {
 /// This structure is created by [`env::vars_os()`]. See its documentation for more.
 ///
 /// [`env::vars_os()`]: vars_os
+#[cfg(not(feature = "no_env"))]
 pub struct VarsOs {
     inner: os_imp::Env,
 }
@@ -134,6 +147,7 @@ pub struct VarsOs {
 /// ```
 ///
 /// [`env::vars_os()`]: vars_os
+#[cfg(not(feature = "no_env"))]
 #[must_use]
 pub fn vars() -> Vars {
     Vars { inner: vars_os() }
@@ -161,11 +175,13 @@ pub fn vars() -> Vars {
 ///     println!("{key:?}: {value:?}");
 /// }
 /// ```
+#[cfg(not(feature = "no_env"))]
 #[must_use]
 pub fn vars_os() -> VarsOs {
     VarsOs { inner: os_imp::env() }
 }
 
+#[cfg(not(feature = "no_env"))]
 impl Iterator for Vars {
     type Item = (String, String);
     fn next(&mut self) -> Option<(String, String)> {
@@ -176,12 +192,14 @@ impl Iterator for Vars {
     }
 }
 
+#[cfg(not(feature = "no_env"))]
 impl fmt::Debug for Vars {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Vars").finish_non_exhaustive()
     }
 }
 
+#[cfg(not(feature = "no_env"))]
 impl Iterator for VarsOs {
     type Item = (OsString, OsString);
     fn next(&mut self) -> Option<(OsString, OsString)> {
@@ -192,6 +210,7 @@ impl Iterator for VarsOs {
     }
 }
 
+#[cfg(not(feature = "no_env"))]
 impl fmt::Debug for VarsOs {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("VarOs").finish_non_exhaustive()
@@ -221,10 +240,12 @@ impl fmt::Debug for VarsOs {
 ///     Err(e) => println!("couldn't interpret {key}: {e}"),
 /// }
 /// ```
+#[cfg(not(feature = "no_env"))]
 pub fn var<K: AsRef<OsStr>>(key: K) -> Result<String, VarError> {
     _var(key.as_ref())
 }
 
+#[cfg(not(feature = "no_env"))]
 fn _var(key: &OsStr) -> Result<String, VarError> {
     match var_os(key) {
         Some(s) => s.into_string().map_err(VarError::NotUnicode),
@@ -260,16 +281,52 @@ fn _var(key: &OsStr) -> Result<String, VarError> {
 ///     None => println!("{key} is not defined in the environment.")
 /// }
 /// ```
+#[cfg(not(feature = "no_env"))]
 #[must_use]
 pub fn var_os<K: AsRef<OsStr>>(key: K) -> Option<OsString> {
     _var_os(key.as_ref())
 }
 
+#[cfg(not(feature = "no_env"))]
 fn _var_os(key: &OsStr) -> Option<OsString> {
     os_imp::getenv(key)
         .unwrap_or_else(|e| panic!("failed to get environment variable `{key:?}`: {e}"))
 }
 
+/// Restricts the environment visible to the enclave to the given keys.
+///
+/// By default the environment is read from the host through ocalls, so every
+/// value returned by [`var`] or [`vars`] is controlled by the untrusted side.
+/// Once an allowlist is declared the host environment is no longer consulted:
+/// lookups are served from the snapshot the uRTS passes in with
+/// `SgxEnclave::pass_env`, which is validated for length and UTF-8 before use,
+/// and any key not on the allowlist reports [`VarError::NotPresent`].
+/// [`set_var`] and [`remove_var`] then only modify the enclave's own copy.
+///
+/// The allowlist can only be set once.
+///
+/// # Errors
+///
+/// Returns an error if the allowlist was already set, or if a key is empty
+/// or contains an ASCII equals sign `'='` or the NUL character `'\0'`.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+///
+/// env::set_allowlist(["RUST_LOG", "TZ"]).unwrap();
+/// assert_eq!(env::var("HOME"), Err(env::VarError::NotPresent));
+/// ```
+#[cfg(not(feature = "no_env"))]
+pub fn set_allowlist<I, K>(keys: I) -> io::Result<()>
+where
+    I: IntoIterator<Item = K>,
+    K: AsRef<OsStr>,
+{
+    os_imp::set_env_allowlist(keys.into_iter().map(|k| k.as_ref().to_owned()).collect())
+}
+
 /// The error type for operations interacting with environment variables.
 /// Possibly returned from [`env::var()`].
 ///
@@ -335,10 +392,12 @@ impl Error for VarError {
 /// env::set_var(key, "VALUE");
 /// assert_eq!(env::var(key), Ok("VALUE".to_string()));
 /// ```
+#[cfg(not(feature = "no_env"))]
 pub fn set_var<K: AsRef<OsStr>, V: AsRef<OsStr>>(key: K, value: V) {
     _set_var(key.as_ref(), value.as_ref())
 }
 
+#[cfg(not(feature = "no_env"))]
 fn _set_var(key: &OsStr, value: &OsStr) {
     os_imp::setenv(key, value).unwrap_or_else(|e| {
         panic!("failed to set environment variable `{key:?}` to `{value:?}`: {e}")
@@ -376,10 +435,12 @@ fn _set_var(key: &OsStr, value: &OsStr) {
 /// env::remove_var(key);
 /// assert!(env::var(key).is_err());
 /// ```
+#[cfg(not(feature = "no_env"))]
 pub fn remove_var<K: AsRef<OsStr>>(key: K) {
     _remove_var(key.as_ref())
 }
 
+#[cfg(not(feature = "no_env"))]
 fn _remove_var(key: &OsStr) {
     os_imp::unsetenv(key)
         .unwrap_or_else(|e| panic!("failed to remove environment variable `{key:?}`: {e}"))
@@ -562,6 +623,7 @@ impl Error for JoinPathsError {
 ///     None => println!("Impossible to get your home dir!"),
 /// }
 /// ```
+#[cfg(not(feature = "no_env"))]
 #[must_use]
 pub fn home_dir() -> Option<PathBuf> {
     os_imp::home_dir()
@@ -598,6 +660,7 @@ pub fn home_dir() -> Option<PathBuf> {
 ///     println!("Temporary directory: {}", dir.display());
 /// }
 /// ```
+#[cfg(not(feature = "no_env"))]
 #[must_use]
 pub fn temp_dir() -> PathBuf {
     os_imp::temp_dir()
@@ -656,6 +719,7 @@ pub fn temp_dir() -> PathBuf {
 ///     Err(e) => println!("failed to get current exe path: {e}"),
 /// };
 /// ```
+#[cfg(not(feature = "no_env"))]
 pub fn current_exe() -> io::Result<PathBuf> {
     os_imp::current_exe()
 }
@@ -28,6 +28,9 @@ use crate::time::SystemTime;
 #[cfg(not(feature = "untrusted_fs"))]
 use crate::untrusted::path::PathEx;
 
+#[cfg(all(feature = "untrusted_fs", not(feature = "no_fs")))]
+pub use crate::sgxfs::SecureTempDir;
+
 /// An object providing access to an open file on the filesystem.
 ///
 /// An instance of a `File` can be read and/or written depending on what options
@@ -429,6 +432,29 @@ impl File {
         OpenOptions::new()
     }
 
+    /// Creates an unnamed file in read-write mode in the directory `dir`.
+    ///
+    /// The file is created with `O_TMPFILE`: it never appears in the
+    /// directory listing, so other processes sharing the directory cannot
+    /// open or clobber it, and it is removed once it is closed. The host
+    /// filesystem has to support `O_TMPFILE`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::io::prelude::*;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let mut f = File::create_anonymous_in("/tmp")?;
+    ///     f.write_all(b"scratch data")?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn create_anonymous_in<P: AsRef<Path>>(dir: P) -> io::Result<File> {
+        fs_imp::File::create_anonymous_in(dir.as_ref()).map(|inner| File { inner })
+    }
+
     /// Attempts to sync all OS-internal metadata to disk.
     ///
     /// This function will attempt to ensure that all in-memory data reaches the
@@ -486,6 +512,48 @@ impl File {
         self.inner.datasync()
     }
 
+    /// Acquires an exclusive advisory lock on the file, blocking until it is
+    /// available.
+    ///
+    /// The lock is taken with `flock` on the host and is held until it is
+    /// released with [`unlock`](File::unlock) or the file is closed. It only
+    /// coordinates with others that also take locks on the file, such as
+    /// other enclave instances sharing a host directory; it does not stop
+    /// anyone from writing to the file.
+    pub fn lock(&self) -> io::Result<()> {
+        self.inner.lock()
+    }
+
+    /// Acquires a shared advisory lock on the file, blocking until it is
+    /// available.
+    ///
+    /// Several shared locks can be held at the same time, but not together
+    /// with an exclusive lock. See [`lock`](File::lock).
+    pub fn lock_shared(&self) -> io::Result<()> {
+        self.inner.lock_shared()
+    }
+
+    /// Tries to acquire an exclusive advisory lock on the file.
+    ///
+    /// Returns `Ok(false)` if the file is already locked by someone else.
+    /// See [`lock`](File::lock).
+    pub fn try_lock(&self) -> io::Result<bool> {
+        self.inner.try_lock()
+    }
+
+    /// Tries to acquire a shared advisory lock on the file.
+    ///
+    /// Returns `Ok(false)` if the file is exclusively locked by someone else.
+    /// See [`lock_shared`](File::lock_shared).
+    pub fn try_lock_shared(&self) -> io::Result<bool> {
+        self.inner.try_lock_shared()
+    }
+
+    /// Releases the advisory lock held on the file.
+    pub fn unlock(&self) -> io::Result<()> {
+        self.inner.unlock()
+    }
+
     /// Truncates or extends the underlying file, updating the size of
     /// this file to become `size`.
     ///
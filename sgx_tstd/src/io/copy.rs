@@ -63,6 +63,11 @@ use crate::mem::MaybeUninit;
 /// `sendfile(2)` or `splice(2)` syscalls to move data directly between file
 /// descriptors if possible.
 ///
+/// If none of them applies, for example between a socket and a regular
+/// file, data between two file descriptors is moved through a buffer outside
+/// the enclave that is allocated once for the whole copy, with a single
+/// ocall per chunk. Its size is set with [`set_copy_buffer_size`].
+///
 /// Note that platform-specific behavior [may change in the future][changes].
 ///
 /// [changes]: crate::io#platform-specific-behavior
@@ -74,6 +79,23 @@ where
     crate::sys::kernel_copy::copy_spec(reader, writer)
 }
 
+/// Sets the size of the untrusted buffer [`copy`] moves data through between
+/// two file descriptors.
+///
+/// Larger buffers mean fewer ocalls per copy. The default is 64 KiB; a size
+/// of zero disables the buffer, and such copies go through the enclave.
+pub fn set_copy_buffer_size(size: usize) {
+    crate::sys::kernel_copy::BOUNCE_BUFFER_SIZE.store(size, crate::sync::atomic::Ordering::Relaxed);
+}
+
+/// Returns the size of the untrusted buffer [`copy`] moves data through
+/// between two file descriptors.
+///
+/// See [`set_copy_buffer_size`].
+pub fn copy_buffer_size() -> usize {
+    crate::sys::kernel_copy::BOUNCE_BUFFER_SIZE.load(crate::sync::atomic::Ordering::Relaxed)
+}
+
 /// The userspace read-write-loop implementation of `io::copy` that is used when
 /// OS-specific specializations for copy offloading are not available or not applicable.
 pub(crate) fn generic_copy<R: ?Sized, W: ?Sized>(reader: &mut R, writer: &mut W) -> Result<u64>
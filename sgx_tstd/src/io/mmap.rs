@@ -0,0 +1,334 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Read-only memory-mapped files for enclaves.
+//!
+//! The host cannot map a file into enclave memory, and handing out raw host
+//! pointers would let the host change the data under the enclave's feet.
+//! [`Mmap`] instead reserves a range of enclave memory with the EMM. Each page
+//! is read from the file through ocalls, checked against an optional
+//! per-chunk SHA-256 hash, and committed read-only into the enclave.
+//!
+//! Ocalls are not allowed while an exception is handled, so pages are never
+//! read from the page fault handler. They are all loaded when the file is
+//! mapped, or, with [`MmapOptions::populate`] turned off, by
+//! [`Mmap::ensure_loaded`] before they are accessed. The handler only lets an
+//! access retry once another thread has committed its page; any other access
+//! to a page that is not loaded is treated like any other invalid memory
+//! access.
+//!
+//! This requires EDMM support from the platform.
+
+use crate::boxed::Box;
+use crate::fmt;
+use crate::io;
+use crate::ops::{Deref, Range};
+use crate::os::unix::fs::FileExt;
+use crate::ptr::NonNull;
+use crate::slice;
+use crate::sync::atomic::{AtomicBool, Ordering};
+use crate::sync::{PoisonError, SgxMutex};
+use crate::fs::File;
+use crate::vec::Vec;
+
+use sgx_trts::emm::{AllocAddr, AllocFlags, AllocOptions, EmmAlloc, HandleResult, Perm};
+use sgx_trts::enclave::rsgx_is_supported_EDMM;
+use sgx_types::metadata::SE_PAGE_SIZE;
+use sgx_types::{sgx_pfinfo, sgx_sha256_hash_t, sgx_sha256_msg, sgx_status_t};
+
+/// The size of the chunks covered by one integrity hash: one enclave page.
+pub const MMAP_CHUNK_SIZE: usize = SE_PAGE_SIZE;
+
+// Bit 1 of the page fault error code is set for write accesses.
+const PFEC_WRITE: u32 = 1 << 1;
+
+struct MmapInner {
+    file: File,
+    base: usize,
+    len: usize,
+    offset: u64,
+    hashes: Option<Vec<sgx_sha256_hash_t>>,
+    // Set once a page is committed; read by the page fault handler, which
+    // must not block.
+    loaded: Vec<AtomicBool>,
+    // Serializes the loading of pages.
+    load_lock: SgxMutex<()>,
+}
+
+impl MmapInner {
+    fn ensure_loaded(&self, range: Range<usize>) -> io::Result<()> {
+        if range.start > range.end || range.end > self.len {
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidInput,
+                "range is outside the memory map",
+            ));
+        }
+        if range.is_empty() {
+            return Ok(());
+        }
+
+        let _guard = self.load_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        for index in range.start / MMAP_CHUNK_SIZE..=(range.end - 1) / MMAP_CHUNK_SIZE {
+            if !self.loaded[index].load(Ordering::Acquire) {
+                self.load_page(index)?;
+                self.loaded[index].store(true, Ordering::Release);
+            }
+        }
+        Ok(())
+    }
+
+    fn load_page(&self, index: usize) -> io::Result<()> {
+        let mut page = vec![0_u8; MMAP_CHUNK_SIZE];
+        let chunk_len = MMAP_CHUNK_SIZE.min(self.len - index * MMAP_CHUNK_SIZE);
+        let mut read = 0;
+        while read < chunk_len {
+            let offset = self.offset + (index * MMAP_CHUNK_SIZE + read) as u64;
+            match self.file.read_at(&mut page[read..chunk_len], offset) {
+                Ok(0) => break,
+                Ok(n) => read += n.min(chunk_len - read),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        if let Some(ref hashes) = self.hashes {
+            let mut hash = sgx_sha256_hash_t::default();
+            let ret = unsafe { sgx_sha256_msg(page.as_ptr(), read as u32, &mut hash) };
+            if ret != sgx_status_t::SGX_SUCCESS || hash != hashes[index] {
+                return Err(io::const_io_error!(
+                    io::ErrorKind::InvalidData,
+                    "memory-mapped chunk failed its integrity check",
+                ));
+            }
+        }
+
+        let addr = (self.base + index * MMAP_CHUNK_SIZE) as *mut u8;
+        unsafe {
+            EmmAlloc::commit_with_data(NonNull::new_unchecked(addr), &page, Perm::READ)
+                .map_err(io::Error::from_raw_os_error)
+        }
+    }
+}
+
+extern "C" fn mmap_fault_handler(pfinfo: &sgx_pfinfo, private: usize) -> HandleResult {
+    let inner = unsafe { &*(private as *const MmapInner) };
+    let addr = pfinfo.maddr as usize;
+    if addr < inner.base || addr >= inner.base + inner.len {
+        return HandleResult::Search;
+    }
+
+    // No ocalls and no locks here: the page is either committed already, by
+    // another thread that loaded it, and the access is retried, or it is not
+    // loaded and the access is invalid. Writes to the read-only mapping are
+    // always invalid.
+    let index = (addr - inner.base) / MMAP_CHUNK_SIZE;
+    if pfinfo.pfec & PFEC_WRITE == 0 && inner.loaded[index].load(Ordering::Acquire) {
+        HandleResult::Execution
+    } else {
+        HandleResult::Search
+    }
+}
+
+/// Options to configure how a file is mapped.
+#[derive(Clone, Debug, Default)]
+pub struct MmapOptions {
+    offset: u64,
+    len: Option<usize>,
+    hashes: Option<Vec<sgx_sha256_hash_t>>,
+    lazy: bool,
+}
+
+impl MmapOptions {
+    /// Creates a new set of options: map and load the whole file, without
+    /// integrity hashes.
+    pub fn new() -> MmapOptions {
+        MmapOptions::default()
+    }
+
+    /// Sets whether the whole mapping is loaded when the file is mapped,
+    /// which is the default.
+    ///
+    /// Without it, no page is loaded until [`Mmap::ensure_loaded`] loads it,
+    /// and every access must be to a range loaded that way.
+    pub fn populate(&mut self, populate: bool) -> &mut MmapOptions {
+        self.lazy = !populate;
+        self
+    }
+
+    /// Sets the file offset the mapping starts at.
+    pub fn offset(&mut self, offset: u64) -> &mut MmapOptions {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets the length of the mapping. Defaults to the rest of the file.
+    pub fn len(&mut self, len: usize) -> &mut MmapOptions {
+        self.len = Some(len);
+        self
+    }
+
+    /// Sets the expected SHA-256 hash of every [`MMAP_CHUNK_SIZE`] chunk of
+    /// the mapping, the last chunk being hashed over its actual length.
+    pub fn chunk_hashes(&mut self, hashes: Vec<sgx_sha256_hash_t>) -> &mut MmapOptions {
+        self.hashes = Some(hashes);
+        self
+    }
+
+    /// Maps `file` into enclave memory.
+    ///
+    /// # Safety
+    ///
+    /// Changes made by the host to the file before a page is loaded are
+    /// visible to the enclave. Use [`chunk_hashes`](MmapOptions::chunk_hashes)
+    /// to detect them. Without [`populate`](MmapOptions::populate), only the
+    /// ranges loaded with [`Mmap::ensure_loaded`] may be accessed.
+    pub unsafe fn map(&self, file: &File) -> io::Result<Mmap> {
+        if !rsgx_is_supported_EDMM() {
+            return Err(io::const_io_error!(
+                io::ErrorKind::Unsupported,
+                "memory-mapped files require EDMM support",
+            ));
+        }
+
+        let len = match self.len {
+            Some(len) => len,
+            None => {
+                let file_len = file.metadata()?.len();
+                let len = file_len.checked_sub(self.offset).ok_or_else(|| {
+                    io::const_io_error!(io::ErrorKind::InvalidInput, "offset is past end of file")
+                })?;
+                usize::try_from(len).map_err(|_| {
+                    io::const_io_error!(io::ErrorKind::InvalidInput, "file is too large to map")
+                })?
+            }
+        };
+        if len == 0 {
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidInput,
+                "memory map must have a non-zero length",
+            ));
+        }
+
+        let chunks = (len + MMAP_CHUNK_SIZE - 1) / MMAP_CHUNK_SIZE;
+        if let Some(ref hashes) = self.hashes {
+            if hashes.len() != chunks {
+                return Err(io::const_io_error!(
+                    io::ErrorKind::InvalidInput,
+                    "number of chunk hashes does not match the mapping length",
+                ));
+            }
+        }
+
+        let map_len = chunks * MMAP_CHUNK_SIZE;
+        let mut inner = Box::new(MmapInner {
+            file: file.try_clone()?,
+            base: 0,
+            len,
+            offset: self.offset,
+            hashes: self.hashes.clone(),
+            loaded: (0..chunks).map(|_| AtomicBool::new(false)).collect(),
+            load_lock: SgxMutex::new(()),
+        });
+
+        let options = AllocOptions::new()
+            .set_flags(AllocFlags::COMMIT_ON_DEMAND)
+            .set_handler(mmap_fault_handler, &*inner as *const MmapInner as usize);
+        let ptr = EmmAlloc
+            .alloc(AllocAddr::Any, map_len, options)
+            .map_err(io::Error::from_raw_os_error)?;
+        inner.base = ptr.as_ptr() as usize;
+
+        let map = Mmap {
+            ptr,
+            len,
+            map_len,
+            inner,
+        };
+        if !self.lazy {
+            map.ensure_loaded(0..len)?;
+        }
+        Ok(map)
+    }
+}
+
+/// A read-only memory-mapped view of a file.
+pub struct Mmap {
+    ptr: NonNull<u8>,
+    len: usize,
+    map_len: usize,
+    // Referenced by the page fault handler until the range is released.
+    inner: Box<MmapInner>,
+}
+
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    /// Maps the whole of `file` into enclave memory. See [`MmapOptions::map`].
+    ///
+    /// # Safety
+    ///
+    /// See [`MmapOptions::map`].
+    pub unsafe fn map(file: &File) -> io::Result<Mmap> {
+        MmapOptions::new().map(file)
+    }
+
+    /// Returns the file offset the mapping starts at.
+    pub fn offset(&self) -> u64 {
+        self.inner.offset
+    }
+
+    /// Loads the pages holding `range` of the mapping, reading them from the
+    /// file and checking their hashes, unless they are loaded already.
+    ///
+    /// A page that cannot be read or fails its integrity check is not
+    /// loaded, and the error is returned.
+    pub fn ensure_loaded(&self, range: Range<usize>) -> io::Result<()> {
+        self.inner.ensure_loaded(range)
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl AsRef<[u8]> for Mmap {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        let _ = unsafe { EmmAlloc.dealloc(self.ptr, self.map_len) };
+    }
+}
+
+impl fmt::Debug for Mmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mmap")
+            .field("ptr", &self.ptr)
+            .field("len", &self.len)
+            .finish()
+    }
+}
@@ -279,18 +279,25 @@ pub(crate) use self::stdio::attempt_print_to_stderr;
 #[cfg(feature = "stdio")]
 pub use self::stdio::set_output_capture;
 #[cfg(feature = "stdio")]
+pub use self::stdio::{set_stderr, set_stdout, StdioSink};
+#[cfg(feature = "stdio")]
 pub use self::stdio::{_eprint, _print};
 #[cfg(feature = "stdio")]
 pub use self::stdio::{stderr, stdin, stdout, Stderr, StderrLock, Stdin, StdinLock, Stdout, StdoutLock};
 pub use self::{
     buffered::{BufReader, BufWriter, IntoInnerError, LineWriter},
-    copy::copy,
+    copy::{copy, copy_buffer_size, set_copy_buffer_size},
     cursor::Cursor,
     error::{Error, ErrorKind, Result},
     util::{empty, repeat, sink, Empty, Repeat, Sink},
 };
 
+#[cfg(not(feature = "no_fs"))]
+pub use self::mmap::{Mmap, MmapOptions, MMAP_CHUNK_SIZE};
+#[cfg(feature = "pipe")]
+pub use self::pipe::{pipe, register_fd, unregister_fd, PipeReader, PipeWriter};
 pub use self::readbuf::{BorrowedBuf, BorrowedCursor};
+pub use self::shm::{ShmReader, ShmWriter};
 pub(crate) use error::const_io_error;
 
 mod buffered;
@@ -298,8 +305,13 @@ pub(crate) mod copy;
 mod cursor;
 mod error;
 mod impls;
+#[cfg(not(feature = "no_fs"))]
+mod mmap;
+#[cfg(feature = "pipe")]
+mod pipe;
 pub mod prelude;
 mod readbuf;
+mod shm;
 #[cfg(feature = "stdio")]
 mod stdio;
 mod util;
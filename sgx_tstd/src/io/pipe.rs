@@ -0,0 +1,282 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Anonymous pipes between the enclave and the host.
+//!
+//! [`pipe`] creates a pipe through an ocall. Both ends are host file
+//! descriptors, so everything written to a pipe is visible to the host; data
+//! that must stay confidential has to be protected before it is written. One
+//! end can be handed to a helper process started by the host to stream data
+//! in or out of the enclave without going through a TCP socket.
+//!
+//! A descriptor number that arrives from the host is only an integer, and the
+//! host could name any descriptor, including one the enclave already uses for
+//! something else. Such descriptors must first be registered with
+//! [`register_fd`] by the code that legitimately received them, usually an
+//! ecall, and can then be claimed exactly once with
+//! [`PipeReader::from_received_fd`] or [`PipeWriter::from_received_fd`].
+//! Claiming also checks that the descriptor is a pipe opened in the right
+//! direction. That check relies on the host's answer and only catches
+//! mistakes; the registration is what the enclave itself controls.
+
+use crate::fmt;
+use crate::io::{self, IoSlice, IoSliceMut, Read, Write};
+use crate::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use crate::sync::{PoisonError, SgxMutex};
+use crate::sys::pipe::{self as imp, AnonPipe};
+use crate::vec::Vec;
+
+static RECEIVED_FDS: SgxMutex<Vec<RawFd>> = SgxMutex::new(Vec::new());
+
+/// Creates an anonymous pipe.
+///
+/// Returns the read end and the write end of the pipe. Both ends are created
+/// with `O_CLOEXEC` set.
+pub fn pipe() -> io::Result<(PipeReader, PipeWriter)> {
+    imp::anon_pipe().map(|(reader, writer)| (PipeReader(reader), PipeWriter(writer)))
+}
+
+/// Registers a file descriptor that was received from the host.
+///
+/// Only registered descriptors can be claimed with
+/// [`PipeReader::from_received_fd`] or [`PipeWriter::from_received_fd`].
+/// Registering an invalid descriptor number, or one that is already
+/// registered, is an error.
+pub fn register_fd(fd: RawFd) -> io::Result<()> {
+    if fd < 0 {
+        return Err(io::const_io_error!(io::ErrorKind::InvalidInput, "invalid file descriptor"));
+    }
+
+    let mut fds = RECEIVED_FDS.lock().unwrap_or_else(PoisonError::into_inner);
+    if fds.contains(&fd) {
+        return Err(io::const_io_error!(
+            io::ErrorKind::AlreadyExists,
+            "file descriptor is already registered",
+        ));
+    }
+    fds.push(fd);
+    Ok(())
+}
+
+/// Withdraws the registration of a received file descriptor that has not been
+/// claimed yet.
+///
+/// Returns whether the descriptor was registered. The descriptor itself is
+/// left open.
+pub fn unregister_fd(fd: RawFd) -> bool {
+    let mut fds = RECEIVED_FDS.lock().unwrap_or_else(PoisonError::into_inner);
+    match fds.iter().position(|&r| r == fd) {
+        Some(pos) => {
+            fds.swap_remove(pos);
+            true
+        }
+        None => false,
+    }
+}
+
+fn claim_received_fd(fd: RawFd, write: bool) -> io::Result<OwnedFd> {
+    let mut fds = RECEIVED_FDS.lock().unwrap_or_else(PoisonError::into_inner);
+    let pos = fds.iter().position(|&r| r == fd).ok_or_else(|| {
+        io::const_io_error!(io::ErrorKind::PermissionDenied, "file descriptor was not registered")
+    })?;
+
+    // The registration is used up even if the check fails, so that a bad
+    // descriptor cannot be retried in the other direction.
+    fds.swap_remove(pos);
+    drop(fds);
+
+    imp::check_pipe_fd(unsafe { BorrowedFd::borrow_raw(fd) }, write)?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// The read end of a pipe.
+///
+/// Created by [`pipe`] or claimed from a registered host descriptor with
+/// [`PipeReader::from_received_fd`].
+pub struct PipeReader(AnonPipe);
+
+/// The write end of a pipe.
+///
+/// Created by [`pipe`] or claimed from a registered host descriptor with
+/// [`PipeWriter::from_received_fd`].
+pub struct PipeWriter(AnonPipe);
+
+impl PipeReader {
+    /// Takes ownership of a registered host descriptor as the read end of a
+    /// pipe.
+    ///
+    /// The registration is consumed. Fails with
+    /// [`ErrorKind::PermissionDenied`](io::ErrorKind::PermissionDenied) if
+    /// `fd` was not registered with [`register_fd`], and with
+    /// [`ErrorKind::InvalidInput`](io::ErrorKind::InvalidInput) if it is not
+    /// a readable pipe. In the latter case the descriptor is left open.
+    pub fn from_received_fd(fd: RawFd) -> io::Result<PipeReader> {
+        claim_received_fd(fd, false).map(PipeReader::from)
+    }
+
+    /// Creates a new `PipeReader` that shares the same underlying pipe.
+    pub fn try_clone(&self) -> io::Result<PipeReader> {
+        self.0.try_clone().map(PipeReader)
+    }
+}
+
+impl PipeWriter {
+    /// Takes ownership of a registered host descriptor as the write end of a
+    /// pipe.
+    ///
+    /// The registration is consumed. Fails with
+    /// [`ErrorKind::PermissionDenied`](io::ErrorKind::PermissionDenied) if
+    /// `fd` was not registered with [`register_fd`], and with
+    /// [`ErrorKind::InvalidInput`](io::ErrorKind::InvalidInput) if it is not
+    /// a writable pipe. In the latter case the descriptor is left open.
+    pub fn from_received_fd(fd: RawFd) -> io::Result<PipeWriter> {
+        claim_received_fd(fd, true).map(PipeWriter::from)
+    }
+
+    /// Creates a new `PipeWriter` that shares the same underlying pipe.
+    pub fn try_clone(&self) -> io::Result<PipeWriter> {
+        self.0.try_clone().map(PipeWriter)
+    }
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        self.0.read_vectored(bufs)
+    }
+
+    #[inline]
+    fn is_read_vectored(&self) -> bool {
+        self.0.is_read_vectored()
+    }
+}
+
+impl Read for &PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        self.0.read_vectored(bufs)
+    }
+
+    #[inline]
+    fn is_read_vectored(&self) -> bool {
+        self.0.is_read_vectored()
+    }
+}
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.0.write_vectored(bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.0.is_write_vectored()
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for &PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.0.write_vectored(bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.0.is_write_vectored()
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+macro_rules! impl_fd_traits {
+    ($t:ident) => {
+        impl AsFd for $t {
+            #[inline]
+            fn as_fd(&self) -> BorrowedFd<'_> {
+                self.0.as_fd()
+            }
+        }
+
+        impl AsRawFd for $t {
+            #[inline]
+            fn as_raw_fd(&self) -> RawFd {
+                self.0.as_raw_fd()
+            }
+        }
+
+        impl IntoRawFd for $t {
+            #[inline]
+            fn into_raw_fd(self) -> RawFd {
+                self.0.into_raw_fd()
+            }
+        }
+
+        /// Takes ownership of `fd` without any check. Descriptors received
+        /// from the host should go through [`register_fd`] and
+        /// `from_received_fd` instead.
+        impl FromRawFd for $t {
+            #[inline]
+            unsafe fn from_raw_fd(fd: RawFd) -> $t {
+                $t(AnonPipe::from_raw_fd(fd))
+            }
+        }
+
+        impl From<$t> for OwnedFd {
+            #[inline]
+            fn from(pipe: $t) -> OwnedFd {
+                unsafe { OwnedFd::from_raw_fd(pipe.into_raw_fd()) }
+            }
+        }
+
+        impl From<OwnedFd> for $t {
+            #[inline]
+            fn from(fd: OwnedFd) -> $t {
+                unsafe { $t::from_raw_fd(fd.into_raw_fd()) }
+            }
+        }
+
+        impl fmt::Debug for $t {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($t)).field("fd", &self.as_raw_fd()).finish()
+            }
+        }
+    };
+}
+
+impl_fd_traits!(PipeReader);
+impl_fd_traits!(PipeWriter);
@@ -0,0 +1,276 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Shared memory rings between the enclave and the host.
+//!
+//! A ring is a single-producer, single-consumer byte queue in untrusted
+//! memory that the host allocates on request of the enclave. Once it is set
+//! up, data moves through it with plain memory copies, so a pipeline that
+//! streams bulk data in or out of the enclave does not pay for an ocall per
+//! message. Give the host the address from [`ShmWriter::as_raw_ptr`] or
+//! [`ShmReader::as_raw_ptr`]; the host side attaches to it with
+//! `sgx_urts::shm::ShmRing`.
+//!
+//! Everything in a ring is visible to the host and can be changed by it at
+//! any time, so data that must stay confidential has to be protected before
+//! it is written. The enclave keeps its own copy of the ring's capacity and
+//! of the counter it owns, and checks the host's counter on every access, so
+//! a misbehaving host can corrupt the data but cannot make the enclave read
+//! or write outside the ring.
+//!
+//! Both ends are non-blocking: they fail with `WouldBlock` when the ring is
+//! full or empty, and it is up to the caller to back off and retry, for
+//! example with [`thread::yield_now`](crate::thread::yield_now).
+
+use crate::cmp;
+use crate::fmt;
+use crate::io::{self, Read, Write};
+use crate::ptr;
+use crate::sync::atomic::{AtomicU64, Ordering};
+use sgx_libc::ocall::{shm_ring_create, shm_ring_release};
+use sgx_trts::trts::rsgx_raw_is_outside_enclave;
+use sgx_types::metadata::SE_PAGE_SIZE;
+use sgx_types::{
+    SGX_SHM_RING_CLOSED_OFFSET, SGX_SHM_RING_FROM_HOST, SGX_SHM_RING_HEADER_SIZE,
+    SGX_SHM_RING_HEAD_OFFSET, SGX_SHM_RING_READER_CLOSED, SGX_SHM_RING_TAIL_OFFSET,
+    SGX_SHM_RING_TO_HOST, SGX_SHM_RING_WRITER_CLOSED,
+};
+
+// The ring is not registered with the trts: it keeps no table of untrusted
+// memory to register with, and nothing but Ring ever touches the mapping, so
+// the checks in Ring::new and on every access are what keeps the enclave
+// inside it.
+struct Ring {
+    base: *mut u8,
+    capacity: u64,
+    // The counter this end owns. The copy in the ring is only written.
+    pos: u64,
+}
+
+impl Ring {
+    fn new(capacity: usize, direction: u64) -> io::Result<Ring> {
+        if capacity == 0 {
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidInput,
+                "shared memory ring capacity must be non-zero",
+            ));
+        }
+        let len = capacity
+            .checked_add(SGX_SHM_RING_HEADER_SIZE)
+            .ok_or(io::const_io_error!(
+                io::ErrorKind::InvalidInput,
+                "shared memory ring capacity is too large",
+            ))?;
+        let base = unsafe { shm_ring_create(capacity, direction) } as *mut u8;
+        if base.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        // The header words are accessed atomically, and the whole ring must be
+        // host memory, whatever the host returned.
+        if base as usize % SE_PAGE_SIZE != 0 || !rsgx_raw_is_outside_enclave(base, len) {
+            unsafe { shm_ring_release(base as *mut _) };
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidData,
+                "host returned an invalid shared memory ring",
+            ));
+        }
+        Ok(Ring {
+            base,
+            capacity: capacity as u64,
+            pos: 0,
+        })
+    }
+
+    fn word(&self, offset: usize) -> &AtomicU64 {
+        // SAFETY: offsets are within the header, which is 8-byte aligned
+        // since Ring::new checked that the ring is page aligned, and the words
+        // are only ever accessed atomically.
+        unsafe { &*(self.base.add(offset) as *const AtomicU64) }
+    }
+
+    fn data(&self) -> *mut u8 {
+        unsafe { self.base.add(SGX_SHM_RING_HEADER_SIZE) }
+    }
+
+    fn closed(&self, bit: u64) -> bool {
+        self.word(SGX_SHM_RING_CLOSED_OFFSET)
+            .load(Ordering::Acquire)
+            & bit
+            != 0
+    }
+
+    // Returns the number of used bytes, given the host's counter.
+    fn used(&self, produced: u64, consumed: u64) -> io::Result<u64> {
+        let used = produced.wrapping_sub(consumed);
+        if used > self.capacity {
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidData,
+                "host corrupted the shared memory ring",
+            ));
+        }
+        Ok(used)
+    }
+
+    // Returns the offset of the current position and the bytes available up
+    // to the end of the data area.
+    fn segment(&self, n: usize) -> (usize, usize) {
+        let start = (self.pos % self.capacity) as usize;
+        (start, cmp::min(n, self.capacity as usize - start))
+    }
+
+    fn close(&self, bit: u64) {
+        self.word(SGX_SHM_RING_CLOSED_OFFSET)
+            .fetch_or(bit, Ordering::Release);
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        unsafe { shm_ring_release(self.base as *mut _) };
+    }
+}
+
+/// The enclave end of a ring that carries data to the host.
+pub struct ShmWriter(Ring);
+
+/// The enclave end of a ring that carries data from the host.
+pub struct ShmReader(Ring);
+
+unsafe impl Send for ShmWriter {}
+unsafe impl Send for ShmReader {}
+
+impl ShmWriter {
+    /// Creates a ring holding up to `capacity` bytes for the enclave to
+    /// write to.
+    pub fn new(capacity: usize) -> io::Result<ShmWriter> {
+        Ring::new(capacity, SGX_SHM_RING_TO_HOST).map(ShmWriter)
+    }
+
+    /// Returns the address of the ring, to be handed to the host.
+    pub fn as_raw_ptr(&self) -> *mut u8 {
+        self.0.base
+    }
+
+    /// Returns the number of bytes the ring can hold.
+    pub fn capacity(&self) -> usize {
+        self.0.capacity as usize
+    }
+}
+
+impl Write for ShmWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let ring = &mut self.0;
+        if ring.closed(SGX_SHM_RING_READER_CLOSED) {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        let head = ring.word(SGX_SHM_RING_HEAD_OFFSET).load(Ordering::Acquire);
+        let used = ring.used(ring.pos, head)?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if used == ring.capacity {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+
+        let n = cmp::min(ring.capacity - used, buf.len() as u64) as usize;
+        let (start, first) = ring.segment(n);
+        unsafe {
+            ptr::copy_nonoverlapping(buf.as_ptr(), ring.data().add(start), first);
+            ptr::copy_nonoverlapping(buf.as_ptr().add(first), ring.data(), n - first);
+        }
+        ring.pos = ring.pos.wrapping_add(n as u64);
+        ring.word(SGX_SHM_RING_TAIL_OFFSET)
+            .store(ring.pos, Ordering::Release);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for ShmWriter {
+    fn drop(&mut self) {
+        self.0.close(SGX_SHM_RING_WRITER_CLOSED);
+    }
+}
+
+impl ShmReader {
+    /// Creates a ring holding up to `capacity` bytes for the host to write
+    /// to.
+    pub fn new(capacity: usize) -> io::Result<ShmReader> {
+        Ring::new(capacity, SGX_SHM_RING_FROM_HOST).map(ShmReader)
+    }
+
+    /// Returns the address of the ring, to be handed to the host.
+    pub fn as_raw_ptr(&self) -> *mut u8 {
+        self.0.base
+    }
+
+    /// Returns the number of bytes the ring can hold.
+    pub fn capacity(&self) -> usize {
+        self.0.capacity as usize
+    }
+}
+
+impl Read for ShmReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let ring = &mut self.0;
+        let tail = ring.word(SGX_SHM_RING_TAIL_OFFSET).load(Ordering::Acquire);
+        let used = ring.used(tail, ring.pos)?;
+        if used == 0 {
+            return if ring.closed(SGX_SHM_RING_WRITER_CLOSED) {
+                Ok(0)
+            } else {
+                Err(io::ErrorKind::WouldBlock.into())
+            };
+        }
+
+        let n = cmp::min(used, buf.len() as u64) as usize;
+        let (start, first) = ring.segment(n);
+        unsafe {
+            ptr::copy_nonoverlapping(ring.data().add(start), buf.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(ring.data(), buf.as_mut_ptr().add(first), n - first);
+        }
+        ring.pos = ring.pos.wrapping_add(n as u64);
+        ring.word(SGX_SHM_RING_HEAD_OFFSET)
+            .store(ring.pos, Ordering::Release);
+        Ok(n)
+    }
+}
+
+impl Drop for ShmReader {
+    fn drop(&mut self) {
+        self.0.close(SGX_SHM_RING_READER_CLOSED);
+    }
+}
+
+impl fmt::Debug for ShmWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShmWriter")
+            .field("capacity", &self.capacity())
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for ShmReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShmReader")
+            .field("capacity", &self.capacity())
+            .finish_non_exhaustive()
+    }
+}
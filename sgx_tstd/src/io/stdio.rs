@@ -945,6 +945,49 @@ impl fmt::Debug for StderrLock<'_> {
     }
 }
 
+/// A destination for the enclave's standard output or standard error.
+///
+/// Sinks are shared by all threads, so writes take `&self` and an
+/// implementation has to do its own synchronization.
+pub trait StdioSink: Send + Sync {
+    /// Writes a buffer into this sink, returning how many bytes were written.
+    fn write(&self, buf: &[u8]) -> io::Result<usize>;
+
+    /// Flushes any data buffered by this sink.
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> StdioSink for Mutex<W> {
+    fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().unwrap_or_else(crate::sync::PoisonError::into_inner).write(buf)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.lock().unwrap_or_else(crate::sync::PoisonError::into_inner).flush()
+    }
+}
+
+/// Routes the standard output of the enclave to `sink` instead of the host,
+/// returning the previous sink.
+///
+/// This covers `print!`, `println!` and [`stdout`]. Passing `None` restores
+/// output to the host, unless the `no_host_stdio` feature is enabled, in
+/// which case output without a sink is discarded.
+pub fn set_stdout(sink: Option<Arc<dyn StdioSink>>) -> Option<Arc<dyn StdioSink>> {
+    let _ = stdout().flush();
+    stdio::set_stdout_sink(sink)
+}
+
+/// Routes the standard error of the enclave, including panic messages, to
+/// `sink` instead of the host, returning the previous sink.
+///
+/// See [`set_stdout`].
+pub fn set_stderr(sink: Option<Arc<dyn StdioSink>>) -> Option<Arc<dyn StdioSink>> {
+    stdio::set_stderr_sink(sink)
+}
+
 /// Sets the thread-local output capture buffer and returns the old one.
 #[doc(hidden)]
 pub fn set_output_capture(sink: Option<LocalStream>) -> Option<LocalStream> {
@@ -164,6 +164,13 @@ extern crate hashbrown;
 #[macro_use]
 extern crate alloc as alloc_crate;
 
+// Capability flags remove host-backed APIs entirely, so they cannot be
+// combined with the features that provide those APIs.
+#[cfg(all(feature = "net", feature = "no_net"))]
+compile_error!("features `net` and `no_net` are mutually exclusive");
+#[cfg(all(feature = "untrusted_fs", feature = "no_fs"))]
+compile_error!("features `untrusted_fs` and `no_fs` are mutually exclusive");
+
 // We always need an unwinder currently for backtraces
 extern crate sgx_unwind;
 #[cfg(feature = "backtrace")]
@@ -256,31 +263,29 @@ pub mod collections;
 pub mod env;
 pub mod error;
 pub mod ffi;
+#[cfg(not(feature = "no_fs"))]
+pub mod sealedfs;
+#[cfg(not(feature = "no_fs"))]
 pub mod sgxfs;
 #[cfg(feature = "untrusted_fs")]
 pub mod fs;
 pub mod io;
+pub mod log;
+pub mod memfs;
 pub mod net;
 pub mod num;
 pub mod os;
 pub mod panic;
 pub mod path;
+pub mod process;
+pub mod rand;
 pub mod sync;
+pub mod task;
 pub mod time;
 pub mod enclave;
+pub mod ecall;
 pub mod untrusted;
 
-
-pub mod task {
-    //! Types and Traits for working with asynchronous tasks.
-
-    #[doc(inline)]
-    pub use core::task::*;
-
-    #[doc(inline)]
-    pub use alloc_crate::task::*;
-}
-
 pub mod arch {
     // The `no_inline`-attribute is required to make the documentation of all
     // targets available.
@@ -302,6 +307,7 @@ mod panicking;
 mod personality;
 
 #[cfg(not(feature = "untrusted_fs"))]
+#[cfg_attr(feature = "no_fs", allow(dead_code))]
 mod fs;
 
 pub use cpuid::*;
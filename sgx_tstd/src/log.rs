@@ -0,0 +1,401 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! A logging facade whose sink and filters are chosen inside the enclave.
+//!
+//! The facade mirrors the `log` crate: records have a [`Level`] and a target,
+//! usually the module path, and are handed to a [`LogSink`]. A `log::Log`
+//! implementation in the enclave can forward to [`log`] to use it as the
+//! backend of the `log` macros.
+//!
+//! Nothing here is configured by the host. In particular, filters are not
+//! read from `RUST_LOG`: the maximum level and the per-module levels are set
+//! with [`set_max_level`] and [`set_module_level`].
+//!
+//! The sink is negotiated once, with [`init`]: the enclave passes the sinks
+//! it is willing to use in order of preference, and the first one that
+//! reports itself as available is used from then on. Besides sinks provided
+//! by the enclave, such as one that sends records over an attested channel,
+//! two sinks are built in:
+//!
+//! * [`HostStderrSink`] writes records in plain text to the host's standard
+//!   error.
+//! * [`EncryptedRingSink`] keeps the most recent records encrypted with
+//!   AES-GCM under a key held by the enclave, to be drained and handed to the
+//!   host for storage.
+
+use crate::collections::VecDeque;
+use crate::fmt;
+use crate::io;
+use crate::ptr;
+use crate::string::String;
+use crate::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use crate::sync::{Arc, PoisonError, SgxMutex, SgxRwLock};
+use crate::vec::Vec;
+
+use sgx_types::{
+    sgx_aes_gcm_128bit_key_t, sgx_aes_gcm_128bit_tag_t, sgx_rijndael128GCM_encrypt, sgx_status_t,
+    SGX_AESGCM_IV_SIZE, SGX_AESGCM_MAC_SIZE,
+};
+
+/// The importance of a log record, from most to least severe.
+///
+/// The values match the levels of the `log` crate.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    /// Returns the name of the level in upper case.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// The most verbose level that passes a filter.
+///
+/// [`LevelFilter::Off`] lets no record through.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LevelFilter {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LevelFilter {
+    fn from_usize(v: usize) -> LevelFilter {
+        match v {
+            0 => LevelFilter::Off,
+            1 => LevelFilter::Error,
+            2 => LevelFilter::Warn,
+            3 => LevelFilter::Info,
+            4 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    }
+
+    fn allows(self, level: Level) -> bool {
+        level as usize <= self as usize
+    }
+}
+
+/// A log record.
+#[derive(Clone, Debug)]
+pub struct Record<'a> {
+    level: Level,
+    target: &'a str,
+    args: fmt::Arguments<'a>,
+}
+
+impl<'a> Record<'a> {
+    /// Returns the level of the record.
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Returns the target of the record, usually the module path.
+    pub fn target(&self) -> &'a str {
+        self.target
+    }
+
+    /// Returns the message of the record.
+    pub fn args(&self) -> &fmt::Arguments<'a> {
+        &self.args
+    }
+}
+
+/// A destination for log records.
+pub trait LogSink: Send + Sync {
+    /// Returns a short name for the sink, reported by [`init`].
+    fn name(&self) -> &str;
+
+    /// Returns whether the sink can be used now.
+    ///
+    /// [`init`] skips sinks that are not available, for example a remote
+    /// sink whose channel has not been established.
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// Writes a record that passed the filters.
+    fn log(&self, record: &Record<'_>);
+
+    /// Flushes buffered records.
+    fn flush(&self) {}
+}
+
+static SINK: SgxRwLock<Option<Arc<dyn LogSink>>> = SgxRwLock::new(None);
+static MAX_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Info as usize);
+static MODULE_LEVELS: SgxRwLock<Vec<(String, LevelFilter)>> = SgxRwLock::new(Vec::new());
+
+/// Selects the sink for all further records.
+///
+/// `sinks` are tried in order and the first available one is used. Returns
+/// the name of the chosen sink. Fails with
+/// [`ErrorKind::AlreadyExists`](io::ErrorKind::AlreadyExists) if a sink was
+/// already selected, and with [`ErrorKind::NotFound`](io::ErrorKind::NotFound)
+/// if none of the sinks is available.
+pub fn init(sinks: Vec<Arc<dyn LogSink>>) -> io::Result<String> {
+    let mut current = SINK.write().unwrap_or_else(PoisonError::into_inner);
+    if current.is_some() {
+        return Err(io::const_io_error!(
+            io::ErrorKind::AlreadyExists,
+            "a log sink has already been selected",
+        ));
+    }
+
+    let sink = sinks
+        .into_iter()
+        .find(|sink| sink.is_available())
+        .ok_or_else(|| io::const_io_error!(io::ErrorKind::NotFound, "no log sink is available"))?;
+    let name = String::from(sink.name());
+    *current = Some(sink);
+    Ok(name)
+}
+
+/// Sets the level filter for targets without a module level of their own.
+///
+/// The default is [`LevelFilter::Info`].
+pub fn set_max_level(filter: LevelFilter) {
+    MAX_LEVEL.store(filter as usize, Ordering::Relaxed);
+}
+
+/// Returns the level filter for targets without a module level of their own.
+pub fn max_level() -> LevelFilter {
+    LevelFilter::from_usize(MAX_LEVEL.load(Ordering::Relaxed))
+}
+
+/// Sets the level filter for `module` and the modules below it.
+///
+/// A target is filtered by the longest module path that equals it or is a
+/// `::`-separated prefix of it.
+pub fn set_module_level(module: &str, filter: LevelFilter) {
+    let mut levels = MODULE_LEVELS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+    match levels.iter_mut().find(|(m, _)| m == module) {
+        Some(entry) => entry.1 = filter,
+        None => levels.push((String::from(module), filter)),
+    }
+}
+
+fn filter_for(target: &str) -> LevelFilter {
+    let levels = MODULE_LEVELS.read().unwrap_or_else(PoisonError::into_inner);
+    levels
+        .iter()
+        .filter(|(module, _)| {
+            target == module
+                || (target.starts_with(module.as_str()) && target[module.len()..].starts_with("::"))
+        })
+        .max_by_key(|(module, _)| module.len())
+        .map_or_else(max_level, |&(_, filter)| filter)
+}
+
+/// Returns whether a record with this level and target would be logged.
+pub fn enabled(level: Level, target: &str) -> bool {
+    filter_for(target).allows(level)
+        && SINK
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_some()
+}
+
+/// Logs a record, if it passes the filters and a sink has been selected.
+///
+/// # Examples
+///
+/// ```
+/// use std::log::{self, Level};
+///
+/// log::log(Level::Info, module_path!(), format_args!("request {} done", 7));
+/// ```
+pub fn log(level: Level, target: &str, args: fmt::Arguments<'_>) {
+    if !filter_for(target).allows(level) {
+        return;
+    }
+    let sink = SINK.read().unwrap_or_else(PoisonError::into_inner).clone();
+    if let Some(sink) = sink {
+        sink.log(&Record {
+            level,
+            target,
+            args,
+        });
+    }
+}
+
+/// Flushes the selected sink.
+pub fn flush() {
+    let sink = SINK.read().unwrap_or_else(PoisonError::into_inner).clone();
+    if let Some(sink) = sink {
+        sink.flush();
+    }
+}
+
+/// Writes records in plain text to the host's standard error.
+///
+/// Everything logged through this sink is visible to the host.
+#[derive(Debug, Default)]
+pub struct HostStderrSink;
+
+impl LogSink for HostStderrSink {
+    fn name(&self) -> &str {
+        "host-stderr"
+    }
+
+    #[cfg(feature = "stdio")]
+    fn log(&self, record: &Record<'_>) {
+        use crate::io::Write;
+
+        let _ = writeln!(
+            io::stderr(),
+            "[{} {}] {}",
+            record.level,
+            record.target,
+            record.args
+        );
+    }
+
+    #[cfg(not(feature = "stdio"))]
+    fn log(&self, _record: &Record<'_>) {}
+
+    fn is_available(&self) -> bool {
+        cfg!(feature = "stdio")
+    }
+}
+
+/// Keeps the most recent records encrypted inside the enclave.
+///
+/// Each record is formatted like [`HostStderrSink`] does, then encrypted
+/// with AES-128-GCM under the key given to [`new`](EncryptedRingSink::new)
+/// and a fresh random IV. An entry is laid out as the 8-byte little endian
+/// sequence number, the 12-byte IV, the 16-byte tag and the ciphertext; the
+/// sequence number is authenticated as additional data, so that dropped or
+/// reordered entries can be detected when they are decrypted.
+pub struct EncryptedRingSink {
+    key: sgx_aes_gcm_128bit_key_t,
+    capacity: usize,
+    seq: AtomicU64,
+    entries: SgxMutex<VecDeque<Vec<u8>>>,
+}
+
+impl EncryptedRingSink {
+    /// Creates a sink that keeps at most `capacity` entries, dropping the
+    /// oldest ones first.
+    pub fn new(key: sgx_aes_gcm_128bit_key_t, capacity: usize) -> EncryptedRingSink {
+        EncryptedRingSink {
+            key,
+            capacity,
+            seq: AtomicU64::new(0),
+            entries: SgxMutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Removes and returns the kept entries, oldest first.
+    pub fn drain(&self) -> Vec<Vec<u8>> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.drain(..).collect()
+    }
+
+    fn encrypt(&self, seq: u64, plaintext: &[u8]) -> Option<Vec<u8>> {
+        let mut iv = [0_u8; SGX_AESGCM_IV_SIZE];
+        sgx_trts::trts::rsgx_read_rand(&mut iv).ok()?;
+        let aad = seq.to_le_bytes();
+        let mut tag: sgx_aes_gcm_128bit_tag_t = [0; SGX_AESGCM_MAC_SIZE];
+        let mut ciphertext = vec![0_u8; plaintext.len()];
+        let status = unsafe {
+            sgx_rijndael128GCM_encrypt(
+                &self.key,
+                plaintext.as_ptr(),
+                plaintext.len() as u32,
+                ciphertext.as_mut_ptr(),
+                iv.as_ptr(),
+                iv.len() as u32,
+                aad.as_ptr(),
+                aad.len() as u32,
+                &mut tag,
+            )
+        };
+        if status != sgx_status_t::SGX_SUCCESS {
+            return None;
+        }
+
+        let mut entry = Vec::with_capacity(aad.len() + iv.len() + tag.len() + ciphertext.len());
+        entry.extend_from_slice(&aad);
+        entry.extend_from_slice(&iv);
+        entry.extend_from_slice(&tag);
+        entry.extend_from_slice(&ciphertext);
+        Some(entry)
+    }
+}
+
+impl LogSink for EncryptedRingSink {
+    fn name(&self) -> &str {
+        "encrypted-ring"
+    }
+
+    fn is_available(&self) -> bool {
+        self.capacity > 0
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        let plaintext = crate::format!("[{} {}] {}", record.level, record.target, record.args);
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        if let Some(entry) = self.encrypt(seq, plaintext.as_bytes()) {
+            let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+            if entries.len() == self.capacity {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+        }
+    }
+}
+
+impl fmt::Debug for EncryptedRingSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedRingSink")
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for EncryptedRingSink {
+    fn drop(&mut self) {
+        // SAFETY: `key` is a valid, aligned place; the volatile write keeps
+        // the compiler from eliding the wipe of a value about to be dropped.
+        unsafe { ptr::write_volatile(&mut self.key, sgx_aes_gcm_128bit_key_t::default()) };
+    }
+}
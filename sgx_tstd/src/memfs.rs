@@ -0,0 +1,662 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! A read-only, in-enclave filesystem.
+//!
+//! A [`MemFs`] is a directory tree whose files live in enclave memory. It is
+//! built either with a [`MemFsBuilder`] or from a *bundle*, a flat archive of
+//! the tree produced by [`MemFsBuilder::to_bundle`]. Bundles are meant to
+//! carry models, configuration files and CA stores without depending on the
+//! host filesystem at run time:
+//!
+//! * a bundle embedded with `include_bytes!` is part of the enclave
+//!   measurement;
+//! * a bundle loaded from the host can be checked against a digest compiled
+//!   into the enclave with [`MemFs::from_bundle_verified`];
+//! * a sealed bundle is unsealed by the enclave and then handed to
+//!   [`MemFs::from_bundle`].
+//!
+//! A tree is read through the methods of [`MemFs`], or [`mount`]ed at a path
+//! and read through the `std::fs`-like functions of this module:
+//!
+//! ```no_run
+//! use std::memfs::{self, MemFs};
+//!
+//! static BUNDLE: &[u8] = include_bytes!("../assets.bundle");
+//!
+//! memfs::mount("/assets", MemFs::from_bundle(BUNDLE)?)?;
+//! let config = memfs::read_to_string("/assets/config.toml")?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Files opened from a tree are backed by an [`io::Cursor`] over the shared
+//! contents, so opening a file does not copy it.
+
+use crate::collections::BTreeMap;
+use crate::ffi::OsString;
+use crate::fmt;
+use crate::io::{self, BufRead, Cursor, IoSliceMut, Read, Seek, SeekFrom};
+use crate::path::{Component, Path, PathBuf};
+use crate::string::String;
+use crate::sync::{Arc, PoisonError, SgxRwLock};
+use crate::vec::{self, Vec};
+
+use sgx_types::{sgx_sha256_hash_t, sgx_sha256_msg, sgx_status_t};
+
+const BUNDLE_MAGIC: &[u8; 8] = b"SGXMEMFS";
+const BUNDLE_VERSION: u32 = 1;
+
+const KIND_DIR: u8 = 0;
+const KIND_FILE: u8 = 1;
+
+#[derive(Clone)]
+enum Node {
+    File(Arc<[u8]>),
+    Dir(BTreeMap<String, Node>),
+}
+
+impl Node {
+    fn metadata(&self) -> Metadata {
+        match self {
+            Node::File(data) => Metadata {
+                len: data.len() as u64,
+                is_dir: false,
+            },
+            Node::Dir(_) => Metadata {
+                len: 0,
+                is_dir: true,
+            },
+        }
+    }
+}
+
+/// Splits `path` into the names of its components below the root of a tree.
+///
+/// `.` components are dropped and `..` goes up one level, stopping at the
+/// root. Components that are not valid UTF-8 never match an entry.
+fn components(path: &Path) -> io::Result<Vec<&str>> {
+    let mut names = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+            Component::ParentDir => {
+                names.pop();
+            }
+            Component::Normal(name) => match name.to_str() {
+                Some(name) => names.push(name),
+                None => return Err(not_found()),
+            },
+        }
+    }
+    Ok(names)
+}
+
+fn not_found() -> io::Error {
+    io::const_io_error!(io::ErrorKind::NotFound, "no such file or directory")
+}
+
+fn invalid_bundle() -> io::Error {
+    io::const_io_error!(io::ErrorKind::InvalidData, "malformed memfs bundle")
+}
+
+/// A read-only directory tree held in enclave memory.
+///
+/// Paths are resolved from the root of the tree; a leading `/` is optional.
+/// Cloning a `MemFs` is cheap, the file contents are shared.
+#[derive(Clone)]
+pub struct MemFs {
+    root: Node,
+}
+
+impl MemFs {
+    /// Returns a builder for a new tree.
+    pub fn builder() -> MemFsBuilder {
+        MemFsBuilder::new()
+    }
+
+    /// Loads a tree from a bundle.
+    ///
+    /// Fails with [`ErrorKind::InvalidData`](io::ErrorKind::InvalidData) if
+    /// the bundle is malformed.
+    pub fn from_bundle(bundle: &[u8]) -> io::Result<MemFs> {
+        let mut reader = BundleReader { buf: bundle };
+        if reader.take(BUNDLE_MAGIC.len())? != BUNDLE_MAGIC || reader.u32()? != BUNDLE_VERSION {
+            return Err(invalid_bundle());
+        }
+
+        let mut builder = MemFsBuilder::new();
+        let count = reader.u32()?;
+        for _ in 0..count {
+            let kind = reader.take(1)?[0];
+            let path_len = reader.u32()? as usize;
+            let path =
+                crate::str::from_utf8(reader.take(path_len)?).map_err(|_| invalid_bundle())?;
+            match kind {
+                KIND_DIR => builder.add_dir(path),
+                KIND_FILE => {
+                    let len = usize::try_from(reader.u64()?).map_err(|_| invalid_bundle())?;
+                    builder.add_file(path, reader.take(len)?)
+                }
+                _ => return Err(invalid_bundle()),
+            }
+            .map_err(|_| invalid_bundle())?;
+        }
+        if !reader.buf.is_empty() {
+            return Err(invalid_bundle());
+        }
+        Ok(builder.build())
+    }
+
+    /// Loads a tree from a bundle after checking that its SHA-256 digest is
+    /// `digest`.
+    ///
+    /// Fails with [`ErrorKind::InvalidData`](io::ErrorKind::InvalidData) if
+    /// the digest does not match, before the bundle is parsed.
+    pub fn from_bundle_verified(bundle: &[u8], digest: &sgx_sha256_hash_t) -> io::Result<MemFs> {
+        let len = u32::try_from(bundle.len()).map_err(|_| invalid_bundle())?;
+        let mut actual: sgx_sha256_hash_t = [0; 32];
+        let status = unsafe { sgx_sha256_msg(bundle.as_ptr(), len, &mut actual) };
+        if status != sgx_status_t::SGX_SUCCESS {
+            return Err(io::Error::from_sgx_error(status));
+        }
+        if actual != *digest {
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidData,
+                "memfs bundle digest mismatch",
+            ));
+        }
+        MemFs::from_bundle(bundle)
+    }
+
+    fn lookup(&self, path: &Path) -> io::Result<&Node> {
+        let mut node = &self.root;
+        for name in components(path)? {
+            node = match node {
+                Node::Dir(entries) => entries.get(name).ok_or_else(not_found)?,
+                Node::File(_) => {
+                    return Err(io::const_io_error!(
+                        io::ErrorKind::NotADirectory,
+                        "not a directory",
+                    ));
+                }
+            };
+        }
+        Ok(node)
+    }
+
+    /// Opens a file for reading.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        match self.lookup(path.as_ref())? {
+            Node::File(data) => Ok(File {
+                inner: Cursor::new(data.clone()),
+            }),
+            Node::Dir(_) => Err(io::const_io_error!(
+                io::ErrorKind::IsADirectory,
+                "is a directory"
+            )),
+        }
+    }
+
+    /// Reads the entire contents of a file into a bytes vector.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        let file = self.open(path)?;
+        Ok(file.inner.get_ref().to_vec())
+    }
+
+    /// Reads the entire contents of a file into a string.
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        String::from_utf8(self.read(path)?).map_err(|_| {
+            io::const_io_error!(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8"
+            )
+        })
+    }
+
+    /// Queries metadata about a file or directory.
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        self.lookup(path.as_ref()).map(Node::metadata)
+    }
+
+    /// Returns an iterator over the entries within a directory, in
+    /// lexicographic order of their names.
+    pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<ReadDir> {
+        let path = path.as_ref();
+        match self.lookup(path)? {
+            Node::Dir(entries) => {
+                let entries = entries.iter().map(|(name, node)| DirEntry {
+                    path: path.join(name),
+                    metadata: node.metadata(),
+                });
+                Ok(ReadDir {
+                    inner: entries.collect::<Vec<_>>().into_iter(),
+                })
+            }
+            Node::File(_) => Err(io::const_io_error!(
+                io::ErrorKind::NotADirectory,
+                "not a directory"
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for MemFs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemFs").finish_non_exhaustive()
+    }
+}
+
+struct BundleReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> BundleReader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.buf.len() < n {
+            return Err(invalid_bundle());
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+/// A builder for a [`MemFs`].
+///
+/// Parent directories are created as needed. Paths must be relative to the
+/// root of the tree, or start at it, and may not contain `..`.
+#[derive(Clone)]
+pub struct MemFsBuilder {
+    root: BTreeMap<String, Node>,
+}
+
+impl MemFsBuilder {
+    /// Creates a builder for an empty tree.
+    pub fn new() -> MemFsBuilder {
+        MemFsBuilder {
+            root: BTreeMap::new(),
+        }
+    }
+
+    fn parent_of<'a, 'b>(
+        &'a mut self,
+        path: &'b str,
+    ) -> io::Result<(&'a mut BTreeMap<String, Node>, &'b str)> {
+        let mut names = Vec::new();
+        for component in Path::new(path).components() {
+            match component {
+                Component::RootDir | Component::CurDir => {}
+                Component::Normal(name) => names.push(name.to_str().unwrap()),
+                Component::Prefix(_) | Component::ParentDir => {
+                    return Err(io::const_io_error!(
+                        io::ErrorKind::InvalidInput,
+                        "memfs paths may not contain `..`",
+                    ));
+                }
+            }
+        }
+        let name = names.pop().ok_or_else(|| {
+            io::const_io_error!(io::ErrorKind::InvalidInput, "memfs path has no file name")
+        })?;
+
+        let mut dir = &mut self.root;
+        for name in names {
+            let node = dir
+                .entry(String::from(name))
+                .or_insert_with(|| Node::Dir(BTreeMap::new()));
+            dir = match node {
+                Node::Dir(entries) => entries,
+                Node::File(_) => {
+                    return Err(io::const_io_error!(
+                        io::ErrorKind::NotADirectory,
+                        "not a directory",
+                    ));
+                }
+            };
+        }
+        Ok((dir, name))
+    }
+
+    /// Adds a file with the given contents.
+    ///
+    /// Fails with [`ErrorKind::AlreadyExists`](io::ErrorKind::AlreadyExists)
+    /// if the path is already taken.
+    pub fn add_file<C: AsRef<[u8]>>(
+        &mut self,
+        path: &str,
+        contents: C,
+    ) -> io::Result<&mut MemFsBuilder> {
+        let (dir, name) = self.parent_of(path)?;
+        if dir.contains_key(name) {
+            return Err(io::const_io_error!(
+                io::ErrorKind::AlreadyExists,
+                "entry already exists"
+            ));
+        }
+        dir.insert(String::from(name), Node::File(Arc::from(contents.as_ref())));
+        Ok(self)
+    }
+
+    /// Adds an empty directory, or does nothing if it already exists.
+    pub fn add_dir(&mut self, path: &str) -> io::Result<&mut MemFsBuilder> {
+        let (dir, name) = self.parent_of(path)?;
+        match dir
+            .entry(String::from(name))
+            .or_insert_with(|| Node::Dir(BTreeMap::new()))
+        {
+            Node::Dir(_) => Ok(self),
+            Node::File(_) => Err(io::const_io_error!(
+                io::ErrorKind::AlreadyExists,
+                "entry already exists"
+            )),
+        }
+    }
+
+    /// Builds the tree.
+    pub fn build(&self) -> MemFs {
+        MemFs {
+            root: Node::Dir(self.root.clone()),
+        }
+    }
+
+    /// Serializes the tree into a bundle for [`MemFs::from_bundle`].
+    pub fn to_bundle(&self) -> Vec<u8> {
+        fn walk(
+            prefix: &str,
+            entries: &BTreeMap<String, Node>,
+            out: &mut Vec<(String, Option<Arc<[u8]>>)>,
+        ) {
+            for (name, node) in entries {
+                let path = if prefix.is_empty() {
+                    name.clone()
+                } else {
+                    crate::format!("{prefix}/{name}")
+                };
+                match node {
+                    Node::File(data) => out.push((path, Some(data.clone()))),
+                    Node::Dir(children) => {
+                        out.push((path.clone(), None));
+                        walk(&path, children, out);
+                    }
+                }
+            }
+        }
+
+        let mut entries = Vec::new();
+        walk("", &self.root, &mut entries);
+
+        let mut bundle = Vec::new();
+        bundle.extend_from_slice(BUNDLE_MAGIC);
+        bundle.extend_from_slice(&BUNDLE_VERSION.to_le_bytes());
+        bundle.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for (path, data) in entries {
+            bundle.push(if data.is_some() { KIND_FILE } else { KIND_DIR });
+            bundle.extend_from_slice(&(path.len() as u32).to_le_bytes());
+            bundle.extend_from_slice(path.as_bytes());
+            if let Some(data) = data {
+                bundle.extend_from_slice(&(data.len() as u64).to_le_bytes());
+                bundle.extend_from_slice(&data);
+            }
+        }
+        bundle
+    }
+}
+
+impl Default for MemFsBuilder {
+    fn default() -> MemFsBuilder {
+        MemFsBuilder::new()
+    }
+}
+
+impl fmt::Debug for MemFsBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemFsBuilder").finish_non_exhaustive()
+    }
+}
+
+/// A file opened from a [`MemFs`].
+///
+/// Files are read-only; they implement [`Read`], [`BufRead`] and [`Seek`].
+#[derive(Clone)]
+pub struct File {
+    inner: Cursor<Arc<[u8]>>,
+}
+
+impl File {
+    /// Queries metadata about the file.
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        Ok(Metadata {
+            len: self.inner.get_ref().len() as u64,
+            is_dir: false,
+        })
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        self.inner.read_vectored(bufs)
+    }
+
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.inner.read_to_end(buf)
+    }
+}
+
+impl BufRead for File {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl fmt::Debug for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("File")
+            .field("len", &self.inner.get_ref().len())
+            .field("pos", &self.inner.position())
+            .finish()
+    }
+}
+
+/// Metadata about a file or directory in a [`MemFs`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Metadata {
+    len: u64,
+    is_dir: bool,
+}
+
+impl Metadata {
+    /// Returns `true` if this metadata is for a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Returns `true` if this metadata is for a regular file.
+    pub fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+
+    /// Returns the size of the file in bytes, or zero for a directory.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the file is empty or this is a directory.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// An iterator over the entries of a directory, returned by
+/// [`MemFs::read_dir`] and [`read_dir`].
+#[derive(Debug)]
+pub struct ReadDir {
+    inner: vec::IntoIter<DirEntry>,
+}
+
+impl Iterator for ReadDir {
+    type Item = io::Result<DirEntry>;
+
+    fn next(&mut self) -> Option<io::Result<DirEntry>> {
+        self.inner.next().map(Ok)
+    }
+}
+
+/// An entry of a directory.
+#[derive(Clone, Debug)]
+pub struct DirEntry {
+    path: PathBuf,
+    metadata: Metadata,
+}
+
+impl DirEntry {
+    /// Returns the full path to the entry, made of the path passed to
+    /// `read_dir` and the name of the entry.
+    pub fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    /// Returns the name of the entry.
+    pub fn file_name(&self) -> OsString {
+        self.path
+            .file_name()
+            .map(OsString::from)
+            .unwrap_or_default()
+    }
+
+    /// Returns the metadata of the entry.
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        Ok(self.metadata)
+    }
+}
+
+static MOUNTS: SgxRwLock<Vec<(PathBuf, MemFs)>> = SgxRwLock::new(Vec::new());
+
+/// Mounts a tree at `path`, which must be absolute.
+///
+/// The module-level functions resolve a path through the mount whose path
+/// is its longest prefix. Fails with
+/// [`ErrorKind::AlreadyExists`](io::ErrorKind::AlreadyExists) if a tree is
+/// already mounted at `path`.
+pub fn mount<P: AsRef<Path>>(path: P, fs: MemFs) -> io::Result<()> {
+    let path = path.as_ref();
+    if !path.is_absolute() {
+        return Err(io::const_io_error!(
+            io::ErrorKind::InvalidInput,
+            "mount point must be an absolute path",
+        ));
+    }
+    let mut point = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                point.pop();
+            }
+            Component::CurDir => {}
+            c => point.push(c),
+        }
+    }
+
+    let mut mounts = MOUNTS.write().unwrap_or_else(PoisonError::into_inner);
+    if mounts.iter().any(|(p, _)| *p == point) {
+        return Err(io::const_io_error!(
+            io::ErrorKind::AlreadyExists,
+            "path is already mounted"
+        ));
+    }
+    mounts.push((point, fs));
+    Ok(())
+}
+
+/// Unmounts the tree mounted at `path`, returning it.
+pub fn unmount<P: AsRef<Path>>(path: P) -> Option<MemFs> {
+    let path = path.as_ref();
+    let mut mounts = MOUNTS.write().unwrap_or_else(PoisonError::into_inner);
+    let index = mounts.iter().position(|(p, _)| p == path)?;
+    Some(mounts.remove(index).1)
+}
+
+fn with_mount<T, F>(path: &Path, f: F) -> io::Result<T>
+where
+    F: FnOnce(&MemFs, &Path) -> io::Result<T>,
+{
+    let mounts = MOUNTS.read().unwrap_or_else(PoisonError::into_inner);
+    mounts
+        .iter()
+        .filter_map(|(point, fs)| path.strip_prefix(point).ok().map(|rest| (point, fs, rest)))
+        .max_by_key(|(point, _, _)| point.as_os_str().len())
+        .ok_or_else(not_found)
+        .and_then(|(_, fs, rest)| f(fs, rest))
+}
+
+/// Opens a file in a mounted tree.
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    with_mount(path.as_ref(), |fs, path| fs.open(path))
+}
+
+/// Reads the entire contents of a file in a mounted tree into a bytes vector.
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    with_mount(path.as_ref(), |fs, path| fs.read(path))
+}
+
+/// Reads the entire contents of a file in a mounted tree into a string.
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    with_mount(path.as_ref(), |fs, path| fs.read_to_string(path))
+}
+
+/// Queries metadata about a file or directory in a mounted tree.
+pub fn metadata<P: AsRef<Path>>(path: P) -> io::Result<Metadata> {
+    with_mount(path.as_ref(), |fs, path| fs.metadata(path))
+}
+
+/// Returns an iterator over the entries within a directory of a mounted
+/// tree.
+///
+/// The paths of the entries start with `path`.
+pub fn read_dir<P: AsRef<Path>>(path: P) -> io::Result<ReadDir> {
+    let path = path.as_ref();
+    with_mount(path, |fs, rest| {
+        let entries = fs.read_dir(rest)?.inner.map(|entry| DirEntry {
+            path: path.join(entry.path.file_name().unwrap()),
+            metadata: entry.metadata,
+        });
+        Ok(ReadDir {
+            inner: entries.collect::<Vec<_>>().into_iter(),
+        })
+    })
+}
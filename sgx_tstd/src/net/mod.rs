@@ -24,6 +24,8 @@
 //!
 //! * [`TcpListener`] and [`TcpStream`] provide functionality for communication over TCP
 //! * [`UdpSocket`] provides functionality for communication over UDP
+//! * [`TlsStream`] runs a [`TlsSession`] over a [`TcpStream`], keeping TLS termination
+//!   inside the enclave
 //! * [`IpAddr`] represents IP addresses of either IPv4 or IPv6; [`Ipv4Addr`] and
 //!   [`Ipv6Addr`] are respectively IPv4 and IPv6 addresses
 //! * [`SocketAddr`] represents socket addresses of either IPv4 or IPv6; [`SocketAddrV4`]
@@ -42,19 +44,29 @@ pub use self::ip_addr::{IpAddr, Ipv4Addr, Ipv6Addr, Ipv6MulticastScope};
 pub use self::parser::AddrParseError;
 pub use self::socket_addr::{SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs};
 #[cfg(feature = "net")]
+pub use self::policy::{
+    has_connect_policy, set_connect_policy, ConnectDecision, ConnectPolicy, EgressPolicy, Protocol,
+};
+#[cfg(feature = "net")]
 pub use self::tcp::IntoIncoming;
 #[cfg(feature = "net")]
 pub use self::tcp::{Incoming, TcpListener, TcpStream};
 #[cfg(feature = "net")]
+pub use self::tls::{TlsProvider, TlsSession, TlsStream};
+#[cfg(feature = "net")]
 pub use self::udp::UdpSocket;
 
 mod display_buffer;
 mod ip_addr;
 mod parser;
+#[cfg(feature = "net")]
+pub(crate) mod policy;
 mod socket_addr;
 #[cfg(feature = "net")]
 mod tcp;
 #[cfg(feature = "net")]
+mod tls;
+#[cfg(feature = "net")]
 mod udp;
 
 /// Possible values which can be passed to the [`TcpStream::shutdown`] method.
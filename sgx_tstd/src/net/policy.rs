@@ -0,0 +1,253 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Egress control for outbound connections.
+//!
+//! An enclave can install one [`ConnectPolicy`] for its whole lifetime with
+//! [`set_connect_policy`]. The policy is consulted inside the enclave before
+//! every outbound TCP connect, UDP connect and UDP `send_to`, and can let the
+//! connection through, send it to another address (typically a local proxy)
+//! or refuse it. Because the check runs before the ocall, the host cannot
+//! bypass it; it can of course still drop or reroute the traffic itself, so
+//! the policy limits what the enclave is willing to send out, not where the
+//! packets end up.
+//!
+//! [`EgressPolicy`] covers the common case of an ordered list of CIDR and
+//! port rules. Anything more involved can implement [`ConnectPolicy`]
+//! directly, and closures of the right shape implement it too.
+
+use crate::io;
+use crate::net::{IpAddr, SocketAddr};
+use crate::ops::RangeInclusive;
+use crate::sync::OnceLock;
+
+/// The transport an outbound connection uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    /// A TCP connection.
+    Tcp,
+    /// A UDP `connect` or `send_to`.
+    Udp,
+}
+
+/// What a [`ConnectPolicy`] decides for an outbound connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectDecision {
+    /// Connect to the requested address.
+    Allow,
+    /// Connect to this address instead.
+    Redirect(SocketAddr),
+    /// Refuse the connection with a `PermissionDenied` error.
+    Deny,
+}
+
+/// A policy consulted for every outbound connection.
+pub trait ConnectPolicy: Send + Sync {
+    /// Decides what to do with a connection to `addr`.
+    fn check(&self, protocol: Protocol, addr: &SocketAddr) -> ConnectDecision;
+}
+
+impl<F> ConnectPolicy for F
+where
+    F: Fn(Protocol, &SocketAddr) -> ConnectDecision + Send + Sync,
+{
+    fn check(&self, protocol: Protocol, addr: &SocketAddr) -> ConnectDecision {
+        self(protocol, addr)
+    }
+}
+
+static POLICY: OnceLock<Box<dyn ConnectPolicy>> = OnceLock::new();
+
+/// Installs the connect policy of this enclave.
+///
+/// The policy can only be set once; later calls fail with `AlreadyExists`.
+/// It should be installed before any other code gets a chance to connect.
+pub fn set_connect_policy<P: ConnectPolicy + 'static>(policy: P) -> io::Result<()> {
+    POLICY.set(Box::new(policy)).map_err(|_| {
+        io::const_io_error!(
+            io::ErrorKind::AlreadyExists,
+            "connect policy is already set"
+        )
+    })
+}
+
+/// Returns whether a connect policy has been installed.
+pub fn has_connect_policy() -> bool {
+    POLICY.get().is_some()
+}
+
+/// Applies the installed policy to a connection to `addr`, returning the
+/// address to actually connect to.
+pub(crate) fn check(protocol: Protocol, addr: &SocketAddr) -> io::Result<SocketAddr> {
+    let policy = match POLICY.get() {
+        Some(policy) => policy,
+        None => return Ok(*addr),
+    };
+    match policy.check(protocol, addr) {
+        ConnectDecision::Allow => Ok(*addr),
+        ConnectDecision::Redirect(to) => Ok(to),
+        ConnectDecision::Deny => Err(io::const_io_error!(
+            io::ErrorKind::PermissionDenied,
+            "connection refused by the enclave's connect policy",
+        )),
+    }
+}
+
+/// A connect policy made of CIDR and port rules.
+///
+/// Rules are tried in the order they were added and the first one matching
+/// the destination address and port decides; if none matches, the default
+/// decision applies. IPv4 rules only match IPv4 destinations and IPv6 rules
+/// only IPv6 ones, so an IPv4-mapped IPv6 address has to be covered by an
+/// IPv6 rule.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::{set_connect_policy, EgressPolicy, IpAddr, Ipv4Addr, SocketAddr};
+///
+/// let proxy = SocketAddr::from(([127, 0, 0, 1], 3128));
+/// let policy = EgressPolicy::deny_by_default()
+///     .allow(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 8, 443..=443)
+///     .redirect(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0, 80..=80, proxy);
+/// set_connect_policy(policy)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct EgressPolicy {
+    rules: Vec<Rule>,
+    default: ConnectDecision,
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    network: IpAddr,
+    prefix_len: u8,
+    ports: RangeInclusive<u16>,
+    decision: ConnectDecision,
+}
+
+impl EgressPolicy {
+    /// Creates a policy that allows everything no rule matches.
+    pub fn allow_by_default() -> EgressPolicy {
+        EgressPolicy {
+            rules: Vec::new(),
+            default: ConnectDecision::Allow,
+        }
+    }
+
+    /// Creates a policy that denies everything no rule matches.
+    pub fn deny_by_default() -> EgressPolicy {
+        EgressPolicy {
+            rules: Vec::new(),
+            default: ConnectDecision::Deny,
+        }
+    }
+
+    /// Allows connections to `network/prefix_len` on `ports`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_len` is longer than the address.
+    pub fn allow(self, network: IpAddr, prefix_len: u8, ports: RangeInclusive<u16>) -> Self {
+        self.rule(network, prefix_len, ports, ConnectDecision::Allow)
+    }
+
+    /// Denies connections to `network/prefix_len` on `ports`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_len` is longer than the address.
+    pub fn deny(self, network: IpAddr, prefix_len: u8, ports: RangeInclusive<u16>) -> Self {
+        self.rule(network, prefix_len, ports, ConnectDecision::Deny)
+    }
+
+    /// Sends connections to `network/prefix_len` on `ports` to `to` instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_len` is longer than the address.
+    pub fn redirect(
+        self,
+        network: IpAddr,
+        prefix_len: u8,
+        ports: RangeInclusive<u16>,
+        to: SocketAddr,
+    ) -> Self {
+        self.rule(network, prefix_len, ports, ConnectDecision::Redirect(to))
+    }
+
+    fn rule(
+        mut self,
+        network: IpAddr,
+        prefix_len: u8,
+        ports: RangeInclusive<u16>,
+        decision: ConnectDecision,
+    ) -> Self {
+        let max_len = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        assert!(
+            prefix_len <= max_len,
+            "prefix length {prefix_len} is too long for {network}"
+        );
+        self.rules.push(Rule {
+            network,
+            prefix_len,
+            ports,
+            decision,
+        });
+        self
+    }
+}
+
+impl Rule {
+    fn matches(&self, addr: &SocketAddr) -> bool {
+        if !self.ports.contains(&addr.port()) {
+            return false;
+        }
+        match (self.network, addr.ip()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => prefix_matches(
+                u32::from(net).into(),
+                u32::from(ip).into(),
+                self.prefix_len,
+                32,
+            ),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_matches(u128::from(net), u128::from(ip), self.prefix_len, 128)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: u128, ip: u128, prefix_len: u8, bits: u32) -> bool {
+    let shift = bits - u32::from(prefix_len);
+    // A zero-length prefix matches everything, and shifting by the full
+    // width would overflow.
+    shift >= bits || (net ^ ip) >> shift == 0
+}
+
+impl ConnectPolicy for EgressPolicy {
+    fn check(&self, _protocol: Protocol, addr: &SocketAddr) -> ConnectDecision {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(addr))
+            .map_or(self.default, |rule| rule.decision)
+    }
+}
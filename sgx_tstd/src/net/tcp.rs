@@ -25,7 +25,7 @@ use crate::sys_common::net as net_imp;
 use crate::sys_common::{AsInner, FromInner, IntoInner};
 use crate::time::Duration;
 
-use sgx_libc::c_int;
+use sgx_libc::{c_int, SO_RCVBUF, SO_SNDBUF};
 
 /// A TCP stream between a local and a remote socket.
 ///
@@ -541,6 +541,133 @@ impl TcpStream {
         self.0.nodelay()
     }
 
+    /// Sets the keepalive time of this socket, or disables keepalive.
+    ///
+    /// With `Some(time)`, `SO_KEEPALIVE` is enabled and `TCP_KEEPIDLE` is set
+    /// to `time`: the connection has to be idle that long before the first
+    /// keepalive probe is sent. `None` disables keepalive.
+    ///
+    /// The time has a granularity of one second and is rounded up. Values
+    /// above 32767 seconds are rejected with
+    /// [`ErrorKind::InvalidInput`](io::ErrorKind::InvalidInput).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    /// use std::time::Duration;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// stream.set_keepalive(Some(Duration::from_secs(60))).expect("set_keepalive call failed");
+    /// stream.set_keepalive_interval(Duration::from_secs(10)).expect("set_keepalive_interval call failed");
+    /// stream.set_keepalive_retries(3).expect("set_keepalive_retries call failed");
+    /// ```
+    pub fn set_keepalive(&self, keepalive: Option<Duration>) -> io::Result<()> {
+        self.0.socket().set_keepalive(keepalive)
+    }
+
+    /// Gets the keepalive time of this socket, or `None` if keepalive is
+    /// disabled.
+    ///
+    /// For more information about this option, see [`TcpStream::set_keepalive`].
+    pub fn keepalive(&self) -> io::Result<Option<Duration>> {
+        self.0.socket().keepalive()
+    }
+
+    /// Sets the value of the `TCP_KEEPINTVL` option on this socket.
+    ///
+    /// This is the time between two keepalive probes. It is validated like the
+    /// time given to [`TcpStream::set_keepalive`].
+    pub fn set_keepalive_interval(&self, interval: Duration) -> io::Result<()> {
+        self.0.socket().set_keepalive_interval(interval)
+    }
+
+    /// Gets the value of the `TCP_KEEPINTVL` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`TcpStream::set_keepalive_interval`].
+    pub fn keepalive_interval(&self) -> io::Result<Duration> {
+        self.0.socket().keepalive_interval()
+    }
+
+    /// Sets the value of the `TCP_KEEPCNT` option on this socket.
+    ///
+    /// This is the number of unanswered keepalive probes after which the
+    /// connection is dropped. It must be between 1 and 127.
+    pub fn set_keepalive_retries(&self, retries: u32) -> io::Result<()> {
+        self.0.socket().set_keepalive_retries(retries)
+    }
+
+    /// Gets the value of the `TCP_KEEPCNT` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`TcpStream::set_keepalive_retries`].
+    pub fn keepalive_retries(&self) -> io::Result<u32> {
+        self.0.socket().keepalive_retries()
+    }
+
+    /// Sets the value of the `TCP_USER_TIMEOUT` option on this socket.
+    ///
+    /// This is how long transmitted data may remain unacknowledged before the
+    /// connection is dropped. The timeout is rounded up to milliseconds;
+    /// `None` restores the system default. A zero [`Duration`] is rejected
+    /// with [`ErrorKind::InvalidInput`](io::ErrorKind::InvalidInput).
+    pub fn set_user_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.0.socket().set_user_timeout(timeout)
+    }
+
+    /// Gets the value of the `TCP_USER_TIMEOUT` option on this socket.
+    ///
+    /// For more information about this option, see [`TcpStream::set_user_timeout`].
+    pub fn user_timeout(&self) -> io::Result<Option<Duration>> {
+        self.0.socket().user_timeout()
+    }
+
+    /// Sets the size of the `SO_RCVBUF` receive buffer of this socket.
+    ///
+    /// Linux doubles the value to leave room for bookkeeping, and
+    /// [`recv_buffer_size`] reports the doubled value. A size of zero, or one
+    /// too large to be doubled, is rejected with
+    /// [`ErrorKind::InvalidInput`](io::ErrorKind::InvalidInput).
+    ///
+    /// [`recv_buffer_size`]: TcpStream::recv_buffer_size
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpStream;
+    ///
+    /// let socket = TcpStream::connect("127.0.0.1:8080")
+    ///                        .expect("Couldn't connect to the server...");
+    /// socket.set_recv_buffer_size(256 * 1024).expect("set_recv_buffer_size call failed");
+    /// ```
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.0.socket().set_buffer_size(size, SO_RCVBUF)
+    }
+
+    /// Gets the size of the `SO_RCVBUF` receive buffer of this socket.
+    ///
+    /// For more information about this option, see [`TcpStream::set_recv_buffer_size`].
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.0.socket().buffer_size(SO_RCVBUF)
+    }
+
+    /// Sets the size of the `SO_SNDBUF` send buffer of this socket.
+    ///
+    /// The value is validated and reported like the one of
+    /// [`TcpStream::set_recv_buffer_size`].
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.0.socket().set_buffer_size(size, SO_SNDBUF)
+    }
+
+    /// Gets the size of the `SO_SNDBUF` send buffer of this socket.
+    ///
+    /// For more information about this option, see [`TcpStream::set_send_buffer_size`].
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.0.socket().buffer_size(SO_SNDBUF)
+    }
+
     /// Sets the value for the `IP_TTL` option on this socket.
     ///
     /// This value sets the time-to-live field that is used in every packet sent
@@ -978,6 +1105,92 @@ impl TcpListener {
         self.0.only_v6()
     }
 
+    /// Sets the value of the `SO_REUSEADDR` option on this socket.
+    ///
+    /// The option only has an effect if it is set before the socket is bound,
+    /// so it is meant to be used with a socket from [`TcpListener::new_v4`] or
+    /// [`TcpListener::new_v6`] before [`TcpListener::bind_socket`].
+    pub fn set_reuse_address(&self, reuse: bool) -> io::Result<()> {
+        self.0.socket().set_reuse_address(reuse)
+    }
+
+    /// Gets the value of the `SO_REUSEADDR` option on this socket.
+    ///
+    /// For more information about this option, see [`TcpListener::set_reuse_address`].
+    pub fn reuse_address(&self) -> io::Result<bool> {
+        self.0.socket().reuse_address()
+    }
+
+    /// Sets the value of the `SO_REUSEPORT` option on this socket.
+    ///
+    /// If set on every socket before it is bound, several sockets can be bound
+    /// to the same address and port, and the host kernel spreads incoming
+    /// connections between them. As with [`TcpListener::set_reuse_address`], the option
+    /// has to be set before [`TcpListener::bind_socket`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    ///
+    /// let socket = TcpListener::new_v4().expect("couldn't create socket");
+    /// socket.set_reuse_port(true).expect("set_reuse_port call failed");
+    /// socket.bind_socket("0.0.0.0:8080").expect("couldn't bind to address");
+    /// ```
+    pub fn set_reuse_port(&self, reuse: bool) -> io::Result<()> {
+        self.0.socket().set_reuse_port(reuse)
+    }
+
+    /// Gets the value of the `SO_REUSEPORT` option on this socket.
+    ///
+    /// For more information about this option, see [`TcpListener::set_reuse_port`].
+    pub fn reuse_port(&self) -> io::Result<bool> {
+        self.0.socket().reuse_port()
+    }
+
+    /// Sets the size of the `SO_RCVBUF` receive buffer of this socket.
+    ///
+    /// Linux doubles the value to leave room for bookkeeping, and
+    /// [`recv_buffer_size`] reports the doubled value. A size of zero, or one
+    /// too large to be doubled, is rejected with
+    /// [`ErrorKind::InvalidInput`](io::ErrorKind::InvalidInput).
+    ///
+    /// [`recv_buffer_size`]: TcpListener::recv_buffer_size
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::TcpListener;
+    ///
+    /// let socket = TcpListener::bind("127.0.0.1:80").unwrap();
+    /// socket.set_recv_buffer_size(256 * 1024).expect("set_recv_buffer_size call failed");
+    /// ```
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.0.socket().set_buffer_size(size, SO_RCVBUF)
+    }
+
+    /// Gets the size of the `SO_RCVBUF` receive buffer of this socket.
+    ///
+    /// For more information about this option, see [`TcpListener::set_recv_buffer_size`].
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.0.socket().buffer_size(SO_RCVBUF)
+    }
+
+    /// Sets the size of the `SO_SNDBUF` send buffer of this socket.
+    ///
+    /// The value is validated and reported like the one of
+    /// [`TcpListener::set_recv_buffer_size`].
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.0.socket().set_buffer_size(size, SO_SNDBUF)
+    }
+
+    /// Gets the size of the `SO_SNDBUF` send buffer of this socket.
+    ///
+    /// For more information about this option, see [`TcpListener::set_send_buffer_size`].
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.0.socket().buffer_size(SO_SNDBUF)
+    }
+
     /// Gets the value of the `SO_ERROR` option on this socket.
     ///
     /// This will retrieve the stored error in the underlying socket, clearing
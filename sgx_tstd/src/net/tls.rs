@@ -0,0 +1,252 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! TLS over [`TcpStream`], terminated inside the enclave.
+//!
+//! `sgx_tstd` cannot depend on a TLS library itself, since the TLS libraries
+//! for enclaves are built on top of it. Instead, [`TlsSession`] describes the
+//! interface of a buffered TLS connection such as a rustls `ClientConnection`
+//! or `ServerConnection`, and [`TlsStream`] drives such a session over a
+//! [`TcpStream`]. The enclave port of the TLS library implements
+//! [`TlsSession`] for its connection types, so that records are encrypted and
+//! decrypted inside the enclave and only ciphertext crosses the ocall
+//! boundary.
+//!
+//! [`TlsProvider`] gives the TLS library access to the trusted random number
+//! generator and to the current time. There is no trusted wall clock in an
+//! enclave: the time comes from the host and must only be used where a host
+//! that lies about it cannot break security, such as certificate validity
+//! checks that are also backed by other means.
+
+use crate::fmt;
+use crate::io::{self, Read, Write};
+use crate::net::{Shutdown, TcpStream};
+use crate::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sgx_trts::trts::rsgx_read_rand;
+
+/// The interface of a buffered TLS connection, as provided by rustls.
+///
+/// The methods correspond to the methods of the same names on rustls'
+/// `ConnectionCommon`, its `reader()` and its `writer()`.
+pub trait TlsSession {
+    /// Returns whether the session wants to read TLS records.
+    fn wants_read(&self) -> bool;
+
+    /// Returns whether the session has TLS records to write.
+    fn wants_write(&self) -> bool;
+
+    /// Returns whether the handshake is still in progress.
+    fn is_handshaking(&self) -> bool;
+
+    /// Reads TLS records from `rd` into the session's buffer.
+    fn read_tls(&mut self, rd: &mut dyn Read) -> io::Result<usize>;
+
+    /// Writes buffered TLS records to `wr`.
+    fn write_tls(&mut self, wr: &mut dyn Write) -> io::Result<usize>;
+
+    /// Processes the TLS records that were read, decrypting application data.
+    fn process_new_packets(&mut self) -> io::Result<()>;
+
+    /// Reads decrypted application data.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::WouldBlock`] if no data is
+    /// available yet, and `Ok(0)` once the peer has closed the connection.
+    fn read_plaintext(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Queues application data to be encrypted and sent.
+    fn write_plaintext(&mut self, buf: &[u8]) -> io::Result<usize>;
+
+    /// Queues a `close_notify` alert to be sent.
+    fn send_close_notify(&mut self);
+}
+
+/// A TLS connection over a [`TcpStream`].
+///
+/// Reads and writes complete the handshake first, then transfer application
+/// data through the session.
+pub struct TlsStream<S: TlsSession> {
+    session: S,
+    sock: TcpStream,
+}
+
+impl<S: TlsSession> TlsStream<S> {
+    /// Creates a TLS stream that runs `session` over `sock`.
+    ///
+    /// No I/O is done until the stream is first used, or until
+    /// [`handshake`](TlsStream::handshake) is called.
+    pub fn new(session: S, sock: TcpStream) -> TlsStream<S> {
+        TlsStream { session, sock }
+    }
+
+    /// Drives the handshake to completion.
+    pub fn handshake(&mut self) -> io::Result<()> {
+        while self.session.is_handshaking() {
+            self.complete_io()?;
+        }
+        Ok(())
+    }
+
+    /// Returns a reference to the TLS session.
+    pub fn session(&self) -> &S {
+        &self.session
+    }
+
+    /// Returns a mutable reference to the TLS session.
+    pub fn session_mut(&mut self) -> &mut S {
+        &mut self.session
+    }
+
+    /// Returns a reference to the underlying TCP stream.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.sock
+    }
+
+    /// Sends a `close_notify` alert and shuts down the write half of the TCP
+    /// stream.
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        self.session.send_close_notify();
+        while self.session.wants_write() {
+            self.session.write_tls(&mut self.sock)?;
+        }
+        self.sock.shutdown(Shutdown::Write)
+    }
+
+    /// Consumes the stream, returning the session and the TCP stream.
+    pub fn into_inner(self) -> (S, TcpStream) {
+        (self.session, self.sock)
+    }
+
+    // Performs reads and writes until the handshake is complete, or until
+    // some data has been transferred once the handshake is done. Returns the
+    // number of TLS bytes read and written.
+    fn complete_io(&mut self) -> io::Result<(usize, usize)> {
+        let until_handshaked = self.session.is_handshaking();
+        let mut eof = false;
+        let mut wrlen = 0;
+        let mut rdlen = 0;
+
+        loop {
+            while self.session.wants_write() {
+                wrlen += self.session.write_tls(&mut self.sock)?;
+            }
+            if !until_handshaked && wrlen > 0 {
+                return Ok((rdlen, wrlen));
+            }
+
+            if !eof && self.session.wants_read() {
+                match self.session.read_tls(&mut self.sock)? {
+                    0 => eof = true,
+                    n => rdlen += n,
+                }
+            }
+
+            if let Err(e) = self.session.process_new_packets() {
+                // Try to tell the peer why the connection failed.
+                let _ = self.session.write_tls(&mut self.sock);
+                return Err(e);
+            }
+
+            match (eof, until_handshaked, self.session.is_handshaking()) {
+                (_, true, false) | (_, false, _) => return Ok((rdlen, wrlen)),
+                (true, true, true) => {
+                    return Err(io::const_io_error!(
+                        io::ErrorKind::UnexpectedEof,
+                        "connection closed during the TLS handshake",
+                    ))
+                }
+                (false, true, true) => {}
+            }
+        }
+    }
+
+    fn complete_prior_io(&mut self) -> io::Result<()> {
+        if self.session.is_handshaking() {
+            self.complete_io()?;
+        }
+        if self.session.wants_write() {
+            self.complete_io()?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: TlsSession> Read for TlsStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.complete_prior_io()?;
+        while self.session.wants_read() {
+            if self.complete_io()?.0 == 0 {
+                break;
+            }
+        }
+        // If the peer closed the TCP stream without a close_notify alert,
+        // the session reports an error rather than a clean end of stream,
+        // so that a truncated stream is not mistaken for a complete one.
+        self.session.read_plaintext(buf)
+    }
+}
+
+impl<S: TlsSession> Write for TlsStream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.complete_prior_io()?;
+        let len = self.session.write_plaintext(buf)?;
+        // Try to send the data right away, but report it as written even if
+        // that fails; the error will come up again on the next call.
+        let _ = self.complete_io();
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.complete_prior_io()?;
+        while self.session.wants_write() {
+            self.session.write_tls(&mut self.sock)?;
+        }
+        self.sock.flush()
+    }
+}
+
+impl<S: TlsSession + fmt::Debug> fmt::Debug for TlsStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsStream")
+            .field("session", &self.session)
+            .field("sock", &self.sock)
+            .finish()
+    }
+}
+
+/// Randomness and time for a TLS library running in the enclave.
+///
+/// The methods have the shape of rustls' `SecureRandom::fill` and
+/// `TimeProvider::current_time`, so that an enclave crypto provider can
+/// forward to them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TlsProvider;
+
+impl TlsProvider {
+    /// Fills `buf` from the trusted random number generator (`RDRAND`,
+    /// through `sgx_read_rand`).
+    pub fn fill(&self, buf: &mut [u8]) -> io::Result<()> {
+        rsgx_read_rand(buf).map_err(io::Error::from_sgx_error)
+    }
+
+    /// Returns the current time as the duration since the Unix epoch.
+    ///
+    /// The time is read from the host, see the [module documentation](self).
+    pub fn current_time(&self) -> Option<Duration> {
+        SystemTime::_now().duration_since(UNIX_EPOCH).ok()
+    }
+}
@@ -22,7 +22,7 @@ use crate::sys_common::net as net_imp;
 use crate::sys_common::{AsInner, FromInner, IntoInner};
 use crate::time::Duration;
 
-use sgx_libc::c_int;
+use sgx_libc::{c_int, SO_RCVBUF, SO_SNDBUF};
 
 /// A UDP socket.
 ///
@@ -565,6 +565,92 @@ impl UdpSocket {
         self.0.ttl()
     }
 
+    /// Sets the value of the `SO_REUSEADDR` option on this socket.
+    ///
+    /// The option only has an effect if it is set before the socket is bound,
+    /// so it is meant to be used with a socket from [`UdpSocket::new_v4`] or
+    /// [`UdpSocket::new_v6`] before [`UdpSocket::bind_socket`].
+    pub fn set_reuse_address(&self, reuse: bool) -> io::Result<()> {
+        self.0.socket().set_reuse_address(reuse)
+    }
+
+    /// Gets the value of the `SO_REUSEADDR` option on this socket.
+    ///
+    /// For more information about this option, see [`UdpSocket::set_reuse_address`].
+    pub fn reuse_address(&self) -> io::Result<bool> {
+        self.0.socket().reuse_address()
+    }
+
+    /// Sets the value of the `SO_REUSEPORT` option on this socket.
+    ///
+    /// If set on every socket before it is bound, several sockets can be bound
+    /// to the same address and port, and the host kernel spreads incoming
+    /// datagrams between them. As with [`UdpSocket::set_reuse_address`], the option
+    /// has to be set before [`UdpSocket::bind_socket`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::new_v4().expect("couldn't create socket");
+    /// socket.set_reuse_port(true).expect("set_reuse_port call failed");
+    /// socket.bind_socket("0.0.0.0:8080").expect("couldn't bind to address");
+    /// ```
+    pub fn set_reuse_port(&self, reuse: bool) -> io::Result<()> {
+        self.0.socket().set_reuse_port(reuse)
+    }
+
+    /// Gets the value of the `SO_REUSEPORT` option on this socket.
+    ///
+    /// For more information about this option, see [`UdpSocket::set_reuse_port`].
+    pub fn reuse_port(&self) -> io::Result<bool> {
+        self.0.socket().reuse_port()
+    }
+
+    /// Sets the size of the `SO_RCVBUF` receive buffer of this socket.
+    ///
+    /// Linux doubles the value to leave room for bookkeeping, and
+    /// [`recv_buffer_size`] reports the doubled value. A size of zero, or one
+    /// too large to be doubled, is rejected with
+    /// [`ErrorKind::InvalidInput`](io::ErrorKind::InvalidInput).
+    ///
+    /// [`recv_buffer_size`]: UdpSocket::recv_buffer_size
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::net::UdpSocket;
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:34254").expect("couldn't bind to address");
+    /// socket.set_recv_buffer_size(256 * 1024).expect("set_recv_buffer_size call failed");
+    /// ```
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.0.socket().set_buffer_size(size, SO_RCVBUF)
+    }
+
+    /// Gets the size of the `SO_RCVBUF` receive buffer of this socket.
+    ///
+    /// For more information about this option, see [`UdpSocket::set_recv_buffer_size`].
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.0.socket().buffer_size(SO_RCVBUF)
+    }
+
+    /// Sets the size of the `SO_SNDBUF` send buffer of this socket.
+    ///
+    /// The value is validated and reported like the one of
+    /// [`UdpSocket::set_recv_buffer_size`].
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.0.socket().set_buffer_size(size, SO_SNDBUF)
+    }
+
+    /// Gets the size of the `SO_SNDBUF` send buffer of this socket.
+    ///
+    /// For more information about this option, see [`UdpSocket::set_send_buffer_size`].
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.0.socket().buffer_size(SO_SNDBUF)
+    }
+
     /// Executes an operation of the `IP_ADD_MEMBERSHIP` type.
     ///
     /// This function specifies a new multicast group for this socket to join.
@@ -19,10 +19,13 @@
 //!
 //! [`std::thread`]: crate::thread
 
+use crate::ffi::CString;
+use crate::io;
 #[allow(deprecated)]
 use crate::os::unix::raw::pthread_t;
+use crate::sealed::Sealed;
 use crate::sys_common::{AsInner, IntoInner};
-use crate::thread::JoinHandle;
+use crate::thread::{JoinHandle, SgxThread};
 
 #[allow(deprecated)]
 pub type RawPthread = pthread_t;
@@ -49,3 +52,66 @@ impl<T> JoinHandleExt for JoinHandle<T> {
         self.into_inner().into_id() as RawPthread
     }
 }
+
+/// SGX-specific extensions to [`SgxThread`].
+///
+/// A thread gets a TCS (thread control structure) when it first runs inside
+/// the enclave. TCSes are numbered in the order threads first ran on them;
+/// the numbers are small and stable for the lifetime of the enclave, which
+/// makes them easier to follow in diagnostics than addresses. Panic messages
+/// and backtraces include both.
+pub trait ThreadExt: Sealed {
+    /// Returns the address of the TCS the thread runs on, or `None` if the
+    /// thread has not started running yet.
+    fn tcs(&self) -> Option<usize>;
+
+    /// Returns the index of the TCS the thread runs on, or `None` if the
+    /// thread has not started running yet.
+    fn tcs_index(&self) -> Option<usize>;
+
+    /// Names a thread that does not have a name yet.
+    ///
+    /// Threads spawned through [`Builder::name`](crate::thread::Builder::name)
+    /// are named when they are created; this is meant for threads that
+    /// entered the enclave through an ecall.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`](io::ErrorKind::InvalidInput)
+    /// if `name` contains a nul byte, and with
+    /// [`ErrorKind::AlreadyExists`](io::ErrorKind::AlreadyExists) if the
+    /// thread is already named.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::os::unix::thread::ThreadExt;
+    /// use std::thread;
+    ///
+    /// thread::current().set_name("ecall worker").unwrap();
+    /// assert_eq!(thread::current().name(), Some("ecall worker"));
+    /// ```
+    fn set_name(&self, name: &str) -> io::Result<()>;
+}
+
+impl Sealed for SgxThread {}
+
+impl ThreadExt for SgxThread {
+    fn tcs(&self) -> Option<usize> {
+        SgxThread::tcs(self).map(|(tcs, _)| tcs)
+    }
+
+    fn tcs_index(&self) -> Option<usize> {
+        SgxThread::tcs(self).map(|(_, index)| index)
+    }
+
+    fn set_name(&self, name: &str) -> io::Result<()> {
+        let name = CString::new(name).map_err(|_| {
+            io::const_io_error!(
+                io::ErrorKind::InvalidInput,
+                "thread name may not contain interior null bytes",
+            )
+        })?;
+        SgxThread::set_name(self, name).map_err(|_| {
+            io::const_io_error!(io::ErrorKind::AlreadyExists, "thread is already named")
+        })
+    }
+}
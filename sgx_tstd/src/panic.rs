@@ -20,9 +20,12 @@
 use crate::any::Any;
 use crate::collections;
 use crate::panicking;
+use crate::string::String;
+use crate::sync::atomic::{AtomicU8, Ordering};
 #[cfg(feature = "backtrace")]
-use crate::sync::atomic::{AtomicUsize, Ordering};
-use crate::sync::{SgxMutex, SgxRwLock};
+use crate::sync::atomic::AtomicUsize;
+use crate::sync::{OnceLock, SgxMutex, SgxRwLock};
+use crate::vec::Vec;
 use crate::thread::Result;
 
 #[doc(hidden)]
@@ -286,3 +289,117 @@ pub fn set_backtrace_style(style: BacktraceStyle) {
 pub fn get_backtrace_style() -> Option<BacktraceStyle> {
     BacktraceStyle::from_usize(SHOULD_CAPTURE.load(Ordering::Acquire))
 }
+
+/// How the default panic hook redacts panic messages before they leave the
+/// enclave.
+///
+/// Panic messages often include values from the failing code, and the
+/// default hook writes them to the host's standard error. With redaction
+/// enabled, the hook prints an identifier instead of the message and keeps
+/// the full message inside the enclave, where it can be looked up with
+/// [`redacted_message`] or collected with [`take_redacted_messages`]. Handing
+/// those out is up to the enclave, for example over an attested channel to
+/// an authorized debugger.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PanicRedaction {
+    /// Print panic messages unchanged. This is the default.
+    Off,
+    /// Print a keyed hash of the message in place of the message, along with
+    /// the location of the panic.
+    Hash,
+    /// Print only the file name and line of the panic, in addition to the
+    /// hash identifier.
+    Location,
+}
+
+impl PanicRedaction {
+    fn as_u8(self) -> u8 {
+        match self {
+            PanicRedaction::Off => 0,
+            PanicRedaction::Hash => 1,
+            PanicRedaction::Location => 2,
+        }
+    }
+
+    fn from_u8(v: u8) -> Self {
+        match v {
+            0 => PanicRedaction::Off,
+            1 => PanicRedaction::Hash,
+            2 => PanicRedaction::Location,
+            _ => unreachable!(),
+        }
+    }
+}
+
+// The number of redacted messages kept for retrieval. Older ones are dropped.
+const REDACTED_MESSAGES_MAX: usize = 16;
+
+static REDACTION: AtomicU8 = AtomicU8::new(0);
+static REDACTED_MESSAGES: SgxMutex<Vec<(u64, String)>> = SgxMutex::new(Vec::new());
+
+/// Configures how the default panic hook redacts panic messages.
+pub fn set_panic_redaction(mode: PanicRedaction) {
+    REDACTION.store(mode.as_u8(), Ordering::Release);
+}
+
+/// Returns how the default panic hook currently redacts panic messages.
+pub fn panic_redaction() -> PanicRedaction {
+    PanicRedaction::from_u8(REDACTION.load(Ordering::Acquire))
+}
+
+/// Returns the full message of a redacted panic, given the identifier that
+/// the default panic hook printed for it.
+///
+/// Only the most recent redacted messages are kept.
+pub fn redacted_message(id: u64) -> Option<String> {
+    let messages = REDACTED_MESSAGES.lock().unwrap_or_else(crate::sync::PoisonError::into_inner);
+    messages.iter().find(|(i, _)| *i == id).map(|(_, msg)| msg.clone())
+}
+
+/// Removes and returns the kept redacted panic messages with their
+/// identifiers, oldest first.
+pub fn take_redacted_messages() -> Vec<(u64, String)> {
+    let mut messages = REDACTED_MESSAGES.lock().unwrap_or_else(crate::sync::PoisonError::into_inner);
+    crate::mem::take(&mut *messages)
+}
+
+// Keeps `msg` for later retrieval and returns its identifier: an HMAC keyed
+// with a per-enclave random key, so that the host cannot confirm guesses of
+// the message.
+pub(crate) fn redact(msg: &str, location: &Location<'_>) -> u64 {
+    static KEY: OnceLock<[u8; 32]> = OnceLock::new();
+    let key = KEY.get_or_init(|| {
+        let mut key = [0_u8; 32];
+        let _ = sgx_trts::trts::rsgx_read_rand(&mut key);
+        key
+    });
+
+    let full = crate::format!("'{msg}', {location}");
+    let mut mac = [0_u8; 32];
+    unsafe {
+        sgx_types::sgx_hmac_sha256_msg(
+            full.as_ptr(),
+            full.len() as i32,
+            key.as_ptr(),
+            key.len() as i32,
+            mac.as_mut_ptr(),
+            mac.len() as i32,
+        );
+    }
+    let mut id_bytes = [0_u8; 8];
+    id_bytes.copy_from_slice(&mac[..8]);
+    let id = u64::from_be_bytes(id_bytes);
+
+    // Never block in the panic hook: if the list is busy, the message is
+    // simply not kept.
+    if let Ok(mut messages) = REDACTED_MESSAGES.try_lock() {
+        if !messages.iter().any(|(i, _)| *i == id) {
+            if messages.len() == REDACTED_MESSAGES_MAX {
+                messages.remove(0);
+            }
+            messages.push((id, full));
+        }
+    }
+    id
+}
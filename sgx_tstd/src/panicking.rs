@@ -269,9 +269,29 @@ fn default_hook(info: &PanicInfo<'_>) {
     };
     let thread = thread_info::current_thread();
     let name = thread.as_ref().and_then(|t| t.name()).unwrap_or("<unnamed>");
+    let tcs = crate::thread::OnTcs(thread.as_ref().and_then(|t| t.tcs()));
+
+    let redaction = crate::panic::panic_redaction();
+    let redacted_id = match redaction {
+        crate::panic::PanicRedaction::Off => 0,
+        _ => crate::panic::redact(msg, location),
+    };
 
     let write = |err: &mut dyn crate::io::Write| {
-        let _ = writeln!(err, "thread '{name}' panicked at '{msg}', {location}");
+        let _ = match redaction {
+            crate::panic::PanicRedaction::Off => {
+                writeln!(err, "thread '{name}'{tcs} panicked at '{msg}', {location}")
+            }
+            crate::panic::PanicRedaction::Hash => writeln!(
+                err,
+                "thread '{name}'{tcs} panicked at <redacted {redacted_id:016x}>, {location}"
+            ),
+            _ => {
+                let file = location.file().rsplit('/').next().unwrap_or("");
+                let line = location.line();
+                writeln!(err, "thread '{name}'{tcs} panicked at <redacted {redacted_id:016x}>, {file}:{line}")
+            }
+        };
 
         #[cfg(feature = "backtrace")]
         {
@@ -0,0 +1,426 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! A process-less `Command` for enclaves.
+//!
+//! Enclaves cannot create processes. Many crates still call
+//! `std::process::Command`, so this module provides a `Command` with the
+//! familiar builder API that forwards to the uRTS, which runs the program on
+//! the host and hands back its captured output.
+//!
+//! Nothing is forwarded by default. A program can only be run once the
+//! enclave has registered it with [`allow_program`], together with a
+//! validator for its arguments; every other command fails with
+//! [`io::ErrorKind::Unsupported`]. Forwarding also requires the `process`
+//! feature and importing `sgx_process.edl`.
+//!
+//! The host is untrusted: it decides what actually runs and what the enclave
+//! gets back, so the output of a command must be treated as untrusted input.
+//!
+//! ```ignore
+//! use std::process::{self, Command};
+//!
+//! process::allow_program("/usr/bin/uname", |args| args.len() == 1 && args[0] == "-r");
+//!
+//! let output = Command::new("/usr/bin/uname").arg("-r").output()?;
+//! assert!(output.status.success());
+//! ```
+
+use crate::boxed::Box;
+use crate::ffi::{OsStr, OsString};
+use crate::fmt;
+use crate::io;
+use crate::path::{Path, PathBuf};
+use crate::sync::{PoisonError, SgxRwLock};
+use crate::vec::Vec;
+
+/// Default number of bytes captured from each output stream of a command.
+pub const DEFAULT_CAPTURE_LIMIT: usize = 64 * 1024;
+
+type Validator = Box<dyn Fn(&[OsString]) -> bool + Send + Sync>;
+
+struct Rule {
+    program: PathBuf,
+    validator: Validator,
+}
+
+static POLICY: SgxRwLock<Vec<Rule>> = SgxRwLock::new(Vec::new());
+
+/// Allows `program` to be run through [`Command`] when `validator` accepts
+/// its arguments.
+///
+/// The program is matched exactly against the one given to [`Command::new`];
+/// no `PATH` lookup takes place, so registering absolute paths is
+/// recommended. Registering the same program again replaces its validator.
+pub fn allow_program<P, F>(program: P, validator: F)
+where
+    P: AsRef<Path>,
+    F: Fn(&[OsString]) -> bool + Send + Sync + 'static,
+{
+    let program = program.as_ref().to_owned();
+    let mut policy = POLICY.write().unwrap_or_else(PoisonError::into_inner);
+    policy.retain(|r| r.program != program);
+    policy.push(Rule {
+        program,
+        validator: Box::new(validator),
+    });
+}
+
+/// Removes `program` from the set of programs allowed to run.
+pub fn disallow_program<P: AsRef<Path>>(program: P) {
+    let mut policy = POLICY.write().unwrap_or_else(PoisonError::into_inner);
+    policy.retain(|r| r.program != program.as_ref());
+}
+
+fn check_policy(program: &OsStr, args: &[OsString]) -> io::Result<()> {
+    let policy = POLICY.read().unwrap_or_else(PoisonError::into_inner);
+    match policy.iter().find(|r| r.program.as_os_str() == program) {
+        Some(rule) if (rule.validator)(args) => Ok(()),
+        Some(_) => Err(io::const_io_error!(
+            io::ErrorKind::PermissionDenied,
+            "arguments rejected by the command policy",
+        )),
+        None => Err(io::const_io_error!(
+            io::ErrorKind::Unsupported,
+            "program is not allowed to run from the enclave",
+        )),
+    }
+}
+
+/// A process builder whose commands are run by the host.
+///
+/// Only [`output`](Command::output) and [`status`](Command::status) are
+/// supported: the command always runs to completion with a null stdin and
+/// its output captured.
+pub struct Command {
+    program: OsString,
+    args: Vec<OsString>,
+    env: Vec<(OsString, Option<OsString>)>,
+    env_clear: bool,
+    cwd: Option<PathBuf>,
+    capture_limit: usize,
+}
+
+impl Command {
+    /// Constructs a new `Command` for launching the program at path `program`.
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Command {
+        Command {
+            program: program.as_ref().to_owned(),
+            args: Vec::new(),
+            env: Vec::new(),
+            env_clear: false,
+            cwd: None,
+            capture_limit: DEFAULT_CAPTURE_LIMIT,
+        }
+    }
+
+    /// Adds an argument to pass to the program.
+    pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Command {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    /// Adds multiple arguments to pass to the program.
+    pub fn args<I, S>(&mut self, args: I) -> &mut Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        for arg in args {
+            self.arg(arg.as_ref());
+        }
+        self
+    }
+
+    /// Inserts or updates an environment variable of the child process.
+    pub fn env<K, V>(&mut self, key: K, val: V) -> &mut Command
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.set_env(key.as_ref(), Some(val.as_ref()));
+        self
+    }
+
+    /// Adds or updates multiple environment variables of the child process.
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Command
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        for (key, val) in vars {
+            self.env(key, val);
+        }
+        self
+    }
+
+    /// Removes an environment variable from the child process.
+    pub fn env_remove<K: AsRef<OsStr>>(&mut self, key: K) -> &mut Command {
+        self.set_env(key.as_ref(), None);
+        self
+    }
+
+    /// Clears the environment of the child process, so that it only sees the
+    /// variables explicitly set on this `Command`.
+    pub fn env_clear(&mut self) -> &mut Command {
+        self.env.clear();
+        self.env_clear = true;
+        self
+    }
+
+    /// Sets the working directory of the child process.
+    pub fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Command {
+        self.cwd = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Sets how many bytes are captured from each of stdout and stderr. Any
+    /// output beyond the limit is discarded.
+    pub fn capture_limit(&mut self, limit: usize) -> &mut Command {
+        self.capture_limit = limit;
+        self
+    }
+
+    /// Returns the path to the program that was given to [`Command::new`].
+    pub fn get_program(&self) -> &OsStr {
+        &self.program
+    }
+
+    /// Returns an iterator of the arguments that will be passed to the program.
+    pub fn get_args(&self) -> impl Iterator<Item = &OsStr> {
+        self.args.iter().map(|a| a.as_os_str())
+    }
+
+    /// Returns the working directory for the child process, if it was set.
+    pub fn get_current_dir(&self) -> Option<&Path> {
+        self.cwd.as_deref()
+    }
+
+    /// Runs the command on the host, waiting for it to finish and collecting
+    /// its output.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::ErrorKind::Unsupported`] if the program has not been
+    /// registered with [`allow_program`] or the `process` feature is not
+    /// enabled, and [`io::ErrorKind::PermissionDenied`] if the validator
+    /// rejects the arguments.
+    pub fn output(&mut self) -> io::Result<Output> {
+        check_policy(&self.program, &self.args)?;
+        self.run()
+    }
+
+    /// Runs the command on the host, waiting for it to finish and returning
+    /// its exit status. The output of the command is discarded.
+    pub fn status(&mut self) -> io::Result<ExitStatus> {
+        self.output().map(|output| output.status)
+    }
+
+    fn set_env(&mut self, key: &OsStr, val: Option<&OsStr>) {
+        self.env.retain(|(k, _)| k != key);
+        self.env.push((key.to_owned(), val.map(|v| v.to_owned())));
+    }
+
+    #[cfg(feature = "process")]
+    fn run(&self) -> io::Result<Output> {
+        use crate::ffi::CString;
+        use crate::os::unix::ffi::OsStrExt;
+
+        fn push(buf: &mut Vec<u8>, s: &[u8]) -> io::Result<()> {
+            if s.contains(&0) {
+                return Err(io::const_io_error!(
+                    io::ErrorKind::InvalidInput,
+                    "nul byte found in provided data",
+                ));
+            }
+            buf.extend_from_slice(s);
+            buf.push(0);
+            Ok(())
+        }
+
+        let mut argv = Vec::new();
+        push(&mut argv, self.program.as_bytes())?;
+        for arg in self.args.iter() {
+            // Empty arguments cannot be told apart from separators.
+            if arg.is_empty() {
+                return Err(io::const_io_error!(
+                    io::ErrorKind::InvalidInput,
+                    "empty arguments are not supported",
+                ));
+            }
+            push(&mut argv, arg.as_bytes())?;
+        }
+
+        let mut envp = Vec::new();
+        for (key, val) in self.env.iter() {
+            if key.is_empty() || key.as_bytes().contains(&b'=') {
+                return Err(io::const_io_error!(
+                    io::ErrorKind::InvalidInput,
+                    "invalid environment variable name",
+                ));
+            }
+            match val {
+                Some(val) => {
+                    let mut entry = key.as_bytes().to_vec();
+                    entry.push(b'=');
+                    entry.extend_from_slice(val.as_bytes());
+                    push(&mut envp, &entry)?;
+                }
+                None if !self.env_clear => push(&mut envp, key.as_bytes())?,
+                None => {}
+            }
+        }
+
+        let cwd = match self.cwd {
+            Some(ref cwd) => Some(CString::new(cwd.as_os_str().as_bytes())?),
+            None => None,
+        };
+
+        let raw = crate::sys::process::run(
+            &argv,
+            &envp,
+            self.env_clear,
+            cwd.as_deref(),
+            self.capture_limit,
+        )?;
+        Ok(Output {
+            status: ExitStatus(raw.wait_status),
+            stdout: raw.stdout,
+            stderr: raw.stderr,
+        })
+    }
+
+    #[cfg(not(feature = "process"))]
+    fn run(&self) -> io::Result<Output> {
+        Err(io::const_io_error!(
+            io::ErrorKind::Unsupported,
+            "running commands requires the `process` feature",
+        ))
+    }
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.program)?;
+        for arg in self.args.iter() {
+            write!(f, " {arg:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The output of a finished command.
+#[derive(PartialEq, Eq, Clone)]
+pub struct Output {
+    /// The status (exit code) of the process.
+    pub status: ExitStatus,
+    /// The data that the process wrote to stdout, up to the capture limit.
+    pub stdout: Vec<u8>,
+    /// The data that the process wrote to stderr, up to the capture limit.
+    pub stderr: Vec<u8>,
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stdout_utf8 = crate::str::from_utf8(&self.stdout);
+        let stdout_debug: &dyn fmt::Debug = match stdout_utf8 {
+            Ok(ref str) => str,
+            Err(_) => &self.stdout,
+        };
+
+        let stderr_utf8 = crate::str::from_utf8(&self.stderr);
+        let stderr_debug: &dyn fmt::Debug = match stderr_utf8 {
+            Ok(ref str) => str,
+            Err(_) => &self.stderr,
+        };
+
+        fmt.debug_struct("Output")
+            .field("status", &self.status)
+            .field("stdout", stdout_debug)
+            .field("stderr", stderr_debug)
+            .finish()
+    }
+}
+
+/// Describes the result of a process after it has terminated, as reported by
+/// the host.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct ExitStatus(i32);
+
+impl ExitStatus {
+    /// Was termination successful? Signal termination is not considered a
+    /// success, and success is defined as a zero exit status.
+    pub fn success(&self) -> bool {
+        self.code() == Some(0)
+    }
+
+    /// Returns the exit code of the process, if any.
+    ///
+    /// Returns `None` if the process was terminated by a signal.
+    pub fn code(&self) -> Option<i32> {
+        if self.signal().is_none() {
+            Some((self.0 >> 8) & 0xff)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the signal that terminated the process, if any.
+    pub fn signal(&self) -> Option<i32> {
+        let sig = self.0 & 0x7f;
+        if sig != 0 {
+            Some(sig)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the raw wait status reported by the host.
+    pub fn into_raw(self) -> i32 {
+        self.0
+    }
+}
+
+impl fmt::Debug for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ExitStatus").field(&self.0).finish()
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(code) = self.code() {
+            write!(f, "exit status: {code}")
+        } else if let Some(signal) = self.signal() {
+            write!(f, "signal: {signal}")
+        } else {
+            write!(f, "unrecognised wait status: {}", self.0)
+        }
+    }
+}
+
+/// Returns the OS-assigned process identifier of the host process running
+/// the enclave.
+pub fn id() -> u32 {
+    unsafe { sgx_libc::ocall::getpid() as u32 }
+}
+
+/// Terminates the enclave in an abnormal fashion.
+pub fn abort() -> ! {
+    crate::sys::abort_internal();
+}
@@ -0,0 +1,144 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Randomness from the enclave's trusted entropy source.
+//!
+//! Everything in this module draws from `sgx_read_rand`, which is backed by
+//! the CPU's `RDRAND` instruction and never leaves the enclave. It is the
+//! source to use for keys, nonces and seeds; reading `/dev/urandom` through
+//! an ocall hands the host control over the result.
+//!
+//! With the `rand_core` feature, [`TrustedRng`] implements `RngCore` and
+//! `CryptoRng` of `rand_core` 0.6, so it can be handed to the `rand`
+//! ecosystem directly. Entropy failures surface as a `rand_core::Error` with
+//! a custom code, the sgx_status_t of the failure added to
+//! `Error::CUSTOM_START`.
+//!
+//! With the `getrandom` feature, this crate also provides the custom backend
+//! symbol of the `getrandom` crate (0.2, built with its `custom` feature), so
+//! crates that call `getrandom::getrandom` get trusted entropy without any
+//! registration in the enclave.
+
+use crate::fmt;
+use crate::io;
+use crate::mem;
+use sgx_trts::trts::rsgx_read_rand;
+
+/// Fills `buf` with random bytes from the trusted entropy source.
+pub fn fill_bytes(buf: &mut [u8]) -> io::Result<()> {
+    rsgx_read_rand(buf).map_err(io::Error::from_sgx_error)
+}
+
+/// A cryptographically secure random number generator backed by the
+/// trusted entropy source.
+///
+/// The generator has no state of its own: every call reads fresh entropy, so
+/// it can be created wherever it is needed and copied freely.
+#[derive(Clone, Copy, Default)]
+pub struct TrustedRng;
+
+impl TrustedRng {
+    /// Creates a new generator.
+    pub const fn new() -> TrustedRng {
+        TrustedRng
+    }
+
+    /// Returns a random `u32`.
+    pub fn next_u32(&mut self) -> u32 {
+        let mut buf = [0_u8; mem::size_of::<u32>()];
+        self.fill_bytes(&mut buf);
+        u32::from_ne_bytes(buf)
+    }
+
+    /// Returns a random `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        let mut buf = [0_u8; mem::size_of::<u64>()];
+        self.fill_bytes(&mut buf);
+        u64::from_ne_bytes(buf)
+    }
+
+    /// Fills `dest` with random bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the entropy source fails, which only happens if the CPU
+    /// keeps failing to return entropy.
+    pub fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("trusted entropy source failed")
+    }
+
+    /// Fills `dest` with random bytes, returning an error if the entropy
+    /// source fails.
+    pub fn try_fill_bytes(&mut self, dest: &mut [u8]) -> io::Result<()> {
+        fill_bytes(dest)
+    }
+}
+
+impl fmt::Debug for TrustedRng {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrustedRng").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "rand_core")]
+impl rand_core::RngCore for TrustedRng {
+    fn next_u32(&mut self) -> u32 {
+        TrustedRng::next_u32(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        TrustedRng::next_u64(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        TrustedRng::fill_bytes(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        rsgx_read_rand(dest).map_err(|status| {
+            let code = rand_core::Error::CUSTOM_START + status as u32;
+            rand_core::Error::from(crate::num::NonZeroU32::new(code).unwrap())
+        })
+    }
+}
+
+#[cfg(feature = "rand_core")]
+impl rand_core::CryptoRng for TrustedRng {}
+
+// The first error code `getrandom` reserves for custom backends.
+#[cfg(feature = "getrandom")]
+const GETRANDOM_CUSTOM_START: u32 = (1 << 31) + (1 << 30);
+
+/// The custom backend of the `getrandom` crate.
+///
+/// This has the signature expected by `getrandom::register_custom_getrandom!`
+/// and returns zero on success or a custom error code on failure. Enclaves
+/// built with this feature must not register a backend of their own.
+#[cfg(feature = "getrandom")]
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe fn __getrandom_custom(dest: *mut u8, len: usize) -> u32 {
+    if len == 0 {
+        return 0;
+    }
+    let buf = crate::slice::from_raw_parts_mut(dest, len);
+    match rsgx_read_rand(buf) {
+        Ok(()) => 0,
+        Err(status) => GETRANDOM_CUSTOM_START + status as u32,
+    }
+}
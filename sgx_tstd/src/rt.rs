@@ -26,6 +26,7 @@ use crate::sync::Once;
 use crate::sync::SgxSpinlock;
 use crate::sys;
 use crate::thread;
+use sgx_libc as libc;
 use sgx_trts::enclave::rsgx_is_supported_EDMM;
 use sgx_types::{sgx_enclave_id_t, sgx_thread_t, SGX_THREAD_T_NULL};
 
@@ -131,6 +132,20 @@ pub extern "C" fn t_global_init_ecall(id: u64, path: *const u8, len: usize) {
     });
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn t_env_snapshot_ecall(env: *const u8, len: usize) -> i32 {
+    if env.is_null() && len != 0 {
+        return libc::EINVAL;
+    }
+
+    let snapshot = if len == 0 { &[][..] } else { unsafe { slice::from_raw_parts(env, len) } };
+    match sys::os::set_env_snapshot(snapshot) {
+        Ok(()) => 0,
+        Err(_) => libc::EINVAL,
+    }
+}
+
 global_dtors_object! {
     GLOBAL_DTORS, global_dtors = {
         let _ = crate::panic::catch_unwind(cleanup);
@@ -0,0 +1,427 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! A `std::fs`-shaped façade over the protected filesystem.
+//!
+//! Every file opened through this module is an Intel SGX protected file:
+//! contents are encrypted and integrity-protected on the untrusted disk,
+//! and unless an explicit key is supplied the file key is derived
+//! automatically from the enclave sealing key.
+//!
+//! Code written against `std::fs` can usually be switched over by changing
+//! a single import:
+//!
+//! ```no_run
+//! use std::sealedfs as fs;
+//! use std::io::prelude::*;
+//!
+//! let mut file = fs::File::create("secret.txt")?;
+//! file.write_all(b"Hello, world!")?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Raw, unprotected access to the host filesystem remains available through
+//! [`std::untrusted::fs`](crate::untrusted::fs).
+
+use crate::ffi::CString;
+use crate::fmt;
+use crate::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use crate::os::unix::ffi::OsStrExt;
+use crate::path::Path;
+use crate::sys::sgxfs as fs_imp;
+use crate::sys_common::{AsInner, FromInner, IntoInner};
+use sgx_types::sgx_key_128bit_t;
+
+/// An object providing access to an open protected file.
+///
+/// An instance of a `File` can be read and/or written depending on what options
+/// it was opened with. Files also implement [`Seek`] to alter the logical cursor
+/// that the file contains internally.
+///
+/// Files are automatically closed when they go out of scope. Use
+/// [`sync_all`](File::sync_all) if flush errors must be handled.
+pub struct File {
+    inner: fs_imp::SgxFile,
+}
+
+/// Options and flags which can be used to configure how a protected file is
+/// opened.
+///
+/// The flags follow the semantics of `std::fs::OpenOptions`. In addition,
+/// [`key`](OpenOptions::key) selects an explicit key derivation key instead of
+/// the automatically derived one, and [`cache_size`](OpenOptions::cache_size)
+/// tunes the in-enclave node cache.
+#[derive(Clone)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+    key: Option<sgx_key_128bit_t>,
+    cache_size: Option<u64>,
+}
+
+/// Read the entire contents of a protected file into a bytes vector.
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Read the entire contents of a protected file into a string.
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut string = String::new();
+    File::open(path)?.read_to_string(&mut string)?;
+    Ok(string)
+}
+
+/// Write a slice as the entire contents of a protected file.
+///
+/// This function will create a file if it does not exist,
+/// and will entirely replace its contents if it does.
+pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    File::create(path)?.write_all(contents.as_ref())
+}
+
+/// Removes a protected file from the filesystem.
+pub fn remove_file<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    fs_imp::remove(path.as_ref())
+}
+
+/// Copies the contents of one protected file to another, re-encrypting the
+/// data under the destination file's key.
+///
+/// On success, the total number of bytes copied is returned.
+pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<u64> {
+    fs_imp::copy(from.as_ref(), to.as_ref())
+}
+
+impl File {
+    /// Attempts to open a protected file in read-only mode.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<File> {
+        OpenOptions::new().read(true).open(path.as_ref())
+    }
+
+    /// Opens a protected file in write-only mode.
+    ///
+    /// This function will create a file if it does not exist,
+    /// and will truncate it if it does.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<File> {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path.as_ref())
+    }
+
+    /// Creates a new protected file in read-write mode; error if the file exists.
+    pub fn create_new<P: AsRef<Path>>(path: P) -> io::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path.as_ref())
+    }
+
+    /// Returns a new OpenOptions object.
+    #[must_use]
+    pub fn options() -> OpenOptions {
+        OpenOptions::new()
+    }
+
+    /// Flushes all cached nodes to the untrusted disk.
+    ///
+    /// Protected files have no separate metadata sync, so this is equivalent
+    /// to [`sync_data`](File::sync_data).
+    pub fn sync_all(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Flushes all cached nodes to the untrusted disk.
+    pub fn sync_data(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Extends the file to `size` bytes, filling it with zeros.
+    ///
+    /// An open protected file cannot be truncated, so a size smaller than
+    /// the file fails with `ErrorKind::Unsupported`; see
+    /// [`sgxfs::set_len`](crate::sgxfs::set_len) to truncate a closed file.
+    pub fn set_len(&self, size: u64) -> io::Result<()> {
+        self.inner.set_len(size)
+    }
+
+    /// Scrubs the in-enclave node cache, writing back any modified data first.
+    pub fn clear_cache(&self) -> io::Result<()> {
+        self.inner.clear_cache()
+    }
+}
+
+impl AsInner<fs_imp::SgxFile> for File {
+    fn as_inner(&self) -> &fs_imp::SgxFile {
+        &self.inner
+    }
+}
+impl FromInner<fs_imp::SgxFile> for File {
+    fn from_inner(f: fs_imp::SgxFile) -> File {
+        File { inner: f }
+    }
+}
+impl IntoInner<fs_imp::SgxFile> for File {
+    fn into_inner(self) -> fs_imp::SgxFile {
+        self.inner
+    }
+}
+
+impl fmt::Debug for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("File").finish_non_exhaustive()
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl Read for &File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Write for &File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Seek for &File {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl OpenOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// All options are initially set to `false`, and the file key is derived
+    /// automatically.
+    #[must_use]
+    pub fn new() -> OpenOptions {
+        OpenOptions {
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+            key: None,
+            cache_size: None,
+        }
+    }
+
+    /// Sets the option for read access.
+    pub fn read(&mut self, read: bool) -> &mut OpenOptions {
+        self.read = read;
+        self
+    }
+
+    /// Sets the option for write access.
+    pub fn write(&mut self, write: bool) -> &mut OpenOptions {
+        self.write = write;
+        self
+    }
+
+    /// Sets the option for the append mode.
+    ///
+    /// Setting `.write(true).append(true)` has the same effect as setting
+    /// only `.append(true)`.
+    pub fn append(&mut self, append: bool) -> &mut OpenOptions {
+        self.append = append;
+        self
+    }
+
+    /// Sets the option for truncating a previous file.
+    ///
+    /// The file must be opened with write access for truncate to work.
+    pub fn truncate(&mut self, truncate: bool) -> &mut OpenOptions {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Sets the option to create a new file, or open it if it already exists.
+    ///
+    /// In order for the file to be created, write or append access must be used.
+    pub fn create(&mut self, create: bool) -> &mut OpenOptions {
+        self.create = create;
+        self
+    }
+
+    /// Sets the option to create a new file, failing if it already exists.
+    ///
+    /// If `.create_new(true)` is set, `.create()` and `.truncate()` are ignored.
+    pub fn create_new(&mut self, create_new: bool) -> &mut OpenOptions {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Uses `key` as the key derivation key of the file instead of the key
+    /// derived from the enclave sealing key.
+    ///
+    /// The same key must be supplied every time the file is opened.
+    pub fn key(&mut self, key: &sgx_key_128bit_t) -> &mut OpenOptions {
+        self.key = Some(*key);
+        self
+    }
+
+    /// Sets the in-enclave node cache size in bytes.
+    ///
+    /// The size must be page aligned and larger than the default cache size.
+    pub fn cache_size(&mut self, cache_size: u64) -> &mut OpenOptions {
+        self.cache_size = Some(cache_size);
+        self
+    }
+
+    /// Opens a protected file at `path` with the options specified by `self`.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        self._open(path.as_ref())
+    }
+
+    fn _open(&self, path: &Path) -> io::Result<File> {
+        let exists = file_exists(path)?;
+        let mode = self.get_access_mode(exists)?;
+
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let mode = CString::new(mode)?;
+        let inner = fs_imp::SgxFile::open_c(
+            &path,
+            &mode,
+            self.key.as_ref(),
+            self.key.is_none(),
+            self.cache_size,
+        )?;
+        Ok(File { inner })
+    }
+
+    // Translates the std-style flags into a protected-fs mode string.
+    fn get_access_mode(&self, exists: bool) -> io::Result<&'static str> {
+        if !self.read && !self.write && !self.append {
+            return Err(io::const_io_error!(
+                ErrorKind::InvalidInput,
+                "must specify at least one of read, write, or append access",
+            ));
+        }
+        let writable = self.write || self.append;
+        if !writable && (self.truncate || self.create || self.create_new) {
+            return Err(io::const_io_error!(
+                ErrorKind::InvalidInput,
+                "creating or truncating a file requires write or append access",
+            ));
+        }
+        if self.append && self.truncate && !self.create_new {
+            return Err(io::const_io_error!(
+                ErrorKind::InvalidInput,
+                "truncating a file is not allowed in append mode",
+            ));
+        }
+
+        if self.create_new {
+            if exists {
+                return Err(io::const_io_error!(
+                    ErrorKind::AlreadyExists,
+                    "protected file already exists",
+                ));
+            }
+        } else if !exists && !self.create {
+            return Err(io::const_io_error!(
+                ErrorKind::NotFound,
+                "protected file not found",
+            ));
+        }
+
+        let mode = if self.append {
+            if self.read {
+                "a+"
+            } else {
+                "a"
+            }
+        } else if !self.write {
+            "r"
+        } else if !exists || self.truncate || self.create_new {
+            if self.read {
+                "w+"
+            } else {
+                "w"
+            }
+        } else {
+            "r+"
+        };
+        Ok(mode)
+    }
+}
+
+impl Default for OpenOptions {
+    fn default() -> OpenOptions {
+        OpenOptions::new()
+    }
+}
+
+impl fmt::Debug for OpenOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpenOptions")
+            .field("read", &self.read)
+            .field("write", &self.write)
+            .field("append", &self.append)
+            .field("truncate", &self.truncate)
+            .field("create", &self.create)
+            .field("create_new", &self.create_new)
+            .field("auto_key", &self.key.is_none())
+            .field("cache_size", &self.cache_size)
+            .finish()
+    }
+}
+
+fn file_exists(path: &Path) -> io::Result<bool> {
+    match crate::fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => Ok(true),
+        Ok(_) => Err(crate::sys_common::fs::NOT_FILE_ERROR),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
@@ -17,12 +17,41 @@
 
 //! Filesystem manipulation operations.
 
+use crate::fmt::{self, Write as _};
+use crate::fs::DirBuilder;
 use crate::io::{self, Read, Seek, SeekFrom, Write};
-use crate::path::Path;
+use crate::mem;
+use crate::os::unix::fs::{DirBuilderExt, FileExt};
+use crate::path::{Component, Path, PathBuf};
+use crate::ptr;
 use crate::sys::sgxfs as fs_imp;
 use crate::sys_common::{AsInner, AsInnerMut, FromInner, IntoInner};
+use sgx_trts::trts::rsgx_read_rand;
 use sgx_types::{sgx_align_key_128bit_t, sgx_key_128bit_t};
 
+mod cache;
+mod convert;
+mod dir;
+mod integrity;
+mod keyring;
+mod rekey;
+mod shared;
+mod transaction;
+
+pub use self::cache::{SgxCachePolicy, SgxCacheStats, SgxCachedFile};
+pub use self::convert::{
+    convert, export, export_file, import, import_file, SgxProgress, SGX_CONVERT_CHUNK_SIZE,
+};
+pub use self::dir::SgxDir;
+pub use self::integrity::IntegrityFile;
+pub use self::keyring::{
+    has_key, key_id, key_id_path, provision_key, provision_wrapped_key, revoke_key, SgxKeyId,
+    SGX_WRAPPED_KEY_LEN,
+};
+pub use self::rekey::{rekey, SgxRekey};
+pub use self::shared::{SgxSharedFile, SgxSharedReader};
+pub use self::transaction::{recover, SgxRecovery, SgxTransaction, SgxTxFile};
+
 /// A reference to an open file on the filesystem.
 ///
 /// An instance of a `File` can be read and/or written depending on what options
@@ -146,6 +175,16 @@ impl SgxFile {
             .open_with(path.as_ref(), key, cache_size)
     }
 
+    /// Extends the file to `size` bytes, filling it with zeros. The
+    /// position of the file does not change.
+    ///
+    /// The protected file library cannot drop nodes from an open file, so
+    /// a size smaller than the file fails with `ErrorKind::Unsupported`; see
+    /// [`set_len`] to truncate a closed file.
+    pub fn set_len(&self, size: u64) -> io::Result<()> {
+        self.inner.set_len(size)
+    }
+
     pub fn is_eof(&self) -> bool {
         self.inner.is_eof()
     }
@@ -217,6 +256,37 @@ impl<'a> Seek for &'a SgxFile {
     }
 }
 
+/// Positional access to a protected file, for readers such as those of
+/// columnar formats that jump between offsets.
+///
+/// The protected file library has no positional reads and writes, so these
+/// seek to `offset` and back to the current position around the read or
+/// write. A handle cannot be shared between threads, so no other read or
+/// write on it can come in between. To read one file from many threads, use
+/// [`SgxSharedFile`].
+impl FileExt for SgxFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.at(offset, |inner| inner.read(buf))
+    }
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        self.at(offset, |inner| inner.write(buf))
+    }
+}
+
+impl SgxFile {
+    fn at<T>(
+        &self,
+        offset: u64,
+        f: impl FnOnce(&fs_imp::SgxFile) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let pos = self.inner.tell()?;
+        self.inner.seek(SeekFrom::Start(offset))?;
+        let result = f(&self.inner);
+        self.inner.seek(SeekFrom::Start(pos))?;
+        result
+    }
+}
+
 impl OpenOptions {
     /// Creates a blank new set of options ready for configuration.
     ///
@@ -346,6 +416,37 @@ pub fn import_auto_key<P: AsRef<Path>>(path: P, key: &sgx_key_128bit_t) -> io::R
     fs_imp::import_auto_key(path.as_ref(), key)
 }
 
+/// Truncates or extends the protected file at `path`, opened with `key` or
+/// the automatically derived key, to `size` bytes.
+///
+/// Extending the file appends zero-filled nodes and leaves the existing ones
+/// as they are. The protected file library cannot drop nodes, so truncating
+/// the file copies the part that is kept into a new protected file that
+/// replaces it, as [`SgxRekey`] does; the nodes past `size` are not read.
+pub fn set_len<P: AsRef<Path>>(
+    path: P,
+    key: Option<&sgx_key_128bit_t>,
+    size: u64,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut options = OpenOptions::new();
+    options.read(true).update(true);
+    let file = match key {
+        Some(key) => options.open_ex(path, key)?,
+        None => options.open(path)?,
+    };
+
+    let len = (&file).seek(SeekFrom::End(0))?;
+    if size >= len {
+        return file.set_len(size);
+    }
+    drop(file);
+
+    let mut rekey = SgxRekey::new(path, key, key)?;
+    rekey.truncate(size);
+    rekey.finish()
+}
+
 /// Copies the contents of one file to another.
 /// This function will **overwrite** the contents of `to`.
 ///
@@ -357,3 +458,146 @@ pub fn import_auto_key<P: AsRef<Path>>(path: P, key: &sgx_key_128bit_t) -> io::R
 pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<u64> {
     fs_imp::copy(from.as_ref(), to.as_ref())
 }
+
+/// A host directory for scratch files that only this enclave instance can
+/// read.
+///
+/// The directory gets a random name and every file in it is a protected file
+/// encrypted with a key drawn from the trusted entropy source when the
+/// directory is created. The key never leaves enclave memory, so the files
+/// are useless to the host and to any other enclave, including a later run
+/// of this one. The directory and everything in it is deleted when the
+/// `SecureTempDir` is dropped.
+///
+/// The host still sees the names, sizes and access pattern of the files, and
+/// it can delete or roll them back; the protected file library detects
+/// tampering but cannot prevent it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::{Read, Write};
+/// use std::sgxfs::SecureTempDir;
+///
+/// let dir = SecureTempDir::new()?;
+/// dir.create("scratch")?.write_all(b"intermediate results")?;
+///
+/// let mut contents = String::new();
+/// dir.open("scratch")?.read_to_string(&mut contents)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct SecureTempDir {
+    path: PathBuf,
+    key: sgx_key_128bit_t,
+}
+
+impl SecureTempDir {
+    /// Creates a new scratch directory in the host's temporary directory.
+    ///
+    /// The location comes from `TMPDIR` when it is set, see
+    /// [`env::temp_dir`](crate::env::temp_dir), and is `/tmp` otherwise.
+    pub fn new() -> io::Result<SecureTempDir> {
+        #[cfg(not(feature = "no_env"))]
+        let base = crate::env::temp_dir();
+        #[cfg(feature = "no_env")]
+        let base = PathBuf::from("/tmp");
+        SecureTempDir::new_in(base)
+    }
+
+    /// Creates a new scratch directory inside `dir`, which must exist.
+    pub fn new_in<P: AsRef<Path>>(dir: P) -> io::Result<SecureTempDir> {
+        const ATTEMPTS: u32 = 16;
+
+        let mut key = sgx_key_128bit_t::default();
+        rsgx_read_rand(&mut key).map_err(io::Error::from_sgx_error)?;
+
+        for _ in 0..ATTEMPTS {
+            let mut suffix = [0_u8; 8];
+            rsgx_read_rand(&mut suffix).map_err(io::Error::from_sgx_error)?;
+            let mut name = String::from(".sgxtmp-");
+            for b in suffix {
+                write!(name, "{:02x}", b).unwrap();
+            }
+
+            let path = dir.as_ref().join(name);
+            match DirBuilder::new().mode(0o700).create(&path) {
+                Ok(()) => return Ok(SecureTempDir { path, key }),
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(io::const_io_error!(
+            io::ErrorKind::AlreadyExists,
+            "too many temporary directories already exist",
+        ))
+    }
+
+    /// Returns the path of the directory on the host.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Creates (or truncates) the file `name` in write-only mode.
+    pub fn create<P: AsRef<Path>>(&self, name: P) -> io::Result<SgxFile> {
+        self.open_with_options(name, OpenOptions::new().write(true))
+    }
+
+    /// Opens the file `name` in read-only mode.
+    pub fn open<P: AsRef<Path>>(&self, name: P) -> io::Result<SgxFile> {
+        self.open_with_options(name, OpenOptions::new().read(true))
+    }
+
+    /// Opens the file `name` with the given options.
+    pub fn open_with_options<P: AsRef<Path>>(
+        &self,
+        name: P,
+        options: &OpenOptions,
+    ) -> io::Result<SgxFile> {
+        options.open_ex(self.file_path(name.as_ref())?, &self.key)
+    }
+
+    /// Removes the file `name`.
+    pub fn remove<P: AsRef<Path>>(&self, name: P) -> io::Result<()> {
+        remove(self.file_path(name.as_ref())?)
+    }
+
+    /// Deletes the directory and its contents, returning any error.
+    ///
+    /// Dropping a `SecureTempDir` does the same but ignores errors.
+    pub fn close(mut self) -> io::Result<()> {
+        let path = mem::take(&mut self.path);
+        crate::fs::remove_dir_all(path)
+    }
+
+    // Scratch files live directly in the directory, so `name` must be a
+    // single plain file name.
+    fn file_path(&self, name: &Path) -> io::Result<PathBuf> {
+        let mut components = name.components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => Ok(self.path.join(name)),
+            _ => Err(io::const_io_error!(
+                io::ErrorKind::InvalidInput,
+                "scratch file name must be a single path component",
+            )),
+        }
+    }
+}
+
+impl Drop for SecureTempDir {
+    fn drop(&mut self) {
+        // SAFETY: `key` is a valid, aligned place; the volatile write keeps
+        // the compiler from eliding the wipe of a value about to be dropped.
+        unsafe { ptr::write_volatile(&mut self.key, sgx_key_128bit_t::default()) };
+        if !self.path.as_os_str().is_empty() {
+            let _ = crate::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+impl fmt::Debug for SecureTempDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecureTempDir")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,286 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! An in-enclave node cache in front of a file handle.
+
+use crate::collections::{BTreeMap, HashMap};
+use crate::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+
+const NODE_SIZE: u64 = 4096;
+
+/// Which node an [`SgxCachedFile`] evicts when its cache is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SgxCachePolicy {
+    /// Evicts the node least recently read, which suits workloads that come
+    /// back to the same nodes.
+    Lru,
+    /// Evicts the node cached first, which suits scans that read each node
+    /// once.
+    Fifo,
+}
+
+/// The hits, misses and evictions of the cache of an [`SgxCachedFile`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SgxCacheStats {
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl SgxCacheStats {
+    /// Returns the number of reads of a node found in the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the number of reads of a node read from the file.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Returns the number of nodes evicted to make room for others.
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+}
+
+struct Node {
+    data: Vec<u8>,
+    tick: u64,
+}
+
+/// A file handle with a cache of 4 KiB nodes of a chosen capacity and
+/// eviction policy.
+///
+/// The protected file library has a node cache of its own; its size is set
+/// with [`OpenOptions::open_with`](crate::sgxfs::OpenOptions::open_with) but
+/// its policy is fixed and it reports no statistics. `SgxCachedFile` keeps
+/// the nodes read through it in enclave memory, so that reads of a node it
+/// holds neither call into the file nor leave the enclave, and counts its
+/// hits and misses to tune the capacity against the ocalls of the misses.
+///
+/// Writes go through to the file, and drop the nodes they modify from the
+/// cache. The file must not be modified through other handles while it is
+/// cached.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::{Read, Seek, SeekFrom};
+/// use std::sgxfs::{SgxCachePolicy, SgxCachedFile, SgxFile};
+///
+/// let mut file = SgxCachedFile::new(SgxFile::open("index")?, 1024, SgxCachePolicy::Lru);
+/// let mut entry = [0_u8; 64];
+/// file.seek(SeekFrom::Start(8192))?;
+/// file.read_exact(&mut entry)?;
+/// println!("{:?}", file.stats());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct SgxCachedFile<F> {
+    inner: F,
+    capacity: usize,
+    policy: SgxCachePolicy,
+    nodes: HashMap<u64, Node>,
+    order: BTreeMap<u64, u64>,
+    tick: u64,
+    pos: u64,
+    stats: SgxCacheStats,
+}
+
+impl<F> SgxCachedFile<F> {
+    /// Caches up to `capacity` nodes of `inner`, at least one, evicting them
+    /// according to `policy`. The position starts at 0.
+    pub fn new(inner: F, capacity: usize, policy: SgxCachePolicy) -> SgxCachedFile<F> {
+        SgxCachedFile {
+            inner,
+            capacity: capacity.max(1),
+            policy,
+            nodes: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            pos: 0,
+            stats: SgxCacheStats::default(),
+        }
+    }
+
+    /// Returns the capacity of the cache, in nodes.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes the capacity of the cache, evicting nodes if it shrinks.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.nodes.len() > self.capacity {
+            self.evict();
+        }
+    }
+
+    /// Returns the eviction policy of the cache.
+    pub fn policy(&self) -> SgxCachePolicy {
+        self.policy
+    }
+
+    /// Changes the eviction policy of the cache. The nodes already cached
+    /// keep their order.
+    pub fn set_policy(&mut self, policy: SgxCachePolicy) {
+        self.policy = policy;
+    }
+
+    /// Returns the statistics of the cache.
+    pub fn stats(&self) -> SgxCacheStats {
+        self.stats
+    }
+
+    /// Resets the statistics of the cache.
+    pub fn reset_stats(&mut self) {
+        self.stats = SgxCacheStats::default();
+    }
+
+    /// Returns the number of nodes in the cache.
+    pub fn cached(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Drops every node from the cache.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.order.clear();
+    }
+
+    /// Gets a reference to the underlying file.
+    pub fn get_ref(&self) -> &F {
+        &self.inner
+    }
+
+    /// Drops the cache and returns the underlying file, whose position is
+    /// unspecified.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn evict(&mut self) {
+        let oldest = self.order.keys().next().copied();
+        if let Some(tick) = oldest {
+            if let Some(index) = self.order.remove(&tick) {
+                self.nodes.remove(&index);
+                self.stats.evictions += 1;
+            }
+        }
+    }
+
+    fn invalidate(&mut self, start: u64, end: u64) {
+        for index in start / NODE_SIZE..=(end.max(start + 1) - 1) / NODE_SIZE {
+            if let Some(node) = self.nodes.remove(&index) {
+                self.order.remove(&node.tick);
+            }
+        }
+    }
+}
+
+impl<F: Read + Seek> SgxCachedFile<F> {
+    fn load(&mut self, index: u64) -> io::Result<&[u8]> {
+        if self.nodes.contains_key(&index) {
+            self.stats.hits += 1;
+            if self.policy == SgxCachePolicy::Lru {
+                let tick = self.next_tick();
+                let node = self.nodes.get_mut(&index).unwrap();
+                self.order.remove(&node.tick);
+                node.tick = tick;
+                self.order.insert(tick, index);
+            }
+        } else {
+            self.stats.misses += 1;
+            let mut data = vec![0_u8; NODE_SIZE as usize];
+            self.inner.seek(SeekFrom::Start(index * NODE_SIZE))?;
+            let mut len = 0;
+            while len < data.len() {
+                match self.inner.read(&mut data[len..]) {
+                    Ok(0) => break,
+                    Ok(n) => len += n,
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            data.truncate(len);
+
+            while self.nodes.len() >= self.capacity {
+                self.evict();
+            }
+            let tick = self.next_tick();
+            self.order.insert(tick, index);
+            self.nodes.insert(index, Node { data, tick });
+        }
+        Ok(&self.nodes[&index].data)
+    }
+}
+
+impl<F: Read + Seek> Read for SgxCachedFile<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let offset = (self.pos % NODE_SIZE) as usize;
+        let node = self.load(self.pos / NODE_SIZE)?;
+        if offset >= node.len() {
+            return Ok(0);
+        }
+        let n = buf.len().min(node.len() - offset);
+        buf[..n].copy_from_slice(&node[offset..offset + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<F: Write + Seek> Write for SgxCachedFile<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.seek(SeekFrom::Start(self.pos))?;
+        let n = self.inner.write(buf)?;
+        self.invalidate(self.pos, self.pos + n as u64);
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<F: Seek> Seek for SgxCachedFile<F> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => Some(self.inner.seek(SeekFrom::End(offset))?),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::const_io_error!(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
@@ -0,0 +1,224 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Streaming conversion between plaintext and protected files.
+
+use crate::fs::File;
+use crate::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use crate::path::Path;
+use crate::ptr;
+use crate::sgxfs::{self, OpenOptions, SgxFile};
+use sgx_types::sgx_key_128bit_t;
+
+/// The size of the buffer a conversion copies through.
+pub const SGX_CONVERT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How far a conversion has got, passed to its progress callback after each
+/// chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SgxProgress {
+    /// The number of bytes copied so far.
+    pub copied: u64,
+    /// The number of bytes to copy, if the source's length is known.
+    pub total: Option<u64>,
+}
+
+/// Encrypts everything `reader` yields into a new protected file at `path`,
+/// encrypted with `key`, or the automatically derived key.
+///
+/// The data is copied through a buffer of [`SGX_CONVERT_CHUNK_SIZE`] bytes,
+/// so a dataset of any size is imported in bounded enclave memory. After
+/// each chunk `progress` is called; an error it returns stops the import.
+/// An existing file at `path` is overwritten, and the protected file is
+/// removed if the import fails, so that a partial import is not mistaken
+/// for a complete one. Returns the number of bytes imported.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sgxfs;
+/// use std::untrusted::fs::File;
+///
+/// let plaintext = File::open("dataset.csv")?;
+/// sgxfs::import(plaintext, "dataset.sgx", None, |p| {
+///     println!("{} bytes imported", p.copied);
+///     Ok(())
+/// })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn import<R, P, F>(
+    reader: R,
+    path: P,
+    key: Option<&sgx_key_128bit_t>,
+    mut progress: F,
+) -> io::Result<u64>
+where
+    R: Read,
+    P: AsRef<Path>,
+    F: FnMut(SgxProgress) -> io::Result<()>,
+{
+    import_with_total(reader, None, path.as_ref(), key, &mut progress)
+}
+
+/// Imports the plaintext host file at `from` into a new protected file at
+/// `to`, as [`import`] does. The progress reports the host file's length as
+/// the total.
+pub fn import_file<P, Q, F>(
+    from: P,
+    to: Q,
+    key: Option<&sgx_key_128bit_t>,
+    mut progress: F,
+) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    F: FnMut(SgxProgress) -> io::Result<()>,
+{
+    let file = File::open(from)?;
+    let total = file.metadata()?.len();
+    import_with_total(file, Some(total), to.as_ref(), key, &mut progress)
+}
+
+/// Decrypts the protected file at `path`, opened with `key` or the
+/// automatically derived key, into `writer`, in bounded enclave memory.
+///
+/// After each chunk `progress` is called; an error it returns stops the
+/// export. Returns the number of bytes exported.
+pub fn export<P, W, F>(
+    path: P,
+    key: Option<&sgx_key_128bit_t>,
+    writer: W,
+    mut progress: F,
+) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    W: Write,
+    F: FnMut(SgxProgress) -> io::Result<()>,
+{
+    let mut from = open(path.as_ref(), OpenOptions::new().read(true), key)?;
+    let total = len(&mut from)?;
+    pump(from, writer, Some(total), &mut progress)
+}
+
+/// Exports the protected file at `from` into the plaintext host file at
+/// `to`, as [`export`] does. An existing file at `to` is overwritten.
+pub fn export_file<P, Q, F>(
+    from: P,
+    key: Option<&sgx_key_128bit_t>,
+    to: Q,
+    progress: F,
+) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    F: FnMut(SgxProgress) -> io::Result<()>,
+{
+    export(from, key, File::create(to)?, progress)
+}
+
+/// Copies the protected file at `from`, opened with `from_key`, into a new
+/// protected file at `to`, encrypted with `to_key`. A key of `None` is the
+/// automatically derived key.
+///
+/// The plaintext only passes through enclave memory, a chunk at a time, and
+/// the buffer is wiped when the copy is done. The new file is removed if the
+/// copy fails. To re-encrypt a file under its own name, use [`SgxRekey`]
+/// instead.
+///
+/// [`SgxRekey`]: crate::sgxfs::SgxRekey
+pub fn convert<P, Q, F>(
+    from: P,
+    from_key: Option<&sgx_key_128bit_t>,
+    to: Q,
+    to_key: Option<&sgx_key_128bit_t>,
+    mut progress: F,
+) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    F: FnMut(SgxProgress) -> io::Result<()>,
+{
+    let mut reader = open(from.as_ref(), OpenOptions::new().read(true), from_key)?;
+    let total = len(&mut reader)?;
+    import_with_total(reader, Some(total), to.as_ref(), to_key, &mut progress)
+}
+
+fn import_with_total<R: Read>(
+    reader: R,
+    total: Option<u64>,
+    path: &Path,
+    key: Option<&sgx_key_128bit_t>,
+    progress: &mut dyn FnMut(SgxProgress) -> io::Result<()>,
+) -> io::Result<u64> {
+    let to = open(path, OpenOptions::new().write(true), key)?;
+    match pump(reader, to, total, progress) {
+        Ok(copied) => Ok(copied),
+        Err(e) => {
+            let _ = sgxfs::remove(path);
+            Err(e)
+        }
+    }
+}
+
+fn pump<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    total: Option<u64>,
+    progress: &mut dyn FnMut(SgxProgress) -> io::Result<()>,
+) -> io::Result<u64> {
+    let mut buf = vec![0_u8; SGX_CONVERT_CHUNK_SIZE];
+    let result = (|| {
+        let mut copied = 0_u64;
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            writer.write_all(&buf[..n])?;
+            copied += n as u64;
+            progress(SgxProgress { copied, total })?;
+        }
+        writer.flush()?;
+        Ok(copied)
+    })();
+    wipe(&mut buf);
+    result
+}
+
+fn len(file: &mut SgxFile) -> io::Result<u64> {
+    let len = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(len)
+}
+
+fn open(path: &Path, options: &OpenOptions, key: Option<&sgx_key_128bit_t>) -> io::Result<SgxFile> {
+    match key {
+        Some(key) => options.open_ex(path, key),
+        None => options.open(path),
+    }
+}
+
+// The buffer holds the plaintext of protected files.
+fn wipe(bytes: &mut [u8]) {
+    for b in bytes.iter_mut() {
+        // SAFETY: `b` is a valid, aligned place; the volatile write keeps the
+        // compiler from eliding the wipe of a buffer about to be dropped.
+        unsafe { ptr::write_volatile(b, 0) };
+    }
+}
@@ -0,0 +1,363 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Protected directories, whose listing is kept in the enclave.
+
+use crate::collections::BTreeMap;
+use crate::fmt::{self, Write as _};
+use crate::fs::{self, DirBuilder};
+use crate::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use crate::os::unix::fs::DirBuilderExt;
+use crate::path::{Path, PathBuf};
+use crate::ptr;
+use crate::sgxfs::{OpenOptions, SgxFile};
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_types::sgx_key_128bit_t;
+
+const MAGIC: [u8; 8] = *b"SGXDIR01";
+const INDEX_NAME: &str = ".sgxdir";
+const HOST_NAME_LEN: usize = 32;
+const KEY_LEN: usize = 16;
+const MAX_NAME_LEN: usize = 4096;
+
+struct Entry {
+    host_name: String,
+    key: sgx_key_128bit_t,
+}
+
+/// A host directory whose file names and listing the host cannot see or
+/// change.
+///
+/// The names of the files, and which files exist, are kept in an index: a
+/// protected file named `.sgxdir` in the directory, encrypted with the
+/// automatically derived key or the key given to `create`/`open`. Each file
+/// is a protected file with a random name on the host and a random key of its
+/// own, kept in the index, so a file cannot be swapped with another one or
+/// added to the directory by the host. Creating, renaming and removing files
+/// are checked against the index, and renaming a file only changes the index.
+///
+/// The index is replaced as a whole when it changes, so a crash leaves either
+/// the old or the new listing. The host still sees the number and sizes of
+/// the files, and can roll the whole directory back.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::{Read, Write};
+/// use std::sgxfs::SgxDir;
+///
+/// let mut dir = SgxDir::create("patients", None)?;
+/// dir.create_file("alice.json")?.write_all(b"{}")?;
+/// dir.rename("alice.json", "archived/alice.json")?;
+/// assert_eq!(dir.names(), ["archived/alice.json"]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct SgxDir {
+    path: PathBuf,
+    key: Option<sgx_key_128bit_t>,
+    entries: BTreeMap<String, Entry>,
+}
+
+impl SgxDir {
+    /// Creates a protected directory at `path`, which must not exist, whose
+    /// index is encrypted with `key`, or the automatically derived key.
+    pub fn create<P: AsRef<Path>>(path: P, key: Option<&sgx_key_128bit_t>) -> io::Result<SgxDir> {
+        let path = path.as_ref();
+        DirBuilder::new().mode(0o700).create(path)?;
+        let dir = SgxDir {
+            path: path.to_path_buf(),
+            key: key.copied(),
+            entries: BTreeMap::new(),
+        };
+        dir.store()?;
+        Ok(dir)
+    }
+
+    /// Opens the protected directory at `path`, whose index is encrypted with
+    /// `key`, or the automatically derived key.
+    pub fn open<P: AsRef<Path>>(path: P, key: Option<&sgx_key_128bit_t>) -> io::Result<SgxDir> {
+        let path = path.as_ref();
+        let mut options = OpenOptions::new();
+        options.read(true);
+        let mut index = open(&path.join(INDEX_NAME), &options, key)?;
+        let mut bytes = Vec::new();
+        let entries = index
+            .read_to_end(&mut bytes)
+            .and_then(|_| parse_index(&bytes));
+        wipe(&mut bytes);
+
+        Ok(SgxDir {
+            path: path.to_path_buf(),
+            key: key.copied(),
+            entries: entries?,
+        })
+    }
+
+    /// Returns the path of the directory on the host.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the names of the files, in order.
+    pub fn names(&self) -> Vec<&str> {
+        self.entries.keys().map(String::as_str).collect()
+    }
+
+    /// Returns true if the directory has a file named `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// Returns the length of the file `name`.
+    pub fn file_len(&self, name: &str) -> io::Result<u64> {
+        self.open_file(name)?.seek(SeekFrom::End(0))
+    }
+
+    /// Creates the file `name` in write-only mode.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an `AlreadyExists` error if the directory has a
+    /// file named `name`.
+    pub fn create_file(&mut self, name: &str) -> io::Result<SgxFile> {
+        check_name(name)?;
+        if self.entries.contains_key(name) {
+            return Err(io::const_io_error!(
+                ErrorKind::AlreadyExists,
+                "protected directory entry already exists",
+            ));
+        }
+
+        let mut key = sgx_key_128bit_t::default();
+        rsgx_read_rand(&mut key).map_err(io::Error::from_sgx_error)?;
+        let entry = Entry {
+            host_name: self.host_name()?,
+            key,
+        };
+        let host_path = self.path.join(&entry.host_name);
+        self.entries.insert(name.to_owned(), entry);
+        if let Err(e) = self.store() {
+            self.entries.remove(name);
+            return Err(e);
+        }
+        OpenOptions::new().write(true).open_ex(host_path, &key)
+    }
+
+    /// Opens the file `name` in read-only mode.
+    pub fn open_file(&self, name: &str) -> io::Result<SgxFile> {
+        let mut options = OpenOptions::new();
+        options.read(true);
+        self.open_with_options(name, &options)
+    }
+
+    /// Opens the existing file `name` with the given options.
+    pub fn open_with_options(&self, name: &str, options: &OpenOptions) -> io::Result<SgxFile> {
+        let entry = self.entry(name)?;
+        options.open_ex(self.path.join(&entry.host_name), &entry.key)
+    }
+
+    /// Renames the file `from` to `to`, replacing the file `to` if there is
+    /// one. Only the index changes.
+    pub fn rename(&mut self, from: &str, to: &str) -> io::Result<()> {
+        check_name(to)?;
+        self.entry(from)?;
+        if from == to {
+            return Ok(());
+        }
+
+        let entry = self.entries.remove(from).unwrap();
+        let replaced = self.entries.insert(to.to_owned(), entry);
+        if let Err(e) = self.store() {
+            let entry = self.entries.remove(to).unwrap();
+            self.entries.insert(from.to_owned(), entry);
+            if let Some(replaced) = replaced {
+                self.entries.insert(to.to_owned(), replaced);
+            }
+            return Err(e);
+        }
+        if let Some(replaced) = replaced {
+            let _ = fs::remove_file(self.path.join(&replaced.host_name));
+        }
+        Ok(())
+    }
+
+    /// Removes the file `name`.
+    pub fn remove(&mut self, name: &str) -> io::Result<()> {
+        self.entry(name)?;
+        let entry = self.entries.remove(name).unwrap();
+        if let Err(e) = self.store() {
+            self.entries.insert(name.to_owned(), entry);
+            return Err(e);
+        }
+        match fs::remove_file(self.path.join(&entry.host_name)) {
+            Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    fn entry(&self, name: &str) -> io::Result<&Entry> {
+        self.entries.get(name).ok_or_else(|| {
+            io::const_io_error!(ErrorKind::NotFound, "protected directory entry not found")
+        })
+    }
+
+    fn host_name(&self) -> io::Result<String> {
+        loop {
+            let name = random_hex(HOST_NAME_LEN / 2)?;
+            if !self.entries.values().any(|e| e.host_name == name) {
+                return Ok(name);
+            }
+        }
+    }
+
+    // Writes the index in a fresh directory, since a protected file keeps its
+    // name, and moves it over the current one.
+    fn store(&self) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for (name, entry) in self.entries.iter() {
+            bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(entry.host_name.as_bytes());
+            bytes.extend_from_slice(&entry.key);
+        }
+
+        let staging = self.path.join(format!(".sgxdir-{}", random_hex(8)?));
+        DirBuilder::new().mode(0o700).create(&staging)?;
+        let staged = staging.join(INDEX_NAME);
+        let result = (|| {
+            let mut options = OpenOptions::new();
+            options.write(true);
+            let mut index = open(&staged, &options, self.key.as_ref())?;
+            index.write_all(&bytes)?;
+            index.flush()?;
+            drop(index);
+            fs::rename(&staged, self.path.join(INDEX_NAME))
+        })();
+
+        wipe(&mut bytes);
+        if result.is_err() {
+            let _ = fs::remove_file(&staged);
+        }
+        let _ = fs::remove_dir(&staging);
+        result
+    }
+}
+
+impl Drop for SgxDir {
+    fn drop(&mut self) {
+        for entry in self.entries.values_mut() {
+            // SAFETY: `key` is a valid, aligned place; the volatile write
+            // keeps the compiler from eliding the wipe of a value about to be
+            // dropped.
+            unsafe { ptr::write_volatile(&mut entry.key, sgx_key_128bit_t::default()) };
+        }
+    }
+}
+
+impl fmt::Debug for SgxDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxDir")
+            .field("path", &self.path)
+            .field("entries", &self.entries.len())
+            .finish_non_exhaustive()
+    }
+}
+
+fn parse_index(bytes: &[u8]) -> io::Result<BTreeMap<String, Entry>> {
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+        if bytes.len() < len {
+            return Err(io::const_io_error!(
+                ErrorKind::InvalidData,
+                "protected directory index is truncated",
+            ));
+        }
+        let (head, tail) = bytes.split_at(len);
+        *bytes = tail;
+        Ok(head)
+    }
+    fn take_u32(bytes: &mut &[u8]) -> io::Result<usize> {
+        let mut word = [0_u8; 4];
+        word.copy_from_slice(take(bytes, 4)?);
+        Ok(u32::from_le_bytes(word) as usize)
+    }
+    let invalid =
+        || io::const_io_error!(ErrorKind::InvalidData, "invalid protected directory index");
+
+    let mut bytes = bytes;
+    if take(&mut bytes, MAGIC.len())? != MAGIC {
+        return Err(invalid());
+    }
+    let count = take_u32(&mut bytes)?;
+    let mut entries = BTreeMap::new();
+    for _ in 0..count {
+        let len = take_u32(&mut bytes)?;
+        let name = String::from_utf8(take(&mut bytes, len)?.to_vec()).map_err(|_| invalid())?;
+        let host_name =
+            String::from_utf8(take(&mut bytes, HOST_NAME_LEN)?.to_vec()).map_err(|_| invalid())?;
+        if !host_name.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let mut key = sgx_key_128bit_t::default();
+        key.copy_from_slice(take(&mut bytes, KEY_LEN)?);
+        entries.insert(name, Entry { host_name, key });
+    }
+    if !bytes.is_empty() {
+        return Err(invalid());
+    }
+    Ok(entries)
+}
+
+// Names stay in the enclave, so they may hold any character, but they must
+// not be empty and must fit the index.
+fn check_name(name: &str) -> io::Result<()> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        Err(io::const_io_error!(
+            ErrorKind::InvalidInput,
+            "invalid protected directory entry name",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+// The index holds the keys of the files.
+fn wipe(bytes: &mut [u8]) {
+    for b in bytes.iter_mut() {
+        // SAFETY: `b` is a valid, aligned place; the volatile write keeps the
+        // compiler from eliding the wipe of a buffer about to be dropped.
+        unsafe { ptr::write_volatile(b, 0) };
+    }
+}
+
+fn random_hex(len: usize) -> io::Result<String> {
+    let mut bytes = vec![0_u8; len];
+    rsgx_read_rand(&mut bytes).map_err(io::Error::from_sgx_error)?;
+    let mut hex = String::with_capacity(len * 2);
+    for b in bytes {
+        write!(hex, "{:02x}", b).unwrap();
+    }
+    Ok(hex)
+}
+
+fn open(path: &Path, options: &OpenOptions, key: Option<&sgx_key_128bit_t>) -> io::Result<SgxFile> {
+    match key {
+        Some(key) => options.open_ex(path, key),
+        None => options.open(path),
+    }
+}
@@ -0,0 +1,428 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Protected files whose contents stay in clear text.
+
+use crate::ffi::OsString;
+use crate::fmt;
+use crate::fs;
+use crate::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use crate::path::{Path, PathBuf};
+use crate::ptr;
+use crate::sgxfs::{OpenOptions, SgxFile};
+use sgx_libc as libc;
+use sgx_trts::memeq::ConsttimeMemEq;
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_types::{
+    sgx_cmac_128bit_key_t, sgx_cmac_128bit_tag_t, sgx_key_128bit_t, sgx_rijndael128_cmac_msg,
+    sgx_status_t,
+};
+
+const MAGIC: [u8; 8] = *b"SGXIMAC1";
+const NODE_SIZE: usize = 4096;
+const MAC_SIZE: u64 = 16;
+const LEN_OFFSET: u64 = 8;
+const HEADER_SIZE: u64 = 32;
+
+/// A file whose contents are in clear text on the host, but tamper-evident.
+///
+/// The contents are stored as they are in the file at the given path, so the
+/// host and other programs can read them. Every 4 KiB node of the file is
+/// authenticated by a CMAC, under a random key drawn when the file is created.
+/// The key, the length of the file and the MACs are kept in a protected file
+/// next to it, whose name has `.mac` appended. That protected file is
+/// encrypted with the automatically derived key, or with the key given to
+/// `open_ex`/`create_ex`, so the integrity of the contents is rooted in the
+/// enclave key.
+///
+/// Each node is verified when it is read, and a node that was modified,
+/// moved, truncated or replaced fails with `SGX_ERROR_MAC_MISMATCH`. The
+/// contents are not confidential. A write updates the contents before the
+/// MAC, so a crash between the two leaves a node that fails verification.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::{Read, Write};
+/// use std::sgxfs::IntegrityFile;
+///
+/// IntegrityFile::create("dataset.csv")?.write_all(b"id,value\n1,42\n")?;
+///
+/// let mut contents = String::new();
+/// IntegrityFile::open("dataset.csv")?.read_to_string(&mut contents)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct IntegrityFile {
+    data: fs::File,
+    macs: SgxFile,
+    key: sgx_cmac_128bit_key_t,
+    len: u64,
+    pos: u64,
+    writable: bool,
+    node: Option<(u64, Vec<u8>)>,
+}
+
+impl IntegrityFile {
+    /// Opens a file in read-only mode.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<IntegrityFile> {
+        IntegrityFile::_open(path.as_ref(), None, false)
+    }
+
+    /// Opens a file in read-write mode.
+    pub fn open_rw<P: AsRef<Path>>(path: P) -> io::Result<IntegrityFile> {
+        IntegrityFile::_open(path.as_ref(), None, true)
+    }
+
+    /// Creates a file in read-write mode, truncating it if it exists.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<IntegrityFile> {
+        IntegrityFile::_create(path.as_ref(), None)
+    }
+
+    /// Opens a file in read-only mode, whose MACs are protected by `key`.
+    pub fn open_ex<P: AsRef<Path>>(path: P, key: &sgx_key_128bit_t) -> io::Result<IntegrityFile> {
+        IntegrityFile::_open(path.as_ref(), Some(key), false)
+    }
+
+    /// Opens a file in read-write mode, whose MACs are protected by `key`.
+    pub fn open_rw_ex<P: AsRef<Path>>(
+        path: P,
+        key: &sgx_key_128bit_t,
+    ) -> io::Result<IntegrityFile> {
+        IntegrityFile::_open(path.as_ref(), Some(key), true)
+    }
+
+    /// Creates a file in read-write mode, whose MACs are protected by `key`.
+    pub fn create_ex<P: AsRef<Path>>(path: P, key: &sgx_key_128bit_t) -> io::Result<IntegrityFile> {
+        IntegrityFile::_create(path.as_ref(), Some(key))
+    }
+
+    /// Returns the path of the protected file holding the MACs of `path`.
+    pub fn mac_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut name = OsString::from(path.as_ref().as_os_str());
+        name.push(".mac");
+        PathBuf::from(name)
+    }
+
+    /// Removes a file and the protected file holding its MACs.
+    pub fn remove<P: AsRef<Path>>(path: P) -> io::Result<()> {
+        crate::sgxfs::remove(IntegrityFile::mac_path(path.as_ref()))?;
+        fs::remove_file(path)
+    }
+
+    /// Returns the length of the file.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Truncates or extends the file to `size` bytes, in place. The position
+    /// of the file does not change.
+    ///
+    /// Truncating the file only authenticates its new last node again.
+    /// Extending it fills it with zeros, whose nodes are authenticated as
+    /// they are written.
+    pub fn set_len(&mut self, size: u64) -> io::Result<()> {
+        if !self.writable {
+            return Err(io::Error::from_raw_os_error(libc::EBADF));
+        }
+        if size >= self.len {
+            let pos = self.pos;
+            self.pos = size;
+            let result = self.write(&[]);
+            self.pos = pos;
+            return result.map(|_| ());
+        }
+
+        let index = size / NODE_SIZE as u64;
+        let offset = (size % NODE_SIZE as u64) as usize;
+        let last = if offset > 0 {
+            self.load_node(index)?;
+            self.node.take().map(|(_, mut node)| {
+                node.truncate(offset);
+                node
+            })
+        } else {
+            None
+        };
+
+        self.node = None;
+        self.len = size;
+        self.macs.seek(SeekFrom::Start(LEN_OFFSET))?;
+        self.macs.write_all(&self.len.to_le_bytes())?;
+        if let Some(node) = last {
+            self.store_node(index, node)?;
+        }
+        self.data.set_len(size)
+    }
+
+    /// Verifies every node of the file.
+    pub fn verify(&mut self) -> io::Result<()> {
+        for index in 0..(self.len + NODE_SIZE as u64 - 1) / NODE_SIZE as u64 {
+            self.load_node(index)?;
+        }
+        Ok(())
+    }
+
+    fn _open(
+        path: &Path,
+        key: Option<&sgx_key_128bit_t>,
+        writable: bool,
+    ) -> io::Result<IntegrityFile> {
+        let data = fs::OpenOptions::new()
+            .read(true)
+            .write(writable)
+            .open(path)?;
+        let mut macs = OpenOptions::new().read(true).update(writable).open_with(
+            IntegrityFile::mac_path(path),
+            key,
+            None,
+        )?;
+
+        let mut header = [0_u8; HEADER_SIZE as usize];
+        macs.read_exact(&mut header).map_err(|_| mac_mismatch())?;
+        if header[..8] != MAGIC {
+            return Err(io::const_io_error!(
+                ErrorKind::InvalidData,
+                "not the MAC file of an integrity-protected file",
+            ));
+        }
+        let mut len = [0_u8; 8];
+        len.copy_from_slice(&header[8..16]);
+        let mut mac_key = sgx_cmac_128bit_key_t::default();
+        mac_key.copy_from_slice(&header[16..32]);
+
+        Ok(IntegrityFile {
+            data,
+            macs,
+            key: mac_key,
+            len: u64::from_le_bytes(len),
+            pos: 0,
+            writable,
+            node: None,
+        })
+    }
+
+    fn _create(path: &Path, key: Option<&sgx_key_128bit_t>) -> io::Result<IntegrityFile> {
+        let mut mac_key = sgx_cmac_128bit_key_t::default();
+        rsgx_read_rand(&mut mac_key).map_err(io::Error::from_sgx_error)?;
+
+        let data = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let mut macs = OpenOptions::new().write(true).update(true).open_with(
+            IntegrityFile::mac_path(path),
+            key,
+            None,
+        )?;
+
+        let mut header = [0_u8; HEADER_SIZE as usize];
+        header[..8].copy_from_slice(&MAGIC);
+        header[16..32].copy_from_slice(&mac_key);
+        macs.write_all(&header)?;
+
+        Ok(IntegrityFile {
+            data,
+            macs,
+            key: mac_key,
+            len: 0,
+            pos: 0,
+            writable: true,
+            node: None,
+        })
+    }
+
+    fn node_mac(&self, index: u64, node: &[u8]) -> io::Result<sgx_cmac_128bit_tag_t> {
+        // The index is authenticated with the node so that nodes cannot be
+        // swapped.
+        let mut message = Vec::with_capacity(8 + node.len());
+        message.extend_from_slice(&index.to_le_bytes());
+        message.extend_from_slice(node);
+
+        let mut mac = sgx_cmac_128bit_tag_t::default();
+        let status = unsafe {
+            sgx_rijndael128_cmac_msg(&self.key, message.as_ptr(), message.len() as u32, &mut mac)
+        };
+        match status {
+            sgx_status_t::SGX_SUCCESS => Ok(mac),
+            _ => Err(io::Error::from_sgx_error(status)),
+        }
+    }
+
+    fn load_node(&mut self, index: u64) -> io::Result<()> {
+        if matches!(self.node, Some((cached, _)) if cached == index) {
+            return Ok(());
+        }
+        self.node = None;
+
+        let start = index * NODE_SIZE as u64;
+        let size = (self.len - start).min(NODE_SIZE as u64) as usize;
+        let mut node = vec![0_u8; size];
+        self.data.seek(SeekFrom::Start(start))?;
+        self.data
+            .read_exact(&mut node)
+            .map_err(|e| match e.kind() {
+                ErrorKind::UnexpectedEof => mac_mismatch(),
+                _ => e,
+            })?;
+
+        let mut stored = sgx_cmac_128bit_tag_t::default();
+        self.macs
+            .seek(SeekFrom::Start(HEADER_SIZE + index * MAC_SIZE))?;
+        self.macs
+            .read_exact(&mut stored)
+            .map_err(|_| mac_mismatch())?;
+        if !self.node_mac(index, &node)?.consttime_memeq(&stored) {
+            return Err(mac_mismatch());
+        }
+
+        self.node = Some((index, node));
+        Ok(())
+    }
+
+    fn store_node(&mut self, index: u64, node: Vec<u8>) -> io::Result<()> {
+        self.node = None;
+        let mac = self.node_mac(index, &node)?;
+        self.data.seek(SeekFrom::Start(index * NODE_SIZE as u64))?;
+        self.data.write_all(&node)?;
+        self.macs
+            .seek(SeekFrom::Start(HEADER_SIZE + index * MAC_SIZE))?;
+        self.macs.write_all(&mac)?;
+        self.node = Some((index, node));
+        Ok(())
+    }
+
+    // Writes at `pos`, which is at most the length of the file, within one
+    // node.
+    fn write_node(&mut self, pos: u64, buf: &[u8]) -> io::Result<usize> {
+        let index = pos / NODE_SIZE as u64;
+        let offset = (pos % NODE_SIZE as u64) as usize;
+        let mut node = if index * (NODE_SIZE as u64) < self.len {
+            self.load_node(index)?;
+            self.node.take().map(|(_, node)| node).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let n = buf.len().min(NODE_SIZE - offset);
+        if node.len() < offset + n {
+            node.resize(offset + n, 0);
+        }
+        node[offset..offset + n].copy_from_slice(&buf[..n]);
+        self.store_node(index, node)?;
+
+        if pos + n as u64 > self.len {
+            self.len = pos + n as u64;
+            self.macs.seek(SeekFrom::Start(LEN_OFFSET))?;
+            self.macs.write_all(&self.len.to_le_bytes())?;
+        }
+        Ok(n)
+    }
+}
+
+impl Read for IntegrityFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let index = self.pos / NODE_SIZE as u64;
+        let offset = (self.pos % NODE_SIZE as u64) as usize;
+        self.load_node(index)?;
+
+        let node = match self.node {
+            Some((_, ref node)) => node,
+            None => return Err(mac_mismatch()),
+        };
+        let n = buf.len().min(node.len() - offset);
+        buf[..n].copy_from_slice(&node[offset..offset + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for IntegrityFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.writable {
+            return Err(io::Error::from_raw_os_error(libc::EBADF));
+        }
+        // Writing past the end fills the gap with zeros.
+        while self.len < self.pos {
+            let zeros = [0_u8; NODE_SIZE];
+            let gap = (self.pos - self.len).min(NODE_SIZE as u64) as usize;
+            self.write_node(self.len, &zeros[..gap])?;
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let n = self.write_node(self.pos, buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.data.flush()?;
+        self.macs.flush()
+    }
+}
+
+impl Seek for IntegrityFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::const_io_error!(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+impl Drop for IntegrityFile {
+    fn drop(&mut self) {
+        // SAFETY: `key` is a valid, aligned place; the volatile write keeps
+        // the compiler from eliding the wipe of a value about to be dropped.
+        unsafe { ptr::write_volatile(&mut self.key, sgx_cmac_128bit_key_t::default()) };
+    }
+}
+
+impl fmt::Debug for IntegrityFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntegrityFile")
+            .field("len", &self.len)
+            .field("pos", &self.pos)
+            .field("writable", &self.writable)
+            .finish_non_exhaustive()
+    }
+}
+
+fn mac_mismatch() -> io::Error {
+    io::Error::from_sgx_error(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+}
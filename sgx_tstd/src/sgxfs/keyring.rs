@@ -0,0 +1,252 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Protected files encrypted with provisioned keys, tagged with a key id.
+
+use crate::ffi::OsString;
+use crate::fs;
+use crate::io::{self, ErrorKind};
+use crate::path::{Path, PathBuf};
+use crate::ptr;
+use crate::sgxfs::{OpenOptions, SgxFile};
+use crate::sync::SgxMutex;
+use sgx_types::{
+    sgx_aes_gcm_128bit_key_t, sgx_key_128bit_t, sgx_rijndael128GCM_decrypt, sgx_status_t,
+};
+
+/// The id of a key in the enclave's key ring.
+pub type SgxKeyId = [u8; 16];
+
+/// The length of a wrapped key: a 12-byte IV, the 16-byte encrypted key and
+/// the 16-byte GCM tag.
+pub const SGX_WRAPPED_KEY_LEN: usize = 44;
+
+const MAGIC: [u8; 8] = *b"SGXKEYID";
+const TAG_LEN: usize = 24;
+const IV_LEN: usize = 12;
+const KEY_LEN: usize = 16;
+
+// Keys are looked up by id, and there are few of them.
+static KEYRING: SgxMutex<Vec<(SgxKeyId, sgx_key_128bit_t)>> = SgxMutex::new(Vec::new());
+
+/// Adds a key to the enclave's key ring under `key_id`, replacing any key
+/// with the same id.
+///
+/// Keys in the ring are used by [`SgxFile::open_with_key_id`] and
+/// [`OpenOptions::open_with_key_id`] instead of the automatically derived
+/// sealing key, so that files can be shared by enclaves on different
+/// machines that were given the same key. The key should come from a
+/// provisioning service over an attested channel; see
+/// [`provision_wrapped_key`].
+pub fn provision_key(key_id: &SgxKeyId, key: &sgx_key_128bit_t) {
+    let mut ring = KEYRING.lock().unwrap_or_else(|e| e.into_inner());
+    match ring.iter_mut().find(|(id, _)| id == key_id) {
+        Some((_, old)) => {
+            wipe(old);
+            *old = *key;
+        }
+        None => ring.push((*key_id, *key)),
+    }
+}
+
+/// Unwraps a key sent by a provisioning service and adds it to the key ring
+/// under `key_id`.
+///
+/// `session_key` is a key shared with the service over an attested channel,
+/// such as the SK of a remote attestation session or the AEK of a local one,
+/// so that the service only sends keys to the enclaves it has checked
+/// against its allowlist. `wrapped` is the key encrypted with AES-GCM under
+/// `session_key`, with `key_id` as the additional data: the 12-byte IV, then
+/// the encrypted key, then the tag. A key wrapped for one id cannot be added
+/// under another.
+pub fn provision_wrapped_key(
+    key_id: &SgxKeyId,
+    wrapped: &[u8],
+    session_key: &sgx_aes_gcm_128bit_key_t,
+) -> io::Result<()> {
+    if wrapped.len() != SGX_WRAPPED_KEY_LEN {
+        return Err(io::const_io_error!(
+            ErrorKind::InvalidInput,
+            "wrapped key has the wrong length"
+        ));
+    }
+    let (iv, rest) = wrapped.split_at(IV_LEN);
+    let (ciphertext, mac) = rest.split_at(KEY_LEN);
+    let mut tag = [0_u8; 16];
+    tag.copy_from_slice(mac);
+    let mut key: sgx_key_128bit_t = [0; KEY_LEN];
+    // SAFETY: every pointer refers to a live buffer of the length passed with it.
+    let status = unsafe {
+        sgx_rijndael128GCM_decrypt(
+            session_key,
+            ciphertext.as_ptr(),
+            KEY_LEN as u32,
+            key.as_mut_ptr(),
+            iv.as_ptr(),
+            IV_LEN as u32,
+            key_id.as_ptr(),
+            key_id.len() as u32,
+            &tag,
+        )
+    };
+    if status != sgx_status_t::SGX_SUCCESS {
+        wipe(&mut key);
+        return Err(io::Error::from_sgx_error(status));
+    }
+    provision_key(key_id, &key);
+    wipe(&mut key);
+    Ok(())
+}
+
+/// Removes a key from the key ring, returning whether it was there.
+///
+/// Files already opened with the key stay open.
+pub fn revoke_key(key_id: &SgxKeyId) -> bool {
+    let mut ring = KEYRING.lock().unwrap_or_else(|e| e.into_inner());
+    match ring.iter().position(|(id, _)| id == key_id) {
+        Some(i) => {
+            let (_, mut key) = ring.swap_remove(i);
+            wipe(&mut key);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Returns whether the key ring holds a key with this id.
+pub fn has_key(key_id: &SgxKeyId) -> bool {
+    KEYRING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .any(|(id, _)| id == key_id)
+}
+
+/// Returns the id of the key a protected file was created with by
+/// [`SgxFile::create_with_key_id`] or [`OpenOptions::open_with_key_id`].
+///
+/// The protected file's own header belongs to the protected file library and
+/// has no room for it, so the id is kept in a tag file next to it, named
+/// after the file with `.keyid` appended. The tag is not secret. If the host
+/// changes it, the file does not open, since it was encrypted with another key.
+pub fn key_id<P: AsRef<Path>>(path: P) -> io::Result<SgxKeyId> {
+    let tag = fs::read(key_id_path(path))?;
+    if tag.len() != TAG_LEN || tag[..8] != MAGIC {
+        return Err(io::const_io_error!(
+            ErrorKind::InvalidData,
+            "not a key id tag"
+        ));
+    }
+    let mut key_id = [0_u8; 16];
+    key_id.copy_from_slice(&tag[8..]);
+    Ok(key_id)
+}
+
+/// Returns the path of the tag file holding the key id of a protected file.
+pub fn key_id_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut name = OsString::from(path.as_ref().as_os_str());
+    name.push(".keyid");
+    PathBuf::from(name)
+}
+
+impl SgxFile {
+    /// Opens a protected file in read-only mode, with the key from the key
+    /// ring named by its key id tag.
+    ///
+    /// Fails with [`ErrorKind::PermissionDenied`] if the key ring has no key
+    /// with that id.
+    pub fn open_with_key_id<P: AsRef<Path>>(path: P) -> io::Result<SgxFile> {
+        let path = path.as_ref();
+        let key_id = key_id(path)?;
+        OpenOptions::new()
+            .read(true)
+            .open_with_key_id(path, &key_id)
+    }
+
+    /// Creates a protected file in write-only mode, encrypted with the key
+    /// from the key ring with id `key_id`, and tags it with the id.
+    pub fn create_with_key_id<P: AsRef<Path>>(path: P, key_id: &SgxKeyId) -> io::Result<SgxFile> {
+        OpenOptions::new()
+            .write(true)
+            .open_with_key_id(path, key_id)
+    }
+}
+
+impl OpenOptions {
+    /// Opens a protected file with these options, with the key from the key
+    /// ring with id `key_id`.
+    ///
+    /// A file opened for writing is tagged with `key_id`. An existing file
+    /// that is tagged with another key id is not opened; the error is of
+    /// kind [`ErrorKind::InvalidData`].
+    pub fn open_with_key_id<P: AsRef<Path>>(
+        &self,
+        path: P,
+        key_id: &SgxKeyId,
+    ) -> io::Result<SgxFile> {
+        let path = path.as_ref();
+        let mut key = lookup(key_id)?;
+        let result = self.open_tagged(path, key_id, &key);
+        wipe(&mut key);
+        result
+    }
+
+    fn open_tagged(
+        &self,
+        path: &Path,
+        key_id: &SgxKeyId,
+        key: &sgx_key_128bit_t,
+    ) -> io::Result<SgxFile> {
+        match crate::sgxfs::key_id(path) {
+            Ok(tagged) if tagged != *key_id => {
+                return Err(io::const_io_error!(
+                    ErrorKind::InvalidData,
+                    "file is tagged with another key id",
+                ));
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound && self.0.creates() => {
+                let mut tag = [0_u8; TAG_LEN];
+                tag[..8].copy_from_slice(&MAGIC);
+                tag[8..].copy_from_slice(key_id);
+                fs::write(key_id_path(path), tag)?;
+            }
+            Err(e) => return Err(e),
+        }
+        self.open_ex(path, key)
+    }
+}
+
+fn lookup(key_id: &SgxKeyId) -> io::Result<sgx_key_128bit_t> {
+    let ring = KEYRING.lock().unwrap_or_else(|e| e.into_inner());
+    match ring.iter().find(|(id, _)| id == key_id) {
+        Some((_, key)) => Ok(*key),
+        None => Err(io::const_io_error!(
+            ErrorKind::PermissionDenied,
+            "no key with this id was provisioned"
+        )),
+    }
+}
+
+// The key ring holds the keys of shared files.
+fn wipe(bytes: &mut [u8]) {
+    for b in bytes.iter_mut() {
+        // SAFETY: `b` is a valid, aligned place; the volatile write keeps the
+        // compiler from eliding the wipe of a key about to be dropped.
+        unsafe { ptr::write_volatile(b, 0) };
+    }
+}
@@ -0,0 +1,231 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Re-encryption of protected files under a new key.
+
+use crate::fmt::{self, Write as _};
+use crate::fs::{self, DirBuilder};
+use crate::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use crate::mem;
+use crate::os::unix::fs::DirBuilderExt;
+use crate::path::{Path, PathBuf};
+use crate::sgxfs::{OpenOptions, SgxFile};
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_types::sgx_key_128bit_t;
+
+const REKEY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Re-encrypts a protected file under a new key, a chunk at a time.
+///
+/// The contents are copied into a new protected file, encrypted with the new
+/// key derivation key, or with a newly derived automatic key when none is
+/// given. The copy is made in a hidden directory next to the file, since a
+/// protected file keeps its name, and replaces the file when it is complete.
+/// Until then the file is still readable with its old key.
+///
+/// [`step`](SgxRekey::step) copies a bounded amount at a time, so that an
+/// enclave can spread the work of a large file between its other requests.
+/// The file must not be written while it is being re-encrypted.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sgxfs::SgxRekey;
+///
+/// # let (old_key, new_key) = ([1_u8; 16], [2_u8; 16]);
+/// # fn serve_next_request() {}
+/// let mut rekey = SgxRekey::new("records", Some(&old_key), Some(&new_key))?;
+/// while !rekey.step(1024 * 1024)? {
+///     serve_next_request();
+/// }
+/// rekey.finish()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct SgxRekey {
+    from: SgxFile,
+    to: SgxFile,
+    path: PathBuf,
+    dir: PathBuf,
+    len: u64,
+    copied: u64,
+}
+
+impl SgxRekey {
+    /// Starts re-encrypting the protected file at `path`, opened with
+    /// `old_key`, under `new_key`. A key of `None` is the automatically
+    /// derived key.
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        old_key: Option<&sgx_key_128bit_t>,
+        new_key: Option<&sgx_key_128bit_t>,
+    ) -> io::Result<SgxRekey> {
+        let path = path.as_ref();
+        let name = path.file_name().ok_or_else(|| {
+            io::const_io_error!(ErrorKind::InvalidInput, "path does not name a file")
+        })?;
+
+        let mut from = open(path, OpenOptions::new().read(true), old_key)?;
+        let len = from.seek(SeekFrom::End(0))?;
+        from.seek(SeekFrom::Start(0))?;
+
+        let dir = create_dir(path.parent().unwrap_or_else(|| Path::new("")))?;
+        let to = match open(&dir.join(name), OpenOptions::new().write(true), new_key) {
+            Ok(to) => to,
+            Err(e) => {
+                let _ = fs::remove_dir(&dir);
+                return Err(e);
+            }
+        };
+
+        Ok(SgxRekey {
+            from,
+            to,
+            path: path.to_path_buf(),
+            dir,
+            len,
+            copied: 0,
+        })
+    }
+
+    /// Re-encrypts up to `max_bytes` more of the file. Returns true once the
+    /// whole file is re-encrypted.
+    pub fn step(&mut self, max_bytes: usize) -> io::Result<bool> {
+        let mut buf = vec![0_u8; max_bytes.clamp(1, REKEY_CHUNK_SIZE)];
+        let mut remaining = max_bytes.max(1);
+        while remaining > 0 && self.copied < self.len {
+            let want = buf.len().min(remaining);
+            let n = self.from.read(&mut buf[..want])?;
+            if n == 0 {
+                return Err(io::const_io_error!(
+                    ErrorKind::UnexpectedEof,
+                    "protected file shrank while it was re-encrypted",
+                ));
+            }
+            self.to.write_all(&buf[..n])?;
+            self.copied += n as u64;
+            remaining -= n;
+        }
+        Ok(self.copied >= self.len)
+    }
+
+    // Copies only the first `len` bytes of the file.
+    pub(super) fn truncate(&mut self, len: u64) {
+        self.len = self.len.min(len);
+    }
+
+    /// Returns the number of bytes re-encrypted so far.
+    pub fn copied(&self) -> u64 {
+        self.copied
+    }
+
+    /// Returns the length of the file.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Re-encrypts the rest of the file, and replaces the file with the
+    /// re-encrypted one.
+    pub fn finish(mut self) -> io::Result<()> {
+        while !self.step(REKEY_CHUNK_SIZE)? {}
+        self.to.flush()?;
+
+        // Close both files before the rename, and keep Drop from removing
+        // the copy.
+        let dir = mem::take(&mut self.dir);
+        let path = mem::take(&mut self.path);
+        drop(self);
+
+        let name = dir.join(path.file_name().unwrap_or_default());
+        let result = fs::rename(&name, &path);
+        if result.is_err() {
+            let _ = fs::remove_file(&name);
+        }
+        let _ = fs::remove_dir(&dir);
+        result
+    }
+
+    /// Abandons the re-encryption. The file keeps its old key.
+    pub fn cancel(self) {}
+}
+
+impl Drop for SgxRekey {
+    fn drop(&mut self) {
+        if !self.dir.as_os_str().is_empty() {
+            if let Some(name) = self.path.file_name() {
+                let _ = fs::remove_file(self.dir.join(name));
+            }
+            let _ = fs::remove_dir(&self.dir);
+        }
+    }
+}
+
+impl fmt::Debug for SgxRekey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxRekey")
+            .field("path", &self.path)
+            .field("len", &self.len)
+            .field("copied", &self.copied)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Re-encrypts the protected file at `path` under `new_key` at once.
+///
+/// See [`SgxRekey`] to spread the work over time.
+pub fn rekey<P: AsRef<Path>>(
+    path: P,
+    old_key: Option<&sgx_key_128bit_t>,
+    new_key: Option<&sgx_key_128bit_t>,
+) -> io::Result<()> {
+    SgxRekey::new(path, old_key, new_key)?.finish()
+}
+
+fn open(path: &Path, options: &OpenOptions, key: Option<&sgx_key_128bit_t>) -> io::Result<SgxFile> {
+    match key {
+        Some(key) => options.open_ex(path, key),
+        None => options.open(path),
+    }
+}
+
+fn create_dir(parent: &Path) -> io::Result<PathBuf> {
+    const ATTEMPTS: u32 = 16;
+
+    for _ in 0..ATTEMPTS {
+        let mut suffix = [0_u8; 8];
+        rsgx_read_rand(&mut suffix).map_err(io::Error::from_sgx_error)?;
+        let mut name = String::from(".sgxrekey-");
+        for b in suffix {
+            write!(name, "{:02x}", b).unwrap();
+        }
+
+        let dir = parent.join(name);
+        match DirBuilder::new().mode(0o700).create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(ref e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::const_io_error!(
+        ErrorKind::AlreadyExists,
+        "too many re-encryption directories already exist",
+    ))
+}
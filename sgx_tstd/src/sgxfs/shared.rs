@@ -0,0 +1,266 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Protected files shared by concurrent readers.
+
+use crate::fmt;
+use crate::io::{self, BufRead, ErrorKind, Read, Seek, SeekFrom};
+use crate::path::Path;
+use crate::sgxfs::{OpenOptions, SgxFile};
+use crate::sync::{Arc, SgxMutex};
+use sgx_types::sgx_key_128bit_t;
+
+// The size of a node of a protected file.
+const READER_BUF_SIZE: usize = 4096;
+
+// SAFETY: the protected file library serializes the operations on a file
+// with a lock of its own, so a handle can be used from any thread.
+struct Stream(SgxFile);
+
+unsafe impl Send for Stream {}
+
+struct Shared {
+    stream: SgxMutex<Stream>,
+    len: u64,
+}
+
+/// A protected file opened once for many concurrent readers.
+///
+/// The file is opened read-only, so its nodes are never updated and the
+/// protected file library holds a shared lock on the host file: the file
+/// cannot be opened for writing, from this enclave or another one, until
+/// every handle is dropped. The readers share one handle and its node cache,
+/// and each [`SgxSharedReader`] has its own position. A read takes the
+/// handle only for the time of the read.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::Read;
+/// use std::sgxfs::SgxSharedFile;
+/// use std::thread;
+///
+/// let file = SgxSharedFile::open("dataset")?;
+/// let scans: Vec<_> = (0..4)
+///     .map(|_| {
+///         let mut reader = file.reader();
+///         thread::spawn(move || {
+///             let mut contents = Vec::new();
+///             reader.read_to_end(&mut contents).map(|_| contents)
+///         })
+///     })
+///     .collect();
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct SgxSharedFile {
+    shared: Arc<Shared>,
+}
+
+impl SgxSharedFile {
+    /// Opens a protected file with the automatically derived key.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<SgxSharedFile> {
+        SgxSharedFile::from_file(OpenOptions::new().read(true).open(path)?)
+    }
+
+    /// Opens a protected file with `key`.
+    pub fn open_ex<P: AsRef<Path>>(path: P, key: &sgx_key_128bit_t) -> io::Result<SgxSharedFile> {
+        SgxSharedFile::from_file(OpenOptions::new().read(true).open_ex(path, key)?)
+    }
+
+    /// Opens a protected file with `key`, or the automatically derived key,
+    /// and a node cache of `cache_size` bytes.
+    pub fn open_with<P: AsRef<Path>>(
+        path: P,
+        key: Option<&sgx_key_128bit_t>,
+        cache_size: Option<u64>,
+    ) -> io::Result<SgxSharedFile> {
+        SgxSharedFile::from_file(
+            OpenOptions::new()
+                .read(true)
+                .open_with(path, key, cache_size)?,
+        )
+    }
+
+    fn from_file(mut file: SgxFile) -> io::Result<SgxSharedFile> {
+        let len = file.seek(SeekFrom::End(0))?;
+        Ok(SgxSharedFile {
+            shared: Arc::new(Shared {
+                stream: SgxMutex::new(Stream(file)),
+                len,
+            }),
+        })
+    }
+
+    /// Returns the length of the file.
+    pub fn len(&self) -> u64 {
+        self.shared.len
+    }
+
+    /// Returns true if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.shared.len == 0
+    }
+
+    /// Returns a reader positioned at the start of the file.
+    pub fn reader(&self) -> SgxSharedReader {
+        SgxSharedReader {
+            file: self.clone(),
+            pos: 0,
+            buf: Vec::new(),
+            filled: 0,
+            consumed: 0,
+        }
+    }
+
+    /// Reads from the file at `offset`, without moving any reader. Returns
+    /// the number of bytes read, which is zero at the end of the file.
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        if offset >= self.shared.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let mut stream = self.shared.stream.lock().unwrap_or_else(|e| e.into_inner());
+        stream.0.seek(SeekFrom::Start(offset))?;
+        stream.0.read(buf)
+    }
+
+    /// Reads exactly `buf.len()` bytes from the file at `offset`.
+    pub fn read_exact_at(&self, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(buf, offset) {
+                Ok(0) => break,
+                Ok(n) => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if !buf.is_empty() {
+            Err(io::const_io_error!(
+                ErrorKind::UnexpectedEof,
+                "failed to fill whole buffer",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl fmt::Debug for SgxSharedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxSharedFile")
+            .field("len", &self.shared.len)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A reader of an [`SgxSharedFile`], with its own position.
+///
+/// The reader is buffered, a node at a time, and implements [`BufRead`], so
+/// line and record parsers can read from it directly. Seeking within the
+/// buffered node keeps the buffer.
+#[derive(Clone)]
+pub struct SgxSharedReader {
+    file: SgxSharedFile,
+    pos: u64,
+    // `buf[consumed..filled]` holds the file's bytes from `pos` on.
+    buf: Vec<u8>,
+    filled: usize,
+    consumed: usize,
+}
+
+impl SgxSharedReader {
+    /// Returns the file the reader reads.
+    pub fn file(&self) -> &SgxSharedFile {
+        &self.file
+    }
+
+    fn discard_buffer(&mut self) {
+        self.filled = 0;
+        self.consumed = 0;
+    }
+}
+
+impl Read for SgxSharedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Reads of a node or more bypass the buffer when it is empty.
+        if self.consumed == self.filled && buf.len() >= READER_BUF_SIZE {
+            let n = self.file.read_at(buf, self.pos)?;
+            self.pos += n as u64;
+            return Ok(n);
+        }
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for SgxSharedReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.consumed == self.filled {
+            self.buf.resize(READER_BUF_SIZE, 0);
+            self.discard_buffer();
+            self.filled = self.file.read_at(&mut self.buf, self.pos)?;
+        }
+        Ok(&self.buf[self.consumed..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let amt = amt.min(self.filled - self.consumed);
+        self.consumed += amt;
+        self.pos += amt as u64;
+    }
+}
+
+impl Seek for SgxSharedReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.file.len().checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match pos {
+            Some(pos) => {
+                let start = self.pos - self.consumed as u64;
+                if pos >= start && pos <= start + self.filled as u64 {
+                    self.consumed = (pos - start) as usize;
+                } else {
+                    self.discard_buffer();
+                }
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::const_io_error!(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for SgxSharedReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxSharedReader")
+            .field("file", &self.file)
+            .field("pos", &self.pos)
+            .finish_non_exhaustive()
+    }
+}
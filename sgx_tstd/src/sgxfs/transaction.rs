@@ -0,0 +1,390 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Groups of writes to a protected file that are applied all or none.
+
+use crate::ffi::OsString;
+use crate::fmt;
+use crate::fs;
+use crate::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use crate::path::{Path, PathBuf};
+use crate::sgxfs::{OpenOptions, SgxFile};
+use sgx_types::sgx_key_128bit_t;
+
+const MAGIC: [u8; 8] = *b"SGXJRNL1";
+const COMMIT: u64 = u64::MAX;
+
+// A write of a transaction: its offset in the file and its data.
+type TxWrite = (u64, Vec<u8>);
+
+/// What [`recover`] found for a protected file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SgxRecovery {
+    /// No transaction was interrupted.
+    Clean,
+    /// A committed transaction was interrupted while it was applied, and was
+    /// applied again: `writes` writes of `bytes` bytes in total.
+    RolledForward { writes: usize, bytes: u64 },
+    /// A transaction was interrupted before it was committed, and was
+    /// discarded: the file does not have any of the `writes` writes of
+    /// `bytes` bytes in total that were journaled.
+    RolledBack { writes: usize, bytes: u64 },
+}
+
+/// A protected file written only by transactions.
+///
+/// The writes of an [`SgxTransaction`] are kept in enclave memory until it is
+/// committed. The commit writes them to a journal, a protected file next to
+/// the file whose name has `.journal` appended, and flushes it before it
+/// writes them to the file. The protected file library makes each flush
+/// atomic with its recovery file, so after a crash the journal either holds
+/// the whole transaction and its commit record, and the transaction is
+/// applied again, or it does not, and the file was not touched.
+///
+/// [`recover`] finishes or discards an interrupted transaction; opening the
+/// file does it first. The host can still delete the journal or roll both
+/// files back, as it can roll any protected file back.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::{Seek, SeekFrom, Write};
+/// use std::sgxfs::SgxTxFile;
+///
+/// # let (debit, credit) = ([0_u8; 16], [0_u8; 16]);
+/// let mut file = SgxTxFile::open("accounts", None)?;
+/// let mut tx = file.begin_transaction();
+/// tx.seek(SeekFrom::Start(0))?;
+/// tx.write_all(&debit)?;
+/// tx.seek(SeekFrom::Start(4096))?;
+/// tx.write_all(&credit)?;
+/// tx.commit()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct SgxTxFile {
+    file: SgxFile,
+    path: PathBuf,
+    key: Option<sgx_key_128bit_t>,
+    recovery: SgxRecovery,
+}
+
+impl SgxTxFile {
+    /// Opens the protected file at `path` with `key`, or the automatically
+    /// derived key, after recovering it.
+    pub fn open<P: AsRef<Path>>(path: P, key: Option<&sgx_key_128bit_t>) -> io::Result<SgxTxFile> {
+        let path = path.as_ref();
+        let recovery = recover(path, key)?;
+        let mut options = OpenOptions::new();
+        options.read(true).update(true);
+        let file = open(path, &options, key)?;
+        Ok(SgxTxFile {
+            file,
+            path: path.to_path_buf(),
+            key: key.copied(),
+            recovery,
+        })
+    }
+
+    /// Creates the protected file at `path` with `key`, or the automatically
+    /// derived key, truncating it if it exists.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        key: Option<&sgx_key_128bit_t>,
+    ) -> io::Result<SgxTxFile> {
+        let path = path.as_ref();
+        remove_journal(&journal_path(path))?;
+        let mut options = OpenOptions::new();
+        options.write(true).update(true);
+        let file = open(path, &options, key)?;
+        Ok(SgxTxFile {
+            file,
+            path: path.to_path_buf(),
+            key: key.copied(),
+            recovery: SgxRecovery::Clean,
+        })
+    }
+
+    /// Returns what the recovery found when the file was opened.
+    pub fn recovery(&self) -> SgxRecovery {
+        self.recovery
+    }
+
+    /// Returns the length of the file.
+    pub fn len(&self) -> io::Result<u64> {
+        let mut file = &self.file;
+        let pos = file.seek(SeekFrom::Current(0))?;
+        let len = file.seek(SeekFrom::End(0))?;
+        file.seek(SeekFrom::Start(pos))?;
+        Ok(len)
+    }
+
+    /// Starts a transaction, positioned at the start of the file.
+    pub fn begin_transaction(&mut self) -> SgxTransaction<'_> {
+        SgxTransaction {
+            file: self,
+            writes: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for SgxTxFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for SgxTxFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl fmt::Debug for SgxTxFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxTxFile")
+            .field("path", &self.path)
+            .field("recovery", &self.recovery)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The writes of a transaction on an [`SgxTxFile`].
+///
+/// Writing past the end of the file extends it with zeros. Reads of the file
+/// do not see the writes until the transaction is committed. Dropping the
+/// transaction without committing it discards them.
+pub struct SgxTransaction<'a> {
+    file: &'a mut SgxTxFile,
+    writes: Vec<TxWrite>,
+    pos: u64,
+}
+
+impl SgxTransaction<'_> {
+    /// Returns the number of writes in the transaction.
+    pub fn writes(&self) -> usize {
+        self.writes.len()
+    }
+
+    /// Commits the transaction: journals its writes, applies them to the file
+    /// and removes the journal. If it fails, [`recover`] tells whether the
+    /// transaction was applied.
+    pub fn commit(self) -> io::Result<()> {
+        if self.writes.is_empty() {
+            return Ok(());
+        }
+        let journal = journal_path(&self.file.path);
+        {
+            let mut options = OpenOptions::new();
+            options.write(true);
+            let mut file = open(&journal, &options, self.file.key.as_ref())?;
+            file.write_all(&MAGIC)?;
+            for (offset, data) in self.writes.iter() {
+                file.write_all(&offset.to_le_bytes())?;
+                file.write_all(&(data.len() as u64).to_le_bytes())?;
+                file.write_all(data)?;
+            }
+            file.write_all(&COMMIT.to_le_bytes())?;
+            file.write_all(&(self.writes.len() as u64).to_le_bytes())?;
+            file.flush()?;
+        }
+
+        apply(&mut self.file.file, &self.writes)?;
+        fs::remove_file(journal)
+    }
+
+    /// Discards the transaction.
+    pub fn rollback(self) {}
+}
+
+impl Write for SgxTransaction<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() {
+            self.writes.push((self.pos, buf.to_vec()));
+            self.pos += buf.len() as u64;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for SgxTransaction<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => {
+                let end = self
+                    .writes
+                    .iter()
+                    .map(|(offset, data)| offset + data.len() as u64)
+                    .fold(self.file.len()?, u64::max);
+                end.checked_add_signed(offset)
+            }
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::const_io_error!(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for SgxTransaction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxTransaction")
+            .field("writes", &self.writes.len())
+            .field("pos", &self.pos)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Finishes or discards a transaction on the protected file at `path`, opened
+/// with `key` or the automatically derived key, that was interrupted, and
+/// reports what it did.
+///
+/// # Errors
+///
+/// This function returns an error if the journal exists but cannot be opened,
+/// for instance because it was tampered with, or if the transaction cannot be
+/// applied again. The journal is kept then.
+pub fn recover<P: AsRef<Path>>(path: P, key: Option<&sgx_key_128bit_t>) -> io::Result<SgxRecovery> {
+    let path = path.as_ref();
+    let journal = journal_path(path);
+    match fs::metadata(&journal) {
+        Ok(_) => {}
+        Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(SgxRecovery::Clean),
+        Err(e) => return Err(e),
+    }
+
+    let (writes, committed) = {
+        let mut options = OpenOptions::new();
+        options.read(true);
+        let mut file = open(&journal, &options, key)?;
+        read_journal(&mut file)?
+    };
+    let bytes = writes.iter().map(|(_, data)| data.len() as u64).sum();
+    let count = writes.len();
+
+    if committed {
+        let mut options = OpenOptions::new();
+        options.read(true).update(true);
+        let mut file = open(path, &options, key)?;
+        apply(&mut file, &writes)?;
+        drop(file);
+        fs::remove_file(&journal)?;
+        Ok(SgxRecovery::RolledForward {
+            writes: count,
+            bytes,
+        })
+    } else {
+        fs::remove_file(&journal)?;
+        Ok(SgxRecovery::RolledBack {
+            writes: count,
+            bytes,
+        })
+    }
+}
+
+// Reads the writes of a journal, and whether its commit record was written.
+fn read_journal(file: &mut SgxFile) -> io::Result<(Vec<TxWrite>, bool)> {
+    let mut writes = Vec::new();
+    let mut magic = [0_u8; 8];
+    if read_full(file, &mut magic)? < magic.len() || magic != MAGIC {
+        return Ok((writes, false));
+    }
+    loop {
+        let mut header = [0_u8; 16];
+        if read_full(file, &mut header)? < header.len() {
+            return Ok((writes, false));
+        }
+        let mut word = [0_u8; 8];
+        word.copy_from_slice(&header[..8]);
+        let offset = u64::from_le_bytes(word);
+        word.copy_from_slice(&header[8..]);
+        let len = u64::from_le_bytes(word);
+
+        if offset == COMMIT {
+            let committed = len == writes.len() as u64;
+            return Ok((writes, committed));
+        }
+        let len = usize::try_from(len).map_err(|_| {
+            io::const_io_error!(ErrorKind::InvalidData, "journal entry is too large")
+        })?;
+        let mut data = vec![0_u8; len];
+        if read_full(file, &mut data)? < len {
+            return Ok((writes, false));
+        }
+        writes.push((offset, data));
+    }
+}
+
+fn read_full(file: &mut SgxFile, mut buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while !buf.is_empty() {
+        match file.read(buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                total += n;
+                buf = &mut buf[n..];
+            }
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(total)
+}
+
+fn apply(file: &mut SgxFile, writes: &[TxWrite]) -> io::Result<()> {
+    for (offset, data) in writes {
+        let len = file.seek(SeekFrom::End(0))?;
+        if *offset > len {
+            file.set_len(*offset)?;
+        }
+        file.seek(SeekFrom::Start(*offset))?;
+        file.write_all(data)?;
+    }
+    file.flush()
+}
+
+fn open(path: &Path, options: &OpenOptions, key: Option<&sgx_key_128bit_t>) -> io::Result<SgxFile> {
+    match key {
+        Some(key) => options.open_ex(path, key),
+        None => options.open(path),
+    }
+}
+
+fn journal_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".journal");
+    PathBuf::from(name)
+}
+
+fn remove_journal(journal: &Path) -> io::Result<()> {
+    match fs::remove_file(journal) {
+        Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
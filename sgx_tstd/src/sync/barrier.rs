@@ -15,8 +15,12 @@
 // specific language governing permissions and limitations
 // under the License..
 
+use crate::cell::UnsafeCell;
 use crate::fmt;
-use crate::sync::{SgxCondvar as Condvar, SgxMutex as Mutex};
+use crate::mem;
+use crate::sync::atomic::{AtomicUsize, Ordering};
+use crate::sync::SgxSpinlock as Spinlock;
+use crate::thread::{self, SgxThread as Thread};
 
 /// A barrier enables multiple threads to synchronize the beginning
 /// of some computation.
@@ -44,18 +48,26 @@ use crate::sync::{SgxCondvar as Condvar, SgxMutex as Mutex};
 ///     handle.join().unwrap();
 /// }
 /// ```
+//
+// Waiting threads park on their TCS event instead of going through a
+// condition variable, and the thread completing a generation unparks them
+// directly, so a rendezvous costs one wakeup per waiter.
 pub struct Barrier {
-    lock: Mutex<BarrierState>,
-    cvar: Condvar,
+    lock: Spinlock,
+    state: UnsafeCell<BarrierState>,
+    generation_id: AtomicUsize,
     num_threads: usize,
 }
 
-// The inner state of a double barrier
+// The inner state of a double barrier, protected by `lock`.
 struct BarrierState {
     count: usize,
-    generation_id: usize,
+    waiters: Vec<Thread>,
 }
 
+unsafe impl Send for Barrier {}
+unsafe impl Sync for Barrier {}
+
 /// A `BarrierWaitResult` is returned by [`Barrier::wait()`] when all threads
 /// in the [`Barrier`] have rendezvoused.
 ///
@@ -93,8 +105,9 @@ impl Barrier {
     #[must_use]
     pub fn new(n: usize) -> Barrier {
         Barrier {
-            lock: Mutex::new(BarrierState { count: 0, generation_id: 0 }),
-            cvar: Condvar::new(),
+            lock: Spinlock::new(),
+            state: UnsafeCell::new(BarrierState { count: 0, waiters: Vec::new() }),
+            generation_id: AtomicUsize::new(0),
             num_threads: n,
         }
     }
@@ -133,21 +146,38 @@ impl Barrier {
     /// }
     /// ```
     pub fn wait(&self) -> BarrierWaitResult {
-        let mut lock = self.lock.lock().unwrap();
-        let local_gen = lock.generation_id;
-        lock.count += 1;
-        if lock.count < self.num_threads {
-            // We need a while loop to guard against spurious wakeups.
-            // https://en.wikipedia.org/wiki/Spurious_wakeup
-            while local_gen == lock.generation_id {
-                lock = self.cvar.wait(lock).unwrap();
+        let current = thread::current();
+        let (local_gen, woken) = {
+            let _guard = self.lock.lock();
+            // SAFETY: the state is only ever accessed while the spinlock is held.
+            let state = unsafe { &mut *self.state.get() };
+            let local_gen = self.generation_id.load(Ordering::Relaxed);
+            state.count += 1;
+            if state.count < self.num_threads {
+                state.waiters.push(current);
+                (local_gen, None)
+            } else {
+                state.count = 0;
+                self.generation_id.store(local_gen.wrapping_add(1), Ordering::Release);
+                (local_gen, Some(mem::take(&mut state.waiters)))
+            }
+        };
+
+        match woken {
+            Some(waiters) => {
+                for waiter in waiters {
+                    waiter.unpark();
+                }
+                BarrierWaitResult(true)
+            }
+            None => {
+                // We need a while loop to guard against spurious wakeups.
+                // https://en.wikipedia.org/wiki/Spurious_wakeup
+                while local_gen == self.generation_id.load(Ordering::Acquire) {
+                    thread::park();
+                }
+                BarrierWaitResult(false)
             }
-            BarrierWaitResult(false)
-        } else {
-            lock.count = 0;
-            lock.generation_id = lock.generation_id.wrapping_add(1);
-            self.cvar.notify_all();
-            BarrierWaitResult(true)
         }
     }
 }
@@ -400,10 +400,17 @@ impl SgxCondvar {
         guard: SgxMutexGuard<'a, T>,
         dur: Duration,
     ) -> LockResult<(SgxMutexGuard<'a, T>, WaitTimeoutResult)> {
+        let start = Instant::now();
         let (poisoned, result) = unsafe {
             let lock = mutex::guard_lock(&guard);
             let success = self.inner.wait_timeout(lock, dur);
-            (mutex::guard_poison(&guard).get(), WaitTimeoutResult(!success))
+            // The host may claim a timeout early. Only report it once the
+            // timeout has elapsed according to `Instant`, otherwise it is
+            // treated like a spurious wakeup. `Instant` is read from the host
+            // too and is only kept from going backwards, so this does not
+            // stop a host that also moves the clock forwards.
+            let timed_out = !success && start.elapsed() >= dur;
+            (mutex::guard_poison(&guard).get(), WaitTimeoutResult(timed_out))
         };
         if poisoned { Err(PoisonError::new((guard, result))) } else { Ok((guard, result)) }
     }
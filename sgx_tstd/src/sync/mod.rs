@@ -35,7 +35,9 @@ pub use self::condvar::{SgxCondvar, WaitTimeoutResult};
 pub use self::mutex::{SgxMutex, SgxMutexGuard};
 pub use self::once::{Once, OnceState, ONCE_INIT};
 pub use self::poison::{LockResult, PoisonError, TryLockError, TryLockResult};
+pub use self::rate_limiter::RateLimiter;
 pub use self::rwlock::{SgxRwLock, SgxRwLockReadGuard, SgxRwLockWriteGuard};
+pub use self::semaphore::{Semaphore, SemaphoreGuard};
 pub use self::spinlock::{SgxSpinlock, SgxSpinlockGuard};
 
 pub use self::lazy_lock::LazyLock;
@@ -53,5 +55,7 @@ mod mutex;
 mod once;
 mod once_lock;
 mod poison;
+mod rate_limiter;
 mod rwlock;
+mod semaphore;
 mod spinlock;
@@ -0,0 +1,173 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::fmt;
+use crate::sync::{PoisonError, SgxMutex as Mutex};
+use crate::time::{Duration, Instant};
+
+/// A token bucket rate limiter.
+///
+/// The bucket holds up to `capacity` tokens and gains one token every
+/// `interval`. Each operation takes one or more tokens, so bursts of up to
+/// `capacity` operations are allowed on top of the steady rate of one per
+/// `interval`. A typical use is throttling how often an enclave service
+/// leaves the enclave through (switchless or regular) ocalls; see
+/// [`call_or_switchless`](RateLimiter::call_or_switchless).
+///
+/// The limiter is driven by the enclave's monotonic [`Instant`], which never
+/// goes backwards even if the host clock does. The host can still make time
+/// pass faster or slower, so the limiter bounds the rate as seen by the
+/// host's clock and is not a defence against a host that wants to speed the
+/// enclave up.
+///
+/// It is implemented with the generic cell rate algorithm: instead of a token
+/// count, it keeps the time at which the bucket would be full again, so no
+/// background refill is needed.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::RateLimiter;
+/// use std::time::Duration;
+///
+/// let limiter = RateLimiter::new(2, Duration::from_secs(1));
+/// assert!(limiter.try_acquire(1).is_ok());
+/// assert!(limiter.try_acquire(1).is_ok());
+/// assert!(limiter.try_acquire(1).is_err());
+/// ```
+pub struct RateLimiter {
+    capacity: u32,
+    interval: Duration,
+    // The time at which the bucket would be full again.
+    full_at: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// Creates a full bucket that holds `capacity` tokens and gains one token
+    /// every `interval`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero or `interval` is zero.
+    pub fn new(capacity: u32, interval: Duration) -> RateLimiter {
+        assert!(capacity > 0, "rate limiter capacity must be non-zero");
+        assert!(!interval.is_zero(), "rate limiter interval must be non-zero");
+        RateLimiter { capacity, interval, full_at: Mutex::new(Instant::_now()) }
+    }
+
+    /// Returns the maximum number of tokens in the bucket.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Returns the time it takes to gain one token.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the number of tokens currently in the bucket.
+    pub fn available(&self) -> u32 {
+        let full_at = *self.full_at.lock().unwrap_or_else(PoisonError::into_inner);
+        let missing = full_at.saturating_duration_since(Instant::_now());
+        let interval = self.interval.as_nanos();
+        let missing = (missing.as_nanos() + interval - 1) / interval;
+        self.capacity.saturating_sub(missing.min(u32::MAX as u128) as u32)
+    }
+
+    /// Takes `tokens` tokens from the bucket if they are available.
+    ///
+    /// On failure nothing is taken and the error holds how long to wait
+    /// before the tokens will be available, or [`Duration::MAX`] if `tokens`
+    /// exceeds the capacity or the bucket would only be full again after the
+    /// end of the monotonic clock, so they can never be granted.
+    pub fn try_acquire(&self, tokens: u32) -> Result<(), Duration> {
+        if tokens > self.capacity {
+            return Err(Duration::MAX);
+        }
+
+        let now = Instant::_now();
+        let mut full_at = self.full_at.lock().unwrap_or_else(PoisonError::into_inner);
+        let start = if *full_at > now { *full_at } else { now };
+        let new_full_at = start
+            .checked_add(self.interval.saturating_mul(tokens))
+            .ok_or(Duration::MAX)?;
+        // A bucket that would be full again only after the end of the clock
+        // bounds nothing that fits on the clock.
+        if let Some(limit) = now.checked_add(self.interval.saturating_mul(self.capacity)) {
+            if new_full_at > limit {
+                return Err(new_full_at - limit);
+            }
+        }
+        *full_at = new_full_at;
+        Ok(())
+    }
+
+    /// Makes a call through `ordinary` if a token is available, and through
+    /// `switchless` otherwise.
+    ///
+    /// With an ocall declared in the EDL both as an ordinary function and as
+    /// a `transition_using_threads` one, routing its calls through this caps
+    /// the enclave transitions it makes at the rate of the limiter: the calls
+    /// above the rate are handed to the untrusted switchless workers instead.
+    /// A switchless call still leaves the enclave when its retries before
+    /// fallback run out, so the cap only holds while the workers keep up; the
+    /// application sees those calls as missed in `rsgx_switchless_stats`.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let limiter = RateLimiter::new(100, Duration::from_millis(10));
+    /// let status = limiter.call_or_switchless(
+    ///     || unsafe { ocall_log(msg.as_ptr(), msg.len()) },
+    ///     || unsafe { ocall_log_switchless(msg.as_ptr(), msg.len()) },
+    /// );
+    /// ```
+    pub fn call_or_switchless<R, O, S>(&self, ordinary: O, switchless: S) -> R
+    where
+        O: FnOnce() -> R,
+        S: FnOnce() -> R,
+    {
+        match self.try_acquire(1) {
+            Ok(()) => ordinary(),
+            Err(_) => switchless(),
+        }
+    }
+
+    /// Takes `tokens` tokens from the bucket, sleeping until they are
+    /// available.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tokens` exceeds the capacity of the bucket.
+    #[cfg(feature = "thread")]
+    pub fn acquire(&self, tokens: u32) {
+        assert!(tokens <= self.capacity, "requested more tokens than the rate limiter can hold");
+        while let Err(wait) = self.try_acquire(tokens) {
+            crate::thread::sleep(wait);
+        }
+    }
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("capacity", &self.capacity)
+            .field("interval", &self.interval)
+            .field("available", &self.available())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,267 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::cell::UnsafeCell;
+use crate::collections::VecDeque;
+use crate::fmt;
+use crate::mem;
+use crate::sync::atomic::{AtomicBool, Ordering};
+use crate::sync::{Arc, SgxSpinlock as Spinlock};
+use crate::thread::{self, SgxThread as Thread};
+use crate::time::{Duration, Instant};
+
+/// A counting semaphore.
+///
+/// A semaphore holds a number of permits. [`acquire`] takes one permit,
+/// blocking until one is available, and the returned [`SemaphoreGuard`]
+/// gives it back when dropped.
+///
+/// Blocked threads are queued in order and parked on their TCS event, so
+/// waiting does not spin. When a permit is released it is handed directly to
+/// the thread at the front of the queue, which means a thread arriving late
+/// cannot overtake the ones already waiting.
+///
+/// [`acquire`]: Semaphore::acquire
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::{Arc, Semaphore};
+/// use std::thread;
+///
+/// // At most two workers run at the same time.
+/// let sem = Arc::new(Semaphore::new(2));
+/// let handles: Vec<_> = (0..8)
+///     .map(|_| {
+///         let sem = Arc::clone(&sem);
+///         thread::spawn(move || {
+///             let _permit = sem.acquire();
+///             // ... do work ...
+///         })
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// assert_eq!(sem.available_permits(), 2);
+/// ```
+pub struct Semaphore {
+    lock: Spinlock,
+    state: UnsafeCell<State>,
+}
+
+struct State {
+    permits: usize,
+    waiters: VecDeque<Arc<Waiter>>,
+}
+
+struct Waiter {
+    thread: Thread,
+    // Set, under the lock, when a permit has been handed to this waiter.
+    granted: AtomicBool,
+}
+
+unsafe impl Send for Semaphore {}
+unsafe impl Sync for Semaphore {}
+
+/// A permit taken from a [`Semaphore`].
+///
+/// The permit is returned to the semaphore when the guard is dropped, unless
+/// it is given up with [`SemaphoreGuard::forget`].
+#[must_use = "if unused the permit will immediately be released"]
+pub struct SemaphoreGuard<'a> {
+    sem: &'a Semaphore,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with `permits` permits available.
+    pub fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            lock: Spinlock::new(),
+            state: UnsafeCell::new(State {
+                permits,
+                waiters: VecDeque::new(),
+            }),
+        }
+    }
+
+    fn with_state<R, F: FnOnce(&mut State) -> R>(&self, f: F) -> R {
+        let _guard = self.lock.lock();
+        // SAFETY: the state is only ever accessed while the spinlock is held.
+        f(unsafe { &mut *self.state.get() })
+    }
+
+    /// Takes a permit, blocking the current thread until one is available.
+    pub fn acquire(&self) -> SemaphoreGuard<'_> {
+        self.acquire_until(None).unwrap()
+    }
+
+    /// Takes a permit if one is available right now.
+    ///
+    /// This does not overtake threads that are already waiting: it fails if
+    /// any thread is queued, even when permits are being released.
+    pub fn try_acquire(&self) -> Option<SemaphoreGuard<'_>> {
+        if self.with_state(State::try_take) {
+            Some(SemaphoreGuard { sem: self })
+        } else {
+            None
+        }
+    }
+
+    /// Takes a permit, blocking the current thread for at most `dur`.
+    ///
+    /// Returns `None` if no permit was handed to this thread within `dur`.
+    pub fn acquire_timeout(&self, dur: Duration) -> Option<SemaphoreGuard<'_>> {
+        // A deadline that does not fit in an `Instant` is the same as none.
+        self.acquire_until(Instant::_now().checked_add(dur))
+    }
+
+    fn acquire_until(&self, deadline: Option<Instant>) -> Option<SemaphoreGuard<'_>> {
+        if self.with_state(State::try_take) {
+            return Some(SemaphoreGuard { sem: self });
+        }
+
+        // Build the waiter outside the spinlock, then check once more before
+        // queueing it in case a permit was released in the meantime.
+        let waiter = Arc::new(Waiter {
+            thread: thread::current(),
+            granted: AtomicBool::new(false),
+        });
+        let queued = self.with_state(|state| {
+            if state.try_take() {
+                false
+            } else {
+                state.waiters.push_back(Arc::clone(&waiter));
+                true
+            }
+        });
+
+        if queued {
+            // Parking can wake up spuriously, so the grant flag is the only
+            // thing that tells whether the permit is ours.
+            while !waiter.granted.load(Ordering::Acquire) {
+                match deadline {
+                    None => thread::park(),
+                    Some(deadline) => {
+                        let now = Instant::_now();
+                        if now >= deadline {
+                            return self.cancel(&waiter);
+                        }
+                        thread::park_timeout(deadline - now);
+                    }
+                }
+            }
+        }
+        Some(SemaphoreGuard { sem: self })
+    }
+
+    // Removes a timed out waiter from the queue. The permit may have been
+    // granted after the deadline passed but before the lock was taken, in
+    // which case the waiter keeps it.
+    fn cancel(&self, waiter: &Arc<Waiter>) -> Option<SemaphoreGuard<'_>> {
+        let granted = self.with_state(|state| {
+            if waiter.granted.load(Ordering::Acquire) {
+                true
+            } else {
+                state.waiters.retain(|w| !Arc::ptr_eq(w, waiter));
+                false
+            }
+        });
+        if granted {
+            Some(SemaphoreGuard { sem: self })
+        } else {
+            None
+        }
+    }
+
+    /// Adds `n` permits to the semaphore, waking up as many waiting threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of available permits would overflow a `usize`.
+    pub fn add_permits(&self, n: usize) {
+        let mut woken = Vec::new();
+        self.with_state(|state| {
+            state.permits = state
+                .permits
+                .checked_add(n)
+                .expect("overflow in semaphore permits");
+            while state.permits > 0 {
+                match state.waiters.pop_front() {
+                    Some(waiter) => {
+                        state.permits -= 1;
+                        waiter.granted.store(true, Ordering::Release);
+                        woken.push(waiter);
+                    }
+                    None => break,
+                }
+            }
+        });
+        // Unpark outside the spinlock so woken threads do not spin on it.
+        for waiter in woken {
+            waiter.thread.unpark();
+        }
+    }
+
+    /// Returns the number of permits that can be taken without blocking.
+    pub fn available_permits(&self) -> usize {
+        self.with_state(|state| state.permits)
+    }
+}
+
+impl State {
+    fn try_take(&mut self) -> bool {
+        if self.permits > 0 && self.waiters.is_empty() {
+            self.permits -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (permits, waiters) = self.with_state(|state| (state.permits, state.waiters.len()));
+        f.debug_struct("Semaphore")
+            .field("permits", &permits)
+            .field("waiters", &waiters)
+            .finish()
+    }
+}
+
+impl SemaphoreGuard<'_> {
+    /// Gives up the permit without returning it to the semaphore.
+    ///
+    /// This permanently lowers the number of permits, which is useful when
+    /// the permits stand for a resource that has gone away.
+    pub fn forget(self) {
+        mem::forget(self);
+    }
+}
+
+impl Drop for SemaphoreGuard<'_> {
+    fn drop(&mut self) {
+        self.sem.add_permits(1);
+    }
+}
+
+impl fmt::Debug for SemaphoreGuard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SemaphoreGuard").finish_non_exhaustive()
+    }
+}
@@ -79,6 +79,18 @@ impl FileDesc {
         }
     }
 
+    pub fn read_vectored_at(&self, bufs: &mut [IoSliceMut<'_>], offset: u64) -> io::Result<usize> {
+        let ret = cvt(unsafe {
+            libc::preadv64(
+                self.as_raw_fd(),
+                bufs.as_ptr() as *const libc::iovec,
+                cmp::min(bufs.len(), max_iov()) as c_int,
+                offset as off64_t,
+            )
+        })?;
+        Ok(ret as usize)
+    }
+
     pub fn read_buf(&self, mut cursor: BorrowedCursor<'_>) -> io::Result<()> {
         let ret = cvt(unsafe {
             libc::read(
@@ -136,6 +148,18 @@ impl FileDesc {
         }
     }
 
+    pub fn write_vectored_at(&self, bufs: &[IoSlice<'_>], offset: u64) -> io::Result<usize> {
+        let ret = cvt(unsafe {
+            libc::pwritev64(
+                self.as_raw_fd(),
+                bufs.as_ptr() as *const libc::iovec,
+                cmp::min(bufs.len(), max_iov()) as c_int,
+                offset as off64_t,
+            )
+        })?;
+        Ok(ret as usize)
+    }
+
     pub fn get_cloexec(&self) -> io::Result<bool> {
         unsafe { Ok((cvt(libc::fcntl_arg0(self.as_raw_fd(), libc::F_GETFD))? & libc::FD_CLOEXEC) != 0) }
     }
@@ -215,8 +239,8 @@ impl FromRawFd for FileDesc {
 
 mod libc {
     pub use sgx_libc::ocall::{
-        close, fcntl_arg0, fcntl_arg1, ioctl_arg0, ioctl_arg1, pread64, pwrite64, read, readv,
-        write, writev,
+        close, fcntl_arg0, fcntl_arg1, ioctl_arg0, ioctl_arg1, pread64, preadv64, pwrite64,
+        pwritev64, read, readv, write, writev,
     };
     pub use sgx_libc::*;
 }
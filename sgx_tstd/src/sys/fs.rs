@@ -414,6 +414,45 @@ impl File {
         }
     }
 
+    pub fn create_anonymous_in(dir: &Path) -> io::Result<File> {
+        run_path_with_cstr(dir, |dir| {
+            let flags = libc::O_CLOEXEC | libc::O_RDWR | libc::O_TMPFILE;
+            let fd = cvt_r(|| unsafe { libc::open64(dir.as_ptr(), flags, 0o600) })?;
+            Ok(File(unsafe { FileDesc::from_raw_fd(fd) }))
+        })
+    }
+
+    pub fn lock(&self) -> io::Result<()> {
+        cvt_r(|| unsafe { libc::flock(self.as_raw_fd(), libc::LOCK_EX) })?;
+        Ok(())
+    }
+
+    pub fn lock_shared(&self) -> io::Result<()> {
+        cvt_r(|| unsafe { libc::flock(self.as_raw_fd(), libc::LOCK_SH) })?;
+        Ok(())
+    }
+
+    pub fn try_lock(&self) -> io::Result<bool> {
+        self.try_flock(libc::LOCK_EX)
+    }
+
+    pub fn try_lock_shared(&self) -> io::Result<bool> {
+        self.try_flock(libc::LOCK_SH)
+    }
+
+    fn try_flock(&self, operation: c_int) -> io::Result<bool> {
+        match cvt_r(|| unsafe { libc::flock(self.as_raw_fd(), operation | libc::LOCK_NB) }) {
+            Ok(_) => Ok(true),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn unlock(&self) -> io::Result<()> {
+        cvt_r(|| unsafe { libc::flock(self.as_raw_fd(), libc::LOCK_UN) })?;
+        Ok(())
+    }
+
     pub fn truncate(&self, size: u64) -> io::Result<()> {
         let size: off64_t =
             size.try_into().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
@@ -437,6 +476,10 @@ impl File {
         self.0.read_at(buf, offset)
     }
 
+    pub fn read_vectored_at(&self, bufs: &mut [IoSliceMut<'_>], offset: u64) -> io::Result<usize> {
+        self.0.read_vectored_at(bufs, offset)
+    }
+
     pub fn read_buf(&self, cursor: BorrowedCursor<'_>) -> io::Result<()> {
         self.0.read_buf(cursor)
     }
@@ -458,6 +501,10 @@ impl File {
         self.0.write_at(buf, offset)
     }
 
+    pub fn write_vectored_at(&self, bufs: &[IoSlice<'_>], offset: u64) -> io::Result<usize> {
+        self.0.write_vectored_at(bufs, offset)
+    }
+
     pub fn flush(&self) -> io::Result<()> {
         Ok(())
     }
@@ -705,7 +752,7 @@ pub fn canonicalize(p: &Path) -> io::Result<PathBuf> {
 }
 
 fn open_from(from: &Path) -> io::Result<(crate::fs::File, crate::fs::Metadata)> {
-    use crate::untrusted::fs::File;
+    use crate::fs::File;
     use crate::sys_common::fs::NOT_FILE_ERROR;
 
     let reader = File::open(from)?;
@@ -775,7 +822,7 @@ pub fn chroot(_dir: &Path) -> io::Result<()> {
 
 mod libc {
     pub use sgx_libc::ocall::{
-        chmod, closedir, dirfd, fchmod, fcntl_arg0, fdatasync, free, fstat64, fstatat64, fsync,
+        chmod, closedir, dirfd, fchmod, fcntl_arg0, fdatasync, flock, free, fstat64, fstatat64, fsync,
         ftruncate64, linkat, lseek64, lstat64, mkdir, open64, opendir, readdir64_r, readlink,
         realpath, rename, rmdir, stat64, symlink, unlink, futimens
     };
@@ -61,6 +61,7 @@
 //!   progress, they can hit a performance cliff.
 //! * complexity
 
+use crate::cell::RefCell;
 use crate::cmp::min;
 use crate::fs::{File, Metadata};
 use crate::io::copy::generic_copy;
@@ -75,7 +76,7 @@ use crate::os::unix::io::{AsRawFd, FromRawFd, RawFd};
 #[cfg(feature = "net")]
 use crate::os::unix::net::UnixStream;
 use crate::ptr;
-use crate::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use crate::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
 use crate::sys::cvt;
 use sgx_libc::{EBADF, EINVAL, ENOSYS, EOPNOTSUPP, EOVERFLOW, EPERM, EXDEV};
 
@@ -219,6 +220,17 @@ impl<R: CopyRead, W: CopyWrite> SpecCopy for Copier<'_, '_, R, W> {
                     }
                 }
             }
+
+            // Neither side has to be seen by the enclave, so move the data
+            // through an untrusted buffer without copying it in and out.
+            let result = bounce_copy(readfd, writefd, max_write);
+            result.update_take(reader);
+
+            match result {
+                CopyResult::Ended(bytes_copied) => return Ok(bytes_copied + written),
+                CopyResult::Error(e, _) => return Err(e),
+                CopyResult::Fallback(bytes) => written += bytes,
+            }
         }
 
         // fallback if none of the more specialized syscalls wants to work with these file descriptors
@@ -663,7 +675,64 @@ fn sendfile_splice(mode: SpliceMode, reader: RawFd, writer: RawFd, len: u64) ->
     CopyResult::Ended(written)
 }
 
+/// The size of the untrusted buffer `bounce_copy` uses, see
+/// [`io::set_copy_buffer_size`](crate::io::set_copy_buffer_size).
+pub(crate) static BOUNCE_BUFFER_SIZE: AtomicUsize = AtomicUsize::new(64 * 1024);
+
+thread_local! {
+    // The error that ended a bounce copy after some bytes went through, with
+    // its descriptors, reported by the next copy between them.
+    static BOUNCE_ERROR: RefCell<Option<(RawFd, RawFd, Error)>> = const { RefCell::new(None) };
+}
+
+/// Copies between two file descriptors through a buffer allocated outside
+/// the enclave for the whole copy.
+///
+/// Each chunk takes a single ocall that reads into the buffer and writes it
+/// out again, instead of a read and a write ocall that each stage the data
+/// through a fresh buffer and copy it across the enclave boundary.
+///
+/// Like `write`, a copy that fails after some bytes went through returns
+/// that count, and the error is returned by the next copy between the same
+/// descriptors.
+fn bounce_copy(reader: RawFd, writer: RawFd, len: u64) -> CopyResult {
+    let pending = BOUNCE_ERROR.with(|pending| pending.borrow_mut().take());
+    match pending {
+        Some((r, w, err)) if r == reader && w == writer => return CopyResult::Error(err, 0),
+        _ => {}
+    }
+
+    let size = BOUNCE_BUFFER_SIZE.load(Ordering::Relaxed);
+    if size == 0 {
+        return CopyResult::Fallback(0);
+    }
+    let buf = unsafe { libc::malloc(size) };
+    if buf.is_null() {
+        return CopyResult::Fallback(0);
+    }
+
+    let mut written = 0u64;
+    let result = loop {
+        if written >= len {
+            break CopyResult::Ended(written);
+        }
+        let chunk_size = min(len - written, size as u64) as usize;
+        match cvt(unsafe { libc::copy_through(reader, writer, buf, chunk_size) }) {
+            Ok(0) => break CopyResult::Ended(written),
+            Ok(ret) => written += ret as u64,
+            Err(ref err) if err.kind() == crate::io::ErrorKind::Interrupted => {}
+            Err(err) if written > 0 => {
+                BOUNCE_ERROR.with(|pending| *pending.borrow_mut() = Some((reader, writer, err)));
+                break CopyResult::Ended(written);
+            }
+            Err(err) => break CopyResult::Error(err, 0),
+        }
+    };
+    unsafe { libc::free(buf) };
+    result
+}
+
 mod libc {
-    pub use sgx_libc::ocall::{copy_file_range, sendfile, splice};
+    pub use sgx_libc::ocall::{copy_file_range, copy_through, free, malloc, sendfile, splice};
     pub use sgx_libc::*;
 }
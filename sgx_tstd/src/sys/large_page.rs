@@ -0,0 +1,323 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Large allocations served from 2 MiB aligned EMM regions.
+//!
+//! Allocations of at least `threshold` bytes bypass the tlibc heap and get
+//! their own region from the EMM, aligned to 2 MiB and rounded up to a
+//! multiple of `arena_size`. Pages are committed on demand, `fault_ahead`
+//! bytes at a time, so that sequential access does not fault on every page.
+//! Freed regions stay committed in a small cache, up to `decommit_threshold`
+//! bytes in total, and are reused for allocations of the same rounded size;
+//! beyond that they are released back to the EMM.
+//!
+//! Regions are tracked in a fixed table, so the allocator never allocates
+//! itself. If the table is full, EDMM is not available, or the EMM cannot
+//! provide a region, allocations fall back to the tlibc heap.
+
+use crate::alloc::{GlobalAlloc, Layout, System};
+use crate::cmp;
+use crate::io;
+use crate::ptr::{self, NonNull};
+use crate::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use crate::sync::SgxThreadSpinlock;
+
+use sgx_trts::emm::{Align, AllocAddr, AllocFlags, AllocOptions, EmmAlloc, HandleResult};
+use sgx_trts::enclave::rsgx_is_supported_EDMM;
+use sgx_types::metadata::SE_PAGE_SIZE;
+use sgx_types::sgx_pfinfo;
+
+const LARGE_PAGE_SIZE: usize = 0x20_0000;
+const MAX_REGIONS: usize = 256;
+
+const SLOT_FREE: u8 = 0;
+const SLOT_RESERVED: u8 = 1;
+const SLOT_IN_USE: u8 = 2;
+const SLOT_CACHED: u8 = 3;
+
+/// Tunables of the large-page allocator.
+///
+/// Set with [`set_large_page_config`](crate::alloc::set_large_page_config).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LargePageConfig {
+    /// Allocations of at least this many bytes get their own EMM region.
+    pub threshold: usize,
+    /// Regions are rounded up to a multiple of this size. Must be a
+    /// non-zero multiple of 2 MiB.
+    pub arena_size: usize,
+    /// Number of bytes committed on each page fault. Must be a non-zero
+    /// multiple of the page size.
+    pub fault_ahead: usize,
+    /// Freed regions are kept committed for reuse as long as the cached
+    /// regions add up to at most this many bytes.
+    pub decommit_threshold: usize,
+}
+
+impl LargePageConfig {
+    /// The configuration used until another one is set.
+    pub const DEFAULT: LargePageConfig = LargePageConfig {
+        threshold: LARGE_PAGE_SIZE,
+        arena_size: LARGE_PAGE_SIZE,
+        fault_ahead: 0x1_0000,
+        decommit_threshold: 0x200_0000,
+    };
+}
+
+impl Default for LargePageConfig {
+    fn default() -> LargePageConfig {
+        LargePageConfig::DEFAULT
+    }
+}
+
+static THRESHOLD: AtomicUsize = AtomicUsize::new(LargePageConfig::DEFAULT.threshold);
+// The lowest threshold ever set: smaller allocations never come from a region.
+static LOWEST_THRESHOLD: AtomicUsize = AtomicUsize::new(LargePageConfig::DEFAULT.threshold);
+static ARENA_SIZE: AtomicUsize = AtomicUsize::new(LargePageConfig::DEFAULT.arena_size);
+static FAULT_AHEAD: AtomicUsize = AtomicUsize::new(LargePageConfig::DEFAULT.fault_ahead);
+static DECOMMIT_THRESHOLD: AtomicUsize =
+    AtomicUsize::new(LargePageConfig::DEFAULT.decommit_threshold);
+
+pub fn set_config(config: LargePageConfig) -> io::Result<()> {
+    if config.threshold == 0
+        || config.arena_size == 0
+        || config.arena_size % LARGE_PAGE_SIZE != 0
+        || config.fault_ahead == 0
+        || config.fault_ahead % SE_PAGE_SIZE != 0
+    {
+        return Err(io::const_io_error!(
+            io::ErrorKind::InvalidInput,
+            "invalid large page allocator configuration",
+        ));
+    }
+
+    LOWEST_THRESHOLD.fetch_min(config.threshold, Ordering::Relaxed);
+    THRESHOLD.store(config.threshold, Ordering::Relaxed);
+    ARENA_SIZE.store(config.arena_size, Ordering::Relaxed);
+    FAULT_AHEAD.store(config.fault_ahead, Ordering::Relaxed);
+    DECOMMIT_THRESHOLD.store(config.decommit_threshold, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn config() -> LargePageConfig {
+    LargePageConfig {
+        threshold: THRESHOLD.load(Ordering::Relaxed),
+        arena_size: ARENA_SIZE.load(Ordering::Relaxed),
+        fault_ahead: FAULT_AHEAD.load(Ordering::Relaxed),
+        decommit_threshold: DECOMMIT_THRESHOLD.load(Ordering::Relaxed),
+    }
+}
+
+struct Region {
+    base: AtomicUsize,
+    len: AtomicUsize,
+    state: AtomicU8,
+}
+
+impl Region {
+    const EMPTY: Region = Region {
+        base: AtomicUsize::new(0),
+        len: AtomicUsize::new(0),
+        state: AtomicU8::new(SLOT_FREE),
+    };
+}
+
+// The table is only modified with `LOCK` held. The fault handler reads the
+// bounds of its own region without the lock.
+static REGIONS: [Region; MAX_REGIONS] = [Region::EMPTY; MAX_REGIONS];
+static LOCK: SgxThreadSpinlock = SgxThreadSpinlock::new();
+static CACHED_BYTES: AtomicUsize = AtomicUsize::new(0);
+static LIVE_REGIONS: AtomicUsize = AtomicUsize::new(0);
+
+struct LockGuard;
+
+impl LockGuard {
+    fn lock() -> LockGuard {
+        unsafe { LOCK.lock() };
+        LockGuard
+    }
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        unsafe { LOCK.unlock() };
+    }
+}
+
+extern "C" fn fault_ahead_handler(pfinfo: &sgx_pfinfo, slot: usize) -> HandleResult {
+    let region = &REGIONS[slot];
+    let base = region.base.load(Ordering::Acquire);
+    let end = base + region.len.load(Ordering::Acquire);
+    let addr = pfinfo.maddr as usize & !(SE_PAGE_SIZE - 1);
+    if addr < base || addr >= end {
+        return HandleResult::Search;
+    }
+
+    let len = cmp::min(FAULT_AHEAD.load(Ordering::Relaxed), end - addr);
+    let page = unsafe { NonNull::new_unchecked(addr as *mut u8) };
+    // Some pages of the range may already be committed; fall back to the
+    // faulting page alone if the EMM refuses the whole range.
+    let committed = unsafe {
+        EmmAlloc
+            .commit(page, len)
+            .or_else(|_| EmmAlloc.commit(page, SE_PAGE_SIZE))
+    };
+    match committed {
+        Ok(()) => HandleResult::Execution,
+        Err(_) => HandleResult::Search,
+    }
+}
+
+fn is_eligible(layout: &Layout) -> bool {
+    layout.size() >= THRESHOLD.load(Ordering::Relaxed) && layout.align() <= LARGE_PAGE_SIZE
+}
+
+unsafe fn alloc_region(layout: Layout, zeroed: bool) -> Option<*mut u8> {
+    let arena_size = ARENA_SIZE.load(Ordering::Relaxed);
+    let len = layout.size().checked_add(arena_size - 1)? / arena_size * arena_size;
+
+    let slot = {
+        let _guard = LockGuard::lock();
+        let cached = REGIONS.iter().find(|r| {
+            r.state.load(Ordering::Relaxed) == SLOT_CACHED && r.len.load(Ordering::Relaxed) == len
+        });
+        if let Some(region) = cached {
+            region.state.store(SLOT_IN_USE, Ordering::Relaxed);
+            CACHED_BYTES.fetch_sub(len, Ordering::Relaxed);
+            let base = region.base.load(Ordering::Relaxed) as *mut u8;
+            drop(_guard);
+            if zeroed {
+                ptr::write_bytes(base, 0, layout.size());
+            }
+            return Some(base);
+        }
+
+        let slot = REGIONS
+            .iter()
+            .position(|r| r.state.load(Ordering::Relaxed) == SLOT_FREE)?;
+        REGIONS[slot].state.store(SLOT_RESERVED, Ordering::Relaxed);
+        slot
+    };
+
+    let options = AllocOptions::new()
+        .set_flags(AllocFlags::COMMIT_ON_DEMAND)
+        .set_align(Align::A2MB)
+        .set_handler(fault_ahead_handler, slot);
+    let region = &REGIONS[slot];
+    // Clear the bounds left by a previous region in this slot until the EMM
+    // has handed out the new one.
+    region.base.store(0, Ordering::Release);
+    region.len.store(0, Ordering::Release);
+    match EmmAlloc.alloc(AllocAddr::Any, len, options) {
+        Ok(base) => {
+            region.base.store(base.as_ptr() as usize, Ordering::Release);
+            region.len.store(len, Ordering::Release);
+            let _guard = LockGuard::lock();
+            region.state.store(SLOT_IN_USE, Ordering::Relaxed);
+            LIVE_REGIONS.fetch_add(1, Ordering::Relaxed);
+            // Fresh EMM pages are zero-filled.
+            Some(base.as_ptr())
+        }
+        Err(_) => {
+            let _guard = LockGuard::lock();
+            region.state.store(SLOT_FREE, Ordering::Relaxed);
+            None
+        }
+    }
+}
+
+fn find_region(ptr: *mut u8, layout: &Layout) -> Option<&'static Region> {
+    if layout.size() < LOWEST_THRESHOLD.load(Ordering::Relaxed)
+        || LIVE_REGIONS.load(Ordering::Relaxed) == 0
+    {
+        return None;
+    }
+    REGIONS.iter().find(|r| {
+        r.state.load(Ordering::Relaxed) == SLOT_IN_USE
+            && r.base.load(Ordering::Relaxed) == ptr as usize
+    })
+}
+
+// Called with `LOCK` held. Returns the region to release to the EMM, which
+// the caller does after dropping the lock.
+fn retire_region(region: &'static Region) -> Option<(NonNull<u8>, usize)> {
+    let len = region.len.load(Ordering::Relaxed);
+    if CACHED_BYTES.load(Ordering::Relaxed) + len <= DECOMMIT_THRESHOLD.load(Ordering::Relaxed) {
+        region.state.store(SLOT_CACHED, Ordering::Relaxed);
+        CACHED_BYTES.fetch_add(len, Ordering::Relaxed);
+        return None;
+    }
+
+    region.state.store(SLOT_RESERVED, Ordering::Relaxed);
+    LIVE_REGIONS.fetch_sub(1, Ordering::Relaxed);
+    NonNull::new(region.base.load(Ordering::Relaxed) as *mut u8).map(|base| (base, len))
+}
+
+pub unsafe fn alloc(layout: Layout) -> *mut u8 {
+    if is_eligible(&layout) && rsgx_is_supported_EDMM() {
+        if let Some(ptr) = alloc_region(layout, false) {
+            return ptr;
+        }
+    }
+    System.alloc(layout)
+}
+
+pub unsafe fn alloc_zeroed(layout: Layout) -> *mut u8 {
+    if is_eligible(&layout) && rsgx_is_supported_EDMM() {
+        if let Some(ptr) = alloc_region(layout, true) {
+            return ptr;
+        }
+    }
+    System.alloc_zeroed(layout)
+}
+
+pub unsafe fn dealloc(ptr: *mut u8, layout: Layout) {
+    let region = {
+        let _guard = LockGuard::lock();
+        find_region(ptr, &layout).map(|region| (region, retire_region(region)))
+    };
+    match region {
+        Some((region, Some((base, len)))) => {
+            let _ = EmmAlloc.dealloc(base, len);
+            let _guard = LockGuard::lock();
+            region.state.store(SLOT_FREE, Ordering::Relaxed);
+        }
+        Some((_, None)) => {}
+        None => System.dealloc(ptr, layout),
+    }
+}
+
+pub unsafe fn realloc(ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+    let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+    let region_len = {
+        let _guard = LockGuard::lock();
+        find_region(ptr, &layout).map(|r| r.len.load(Ordering::Relaxed))
+    };
+
+    match region_len {
+        // Stay in the region while the allocation fits and is still large.
+        Some(len) if new_size <= len && is_eligible(&new_layout) => ptr,
+        None if !is_eligible(&new_layout) => System.realloc(ptr, layout, new_size),
+        _ => {
+            let new_ptr = alloc(new_layout);
+            if !new_ptr.is_null() {
+                ptr::copy_nonoverlapping(ptr, new_ptr, cmp::min(layout.size(), new_size));
+                dealloc(ptr, layout);
+            }
+            new_ptr
+        }
+    }
+}
@@ -30,6 +30,8 @@ pub mod fd;
 pub mod fs;
 pub mod io;
 pub mod kernel_copy;
+#[cfg(feature = "large_page_alloc")]
+pub mod large_page;
 pub mod locks;
 pub mod memchr;
 #[cfg(feature = "net")]
@@ -39,7 +41,10 @@ pub mod os_str;
 pub mod path;
 #[cfg(feature = "pipe")]
 pub mod pipe;
+#[cfg(feature = "process")]
+pub mod process;
 pub mod rand;
+#[cfg(not(feature = "no_fs"))]
 pub mod sgxfs;
 #[cfg(feature = "stdio")]
 pub mod stdio;
@@ -95,6 +100,34 @@ pub fn decode_error_kind(errno: i32) -> ErrorKind {
 
         libc::EACCES | libc::EPERM => PermissionDenied,
 
+        // Host errnos that have no kind of their own in upstream std but
+        // clearly fall into an existing category. The raw errno is kept in
+        // the error, so callers can still tell them apart.
+        // ENOTSUP has the same value as EOPNOTSUPP on Linux.
+        libc::EOPNOTSUPP
+        | libc::EAFNOSUPPORT
+        | libc::EPFNOSUPPORT
+        | libc::EPROTONOSUPPORT
+        | libc::ESOCKTNOSUPPORT
+        | libc::ENOPROTOOPT
+        | libc::ENOTTY => Unsupported,
+        libc::ENODEV | libc::ENXIO | libc::ENOMEDIUM => NotFound,
+        libc::ENETRESET => ConnectionReset,
+        libc::EHOSTDOWN => HostUnreachable,
+        libc::ESHUTDOWN => BrokenPipe,
+        libc::ETIME => TimedOut,
+        libc::ENOBUFS => OutOfMemory,
+        libc::EBADF
+        | libc::EFAULT
+        | libc::EDESTADDRREQ
+        | libc::ENOTSOCK
+        | libc::EMSGSIZE
+        | libc::EPROTOTYPE => InvalidInput,
+        libc::EILSEQ | libc::EBADMSG | libc::EPROTO | libc::EOVERFLOW => InvalidData,
+
+        // Set by the ocall wrappers when the ocall itself failed.
+        libc::ESGX => SgxError,
+
         // These two constants can have the same value on some systems,
         // but different values on others, so we can't use a match
         // clause
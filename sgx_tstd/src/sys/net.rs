@@ -37,6 +37,24 @@ pub type wrlen_t = size_t;
 
 pub struct Socket(FileDesc);
 
+// Upper bounds the Linux TCP stack enforces on the keepalive options.
+const MAX_TCP_KEEPIDLE: u64 = 32767;
+const MAX_TCP_KEEPINTVL: u64 = 32767;
+const MAX_TCP_KEEPCNT: u32 = 127;
+
+fn keepalive_secs(dur: Duration, max: u64, what: &str) -> io::Result<c_int> {
+    // The options have a granularity of one second; round up so that a
+    // sub-second value is not rejected as zero.
+    let secs = dur.as_secs() + (dur.subsec_nanos() > 0) as u64;
+    if secs == 0 || secs > max {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            crate::format!("{what} must be between 1 and {max} seconds"),
+        ));
+    }
+    Ok(secs as c_int)
+}
+
 pub fn init() {}
 
 pub fn cvt_gai(err: c_int) -> io::Result<()> {
@@ -349,6 +367,107 @@ impl Socket {
         Ok(passcred != 0)
     }
 
+    pub fn set_keepalive(&self, keepalive: Option<Duration>) -> io::Result<()> {
+        if let Some(time) = keepalive {
+            let secs = keepalive_secs(time, MAX_TCP_KEEPIDLE, "keepalive time")?;
+            setsockopt(self, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE, secs)?;
+        }
+        setsockopt(self, libc::SOL_SOCKET, libc::SO_KEEPALIVE, keepalive.is_some() as c_int)
+    }
+
+    pub fn keepalive(&self) -> io::Result<Option<Duration>> {
+        let raw: c_int = getsockopt(self, libc::SOL_SOCKET, libc::SO_KEEPALIVE)?;
+        if raw == 0 {
+            return Ok(None);
+        }
+        let secs: c_int = getsockopt(self, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE)?;
+        Ok(Some(Duration::from_secs(secs as u64)))
+    }
+
+    pub fn set_keepalive_interval(&self, interval: Duration) -> io::Result<()> {
+        let secs = keepalive_secs(interval, MAX_TCP_KEEPINTVL, "keepalive interval")?;
+        setsockopt(self, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL, secs)
+    }
+
+    pub fn keepalive_interval(&self) -> io::Result<Duration> {
+        let secs: c_int = getsockopt(self, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL)?;
+        Ok(Duration::from_secs(secs as u64))
+    }
+
+    pub fn set_keepalive_retries(&self, retries: u32) -> io::Result<()> {
+        if retries == 0 || retries > MAX_TCP_KEEPCNT {
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidInput,
+                "keepalive retries must be between 1 and 127",
+            ));
+        }
+        setsockopt(self, libc::IPPROTO_TCP, libc::TCP_KEEPCNT, retries as c_int)
+    }
+
+    pub fn keepalive_retries(&self) -> io::Result<u32> {
+        let raw: c_int = getsockopt(self, libc::IPPROTO_TCP, libc::TCP_KEEPCNT)?;
+        Ok(raw as u32)
+    }
+
+    pub fn set_user_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let millis = match timeout {
+            Some(dur) if dur.is_zero() => {
+                return Err(io::const_io_error!(
+                    io::ErrorKind::InvalidInput,
+                    "cannot set a 0 duration timeout",
+                ));
+            }
+            // Round up, so that a sub-millisecond timeout does not turn into
+            // "no timeout".
+            Some(dur) => {
+                let millis = dur.as_nanos().saturating_add(999_999) / 1_000_000;
+                cmp::min(millis, c_int::MAX as u128) as c_int
+            }
+            None => 0,
+        };
+        setsockopt(self, libc::IPPROTO_TCP, libc::TCP_USER_TIMEOUT, millis)
+    }
+
+    pub fn user_timeout(&self) -> io::Result<Option<Duration>> {
+        let raw: c_int = getsockopt(self, libc::IPPROTO_TCP, libc::TCP_USER_TIMEOUT)?;
+        Ok((raw > 0).then(|| Duration::from_millis(raw as u64)))
+    }
+
+    pub fn set_reuse_address(&self, reuse: bool) -> io::Result<()> {
+        setsockopt(self, libc::SOL_SOCKET, libc::SO_REUSEADDR, reuse as c_int)
+    }
+
+    pub fn reuse_address(&self) -> io::Result<bool> {
+        let raw: c_int = getsockopt(self, libc::SOL_SOCKET, libc::SO_REUSEADDR)?;
+        Ok(raw != 0)
+    }
+
+    pub fn set_reuse_port(&self, reuse: bool) -> io::Result<()> {
+        setsockopt(self, libc::SOL_SOCKET, libc::SO_REUSEPORT, reuse as c_int)
+    }
+
+    pub fn reuse_port(&self) -> io::Result<bool> {
+        let raw: c_int = getsockopt(self, libc::SOL_SOCKET, libc::SO_REUSEPORT)?;
+        Ok(raw != 0)
+    }
+
+    pub fn set_buffer_size(&self, size: usize, kind: c_int) -> io::Result<()> {
+        // Linux doubles the value it is given, so anything above half of
+        // `c_int::MAX` cannot be honoured.
+        if size == 0 || size > (c_int::MAX / 2) as usize {
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidInput,
+                "buffer size out of range",
+            ));
+        }
+        setsockopt(self, libc::SOL_SOCKET, kind, size as c_int)
+    }
+
+    pub fn buffer_size(&self, kind: c_int) -> io::Result<usize> {
+        let raw: c_int = getsockopt(self, libc::SOL_SOCKET, kind)?;
+        Ok(raw as usize)
+    }
+
     pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
         let mut nonblocking = nonblocking as c_int;
         cvt(unsafe { libc::ioctl_arg1(self.as_raw_fd(), libc::FIONBIO, &mut nonblocking) }).map(drop)
@@ -196,6 +196,9 @@ pub fn env_read_lock() -> impl Drop {
 /// Returns a vector of (variable, value) byte-vector pairs for all the
 /// environment variables of the current process.
 pub fn env() -> Env {
+    if let Some(result) = filtered_env() {
+        return Env { iter: result.into_iter() };
+    }
     unsafe {
         let _guard = env_read_lock();
         let mut environ = environ();
@@ -230,6 +233,9 @@ pub fn env() -> Env {
 }
 
 pub fn getenv(k: &OsStr) -> io::Result<Option<OsString>> {
+    if let Some(v) = filtered_getenv(k) {
+        return Ok(v);
+    }
     // environment variables with a nul byte can't be set, so their value is
     // always None as well
     let s = run_with_cstr(k.as_bytes(), |k| {
@@ -244,6 +250,9 @@ pub fn getenv(k: &OsStr) -> io::Result<Option<OsString>> {
 }
 
 pub fn setenv(k: &OsStr, v: &OsStr) -> io::Result<()> {
+    if let Some(r) = filtered_setenv(k, Some(v)) {
+        return r;
+    }
     run_with_cstr(k.as_bytes(), |k| {
         run_with_cstr(v.as_bytes(), |v| {
             let _guard = ENV_LOCK.write();
@@ -253,24 +262,159 @@ pub fn setenv(k: &OsStr, v: &OsStr) -> io::Result<()> {
 }
 
 pub fn unsetenv(n: &OsStr) -> io::Result<()> {
+    if let Some(r) = filtered_setenv(n, None) {
+        return r;
+    }
     run_with_cstr(n.as_bytes(), |nbuf| {
         let _guard = ENV_LOCK.write();
         cvt(unsafe { libc::unsetenv(nbuf.as_ptr()) }).map(drop)
     })
 }
 
+/// Upper bound on the size of the environment snapshot accepted from the uRTS.
+pub const ENV_SNAPSHOT_MAX_LEN: usize = 64 * 1024;
+/// Upper bound on the size of a single `key=value` entry in the snapshot.
+pub const ENV_ENTRY_MAX_LEN: usize = 8 * 1024;
+
+// Once an allowlist has been declared, the environment is no longer read
+// from the host: lookups are served from the last snapshot passed in by
+// the uRTS, and only allowlisted keys are ever visible.
+struct EnvFilter {
+    allowlist: Vec<OsString>,
+    vars: Vec<(OsString, OsString)>,
+}
+
+impl EnvFilter {
+    fn is_allowed(&self, k: &OsStr) -> bool {
+        self.allowlist.iter().any(|a| a.as_os_str() == k)
+    }
+}
+
+static ENV_FILTER: RwLock<Option<EnvFilter>> = RwLock::new(None);
+static ENV_SNAPSHOT: RwLock<Vec<(OsString, OsString)>> = RwLock::new(Vec::new());
+
+pub fn set_env_allowlist(keys: Vec<OsString>) -> io::Result<()> {
+    for k in keys.iter() {
+        let k = k.as_bytes();
+        if k.is_empty() || k.contains(&b'=') || k.contains(&b'\0') {
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidInput,
+                "invalid environment variable name in allowlist",
+            ));
+        }
+    }
+
+    let mut filter = ENV_FILTER.write().unwrap_or_else(PoisonError::into_inner);
+    if filter.is_some() {
+        return Err(io::const_io_error!(
+            io::ErrorKind::AlreadyExists,
+            "environment allowlist has already been set",
+        ));
+    }
+    let vars = ENV_SNAPSHOT.read().unwrap_or_else(PoisonError::into_inner).clone();
+    *filter = Some(EnvFilter { allowlist: keys, vars });
+    Ok(())
+}
+
+/// Validates a snapshot of `key=value\0` entries and makes it the current
+/// environment seen by an allowlisted enclave. The snapshot is rejected as a
+/// whole if it is too large or any entry is malformed or not valid UTF-8.
+pub fn set_env_snapshot(snapshot: &[u8]) -> io::Result<()> {
+    if snapshot.len() > ENV_SNAPSHOT_MAX_LEN {
+        return Err(io::const_io_error!(
+            io::ErrorKind::InvalidInput,
+            "environment snapshot is too large",
+        ));
+    }
+
+    let mut vars: Vec<(OsString, OsString)> = Vec::new();
+    for entry in snapshot.split(|b| *b == 0).filter(|e| !e.is_empty()) {
+        if entry.len() > ENV_ENTRY_MAX_LEN {
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidInput,
+                "environment snapshot entry is too large",
+            ));
+        }
+        let entry = str::from_utf8(entry).map_err(|_| {
+            io::const_io_error!(
+                io::ErrorKind::InvalidData,
+                "environment snapshot entry is not valid UTF-8",
+            )
+        })?;
+        let (k, v) = match entry.split_once('=') {
+            Some((k, v)) if !k.is_empty() => (k, v),
+            _ => {
+                return Err(io::const_io_error!(
+                    io::ErrorKind::InvalidInput,
+                    "malformed environment snapshot entry",
+                ));
+            }
+        };
+        vars.retain(|(key, _)| key.as_os_str() != OsStr::new(k));
+        vars.push((OsString::from(k), OsString::from(v)));
+    }
+
+    let mut filter = ENV_FILTER.write().unwrap_or_else(PoisonError::into_inner);
+    if let Some(filter) = filter.as_mut() {
+        filter.vars = vars.clone();
+    }
+    *ENV_SNAPSHOT.write().unwrap_or_else(PoisonError::into_inner) = vars;
+    Ok(())
+}
+
+fn filtered_env() -> Option<Vec<(OsString, OsString)>> {
+    let filter = ENV_FILTER.read().unwrap_or_else(PoisonError::into_inner);
+    filter.as_ref().map(|f| {
+        f.vars.iter().filter(|(k, _)| f.is_allowed(k)).cloned().collect()
+    })
+}
+
+fn filtered_getenv(k: &OsStr) -> Option<Option<OsString>> {
+    let filter = ENV_FILTER.read().unwrap_or_else(PoisonError::into_inner);
+    filter.as_ref().map(|f| {
+        if !f.is_allowed(k) {
+            return None;
+        }
+        f.vars.iter().find(|(key, _)| key.as_os_str() == k).map(|(_, v)| v.clone())
+    })
+}
+
+// Modifications made by an allowlisted enclave stay inside the enclave and
+// are never propagated to the host environment.
+fn filtered_setenv(k: &OsStr, v: Option<&OsStr>) -> Option<io::Result<()>> {
+    let mut filter = ENV_FILTER.write().unwrap_or_else(PoisonError::into_inner);
+    let f = filter.as_mut()?;
+
+    let key = k.as_bytes();
+    if key.is_empty() || key.contains(&b'=') || key.contains(&b'\0') {
+        return Some(Err(io::Error::from_raw_os_error(libc::EINVAL)));
+    }
+    if v.map_or(false, |v| v.as_bytes().contains(&b'\0')) {
+        return Some(Err(io::const_io_error!(
+            io::ErrorKind::InvalidInput,
+            "environment variable contained an unexpected NUL byte",
+        )));
+    }
+
+    f.vars.retain(|(key, _)| key.as_os_str() != k);
+    if let Some(v) = v {
+        f.vars.push((k.to_owned(), v.to_owned()));
+    }
+    Some(Ok(()))
+}
+
 pub fn page_size() -> usize {
     SE_PAGE_SIZE
 }
 
 pub fn temp_dir() -> PathBuf {
-    crate::env::var_os("TMPDIR").map(PathBuf::from).unwrap_or_else(|| {
+    getenv(OsStr::new("TMPDIR")).ok().flatten().map(PathBuf::from).unwrap_or_else(|| {
         PathBuf::from("/tmp")
     })
 }
 
 pub fn home_dir() -> Option<PathBuf> {
-    return crate::env::var_os("HOME").or_else(|| unsafe { fallback() }).map(PathBuf::from);
+    return getenv(OsStr::new("HOME")).ok().flatten().or_else(|| unsafe { fallback() }).map(PathBuf::from);
 
     unsafe fn fallback() -> Option<OsString> {
         let amt = match libc::sysconf(libc::_SC_GETPW_R_SIZE_MAX) {
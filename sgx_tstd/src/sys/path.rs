@@ -15,7 +15,6 @@
 // specific language governing permissions and limitations
 // under the License..
 
-use crate::env;
 use crate::ffi::OsStr;
 use crate::io;
 use crate::path::{Path, PathBuf, Prefix};
@@ -62,7 +61,7 @@ pub(crate) fn absolute(path: &Path) -> io::Result<PathBuf> {
             PathBuf::new()
         }
     } else {
-        env::current_dir()?
+        crate::sys::os::getcwd()?
     };
     normalized.extend(components);
 
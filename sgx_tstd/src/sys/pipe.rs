@@ -37,6 +37,10 @@ pub fn anon_pipe() -> io::Result<(AnonPipe, AnonPipe)> {
 }
 
 impl AnonPipe {
+    pub fn try_clone(&self) -> io::Result<AnonPipe> {
+        self.0.duplicate().map(AnonPipe)
+    }
+
     pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
         self.0.read(buf)
     }
@@ -118,6 +122,32 @@ pub fn read2(p1: AnonPipe, v1: &mut Vec<u8>, p2: AnonPipe, v2: &mut Vec<u8>) ->
     }
 }
 
+/// Checks that a descriptor handed over by the host is a pipe end that can be
+/// read from (or written to, if `write` is set).
+pub fn check_pipe_fd(fd: BorrowedFd<'_>, write: bool) -> io::Result<()> {
+    let fd = fd.as_raw_fd();
+    let mut stat: libc::stat64 = unsafe { mem::zeroed() };
+    cvt(unsafe { libc::fstat64(fd, &mut stat) })?;
+    if stat.st_mode & libc::S_IFMT != libc::S_IFIFO {
+        return Err(io::const_io_error!(io::ErrorKind::InvalidInput, "file descriptor is not a pipe"));
+    }
+
+    let mode = cvt(unsafe { libc::fcntl_arg0(fd, libc::F_GETFL) })? & libc::O_ACCMODE;
+    let allowed = if write {
+        mode == libc::O_WRONLY || mode == libc::O_RDWR
+    } else {
+        mode == libc::O_RDONLY || mode == libc::O_RDWR
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(io::const_io_error!(
+            io::ErrorKind::InvalidInput,
+            "pipe was not opened in the requested direction",
+        ))
+    }
+}
+
 impl AsRawFd for AnonPipe {
     fn as_raw_fd(&self) -> RawFd {
         self.0.as_raw_fd()
@@ -143,7 +173,6 @@ impl FromRawFd for AnonPipe {
 }
 
 mod libc {
-    pub use sgx_libc::ocall::{pipe2, poll};
+    pub use sgx_libc::ocall::{fcntl_arg0, fstat64, pipe2, poll};
     pub use sgx_libc::*;
-    
 }
\ No newline at end of file
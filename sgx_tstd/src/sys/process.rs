@@ -0,0 +1,73 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::ffi::CStr;
+use crate::io;
+use crate::ptr;
+use crate::sys::cvt;
+use crate::vec::Vec;
+
+use sgx_libc as libc;
+
+pub struct RawOutput {
+    pub wait_status: i32,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// Runs a command on the host and waits for it, capturing at most `limit`
+/// bytes of each of its output streams.
+///
+/// `argv` holds the program followed by its arguments and `envp` holds
+/// `key=value` entries (or a bare `key` to remove a variable), all NUL
+/// terminated.
+pub fn run(
+    argv: &[u8],
+    envp: &[u8],
+    env_clear: bool,
+    cwd: Option<&CStr>,
+    limit: usize,
+) -> io::Result<RawOutput> {
+    let mut stdout = vec![0_u8; limit];
+    let mut stderr = vec![0_u8; limit];
+    let mut out_len = 0;
+    let mut err_len = 0;
+    let mut wait_status = 0;
+
+    cvt(unsafe {
+        libc::ocall::run_command(
+            argv,
+            envp,
+            env_clear,
+            cwd.map_or(ptr::null(), |c| c.as_ptr()),
+            &mut stdout,
+            &mut out_len,
+            &mut stderr,
+            &mut err_len,
+            &mut wait_status,
+        )
+    })?;
+
+    // The reported lengths come from the host and may exceed what was copied.
+    stdout.truncate(out_len.min(limit));
+    stderr.truncate(err_len.min(limit));
+    Ok(RawOutput {
+        wait_status,
+        stdout,
+        stderr,
+    })
+}
@@ -61,6 +61,9 @@ impl OpenOptions {
     pub fn binary(&mut self, binary: bool) {
         self.binary = binary;
     }
+    pub fn creates(&self) -> bool {
+        self.write || self.append
+    }
 
     fn get_access_mode(&self) -> io::Result<String> {
         let mut mode = match (self.read, self.write, self.append) {
@@ -113,12 +116,10 @@ impl SgxFile {
         auto: bool,
         cache_size: Option<u64>,
     ) -> io::Result<SgxFile> {
-        let file = if cache_size.is_some() {
-            SgxFileStream::open_ex(path, opts, key, cache_size.unwrap())
-        } else if auto == true || key.is_none() {
-            SgxFileStream::open_auto_key(path, opts)
-        } else {
-            SgxFileStream::open(path, opts, key.unwrap())
+        let file = match (cache_size, key) {
+            (Some(cache_size), _) => SgxFileStream::open_ex(path, opts, key, cache_size),
+            (None, Some(key)) if !auto => SgxFileStream::open(path, opts, key),
+            _ => SgxFileStream::open_auto_key(path, opts),
         };
 
         file.map(SgxFile).map_err(|err| match err {
@@ -214,6 +215,33 @@ impl SgxFile {
         })
     }
 
+    pub fn set_len(&self, size: u64) -> io::Result<()> {
+        const ZEROS: [u8; 4096] = [0; 4096];
+
+        let pos = self.tell()?;
+        let mut len = self.seek(SeekFrom::End(0))?;
+        if size < len {
+            self.seek(SeekFrom::Start(pos))?;
+            return Err(io::const_io_error!(
+                io::ErrorKind::Unsupported,
+                "an open protected file cannot be truncated",
+            ));
+        }
+        while len < size {
+            let n = (size - len).min(ZEROS.len() as u64) as usize;
+            let written = self.write(&ZEROS[..n])?;
+            if written == 0 {
+                return Err(io::const_io_error!(
+                    io::ErrorKind::WriteZero,
+                    "failed to extend the protected file",
+                ));
+            }
+            len += written as u64;
+        }
+        self.seek(SeekFrom::Start(pos))?;
+        Ok(())
+    }
+
     pub fn is_eof(&self) -> bool {
         self.0.is_eof()
     }
@@ -320,7 +348,7 @@ pub fn copy(from: &Path, to: &Path) -> io::Result<u64> {
         if #[cfg(feature = "untrusted_fs")] {
             use crate::fs;
         } else {
-            use crate::untrusted::fs;
+            use crate::fs;
             use crate::untrusted::path::PathEx;
         }
     }
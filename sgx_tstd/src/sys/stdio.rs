@@ -15,12 +15,46 @@
 // specific language governing permissions and limitations
 // under the License..
 
-use crate::io::{self, IoSlice, IoSliceMut};
+use crate::io::{self, IoSlice, IoSliceMut, StdioSink};
 use crate::mem::ManuallyDrop;
 use crate::os::unix::io::FromRawFd;
+use crate::sync::{Arc, PoisonError, SgxRwLock as RwLock};
 use crate::sys::fd::FileDesc;
 use sgx_libc as libc;
 
+type Sink = Option<Arc<dyn StdioSink>>;
+
+static STDOUT_SINK: RwLock<Sink> = RwLock::new(None);
+static STDERR_SINK: RwLock<Sink> = RwLock::new(None);
+
+pub fn set_stdout_sink(sink: Sink) -> Sink {
+    crate::mem::replace(&mut *STDOUT_SINK.write().unwrap_or_else(PoisonError::into_inner), sink)
+}
+
+pub fn set_stderr_sink(sink: Sink) -> Sink {
+    crate::mem::replace(&mut *STDERR_SINK.write().unwrap_or_else(PoisonError::into_inner), sink)
+}
+
+// The sink is cloned out of the lock so that writing to it does not hold the
+// lock, and a sink can be replaced while another thread is writing.
+fn sink(which: &RwLock<Sink>) -> Sink {
+    which.read().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+fn write_fd(fd: libc::c_int, buf: &[u8]) -> io::Result<usize> {
+    if cfg!(feature = "no_host_stdio") {
+        return Ok(buf.len());
+    }
+    unsafe { ManuallyDrop::new(FileDesc::from_raw_fd(fd)).write(buf) }
+}
+
+fn write_fd_vectored(fd: libc::c_int, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+    if cfg!(feature = "no_host_stdio") {
+        return Ok(bufs.iter().map(|b| b.len()).sum());
+    }
+    unsafe { ManuallyDrop::new(FileDesc::from_raw_fd(fd)).write_vectored(bufs) }
+}
+
 pub struct Stdin(());
 pub struct Stdout(());
 pub struct Stderr(());
@@ -33,10 +67,16 @@ impl Stdin {
 
 impl io::Read for Stdin {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if cfg!(feature = "no_host_stdio") {
+            return Ok(0);
+        }
         unsafe { ManuallyDrop::new(FileDesc::from_raw_fd(libc::STDIN_FILENO)).read(buf) }
     }
 
     fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        if cfg!(feature = "no_host_stdio") {
+            return Ok(0);
+        }
         unsafe { ManuallyDrop::new(FileDesc::from_raw_fd(libc::STDIN_FILENO)).read_vectored(bufs) }
     }
 
@@ -54,12 +94,16 @@ impl Stdout {
 
 impl io::Write for Stdout {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        unsafe { ManuallyDrop::new(FileDesc::from_raw_fd(libc::STDOUT_FILENO)).write(buf) }
+        match sink(&STDOUT_SINK) {
+            Some(sink) => sink.write(buf),
+            None => write_fd(libc::STDOUT_FILENO, buf),
+        }
     }
 
     fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
-        unsafe {
-            ManuallyDrop::new(FileDesc::from_raw_fd(libc::STDOUT_FILENO)).write_vectored(bufs)
+        match sink(&STDOUT_SINK) {
+            Some(sink) => io::default_write_vectored(|b| sink.write(b), bufs),
+            None => write_fd_vectored(libc::STDOUT_FILENO, bufs),
         }
     }
 
@@ -69,7 +113,10 @@ impl io::Write for Stdout {
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+        match sink(&STDOUT_SINK) {
+            Some(sink) => sink.flush(),
+            None => Ok(()),
+        }
     }
 }
 
@@ -81,12 +128,16 @@ impl Stderr {
 
 impl io::Write for Stderr {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        unsafe { ManuallyDrop::new(FileDesc::from_raw_fd(libc::STDERR_FILENO)).write(buf) }
+        match sink(&STDERR_SINK) {
+            Some(sink) => sink.write(buf),
+            None => write_fd(libc::STDERR_FILENO, buf),
+        }
     }
 
     fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
-        unsafe {
-            ManuallyDrop::new(FileDesc::from_raw_fd(libc::STDERR_FILENO)).write_vectored(bufs)
+        match sink(&STDERR_SINK) {
+            Some(sink) => io::default_write_vectored(|b| sink.write(b), bufs),
+            None => write_fd_vectored(libc::STDERR_FILENO, bufs),
         }
     }
 
@@ -96,7 +147,10 @@ impl io::Write for Stderr {
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+        match sink(&STDERR_SINK) {
+            Some(sink) => sink.flush(),
+            None => Ok(()),
+        }
     }
 }
 
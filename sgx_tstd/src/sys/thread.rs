@@ -22,6 +22,7 @@ use crate::mem;
 use crate::num::NonZeroUsize;
 use crate::ptr;
 use crate::sys::os;
+use crate::sys::time::Instant;
 use crate::time::Duration;
 
 use sgx_trts::enclave;
@@ -85,7 +86,25 @@ impl Thread {
         }
     }
 
-    pub fn sleep(dur: Duration) {
+    /// Sleeps for at least `dur` and returns how long the thread actually slept.
+    pub fn sleep(dur: Duration) -> Duration {
+        let start = Instant::now();
+        let mut remaining = dur;
+        loop {
+            Self::nanosleep(remaining);
+
+            // The host decides when we wake up. Re-check the deadline against
+            // `Instant`, which is also read from the host but cannot go
+            // backwards, and go back to sleep if woken early.
+            let elapsed = Instant::now().checked_sub_instant(&start).unwrap_or_default();
+            match dur.checked_sub(elapsed) {
+                Some(left) if !left.is_zero() => remaining = left,
+                _ => return elapsed,
+            }
+        }
+    }
+
+    fn nanosleep(dur: Duration) {
         let mut secs = dur.as_secs();
         let mut nsecs = dur.subsec_nanos() as _;
 
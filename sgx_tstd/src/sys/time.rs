@@ -175,11 +175,12 @@ impl From<libc::timespec> for Timespec {
 mod inner {
     use crate::fmt;
     use crate::mem::MaybeUninit;
+    use crate::sync::atomic::{AtomicU64, Ordering};
     use crate::sys::cvt;
     use crate::time::Duration;
 
     use super::libc;
-    use super::{SystemTime, Timespec};
+    use super::{SystemTime, Timespec, NSEC_PER_SEC};
 
     #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
     pub struct Instant {
@@ -188,7 +189,24 @@ mod inner {
 
     impl Instant {
         pub fn now() -> Instant {
-            Instant { t: Timespec::now(libc::CLOCK_MONOTONIC) }
+            // The clock is read from the host. The only thing enforced here is
+            // that it never goes backwards: an instant earlier than one
+            // already observed is replaced by that one. The host still
+            // decides how fast time passes and can make it jump forwards.
+            static LAST: AtomicU64 = AtomicU64::new(0);
+
+            let t = Timespec::now(libc::CLOCK_MONOTONIC);
+            let nanos = (t.tv_sec.max(0) as u64)
+                .saturating_mul(NSEC_PER_SEC)
+                .saturating_add(t.tv_nsec.0 as u64);
+            let last = LAST.fetch_max(nanos, Ordering::AcqRel);
+            if last > nanos {
+                Instant {
+                    t: Timespec::new((last / NSEC_PER_SEC) as i64, (last % NSEC_PER_SEC) as i64),
+                }
+            } else {
+                Instant { t }
+            }
         }
 
         pub fn checked_sub_instant(&self, other: &Instant) -> Option<Duration> {
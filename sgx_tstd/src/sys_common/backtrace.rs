@@ -26,6 +26,7 @@ use crate::io::prelude::*;
 use crate::path::{Path, PathBuf};
 use crate::sync::{SgxMutex as Mutex, PoisonError};
 use crate::sys::backtrace::{self, BacktraceFmt, BytesOrWideString, PrintFmt};
+use crate::sys_common::thread_info;
 
 /// Max number of frames to print.
 const MAX_NB_FRAMES: usize = 100;
@@ -64,7 +65,15 @@ unsafe fn _print_fmt(fmt: &mut fmt::Formatter<'_>, print_fmt: PrintFmt) -> fmt::
     let mut print_path = move |fmt: &mut fmt::Formatter<'_>, bows: BytesOrWideString<'_>| {
         output_filename(fmt, bows, print_fmt, cwd.as_ref())
     };
-    writeln!(fmt, "stack backtrace:")?;
+    match thread_info::current_thread() {
+        Some(thread) => writeln!(
+            fmt,
+            "stack backtrace of thread '{}'{}:",
+            thread.name().unwrap_or("<unnamed>"),
+            crate::thread::OnTcs(thread.tcs()),
+        )?,
+        None => writeln!(fmt, "stack backtrace:")?,
+    }
     let mut bt_fmt = BacktraceFmt::new(fmt, print_fmt, &mut print_path);
     bt_fmt.add_context()?;
     let mut idx = 0;
@@ -22,6 +22,7 @@ use crate::convert::{TryFrom, TryInto};
 use crate::fmt;
 use crate::io::{self, ErrorKind, IoSlice, IoSliceMut};
 use crate::mem;
+use crate::net::policy::{self, Protocol};
 use crate::net::{Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
 use crate::ptr;
 use crate::sys::common::small_c_string::run_with_cstr;
@@ -67,6 +68,14 @@ pub fn getsockopt<T: Copy>(sock: &Socket, level: c_int, option_name: c_int) -> i
             &mut option_value as *mut T as *mut _,
             &mut option_len,
         ))?;
+        // The length comes back from the host; a value that does not fill
+        // `T` exactly means the option was not what we asked for.
+        if option_len as usize != mem::size_of::<T>() {
+            return Err(io::const_io_error!(
+                io::ErrorKind::InvalidData,
+                "getsockopt returned an unexpected option length",
+            ));
+        }
         Ok(option_value)
     }
 }
@@ -210,7 +219,7 @@ impl TcpStream {
     }
 
     pub fn connect(addr: io::Result<&SocketAddr>) -> io::Result<TcpStream> {
-        let addr = addr?;
+        let addr = &policy::check(Protocol::Tcp, addr?)?;
 
         init();
 
@@ -221,7 +230,7 @@ impl TcpStream {
     }
 
     pub fn connect_socket(&self, addr: io::Result<&SocketAddr>) -> io::Result<()> {
-        let addr = addr?;
+        let addr = &policy::check(Protocol::Tcp, addr?)?;
 
         init();
 
@@ -230,6 +239,8 @@ impl TcpStream {
     }
 
     pub fn connect_timeout(addr: &SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+        let addr = &policy::check(Protocol::Tcp, addr)?;
+
         init();
 
         let sock = Socket::new_socket_addr_type(addr, c::SOCK_STREAM)?;
@@ -238,6 +249,7 @@ impl TcpStream {
     }
 
     pub fn connect_socket_timeout(&self, addr: &SocketAddr, timeout: Duration) -> io::Result<()> {
+        let addr = &policy::check(Protocol::Tcp, addr)?;
         self.inner.connect_timeout(addr, timeout)
     }
 
@@ -574,7 +586,7 @@ impl UdpSocket {
 
     pub fn send_to(&self, buf: &[u8], dst: &SocketAddr) -> io::Result<usize> {
         let len = cmp::min(buf.len(), <wrlen_t>::MAX) as wrlen_t;
-        let (dst, dstlen) = dst.into_inner();
+        let (dst, dstlen) = (&policy::check(Protocol::Udp, dst)?).into_inner();
         let ret = cvt(unsafe {
             c::sendto(
                 self.inner.as_raw(),
@@ -720,7 +732,7 @@ impl UdpSocket {
     }
 
     pub fn connect(&self, addr: io::Result<&SocketAddr>) -> io::Result<()> {
-        let (addr, len) = addr?.into_inner();
+        let (addr, len) = (&policy::check(Protocol::Udp, addr?)?).into_inner();
         cvt_r(|| unsafe { c::connect(self.inner.as_raw(), addr.as_ptr(), len) }).map(drop)
     }
 }
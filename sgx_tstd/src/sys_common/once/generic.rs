@@ -97,8 +97,9 @@ const POISONED: usize = 0x1;
 const RUNNING: usize = 0x2;
 const COMPLETE: usize = 0x3;
 
-// Mask to learn about the state. All other bits are the queue of waiters if
-// this is in the RUNNING state.
+// Mask to learn about the state. All other bits are the queue of waiters,
+// which can be non-empty in any state but COMPLETE: threads blocked in
+// `Once::wait` also queue while the `Once` is INCOMPLETE or POISONED.
 const STATE_MASK: usize = 0x3;
 
 // Representation of a node in the linked list of waiters, used while in the
@@ -153,17 +154,19 @@ impl Once {
     pub fn call(&self, ignore_poisoning: bool, init: &mut dyn FnMut(&public::OnceState)) {
         let mut state_and_queue = self.state_and_queue.load(Ordering::Acquire);
         loop {
-            match state_and_queue.addr() {
+            let state = state_and_queue.addr() & STATE_MASK;
+            match state {
                 COMPLETE => break,
                 POISONED if !ignore_poisoning => {
                     // Panic to propagate the poison.
                     panic!("Once instance has previously been poisoned");
                 }
                 POISONED | INCOMPLETE => {
-                    // Try to register this thread as the one RUNNING.
+                    // Try to register this thread as the one RUNNING. Threads
+                    // blocked in `Once::wait` may already be queued, keep them.
                     let exchange_result = self.state_and_queue.compare_exchange(
                         state_and_queue,
-                        ptr::invalid_mut(RUNNING),
+                        state_and_queue.map_addr(|q| (q & !STATE_MASK) | RUNNING),
                         Ordering::Acquire,
                         Ordering::Acquire,
                     );
@@ -181,7 +184,7 @@ impl Once {
                     // poisoned or not.
                     let init_state = public::OnceState {
                         inner: OnceState {
-                            poisoned: state_and_queue.addr() == POISONED,
+                            poisoned: state == POISONED,
                             set_state_on_drop_to: Cell::new(ptr::invalid_mut(COMPLETE)),
                         },
                     };
@@ -192,23 +195,50 @@ impl Once {
                 _ => {
                     // All other values must be RUNNING with possibly a
                     // pointer to the waiter queue in the more significant bits.
-                    assert!(state_and_queue.addr() & STATE_MASK == RUNNING);
-                    wait(&self.state_and_queue, state_and_queue);
-                    state_and_queue = self.state_and_queue.load(Ordering::Acquire);
+                    assert!(state == RUNNING);
+                    state_and_queue = wait(&self.state_and_queue, state_and_queue, true);
+                }
+            }
+        }
+    }
+
+    // Blocks until the `Once` is complete. With `ignore_poisoning` a poisoned
+    // `Once` is waited on like an incomplete one, until a later
+    // `call_once_force` completes it.
+    #[cold]
+    pub fn wait(&self, ignore_poisoning: bool) {
+        let mut state_and_queue = self.state_and_queue.load(Ordering::Acquire);
+        loop {
+            match state_and_queue.addr() & STATE_MASK {
+                COMPLETE => return,
+                POISONED if !ignore_poisoning => {
+                    // Panic to propagate the poison.
+                    panic!("Once instance has previously been poisoned");
+                }
+                _ => {
+                    state_and_queue =
+                        wait(&self.state_and_queue, state_and_queue, !ignore_poisoning);
                 }
             }
         }
     }
 }
 
-fn wait(state_and_queue: &AtomicPtr<Masked>, mut current_state: *mut Masked) {
+// Queues the current thread until the state changes to COMPLETE, or to
+// POISONED if `return_on_poisoned`, and returns the new state. Waiters may be
+// queued in any state but COMPLETE; whoever runs the initialization wakes
+// them up when it is done.
+fn wait(
+    state_and_queue: &AtomicPtr<Masked>,
+    mut current_state: *mut Masked,
+    return_on_poisoned: bool,
+) -> *mut Masked {
     // Note: the following code was carefully written to avoid creating a
     // mutable reference to `node` that gets aliased.
     loop {
-        // Don't queue this thread if the status is no longer running,
-        // otherwise we will not be woken up.
-        if current_state.addr() & STATE_MASK != RUNNING {
-            return;
+        let state = current_state.addr() & STATE_MASK;
+        if state == COMPLETE || (state == POISONED && return_on_poisoned) {
+            return current_state;
         }
 
         // Create the node for our current thread.
@@ -223,9 +253,9 @@ fn wait(state_and_queue: &AtomicPtr<Masked>, mut current_state: *mut Masked) {
         // that another thread didn't just replace the head of the linked list.
         let exchange_result = state_and_queue.compare_exchange(
             current_state,
-            me.with_addr(me.addr() | RUNNING),
+            me.with_addr(me.addr() | state),
             Ordering::Release,
-            Ordering::Relaxed,
+            Ordering::Acquire,
         );
         if let Err(old) = exchange_result {
             current_state = old;
@@ -244,7 +274,7 @@ fn wait(state_and_queue: &AtomicPtr<Masked>, mut current_state: *mut Masked) {
             // an `unpark` just before on an unparked thread it does not park.
             thread::park();
         }
-        break;
+        return state_and_queue.load(Ordering::Acquire);
     }
 }
 
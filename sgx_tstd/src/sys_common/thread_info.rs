@@ -32,8 +32,10 @@ impl SgxThreadInfo {
         THREAD_INFO
             .try_with(move |thread_info| {
                 let mut thread_info = thread_info.borrow_mut();
-                let thread_info = thread_info.get_or_insert_with(|| SgxThreadInfo {
-                    thread: SgxThread::new(None),
+                let thread_info = thread_info.get_or_insert_with(|| {
+                    let thread = SgxThread::new(None);
+                    thread.record_tcs();
+                    SgxThreadInfo { thread }
                 });
                 f(thread_info)
             })
@@ -49,6 +51,7 @@ pub fn set(thread: SgxThread) {
     THREAD_INFO.with(move |thread_info| {
         let mut thread_info = thread_info.borrow_mut();
         //rtassert!(thread_info.is_none());
+        thread.record_tcs();
         *thread_info = Some(SgxThreadInfo { thread });
     });
 }
@@ -15,5 +15,5 @@
 // specific language governing permissions and limitations
 // under the License..
 
-mod generic;
-pub use generic::Parker;
\ No newline at end of file
+mod tcs_event;
+pub use tcs_event::Parker;
\ No newline at end of file
@@ -15,38 +15,46 @@
 // specific language governing permissions and limitations
 // under the License..
 
-//! Parker implementation based on a Mutex and Condvar.
+//! Parker implementation based on the per-TCS wait events of the uRTS.
+//!
+//! This is the enclave counterpart of the futex parker: a parked thread
+//! sleeps on the event of its own TCS, and `unpark` sets exactly that event,
+//! so a blocked thread consumes no CPU and wakeups are targeted. No mutex or
+//! condition variable is involved.
 
 use crate::pin::Pin;
 use crate::sync::atomic::AtomicUsize;
 use crate::sync::atomic::Ordering::SeqCst;
-use crate::sync::{SgxCondvar as Condvar, SgxMutex as Mutex};
+use crate::sys::locks::mutex::{thread_set_event, thread_wait_event};
 use crate::time::Duration;
 
+use sgx_trts::enclave::SgxThreadData;
+
 const EMPTY: usize = 0;
 const PARKED: usize = 1;
 const NOTIFIED: usize = 2;
 
+// An infinite timeout for `thread_wait_event`.
+const FOREVER: Duration = Duration::new(u64::MAX, 1_000_000_000 - 1);
+
 pub struct Parker {
     state: AtomicUsize,
-    lock: Mutex<()>,
-    cvar: Condvar,
+    // The TCS the owning thread is bound to, recorded before it parks. A
+    // thread is created on the TCS of its parent, so this cannot be filled in
+    // by `new`.
+    tcs: AtomicUsize,
 }
 
 impl Parker {
-    /// Construct the generic parker. The UNIX parker implementation
+    /// Construct the event parker. The UNIX parker implementation
     /// requires this to happen in-place.
     #[allow(clippy::new_ret_no_self)]
     pub unsafe fn new(parker: *mut Parker) {
-        parker.write(Parker {
-            state: AtomicUsize::new(EMPTY),
-            lock: Mutex::new(()),
-            cvar: Condvar::new(),
-        });
+        parker.write(Parker { state: AtomicUsize::new(EMPTY), tcs: AtomicUsize::new(0) });
     }
 
-    // This implementation doesn't require `unsafe` and `Pin`, but other implementations do.
-    #[allow(clippy::single_match)]
+    // Assumes this is only called by the thread that owns the Parker,
+    // which means that `self.state != PARKED`.
     pub unsafe fn park(self: Pin<&Self>) {
         // If we were previously notified then we consume this notification and
         // return quickly.
@@ -54,57 +62,57 @@ impl Parker {
             return;
         }
 
-        // Otherwise we need to coordinate going to sleep
-        let mut m = self.lock.lock().unwrap();
+        let tcs = SgxThreadData::current().get_tcs();
+        self.tcs.store(tcs, SeqCst);
         match self.state.compare_exchange(EMPTY, PARKED, SeqCst, SeqCst) {
             Ok(_) => {}
             Err(NOTIFIED) => {
                 // We must read here, even though we know it will be `NOTIFIED`.
-                // This is because `unpark` may have been called again since we read
-                // `NOTIFIED` in the `compare_exchange` above. We must perform an
-                // acquire operation that synchronizes with that `unpark` to observe
-                // any writes it made before the call to unpark. To do that we must
-                // read from the write it made to `state`.
+                // This is because `unpark` may have been called again since we
+                // read `NOTIFIED` in the `compare_exchange` above, and we must
+                // synchronize with the write it made to `state`.
                 let old = self.state.swap(EMPTY, SeqCst);
                 assert_eq!(old, NOTIFIED, "park state changed unexpectedly");
                 return;
-            } // should consume this notification, so prohibit spurious wakeups in next park.
+            }
             Err(_) => panic!("inconsistent park state"),
         }
+
         loop {
-            m = self.cvar.wait(m).unwrap();
-            match self.state.compare_exchange(NOTIFIED, EMPTY, SeqCst, SeqCst) {
-                Ok(_) => return, // got a notification
-                Err(_) => {}     // spurious wakeup, go back to sleep
+            // The event is sticky, so an `unpark` that sets it before we get to
+            // sleep is not lost. Wakeups from other users of the event (or a
+            // stale event) are spurious and put us back to sleep.
+            thread_wait_event(tcs, FOREVER);
+            if self.state.compare_exchange(NOTIFIED, EMPTY, SeqCst, SeqCst).is_ok() {
+                return;
             }
         }
     }
 
-    // This implementation doesn't require `unsafe` and `Pin`, but other implementations do.
+    // Assumes this is only called by the thread that owns the Parker,
+    // which means that `self.state != PARKED`.
     pub unsafe fn park_timeout(self: Pin<&Self>, dur: Duration) {
-        // Like `park` above we have a fast path for an already-notified thread, and
-        // afterwards we start coordinating for a sleep.
-        // return quickly.
         if self.state.compare_exchange(NOTIFIED, EMPTY, SeqCst, SeqCst).is_ok() {
             return;
         }
-        let m = self.lock.lock().unwrap();
+
+        let tcs = SgxThreadData::current().get_tcs();
+        self.tcs.store(tcs, SeqCst);
         match self.state.compare_exchange(EMPTY, PARKED, SeqCst, SeqCst) {
             Ok(_) => {}
             Err(NOTIFIED) => {
-                // We must read again here, see `park`.
                 let old = self.state.swap(EMPTY, SeqCst);
                 assert_eq!(old, NOTIFIED, "park state changed unexpectedly");
                 return;
-            } // should consume this notification, so prohibit spurious wakeups in next park.
+            }
             Err(_) => panic!("inconsistent park_timeout state"),
         }
 
         // Wait with a timeout, and if we spuriously wake up or otherwise wake up
-        // from a notification we just want to unconditionally set the state back to
-        // empty, either consuming a notification or un-flagging ourselves as
+        // from a notification we just want to unconditionally set the state back
+        // to empty, either consuming a notification or un-flagging ourselves as
         // parked.
-        let (_m, _result) = self.cvar.wait_timeout(m, dur).unwrap();
+        thread_wait_event(tcs, dur);
         match self.state.swap(EMPTY, SeqCst) {
             NOTIFIED => {} // got a notification, hurray!
             PARKED => {}   // no notification, alas
@@ -112,33 +120,22 @@ impl Parker {
         }
     }
 
-    // This implementation doesn't require `Pin`, but other implementations do.
     pub fn unpark(self: Pin<&Self>) {
         // To ensure the unparked thread will observe any writes we made
         // before this call, we must perform a release operation that `park`
-        // can synchronize with. To do that we must write `NOTIFIED` even if
-        // `state` is already `NOTIFIED`. That is why this must be a swap
-        // rather than a compare-and-swap that returns if it reads `NOTIFIED`
-        // on failure.
+        // can synchronize with, so this must be a swap even if `state` is
+        // already `NOTIFIED`.
         match self.state.swap(NOTIFIED, SeqCst) {
-            EMPTY => return,    // no one was waiting
-            NOTIFIED => return, // already unparked
-            PARKED => {}        // gotta go wake someone up
+            EMPTY => {}    // no one was waiting
+            NOTIFIED => {} // already unparked
+            PARKED => {
+                // `tcs` was stored before the transition to `PARKED`.
+                let tcs = self.tcs.load(SeqCst);
+                unsafe {
+                    thread_set_event(tcs);
+                }
+            }
             _ => panic!("inconsistent state in unpark"),
         }
-
-        // There is a period between when the parked thread sets `state` to
-        // `PARKED` (or last checked `state` in the case of a spurious wake
-        // up) and when it actually waits on `cvar`. If we were to notify
-        // during this period it would be ignored and then when the parked
-        // thread went to sleep it would never wake up. Fortunately, it has
-        // `lock` locked at this stage so we can acquire `lock` to wait until
-        // it is ready to receive the notification.
-        //
-        // Releasing `lock` before the call to `notify_one` means that when the
-        // parked thread wakes it doesn't get woken only to have to wait for us
-        // to release `lock`.
-        drop(self.lock.lock().unwrap());
-        self.cvar.notify_one()
     }
 }
@@ -0,0 +1,185 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::future::Future;
+use crate::pin::Pin;
+use crate::sync::atomic::{AtomicBool, Ordering};
+use crate::sync::Arc;
+use crate::task::{Context, Poll, Wake, Waker};
+use crate::thread::{self, SgxThread};
+
+#[cfg(feature = "thread")]
+use crate::fmt;
+#[cfg(feature = "thread")]
+use crate::io;
+#[cfg(feature = "thread")]
+use crate::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "thread")]
+use crate::sync::{PoisonError, SgxMutex};
+
+use super::reactor;
+
+struct ThreadWaker {
+    thread: SgxThread,
+    woken: AtomicBool,
+}
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if !self.woken.swap(true, Ordering::Release) {
+            self.thread.unpark();
+        }
+    }
+}
+
+/// Runs a future to completion on the current thread.
+///
+/// While the future is pending, the current thread drives the reactor: it
+/// waits in the `poll` ocall for the file descriptors that futures are
+/// waiting on, or parks until the next timer expires or a waker is invoked.
+///
+/// # Examples
+///
+/// ```
+/// use std::task;
+/// use std::time::Duration;
+///
+/// let value = task::block_on(async {
+///     task::sleep(Duration::from_millis(1)).await;
+///     42
+/// });
+/// assert_eq!(value, 42);
+/// ```
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let signal = Arc::new(ThreadWaker {
+        thread: thread::current(),
+        woken: AtomicBool::new(false),
+    });
+    let waker = Waker::from(Arc::clone(&signal));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = future;
+    // SAFETY: the future is shadowed and never moved again.
+    let mut future = unsafe { Pin::new_unchecked(&mut future) };
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        while !signal.woken.swap(false, Ordering::Acquire) {
+            reactor::turn();
+        }
+    }
+}
+
+#[cfg(feature = "thread")]
+struct Packet<T> {
+    result: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// An owned permission to await the result of a spawned future.
+///
+/// Awaiting the handle, or calling [`join`](JoinHandle::join), yields the
+/// output of the future, or the panic payload if it panicked. Dropping the
+/// handle detaches the task.
+#[cfg(feature = "thread")]
+pub struct JoinHandle<T> {
+    packet: Arc<SgxMutex<Packet<T>>>,
+    thread: SgxThread,
+}
+
+#[cfg(feature = "thread")]
+impl<T> JoinHandle<T> {
+    /// Blocks the current thread until the task has finished.
+    pub fn join(self) -> thread::Result<T> {
+        block_on(self)
+    }
+
+    /// Returns the thread the task is running on.
+    pub fn thread(&self) -> &SgxThread {
+        &self.thread
+    }
+
+    /// Checks whether the task has finished, without blocking.
+    pub fn is_finished(&self) -> bool {
+        self.packet
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .result
+            .is_some()
+    }
+}
+
+#[cfg(feature = "thread")]
+impl<T> Future for JoinHandle<T> {
+    type Output = thread::Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<thread::Result<T>> {
+        let mut packet = self.packet.lock().unwrap_or_else(PoisonError::into_inner);
+        match packet.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                packet.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(feature = "thread")]
+impl<T> fmt::Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinHandle").finish_non_exhaustive()
+    }
+}
+
+/// Runs a future to completion on a new enclave thread.
+///
+/// Each task occupies one thread, and so one TCS, for its whole lifetime.
+/// When the enclave has no TCS left for a new thread, an error is returned
+/// instead of queueing the task.
+#[cfg(feature = "thread")]
+pub fn spawn<F>(future: F) -> io::Result<JoinHandle<F::Output>>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let packet = Arc::new(SgxMutex::new(Packet {
+        result: None,
+        waker: None,
+    }));
+    let their_packet = Arc::clone(&packet);
+    let handle = thread::Builder::new().spawn(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| block_on(future)));
+        let waker = {
+            let mut packet = their_packet.lock().unwrap_or_else(PoisonError::into_inner);
+            packet.result = Some(result);
+            packet.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    })?;
+    Ok(JoinHandle {
+        packet,
+        thread: handle.thread().clone(),
+    })
+}
@@ -0,0 +1,49 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Types and Traits for working with asynchronous tasks.
+//!
+//! With the `asyncio` feature this module also provides a small executor for
+//! running futures inside the enclave without an external runtime:
+//! [`block_on`] drives a future on the current thread, [`sleep`] and
+//! [`readable`]/[`writable`] are the leaf futures backed by the reactor, and
+//! [`spawn`] (with the `thread` feature) runs a future on a new enclave
+//! thread.
+//!
+//! The reactor waits for file descriptors through the `poll` ocall declared
+//! in `sgx_asyncio.edl`, and for timers on the enclave's monotonic
+//! [`Instant`](crate::time::Instant). Readiness reported by the host is only a
+//! hint: the following read or write may still fail or block, exactly as with
+//! a host that lies about readiness to a normal program.
+
+#[doc(inline)]
+pub use core::task::*;
+
+#[doc(inline)]
+pub use alloc_crate::task::*;
+
+#[cfg(feature = "asyncio")]
+pub use self::executor::block_on;
+#[cfg(all(feature = "asyncio", feature = "thread"))]
+pub use self::executor::{spawn, JoinHandle};
+#[cfg(feature = "asyncio")]
+pub use self::reactor::{readable, sleep, writable, Ready, Sleep};
+
+#[cfg(feature = "asyncio")]
+mod executor;
+#[cfg(feature = "asyncio")]
+mod reactor;
@@ -0,0 +1,308 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+use crate::collections::BTreeMap;
+use crate::fmt;
+use crate::future::Future;
+use crate::io;
+use crate::os::fd::RawFd;
+use crate::pin::Pin;
+use crate::sync::{PoisonError, SgxMutex};
+use crate::sys::cvt;
+use crate::task::{Context, Poll, Waker};
+use crate::thread;
+use crate::time::{Duration, Instant};
+use crate::vec::Vec;
+
+// Upper bound for a single poll ocall. A waker fired by another thread
+// cannot interrupt an ongoing ocall, so the driving thread comes back at
+// least this often to notice it.
+const MAX_POLL_TIMEOUT: Duration = Duration::from_millis(10);
+
+struct IoWaiter {
+    id: u64,
+    fd: RawFd,
+    events: i16,
+    revents: Option<i16>,
+    waker: Waker,
+}
+
+struct Reactor {
+    next_id: u64,
+    timers: BTreeMap<(Instant, u64), Waker>,
+    io: Vec<IoWaiter>,
+}
+
+static REACTOR: SgxMutex<Reactor> = SgxMutex::new(Reactor {
+    next_id: 0,
+    timers: BTreeMap::new(),
+    io: Vec::new(),
+});
+
+fn reactor() -> crate::sync::SgxMutexGuard<'static, Reactor> {
+    REACTOR.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Reactor {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    // Removes the expired timers and returns their wakers. A waker can run
+    // arbitrary code, including code that registers a timer, so it must only
+    // be woken once the reactor is unlocked.
+    fn expired_timers(&mut self, now: Instant) -> Vec<Waker> {
+        let mut wakers = Vec::new();
+        while let Some(entry) = self.timers.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            wakers.push(entry.remove());
+        }
+        wakers
+    }
+}
+
+fn fire_timers(now: Instant) {
+    let wakers = reactor().expired_timers(now);
+    wakers.into_iter().for_each(Waker::wake);
+}
+
+/// Waits for timers or file descriptors until something may have become
+/// ready, or until `park` returns because a waker unparked this thread.
+pub(super) fn turn() {
+    fire_timers(Instant::_now());
+    let (mut fds, deadline) = {
+        let reactor = reactor();
+        let fds: Vec<libc::pollfd> = reactor
+            .io
+            .iter()
+            .filter(|w| w.revents.is_none())
+            .map(|w| libc::pollfd {
+                fd: w.fd,
+                events: w.events,
+                revents: 0,
+            })
+            .collect();
+        (
+            fds,
+            reactor.timers.keys().next().map(|&(deadline, _)| deadline),
+        )
+    };
+    let timeout = deadline.map(|d| d.saturating_duration_since(Instant::_now()));
+
+    if fds.is_empty() {
+        match timeout {
+            Some(timeout) => thread::park_timeout(timeout),
+            None => thread::park(),
+        }
+    } else {
+        let timeout = timeout.map_or(MAX_POLL_TIMEOUT, |t| t.min(MAX_POLL_TIMEOUT));
+        // Round up so that a timer is not polled for just before it expires.
+        let timeout_ms = ((timeout.as_nanos() + 999_999) / 1_000_000) as libc::c_int;
+        let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout_ms) };
+        // EINTR and friends just mean another round.
+        if cvt(ready).unwrap_or(0) > 0 {
+            let mut wakers = Vec::new();
+            let mut reactor = reactor();
+            for pfd in fds.iter().filter(|pfd| pfd.revents != 0) {
+                for waiter in reactor.io.iter_mut() {
+                    if waiter.fd == pfd.fd
+                        && waiter.events == pfd.events
+                        && waiter.revents.is_none()
+                    {
+                        waiter.revents = Some(pfd.revents);
+                        wakers.push(waiter.waker.clone());
+                    }
+                }
+            }
+            drop(reactor);
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+
+    fire_timers(Instant::_now());
+}
+
+/// A future that completes once a deadline has passed.
+///
+/// Created by [`sleep`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Sleep {
+    deadline: Instant,
+    id: Option<u64>,
+}
+
+/// Returns a future that completes after `dur` has elapsed on the enclave's
+/// monotonic clock.
+pub fn sleep(dur: Duration) -> Sleep {
+    Sleep {
+        deadline: Instant::_now() + dur,
+        id: None,
+    }
+}
+
+impl Sleep {
+    /// Returns the instant at which this future completes.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut reactor = reactor();
+        if Instant::_now() >= self.deadline {
+            if let Some(id) = self.id.take() {
+                reactor.timers.remove(&(self.deadline, id));
+            }
+            return Poll::Ready(());
+        }
+
+        let id = match self.id {
+            Some(id) => id,
+            None => {
+                let id = reactor.next_id();
+                self.id = Some(id);
+                id
+            }
+        };
+        reactor
+            .timers
+            .insert((self.deadline, id), cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            reactor().timers.remove(&(self.deadline, id));
+        }
+    }
+}
+
+impl fmt::Debug for Sleep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sleep")
+            .field("deadline", &self.deadline)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A future that completes once a file descriptor is ready.
+///
+/// Created by [`readable`] or [`writable`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Ready {
+    fd: RawFd,
+    events: i16,
+    id: Option<u64>,
+}
+
+/// Returns a future that completes once `fd` is readable, has hung up or has
+/// a pending error.
+pub fn readable(fd: RawFd) -> Ready {
+    Ready {
+        fd,
+        events: libc::POLLIN,
+        id: None,
+    }
+}
+
+/// Returns a future that completes once `fd` is writable, has hung up or has
+/// a pending error.
+pub fn writable(fd: RawFd) -> Ready {
+    Ready {
+        fd,
+        events: libc::POLLOUT,
+        id: None,
+    }
+}
+
+impl Future for Ready {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut reactor = reactor();
+        match self.id {
+            None => {
+                let id = reactor.next_id();
+                self.id = Some(id);
+                let (fd, events) = (self.fd, self.events);
+                reactor.io.push(IoWaiter {
+                    id,
+                    fd,
+                    events,
+                    revents: None,
+                    waker: cx.waker().clone(),
+                });
+                Poll::Pending
+            }
+            Some(id) => {
+                let pos = reactor
+                    .io
+                    .iter()
+                    .position(|w| w.id == id)
+                    .expect("reactor lost an io waiter");
+                match reactor.io[pos].revents {
+                    Some(revents) => {
+                        reactor.io.swap_remove(pos);
+                        self.id = None;
+                        if revents & libc::POLLNVAL != 0 {
+                            Poll::Ready(Err(io::const_io_error!(
+                                io::ErrorKind::InvalidInput,
+                                "invalid file descriptor",
+                            )))
+                        } else {
+                            Poll::Ready(Ok(()))
+                        }
+                    }
+                    None => {
+                        reactor.io[pos].waker = cx.waker().clone();
+                        Poll::Pending
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Ready {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            reactor().io.retain(|w| w.id != id);
+        }
+    }
+}
+
+impl fmt::Debug for Ready {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ready")
+            .field("fd", &self.fd)
+            .field("events", &self.events)
+            .finish_non_exhaustive()
+    }
+}
+
+mod libc {
+    pub use sgx_libc::ocall::poll;
+    pub use sgx_libc::*;
+}
@@ -38,7 +38,7 @@ use crate::panicking;
 use crate::pin::Pin;
 use crate::ptr::addr_of_mut;
 use crate::str;
-use crate::sync::Arc;
+use crate::sync::{Arc, OnceLock, PoisonError, SgxMutex};
 #[cfg(feature = "thread")]
 use crate::sys::thread as imp;
 use crate::sys_common::thread_info;
@@ -410,11 +410,11 @@ impl Builder {
             // Similarly, the `sys` implementation must guarantee that no references to the closure
             // exist after the thread has terminated, which is signaled by `Thread::join`
             // returning.
-            native: imp::Thread::new(
+            native: Some(imp::Thread::new(
                 mem::transmute::<Box<dyn FnOnce() + 'a>, Box<dyn FnOnce() + 'static>>(
                     Box::new(main),
                 ),
-            )?,
+            )?),
             thread: my_thread,
             packet: my_packet,
         })
@@ -713,7 +713,33 @@ pub fn sleep_ms(ms: u32) {
 /// ```
 #[cfg(feature = "thread")]
 pub fn sleep(dur: Duration) {
-    imp::Thread::sleep(dur)
+    imp::Thread::sleep(dur);
+}
+
+/// Puts the current thread to sleep for at least `dur`, and reports an error
+/// if it was kept asleep past `dur + margin`.
+///
+/// Like [`sleep`], the thread goes back to sleep if it wakes up before `dur`
+/// has elapsed according to [`Instant`]. If more than `dur + margin` has
+/// elapsed by then, an error of kind [`io::ErrorKind::TimedOut`] is returned.
+///
+/// Both checks measure time with [`Instant`], which is read from the host.
+/// The enclave only prevents it from going backwards; the host can still
+/// make it run faster or slower or jump forwards, and so cut a sleep short
+/// or hide a late wakeup. These checks only catch a host that wakes the
+/// thread at the wrong time without also lying about the time.
+///
+/// [`Instant`]: crate::time::Instant
+#[cfg(feature = "thread")]
+pub fn sleep_checked(dur: Duration, margin: Duration) -> io::Result<()> {
+    let slept = imp::Thread::sleep(dur);
+    match dur.checked_add(margin) {
+        Some(limit) if slept > limit => Err(io::const_io_error!(
+            io::ErrorKind::TimedOut,
+            "thread was woken up past the deadline margin",
+        )),
+        _ => Ok(()),
+    }
 }
 
 /// Used to ensure that `park` and `park_timeout` do not unwind, as that can
@@ -984,8 +1010,11 @@ impl ThreadId {
 
 /// The internal representation of a `Thread` handle
 struct Inner {
-    name: Option<CString>, // Guaranteed to be UTF-8
+    name: OnceLock<CString>, // Guaranteed to be UTF-8
     id: ThreadId,
+    // The TCS the thread runs on and its index, see `tcs_index`. Set the
+    // first time the thread info of the thread is initialized.
+    tcs: OnceLock<(usize, usize)>,
     parker: Parker,
 }
 
@@ -1030,8 +1059,9 @@ impl SgxThread {
         let inner = unsafe {
             let mut arc = Arc::<Inner>::new_uninit();
             let ptr = Arc::get_mut_unchecked(&mut arc).as_mut_ptr();
-            addr_of_mut!((*ptr).name).write(name);
+            addr_of_mut!((*ptr).name).write(name.map_or_else(OnceLock::new, OnceLock::from));
             addr_of_mut!((*ptr).id).write(ThreadId::new());
+            addr_of_mut!((*ptr).tcs).write(OnceLock::new());
             Parker::new(addr_of_mut!((*ptr).parker));
             Pin::new_unchecked(arc.assume_init())
         };
@@ -1136,7 +1166,55 @@ impl SgxThread {
     }
 
     fn cname(&self) -> Option<&CStr> {
-        self.inner.name.as_deref()
+        self.inner.name.get().map(CString::as_c_str)
+    }
+
+    // Names a thread that was not given a name when it was created, such as
+    // a thread that entered the enclave through an ecall.
+    pub(crate) fn set_name(&self, name: CString) -> crate::result::Result<(), CString> {
+        self.inner.name.set(name)
+    }
+
+    // Records the TCS of the calling thread, which must be the thread this
+    // handle refers to.
+    pub(crate) fn record_tcs(&self) {
+        self.inner.tcs.get_or_init(|| {
+            let tcs = SgxThreadData::current().get_tcs();
+            (tcs, tcs_index(tcs))
+        });
+    }
+
+    // Returns the address of the thread's TCS and its index.
+    pub(crate) fn tcs(&self) -> Option<(usize, usize)> {
+        self.inner.tcs.get().copied()
+    }
+}
+
+// Returns a small, stable number for a TCS: TCSes are numbered in the order
+// threads first ran on them.
+fn tcs_index(tcs: usize) -> usize {
+    static TCS_TABLE: SgxMutex<Vec<usize>> = SgxMutex::new(Vec::new());
+
+    let mut table = TCS_TABLE.lock().unwrap_or_else(PoisonError::into_inner);
+    match table.iter().position(|&t| t == tcs) {
+        Some(index) => index,
+        None => {
+            table.push(tcs);
+            table.len() - 1
+        }
+    }
+}
+
+/// Formats the TCS of a thread for diagnostics, as ` on TCS #<index>
+/// (<address>)`, or as nothing if the TCS is not known.
+pub(crate) struct OnTcs(pub(crate) Option<(usize, usize)>);
+
+impl fmt::Display for OnTcs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some((tcs, index)) => write!(f, " on TCS #{index} ({tcs:#x})"),
+            None => Ok(()),
+        }
     }
 }
 
@@ -1145,6 +1223,7 @@ impl fmt::Debug for SgxThread {
         f.debug_struct("SgxThread")
             .field("id", &self.id())
             .field("name", &self.name())
+            .field("tcs", &self.tcs().map(|(tcs, _)| tcs as *const ()))
             .finish_non_exhaustive()
     }
 }
@@ -1255,7 +1334,8 @@ impl<'scope, T> Drop for Packet<'scope, T> {
 /// Inner representation for JoinHandle
 #[cfg(feature = "thread")]
 struct JoinInner<'scope, T> {
-    native: imp::Thread,
+    // Only `None` once the thread has been joined or handed out.
+    native: Option<imp::Thread>,
     thread: SgxThread,
     packet: Arc<Packet<'scope, T>>,
 }
@@ -1263,11 +1343,23 @@ struct JoinInner<'scope, T> {
 #[cfg(feature = "thread")]
 impl<'scope, T> JoinInner<'scope, T> {
     fn join(mut self) -> Result<T> {
-        self.native.join();
+        self.native.take().unwrap().join();
         Arc::get_mut(&mut self.packet).unwrap().result.get_mut().take().unwrap()
     }
 }
 
+#[cfg(feature = "thread")]
+impl<'scope, T> Drop for JoinInner<'scope, T> {
+    fn drop(&mut self) {
+        // A scoped thread whose handle is dropped is joined by its scope
+        // instead, so that the scope cannot return while the thread is still
+        // running on its TCS.
+        if let (Some(native), Some(scope)) = (self.native.take(), &self.packet.scope) {
+            scope.add_unjoined(native);
+        }
+    }
+}
+
 /// An owned permission to join on a thread (block on its termination).
 ///
 /// A `JoinHandle` *detaches* the associated thread when it is dropped, which
@@ -1416,14 +1508,15 @@ impl<T> JoinHandle<T> {
 #[cfg(feature = "thread")]
 impl<T> AsInner<imp::Thread> for JoinHandle<T> {
     fn as_inner(&self) -> &imp::Thread {
-        &self.0.native
+        self.0.native.as_ref().unwrap()
     }
 }
 
 #[cfg(feature = "thread")]
 impl<T> IntoInner<imp::Thread> for JoinHandle<T> {
     fn into_inner(self) -> imp::Thread {
-        self.0.native
+        let mut inner = self.0;
+        inner.native.take().unwrap()
     }
 }
 
@@ -20,8 +20,10 @@ use crate::fmt;
 use crate::io;
 use crate::marker::PhantomData;
 use crate::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use crate::mem;
 use crate::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use crate::sync::Arc;
+use crate::sync::{Arc, PoisonError, SgxMutex};
+use crate::sys::thread as imp;
 
 /// A scope to spawn scoped threads in.
 ///
@@ -54,6 +56,10 @@ pub(super) struct ScopeData {
     num_running_threads: AtomicUsize,
     a_thread_panicked: AtomicBool,
     main_thread: SgxThread,
+    // Threads whose handles were dropped without joining them. The packet of
+    // a thread is released before the thread leaves the enclave, so `scope`
+    // joins these before it returns.
+    unjoined: SgxMutex<Vec<imp::Thread>>,
 }
 
 impl ScopeData {
@@ -74,6 +80,9 @@ impl ScopeData {
             self.main_thread.unpark();
         }
     }
+    pub(super) fn add_unjoined(&self, native: imp::Thread) {
+        self.unjoined.lock().unwrap_or_else(PoisonError::into_inner).push(native);
+    }
 }
 
 /// Create a scope for spawning scoped threads.
@@ -151,6 +160,7 @@ where
             num_running_threads: AtomicUsize::new(0),
             main_thread: current(),
             a_thread_panicked: AtomicBool::new(false),
+            unjoined: SgxMutex::new(Vec::new()),
         }),
         env: PhantomData,
         scope: PhantomData,
@@ -163,6 +173,12 @@ where
     while scope.data.num_running_threads.load(Ordering::Acquire) != 0 {
         park();
     }
+    // And until the ones that were not joined explicitly have left the
+    // enclave.
+    let unjoined = mem::take(&mut *scope.data.unjoined.lock().unwrap_or_else(PoisonError::into_inner));
+    for native in unjoined {
+        native.join();
+    }
 
     // Throw any panic from `f`, or the return value of `f` if no thread panicked.
     match result {
@@ -15,6 +15,10 @@
 // specific language governing permissions and limitations
 // under the License..
 
+#[cfg(not(feature = "no_fs"))]
 pub mod fs;
+#[cfg(not(feature = "no_fs"))]
 pub mod path;
+#[cfg(feature = "no_fs")]
+pub(crate) mod path;
 pub mod time;
@@ -665,6 +665,7 @@ extern "C" {
     //
     pub fn sgx_ocalloc(size: size_t) -> *mut c_void;
     pub fn sgx_ocfree();
+    pub fn sgx_ocall(index: uint32_t, ms: *mut c_void) -> sgx_status_t;
 
     /* intel sgx sdk 2.18 */
     pub fn sgx_mm_mutex_create() -> *mut sgx_mm_mutex;
@@ -945,6 +946,13 @@ extern "C" {
 
     pub fn sgx_destroy_enclave(enclave_id: sgx_enclave_id_t) -> sgx_status_t;
 
+    pub fn sgx_ecall(
+        enclave_id: sgx_enclave_id_t,
+        index: c_int,
+        ocall_table: *const c_void,
+        ms: *mut c_void,
+    ) -> sgx_status_t;
+
     /* intel sgx sdk 2.4 */
     pub fn sgx_get_target_info(
         enclave_id: sgx_enclave_id_t,
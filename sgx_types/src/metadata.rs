@@ -264,3 +264,145 @@ pub const SI_FLAGS_TCS: u64 = SI_FLAG_TCS;
 pub const SI_FLAGS_SECS: u64 = SI_FLAG_SECS;
 pub const SI_MASK_TCS: u64 = SI_FLAG_PT_MASK;
 pub const SI_MASK_MEM_ATTRIBUTE: u64 = 0x7;
+
+pub const SI_FLAG_VA: u64 = 0x03 << SI_FLAG_PT_LOW_BIT; /* Version Array Page */
+pub const SI_FLAG_SS_FIRST: u64 = 0x05 << SI_FLAG_PT_LOW_BIT; /* First Page of a Shadow Stack */
+pub const SI_FLAG_SS_REST: u64 = 0x06 << SI_FLAG_PT_LOW_BIT; /* Other Pages of a Shadow Stack */
+
+pub const TCS_FLAG_DBGOPTIN: u64 = 0x1; /* Debug Opt-In, for debug enclaves */
+pub const TCS_FLAG_AEXNOTIFY: u64 = 0x2; /* AEX-Notify, with SGX_FLAGS_AEX_NOTIFY */
+
+/* CPUID.(EAX=12H, ECX=0):EAX */
+pub const SGX_CPUID_SGX1: u32 = 0x1;
+pub const SGX_CPUID_SGX2: u32 = 0x2; /* EDMM: EAUG, EMODPR, EMODT, EACCEPT, EACCEPTCOPY, EMODPE */
+pub const SGX_CPUID_ENCLV: u32 = 0x20; /* EINCVIRTCHILD, EDECVIRTCHILD, ESETCONTEXT */
+pub const SGX_CPUID_OVERSUB: u32 = 0x40; /* ETRACKC, ERDINFO, ELDBC, ELDUC */
+pub const SGX_CPUID_EVERIFYREPORT2: u32 = 0x80;
+pub const SGX_CPUID_EUPDATESVN: u32 = 0x400;
+pub const SGX_CPUID_EDECCSSA: u32 = 0x800;
+
+impl_bitflags! {
+    /// The access and state bits of SECINFO.FLAGS, without the page type.
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub struct SecinfoFlags: u64 {
+        const R = SI_FLAG_R;
+        const W = SI_FLAG_W;
+        const X = SI_FLAG_X;
+        const PENDING = SI_FLAG_PENDING;
+        const MODIFIED = SI_FLAG_MODIFIED;
+        const PR = SI_FLAG_PR;
+    }
+}
+
+// The page type in SECINFO.FLAGS[15:8].
+impl_enum! {
+    #[repr(u64)]
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum SecinfoPageType {
+        SECS = 0x00,
+        TCS = 0x01,
+        REG = 0x02,
+        VA = 0x03,
+        TRIM = 0x04,
+        SS_FIRST = 0x05,
+        SS_REST = 0x06,
+    }
+}
+
+/* arch.h */
+#[repr(C, align(64))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct secinfo_t {
+    pub flags: u64,
+    pub reserved: [u64; 7],
+}
+
+impl secinfo_t {
+    pub const fn new(flags: SecinfoFlags, page_type: SecinfoPageType) -> secinfo_t {
+        secinfo_t {
+            flags: flags.bits() | (page_type as u64) << SI_FLAG_PT_LOW_BIT,
+            reserved: [0; 7],
+        }
+    }
+
+    pub const fn access(&self) -> SecinfoFlags {
+        SecinfoFlags::from_bits_truncate(self.flags)
+    }
+
+    pub fn page_type(&self) -> Option<SecinfoPageType> {
+        SecinfoPageType::from_repr((self.flags & SI_FLAG_PT_MASK) >> SI_FLAG_PT_LOW_BIT)
+    }
+}
+
+impl_bitflags! {
+    /// The bits of TCS.FLAGS.
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub struct TcsFlags: u64 {
+        const DBGOPTIN = TCS_FLAG_DBGOPTIN;
+        const AEXNOTIFY = TCS_FLAG_AEXNOTIFY;
+    }
+}
+
+impl_bitflags! {
+    /// The SGX leaves and features reported in CPUID.(EAX=12H, ECX=0):EAX.
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub struct SgxCpuidFeatures: u32 {
+        const SGX1 = SGX_CPUID_SGX1;
+        const SGX2 = SGX_CPUID_SGX2;
+        const ENCLV = SGX_CPUID_ENCLV;
+        const OVERSUB = SGX_CPUID_OVERSUB;
+        const EVERIFYREPORT2 = SGX_CPUID_EVERIFYREPORT2;
+        const EUPDATESVN = SGX_CPUID_EUPDATESVN;
+        const EDECCSSA = SGX_CPUID_EDECCSSA;
+    }
+}
+
+// The leaf functions of ENCLU, in EAX.
+impl_enum! {
+    #[repr(u32)]
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum EncluLeaf {
+        EREPORT = 0x00,
+        EGETKEY = 0x01,
+        EENTER = 0x02,
+        ERESUME = 0x03,
+        EEXIT = 0x04,
+        EACCEPT = 0x05,
+        EMODPE = 0x06,
+        EACCEPTCOPY = 0x07,
+        EVERIFYREPORT2 = 0x08,
+        EDECCSSA = 0x09,
+    }
+}
+
+// The leaf functions of ENCLS, in EAX.
+impl_enum! {
+    #[repr(u32)]
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum EnclsLeaf {
+        ECREATE = 0x00,
+        EADD = 0x01,
+        EINIT = 0x02,
+        EREMOVE = 0x03,
+        EDBGRD = 0x04,
+        EDBGWR = 0x05,
+        EEXTEND = 0x06,
+        ELDB = 0x07,
+        ELDU = 0x08,
+        EBLOCK = 0x09,
+        EPA = 0x0A,
+        EWB = 0x0B,
+        ETRACK = 0x0C,
+        EAUG = 0x0D,
+        EMODPR = 0x0E,
+        EMODT = 0x0F,
+        ERDINFO = 0x10,
+        ETRACKC = 0x11,
+        ELDBC = 0x12,
+        ELDUC = 0x13,
+        EUPDATESVN = 0x18,
+    }
+}
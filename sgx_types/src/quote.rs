@@ -0,0 +1,533 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Typed views of quotes, reports and target infos.
+//!
+//! The parsers check the structure of the data and give access to its fields, so
+//! neither enclaves nor untrusted tools have to compute offsets into quotes. Nothing
+//! is verified. `SgxDisplay` formats the structures for logs and tools.
+//!
+//! Reports, report bodies, target infos and key requests convert to and from
+//! their byte layout with `as_bytes` and `from_bytes`, to be sent between
+//! enclaves and hosts without transmutes.
+
+use crate::*;
+use core::fmt;
+use core::mem;
+use core::ptr;
+
+/// The TEE type of SGX quotes.
+pub const SGX_QUOTE_TEE_TYPE_SGX: u32 = 0x0000_0000;
+/// The TEE type of TDX quotes.
+pub const SGX_QUOTE_TEE_TYPE_TDX: u32 = 0x0000_0081;
+
+// The size of a TDX 1.0 TD report body.
+const TD10_REPORT_BODY_SIZE: usize = 584;
+/// The certification data type of the PEM PCK certificate chain.
+pub const SGX_QL_CERT_TYPE_PCK_CERT_CHAIN: u16 = 5;
+/// The certification data type of the QE report certification data of version 4.
+pub const SGX_QL_CERT_TYPE_QE_REPORT: u16 = 6;
+
+///
+/// The header of an ECDSA quote.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SgxQuoteHeader {
+    pub version: u16,
+    pub att_key_type: u16,
+    /// The TEE type of version 4; SGX for version 3.
+    pub tee_type: u32,
+    /// The QE SVN of version 3; reserved in version 4.
+    pub qe_svn: u16,
+    /// The PCE SVN of version 3; reserved in version 4.
+    pub pce_svn: u16,
+    pub qe_vendor_id: [u8; 16],
+    pub user_data: [u8; 20],
+}
+
+///
+/// The body of an ECDSA quote.
+///
+#[derive(Clone, Copy)]
+pub enum SgxQuoteBody<'a> {
+    /// The report body of an SGX enclave.
+    Sgx(&'a [u8]),
+    /// The TD report body of a TDX 1.0 trust domain.
+    Td10(&'a [u8]),
+}
+
+impl<'a> SgxQuoteBody<'a> {
+    ///
+    /// Get the report data.
+    ///
+    pub fn get_report_data(&self) -> sgx_report_data_t {
+        match self {
+            SgxQuoteBody::Sgx(body) => read_struct::<sgx_report_body_t>(body).report_data,
+            SgxQuoteBody::Td10(body) => {
+                let mut data = sgx_report_data_t::default();
+                data.d.copy_from_slice(&body[520..584]);
+                data
+            }
+        }
+    }
+}
+
+///
+/// A version 3 or version 4 ECDSA P-256 quote.
+///
+#[derive(Clone, Copy)]
+pub struct SgxQuote<'a> {
+    raw: &'a [u8],
+    header: SgxQuoteHeader,
+    body: SgxQuoteBody<'a>,
+    signed: &'a [u8],
+    signature: &'a [u8],
+    attest_key: &'a [u8],
+    qe_report: &'a [u8],
+    qe_report_signature: &'a [u8],
+    auth_data: &'a [u8],
+    cert_type: u16,
+    cert_data: &'a [u8],
+}
+
+impl<'a> SgxQuote<'a> {
+    ///
+    /// Parses a quote.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_QL_QUOTE_FORMAT_UNSUPPORTED**
+    ///
+    /// The quote is not of version 3 or 4, is not signed with an ECDSA P-256 key, is
+    /// for an unknown TEE, or its parts do not fit its size.
+    ///
+    /// **SGX_QL_QUOTE_CERTIFICATION_DATA_UNSUPPORTED**
+    ///
+    /// A version 4 quote does not carry the QE report certification data.
+    ///
+    pub fn parse(quote: &'a [u8]) -> SgxQuote3Result<SgxQuote<'a>> {
+        Self::parse_parts(quote).ok_or(sgx_quote3_error_t::SGX_QL_QUOTE_FORMAT_UNSUPPORTED)?
+    }
+
+    fn parse_parts(quote: &'a [u8]) -> Option<SgxQuote3Result<SgxQuote<'a>>> {
+        let mut reader = Reader(quote);
+        let version = reader.u16()?;
+        let att_key_type = reader.u16()?;
+        let key_data = reader.u32()?;
+        let header = SgxQuoteHeader {
+            version,
+            att_key_type,
+            tee_type: if version == 3 {
+                SGX_QUOTE_TEE_TYPE_SGX
+            } else {
+                key_data
+            },
+            qe_svn: reader.u16()?,
+            pce_svn: reader.u16()?,
+            qe_vendor_id: reader.array()?,
+            user_data: reader.array()?,
+        };
+        if !(3..=4).contains(&version)
+            || att_key_type != sgx_ql_attestation_algorithm_id_t::SGX_QL_ALG_ECDSA_P256 as u16
+        {
+            return None;
+        }
+        let body = match header.tee_type {
+            SGX_QUOTE_TEE_TYPE_SGX => {
+                SgxQuoteBody::Sgx(reader.take(mem::size_of::<sgx_report_body_t>())?)
+            }
+            SGX_QUOTE_TEE_TYPE_TDX if version == 4 => {
+                SgxQuoteBody::Td10(reader.take(TD10_REPORT_BODY_SIZE)?)
+            }
+            _ => return None,
+        };
+        let signed = &quote[..quote.len() - reader.0.len()];
+
+        let sig_len = reader.u32()? as usize;
+        let mut sig = Reader(reader.take(sig_len)?);
+        if !reader.0.is_empty() {
+            return None;
+        }
+        let signature = sig.take(64)?;
+        let attest_key = sig.take(64)?;
+        if version == 4 {
+            // The QE report and the PCK certification nest in certification data.
+            let cert_type = sig.u16()?;
+            let cert_len = sig.u32()? as usize;
+            let cert = sig.take(cert_len)?;
+            if cert_type != SGX_QL_CERT_TYPE_QE_REPORT {
+                return Some(Err(
+                    sgx_quote3_error_t::SGX_QL_QUOTE_CERTIFICATION_DATA_UNSUPPORTED,
+                ));
+            }
+            sig = Reader(cert);
+        }
+        let qe_report = sig.take(mem::size_of::<sgx_report_body_t>())?;
+        let qe_report_signature = sig.take(64)?;
+        let auth_len = sig.u16()? as usize;
+        let auth_data = sig.take(auth_len)?;
+        let cert_type = sig.u16()?;
+        let cert_len = sig.u32()? as usize;
+        let cert_data = sig.take(cert_len)?;
+        Some(Ok(SgxQuote {
+            raw: quote,
+            header,
+            body,
+            signed,
+            signature,
+            attest_key,
+            qe_report,
+            qe_report_signature,
+            auth_data,
+            cert_type,
+            cert_data,
+        }))
+    }
+
+    /// Get the whole quote.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    pub fn get_header(&self) -> &SgxQuoteHeader {
+        &self.header
+    }
+
+    pub fn get_version(&self) -> u16 {
+        self.header.version
+    }
+
+    pub fn get_tee_type(&self) -> u32 {
+        self.header.tee_type
+    }
+
+    /// Get the vendor of the QE, Intel for the Intel QE.
+    pub fn get_qe_vendor_id(&self) -> &[u8; 16] {
+        &self.header.qe_vendor_id
+    }
+
+    /// Get the SVN of the QE, which is the ISVSVN of the QE report for version 4.
+    pub fn get_qe_svn(&self) -> u16 {
+        match self.header.version {
+            3 => self.header.qe_svn,
+            _ => self.get_qe_report_body().isv_svn,
+        }
+    }
+
+    /// Get the PCE SVN of version 3 quotes. Version 4 quotes state it in the PCK
+    /// certificate only.
+    pub fn get_pce_svn(&self) -> Option<u16> {
+        match self.header.version {
+            3 => Some(self.header.pce_svn),
+            _ => None,
+        }
+    }
+
+    pub fn get_body(&self) -> &SgxQuoteBody<'a> {
+        &self.body
+    }
+
+    /// Get the report body of an SGX quote.
+    pub fn get_report_body(&self) -> Option<sgx_report_body_t> {
+        match self.body {
+            SgxQuoteBody::Sgx(body) => Some(read_struct(body)),
+            SgxQuoteBody::Td10(_) => None,
+        }
+    }
+
+    /// Get the header and the body, over which the attestation key signs.
+    pub fn get_signed_data(&self) -> &'a [u8] {
+        self.signed
+    }
+
+    /// Get the big-endian r || s signature of the attestation key.
+    pub fn get_signature(&self) -> &'a [u8; 64] {
+        self.signature.try_into().unwrap()
+    }
+
+    /// Get the big-endian x || y attestation public key.
+    pub fn get_attest_key(&self) -> &'a [u8; 64] {
+        self.attest_key.try_into().unwrap()
+    }
+
+    /// Get the QE report body, over which the PCK signs.
+    pub fn get_qe_report(&self) -> &'a [u8] {
+        self.qe_report
+    }
+
+    pub fn get_qe_report_body(&self) -> sgx_report_body_t {
+        read_struct(self.qe_report)
+    }
+
+    /// Get the big-endian r || s signature of the PCK over the QE report.
+    pub fn get_qe_report_signature(&self) -> &'a [u8; 64] {
+        self.qe_report_signature.try_into().unwrap()
+    }
+
+    /// Get the authentication data, which the QE report data commits to.
+    pub fn get_auth_data(&self) -> &'a [u8] {
+        self.auth_data
+    }
+
+    /// Get the type of the PCK certification data.
+    pub fn get_certification_type(&self) -> u16 {
+        self.cert_type
+    }
+
+    pub fn get_certification_data(&self) -> &'a [u8] {
+        self.cert_data
+    }
+
+    /// Get the PEM PCK certificate chain, if the quote carries it.
+    pub fn get_pck_cert_chain(&self) -> Option<&'a [u8]> {
+        if self.cert_type == SGX_QL_CERT_TYPE_PCK_CERT_CHAIN {
+            Some(self.cert_data)
+        } else {
+            None
+        }
+    }
+}
+
+// The structures have no padding, so every byte of them is initialized and
+// every byte string of their size is a valid value.
+const _: () = assert!(mem::size_of::<sgx_report_body_t>() == 384);
+const _: () = assert!(mem::size_of::<sgx_report_t>() == 432);
+const _: () = assert!(mem::size_of::<sgx_target_info_t>() == 512);
+const _: () = assert!(mem::size_of::<sgx_key_request_t>() == 512);
+
+impl sgx_report_body_t {
+    ///
+    /// Reads a report body from its 384 bytes.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> Option<sgx_report_body_t> {
+        from_exact(bytes)
+    }
+
+    ///
+    /// Views the report body as its 384 bytes.
+    ///
+    pub fn as_bytes(&self) -> &[u8] {
+        bytes_of(self)
+    }
+}
+
+impl sgx_report_t {
+    ///
+    /// Reads a report from its 432 bytes.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> Option<sgx_report_t> {
+        from_exact(bytes)
+    }
+
+    ///
+    /// Views the report as its 432 bytes.
+    ///
+    pub fn as_bytes(&self) -> &[u8] {
+        bytes_of(self)
+    }
+}
+
+impl sgx_target_info_t {
+    ///
+    /// Reads a target info from its 512 bytes.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> Option<sgx_target_info_t> {
+        from_exact(bytes)
+    }
+
+    ///
+    /// Views the target info as its 512 bytes.
+    ///
+    pub fn as_bytes(&self) -> &[u8] {
+        bytes_of(self)
+    }
+}
+
+impl sgx_key_request_t {
+    ///
+    /// Reads a key request from its 512 bytes.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> Option<sgx_key_request_t> {
+        from_exact(bytes)
+    }
+
+    ///
+    /// Views the key request as its 512 bytes.
+    ///
+    pub fn as_bytes(&self) -> &[u8] {
+        bytes_of(self)
+    }
+}
+
+fn from_exact<T: Copy>(bytes: &[u8]) -> Option<T> {
+    if bytes.len() != mem::size_of::<T>() {
+        return None;
+    }
+    Some(read_struct(bytes))
+}
+
+fn bytes_of<T: Copy>(value: &T) -> &[u8] {
+    unsafe { core::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+fn read_struct<T: Copy>(bytes: &[u8]) -> T {
+    assert!(bytes.len() >= mem::size_of::<T>());
+    unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+}
+
+///
+/// Formats a structure field by field, with binary fields in hex.
+///
+/// # Examples
+///
+/// ```
+/// use sgx_types::quote::SgxDisplay;
+/// use sgx_types::sgx_report_body_t;
+///
+/// let body = sgx_report_body_t::default();
+/// let text = format!("{}", SgxDisplay(&body));
+/// assert!(text.contains("mr_enclave: 0000"));
+/// ```
+///
+pub struct SgxDisplay<'a, T>(pub &'a T);
+
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Debug for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.0 {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SgxDisplay<'_, sgx_report_body_t> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let b = self.0;
+        writeln!(f, "cpu_svn: {}", Hex(&b.cpu_svn.svn))?;
+        writeln!(f, "misc_select: {:#010x}", b.misc_select)?;
+        writeln!(f, "isv_ext_prod_id: {}", Hex(&b.isv_ext_prod_id))?;
+        writeln!(f, "attributes.flags: {:#018x}", b.attributes.flags)?;
+        writeln!(f, "attributes.xfrm: {:#018x}", b.attributes.xfrm)?;
+        writeln!(f, "mr_enclave: {}", Hex(&b.mr_enclave.m))?;
+        writeln!(f, "mr_signer: {}", Hex(&b.mr_signer.m))?;
+        writeln!(f, "config_id: {}", Hex(&b.config_id))?;
+        writeln!(f, "isv_prod_id: {}", b.isv_prod_id)?;
+        writeln!(f, "isv_svn: {}", b.isv_svn)?;
+        writeln!(f, "config_svn: {}", b.config_svn)?;
+        writeln!(f, "isv_family_id: {}", Hex(&b.isv_family_id))?;
+        write!(f, "report_data: {}", Hex(&b.report_data.d))
+    }
+}
+
+impl fmt::Display for SgxDisplay<'_, sgx_report_t> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", SgxDisplay(&self.0.body))?;
+        writeln!(f, "key_id: {}", Hex(&self.0.key_id.id))?;
+        write!(f, "mac: {}", Hex(&self.0.mac))
+    }
+}
+
+impl fmt::Display for SgxDisplay<'_, sgx_target_info_t> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let t = self.0;
+        writeln!(f, "mr_enclave: {}", Hex(&t.mr_enclave.m))?;
+        writeln!(f, "attributes.flags: {:#018x}", t.attributes.flags)?;
+        writeln!(f, "attributes.xfrm: {:#018x}", t.attributes.xfrm)?;
+        writeln!(f, "config_svn: {}", t.config_svn)?;
+        writeln!(f, "misc_select: {:#010x}", t.misc_select)?;
+        write!(f, "config_id: {}", Hex(&t.config_id))
+    }
+}
+
+impl fmt::Display for SgxDisplay<'_, SgxQuote<'_>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let q = self.0;
+        let h = q.get_header();
+        writeln!(f, "version: {}", h.version)?;
+        writeln!(f, "att_key_type: {}", h.att_key_type)?;
+        writeln!(f, "tee_type: {:#010x}", h.tee_type)?;
+        writeln!(f, "qe_svn: {}", q.get_qe_svn())?;
+        if let Some(pce_svn) = q.get_pce_svn() {
+            writeln!(f, "pce_svn: {}", pce_svn)?;
+        }
+        writeln!(f, "qe_vendor_id: {}", Hex(&h.qe_vendor_id))?;
+        writeln!(f, "user_data: {}", Hex(&h.user_data))?;
+        match q.get_body() {
+            SgxQuoteBody::Sgx(body) => {
+                writeln!(f, "{}", SgxDisplay(&read_struct::<sgx_report_body_t>(body)))?
+            }
+            SgxQuoteBody::Td10(body) => writeln!(f, "td_report_body: {}", Hex(body))?,
+        }
+        writeln!(f, "signature: {}", Hex(q.get_signature()))?;
+        writeln!(f, "attest_key: {}", Hex(q.get_attest_key()))?;
+        writeln!(
+            f,
+            "qe_report_signature: {}",
+            Hex(q.get_qe_report_signature())
+        )?;
+        writeln!(f, "auth_data: {}", Hex(q.get_auth_data()))?;
+        write!(
+            f,
+            "certification_data: type {}, {} bytes",
+            q.get_certification_type(),
+            q.get_certification_data().len()
+        )
+    }
+}
+
+impl fmt::Debug for SgxQuote<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SgxQuote")
+            .field("header", &self.header)
+            .field("report_data", &Hex(&self.body.get_report_data().d))
+            .field("certification_type", &self.cert_type)
+            .finish_non_exhaustive()
+    }
+}
@@ -41,6 +41,21 @@ pub const SGX_FLAGS_RESERVED: uint64_t = !(SGX_FLAGS_INITTED
     | SGX_FLAGS_KSS);
 pub const SGX_FLAGS_NON_CHECK_BITS: uint64_t = 0x00FF_0000_0000_0000; //BIT[55-48] will not be checked
 
+impl_bitflags! {
+    /// The bits of `sgx_attributes_t::flags`.
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub struct SgxAttributesFlags: uint64_t {
+        const INITTED = SGX_FLAGS_INITTED;
+        const DEBUG = SGX_FLAGS_DEBUG;
+        const MODE64BIT = SGX_FLAGS_MODE64BIT;
+        const PROVISION_KEY = SGX_FLAGS_PROVISION_KEY;
+        const EINITTOKEN_KEY = SGX_FLAGS_EINITTOKEN_KEY;
+        const KSS = SGX_FLAGS_KSS;
+        const AEX_NOTIFY = SGX_FLAGS_AEX_NOTIFY;
+    }
+}
+
 // XSAVE Feature Request Mask
 pub const SGX_XFRM_LEGACY: uint64_t = 0x0000_0000_0000_0003; //Legacy XFRM
 pub const SGX_XFRM_AVX: uint64_t = 0x0000_0000_0000_0006; // AVX
@@ -652,6 +667,8 @@ pub const SGX_SHA256_HASH_SIZE: size_t = 32;
 pub const SGX_SHA384_HASH_SIZE: size_t = 48;
 pub const SGX_ECP256_KEY_SIZE: size_t = 32;
 pub const SGX_NISTP_ECP256_KEY_SIZE: size_t = SGX_ECP256_KEY_SIZE / 4;
+pub const SGX_ECP384_KEY_SIZE: size_t = 48;
+pub const SGX_NISTP_ECP384_KEY_SIZE: size_t = SGX_ECP384_KEY_SIZE / 4;
 pub const SGX_AESGCM_IV_SIZE: size_t = 12;
 pub const SGX_AESGCM_KEY_SIZE: size_t = 16;
 pub const SGX_AESGCM_MAC_SIZE: size_t = 16;
@@ -691,6 +708,40 @@ impl_struct! {
     }
 }
 
+impl_copy_clone! {
+    pub struct sgx_ec384_dh_shared_t {
+        pub s: [uint8_t; SGX_ECP384_KEY_SIZE],
+    }
+
+    pub struct sgx_ec384_private_t {
+        pub r: [uint8_t; SGX_ECP384_KEY_SIZE],
+    }
+
+    pub struct sgx_ec384_public_t {
+        pub gx: [uint8_t; SGX_ECP384_KEY_SIZE],
+        pub gy: [uint8_t; SGX_ECP384_KEY_SIZE],
+    }
+
+    pub struct sgx_ec384_signature_t {
+        pub x: [uint32_t; SGX_NISTP_ECP384_KEY_SIZE],
+        pub y: [uint32_t; SGX_NISTP_ECP384_KEY_SIZE],
+    }
+}
+
+impl_struct_default! {
+    sgx_ec384_dh_shared_t; //48
+    sgx_ec384_private_t; //48
+    sgx_ec384_public_t; //96
+    sgx_ec384_signature_t; //96
+}
+
+impl_struct_ContiguousMemory! {
+    sgx_ec384_dh_shared_t;
+    sgx_ec384_private_t;
+    sgx_ec384_public_t;
+    sgx_ec384_signature_t;
+}
+
 impl_copy_clone! {
     pub struct sgx_rsa3072_public_key_t {
         pub modulus: [uint8_t; SGX_RSA3072_KEY_SIZE],
@@ -2130,3 +2181,57 @@ pub const SGX_MM_EXCEPTION_CONTINUE_SEARCH: int32_t = 0;
 // Return value used by the EMM #PF handler to indicate
 // to the dispatcher that it should stop searching and continue execution.
 pub const SGX_MM_EXCEPTION_CONTINUE_EXECUTION: int32_t = -1;
+
+//
+// Shared memory rings between the enclave and the host.
+//
+// A ring is one untrusted mapping: a header followed by `capacity` bytes of
+// data. `head` and `tail` count the bytes consumed and produced since the
+// ring was created; each has its own cache line, written only by its side.
+//
+pub const SGX_SHM_RING_MAGIC: uint64_t = 0x474E_4952_4D48_5358; // "XSHMRING"
+pub const SGX_SHM_RING_MAGIC_OFFSET: size_t = 0;
+pub const SGX_SHM_RING_CAPACITY_OFFSET: size_t = 8;
+pub const SGX_SHM_RING_DIRECTION_OFFSET: size_t = 16;
+pub const SGX_SHM_RING_CLOSED_OFFSET: size_t = 24;
+pub const SGX_SHM_RING_HEAD_OFFSET: size_t = 64;
+pub const SGX_SHM_RING_TAIL_OFFSET: size_t = 128;
+pub const SGX_SHM_RING_HEADER_SIZE: size_t = 192;
+
+// Ring directions.
+pub const SGX_SHM_RING_TO_HOST: uint64_t = 1;
+pub const SGX_SHM_RING_FROM_HOST: uint64_t = 2;
+
+// Bits of the closed word.
+pub const SGX_SHM_RING_WRITER_CLOSED: uint64_t = 0x1;
+pub const SGX_SHM_RING_READER_CLOSED: uint64_t = 0x2;
+
+//
+// Rust-native ecalls.
+//
+// Ecalls declared with sgx_ecall_derive have no EDL. They all go through the
+// ecall at SGX_RUST_ECALL_INDEX, which takes this marshalling structure. The
+// arguments are encoded in the input buffer, and the enclave writes the return
+// value and the in/out parameters to the output buffer. Both buffers are in
+// untrusted memory.
+//
+pub const SGX_RUST_ECALL_INDEX: c_int = 0;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct sgx_rust_ecall_ms_t {
+    pub ms_retval: sgx_status_t,
+    pub ms_id: uint32_t,
+    pub ms_input: *const uint8_t,
+    pub ms_input_len: size_t,
+    pub ms_output: *mut uint8_t,
+    pub ms_output_size: size_t,
+    pub ms_output_len: size_t,
+}
+
+// Rust-native ocalls go through the ocall at SGX_RUST_OCALL_INDEX of the ocall
+// table that sgx_urts passes to Rust-native ecalls. They take the same
+// marshalling structure, which the enclave allocates on the untrusted stack.
+pub const SGX_RUST_OCALL_INDEX: uint32_t = 0;
+
+pub type sgx_rust_ocall_ms_t = sgx_rust_ecall_ms_t;
@@ -0,0 +1,157 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Local attestation between enclaves of one process.
+//!
+//! When a frontend enclave attests to a backend enclave hosted by the same application,
+//! the uRTS only carries blobs between them. `SgxLaBroker` carries the messages of
+//! a session, such as the three messages of sgx_tdh, in order and with bounded sizes,
+//! and rsgx_la_report carries a target info and a report for a one-way attestation.
+//!
+//! ```rust,ignore
+//! let messages = SgxLaBroker::dh(0).run(
+//!     |message| initiator_step(&frontend, message),
+//!     |message| responder_step(&backend, message),
+//! )?;
+//! ```
+//!
+//! The blobs are untrusted: each enclave must still verify what it receives.
+
+use crate::enclave::SgxEnclave;
+use sgx_types::*;
+use std::mem;
+
+///
+/// What a side of a session does after it handled a message.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SgxLaStep {
+    ///
+    /// Sends the next message to the other side.
+    ///
+    Send(Vec<u8>),
+    ///
+    /// Ends the session, after the last message.
+    ///
+    Done,
+}
+
+///
+/// The messages of a local attestation session, and their maximum sizes.
+///
+/// The responder sends the first message, with an empty input, and the sides then
+/// alternate. The side that receives the last message must end the session.
+///
+#[derive(Clone, Debug, Default)]
+pub struct SgxLaBroker {
+    max_sizes: Vec<usize>,
+}
+
+impl SgxLaBroker {
+    pub fn new() -> SgxLaBroker {
+        SgxLaBroker::default()
+    }
+
+    ///
+    /// The session of sgx_tdh: msg1 from the responder, msg2 from the initiator and
+    /// msg3 from the responder, with up to max_additional_prop bytes of additional
+    /// properties.
+    ///
+    pub fn dh(max_additional_prop: usize) -> SgxLaBroker {
+        SgxLaBroker::new()
+            .message(mem::size_of::<sgx_dh_msg1_t>())
+            .message(mem::size_of::<sgx_dh_msg2_t>())
+            .message(mem::size_of::<sgx_dh_msg3_t>() + max_additional_prop)
+    }
+
+    ///
+    /// Adds a message, of at most max_size bytes, to the session.
+    ///
+    pub fn message(mut self, max_size: usize) -> SgxLaBroker {
+        self.max_sizes.push(max_size);
+        self
+    }
+
+    pub fn get_message_count(&self) -> usize {
+        self.max_sizes.len()
+    }
+
+    ///
+    /// Runs a session between initiator and responder, which take the message of the
+    /// other side and return their step. Returns the number of messages carried.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// A message is longer than its maximum size.
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// A side ended the session before the last message, or did not end it after.
+    ///
+    /// The errors of initiator and responder, which end the session.
+    ///
+    pub fn run<I, R>(&self, mut initiator: I, mut responder: R) -> SgxResult<usize>
+    where
+        I: FnMut(&[u8]) -> SgxResult<SgxLaStep>,
+        R: FnMut(&[u8]) -> SgxResult<SgxLaStep>,
+    {
+        let mut message = Vec::new();
+        for (i, max_size) in self.max_sizes.iter().enumerate() {
+            let step = if i % 2 == 0 {
+                responder(&message)?
+            } else {
+                initiator(&message)?
+            };
+            message = match step {
+                SgxLaStep::Send(next) if next.len() <= *max_size => next,
+                SgxLaStep::Send(_) => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+                SgxLaStep::Done => return Err(sgx_status_t::SGX_ERROR_INVALID_STATE),
+            };
+        }
+
+        let count = self.max_sizes.len();
+        let step = if count % 2 == 0 {
+            responder(&message)?
+        } else {
+            initiator(&message)?
+        };
+        match step {
+            SgxLaStep::Done => Ok(count),
+            SgxLaStep::Send(_) => Err(sgx_status_t::SGX_ERROR_INVALID_STATE),
+        }
+    }
+}
+
+///
+/// rsgx_la_report gets the target info of the enclave target, has make_report create
+/// a report for it in the attesting enclave, and has verify_report check it in target.
+///
+/// # Errors
+///
+/// The errors of rsgx_get_target_info, make_report and verify_report.
+///
+pub fn rsgx_la_report<F, G>(target: &SgxEnclave, make_report: F, verify_report: G) -> SgxError
+where
+    F: FnOnce(&sgx_target_info_t) -> SgxResult<sgx_report_t>,
+    G: FnOnce(&sgx_report_t) -> SgxError,
+{
+    let target_info = target.get_target_info()?;
+    let report = make_report(&target_info)?;
+    verify_report(&report)
+}
@@ -0,0 +1,209 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Diagnostics of enclave crashes.
+//!
+//! When an ecall returns SGX_ERROR_ENCLAVE_CRASHED, what the application can see of it
+//! is recorded: the enclave, the ecall, the thread, when it started and how long it
+//! ran. The SSA frames and the exit information of the AEX that led to the crash are
+//! in enclave memory and are not available to the uRTS; an enclave that needs them must
+//! record them itself, for instance from an exception handler.
+//!
+//! Rust-native ecalls are recorded by rsgx_rust_ecall. Ecalls generated by edger8r are
+//! recorded when they are made through rsgx_diagnose_ecall.
+
+use crate::shutdown::EcallGuard;
+use crate::stats::{record_ecall, StatsScope};
+use sgx_types::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Once};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant, SystemTime};
+
+///
+/// The ecall that crashed an enclave.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SgxEcallSite {
+    ///
+    /// An ecall generated by edger8r, with its index in the EDL.
+    ///
+    Index(i32),
+    ///
+    /// A Rust-native ecall, with the identifier of its name.
+    ///
+    Rust(u32),
+}
+
+///
+/// What the uRTS knows of an enclave crash.
+///
+#[derive(Clone, Debug)]
+pub struct SgxCrashDiagnostics {
+    enclave_id: sgx_enclave_id_t,
+    site: SgxEcallSite,
+    status: sgx_status_t,
+    started: SystemTime,
+    duration: Duration,
+    thread_id: ThreadId,
+    thread_name: Option<String>,
+}
+
+impl SgxCrashDiagnostics {
+    pub fn get_enclave_id(&self) -> sgx_enclave_id_t {
+        self.enclave_id
+    }
+
+    pub fn get_site(&self) -> SgxEcallSite {
+        self.site
+    }
+
+    ///
+    /// Get the status the ecall returned.
+    ///
+    pub fn get_status(&self) -> sgx_status_t {
+        self.status
+    }
+
+    ///
+    /// Get when the ecall started.
+    ///
+    pub fn get_started(&self) -> SystemTime {
+        self.started
+    }
+
+    ///
+    /// Get how long the ecall ran until it returned the crash.
+    ///
+    pub fn get_duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn get_thread_id(&self) -> ThreadId {
+        self.thread_id
+    }
+
+    pub fn get_thread_name(&self) -> Option<&str> {
+        self.thread_name.as_deref()
+    }
+}
+
+type CrashHook = Arc<dyn Fn(&SgxCrashDiagnostics) + Send + Sync>;
+
+struct CrashRegistry {
+    last: HashMap<sgx_enclave_id_t, SgxCrashDiagnostics>,
+    hook: Option<CrashHook>,
+}
+
+static mut CRASH_REGISTRY: Option<Mutex<CrashRegistry>> = None;
+static CRASH_REGISTRY_INIT: Once = Once::new();
+
+fn registry() -> &'static Mutex<CrashRegistry> {
+    CRASH_REGISTRY_INIT.call_once(|| unsafe {
+        CRASH_REGISTRY = Some(Mutex::new(CrashRegistry {
+            last: HashMap::new(),
+            hook: None,
+        }));
+    });
+    unsafe { CRASH_REGISTRY.as_ref().unwrap() }
+}
+
+///
+/// rsgx_diagnose_ecall makes the ecall f of the enclave enclave_id, and records its
+/// diagnostics if it returns SGX_ERROR_ENCLAVE_CRASHED. The ecall, and the ocalls it
+/// makes, are counted in the stats of the enclave. Returns the status of f, or
+/// SGX_ERROR_ECALL_NOT_ALLOWED without calling it if the enclave is shutting down.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// let mut retval = sgx_status_t::SGX_SUCCESS;
+/// let ret = rsgx_diagnose_ecall(eid, SgxEcallSite::Index(3), || unsafe {
+///     process(eid, &mut retval, input.as_ptr(), input.len())
+/// });
+/// ```
+///
+pub fn rsgx_diagnose_ecall<F>(
+    enclave_id: sgx_enclave_id_t,
+    site: SgxEcallSite,
+    f: F,
+) -> sgx_status_t
+where
+    F: FnOnce() -> sgx_status_t,
+{
+    let _guard = match EcallGuard::enter(enclave_id, None) {
+        Ok(guard) => guard,
+        Err(e) => return e,
+    };
+    let started = SystemTime::now();
+    let start = Instant::now();
+    let status = {
+        let _scope = StatsScope::enter(enclave_id);
+        f()
+    };
+    record_ecall(enclave_id, status, start.elapsed());
+    if status == sgx_status_t::SGX_ERROR_ENCLAVE_CRASHED {
+        let thread = thread::current();
+        record(SgxCrashDiagnostics {
+            enclave_id,
+            site,
+            status,
+            started,
+            duration: start.elapsed(),
+            thread_id: thread.id(),
+            thread_name: thread.name().map(String::from),
+        });
+    }
+    status
+}
+
+fn record(diagnostics: SgxCrashDiagnostics) {
+    let hook = {
+        let mut registry = registry().lock().unwrap_or_else(|e| e.into_inner());
+        registry
+            .last
+            .insert(diagnostics.enclave_id, diagnostics.clone());
+        registry.hook.clone()
+    };
+    if let Some(hook) = hook {
+        hook(&diagnostics);
+    }
+}
+
+///
+/// rsgx_last_crash returns the diagnostics of the last crash of the enclave
+/// enclave_id. They are kept after the enclave is destroyed.
+///
+pub fn rsgx_last_crash(enclave_id: sgx_enclave_id_t) -> Option<SgxCrashDiagnostics> {
+    registry()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .last
+        .get(&enclave_id)
+        .cloned()
+}
+
+///
+/// rsgx_set_crash_hook calls hook with the diagnostics of every crash, on the thread
+/// of the ecall, to report it. It replaces the hook set before.
+///
+pub fn rsgx_set_crash_hook<F>(hook: F)
+where
+    F: Fn(&SgxCrashDiagnostics) + Send + Sync + 'static,
+{
+    registry().lock().unwrap_or_else(|e| e.into_inner()).hook = Some(Arc::new(hook));
+}
@@ -0,0 +1,365 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Rust-native ecalls, without EDL.
+//!
+//! `sgx_ecall_proxy!` declares typed proxies for the ecalls an enclave defines with
+//! `#[sgx_ecall]` and `sgx_trts::sgx_ecall_table!`. The proxies encode their arguments,
+//! make the ecall and decode the return value and the `&mut [u8]` parameters:
+//!
+//! ```rust,ignore
+//! sgx_urts::sgx_ecall_proxy! {
+//!     fn add(a: u32, b: u32) -> u32;
+//!     fn fill(buf: &mut [u8], byte: u8);
+//! }
+//!
+//! let sum = add(&enclave, 1, 2)?;
+//! ```
+//!
+//! The declarations must match the trusted functions, which is not checked, and the
+//! output of an ecall must fit in SGX_RUST_ECALL_OUTPUT_SIZE bytes. rsgx_rust_ecall
+//! takes any output size.
+
+use crate::diagnostics::{rsgx_diagnose_ecall, SgxEcallSite};
+use crate::ocall::{rust_ocall_table, OcallScope, OcallTable};
+use sgx_types::*;
+
+///
+/// The size of the output buffer of the proxies declared by `sgx_ecall_proxy!`.
+///
+pub const SGX_RUST_ECALL_OUTPUT_SIZE: usize = 64 * 1024;
+
+///
+/// The identifier of the ecall of a function, from its name.
+///
+pub const fn rsgx_ecall_id(name: &str) -> u32 {
+    // FNV-1a
+    let bytes = name.as_bytes();
+    let mut hash: u32 = 0x811c_9dc5;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    hash
+}
+
+///
+/// rsgx_rust_ecall makes the Rust-native ecall id with the encoded arguments input,
+/// and returns the encoded output. The ocalls of the enclave are handled by the
+/// handlers set with rsgx_set_ocalls.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_FUNCTION**
+///
+/// The enclave has no ecall with the identifier.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// The output is longer than output_size. The ecall was made.
+///
+/// The errors of the ecall, and of the trusted function.
+///
+pub fn rsgx_rust_ecall(
+    eid: sgx_enclave_id_t,
+    id: u32,
+    input: &[u8],
+    output_size: usize,
+) -> SgxResult<Vec<u8>> {
+    let ocall_table = rust_ocall_table();
+    let mut output = vec![0_u8; output_size];
+    let mut ms = sgx_rust_ecall_ms_t {
+        ms_retval: sgx_status_t::SGX_ERROR_UNEXPECTED,
+        ms_id: id,
+        ms_input: input.as_ptr(),
+        ms_input_len: input.len(),
+        ms_output: output.as_mut_ptr(),
+        ms_output_size: output_size,
+        ms_output_len: 0,
+    };
+    let _scope = OcallScope::enter(eid);
+    let ret = rsgx_diagnose_ecall(eid, SgxEcallSite::Rust(id), || unsafe {
+        sgx_ecall(
+            eid,
+            SGX_RUST_ECALL_INDEX,
+            &ocall_table as *const OcallTable as *const c_void,
+            &mut ms as *mut sgx_rust_ecall_ms_t as *mut c_void,
+        )
+    });
+    if ret != sgx_status_t::SGX_SUCCESS {
+        return Err(ret);
+    }
+    if ms.ms_retval != sgx_status_t::SGX_SUCCESS {
+        return Err(ms.ms_retval);
+    }
+    if ms.ms_output_len > output_size {
+        return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+    }
+    output.truncate(ms.ms_output_len);
+    Ok(output)
+}
+
+///
+/// The encoded arguments of an ecall.
+///
+#[derive(Clone, Debug, Default)]
+pub struct EcallWriter {
+    buf: Vec<u8>,
+}
+
+impl EcallWriter {
+    pub fn new() -> EcallWriter {
+        EcallWriter::default()
+    }
+
+    pub fn write<T: EcallArg + ?Sized>(&mut self, value: &T) {
+        value.encode(self);
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buf
+            .extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn write_raw(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+///
+/// A reader of the encoded output of an ecall.
+///
+/// # Errors
+///
+/// The read functions return **SGX_ERROR_UNEXPECTED** when the encoding is truncated
+/// or malformed.
+///
+#[derive(Clone, Debug)]
+pub struct EcallReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> EcallReader<'a> {
+    pub fn new(buf: &'a [u8]) -> EcallReader<'a> {
+        EcallReader { buf }
+    }
+
+    pub fn read<T: EcallValue>(&mut self) -> SgxResult<T> {
+        T::decode(self)
+    }
+
+    pub fn read_bytes(&mut self) -> SgxResult<Vec<u8>> {
+        let len = u64::from_le_bytes(self.read_array()?);
+        if len > self.buf.len() as u64 {
+            return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+        }
+        Ok(self.read_raw(len as usize)?.to_vec())
+    }
+
+    pub fn read_str(&mut self) -> SgxResult<String> {
+        String::from_utf8(self.read_bytes()?).map_err(|_| sgx_status_t::SGX_ERROR_UNEXPECTED)
+    }
+
+    pub fn read_raw(&mut self, len: usize) -> SgxResult<&'a [u8]> {
+        if len > self.buf.len() {
+            return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+        }
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> SgxResult<[u8; N]> {
+        let mut array = [0_u8; N];
+        array.copy_from_slice(self.read_raw(N)?);
+        Ok(array)
+    }
+
+    ///
+    /// Checks that everything was read.
+    ///
+    pub fn finish(&self) -> SgxError {
+        if self.buf.is_empty() {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_UNEXPECTED)
+        }
+    }
+}
+
+///
+/// A type passed by value through a Rust-native ecall.
+///
+/// The encoding must match the one of `sgx_trts::ecall::EcallValue`.
+///
+pub trait EcallValue: Sized {
+    fn encode(&self, writer: &mut EcallWriter);
+    fn decode(reader: &mut EcallReader) -> SgxResult<Self>;
+}
+
+///
+/// A parameter of a proxy declared by `sgx_ecall_proxy!`.
+///
+pub trait EcallArg {
+    fn encode(&self, writer: &mut EcallWriter);
+
+    ///
+    /// Updates an in/out parameter from the output of the ecall.
+    ///
+    fn write_back(&mut self, _reader: &mut EcallReader) -> SgxError {
+        Ok(())
+    }
+}
+
+impl<T: EcallValue> EcallArg for T {
+    fn encode(&self, writer: &mut EcallWriter) {
+        EcallValue::encode(self, writer);
+    }
+}
+
+macro_rules! impl_ecall_value_int {
+    ($($t:ty)*) => {$(
+        impl EcallValue for $t {
+            fn encode(&self, writer: &mut EcallWriter) {
+                writer.write_raw(&self.to_le_bytes());
+            }
+
+            fn decode(reader: &mut EcallReader) -> SgxResult<$t> {
+                reader.read_array().map(<$t>::from_le_bytes)
+            }
+        }
+    )*}
+}
+
+impl_ecall_value_int! { u8 u16 u32 u64 i8 i16 i32 i64 }
+
+impl EcallValue for usize {
+    fn encode(&self, writer: &mut EcallWriter) {
+        EcallValue::encode(&(*self as u64), writer);
+    }
+
+    fn decode(reader: &mut EcallReader) -> SgxResult<usize> {
+        usize::try_from(u64::decode(reader)?).map_err(|_| sgx_status_t::SGX_ERROR_UNEXPECTED)
+    }
+}
+
+impl EcallValue for bool {
+    fn encode(&self, writer: &mut EcallWriter) {
+        EcallValue::encode(&(*self as u8), writer);
+    }
+
+    fn decode(reader: &mut EcallReader) -> SgxResult<bool> {
+        match u8::decode(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(sgx_status_t::SGX_ERROR_UNEXPECTED),
+        }
+    }
+}
+
+impl EcallValue for () {
+    fn encode(&self, _writer: &mut EcallWriter) {}
+
+    fn decode(_reader: &mut EcallReader) -> SgxResult<()> {
+        Ok(())
+    }
+}
+
+impl EcallValue for Vec<u8> {
+    fn encode(&self, writer: &mut EcallWriter) {
+        writer.write_bytes(self);
+    }
+
+    fn decode(reader: &mut EcallReader) -> SgxResult<Vec<u8>> {
+        reader.read_bytes()
+    }
+}
+
+impl EcallValue for String {
+    fn encode(&self, writer: &mut EcallWriter) {
+        writer.write_bytes(self.as_bytes());
+    }
+
+    fn decode(reader: &mut EcallReader) -> SgxResult<String> {
+        reader.read_str()
+    }
+}
+
+impl EcallArg for &str {
+    fn encode(&self, writer: &mut EcallWriter) {
+        writer.write_bytes(self.as_bytes());
+    }
+}
+
+impl EcallArg for &[u8] {
+    fn encode(&self, writer: &mut EcallWriter) {
+        writer.write_bytes(self);
+    }
+}
+
+impl EcallArg for &mut [u8] {
+    fn encode(&self, writer: &mut EcallWriter) {
+        writer.write_bytes(self);
+    }
+
+    fn write_back(&mut self, reader: &mut EcallReader) -> SgxError {
+        let bytes = reader.read_bytes()?;
+        if bytes.len() != self.len() {
+            return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+        }
+        self.copy_from_slice(&bytes);
+        Ok(())
+    }
+}
+
+///
+/// Declares proxies for Rust-native ecalls. Each proxy takes the enclave, then the
+/// parameters of the trusted function, and returns its return value.
+///
+#[macro_export]
+macro_rules! sgx_ecall_proxy {
+    (@ret) => { () };
+    (@ret $ret:ty) => { $ret };
+    ($($(#[$attr:meta])* $vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)?;)*) => {$(
+        $(#[$attr])*
+        $vis fn $name(
+            enclave: &$crate::SgxEnclave,
+            $(mut $arg: $ty),*
+        ) -> ::sgx_types::SgxResult<$crate::sgx_ecall_proxy!(@ret $($ret)?)> {
+            let mut input = $crate::EcallWriter::new();
+            $($crate::EcallArg::encode(&$arg, &mut input);)*
+            let output = $crate::rsgx_rust_ecall(
+                enclave.geteid(),
+                $crate::rsgx_ecall_id(stringify!($name)),
+                input.as_bytes(),
+                $crate::SGX_RUST_ECALL_OUTPUT_SIZE,
+            )?;
+            let mut reader = $crate::EcallReader::new(&output);
+            let ret = reader.read()?;
+            $($crate::EcallArg::write_back(&mut $arg, &mut reader)?;)*
+            reader.finish()?;
+            Ok(ret)
+        }
+    )*};
+}
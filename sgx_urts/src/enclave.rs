@@ -15,6 +15,10 @@
 // specific language governing permissions and limitations
 // under the License..
 
+use crate::ocall::rsgx_clear_ocalls;
+use crate::shutdown::is_destroyed;
+use crate::stats::rsgx_clear_enclave_stats;
+use crate::switchless::SgxSwitchlessConfig;
 use sgx_types::*;
 use std::ffi::{CStr, CString};
 use std::io;
@@ -435,17 +439,155 @@ pub fn rsgx_create_enclave_with_workers(
     num_uworkers: u32,
     num_tworkers: u32,
 ) -> SgxResult<sgx_enclave_id_t> {
-    let us_config = sgx_uswitchless_config_t {
-        num_tworkers: num_tworkers as u64,
-        num_uworkers: num_uworkers as u64,
-        ..Default::default()
-    };
-    let mut enclave_ex_p: [*const c_void; 32] = [ptr::null(); 32];
-    enclave_ex_p[SGX_CREATE_ENCLAVE_EX_SWITCHLESS_BIT_IDX] =
-        &us_config as *const sgx_uswitchless_config_t as *const c_void;
+    let config = SgxSwitchlessConfig::new()
+        .untrusted_workers(num_uworkers)
+        .trusted_workers(num_tworkers);
+    rsgx_create_enclave_switchless(
+        file_name,
+        debug,
+        launch_token,
+        launch_token_updated,
+        misc_attr,
+        &config,
+    )
+}
+
+///
+/// Loads the enclave using its file name and initializes it with switchless calls.
+///
+/// # Description
+///
+/// Like rsgx_create_enclave_with_workers, with the whole switchless configuration:
+/// the number of workers, the size of the task pool and the retry counts before
+/// falling back and sleeping. The workers report to rsgx_switchless_stats.
+///
+/// # Errors
+///
+/// The errors of rsgx_create_enclave.
+///
+pub fn rsgx_create_enclave_switchless(
+    file_name: &CStr,
+    debug: i32,
+    launch_token: &mut sgx_launch_token_t,
+    launch_token_updated: &mut i32,
+    misc_attr: &mut sgx_misc_attribute_t,
+    config: &SgxSwitchlessConfig,
+) -> SgxResult<sgx_enclave_id_t> {
+    rsgx_create_enclave_ex(
+        file_name,
+        debug,
+        launch_token,
+        launch_token_updated,
+        misc_attr,
+        &SgxEnclaveOptions::new().switchless(config),
+    )
+}
+
+///
+/// The extended features an enclave is created with.
+///
+/// # Examples
+///
+/// ```ignore
+/// // A tenant enclave keyed to its tenant, with switchless ocalls.
+/// let options = SgxEnclaveOptions::new()
+///     .kss(&tenant_config_id, 1)
+///     .switchless(&SgxSwitchlessConfig::new().untrusted_workers(2));
+/// let enclave = SgxEnclave::create_ex(path, debug, &mut token, &mut updated, &mut misc, &options)?;
+/// ```
+///
+#[derive(Clone, Copy, Default)]
+pub struct SgxEnclaveOptions {
+    switchless: Option<SgxSwitchlessConfig>,
+    kss: Option<sgx_kss_config_t>,
+}
+
+impl SgxEnclaveOptions {
+    ///
+    /// No extended features.
+    ///
+    pub fn new() -> SgxEnclaveOptions {
+        SgxEnclaveOptions::default()
+    }
+
+    ///
+    /// Enables switchless calls with config.
+    ///
+    pub fn switchless(mut self, config: &SgxSwitchlessConfig) -> SgxEnclaveOptions {
+        self.switchless = Some(*config);
+        self
+    }
+
+    ///
+    /// Launches the enclave with Key Separation and Sharing, with the given CONFIGID and
+    /// CONFIGSVN. They become part of the identity of the enclave, in its reports and in
+    /// the keys it derives, so enclaves of one image with different CONFIGIDs, such as
+    /// those of different tenants, do not share keys. The enclave must be signed with
+    /// KSS enabled.
+    ///
+    pub fn kss(
+        mut self,
+        config_id: &sgx_config_id_t,
+        config_svn: sgx_config_svn_t,
+    ) -> SgxEnclaveOptions {
+        self.kss = Some(sgx_kss_config_t {
+            config_id: *config_id,
+            config_svn,
+        });
+        self
+    }
+
+    // Builds the feature mask and the configuration pointers of sgx_create_enclave_ex,
+    // which are only valid during f.
+    fn with_raw<R>(&self, f: impl FnOnce(u32, &[*const c_void; 32]) -> R) -> R {
+        let mut features = 0;
+        let mut features_p: [*const c_void; 32] = [ptr::null(); 32];
+        let us_config = self.switchless.map(|config| config.to_raw());
+        if let Some(ref config) = us_config {
+            features |= SGX_CREATE_ENCLAVE_EX_SWITCHLESS;
+            features_p[SGX_CREATE_ENCLAVE_EX_SWITCHLESS_BIT_IDX] =
+                config as *const sgx_uswitchless_config_t as *const c_void;
+        }
+        if let Some(ref config) = self.kss {
+            features |= SGX_CREATE_ENCLAVE_EX_KSS;
+            features_p[SGX_CREATE_ENCLAVE_EX_KSS_BIT_IDX] =
+                config as *const sgx_kss_config_t as *const c_void;
+        }
+        f(features, &features_p)
+    }
+}
 
+///
+/// Loads the enclave using its file name and initializes it with extended features.
+///
+/// # Description
+///
+/// Like rsgx_create_enclave, with the switchless calls and Key Separation and Sharing
+/// configured in options.
+///
+/// # Errors
+///
+/// **SGX_ERROR_FEATURE_NOT_SUPPORTED**
+///
+/// The platform or the loader does not support a requested feature; for KSS, the
+/// platform has no KSS support.
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// A CONFIGID or CONFIGSVN is given but the enclave was not signed with KSS enabled.
+///
+/// The other errors of rsgx_create_enclave.
+///
+pub fn rsgx_create_enclave_ex(
+    file_name: &CStr,
+    debug: i32,
+    launch_token: &mut sgx_launch_token_t,
+    launch_token_updated: &mut i32,
+    misc_attr: &mut sgx_misc_attribute_t,
+    options: &SgxEnclaveOptions,
+) -> SgxResult<sgx_enclave_id_t> {
     let mut enclave_id: sgx_enclave_id_t = 0;
-    let ret = unsafe {
+    let ret = options.with_raw(|features, features_p| unsafe {
         sgx_create_enclave_ex(
             file_name.as_ptr() as *const c_schar,
             debug as int32_t,
@@ -453,16 +595,85 @@ pub fn rsgx_create_enclave_with_workers(
             launch_token_updated as *mut int32_t,
             &mut enclave_id as *mut sgx_enclave_id_t,
             misc_attr as *mut sgx_misc_attribute_t,
-            SGX_CREATE_ENCLAVE_EX_SWITCHLESS,
-            &enclave_ex_p as *const [*const c_void; 32],
+            features,
+            features_p as *const [*const c_void; 32],
         )
-    };
+    });
     match ret {
         sgx_status_t::SGX_SUCCESS => Ok(enclave_id),
         _ => Err(ret),
     }
 }
 
+///
+/// Loads the enclave from a signed enclave image in memory and initializes it.
+///
+/// # Description
+///
+/// The image is the content of the signed enclave file, so an enclave can be embedded
+/// in the application, decrypted, or fetched over the network without being written
+/// to disk. Loading is otherwise the same as for rsgx_create_enclave, except that no
+/// launch token is used.
+///
+/// # Parameters
+///
+/// **buffer**
+///
+/// The signed enclave image.
+///
+/// **debug**
+///
+/// The valid value is 0 or 1, as for rsgx_create_enclave.
+///
+/// **misc_attr**
+///
+/// The misc select and attributes of the loaded enclave.
+///
+/// # Requirements
+///
+/// Header: sgx_urts.h
+///
+/// Library: libsgx_urts.a
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The buffer is empty.
+///
+/// **SGX_ERROR_INVALID_ENCLAVE**
+///
+/// The buffer is not a valid enclave image.
+///
+/// The other errors of rsgx_create_enclave.
+///
+pub fn rsgx_create_enclave_from_buffer(
+    buffer: &[u8],
+    debug: i32,
+    misc_attr: &mut sgx_misc_attribute_t,
+) -> SgxResult<sgx_enclave_id_t> {
+    let ex_features_p: [*const c_void; 32] = [ptr::null(); 32];
+    rsgx_create_enclave_from_buffer_ex(buffer, debug, misc_attr, 0, &ex_features_p)
+}
+
+///
+/// Loads the enclave from a signed enclave image in memory with extended features.
+///
+/// # Description
+///
+/// Like rsgx_create_enclave_from_buffer, with the extended features of
+/// sgx_create_enclave_ex. ex_features is a bitmask of SGX_CREATE_ENCLAVE_EX_*, and
+/// the entry of ex_features_p at the index of each bit set points to the configuration
+/// of that feature.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The buffer is empty, or a feature is set without its configuration.
+///
+/// The other errors of rsgx_create_enclave_from_buffer.
+///
 pub fn rsgx_create_enclave_from_buffer_ex(
     buffer: &[u8],
     debug: i32,
@@ -474,9 +685,6 @@ pub fn rsgx_create_enclave_from_buffer_ex(
         return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
     }
     let buf_len = buffer.len();
-    if buf_len == 0 {
-        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
-    }
 
     let mut enclave_id: sgx_enclave_id_t = 0;
     let ret = unsafe {
@@ -547,7 +755,7 @@ pub fn rsgx_get_target_info(enclave_id: sgx_enclave_id_t) -> SgxResult<sgx_targe
     }
 }
 
-fn cstr(path: &Path) -> io::Result<CString> {
+pub(crate) fn cstr(path: &Path) -> io::Result<CString> {
     Ok(CString::new(path.as_os_str().as_bytes())?)
 }
 
@@ -643,6 +851,106 @@ impl SgxEnclave {
         Ok(enclave)
     }
 
+    ///
+    /// Loads an enclave from a signed enclave image in memory. ex_features and
+    /// ex_features_p select extended features as for rsgx_create_enclave_from_buffer_ex;
+    /// pass 0 and null pointers for none. The path of the enclave is empty.
+    ///
+    ///
+    /// Loads an enclave with switchless calls configured by config.
+    ///
+    pub fn create_switchless<P: AsRef<Path>>(
+        file_name: P,
+        debug: i32,
+        launch_token: &mut sgx_launch_token_t,
+        launch_token_updated: &mut i32,
+        misc_attr: &mut sgx_misc_attribute_t,
+        config: &SgxSwitchlessConfig,
+    ) -> SgxResult<SgxEnclave> {
+        let path: CString =
+            cstr(file_name.as_ref()).map_err(|_| sgx_status_t::SGX_ERROR_INVALID_ENCLAVE)?;
+        let enclave = rsgx_create_enclave_switchless(
+            path.as_c_str(),
+            debug,
+            launch_token,
+            launch_token_updated,
+            misc_attr,
+            config,
+        )
+        .map(|eid| SgxEnclave {
+            id: eid,
+            debug,
+            path: file_name.as_ref().to_owned(),
+        })?;
+
+        enclave.init();
+        Ok(enclave)
+    }
+
+    ///
+    /// Loads an enclave from a signed enclave image in memory with switchless calls
+    /// configured by config.
+    ///
+    pub fn create_from_buffer_switchless(
+        buffer: &[u8],
+        debug: i32,
+        misc_attr: &mut sgx_misc_attribute_t,
+        config: &SgxSwitchlessConfig,
+    ) -> SgxResult<SgxEnclave> {
+        SgxEnclave::create_from_buffer_with_options(
+            buffer,
+            debug,
+            misc_attr,
+            &SgxEnclaveOptions::new().switchless(config),
+        )
+    }
+
+    ///
+    /// Loads an enclave with the extended features of options.
+    ///
+    pub fn create_ex<P: AsRef<Path>>(
+        file_name: P,
+        debug: i32,
+        launch_token: &mut sgx_launch_token_t,
+        launch_token_updated: &mut i32,
+        misc_attr: &mut sgx_misc_attribute_t,
+        options: &SgxEnclaveOptions,
+    ) -> SgxResult<SgxEnclave> {
+        let path: CString =
+            cstr(file_name.as_ref()).map_err(|_| sgx_status_t::SGX_ERROR_INVALID_ENCLAVE)?;
+        let enclave = rsgx_create_enclave_ex(
+            path.as_c_str(),
+            debug,
+            launch_token,
+            launch_token_updated,
+            misc_attr,
+            options,
+        )
+        .map(|eid| SgxEnclave {
+            id: eid,
+            debug,
+            path: file_name.as_ref().to_owned(),
+        })?;
+
+        enclave.init();
+        Ok(enclave)
+    }
+
+    ///
+    /// Loads an enclave from a signed enclave image in memory with the extended features
+    /// of options.
+    ///
+    pub fn create_from_buffer_with_options(
+        buffer: &[u8],
+        debug: i32,
+        misc_attr: &mut sgx_misc_attribute_t,
+        options: &SgxEnclaveOptions,
+    ) -> SgxResult<SgxEnclave> {
+        options.with_raw(|features, features_p| {
+            SgxEnclave::create_from_buffer(buffer, debug, misc_attr, features, features_p)
+        })
+    }
+
     pub fn create_from_buffer(
         buffer: &[u8],
         debug: i32,
@@ -692,7 +1000,72 @@ impl SgxEnclave {
         rsgx_get_target_info(self.id)
     }
 
-    fn exit(&self) {
+    ///
+    /// Passes a snapshot of environment variables to an enclave that declared an
+    /// allowlist with `std::env::set_allowlist`. It replaces the previous snapshot
+    /// and should be called before the ecalls which read the environment.
+    ///
+    /// Entries whose key is empty or contains `'='` or NUL, and values containing
+    /// NUL, cannot be represented and are skipped. The enclave rejects the whole
+    /// snapshot if it is too large or not valid UTF-8.
+    ///
+    #[cfg(feature = "env_snapshot")]
+    pub fn pass_env<I, K, V>(&self, vars: I) -> SgxResult<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<std::ffi::OsStr>,
+        V: AsRef<std::ffi::OsStr>,
+    {
+        extern "C" {
+            fn t_env_snapshot_ecall(
+                eid: sgx_enclave_id_t,
+                retval: *mut i32,
+                env: *const u8,
+                len: usize,
+            ) -> sgx_status_t;
+        }
+
+        let mut snapshot = Vec::new();
+        for (k, v) in vars {
+            let k = k.as_ref().as_bytes();
+            let v = v.as_ref().as_bytes();
+            if k.is_empty() || k.contains(&b'=') || k.contains(&0) || v.contains(&0) {
+                continue;
+            }
+            snapshot.extend_from_slice(k);
+            snapshot.push(b'=');
+            snapshot.extend_from_slice(v);
+            snapshot.push(0);
+        }
+
+        let mut retval = 0_i32;
+        let ret = unsafe {
+            t_env_snapshot_ecall(self.id, &mut retval, snapshot.as_ptr(), snapshot.len())
+        };
+        match ret {
+            sgx_status_t::SGX_SUCCESS if retval == 0 => Ok(()),
+            sgx_status_t::SGX_SUCCESS => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+            _ => Err(ret),
+        }
+    }
+
+    ///
+    /// Passes the subset of the host environment named by `keys` to the enclave.
+    /// See [`pass_env`](SgxEnclave::pass_env).
+    ///
+    #[cfg(feature = "env_snapshot")]
+    pub fn pass_host_env<I, K>(&self, keys: I) -> SgxResult<()>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<std::ffi::OsStr>,
+    {
+        self.pass_env(
+            keys.into_iter()
+                .filter_map(|k| std::env::var_os(&k).map(|v| (k.as_ref().to_owned(), v))),
+        )
+    }
+
+    pub(crate) fn exit(&self) {
         #[cfg(feature = "global_exit")]
         {
             extern "C" {
@@ -729,7 +1102,11 @@ impl SgxEnclave {
 
 impl Drop for SgxEnclave {
     fn drop(&mut self) {
-        self.exit();
-        let _ = rsgx_destroy_enclave(self.id);
+        if !is_destroyed(self.id) {
+            self.exit();
+            let _ = rsgx_destroy_enclave(self.id);
+        }
+        rsgx_clear_ocalls(self.id);
+        rsgx_clear_enclave_stats(self.id);
     }
 }
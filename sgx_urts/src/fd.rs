@@ -16,6 +16,7 @@
 // under the License..
 
 use libc::{self, c_int, c_uint, c_ulong, c_void, iovec, loff_t, off64_t, off_t, size_t, ssize_t, timespec};
+use std::cell::Cell;
 use std::io::Error;
 
 #[no_mangle]
@@ -259,6 +260,75 @@ pub extern "C" fn u_splice_ocall(
     ret
 }
 
+thread_local! {
+    // The write error of a partial u_copy_through_ocall, with its descriptors,
+    // reported by the next call.
+    static COPY_THROUGH_ERROR: Cell<Option<(c_int, c_int, c_int)>> = Cell::new(None);
+}
+
+#[no_mangle]
+pub extern "C" fn u_copy_through_ocall(
+    error: *mut c_int,
+    fd_in: c_int,
+    fd_out: c_int,
+    buf: *mut c_void,
+    count: size_t,
+) -> ssize_t {
+    let mut errno = 0;
+    let mut ret = match COPY_THROUGH_ERROR.with(Cell::take) {
+        Some((pending_in, pending_out, err)) if pending_in == fd_in && pending_out == fd_out => {
+            errno = err;
+            -1
+        }
+        _ => unsafe { libc::read(fd_in, buf, count) },
+    };
+    if ret < 0 {
+        if errno == 0 {
+            errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+        }
+    } else {
+        // Write everything that was read, the enclave does not see the data
+        // and could not retry a partial write. If the write fails after some
+        // of it went out, that count is returned and the error is kept for the
+        // next call, like write does.
+        let mut written = 0;
+        while written < ret {
+            let n = unsafe {
+                libc::write(
+                    fd_out,
+                    (buf as *const u8).add(written as usize) as *const c_void,
+                    (ret - written) as size_t,
+                )
+            };
+            let err = match n {
+                n if n > 0 => {
+                    written += n;
+                    continue;
+                }
+                0 => libc::EIO,
+                _ => Error::last_os_error().raw_os_error().unwrap_or(0),
+            };
+            if err == libc::EINTR {
+                continue;
+            }
+            if written > 0 {
+                COPY_THROUGH_ERROR.with(|pending| pending.set(Some((fd_in, fd_out, err))));
+                ret = written;
+            } else {
+                errno = err;
+                ret = -1;
+            }
+            break;
+        }
+    }
+    if !error.is_null() {
+        unsafe {
+            *error = errno;
+        }
+    }
+    ret
+}
+
 #[no_mangle]
 pub extern "C" fn u_fcntl_arg0_ocall(error: *mut c_int, fd: c_int, cmd: c_int) -> c_int {
     let mut errno = 0;
@@ -403,3 +473,54 @@ pub extern "C" fn u_futimens_ocall(error: *mut c_int, fd: c_int, times: *const t
     }
     ret
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+
+    // The host half of a bounce copy whose writer accepts part of a chunk: the
+    // part is reported, then the error.
+    #[test]
+    fn test_copy_through_partial_write() {
+        const LEN: usize = 1 << 20;
+        let path = std::env::temp_dir().join(format!("copy_through_{}", std::process::id()));
+        File::create(&path).unwrap().write_all(&vec![7; 2 * LEN]).unwrap();
+        let input = File::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK) }, 0);
+        let mut buf = vec![0_u8; LEN];
+        let mut error = 0;
+        let copy = |error: &mut c_int, buf: &mut [u8]| {
+            u_copy_through_ocall(
+                error,
+                input.as_raw_fd(),
+                fds[1],
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+            )
+        };
+
+        // The pipe holds less than the chunk.
+        let n = copy(&mut error, &mut buf);
+        assert!(n > 0 && (n as usize) < LEN);
+        assert_eq!(error, 0);
+        assert_eq!(copy(&mut error, &mut buf), -1);
+        assert_eq!(error, libc::EAGAIN);
+
+        // The error is only reported once.
+        let drained = unsafe { libc::read(fds[0], buf.as_mut_ptr() as *mut c_void, LEN) };
+        assert_eq!(drained, n);
+        assert_eq!(copy(&mut error, &mut buf[..16]), 16);
+        assert_eq!(error, 0);
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+    }
+}
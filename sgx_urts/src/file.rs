@@ -308,6 +308,21 @@ pub extern "C" fn u_fdatasync_ocall(error: *mut c_int, fd: c_int) -> c_int {
     ret
 }
 
+#[no_mangle]
+pub extern "C" fn u_flock_ocall(error: *mut c_int, fd: c_int, operation: c_int) -> c_int {
+    let mut errno = 0;
+    let ret = unsafe { libc::flock(fd, operation) };
+    if ret < 0 {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe {
+            *error = errno;
+        }
+    }
+    ret
+}
+
 #[no_mangle]
 pub extern "C" fn u_fchmod_ocall(error: *mut c_int, fd: c_int, mode: mode_t) -> c_int {
     let mut errno = 0;
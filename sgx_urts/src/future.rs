@@ -0,0 +1,287 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Asynchronous ecalls.
+//!
+//! `SgxEnclave::call_async` runs an ecall on a thread of a worker pool and returns a
+//! future of its result, so that an async runtime can drive many ecalls without blocking
+//! its own threads. Each worker blocks in the enclave while it runs an ecall, so the pool
+//! should not have more workers than the enclaves have TCS.
+//!
+//! An ecall is cancelled when its future is dropped or `SgxEcallFuture::cancel` is
+//! called. An ecall not started yet is not made. An ecall already running is told through
+//! a flag in untrusted memory, which the closure passes to the enclave as a [user_check]
+//! pointer and the enclave polls with `sgx_trts::ecall::EcallCancelFlag`.
+
+use crate::enclave::SgxEnclave;
+use crate::shutdown::EcallGuard;
+use sgx_types::*;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, Once};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+///
+/// The cancellation flag of an asynchronous ecall.
+///
+#[derive(Debug, Default)]
+pub struct SgxEcallCancel {
+    flag: AtomicU32,
+}
+
+impl SgxEcallCancel {
+    ///
+    /// The flag to pass to the enclave, which stays valid until the ecall returns. It is
+    /// nonzero once the ecall is cancelled.
+    ///
+    pub fn as_ptr(&self) -> *const u32 {
+        &self.flag as *const AtomicU32 as *const u32
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Acquire) != 0
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.flag.store(1, Ordering::Release);
+    }
+}
+
+struct EcallState<R> {
+    result: Option<SgxResult<R>>,
+    waker: Option<Waker>,
+}
+
+struct EcallShared<R> {
+    state: Mutex<EcallState<R>>,
+    cancel: Arc<SgxEcallCancel>,
+}
+
+impl<R> EcallShared<R> {
+    fn complete(&self, result: SgxResult<R>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+///
+/// The future of the result of an asynchronous ecall.
+///
+/// # Errors
+///
+/// **SGX_ERROR_ECALL_NOT_ALLOWED**
+///
+/// The ecall was cancelled before it started, or the enclave is shutting down.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// The worker pool has stopped.
+///
+/// The errors of the ecall.
+///
+pub struct SgxEcallFuture<R> {
+    shared: Arc<EcallShared<R>>,
+}
+
+impl<R> SgxEcallFuture<R> {
+    ///
+    /// Cancels the ecall. The future still resolves, with the result of the ecall if it
+    /// was running.
+    ///
+    pub fn cancel(&self) {
+        self.shared.cancel.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.cancel.is_cancelled()
+    }
+}
+
+impl<R> Future for SgxEcallFuture<R> {
+    type Output = SgxResult<R>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<SgxResult<R>> {
+        let mut state = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<R> Drop for SgxEcallFuture<R> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+///
+/// A pool of threads that make asynchronous ecalls.
+///
+/// The workers exit once the pool is dropped and the ecalls submitted to it are done.
+///
+pub struct SgxEcallWorkers {
+    sender: Mutex<Sender<Job>>,
+    workers: usize,
+}
+
+impl SgxEcallWorkers {
+    ///
+    /// Starts a pool of workers threads, at least one.
+    ///
+    pub fn new(workers: usize) -> SgxEcallWorkers {
+        let workers = workers.max(1);
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers {
+            let receiver = receiver.clone();
+            thread::spawn(move || worker(&receiver));
+        }
+        SgxEcallWorkers {
+            sender: Mutex::new(sender),
+            workers,
+        }
+    }
+
+    ///
+    /// Get the number of worker threads.
+    ///
+    pub fn get_workers(&self) -> usize {
+        self.workers
+    }
+
+    ///
+    /// Makes f run on a worker, with the enclave and the cancellation flag of the ecall,
+    /// and returns the future of its result.
+    ///
+    pub fn call<R, F>(&self, enclave: &Arc<SgxEnclave>, f: F) -> SgxEcallFuture<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&SgxEnclave, &SgxEcallCancel) -> SgxResult<R> + Send + 'static,
+    {
+        let shared = Arc::new(EcallShared {
+            state: Mutex::new(EcallState {
+                result: None,
+                waker: None,
+            }),
+            cancel: Arc::new(SgxEcallCancel::default()),
+        });
+
+        let enclave = enclave.clone();
+        let job_shared = shared.clone();
+        let job: Job = Box::new(move || {
+            let result = if job_shared.cancel.is_cancelled() {
+                Err(sgx_status_t::SGX_ERROR_ECALL_NOT_ALLOWED)
+            } else {
+                EcallGuard::enter(enclave.geteid(), Some(job_shared.cancel.clone()))
+                    .and_then(|_guard| f(&enclave, &job_shared.cancel))
+            };
+            job_shared.complete(result);
+        });
+
+        let sender = self.sender.lock().unwrap_or_else(|e| e.into_inner());
+        if sender.send(job).is_err() {
+            shared.complete(Err(sgx_status_t::SGX_ERROR_UNEXPECTED));
+        }
+        SgxEcallFuture { shared }
+    }
+}
+
+fn worker(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = match receiver.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        job();
+    }
+}
+
+static mut ECALL_WORKERS: Option<SgxEcallWorkers> = None;
+static ECALL_WORKERS_INIT: Once = Once::new();
+
+///
+/// rsgx_set_ecall_workers sets the number of workers of the pool used by
+/// `SgxEnclave::call_async`, which otherwise has one per available CPU.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_STATE**
+///
+/// The pool is already started.
+///
+pub fn rsgx_set_ecall_workers(workers: usize) -> SgxError {
+    let mut set = false;
+    ECALL_WORKERS_INIT.call_once(|| {
+        unsafe { ECALL_WORKERS = Some(SgxEcallWorkers::new(workers)) };
+        set = true;
+    });
+    if set {
+        Ok(())
+    } else {
+        Err(sgx_status_t::SGX_ERROR_INVALID_STATE)
+    }
+}
+
+///
+/// The pool used by `SgxEnclave::call_async`.
+///
+pub fn rsgx_ecall_workers() -> &'static SgxEcallWorkers {
+    ECALL_WORKERS_INIT.call_once(|| {
+        let workers = thread::available_parallelism().map_or(1, |n| n.get());
+        unsafe { ECALL_WORKERS = Some(SgxEcallWorkers::new(workers)) };
+    });
+    unsafe { ECALL_WORKERS.as_ref().unwrap() }
+}
+
+impl SgxEnclave {
+    ///
+    /// Makes f run on a worker of the default pool, with the enclave and the cancellation
+    /// flag of the ecall, and returns the future of its result.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let enclave = Arc::new(enclave);
+    /// let result = enclave
+    ///     .call_async(|enclave, cancel| {
+    ///         let mut retval = sgx_status_t::SGX_SUCCESS;
+    ///         let ret = unsafe { process(enclave.geteid(), &mut retval, cancel.as_ptr()) };
+    ///         ...
+    ///     })
+    ///     .await?;
+    /// ```
+    ///
+    pub fn call_async<R, F>(self: &Arc<Self>, f: F) -> SgxEcallFuture<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&SgxEnclave, &SgxEcallCancel) -> SgxResult<R> + Send + 'static,
+    {
+        rsgx_ecall_workers().call(self, f)
+    }
+}
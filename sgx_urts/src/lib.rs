@@ -30,6 +30,11 @@ pub mod mem;
 pub mod net;
 pub mod pipe;
 pub mod process;
+#[cfg(feature = "quote")]
+pub mod quote;
+#[cfg(feature = "quote_verify")]
+pub mod quote_verify;
+pub mod shm;
 pub mod signal;
 pub mod socket;
 pub mod sys;
@@ -38,3 +43,32 @@ pub mod time;
 
 mod enclave;
 pub use enclave::*;
+
+mod switchless;
+pub use switchless::*;
+
+mod pool;
+pub use pool::*;
+
+mod ecall;
+pub use ecall::*;
+
+mod ocall;
+pub use ocall::*;
+
+mod future;
+pub use future::*;
+
+mod metadata;
+pub use metadata::*;
+
+mod diagnostics;
+pub use diagnostics::*;
+
+mod broker;
+pub use broker::*;
+
+mod stats;
+pub use stats::*;
+
+mod shutdown;
@@ -0,0 +1,361 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Inspection of the SGX metadata of an enclave image.
+//!
+//! `SgxEnclaveMetadata` reads the metadata that sgx_sign adds to a signed enclave,
+//! without loading it, so that its resources and identity can be checked before it is
+//! launched. The sizes are derived from the layout table, the same way the uRTS lays
+//! out the enclave.
+
+use crate::enclave::cstr;
+use sgx_types::metadata::*;
+use sgx_types::*;
+use std::ffi::CStr;
+use std::mem;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+///
+/// rsgx_get_metadata reads the metadata of the signed enclave image file_name.
+///
+/// # Requirements
+///
+/// Header: sgx_urts.h
+///
+/// Library: libsgx_urts.a
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_ENCLAVE**
+///
+/// The file is not a signed enclave image.
+///
+/// **SGX_ERROR_INVALID_METADATA**
+///
+/// The metadata is incorrect.
+///
+pub fn rsgx_get_metadata(file_name: &CStr, metadata: &mut metadata_t) -> SgxError {
+    let ret = unsafe { sgx_get_metadata(file_name.as_ptr(), metadata as *mut metadata_t) };
+    match ret {
+        sgx_status_t::SGX_SUCCESS => Ok(()),
+        _ => Err(ret),
+    }
+}
+
+///
+/// An entry of the layout table of an enclave.
+///
+#[derive(Clone, Copy)]
+pub enum SgxLayoutEntry {
+    ///
+    /// A range of pages.
+    ///
+    Entry(layout_entry_t),
+    ///
+    /// A repetition, load_times more times, of the entry_count entries before it.
+    ///
+    Group(layout_group_t),
+}
+
+impl SgxLayoutEntry {
+    pub fn get_id(&self) -> u16 {
+        match *self {
+            SgxLayoutEntry::Entry(entry) => entry.id,
+            SgxLayoutEntry::Group(group) => group.id,
+        }
+    }
+}
+
+///
+/// The metadata of a signed enclave.
+///
+#[derive(Clone)]
+pub struct SgxEnclaveMetadata {
+    version: u64,
+    tcs_policy: u32,
+    ssa_frame_size: u32,
+    max_save_buffer_size: u32,
+    misc_select: sgx_misc_select_t,
+    misc_mask: sgx_misc_select_t,
+    tcs_min_pool: u32,
+    enclave_size: u64,
+    attributes: sgx_attributes_t,
+    attribute_mask: sgx_attributes_t,
+    mr_enclave: sgx_measurement_t,
+    isv_prod_id: sgx_prod_id_t,
+    isv_svn: sgx_isv_svn_t,
+    layout: Vec<SgxLayoutEntry>,
+}
+
+impl SgxEnclaveMetadata {
+    ///
+    /// Reads the metadata of the signed enclave image file_name, without loading it.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_ENCLAVE**
+    ///
+    /// The file name is not valid, or the file is not a signed enclave image.
+    ///
+    /// **SGX_ERROR_INVALID_METADATA**
+    ///
+    /// The metadata is incorrect.
+    ///
+    pub fn from_file<P: AsRef<Path>>(file_name: P) -> SgxResult<SgxEnclaveMetadata> {
+        let path = cstr(file_name.as_ref()).map_err(|_| sgx_status_t::SGX_ERROR_INVALID_ENCLAVE)?;
+        let mut metadata: Box<metadata_t> = Box::new(unsafe { mem::zeroed() });
+        rsgx_get_metadata(path.as_c_str(), &mut metadata)?;
+        SgxEnclaveMetadata::from_metadata(&metadata)
+    }
+
+    ///
+    /// Parses metadata as read by rsgx_get_metadata.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_METADATA**
+    ///
+    /// The magic number is wrong, or the layout table is out of the metadata.
+    ///
+    pub fn from_metadata(metadata: &metadata_t) -> SgxResult<SgxEnclaveMetadata> {
+        if { metadata.magic_num } != METADATA_MAGIC {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_METADATA);
+        }
+
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                metadata as *const metadata_t as *const u8,
+                mem::size_of::<metadata_t>(),
+            )
+        };
+        let dir = { metadata.dirs }[dir_index_t::DIR_LAYOUT as usize];
+        let offset = dir.offset as usize;
+        let size = dir.size as usize;
+        let entry_size = mem::size_of::<layout_t>();
+        if size % entry_size != 0
+            || offset
+                .checked_add(size)
+                .map_or(true, |end| end > bytes.len().min(metadata.size as usize))
+        {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_METADATA);
+        }
+
+        let mut layout = Vec::with_capacity(size / entry_size);
+        for (i, chunk) in bytes[offset..offset + size]
+            .chunks_exact(entry_size)
+            .enumerate()
+        {
+            let entry = unsafe { ptr::read_unaligned(chunk.as_ptr() as *const layout_entry_t) };
+            if entry.id as u32 & GROUP_FLAG != 0 {
+                let group = unsafe { ptr::read_unaligned(chunk.as_ptr() as *const layout_group_t) };
+                if group.entry_count as usize > i {
+                    return Err(sgx_status_t::SGX_ERROR_INVALID_METADATA);
+                }
+                layout.push(SgxLayoutEntry::Group(group));
+            } else {
+                layout.push(SgxLayoutEntry::Entry(entry));
+            }
+        }
+
+        let css = &metadata.enclave_css.body;
+        Ok(SgxEnclaveMetadata {
+            version: metadata.version,
+            tcs_policy: metadata.tcs_policy,
+            ssa_frame_size: metadata.ssa_frame_size,
+            max_save_buffer_size: metadata.max_save_buffer_size,
+            misc_select: metadata.desired_misc_select,
+            misc_mask: css.misc_mask,
+            tcs_min_pool: metadata.tcs_min_pool,
+            enclave_size: metadata.enclave_size,
+            attributes: metadata.attributes,
+            attribute_mask: css.attribute_mask,
+            mr_enclave: css.enclave_hash,
+            isv_prod_id: css.isv_prod_id,
+            isv_svn: css.isv_svn,
+            layout,
+        })
+    }
+
+    ///
+    /// Get the version of the metadata, with the major version in the high 32 bits.
+    ///
+    pub fn get_version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn get_tcs_policy(&self) -> u32 {
+        self.tcs_policy
+    }
+
+    ///
+    /// Get the size of an SSA frame, in pages.
+    ///
+    pub fn get_ssa_frame_size(&self) -> u32 {
+        self.ssa_frame_size
+    }
+
+    pub fn get_max_save_buffer_size(&self) -> u32 {
+        self.max_save_buffer_size
+    }
+
+    pub fn get_misc_select(&self) -> sgx_misc_select_t {
+        self.misc_select
+    }
+
+    pub fn get_misc_mask(&self) -> sgx_misc_select_t {
+        self.misc_mask
+    }
+
+    pub fn get_attributes(&self) -> sgx_attributes_t {
+        self.attributes
+    }
+
+    pub fn get_attribute_mask(&self) -> sgx_attributes_t {
+        self.attribute_mask
+    }
+
+    pub fn is_debug(&self) -> bool {
+        self.attributes.flags & SGX_FLAGS_DEBUG != 0
+    }
+
+    pub fn get_enclave_size(&self) -> u64 {
+        self.enclave_size
+    }
+
+    ///
+    /// Get the MRENCLAVE of the enclave, as signed.
+    ///
+    pub fn get_mr_enclave(&self) -> sgx_measurement_t {
+        self.mr_enclave
+    }
+
+    pub fn get_isv_prod_id(&self) -> sgx_prod_id_t {
+        self.isv_prod_id
+    }
+
+    pub fn get_isv_svn(&self) -> sgx_isv_svn_t {
+        self.isv_svn
+    }
+
+    pub fn get_layout(&self) -> &[SgxLayoutEntry] {
+        &self.layout
+    }
+
+    ///
+    /// Get the number of TCS added when the enclave is loaded.
+    ///
+    pub fn get_tcs_num(&self) -> u32 {
+        self.count(LAYOUT_ID_TCS as u16)
+    }
+
+    ///
+    /// Get the maximum number of TCS, with those added at run time with EDMM.
+    ///
+    pub fn get_tcs_max_num(&self) -> u32 {
+        self.get_tcs_num() + self.count(LAYOUT_ID_TCS_DYN as u16)
+    }
+
+    ///
+    /// Get the number of TCS kept available for dynamic threads.
+    ///
+    pub fn get_tcs_min_pool(&self) -> u32 {
+        self.tcs_min_pool
+    }
+
+    pub fn get_heap_min_size(&self) -> u64 {
+        self.size(LAYOUT_ID_HEAP_MIN)
+    }
+
+    pub fn get_heap_init_size(&self) -> u64 {
+        self.get_heap_min_size() + self.size(LAYOUT_ID_HEAP_INIT)
+    }
+
+    pub fn get_heap_max_size(&self) -> u64 {
+        self.get_heap_init_size() + self.size(LAYOUT_ID_HEAP_MAX)
+    }
+
+    ///
+    /// Get the stack size of a thread added when the enclave is loaded.
+    ///
+    pub fn get_stack_min_size(&self) -> u64 {
+        match self.find(LAYOUT_ID_STACK_MIN) {
+            Some(_) => self.size(LAYOUT_ID_STACK_MIN),
+            None => self.get_stack_max_size(),
+        }
+    }
+
+    ///
+    /// Get the stack size a thread can grow to with EDMM.
+    ///
+    pub fn get_stack_max_size(&self) -> u64 {
+        self.size(LAYOUT_ID_STACK_MAX) + self.size(LAYOUT_ID_STACK_MIN)
+    }
+
+    pub fn get_rsrv_min_size(&self) -> u64 {
+        self.size(LAYOUT_ID_RSRV_MIN)
+    }
+
+    pub fn get_rsrv_init_size(&self) -> u64 {
+        self.get_rsrv_min_size() + self.size(LAYOUT_ID_RSRV_INIT)
+    }
+
+    pub fn get_rsrv_max_size(&self) -> u64 {
+        self.get_rsrv_init_size() + self.size(LAYOUT_ID_RSRV_MAX)
+    }
+
+    ///
+    /// Whether some pages are only added at run time, which needs EDMM.
+    ///
+    pub fn uses_edmm(&self) -> bool {
+        self.layout.iter().any(|entry| match *entry {
+            SgxLayoutEntry::Entry(entry) => entry.attributes & PAGE_ATTR_POST_ADD != 0,
+            SgxLayoutEntry::Group(_) => false,
+        })
+    }
+
+    // The first entry with the id.
+    fn find(&self, id: u32) -> Option<layout_entry_t> {
+        self.layout.iter().find_map(|entry| match *entry {
+            SgxLayoutEntry::Entry(entry) if entry.id as u32 == id => Some(entry),
+            _ => None,
+        })
+    }
+
+    // The size of the first entry with the id.
+    fn size(&self, id: u32) -> u64 {
+        self.find(id)
+            .map_or(0, |entry| entry.page_count as u64 * SE_PAGE_SIZE as u64)
+    }
+
+    // The number of entries with the id once the groups are repeated.
+    fn count(&self, id: u16) -> u32 {
+        let mut counts: Vec<u64> = Vec::with_capacity(self.layout.len());
+        for (i, entry) in self.layout.iter().enumerate() {
+            let count = match *entry {
+                SgxLayoutEntry::Entry(entry) => (entry.id == id) as u64,
+                SgxLayoutEntry::Group(group) => {
+                    let repeated: u64 = counts[i - group.entry_count as usize..].iter().sum();
+                    repeated.saturating_mul(group.load_times as u64)
+                }
+            };
+            counts.push(count);
+        }
+        counts.iter().sum::<u64>().min(u32::MAX as u64) as u32
+    }
+}
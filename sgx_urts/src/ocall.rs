@@ -0,0 +1,341 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Rust-native ocalls.
+//!
+//! The application registers the ocalls of an enclave as closures, keyed by the name
+//! the enclave passes to `sgx_trts::ecall::rsgx_rust_ocall` through
+//! `rsgx_ecall_id`. They are handled during the Rust-native ecalls made with
+//! rsgx_rust_ecall or the proxies of `sgx_ecall_proxy!`:
+//!
+//! ```rust,ignore
+//! let ocalls = SgxOcalls::new()
+//!     .register("log", |level: u32, message: String| {
+//!         println!("[{}] {}", level, message);
+//!         Ok(())
+//!     })
+//!     .register("now", || Ok(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()));
+//! enclave.set_ocalls(ocalls);
+//! ```
+//!
+//! The arguments are decoded from the untrusted stack of the enclave into owned values
+//! before the closure runs, and its return value is encoded into the output buffer the
+//! enclave allocated, so no closure sees memory that outlives the ocall.
+
+use crate::ecall::{rsgx_ecall_id, EcallReader, EcallValue, EcallWriter};
+use crate::enclave::SgxEnclave;
+use crate::stats::rsgx_count_ocall;
+use sgx_types::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::{Arc, Mutex, Once};
+
+///
+/// The handler of a Rust-native ocall.
+///
+pub trait SgxOcallHandler: Send + Sync {
+    ///
+    /// Handles the encoded arguments input, and encodes the return value to output.
+    ///
+    fn handle(&self, input: &[u8], output: &mut EcallWriter) -> SgxError;
+}
+
+///
+/// A closure that handles a Rust-native ocall. It takes its arguments by value and
+/// returns a result, and all of them implement `EcallValue`.
+///
+pub trait SgxOcallFn<Args>: Send + Sync + 'static {
+    fn call(&self, reader: &mut EcallReader, output: &mut EcallWriter) -> SgxError;
+}
+
+macro_rules! impl_ocall_fn {
+    ($($arg:ident)*) => {
+        impl<Func, Ret, $($arg),*> SgxOcallFn<($($arg,)*)> for Func
+        where
+            Func: Fn($($arg),*) -> SgxResult<Ret> + Send + Sync + 'static,
+            Ret: EcallValue,
+            $($arg: EcallValue,)*
+        {
+            #[allow(non_snake_case)]
+            fn call(&self, reader: &mut EcallReader, output: &mut EcallWriter) -> SgxError {
+                $(let $arg = reader.read::<$arg>()?;)*
+                reader.finish()?;
+                let ret = self($($arg),*)?;
+                output.write(&ret);
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_ocall_fn! {}
+impl_ocall_fn! { A }
+impl_ocall_fn! { A B }
+impl_ocall_fn! { A B C }
+impl_ocall_fn! { A B C D }
+impl_ocall_fn! { A B C D E }
+impl_ocall_fn! { A B C D E F }
+
+struct TypedOcall<Func, Args> {
+    func: Func,
+    args: PhantomData<fn(Args)>,
+}
+
+impl<Func: SgxOcallFn<Args>, Args> SgxOcallHandler for TypedOcall<Func, Args> {
+    fn handle(&self, input: &[u8], output: &mut EcallWriter) -> SgxError {
+        self.func.call(&mut EcallReader::new(input), output)
+    }
+}
+
+///
+/// The ocall handlers of an enclave.
+///
+#[derive(Default)]
+pub struct SgxOcalls {
+    handlers: HashMap<u32, Box<dyn SgxOcallHandler>>,
+}
+
+impl SgxOcalls {
+    pub fn new() -> SgxOcalls {
+        SgxOcalls::default()
+    }
+
+    ///
+    /// Handles the ocall name with a closure. It replaces the handler of the same name.
+    ///
+    pub fn register<Args: 'static, Func: SgxOcallFn<Args>>(
+        self,
+        name: &str,
+        func: Func,
+    ) -> SgxOcalls {
+        self.register_handler(
+            name,
+            Box::new(TypedOcall {
+                func,
+                args: PhantomData,
+            }),
+        )
+    }
+
+    ///
+    /// Handles the ocall name with a handler, which decodes the arguments itself.
+    ///
+    pub fn register_handler(mut self, name: &str, handler: Box<dyn SgxOcallHandler>) -> SgxOcalls {
+        self.handlers.insert(rsgx_ecall_id(name), handler);
+        self
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.handlers.contains_key(&rsgx_ecall_id(name))
+    }
+
+    fn handle(&self, id: u32, input: &[u8], output: &mut EcallWriter) -> SgxError {
+        match self.handlers.get(&id) {
+            Some(handler) => handler.handle(input, output),
+            None => Err(sgx_status_t::SGX_ERROR_INVALID_FUNCTION),
+        }
+    }
+}
+
+type OcallRegistry = Mutex<HashMap<sgx_enclave_id_t, Arc<SgxOcalls>>>;
+
+static mut OCALL_REGISTRY: Option<OcallRegistry> = None;
+static OCALL_REGISTRY_INIT: Once = Once::new();
+
+fn registry() -> &'static OcallRegistry {
+    OCALL_REGISTRY_INIT.call_once(|| unsafe {
+        OCALL_REGISTRY = Some(Mutex::new(HashMap::new()));
+    });
+    unsafe { OCALL_REGISTRY.as_ref().unwrap() }
+}
+
+thread_local! {
+    static CURRENT_OCALLS: RefCell<Option<Arc<SgxOcalls>>> = RefCell::new(None);
+}
+
+///
+/// rsgx_set_ocalls sets the ocall handlers of the enclave enclave_id. It replaces
+/// those set before, and applies to the ecalls that start afterwards.
+///
+pub fn rsgx_set_ocalls(enclave_id: sgx_enclave_id_t, ocalls: SgxOcalls) {
+    registry()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(enclave_id, Arc::new(ocalls));
+}
+
+///
+/// rsgx_clear_ocalls removes the ocall handlers of the enclave enclave_id.
+///
+pub fn rsgx_clear_ocalls(enclave_id: sgx_enclave_id_t) {
+    registry()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&enclave_id);
+}
+
+impl SgxEnclave {
+    ///
+    /// Sets the ocall handlers of the enclave, which are removed when it is destroyed.
+    ///
+    pub fn set_ocalls(&self, ocalls: SgxOcalls) {
+        rsgx_set_ocalls(self.geteid(), ocalls);
+    }
+}
+
+// Makes the ocall handlers of the enclave current on this thread until it is
+// dropped, for an ecall.
+pub(crate) struct OcallScope {
+    previous: Option<Arc<SgxOcalls>>,
+}
+
+impl OcallScope {
+    pub(crate) fn enter(enclave_id: sgx_enclave_id_t) -> OcallScope {
+        let ocalls = registry()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&enclave_id)
+            .cloned();
+        let previous = CURRENT_OCALLS.with(|current| current.replace(ocalls));
+        OcallScope { previous }
+    }
+}
+
+impl Drop for OcallScope {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT_OCALLS.with(|current| current.replace(previous));
+    }
+}
+
+#[repr(C)]
+pub(crate) struct OcallTable {
+    nr_ocall: size_t,
+    table: [*const c_void; 1],
+}
+
+pub(crate) fn rust_ocall_table() -> OcallTable {
+    OcallTable {
+        nr_ocall: 1,
+        table: [rust_ocall as *const c_void],
+    }
+}
+
+extern "C" fn rust_ocall(pms: *mut c_void) -> sgx_status_t {
+    if pms.is_null() {
+        return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
+    }
+    let p_ms = pms as *mut sgx_rust_ocall_ms_t;
+    let ms = unsafe { ptr::read_unaligned(p_ms) };
+    let input = if ms.ms_input_len > 0 {
+        unsafe { slice::from_raw_parts(ms.ms_input, ms.ms_input_len) }
+    } else {
+        &[]
+    };
+
+    rsgx_count_ocall();
+    let mut output = EcallWriter::new();
+    // The handler may make a nested ecall, which replaces CURRENT_OCALLS, so
+    // no borrow of it is held while the handler runs.
+    let ocalls = CURRENT_OCALLS.with(|current| current.borrow().clone());
+    let retval = match ocalls {
+        Some(ocalls) => panic::catch_unwind(AssertUnwindSafe(|| {
+            ocalls.handle(ms.ms_id, input, &mut output)
+        }))
+        .unwrap_or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED)),
+        None => Err(sgx_status_t::SGX_ERROR_INVALID_FUNCTION),
+    };
+    let output = output.as_bytes();
+
+    let retval = match retval {
+        Ok(()) if output.len() > ms.ms_output_size => sgx_status_t::SGX_ERROR_OUT_OF_MEMORY,
+        Ok(()) => {
+            unsafe { ptr::copy_nonoverlapping(output.as_ptr(), ms.ms_output, output.len()) };
+            sgx_status_t::SGX_SUCCESS
+        }
+        Err(e) => e,
+    };
+    unsafe {
+        ptr::write_unaligned(ptr::addr_of_mut!((*p_ms).ms_output_len), output.len());
+        ptr::write_unaligned(ptr::addr_of_mut!((*p_ms).ms_retval), retval);
+    }
+    sgx_status_t::SGX_SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(id: &str, input: &[u8], output: &mut [u8]) -> SgxResult<usize> {
+        let mut ms = sgx_rust_ocall_ms_t {
+            ms_retval: sgx_status_t::SGX_ERROR_UNEXPECTED,
+            ms_id: rsgx_ecall_id(id),
+            ms_input: input.as_ptr(),
+            ms_input_len: input.len(),
+            ms_output: output.as_mut_ptr(),
+            ms_output_size: output.len(),
+            ms_output_len: 0,
+        };
+        let ret = rust_ocall(&mut ms as *mut sgx_rust_ocall_ms_t as *mut c_void);
+        if ret != sgx_status_t::SGX_SUCCESS {
+            return Err(ret);
+        }
+        if ms.ms_retval != sgx_status_t::SGX_SUCCESS {
+            return Err(ms.ms_retval);
+        }
+        Ok(ms.ms_output_len)
+    }
+
+    // An ocall whose handler makes a nested ecall into another enclave, which
+    // makes an ocall of its own.
+    #[test]
+    fn test_reentrant_ocall() {
+        const OUTER: sgx_enclave_id_t = 0x7fff_0001;
+        const INNER: sgx_enclave_id_t = 0x7fff_0002;
+
+        rsgx_set_ocalls(INNER, SgxOcalls::new().register("inner", || Ok(2_u32)));
+        rsgx_set_ocalls(
+            OUTER,
+            SgxOcalls::new().register("outer", || {
+                let _scope = OcallScope::enter(INNER);
+                let mut output = [0_u8; 16];
+                let len = call("inner", &[], &mut output)?;
+                let inner = EcallReader::new(&output[..len]).read::<u32>()?;
+                Ok(inner + 1)
+            }),
+        );
+
+        {
+            let _scope = OcallScope::enter(OUTER);
+            let mut output = [0_u8; 16];
+            let len = call("outer", &[], &mut output).unwrap();
+            assert_eq!(EcallReader::new(&output[..len]).read::<u32>(), Ok(3));
+            // The nested scope restored the outer handlers.
+            assert_eq!(
+                call("inner", &[], &mut output),
+                Err(sgx_status_t::SGX_ERROR_INVALID_FUNCTION)
+            );
+        }
+
+        rsgx_clear_ocalls(OUTER);
+        rsgx_clear_ocalls(INNER);
+    }
+}
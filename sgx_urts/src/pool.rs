@@ -0,0 +1,197 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! A pool of interchangeable enclaves.
+//!
+//! `SgxEnclavePool` keeps a number of instances of one enclave, spreads ecalls across
+//! them, and replaces instances that crashed or were lost, for instance after a panic in
+//! the enclave, a power transition or a fork. An ecall that fails this way returns its
+//! error, and the next ecall on that slot goes to a new instance.
+//!
+//! Ecalls are only retried on another instance when the pool is built with
+//! `max_retries`, since the pool cannot know whether an ecall that crashed an enclave had
+//! effects outside it. An ecall is always moved to another instance, without counting as
+//! a retry, when an instance has no free TCS.
+
+use crate::enclave::SgxEnclave;
+use sgx_types::*;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+type EnclaveFactory = dyn Fn(usize) -> SgxResult<SgxEnclave> + Send + Sync;
+
+///
+/// A fixed number of instances of an enclave, which are respawned when they fail.
+///
+pub struct SgxEnclavePool {
+    factory: Box<EnclaveFactory>,
+    slots: Vec<Mutex<Option<Arc<SgxEnclave>>>>,
+    next: AtomicUsize,
+    respawns: AtomicU64,
+    max_retries: usize,
+}
+
+impl SgxEnclavePool {
+    ///
+    /// Creates size instances with factory, which is given the index of the slot and is
+    /// called again whenever the instance of that slot has to be replaced.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// size is 0.
+    ///
+    /// The errors of factory. The instances created before are destroyed.
+    ///
+    pub fn new<F>(size: usize, factory: F) -> SgxResult<SgxEnclavePool>
+    where
+        F: Fn(usize) -> SgxResult<SgxEnclave> + Send + Sync + 'static,
+    {
+        if size == 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let mut slots = Vec::with_capacity(size);
+        for index in 0..size {
+            slots.push(Mutex::new(Some(Arc::new(factory(index)?))));
+        }
+        Ok(SgxEnclavePool {
+            factory: Box::new(factory),
+            slots,
+            next: AtomicUsize::new(0),
+            respawns: AtomicU64::new(0),
+            max_retries: 0,
+        })
+    }
+
+    ///
+    /// Retries an ecall whose instance crashed or was lost on up to retries other
+    /// instances. Only for ecalls that are safe to repeat.
+    ///
+    pub fn max_retries(mut self, retries: usize) -> SgxEnclavePool {
+        self.max_retries = retries;
+        self
+    }
+
+    ///
+    /// Get the number of slots of the pool.
+    ///
+    pub fn get_size(&self) -> usize {
+        self.slots.len()
+    }
+
+    ///
+    /// Get the number of instances that were replaced since the pool was created.
+    ///
+    pub fn get_respawn_count(&self) -> u64 {
+        self.respawns.load(Ordering::Relaxed)
+    }
+
+    ///
+    /// Makes an ecall on the next instance, in turn.
+    ///
+    /// f makes the ecall and returns its status. If the instance crashed or was lost,
+    /// it is replaced, and the ecall is retried as allowed by max_retries.
+    ///
+    /// # Errors
+    ///
+    /// The errors of f, and of the factory if an instance could not be replaced.
+    ///
+    /// **SGX_ERROR_OUT_OF_TCS**
+    ///
+    /// No instance had a free TCS.
+    ///
+    pub fn call<R, F>(&self, mut f: F) -> SgxResult<R>
+    where
+        F: FnMut(&SgxEnclave) -> SgxResult<R>,
+    {
+        let mut retries = 0;
+        let mut busy = 0;
+        loop {
+            let index = self.next.fetch_add(1, Ordering::Relaxed) % self.slots.len();
+            let enclave = self.instance(index)?;
+            match f(&enclave) {
+                Err(sgx_status_t::SGX_ERROR_OUT_OF_TCS) if busy + 1 < self.slots.len() => {
+                    busy += 1;
+                }
+                Err(e) if is_failed(e) => {
+                    self.retire(index, &enclave);
+                    if retries >= self.max_retries {
+                        return Err(e);
+                    }
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    ///
+    /// Runs probe on every instance and replaces those for which it fails, whatever the
+    /// error. Returns the number of instances replaced.
+    ///
+    /// # Errors
+    ///
+    /// The errors of the factory. The instances probed before are kept or replaced.
+    ///
+    pub fn check_health<F>(&self, probe: F) -> SgxResult<usize>
+    where
+        F: Fn(&SgxEnclave) -> SgxError,
+    {
+        let mut replaced = 0;
+        for index in 0..self.slots.len() {
+            let enclave = self.instance(index)?;
+            if probe(&enclave).is_err() {
+                self.retire(index, &enclave);
+                self.instance(index)?;
+                replaced += 1;
+            }
+        }
+        Ok(replaced)
+    }
+
+    // The instance of a slot, created if the slot is empty.
+    fn instance(&self, index: usize) -> SgxResult<Arc<SgxEnclave>> {
+        let mut slot = self.slots[index].lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(ref enclave) = *slot {
+            return Ok(enclave.clone());
+        }
+        let enclave = Arc::new((self.factory)(index)?);
+        self.respawns.fetch_add(1, Ordering::Relaxed);
+        *slot = Some(enclave.clone());
+        Ok(enclave)
+    }
+
+    // Empties a slot if it still holds enclave. The instance is destroyed once the
+    // ecalls still running on it return.
+    fn retire(&self, index: usize, enclave: &Arc<SgxEnclave>) {
+        let mut slot = self.slots[index].lock().unwrap_or_else(|e| e.into_inner());
+        if slot
+            .as_ref()
+            .map_or(false, |current| Arc::ptr_eq(current, enclave))
+        {
+            *slot = None;
+        }
+    }
+}
+
+fn is_failed(status: sgx_status_t) -> bool {
+    matches!(
+        status,
+        sgx_status_t::SGX_ERROR_ENCLAVE_CRASHED | sgx_status_t::SGX_ERROR_ENCLAVE_LOST
+    )
+}
@@ -15,8 +15,100 @@
 // specific language governing permissions and limitations
 // under the License..
 
-use libc::{self, pid_t};
+use libc::{self, c_char, c_int, pid_t, size_t};
+use std::cmp;
+use std::ffi::{CStr, OsStr};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, Stdio};
+use std::ptr;
+use std::slice;
+
 #[no_mangle]
 pub extern "C" fn u_getpid_ocall() -> pid_t {
     unsafe { libc::getpid() }
 }
+
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn u_run_command_ocall(
+    error: *mut c_int,
+    argv: *const c_char,
+    argv_len: size_t,
+    envp: *const c_char,
+    envp_len: size_t,
+    env_clear: c_int,
+    cwd: *const c_char,
+    out_buf: *mut u8,
+    out_cap: size_t,
+    out_len: *mut size_t,
+    err_buf: *mut u8,
+    err_cap: size_t,
+    err_len: *mut size_t,
+    wait_status: *mut c_int,
+) -> c_int {
+    fn split<'a>(buf: *const c_char, len: size_t) -> Vec<&'a [u8]> {
+        if buf.is_null() || len == 0 {
+            return Vec::new();
+        }
+        let buf = unsafe { slice::from_raw_parts(buf as *const u8, len) };
+        buf.split(|b| *b == 0).filter(|s| !s.is_empty()).collect()
+    }
+
+    fn copy_out(src: &[u8], buf: *mut u8, cap: size_t, len: *mut size_t) {
+        let n = cmp::min(src.len(), cap);
+        if !buf.is_null() && n > 0 {
+            unsafe { ptr::copy_nonoverlapping(src.as_ptr(), buf, n) };
+        }
+        if !len.is_null() {
+            unsafe { *len = src.len() };
+        }
+    }
+
+    let mut errno = 0;
+    let mut ret = -1;
+    let argv = split(argv, argv_len);
+    if let Some((program, args)) = argv.split_first() {
+        let mut command = Command::new(OsStr::from_bytes(program));
+        command
+            .args(args.iter().map(|a| OsStr::from_bytes(a)))
+            .stdin(Stdio::null());
+        if env_clear != 0 {
+            command.env_clear();
+        }
+        for entry in split(envp, envp_len) {
+            match entry.iter().position(|b| *b == b'=') {
+                Some(pos) => command.env(
+                    OsStr::from_bytes(&entry[..pos]),
+                    OsStr::from_bytes(&entry[pos + 1..]),
+                ),
+                None => command.env_remove(OsStr::from_bytes(entry)),
+            };
+        }
+        if !cwd.is_null() {
+            let cwd = unsafe { CStr::from_ptr(cwd) };
+            command.current_dir(OsStr::from_bytes(cwd.to_bytes()));
+        }
+
+        match command.output() {
+            Ok(output) => {
+                copy_out(&output.stdout, out_buf, out_cap, out_len);
+                copy_out(&output.stderr, err_buf, err_cap, err_len);
+                if !wait_status.is_null() {
+                    unsafe { *wait_status = output.status.into_raw() };
+                }
+                ret = 0;
+            }
+            Err(e) => errno = e.raw_os_error().unwrap_or(libc::EIO),
+        }
+    } else {
+        errno = libc::EINVAL;
+    }
+
+    if !error.is_null() {
+        unsafe {
+            *error = errno;
+        }
+    }
+    ret
+}
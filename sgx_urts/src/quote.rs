@@ -0,0 +1,226 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Host side of ECDSA quote generation, for enclaves importing `sgx_quote.edl`.
+//!
+//! The `u_quote_ex_*` ocalls go through the quote_ex interface of AESM, so the
+//! application links `libsgx_quote_ex`. The ECDSA P-256 attestation key is selected
+//! here; the enclave checks the QE report and the quote it gets back, so nothing
+//! returned by these functions has to be trusted.
+//!
+//! The `u_dcap_qe_*` ocalls load the QE in process through the DCAP quote library
+//! instead, for hosts without AESM. `libsgx_dcap_ql.so.1` is opened on first use, so
+//! applications that only use AESM do not need it installed.
+
+use libc::{self, c_void};
+use sgx_types::*;
+use std::ffi::CStr;
+use std::mem;
+use std::ptr;
+use std::sync::Once;
+
+fn select_ecdsa_key_id() -> Result<sgx_att_key_id_t, sgx_status_t> {
+    let mut num: u32 = 0;
+    let ret = unsafe { sgx_get_supported_att_key_id_num(&mut num as *mut u32) };
+    if ret != sgx_status_t::SGX_SUCCESS {
+        return Err(ret);
+    }
+    let mut list = vec![sgx_att_key_id_ext_t::default(); num as usize];
+    let ret = unsafe { sgx_get_supported_att_key_ids(list.as_mut_ptr(), num) };
+    if ret != sgx_status_t::SGX_SUCCESS {
+        return Err(ret);
+    }
+
+    let ecdsa = sgx_ql_attestation_algorithm_id_t::SGX_QL_ALG_ECDSA_P256 as u32;
+    let key = list
+        .iter()
+        .find(|key| key.base.algorithm_id == ecdsa)
+        .ok_or(sgx_status_t::SGX_ERROR_UNSUPPORTED_ATT_KEY_ID)?;
+    let mut att_key_id = sgx_att_key_id_t::default();
+    unsafe {
+        ptr::copy_nonoverlapping(
+            key as *const _ as *const u8,
+            &mut att_key_id as *mut _ as *mut u8,
+            mem::size_of::<sgx_att_key_id_t>(),
+        );
+    }
+    Ok(att_key_id)
+}
+
+#[no_mangle]
+pub extern "C" fn u_quote_ex_init_ocall(
+    att_key_id: *mut sgx_att_key_id_t,
+    qe_target_info: *mut sgx_target_info_t,
+) -> sgx_status_t {
+    if att_key_id.is_null() || qe_target_info.is_null() {
+        return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
+    }
+    let key_id = match select_ecdsa_key_id() {
+        Ok(key_id) => key_id,
+        Err(e) => return e,
+    };
+
+    // The first call returns the size of the public key ID, the second one
+    // initializes the attestation key and returns the QE target info.
+    let mut pub_key_id_size: usize = 0;
+    let ret = unsafe {
+        sgx_init_quote_ex(
+            &key_id as *const sgx_att_key_id_t,
+            qe_target_info,
+            &mut pub_key_id_size as *mut usize,
+            ptr::null_mut(),
+        )
+    };
+    if ret != sgx_status_t::SGX_SUCCESS {
+        return ret;
+    }
+    let mut pub_key_id = vec![0_u8; pub_key_id_size];
+    let ret = unsafe {
+        sgx_init_quote_ex(
+            &key_id as *const sgx_att_key_id_t,
+            qe_target_info,
+            &mut pub_key_id_size as *mut usize,
+            pub_key_id.as_mut_ptr(),
+        )
+    };
+    if ret == sgx_status_t::SGX_SUCCESS {
+        unsafe {
+            *att_key_id = key_id;
+        }
+    }
+    ret
+}
+
+#[no_mangle]
+pub extern "C" fn u_quote_ex_get_quote_size_ocall(
+    att_key_id: *const sgx_att_key_id_t,
+    quote_size: *mut u32,
+) -> sgx_status_t {
+    if att_key_id.is_null() || quote_size.is_null() {
+        return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
+    }
+    unsafe { sgx_get_quote_size_ex(att_key_id, quote_size) }
+}
+
+#[no_mangle]
+pub extern "C" fn u_quote_ex_get_quote_ocall(
+    report: *const sgx_report_t,
+    att_key_id: *const sgx_att_key_id_t,
+    qe_report_info: *mut sgx_qe_report_info_t,
+    quote: *mut u8,
+    quote_size: u32,
+) -> sgx_status_t {
+    if report.is_null() || att_key_id.is_null() || qe_report_info.is_null() || quote.is_null() {
+        return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
+    }
+    unsafe { sgx_get_quote_ex(report, att_key_id, qe_report_info, quote, quote_size) }
+}
+
+const DCAP_QL_LIBRARY: &[u8] = b"libsgx_dcap_ql.so.1\0";
+
+type QeGetTargetInfoFn = unsafe extern "C" fn(*mut sgx_target_info_t) -> sgx_quote3_error_t;
+type QeGetQuoteSizeFn = unsafe extern "C" fn(*mut u32) -> sgx_quote3_error_t;
+type QeGetQuoteFn = unsafe extern "C" fn(*const sgx_report_t, u32, *mut u8) -> sgx_quote3_error_t;
+
+struct DcapQuoteLibrary {
+    get_target_info: QeGetTargetInfoFn,
+    get_quote_size: QeGetQuoteSizeFn,
+    get_quote: QeGetQuoteFn,
+}
+
+static mut DCAP_QL: Option<DcapQuoteLibrary> = None;
+static DCAP_QL_INIT: Once = Once::new();
+
+unsafe fn dcap_symbol(handle: *mut c_void, name: &[u8]) -> Option<*mut c_void> {
+    let name = CStr::from_bytes_with_nul(name).ok()?;
+    let symbol = libc::dlsym(handle, name.as_ptr());
+    if symbol.is_null() {
+        None
+    } else {
+        Some(symbol)
+    }
+}
+
+unsafe fn dcap_load() -> Option<DcapQuoteLibrary> {
+    let handle = libc::dlopen(
+        DCAP_QL_LIBRARY.as_ptr() as *const libc::c_char,
+        libc::RTLD_NOW,
+    );
+    if handle.is_null() {
+        return None;
+    }
+    let library = (|| {
+        Some(DcapQuoteLibrary {
+            get_target_info: mem::transmute(dcap_symbol(handle, b"sgx_qe_get_target_info\0")?),
+            get_quote_size: mem::transmute(dcap_symbol(handle, b"sgx_qe_get_quote_size\0")?),
+            get_quote: mem::transmute(dcap_symbol(handle, b"sgx_qe_get_quote\0")?),
+        })
+    })();
+    if library.is_none() {
+        libc::dlclose(handle);
+    }
+    library
+}
+
+// The library stays loaded for the lifetime of the process, like the QE it loads.
+fn dcap_library() -> Result<&'static DcapQuoteLibrary, sgx_quote3_error_t> {
+    unsafe {
+        DCAP_QL_INIT.call_once(|| DCAP_QL = dcap_load());
+        DCAP_QL
+            .as_ref()
+            .ok_or(sgx_quote3_error_t::SGX_QL_PLATFORM_LIB_UNAVAILABLE)
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn u_dcap_qe_get_target_info_ocall(
+    qe_target_info: *mut sgx_target_info_t,
+) -> sgx_quote3_error_t {
+    if qe_target_info.is_null() {
+        return sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER;
+    }
+    match dcap_library() {
+        Ok(library) => unsafe { (library.get_target_info)(qe_target_info) },
+        Err(e) => e,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn u_dcap_qe_get_quote_size_ocall(quote_size: *mut u32) -> sgx_quote3_error_t {
+    if quote_size.is_null() {
+        return sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER;
+    }
+    match dcap_library() {
+        Ok(library) => unsafe { (library.get_quote_size)(quote_size) },
+        Err(e) => e,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn u_dcap_qe_get_quote_ocall(
+    report: *const sgx_report_t,
+    quote: *mut u8,
+    quote_size: u32,
+) -> sgx_quote3_error_t {
+    if report.is_null() || quote.is_null() {
+        return sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER;
+    }
+    match dcap_library() {
+        Ok(library) => unsafe { (library.get_quote)(report, quote_size, quote) },
+        Err(e) => e,
+    }
+}
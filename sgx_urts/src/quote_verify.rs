@@ -0,0 +1,74 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Host side of QvE based quote verification, for enclaves importing `sgx_quote.edl`.
+//!
+//! The ocalls go through the DCAP quote verification library, so the application
+//! links `libsgx_dcap_quoteverify`, which fetches the collateral through the
+//! configured PCCS and runs the QvE. The enclave checks the QvE report and its
+//! binding to the verdict, so nothing returned by these functions has to be trusted.
+
+use sgx_types::*;
+use std::ptr;
+
+#[no_mangle]
+pub extern "C" fn u_qv_get_supplemental_data_size_ocall(size: *mut u32) -> sgx_quote3_error_t {
+    if size.is_null() {
+        return sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER;
+    }
+    unsafe { sgx_qv_get_quote_supplemental_data_size(size) }
+}
+
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn u_qv_verify_quote_ocall(
+    quote: *const u8,
+    quote_size: u32,
+    expiration_check_date: time_t,
+    collateral_expiration_status: *mut u32,
+    qv_result: *mut sgx_ql_qv_result_t,
+    qve_report_info: *mut sgx_ql_qe_report_info_t,
+    supplemental_data: *mut u8,
+    supplemental_data_size: u32,
+) -> sgx_quote3_error_t {
+    if quote.is_null()
+        || collateral_expiration_status.is_null()
+        || qv_result.is_null()
+        || qve_report_info.is_null()
+    {
+        return sgx_quote3_error_t::SGX_QL_ERROR_INVALID_PARAMETER;
+    }
+    let supplemental_data = if supplemental_data_size == 0 {
+        ptr::null_mut()
+    } else {
+        supplemental_data
+    };
+    // With no collateral given, the library fetches it from the PCCS.
+    unsafe {
+        sgx_qv_verify_quote(
+            quote,
+            quote_size,
+            ptr::null(),
+            expiration_check_date,
+            collateral_expiration_status,
+            qv_result,
+            qve_report_info,
+            supplemental_data_size,
+            supplemental_data,
+        )
+    }
+}
@@ -0,0 +1,316 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Shared memory rings between the enclave and the host.
+//!
+//! A ring is created by the enclave through `u_shm_ring_create_ocall`; the
+//! enclave then passes the returned address to the host code that produces
+//! or consumes the data, which attaches to it with [`ShmRing::attach`]. The
+//! mapping is released once both sides are done with it.
+
+use libc::{self, c_int, c_void, size_t};
+use sgx_types::*;
+use std::cmp;
+use std::io::{self, Error, ErrorKind, Read, Write};
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+struct Mapping {
+    addr: usize,
+    len: usize,
+    refs: usize,
+}
+
+// Rings created for the enclave that are still mapped.
+static RINGS: Mutex<Vec<Mapping>> = Mutex::new(Vec::new());
+
+#[no_mangle]
+pub extern "C" fn u_shm_ring_create_ocall(
+    error: *mut c_int,
+    capacity: size_t,
+    direction: u64,
+) -> *mut c_void {
+    let mut errno = 0;
+    let ret = create(capacity, direction).unwrap_or_else(|e| {
+        errno = e.raw_os_error().unwrap_or(0);
+        ptr::null_mut()
+    });
+    if !error.is_null() {
+        unsafe {
+            *error = errno;
+        }
+    }
+    ret
+}
+
+#[no_mangle]
+pub extern "C" fn u_shm_ring_release_ocall(ring: *mut c_void) {
+    release(
+        ring as usize,
+        SGX_SHM_RING_WRITER_CLOSED | SGX_SHM_RING_READER_CLOSED,
+    );
+}
+
+fn create(capacity: usize, direction: u64) -> io::Result<*mut c_void> {
+    if capacity == 0
+        || capacity > isize::MAX as usize - SGX_SHM_RING_HEADER_SIZE
+        || (direction != SGX_SHM_RING_TO_HOST && direction != SGX_SHM_RING_FROM_HOST)
+    {
+        return Err(Error::from_raw_os_error(libc::EINVAL));
+    }
+
+    let len = SGX_SHM_RING_HEADER_SIZE + capacity;
+    let addr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    if addr == libc::MAP_FAILED {
+        return Err(Error::last_os_error());
+    }
+
+    // The mapping is zeroed, so the counters and the closed word start at 0.
+    unsafe {
+        let base = addr as *mut u8;
+        ptr::write(
+            base.add(SGX_SHM_RING_MAGIC_OFFSET) as *mut u64,
+            SGX_SHM_RING_MAGIC,
+        );
+        ptr::write(
+            base.add(SGX_SHM_RING_CAPACITY_OFFSET) as *mut u64,
+            capacity as u64,
+        );
+        ptr::write(
+            base.add(SGX_SHM_RING_DIRECTION_OFFSET) as *mut u64,
+            direction,
+        );
+    }
+    RINGS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(Mapping {
+            addr: addr as usize,
+            len,
+            refs: 1,
+        });
+    Ok(addr)
+}
+
+// Marks one side of a ring as closed and unmaps it when nobody uses it.
+fn release(addr: usize, closed: u64) {
+    let mut rings = RINGS.lock().unwrap_or_else(PoisonError::into_inner);
+    let index = match rings.iter().position(|m| m.addr == addr) {
+        Some(index) => index,
+        None => return,
+    };
+
+    unsafe { word(addr as *mut u8, SGX_SHM_RING_CLOSED_OFFSET) }
+        .fetch_or(closed, Ordering::Release);
+    rings[index].refs -= 1;
+    if rings[index].refs == 0 {
+        let mapping = rings.swap_remove(index);
+        unsafe {
+            libc::munmap(mapping.addr as *mut c_void, mapping.len);
+        }
+    }
+}
+
+unsafe fn word<'a>(base: *mut u8, offset: usize) -> &'a AtomicU64 {
+    &*(base.add(offset) as *const AtomicU64)
+}
+
+/// The host end of a shared memory ring created by the enclave.
+///
+/// A ring carries data in one direction: a ring the enclave writes to is
+/// read here with [`Read`], and a ring the enclave reads from is written
+/// here with [`Write`]. Both are non-blocking: they fail with `WouldBlock`
+/// when the ring is empty or full, and reading returns `Ok(0)` once the ring
+/// is empty and the enclave has closed its end.
+pub struct ShmRing {
+    base: *mut u8,
+    capacity: u64,
+    direction: u64,
+    // Our own counter; the copy in the ring is only written, never trusted.
+    pos: u64,
+}
+
+unsafe impl Send for ShmRing {}
+
+impl ShmRing {
+    /// Attaches to the ring at `ring`, an address the enclave received from
+    /// `u_shm_ring_create_ocall`.
+    ///
+    /// Fails with `InvalidInput` if `ring` is not a live ring, and with
+    /// `AlreadyExists` if the host end is already attached.
+    pub fn attach(ring: *mut c_void) -> io::Result<ShmRing> {
+        let mut rings = RINGS.lock().unwrap_or_else(PoisonError::into_inner);
+        let mapping = rings
+            .iter_mut()
+            .find(|m| m.addr == ring as usize)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "not a shared memory ring"))?;
+        if mapping.refs > 1 {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                "shared memory ring is already attached",
+            ));
+        }
+
+        let base = ring as *mut u8;
+        let (capacity, direction, pos) = unsafe {
+            let capacity = ptr::read(base.add(SGX_SHM_RING_CAPACITY_OFFSET) as *const u64);
+            let direction = ptr::read(base.add(SGX_SHM_RING_DIRECTION_OFFSET) as *const u64);
+            let pos = if direction == SGX_SHM_RING_TO_HOST {
+                word(base, SGX_SHM_RING_HEAD_OFFSET).load(Ordering::Acquire)
+            } else {
+                word(base, SGX_SHM_RING_TAIL_OFFSET).load(Ordering::Acquire)
+            };
+            (capacity, direction, pos)
+        };
+        mapping.refs += 1;
+        Ok(ShmRing {
+            base,
+            capacity,
+            direction,
+            pos,
+        })
+    }
+
+    /// Returns the number of data bytes the ring can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// Returns whether the enclave writes to this ring and the host reads.
+    pub fn is_to_host(&self) -> bool {
+        self.direction == SGX_SHM_RING_TO_HOST
+    }
+
+    fn word(&self, offset: usize) -> &AtomicU64 {
+        unsafe { word(self.base, offset) }
+    }
+
+    fn closed(&self, bit: u64) -> bool {
+        self.word(SGX_SHM_RING_CLOSED_OFFSET)
+            .load(Ordering::Acquire)
+            & bit
+            != 0
+    }
+
+    fn data(&self) -> *mut u8 {
+        unsafe { self.base.add(SGX_SHM_RING_HEADER_SIZE) }
+    }
+
+    fn corrupted() -> Error {
+        Error::new(
+            ErrorKind::InvalidData,
+            "shared memory ring counters are inconsistent",
+        )
+    }
+}
+
+impl Read for ShmRing {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.is_to_host() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "the host writes to this ring",
+            ));
+        }
+        let tail = self.word(SGX_SHM_RING_TAIL_OFFSET).load(Ordering::Acquire);
+        let used = tail.wrapping_sub(self.pos);
+        if used > self.capacity {
+            return Err(ShmRing::corrupted());
+        }
+        if used == 0 {
+            return if self.closed(SGX_SHM_RING_WRITER_CLOSED) {
+                Ok(0)
+            } else {
+                Err(ErrorKind::WouldBlock.into())
+            };
+        }
+
+        let n = cmp::min(used, buf.len() as u64) as usize;
+        let start = (self.pos % self.capacity) as usize;
+        let first = cmp::min(n, self.capacity as usize - start);
+        unsafe {
+            ptr::copy_nonoverlapping(self.data().add(start), buf.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(self.data(), buf.as_mut_ptr().add(first), n - first);
+        }
+        self.pos = self.pos.wrapping_add(n as u64);
+        self.word(SGX_SHM_RING_HEAD_OFFSET)
+            .store(self.pos, Ordering::Release);
+        Ok(n)
+    }
+}
+
+impl Write for ShmRing {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.is_to_host() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "the enclave writes to this ring",
+            ));
+        }
+        if self.closed(SGX_SHM_RING_READER_CLOSED) {
+            return Err(ErrorKind::BrokenPipe.into());
+        }
+        let head = self.word(SGX_SHM_RING_HEAD_OFFSET).load(Ordering::Acquire);
+        let used = self.pos.wrapping_sub(head);
+        if used > self.capacity {
+            return Err(ShmRing::corrupted());
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if used == self.capacity {
+            return Err(ErrorKind::WouldBlock.into());
+        }
+
+        let n = cmp::min(self.capacity - used, buf.len() as u64) as usize;
+        let start = (self.pos % self.capacity) as usize;
+        let first = cmp::min(n, self.capacity as usize - start);
+        unsafe {
+            ptr::copy_nonoverlapping(buf.as_ptr(), self.data().add(start), first);
+            ptr::copy_nonoverlapping(buf.as_ptr().add(first), self.data(), n - first);
+        }
+        self.pos = self.pos.wrapping_add(n as u64);
+        self.word(SGX_SHM_RING_TAIL_OFFSET)
+            .store(self.pos, Ordering::Release);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for ShmRing {
+    fn drop(&mut self) {
+        let closed = if self.is_to_host() {
+            SGX_SHM_RING_READER_CLOSED
+        } else {
+            SGX_SHM_RING_WRITER_CLOSED
+        };
+        release(self.base as usize, closed);
+    }
+}
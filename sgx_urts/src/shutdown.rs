@@ -0,0 +1,208 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Graceful shutdown of enclaves.
+//!
+//! `SgxEnclave::shutdown` stops new ecalls, waits for those in flight to return, runs
+//! the exit hooks of the enclave and only then destroys it, instead of destroying it
+//! while workers are still running in it.
+//!
+//! The uRTS can only see the ecalls made through it: those made through
+//! rsgx_diagnose_ecall, which includes Rust-native ecalls, and asynchronous ecalls.
+//! Ecalls that ocalls make to the same enclave are still allowed while it drains.
+
+use crate::enclave::{rsgx_destroy_enclave, SgxEnclave};
+use crate::future::SgxEcallCancel;
+use crate::ocall::rsgx_clear_ocalls;
+use crate::stats::rsgx_clear_enclave_stats;
+use sgx_types::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Once};
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct Gate {
+    closed: bool,
+    destroyed: bool,
+    in_flight: usize,
+    cancels: Vec<Arc<SgxEcallCancel>>,
+}
+
+struct GateRegistry {
+    gates: Mutex<HashMap<sgx_enclave_id_t, Gate>>,
+    drained: Condvar,
+}
+
+static mut GATE_REGISTRY: Option<GateRegistry> = None;
+static GATE_REGISTRY_INIT: Once = Once::new();
+
+fn registry() -> &'static GateRegistry {
+    GATE_REGISTRY_INIT.call_once(|| unsafe {
+        GATE_REGISTRY = Some(GateRegistry {
+            gates: Mutex::new(HashMap::new()),
+            drained: Condvar::new(),
+        });
+    });
+    unsafe { GATE_REGISTRY.as_ref().unwrap() }
+}
+
+fn gates() -> MutexGuard<'static, HashMap<sgx_enclave_id_t, Gate>> {
+    registry().gates.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+thread_local! {
+    static ENTERED: RefCell<Vec<sgx_enclave_id_t>> = RefCell::new(Vec::new());
+}
+
+///
+/// An ecall in flight, which shutdown waits for.
+///
+pub(crate) struct EcallGuard {
+    enclave_id: sgx_enclave_id_t,
+    cancel: Option<Arc<SgxEcallCancel>>,
+}
+
+impl EcallGuard {
+    ///
+    /// Enters an ecall of the enclave enclave_id, whose cancellation flag, if any, is
+    /// signalled when shutdown times out.
+    ///
+    pub(crate) fn enter(
+        enclave_id: sgx_enclave_id_t,
+        cancel: Option<Arc<SgxEcallCancel>>,
+    ) -> SgxResult<EcallGuard> {
+        let nested = ENTERED.with(|entered| entered.borrow().contains(&enclave_id));
+        {
+            let mut gates = gates();
+            let gate = gates.entry(enclave_id).or_default();
+            if gate.destroyed || (gate.closed && !nested) {
+                return Err(sgx_status_t::SGX_ERROR_ECALL_NOT_ALLOWED);
+            }
+            gate.in_flight += 1;
+            if let Some(ref cancel) = cancel {
+                gate.cancels.push(cancel.clone());
+            }
+        }
+        ENTERED.with(|entered| entered.borrow_mut().push(enclave_id));
+        Ok(EcallGuard { enclave_id, cancel })
+    }
+}
+
+impl Drop for EcallGuard {
+    fn drop(&mut self) {
+        ENTERED.with(|entered| entered.borrow_mut().pop());
+        let mut gates = gates();
+        if let Some(gate) = gates.get_mut(&self.enclave_id) {
+            gate.in_flight -= 1;
+            if let Some(ref cancel) = self.cancel {
+                gate.cancels.retain(|c| !Arc::ptr_eq(c, cancel));
+            }
+            if gate.in_flight == 0 {
+                if gate.closed {
+                    registry().drained.notify_all();
+                } else {
+                    gates.remove(&self.enclave_id);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn is_destroyed(enclave_id: sgx_enclave_id_t) -> bool {
+    gates()
+        .get(&enclave_id)
+        .map_or(false, |gate| gate.destroyed)
+}
+
+fn drain(enclave_id: sgx_enclave_id_t, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let mut gates = gates();
+    loop {
+        let in_flight = gates.get(&enclave_id).map_or(0, |gate| gate.in_flight);
+        let now = Instant::now();
+        if in_flight == 0 {
+            return true;
+        }
+        if now >= deadline {
+            return false;
+        }
+        gates = registry()
+            .drained
+            .wait_timeout(gates, deadline - now)
+            .unwrap_or_else(|e| e.into_inner())
+            .0;
+    }
+}
+
+impl SgxEnclave {
+    ///
+    /// Shuts the enclave down. New ecalls fail with SGX_ERROR_ECALL_NOT_ALLOWED, and
+    /// those in flight are given timeout to return. Asynchronous ecalls still running
+    /// then are cancelled, and given timeout again. Once none is in flight, the exit
+    /// hooks of the enclave are run and the enclave is destroyed.
+    ///
+    /// Shutting down an enclave already shut down does nothing. The enclave is not
+    /// destroyed again when it is dropped.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_BUSY**
+    ///
+    /// Ecalls are still in flight. The enclave accepts no new ecall, and is destroyed
+    /// when it is dropped.
+    ///
+    /// The errors of rsgx_destroy_enclave.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// enclave.shutdown(Duration::from_secs(5))?;
+    /// ```
+    ///
+    pub fn shutdown(&self, timeout: Duration) -> SgxError {
+        let enclave_id = self.geteid();
+        {
+            let mut gates = gates();
+            let gate = gates.entry(enclave_id).or_default();
+            if gate.destroyed {
+                return Ok(());
+            }
+            gate.closed = true;
+        }
+
+        if !drain(enclave_id, timeout) {
+            if let Some(gate) = gates().get(&enclave_id) {
+                for cancel in gate.cancels.iter() {
+                    cancel.cancel();
+                }
+            }
+            if !drain(enclave_id, timeout) {
+                return Err(sgx_status_t::SGX_ERROR_BUSY);
+            }
+        }
+
+        self.exit();
+        rsgx_destroy_enclave(enclave_id)?;
+        if let Some(gate) = gates().get_mut(&enclave_id) {
+            gate.destroyed = true;
+        }
+        rsgx_clear_ocalls(enclave_id);
+        rsgx_clear_enclave_stats(enclave_id);
+        Ok(())
+    }
+}
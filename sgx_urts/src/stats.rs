@@ -0,0 +1,326 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Resource usage of enclaves.
+//!
+//! The uRTS counts, per enclave, the ecalls made through rsgx_diagnose_ecall
+//! (which includes Rust-native ecalls) with a histogram of their latency, and the
+//! ocalls made during those ecalls: Rust-native ocalls are counted by the uRTS, and
+//! ocalls generated by edger8r when their implementation calls rsgx_count_ocall.
+//!
+//! The SGX drivers do not report the EPC usage of each enclave, so the EPC usage is
+//! that of all the enclaves of the process, read from /proc/self/smaps.
+
+use crate::enclave::SgxEnclave;
+use sgx_types::*;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Mutex, Once};
+use std::time::Duration;
+
+///
+/// The number of buckets of an SgxLatencyHistogram.
+///
+pub const SGX_LATENCY_BUCKETS: usize = 32;
+
+///
+/// A histogram of latencies. Bucket 0 counts the latencies under 1 microsecond, and
+/// bucket i those from 2^(i-1) up to 2^i microseconds. The last bucket counts all
+/// the longer latencies.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SgxLatencyHistogram {
+    buckets: [u64; SGX_LATENCY_BUCKETS],
+    count: u64,
+    total: Duration,
+}
+
+impl SgxLatencyHistogram {
+    pub fn new() -> SgxLatencyHistogram {
+        SgxLatencyHistogram::default()
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        let micros = latency.as_micros();
+        let bucket = if micros == 0 {
+            0
+        } else {
+            (128 - micros.leading_zeros()) as usize
+        };
+        self.buckets[bucket.min(SGX_LATENCY_BUCKETS - 1)] += 1;
+        self.count += 1;
+        self.total = self.total.saturating_add(latency);
+    }
+
+    pub fn get_buckets(&self) -> &[u64; SGX_LATENCY_BUCKETS] {
+        &self.buckets
+    }
+
+    ///
+    /// Get the upper bound of bucket, or None for the last bucket.
+    ///
+    pub fn get_bucket_bound(bucket: usize) -> Option<Duration> {
+        if bucket < SGX_LATENCY_BUCKETS - 1 {
+            Some(Duration::from_micros(1 << bucket))
+        } else {
+            None
+        }
+    }
+
+    pub fn get_count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn get_mean(&self) -> Option<Duration> {
+        if self.count > 0 {
+            Some(Duration::from_nanos(
+                (self.total.as_nanos() / self.count as u128) as u64,
+            ))
+        } else {
+            None
+        }
+    }
+
+    ///
+    /// Get the upper bound of the bucket of the percentile p, between 0 and 100. Returns
+    /// None if no latency is recorded, or if the percentile is in the last bucket.
+    ///
+    pub fn get_percentile(&self, p: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((p.clamp(0.0, 100.0) / 100.0) * self.count as f64).ceil() as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank.max(1) {
+                return SgxLatencyHistogram::get_bucket_bound(bucket);
+            }
+        }
+        None
+    }
+}
+
+///
+/// The EPC usage of the enclaves of the process.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SgxEpcUsage {
+    mapped: u64,
+    resident: u64,
+}
+
+impl SgxEpcUsage {
+    ///
+    /// Get the size, in bytes, of the enclave memory mapped by the process.
+    ///
+    pub fn get_mapped(&self) -> u64 {
+        self.mapped
+    }
+
+    ///
+    /// Get the size, in bytes, of the enclave pages in the EPC.
+    ///
+    pub fn get_resident(&self) -> u64 {
+        self.resident
+    }
+}
+
+const SGX_DEVICES: [&str; 4] = [
+    "/dev/sgx_enclave",
+    "/dev/sgx/enclave",
+    "/dev/isgx",
+    "/dev/sgx",
+];
+
+///
+/// rsgx_epc_usage reads the EPC usage of the enclaves of the process from
+/// /proc/self/smaps.
+///
+/// # Errors
+///
+/// **SGX_ERROR_FEATURE_NOT_SUPPORTED**
+///
+/// /proc/self/smaps cannot be read.
+///
+pub fn rsgx_epc_usage() -> SgxResult<SgxEpcUsage> {
+    let smaps = fs::read_to_string("/proc/self/smaps")
+        .map_err(|_| sgx_status_t::SGX_ERROR_FEATURE_NOT_SUPPORTED)?;
+    Ok(parse_smaps(&smaps))
+}
+
+fn parse_smaps(smaps: &str) -> SgxEpcUsage {
+    let mut usage = SgxEpcUsage::default();
+    let mut in_enclave = false;
+    for line in smaps.lines() {
+        let mut fields = line.split_whitespace();
+        let first = fields.next().unwrap_or("");
+        if first.ends_with(':') {
+            if !in_enclave {
+                continue;
+            }
+            let kb = fields
+                .next()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(0);
+            match first {
+                "Size:" => usage.mapped += kb * 1024,
+                "Rss:" => usage.resident += kb * 1024,
+                _ => {}
+            }
+        } else if first.contains('-') {
+            // The header of a mapping: range, perms, offset, device, inode and path.
+            let path = fields.nth(4).unwrap_or("");
+            in_enclave = SGX_DEVICES.contains(&path);
+        }
+    }
+    usage
+}
+
+///
+/// The resource usage of an enclave.
+///
+#[derive(Clone, Debug, Default)]
+pub struct SgxEnclaveStats {
+    ecalls: u64,
+    ecall_errors: u64,
+    ocalls: u64,
+    ecall_latency: SgxLatencyHistogram,
+    epc_usage: Option<SgxEpcUsage>,
+}
+
+impl SgxEnclaveStats {
+    pub fn get_ecalls(&self) -> u64 {
+        self.ecalls
+    }
+
+    ///
+    /// Get the number of ecalls that did not return SGX_SUCCESS.
+    ///
+    pub fn get_ecall_errors(&self) -> u64 {
+        self.ecall_errors
+    }
+
+    pub fn get_ocalls(&self) -> u64 {
+        self.ocalls
+    }
+
+    ///
+    /// Get the histogram of the latency of ecalls, from their start to their return.
+    ///
+    pub fn get_ecall_latency(&self) -> &SgxLatencyHistogram {
+        &self.ecall_latency
+    }
+
+    ///
+    /// Get the EPC usage of the enclaves of the process, if it can be read.
+    ///
+    pub fn get_epc_usage(&self) -> Option<SgxEpcUsage> {
+        self.epc_usage
+    }
+}
+
+static mut STATS_REGISTRY: Option<Mutex<HashMap<sgx_enclave_id_t, SgxEnclaveStats>>> = None;
+static STATS_REGISTRY_INIT: Once = Once::new();
+
+fn registry() -> &'static Mutex<HashMap<sgx_enclave_id_t, SgxEnclaveStats>> {
+    STATS_REGISTRY_INIT.call_once(|| unsafe {
+        STATS_REGISTRY = Some(Mutex::new(HashMap::new()));
+    });
+    unsafe { STATS_REGISTRY.as_ref().unwrap() }
+}
+
+thread_local! {
+    static CURRENT_ENCLAVE: Cell<Option<sgx_enclave_id_t>> = Cell::new(None);
+}
+
+pub(crate) struct StatsScope {
+    previous: Option<sgx_enclave_id_t>,
+}
+
+impl StatsScope {
+    pub(crate) fn enter(enclave_id: sgx_enclave_id_t) -> StatsScope {
+        let previous = CURRENT_ENCLAVE.with(|current| current.replace(Some(enclave_id)));
+        StatsScope { previous }
+    }
+}
+
+impl Drop for StatsScope {
+    fn drop(&mut self) {
+        CURRENT_ENCLAVE.with(|current| current.set(self.previous));
+    }
+}
+
+pub(crate) fn record_ecall(enclave_id: sgx_enclave_id_t, status: sgx_status_t, latency: Duration) {
+    let mut registry = registry().lock().unwrap_or_else(|e| e.into_inner());
+    let stats = registry.entry(enclave_id).or_default();
+    stats.ecalls += 1;
+    if status != sgx_status_t::SGX_SUCCESS {
+        stats.ecall_errors += 1;
+    }
+    stats.ecall_latency.record(latency);
+}
+
+///
+/// rsgx_count_ocall counts an ocall of the enclave whose ecall runs on this thread,
+/// if it was made through rsgx_diagnose_ecall. Implementations of ocalls generated
+/// by edger8r call it to have their ocalls counted.
+///
+pub fn rsgx_count_ocall() {
+    if let Some(enclave_id) = CURRENT_ENCLAVE.with(|current| current.get()) {
+        registry()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(enclave_id)
+            .or_default()
+            .ocalls += 1;
+    }
+}
+
+///
+/// rsgx_enclave_stats returns the resource usage of the enclave enclave_id.
+///
+pub fn rsgx_enclave_stats(enclave_id: sgx_enclave_id_t) -> SgxEnclaveStats {
+    let mut stats = registry()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&enclave_id)
+        .cloned()
+        .unwrap_or_default();
+    stats.epc_usage = rsgx_epc_usage().ok();
+    stats
+}
+
+///
+/// rsgx_clear_enclave_stats forgets the resource usage of the enclave enclave_id.
+///
+pub fn rsgx_clear_enclave_stats(enclave_id: sgx_enclave_id_t) {
+    registry()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&enclave_id);
+}
+
+impl SgxEnclave {
+    ///
+    /// Get the resource usage of the enclave. See [`rsgx_enclave_stats`].
+    ///
+    pub fn stats(&self) -> SgxEnclaveStats {
+        rsgx_enclave_stats(self.geteid())
+    }
+}
@@ -0,0 +1,237 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License..
+
+//! Configuration and statistics of switchless calls.
+//!
+//! `SgxSwitchlessConfig` sets up the worker threads of an enclave created with
+//! `SgxEnclave::create_switchless`: how many untrusted workers serve switchless
+//! ocalls and trusted workers serve switchless ecalls, how many calls the task pool
+//! holds, and how long a caller and a worker spin before they fall back to an ordinary
+//! call or go to sleep.
+//!
+//! The workers report their events to this module, which keeps counters for
+//! `rsgx_switchless_stats`. The switchless library counts processed and missed calls
+//! for all workers of a type, so the figures are those of its latest report. They are
+//! kept for the whole process, not per enclave.
+
+use sgx_types::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+///
+/// The worker configuration of switchless calls. A zero pool size or retry count selects
+/// the default of the switchless library.
+///
+#[derive(Clone, Copy, Debug)]
+pub struct SgxSwitchlessConfig {
+    pool_size_qwords: u32,
+    num_uworkers: u32,
+    num_tworkers: u32,
+    retries_before_fallback: u32,
+    retries_before_sleep: u32,
+}
+
+impl Default for SgxSwitchlessConfig {
+    fn default() -> SgxSwitchlessConfig {
+        SgxSwitchlessConfig {
+            pool_size_qwords: 0,
+            num_uworkers: 1,
+            num_tworkers: 1,
+            retries_before_fallback: 0,
+            retries_before_sleep: 0,
+        }
+    }
+}
+
+impl SgxSwitchlessConfig {
+    ///
+    /// One untrusted and one trusted worker, with the defaults of the library.
+    ///
+    pub fn new() -> SgxSwitchlessConfig {
+        SgxSwitchlessConfig::default()
+    }
+
+    ///
+    /// Sets the number of untrusted worker threads, which serve switchless ocalls.
+    ///
+    pub fn untrusted_workers(mut self, num: u32) -> SgxSwitchlessConfig {
+        self.num_uworkers = num;
+        self
+    }
+
+    ///
+    /// Sets the number of trusted worker threads, which serve switchless ecalls. Each
+    /// takes a TCS of the enclave.
+    ///
+    pub fn trusted_workers(mut self, num: u32) -> SgxSwitchlessConfig {
+        self.num_tworkers = num;
+        self
+    }
+
+    ///
+    /// Sets the size of the task pool in 64-bit words; every word holds 64 pending
+    /// calls. The library caps it at SL_MAX_TASKS_MAX_QWORDS.
+    ///
+    pub fn pool_size_qwords(mut self, qwords: u32) -> SgxSwitchlessConfig {
+        self.pool_size_qwords = qwords;
+        self
+    }
+
+    ///
+    /// Sets how many times a caller retries to hand a call to a worker before it makes an
+    /// ordinary call instead.
+    ///
+    pub fn retries_before_fallback(mut self, retries: u32) -> SgxSwitchlessConfig {
+        self.retries_before_fallback = retries;
+        self
+    }
+
+    ///
+    /// Sets how many times an idle worker polls for calls before it sleeps until woken.
+    /// Fewer retries save CPU time at the cost of latency after idle periods.
+    ///
+    pub fn retries_before_sleep(mut self, retries: u32) -> SgxSwitchlessConfig {
+        self.retries_before_sleep = retries;
+        self
+    }
+
+    ///
+    /// Get the number of untrusted workers.
+    ///
+    pub fn get_untrusted_workers(&self) -> u32 {
+        self.num_uworkers
+    }
+
+    ///
+    /// Get the number of trusted workers.
+    ///
+    pub fn get_trusted_workers(&self) -> u32 {
+        self.num_tworkers
+    }
+
+    ///
+    /// Converts the configuration into the form of sgx_create_enclave_ex, with the
+    /// statistics callbacks of this module.
+    ///
+    pub fn to_raw(&self) -> sgx_uswitchless_config_t {
+        let callback: sgx_uswitchless_worker_callback_t = worker_callback;
+        sgx_uswitchless_config_t {
+            switchless_calls_pool_size_qwords: self.pool_size_qwords as u64,
+            num_uworkers: self.num_uworkers as u64,
+            num_tworkers: self.num_tworkers as u64,
+            retries_before_fallback: self.retries_before_fallback as u64,
+            retries_before_sleep: self.retries_before_sleep as u64,
+            callback_func: [callback; SGX_USWITCHLESS_WORKER_EVENT_NUM],
+        }
+    }
+}
+
+///
+/// The statistics of the switchless workers of one type.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SgxSwitchlessWorkerStats {
+    /// The calls the workers have processed.
+    pub processed: u64,
+    /// The calls the workers have missed, which the callers made as ordinary calls.
+    pub missed: u64,
+    /// The workers that started and have not exited.
+    pub running: u64,
+    /// The times a worker went idle.
+    pub idle_events: u64,
+    /// The times a worker reported missed calls.
+    pub miss_events: u64,
+}
+
+///
+/// The statistics of the untrusted and the trusted switchless workers.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SgxSwitchlessStats {
+    pub untrusted: SgxSwitchlessWorkerStats,
+    pub trusted: SgxSwitchlessWorkerStats,
+}
+
+struct WorkerCounters {
+    processed: AtomicU64,
+    missed: AtomicU64,
+    started: AtomicU64,
+    exited: AtomicU64,
+    idle: AtomicU64,
+    miss: AtomicU64,
+}
+
+impl WorkerCounters {
+    const fn new() -> WorkerCounters {
+        WorkerCounters {
+            processed: AtomicU64::new(0),
+            missed: AtomicU64::new(0),
+            started: AtomicU64::new(0),
+            exited: AtomicU64::new(0),
+            idle: AtomicU64::new(0),
+            miss: AtomicU64::new(0),
+        }
+    }
+
+    fn stats(&self) -> SgxSwitchlessWorkerStats {
+        let started = self.started.load(Ordering::Relaxed);
+        let exited = self.exited.load(Ordering::Relaxed);
+        SgxSwitchlessWorkerStats {
+            processed: self.processed.load(Ordering::Relaxed),
+            missed: self.missed.load(Ordering::Relaxed),
+            running: started.saturating_sub(exited),
+            idle_events: self.idle.load(Ordering::Relaxed),
+            miss_events: self.miss.load(Ordering::Relaxed),
+        }
+    }
+}
+
+static UNTRUSTED_COUNTERS: WorkerCounters = WorkerCounters::new();
+static TRUSTED_COUNTERS: WorkerCounters = WorkerCounters::new();
+
+extern "C" fn worker_callback(
+    worker_type: sgx_uswitchless_worker_type_t,
+    worker_event: sgx_uswitchless_worker_event_t,
+    worker_stats: *const sgx_uswitchless_worker_stats_t,
+) {
+    let counters = match worker_type {
+        sgx_uswitchless_worker_type_t::SGX_USWITCHLESS_WORKER_TYPE_TRUSTED => &TRUSTED_COUNTERS,
+        _ => &UNTRUSTED_COUNTERS,
+    };
+    if !worker_stats.is_null() {
+        let stats = unsafe { &*worker_stats };
+        counters.processed.store(stats.processed, Ordering::Relaxed);
+        counters.missed.store(stats.missed, Ordering::Relaxed);
+    }
+    let event = match worker_event {
+        sgx_uswitchless_worker_event_t::SGX_USWITCHLESS_WORKER_EVENT_START => &counters.started,
+        sgx_uswitchless_worker_event_t::SGX_USWITCHLESS_WORKER_EVENT_IDLE => &counters.idle,
+        sgx_uswitchless_worker_event_t::SGX_USWITCHLESS_WORKER_EVENT_MISS => &counters.miss,
+        sgx_uswitchless_worker_event_t::SGX_USWITCHLESS_WORKER_EVENT_EXIT => &counters.exited,
+        _ => return,
+    };
+    event.fetch_add(1, Ordering::Relaxed);
+}
+
+///
+/// Returns the statistics of the switchless workers of the process.
+///
+pub fn rsgx_switchless_stats() -> SgxSwitchlessStats {
+    SgxSwitchlessStats {
+        untrusted: UNTRUSTED_COUNTERS.stats(),
+        trusted: TRUSTED_COUNTERS.stats(),
+    }
+}